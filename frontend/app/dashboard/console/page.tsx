@@ -57,7 +57,7 @@ export default function FixConsolePage() {
     try {
       const res = await fixClient.send({
         msg_type: msgType,
-        fields: { BeginString: version, SenderCompID: "ALICE", TargetCompID: "BROKER" },
+        fields: { BeginString: version, SenderCompID: "ALICE", TargetCompID: "BROKER_A" },
       });
       setResult({ type: "send", data: res });
     } catch (e) {
@@ -54,6 +54,8 @@ export interface FixSession {
 export interface SessionsResponse {
   sessions: FixSession[];
   count: number;
+  total: number;
+  offset: number;
 }
 
 export interface ValidationError {
@@ -71,6 +73,16 @@ export interface ValidateResponse {
 
 // ── HTTP helper ───────────────────────────────────────────────────────────────
 
+let accessToken: string | undefined;
+
+/**
+ * Send the signed-in user's SSO access token as a bearer token with every
+ * request, so the engine authorizes and audits the user; `undefined` stops.
+ */
+export function setAccessToken(token: string | undefined): void {
+  accessToken = token;
+}
+
 async function request<T>(
   method: "GET" | "POST",
   path: string,
@@ -78,7 +90,10 @@ async function request<T>(
 ): Promise<T> {
   const res = await fetch(`${BASE_URL}${path}`, {
     method,
-    headers: { "Content-Type": "application/json" },
+    headers: {
+      "Content-Type": "application/json",
+      ...(accessToken ? { Authorization: `Bearer ${accessToken}` } : {}),
+    },
     body: body !== undefined ? JSON.stringify(body) : undefined,
   });
 
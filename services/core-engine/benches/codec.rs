@@ -0,0 +1,137 @@
+//! Benchmarks of the codec: parsing, building and validating messages, and
+//! the checksum.
+//!
+//! Run with `cargo bench`; `cargo bench -- parse` runs one group. Criterion
+//! keeps each run's results in `target/criterion` and reports the change
+//! from the last run, so a codec change can be measured against the tree
+//! before it.
+
+use bytes::BytesMut;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::{collections::HashMap, hint::black_box};
+
+use fix_engine::bench::{self, Fields, Validator};
+
+/// Frames `|`-delimited body fields as a FIX 4.4 message, with its
+/// BodyLength(9) and CheckSum(10).
+fn frame(body: &str) -> Vec<u8> {
+    let body = body.replace('|', "\x01");
+    let mut raw = format!("8=FIX.4.4\x019={}\x01{body}", body.len()).into_bytes();
+    let checksum = bench::checksum(&raw);
+    raw.extend_from_slice(format!("10={checksum:03}\x01").as_bytes());
+    raw
+}
+
+const HEADER: &str = "49=ALICE|56=BROKER_A|34=2|52=20260223-00:00:00.000|";
+
+/// A NewOrderSingle.
+fn new_order() -> Vec<u8> {
+    frame(&format!(
+        "35=D|{HEADER}11=ORD-1|1=ACC-1|21=1|55=AAPL|54=1|60=20260223-00:00:00.000|\
+         38=100|40=2|44=150.25|59=0|"
+    ))
+}
+
+/// A NewOrderSingle with five parties in NoPartyIDs(453).
+fn new_order_with_parties() -> Vec<u8> {
+    let parties: String = (0..5)
+        .map(|i| format!("448=PARTY-{i}|447=D|452={}|", i + 1))
+        .collect();
+    frame(&format!(
+        "35=D|{HEADER}11=ORD-1|453=5|{parties}55=AAPL|54=1|38=100|40=2|44=150.25|"
+    ))
+}
+
+/// A MarketDataSnapshotFullRefresh with 200 entries in NoMDEntries(268),
+/// over 600 fields.
+fn snapshot() -> Vec<u8> {
+    let entries: String = (0..200)
+        .map(|i| {
+            let (side, price, size) = (i % 2, 15_000 + i, 100 * (i + 1));
+            format!("269={side}|270={}.{:02}|271={size}|", price / 100, price % 100)
+        })
+        .collect();
+    frame(&format!("35=W|{HEADER}262=MD-1|55=AAPL|268=200|{entries}"))
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    let messages = [
+        ("small", new_order()),
+        ("repeating_groups", new_order_with_parties()),
+        ("large", snapshot()),
+    ];
+    for (name, raw) in &messages {
+        group.throughput(Throughput::Bytes(raw.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), raw, |b, raw| {
+            b.iter(|| bench::parse(black_box(raw)))
+        });
+    }
+    group.finish();
+}
+
+fn build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build");
+    for count in [10, 50] {
+        // User-defined tags, each with a value as long as a typical ClOrdID.
+        let fields: HashMap<String, String> = (0..count)
+            .map(|i| ((5000 + i).to_string(), format!("VALUE-{i:06}")))
+            .collect();
+        let mut out = BytesMut::new();
+        group.bench_with_input(BenchmarkId::new("fields", count), &fields, |b, fields| {
+            let mut seq_num = 0;
+            b.iter(|| {
+                seq_num += 1;
+                bench::build(&mut out, seq_num, black_box(fields), &[]);
+            })
+        });
+    }
+    let parties = (0..5)
+        .flat_map(|i| [(448, format!("PARTY-{i}")), (447, "D".to_string())]);
+    let groups: Vec<(u32, String)> = std::iter::once((453, "5".to_string()))
+        .chain(parties)
+        .collect();
+    let (none, mut out) = (HashMap::new(), BytesMut::new());
+    group.bench_function("repeating_groups", |b| {
+        b.iter(|| bench::build(&mut out, 1, &none, black_box(&groups)))
+    });
+    group.finish();
+}
+
+fn validate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("validate");
+    let validator = Validator::fix44();
+    let messages = [
+        ("small", new_order()),
+        ("repeating_groups", new_order_with_parties()),
+        ("large", snapshot()),
+    ];
+    for (name, raw) in &messages {
+        let fields = Fields::from_wire(raw);
+        group.bench_with_input(BenchmarkId::from_parameter(name), &fields, |b, fields| {
+            b.iter(|| validator.validate(black_box(fields)))
+        });
+    }
+    group.finish();
+}
+
+/// The engine's checksum against summing one byte at a time.
+fn checksum(c: &mut Criterion) {
+    let mut group = c.benchmark_group("checksum");
+    let raw = snapshot();
+    group.throughput(Throughput::Bytes(raw.len() as u64));
+    group.bench_function("scalar", |b| {
+        b.iter(|| {
+            black_box(&raw)
+                .iter()
+                .fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+        })
+    });
+    group.bench_function("vectorized", |b| {
+        b.iter(|| bench::checksum(black_box(&raw)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse, build, validate, checksum);
+criterion_main!(benches);
@@ -0,0 +1,7 @@
+fn main() {
+    // The gRPC service is generated from its protobuf definitions, which
+    // needs `protoc` on the PATH.
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/fix_gateway.proto")
+        .expect("failed to compile proto/fix_gateway.proto");
+}
@@ -0,0 +1,253 @@
+//! One task per session, which runs the session's sends.
+//!
+//! Handlers do not send on a session themselves: they hand the session's
+//! task a [`Command`] over a bounded channel and await its reply. The task
+//! runs its commands one at a time, which keeps the session's messages, and
+//! a batch's, in the order they were submitted without a lock shared between
+//! handlers. It owns the session's encode buffer, which keeps its capacity
+//! from one message to the next, and the header template of its fast
+//! orders. Each task is spawned on first use and runs beside the others.
+//! Commands for an unknown session are refused with 404, and a removed
+//! session's task ends once it has run the commands it was handed.
+//!
+//! A command carries the request's span, correlation ID and time of receipt
+//! into the task, so its logs, traces and send latency are the request's.
+//!
+//! Commands are not isolated from panics: release builds abort on panic,
+//! so a bug in one command takes the gateway down rather than leaving a
+//! session half sent.
+//!
+//! The task owns only its commands, buffer and template. The rest of a
+//! session's state is still shared: its MsgSeqNum counter is in the
+//! [`crate::registry`], its journal entries and message log go through the
+//! store's locks, and its orders, expiries and queued messages sit in the
+//! gateway-wide tables of [`AppState`], each behind its own lock. Those
+//! locks are taken by every session's task, so sessions still contend on
+//! them briefly. Sessions have no TCP transport yet, so there is no socket
+//! or heartbeat timer for the task to own.
+
+use axum::http::StatusCode;
+use bytes::BytesMut;
+use dashmap::DashMap;
+use std::{future::Future, time::Instant};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{Instrument, Span};
+
+use crate::{
+    encode::Template,
+    fast_order::{self, FastAck, FastRequest},
+    latency,
+    problem::{self, ApiError},
+    send_in_task,
+    submit_in_task,
+    AppState,
+    SendOptions,
+    SendRequest,
+    SentMessage,
+    Submitted,
+};
+
+/// Commands waiting for a session's task, beyond which handlers wait to
+/// hand it theirs.
+const COMMAND_CAPACITY: usize = 1024;
+
+enum Command {
+    /// Sends each message, or queues it while the session is not logged on,
+    /// stopping at the first one rejected.
+    Submit {
+        opts: SendOptions,
+        messages: Vec<SendRequest>,
+        reply: oneshot::Sender<Vec<Result<Submitted, ApiError>>>,
+    },
+    /// Sends a message now, as a queued one once its session is logged on.
+    Send {
+        opts: SendOptions,
+        req: Box<SendRequest>,
+        reply: oneshot::Sender<Result<SentMessage, ApiError>>,
+    },
+    /// Sends an order on the fast path.
+    Fast {
+        request: Box<FastRequest>,
+        reply: oneshot::Sender<Result<FastAck, ApiError>>,
+    },
+}
+
+/// What a session's task keeps from one command to the next.
+#[derive(Default)]
+struct Owned {
+    buffer: BytesMut,
+    /// The header of the session's fast orders, once it has sent one.
+    template: Option<Template>,
+}
+
+/// The request a command was handed over for.
+struct Origin {
+    span: Span,
+    correlation_id: Option<String>,
+    received_at: Instant,
+}
+
+impl Origin {
+    fn current() -> Self {
+        Self {
+            span: Span::current(),
+            correlation_id: problem::current_correlation_id(),
+            received_at: latency::received_at(),
+        }
+    }
+
+    /// Runs `task` as part of the request.
+    async fn enter<F: Future>(self, task: F) -> F::Output {
+        let task = latency::received(self.received_at, task.instrument(self.span));
+        problem::correlated(self.correlation_id, task).await
+    }
+}
+
+/// The sessions' tasks, each spawned on first use.
+#[derive(Default)]
+pub struct SessionActors {
+    actors: DashMap<String, mpsc::Sender<(Origin, Command)>>,
+}
+
+impl SessionActors {
+    /// The session's task. Only a registered session gets one, so a client
+    /// cannot spawn tasks for session IDs it makes up.
+    fn actor(
+        &self,
+        state: &AppState,
+        session_id: &str,
+    ) -> Result<mpsc::Sender<(Origin, Command)>, ApiError> {
+        if let Some(actor) = self.actors.get(session_id) {
+            return Ok(actor.clone());
+        }
+        if state.sessions.get(session_id).is_none() {
+            return Err(ApiError::new(StatusCode::NOT_FOUND, "unknown_session").detail(format!(
+                "no session {session_id}; messages are sent only on configured sessions"
+            )));
+        }
+        let actor = self
+            .actors
+            .entry(session_id.to_string())
+            .or_insert_with(|| {
+                let (sender, commands) = mpsc::channel(COMMAND_CAPACITY);
+                tokio::spawn(run(state.clone(), session_id.to_string(), commands));
+                sender
+            })
+            .clone();
+        Ok(actor)
+    }
+
+    /// Stops the session's task once it has run the commands already handed
+    /// to it, as when the session is removed.
+    pub fn remove(&self, session_id: &str) {
+        self.actors.remove(session_id);
+    }
+
+    /// Hands the session's task a command and waits for its reply.
+    async fn call<T>(
+        &self,
+        state: &AppState,
+        session_id: &str,
+        command: impl FnOnce(oneshot::Sender<T>) -> Command,
+    ) -> Result<T, ApiError> {
+        let (reply, answer) = oneshot::channel();
+        let actor = self.actor(state, session_id)?;
+        let call = (Origin::current(), command(reply));
+        actor.send(call).await.map_err(|_| failed())?;
+        // The reply is dropped unanswered if the task is gone.
+        answer.await.map_err(|_| failed())
+    }
+
+    /// Sends or queues `messages` in order on the session, with nothing else
+    /// sent between them. There is a result for each message up to the first
+    /// one rejected.
+    pub async fn submit(
+        &self,
+        state: &AppState,
+        session_id: &str,
+        opts: &SendOptions,
+        messages: Vec<SendRequest>,
+    ) -> Result<Vec<Result<Submitted, ApiError>>, ApiError> {
+        let opts = opts.clone();
+        self.call(state, session_id, |reply| Command::Submit {
+            opts,
+            messages,
+            reply,
+        })
+        .await
+    }
+
+    /// Sends a message on the session now.
+    pub async fn send(
+        &self,
+        state: &AppState,
+        session_id: &str,
+        opts: &SendOptions,
+        req: SendRequest,
+    ) -> Result<SentMessage, ApiError> {
+        let opts = opts.clone();
+        let req = Box::new(req);
+        let command = |reply| Command::Send { opts, req, reply };
+        self.call(state, session_id, command).await?
+    }
+
+    /// Sends an order on the session's fast path.
+    pub async fn fast(
+        &self,
+        state: &AppState,
+        session_id: &str,
+        request: FastRequest,
+    ) -> Result<FastAck, ApiError> {
+        let request = Box::new(request);
+        let command = |reply| Command::Fast { request, reply };
+        self.call(state, session_id, command).await?
+    }
+}
+
+fn failed() -> ApiError {
+    ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "session_failed")
+        .detail("the session's task has stopped")
+}
+
+async fn run(
+    state: AppState,
+    session_id: String,
+    mut commands: mpsc::Receiver<(Origin, Command)>,
+) {
+    let mut owned = Owned::default();
+    while let Some((origin, command)) = commands.recv().await {
+        origin
+            .enter(handle(&state, &session_id, command, &mut owned))
+            .await;
+    }
+}
+
+async fn handle(state: &AppState, session_id: &str, command: Command, owned: &mut Owned) {
+    let Owned { buffer, template } = owned;
+    // A handler that has gone away no longer wants its reply.
+    match command {
+        Command::Submit {
+            opts,
+            messages,
+            reply,
+        } => {
+            let mut results = Vec::with_capacity(messages.len());
+            for message in messages {
+                let result = submit_in_task(state, &opts, message, buffer).await;
+                let rejected = result.is_err();
+                results.push(result);
+                if rejected {
+                    break;
+                }
+            }
+            let _ = reply.send(results);
+        }
+        Command::Send { opts, req, reply } => {
+            let _ = reply.send(send_in_task(state, &opts, *req, buffer).await);
+        }
+        Command::Fast { request, reply } => {
+            let sent = fast_order::send_in_task(state, session_id, *request, template, buffer);
+            let _ = reply.send(sent.await);
+        }
+    }
+}
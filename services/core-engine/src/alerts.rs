@@ -0,0 +1,545 @@
+//! Operational alerts, POSTed to webhooks.
+//!
+//! The gateway raises an alert when:
+//!
+//! - `SEQUENCE_GAP`: an inbound message's MsgSeqNum is past the one expected,
+//!   so the messages in between were missed.
+//! - `RESEND_INITIATED`: a ResendRequest is sent or received.
+//! - `SESSION_DOWN`: a session that dropped (not after a Logout) has stayed
+//!   `DISCONNECTED` for `FIX_ALERT_SESSION_DOWN_SECS`. `SESSION_RESTORED`
+//!   follows when it logs on again.
+//! - `REJECT_STORM`: a session received `FIX_ALERT_REJECT_STORM` rejects
+//!   (Reject, BusinessMessageReject, or an ExecutionReport rejecting an
+//!   order) within the window, e.g. `10/60` for 10 in 60 seconds. It is not
+//!   raised again for the same session until a window has passed.
+//!
+//! Every alert is logged, and POSTed as JSON to each URL in
+//! `FIX_ALERT_WEBHOOKS`. A delivery that fails, or is answered with 429 or a
+//! 5xx status, is retried with exponential backoff up to
+//! `FIX_ALERT_MAX_ATTEMPTS` times. With `FIX_ALERT_SECRET` set, each request
+//! is signed: `X-Fix-Signature` is `sha256=` and the hex HMAC-SHA256 of
+//! `<X-Fix-Timestamp>.<body>`.
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+use tokio::sync::Mutex;
+use tracing::{info, warn, Instrument};
+
+use crate::{
+    clock::epoch_ms, records::Direction, secrets::Secret, store::field_value, telemetry,
+    throughput, AppState,
+};
+
+const DEFAULT_SESSION_DOWN_SECS: u64 = 60;
+const DEFAULT_REJECT_THRESHOLD: usize = 10;
+const DEFAULT_REJECT_WINDOW_SECS: u64 = 60;
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+const FIRST_RETRY: Duration = Duration::from_secs(1);
+const MAX_RETRY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AlertKind {
+    SequenceGap,
+    ResendInitiated,
+    SessionDown,
+    SessionRestored,
+    RejectStorm,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    /// `AL-<n>`, increasing since startup.
+    pub alert_id: String,
+    pub kind: AlertKind,
+    pub session_id: String,
+    pub at_ms: u64,
+    /// One line for a pager, e.g. `ALICE->BROKER_A missed MsgSeqNum 5-7`.
+    pub summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub begin_seq_no: Option<u64>,
+    /// 0 for a ResendRequest up to the latest message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_seq_no: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direction: Option<Direction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub down_for_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rejects: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window_ms: Option<u64>,
+}
+
+impl Alert {
+    fn new(kind: AlertKind, session_id: &str, summary: String) -> Self {
+        Self {
+            alert_id: String::new(),
+            kind,
+            session_id: session_id.to_string(),
+            at_ms: epoch_ms(),
+            summary,
+            begin_seq_no: None,
+            end_seq_no: None,
+            direction: None,
+            down_for_ms: None,
+            rejects: None,
+            window_ms: None,
+        }
+    }
+}
+
+// ── Configuration ─────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone)]
+pub struct AlertConfig {
+    webhooks: Vec<String>,
+    secret: Option<Secret>,
+    session_down_ms: u64,
+    reject_threshold: usize,
+    reject_window_ms: u64,
+    max_attempts: u32,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            webhooks: Vec::new(),
+            secret: None,
+            session_down_ms: DEFAULT_SESSION_DOWN_SECS * 1000,
+            reject_threshold: DEFAULT_REJECT_THRESHOLD,
+            reject_window_ms: DEFAULT_REJECT_WINDOW_SECS * 1000,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+}
+
+impl AlertConfig {
+    /// Reads `FIX_ALERT_WEBHOOKS`, `FIX_ALERT_SESSION_DOWN_SECS`,
+    /// `FIX_ALERT_REJECT_STORM` and `FIX_ALERT_MAX_ATTEMPTS`; `secret` is the
+    /// resolved `FIX_ALERT_SECRET`.
+    pub fn from_env(secret: Option<Secret>) -> Self {
+        let mut config = Self {
+            secret,
+            ..Self::default()
+        };
+        let var = |name| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        if let Some(urls) = var("FIX_ALERT_WEBHOOKS") {
+            for url in urls.split(',').map(str::trim).filter(|u| !u.is_empty()) {
+                if url.starts_with("http") {
+                    config.webhooks.push(url.to_string());
+                } else {
+                    warn!(url, "Ignoring malformed FIX_ALERT_WEBHOOKS entry");
+                }
+            }
+        }
+        if let Some(secs) = var("FIX_ALERT_SESSION_DOWN_SECS").and_then(|v| v.parse::<u64>().ok()) {
+            config.session_down_ms = secs * 1000;
+        }
+        if let Some(spec) = var("FIX_ALERT_REJECT_STORM") {
+            match parse_storm(&spec) {
+                Some((threshold, window_ms)) => {
+                    config.reject_threshold = threshold;
+                    config.reject_window_ms = window_ms;
+                }
+                None => warn!(spec, "Ignoring malformed FIX_ALERT_REJECT_STORM"),
+            }
+        }
+        if let Some(attempts) = var("FIX_ALERT_MAX_ATTEMPTS").and_then(|v| v.parse().ok()) {
+            config.max_attempts = attempts;
+        }
+        config.max_attempts = config.max_attempts.max(1);
+        config
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "alerts webhooks={} signed={} session_down_secs={} reject_storm={}/{}",
+            self.webhooks.len(),
+            self.secret.is_some(),
+            self.session_down_ms / 1000,
+            self.reject_threshold,
+            self.reject_window_ms / 1000
+        )
+    }
+}
+
+/// `count/secs`, e.g. `10/60`.
+fn parse_storm(spec: &str) -> Option<(usize, u64)> {
+    let (count, secs) = spec.trim().split_once('/')?;
+    let count: usize = count.trim().parse().ok()?;
+    let secs: u64 = secs.trim().parse().ok()?;
+    (count > 0 && secs > 0).then_some((count, secs * 1000))
+}
+
+// ── Tracking ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Default)]
+struct Outage {
+    since_ms: u64,
+    alerted: bool,
+}
+
+#[derive(Debug, Default)]
+struct Tracker {
+    outages: HashMap<String, Outage>,
+    /// Receipt times of each session's recent rejects, oldest first.
+    rejects: HashMap<String, VecDeque<u64>>,
+    /// No storm is raised again for a session before this.
+    quiet_until_ms: HashMap<String, u64>,
+}
+
+impl Tracker {
+    /// Counts a reject, returning the rejects in the window if that makes a
+    /// storm.
+    fn reject(&mut self, session_id: &str, now_ms: u64, config: &AlertConfig) -> Option<usize> {
+        let times = self.rejects.entry(session_id.to_string()).or_default();
+        times.push_back(now_ms);
+        while times
+            .front()
+            .is_some_and(|&t| t + config.reject_window_ms <= now_ms)
+        {
+            times.pop_front();
+        }
+        let quiet_until = self.quiet_until_ms.get(session_id).copied().unwrap_or(0);
+        if times.len() < config.reject_threshold || now_ms < quiet_until {
+            return None;
+        }
+        self.quiet_until_ms
+            .insert(session_id.to_string(), now_ms + config.reject_window_ms);
+        Some(times.len())
+    }
+
+    /// Sessions down for `after_ms` and not yet alerted, with how long.
+    fn overdue(&mut self, now_ms: u64, after_ms: u64) -> Vec<(String, u64)> {
+        let mut overdue: Vec<(String, u64)> = self
+            .outages
+            .iter_mut()
+            .filter(|(_, o)| !o.alerted && now_ms.saturating_sub(o.since_ms) >= after_ms)
+            .map(|(session_id, o)| {
+                o.alerted = true;
+                (session_id.clone(), now_ms - o.since_ms)
+            })
+            .collect();
+        overdue.sort();
+        overdue
+    }
+
+    /// Ends a session's outage, returning how long it lasted if it was
+    /// alerted.
+    fn restore(&mut self, session_id: &str, now_ms: u64) -> Option<u64> {
+        self.outages
+            .remove(session_id)
+            .filter(|o| o.alerted)
+            .map(|o| now_ms.saturating_sub(o.since_ms))
+    }
+}
+
+pub struct Alerts {
+    config: AlertConfig,
+    tracker: Mutex<Tracker>,
+    last_id: AtomicU64,
+}
+
+impl Alerts {
+    pub fn new(config: AlertConfig) -> Self {
+        Self {
+            config,
+            tracker: Mutex::new(Tracker::default()),
+            last_id: AtomicU64::new(0),
+        }
+    }
+}
+
+// ── Raising ───────────────────────────────────────────────────────────────────
+
+fn raise(state: &AppState, mut alert: Alert) {
+    let alerts = &state.alerts;
+    alert.alert_id = format!("AL-{}", alerts.last_id.fetch_add(1, Ordering::Relaxed) + 1);
+    warn!(
+        alert_id = %alert.alert_id,
+        kind = ?alert.kind,
+        session_id = %alert.session_id,
+        summary = %alert.summary,
+        "Alert raised"
+    );
+    for url in &alerts.config.webhooks {
+        let client = state.http.clone();
+        let config = alerts.config.clone();
+        let url = url.clone();
+        let alert = alert.clone();
+        tokio::spawn(
+            async move { deliver(&client, &config, &url, &alert).await }
+                .instrument(telemetry::background_span()),
+        );
+    }
+}
+
+/// An inbound MsgSeqNum past the expected one: `begin..=end` were missed.
+pub fn sequence_gap(state: &AppState, session_id: &str, begin: u64, end: u64) {
+    let mut alert = Alert::new(
+        AlertKind::SequenceGap,
+        session_id,
+        format!("{session_id} missed MsgSeqNum {begin}-{end}"),
+    );
+    alert.begin_seq_no = Some(begin);
+    alert.end_seq_no = Some(end);
+    raise(state, alert);
+}
+
+/// Raises the alerts a sent or received message calls for.
+pub async fn on_message(
+    state: &AppState,
+    session_id: &str,
+    direction: Direction,
+    msg_type: &str,
+    raw: &[u8],
+) {
+    let number = |tag| {
+        field_value(raw, tag)
+            .and_then(|v| std::str::from_utf8(v).ok())
+            .and_then(|v| v.parse().ok())
+    };
+    if msg_type == "2" {
+        let (begin, end) = (number(7), number(16));
+        let mut alert = Alert::new(
+            AlertKind::ResendInitiated,
+            session_id,
+            format!(
+                "ResendRequest {} on {session_id} for MsgSeqNum {}-{}",
+                match direction {
+                    Direction::Inbound => "received",
+                    Direction::Outbound => "sent",
+                },
+                begin.unwrap_or_default(),
+                end.unwrap_or_default()
+            ),
+        );
+        alert.begin_seq_no = begin;
+        alert.end_seq_no = end;
+        alert.direction = Some(direction);
+        raise(state, alert);
+        return;
+    }
+
+    if direction != Direction::Inbound || !throughput::is_reject(msg_type, raw) {
+        return;
+    }
+    let config = &state.alerts.config;
+    let storm = state
+        .alerts
+        .tracker
+        .lock()
+        .await
+        .reject(session_id, epoch_ms(), config);
+    if let Some(rejects) = storm {
+        let mut alert = Alert::new(
+            AlertKind::RejectStorm,
+            session_id,
+            format!(
+                "{session_id} received {rejects} rejects in {}s",
+                config.reject_window_ms / 1000
+            ),
+        );
+        alert.rejects = Some(rejects);
+        alert.window_ms = Some(config.reject_window_ms);
+        raise(state, alert);
+    }
+}
+
+/// Starts or ends a session's outage. `dropped` is a change to
+/// `DISCONNECTED` other than after a Logout.
+pub async fn on_state_change(state: &AppState, session_id: &str, dropped: bool, current: &str) {
+    let now_ms = epoch_ms();
+    let mut tracker = state.alerts.tracker.lock().await;
+    if dropped {
+        tracker.outages.insert(
+            session_id.to_string(),
+            Outage {
+                since_ms: now_ms,
+                alerted: false,
+            },
+        );
+        return;
+    }
+    if current == "DISCONNECTED" {
+        return;
+    }
+    let Some(down_for_ms) = tracker.restore(session_id, now_ms) else {
+        return;
+    };
+    drop(tracker);
+    let mut alert = Alert::new(
+        AlertKind::SessionRestored,
+        session_id,
+        format!("{session_id} is {current} after {}s down", down_for_ms / 1000),
+    );
+    alert.down_for_ms = Some(down_for_ms);
+    raise(state, alert);
+}
+
+/// Raises `SESSION_DOWN` for sessions that have stayed down too long.
+pub async fn run_watchdog(state: AppState) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        ticker.tick().await;
+        let after_ms = state.alerts.config.session_down_ms;
+        let overdue = state
+            .alerts
+            .tracker
+            .lock()
+            .await
+            .overdue(epoch_ms(), after_ms);
+        for (session_id, down_for_ms) in overdue {
+            let mut alert = Alert::new(
+                AlertKind::SessionDown,
+                &session_id,
+                format!("{session_id} has been down for {}s", down_for_ms / 1000),
+            );
+            alert.down_for_ms = Some(down_for_ms);
+            raise(&state, alert);
+        }
+    }
+}
+
+// ── Delivery ──────────────────────────────────────────────────────────────────
+
+/// Hex HMAC-SHA256 of `message`.
+fn signature(secret: &str, message: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(message);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .fold(String::new(), |mut hex, b| {
+            let _ = write!(hex, "{b:02x}");
+            hex
+        })
+}
+
+fn retry_delay(attempt: u32) -> Duration {
+    FIRST_RETRY
+        .saturating_mul(1 << (attempt - 1).min(16))
+        .min(MAX_RETRY)
+}
+
+async fn deliver(client: &reqwest::Client, config: &AlertConfig, url: &str, alert: &Alert) {
+    let body = match serde_json::to_vec(alert) {
+        Ok(body) => body,
+        Err(e) => {
+            warn!(alert_id = %alert.alert_id, error = %e, "Alert not serializable");
+            return;
+        }
+    };
+    for attempt in 1..=config.max_attempts {
+        let timestamp = epoch_ms().to_string();
+        let mut req = client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .header("X-Fix-Timestamp", &timestamp)
+            .body(body.clone());
+        if let Some(secret) = &config.secret {
+            let signed = [timestamp.as_bytes(), b".", &body].concat();
+            req = req.header(
+                "X-Fix-Signature",
+                format!("sha256={}", signature(secret.expose(), &signed)),
+            );
+        }
+        let error = match req.send().await {
+            Ok(resp) if resp.status().is_success() => {
+                info!(alert_id = %alert.alert_id, url, attempt, "Alert delivered");
+                return;
+            }
+            Ok(resp) => {
+                let status = resp.status();
+                if !(status.is_server_error() || status.as_u16() == 429) {
+                    warn!(alert_id = %alert.alert_id, url, status = %status, "Alert refused");
+                    return;
+                }
+                format!("webhook returned {status}")
+            }
+            Err(e) => e.to_string(),
+        };
+        if attempt == config.max_attempts {
+            warn!(alert_id = %alert.alert_id, url, attempt, error = %error, "Alert not delivered");
+            return;
+        }
+        let delay = retry_delay(attempt);
+        info!(
+            alert_id = %alert.alert_id,
+            url,
+            attempt,
+            error = %error,
+            retry_in_ms = delay.as_millis() as u64,
+            "Alert delivery failed, retrying"
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reject_storms_are_raised_once_per_window() {
+        let config = AlertConfig {
+            reject_threshold: 3,
+            reject_window_ms: 10_000,
+            ..AlertConfig::default()
+        };
+        let mut tracker = Tracker::default();
+        let session = "ALICE->BROKER_A";
+        assert_eq!(tracker.reject(session, 0, &config), None);
+        assert_eq!(tracker.reject(session, 9_000, &config), None);
+        // The first reject has left the window by the time of the third.
+        assert_eq!(tracker.reject(session, 10_000, &config), None);
+        assert_eq!(tracker.reject(session, 11_000, &config), Some(3));
+        assert_eq!(tracker.reject(session, 12_000, &config), None);
+        assert_eq!(tracker.reject("ALICE->BROKER_B", 12_000, &config), None);
+        assert_eq!(tracker.reject(session, 20_000, &config), None);
+        assert_eq!(tracker.reject(session, 21_000, &config), Some(3));
+    }
+
+    #[test]
+    fn outages_are_alerted_once_and_restored() {
+        let mut tracker = Tracker::default();
+        tracker.outages.insert(
+            "ALICE->BROKER_A".to_string(),
+            Outage {
+                since_ms: 1_000,
+                alerted: false,
+            },
+        );
+        assert!(tracker.overdue(30_000, 60_000).is_empty());
+        assert_eq!(
+            tracker.overdue(61_000, 60_000),
+            [("ALICE->BROKER_A".to_string(), 60_000)]
+        );
+        assert!(tracker.overdue(90_000, 60_000).is_empty());
+        assert_eq!(tracker.restore("ALICE->BROKER_A", 91_000), Some(90_000));
+        assert_eq!(tracker.restore("ALICE->BROKER_A", 92_000), None);
+    }
+
+    #[test]
+    fn signatures_are_hmac_sha256() {
+        // RFC 4231, test case 2.
+        assert_eq!(
+            signature("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(parse_storm("10/60"), Some((10, 60_000)));
+        assert_eq!(parse_storm("0/60"), None);
+        assert_eq!(retry_delay(1), Duration::from_secs(1));
+        assert_eq!(retry_delay(4), Duration::from_secs(8));
+        assert_eq!(retry_delay(40), MAX_RETRY);
+    }
+}
@@ -0,0 +1,153 @@
+//! Who sent each outbound message, for the audit of orders.
+//!
+//! Every message a client sends through the API — `/send`, `/send/batch`,
+//! the order entry endpoints and gRPC — carries an [`Attribution`]: the
+//! authenticated principal and its role, the address the request came from,
+//! its `X-Forwarded-For`, `User-Agent` and request ID, and the client's order
+//! metadata. It is stored with the message, travels with it through the
+//! outbound queue, and is returned by message search, which can also filter
+//! on the principal. Messages the gateway sends on its own, such as expiry
+//! cancels and answers to OrderStatusRequests, have none.
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header, request::Parts, HeaderMap},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+};
+use utoipa::ToSchema;
+
+use crate::{
+    auth::{Principal, Role},
+    problem::REQUEST_ID_HEADER,
+};
+
+/// Longest header value kept, so a client cannot bloat the store.
+const MAX_HEADER_LEN: usize = 256;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Attribution {
+    /// The API key or OIDC user that sent the message; absent without
+    /// authentication.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub principal: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "trader")]
+    pub role: Option<Role>,
+    /// The address the request came from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "10.1.4.20")]
+    pub source_ip: Option<IpAddr>,
+    /// `X-Forwarded-For` as the client sent it, unverified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forwarded_for: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// The request's `X-Request-Id`, as in its logs and error responses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// The client's order metadata (strategy ID, parent order ID, ...).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl Attribution {
+    /// An attribution for a request from `source_ip`, by `principal`.
+    pub fn new(
+        principal: Option<&Principal>,
+        source_ip: Option<IpAddr>,
+        headers: &HeaderMap,
+    ) -> Self {
+        let header = |name: &str| {
+            let value = headers.get(name)?.to_str().ok()?.trim();
+            (!value.is_empty()).then(|| truncate(value).to_string())
+        };
+        Self {
+            principal: principal.map(|p| p.name.clone()),
+            role: principal.map(|p| p.role),
+            source_ip,
+            forwarded_for: header("x-forwarded-for"),
+            user_agent: header(header::USER_AGENT.as_str()),
+            request_id: header(REQUEST_ID_HEADER),
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    /// This attribution for an order with `metadata`.
+    pub fn with_metadata(mut self, metadata: &HashMap<String, String>) -> Self {
+        self.metadata = metadata
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        self
+    }
+}
+
+/// `value` cut to at most [`MAX_HEADER_LEN`] bytes, on a character boundary.
+fn truncate(value: &str) -> &str {
+    let mut end = value.len().min(MAX_HEADER_LEN);
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    &value[..end]
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Attribution {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let source_ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let principal = parts.extensions.get::<Principal>();
+        Ok(Self::new(principal, source_ip, &parts.headers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn requests_are_attributed_to_their_principal_and_source() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_static("203.0.113.9, 10.0.0.2"));
+        headers.insert(header::USER_AGENT, HeaderValue::from_static("oms/4.2"));
+        headers.insert(REQUEST_ID_HEADER, HeaderValue::from_static("req-42"));
+        let trader = Principal {
+            name: "oms-prod".to_string(),
+            role: Role::Trader,
+            sessions: BTreeSet::from(["*".to_string()]),
+        };
+        let metadata = HashMap::from([("strategy_id".to_string(), "s-7".to_string())]);
+
+        let attribution = Attribution::new(Some(&trader), "10.1.4.20".parse().ok(), &headers)
+            .with_metadata(&metadata);
+        assert_eq!(
+            serde_json::to_value(&attribution).unwrap(),
+            serde_json::json!({
+                "principal": "oms-prod",
+                "role": "trader",
+                "source_ip": "10.1.4.20",
+                "forwarded_for": "203.0.113.9, 10.0.0.2",
+                "user_agent": "oms/4.2",
+                "request_id": "req-42",
+                "metadata": { "strategy_id": "s-7" }
+            })
+        );
+
+        let anonymous = Attribution::new(None, None, &HeaderMap::new());
+        assert_eq!(anonymous, Attribution::default());
+        assert_eq!(serde_json::to_value(&anonymous).unwrap(), serde_json::json!({}));
+        assert_eq!(truncate(&"é".repeat(200)).len(), 256);
+    }
+}
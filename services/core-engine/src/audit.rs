@@ -0,0 +1,363 @@
+//! Audit log of administrative actions.
+//!
+//! Every state-changing admin action is recorded: who made it, when, and what
+//! it changed. Examples are a session state change (including a forced
+//! logout), a queue drain, a replay or re-drive, a store compaction or export,
+//! and configuration loaded at runtime. With `FIX_AUDIT_LOG` set, or
+//! `FIX_STORE_DIR` (as `audit.log` there), each entry is appended to the file
+//! and synced to disk. The file is never rewritten, and it is read back at
+//! startup so `GET /api/v1/admin/audit` covers earlier runs too.
+//!
+//! Who is the name of the request's API key, or the user its bearer token was
+//! issued to. Without authentication configured, it is the `X-Operator`
+//! header, or `anonymous` without one.
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query, State},
+    http::request::Parts,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    convert::Infallible,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{auth::Principal, clock::epoch_ms, problem::CorrelationId, records, AppState};
+
+pub const OPERATOR_HEADER: &str = "x-operator";
+
+const FILE_NAME: &str = "audit.log";
+const ANONYMOUS: &str = "anonymous";
+const MAX_OPERATOR_LEN: usize = 128;
+const DEFAULT_LIMIT: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AuditAction {
+    SessionStateChanged,
+    QueueDrained,
+    ReplayStarted,
+    RedriveStarted,
+    StoreCompacted,
+    StoreExported,
+    RulePackRegistered,
+    UpstreamRegistered,
+    TemplateStored,
+    TemplateDeleted,
+    SbeSchemaLoaded,
+    FastTemplatesLoaded,
+    ConfigReloaded,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AuditEntry {
+    pub at_ms: u64,
+    pub actor: String,
+    pub action: AuditAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub detail: String,
+    /// The request's `X-Correlation-ID`, to find it in the logs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+/// Who made a request, for the audit log.
+#[derive(Debug, Clone)]
+pub struct Actor {
+    pub name: String,
+    correlation_id: Option<String>,
+}
+
+impl Actor {
+    /// The engine itself, acting on `name`'s behalf, e.g. a signal.
+    pub fn system(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            correlation_id: None,
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Actor {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let name = match parts.extensions.get::<Principal>() {
+            Some(principal) => principal.name.clone(),
+            None => parts
+                .headers
+                .get(OPERATOR_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .map(str::trim)
+                .filter(|name| !name.is_empty() && name.len() <= MAX_OPERATOR_LEN)
+                .unwrap_or(ANONYMOUS)
+                .to_string(),
+        };
+        let correlation_id = parts
+            .extensions
+            .get::<CorrelationId>()
+            .map(|id| id.0.clone());
+        Ok(Self {
+            name,
+            correlation_id,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct AuditLog {
+    path: Option<PathBuf>,
+    file: Option<File>,
+    entries: Vec<AuditEntry>,
+}
+
+impl AuditLog {
+    /// The file in `FIX_AUDIT_LOG`, or `audit.log` in `FIX_STORE_DIR`; kept
+    /// in memory only if neither is set.
+    pub fn from_env() -> io::Result<Self> {
+        let var = |name| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        match (var("FIX_AUDIT_LOG"), var("FIX_STORE_DIR")) {
+            (Some(path), _) => Self::open(Path::new(path.trim())),
+            (None, Some(dir)) => Self::open(&Path::new(dir.trim()).join(FILE_NAME)),
+            (None, None) => Ok(Self::default()),
+        }
+    }
+
+    fn open(path: &Path) -> io::Result<Self> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let entries = if path.exists() {
+            load(path)?
+        } else {
+            Vec::new()
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)?;
+        // A torn last line is left as it is, and later entries start on a
+        // line of their own.
+        if file.metadata()?.len() > 0 {
+            let mut last = [0u8];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                file.write_all(b"\n")?;
+            }
+        }
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            file: Some(file),
+            entries,
+        })
+    }
+
+    /// Where the log is kept, for startup reporting.
+    pub fn describe(&self) -> String {
+        match &self.path {
+            Some(path) => format!(
+                "audit log (file: {}) entries={}",
+                path.display(),
+                self.entries.len()
+            ),
+            None => "audit log (memory only)".to_string(),
+        }
+    }
+
+    /// Records an entry; with a file it is on disk when this returns.
+    pub fn append(&mut self, entry: AuditEntry) -> io::Result<()> {
+        let written = match &mut self.file {
+            Some(file) => {
+                let mut line = records::encode(&entry).to_string();
+                line.push('\n');
+                file.write_all(line.as_bytes())
+                    .and_then(|()| file.sync_data())
+            }
+            None => Ok(()),
+        };
+        self.entries.push(entry);
+        written
+    }
+
+    /// The newest `limit` matching entries, oldest first.
+    fn query(&self, q: &AuditQuery) -> Vec<AuditEntry> {
+        let matching: Vec<&AuditEntry> = self
+            .entries
+            .iter()
+            .filter(|e| q.since_ms.is_none_or(|since| e.at_ms >= since))
+            .filter(|e| q.until_ms.is_none_or(|until| e.at_ms < until))
+            .filter(|e| q.action.is_none_or(|action| e.action == action))
+            .filter(|e| q.actor.as_deref().is_none_or(|actor| e.actor == actor))
+            .filter(|e| {
+                q.session_id
+                    .as_deref()
+                    .is_none_or(|id| e.session_id.as_deref() == Some(id))
+            })
+            .collect();
+        let limit = q.limit.unwrap_or(DEFAULT_LIMIT);
+        matching[matching.len().saturating_sub(limit)..]
+            .iter()
+            .map(|e| (*e).clone())
+            .collect()
+    }
+}
+
+fn load(path: &Path) -> io::Result<Vec<AuditEntry>> {
+    let mut entries = Vec::new();
+    for (n, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line)
+            .map_err(|e| records::RecordError::Malformed(e.to_string()))
+            .and_then(records::decode::<AuditEntry>)
+        {
+            Ok(entry) => entries.push(entry),
+            Err(e) => warn!(
+                path = %path.display(),
+                line = n + 1,
+                error = %e,
+                "Skipping unreadable audit entry"
+            ),
+        }
+    }
+    info!(path = %path.display(), entries = entries.len(), "Audit log loaded");
+    Ok(entries)
+}
+
+/// Records an admin action made by `actor`.
+pub async fn record(
+    state: &AppState,
+    actor: &Actor,
+    action: AuditAction,
+    session_id: Option<&str>,
+    detail: impl Into<String>,
+) {
+    let entry = AuditEntry {
+        at_ms: epoch_ms(),
+        actor: actor.name.clone(),
+        action,
+        session_id: session_id.map(str::to_string),
+        detail: detail.into(),
+        correlation_id: actor.correlation_id.clone(),
+    };
+    info!(
+        actor = %entry.actor,
+        action = ?entry.action,
+        session_id = entry.session_id.as_deref().unwrap_or(""),
+        detail = %entry.detail,
+        "Admin action"
+    );
+    if let Err(e) = state.audit.lock().await.append(entry) {
+        warn!(error = %e, "Failed to write the audit log");
+    }
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditQuery {
+    since_ms: Option<u64>,
+    /// Exclusive.
+    until_ms: Option<u64>,
+    action: Option<AuditAction>,
+    actor: Option<String>,
+    session_id: Option<String>,
+    /// Newest entries returned, default 1000.
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuditResponse {
+    entries: Vec<AuditEntry>,
+    count: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/audit",
+    tag = "admin",
+    params(AuditQuery),
+    responses(
+        (status = 200, description = "OK", body = AuditResponse),
+    )
+)]
+pub async fn list(
+    State(state): State<AppState>,
+    Query(q): Query<AuditQuery>,
+) -> Json<AuditResponse> {
+    let entries = state.audit.lock().await.query(&q);
+    let count = entries.len();
+    Json(AuditResponse { entries, count })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(at_ms: u64, actor: &str, action: AuditAction) -> AuditEntry {
+        AuditEntry {
+            at_ms,
+            actor: actor.to_string(),
+            action,
+            session_id: Some("ALICE->BROKER_A".to_string()),
+            detail: "ACTIVE -> DISCONNECTED".to_string(),
+            correlation_id: None,
+        }
+    }
+
+    #[test]
+    fn entries_survive_reopen_after_a_torn_write() {
+        let dir = std::env::temp_dir().join(format!("fix-audit-{}", uuid::Uuid::new_v4()));
+        let path = dir.join(FILE_NAME);
+        {
+            let mut log = AuditLog::open(&path).unwrap();
+            log.append(entry(1, "ops-jane", AuditAction::SessionStateChanged))
+                .unwrap();
+        }
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"at_ms":2,"act"#).unwrap();
+
+        let mut log = AuditLog::open(&path).unwrap();
+        log.append(entry(3, "ops-raj", AuditAction::QueueDrained))
+            .unwrap();
+        drop(log);
+
+        let log = AuditLog::open(&path).unwrap();
+        let actors: Vec<&str> = log.entries.iter().map(|e| e.actor.as_str()).collect();
+        assert_eq!(actors, ["ops-jane", "ops-raj"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn queries_filter_and_keep_the_newest() {
+        let mut log = AuditLog::default();
+        for (at_ms, actor, action) in [
+            (1, "ops-jane", AuditAction::SessionStateChanged),
+            (2, "ops-raj", AuditAction::QueueDrained),
+            (3, "ops-jane", AuditAction::StoreCompacted),
+        ] {
+            log.append(entry(at_ms, actor, action)).unwrap();
+        }
+        let q = |q: serde_json::Value| serde_json::from_value::<AuditQuery>(q).unwrap();
+
+        let jane = log.query(&q(serde_json::json!({ "actor": "ops-jane" })));
+        assert_eq!(jane.len(), 2);
+        let drained = log.query(&q(serde_json::json!({ "action": "QUEUE_DRAINED" })));
+        assert_eq!(drained[0].at_ms, 2);
+        let newest = log.query(&q(serde_json::json!({ "since_ms": 2, "limit": 1 })));
+        assert_eq!(newest[0].at_ms, 3);
+    }
+}
@@ -0,0 +1,604 @@
+//! API key authentication and role-based authorization of the REST API.
+//!
+//! `FIX_API_KEYS_FILE` names a JSON file of API keys by name. The file holds
+//! the hex SHA-256 of each key, never the key itself, its role, and for a
+//! trader the sessions it may send on:
+//!
+//! ```json
+//! { "oms-prod": { "sha256": "9f86d081884c7d65...", "role": "trader",
+//!     "sessions": ["ALICE->BROKER_A", "ALICE->BROKER_B"] },
+//!   "grafana": { "sha256": "60303ae22b998861...", "role": "viewer",
+//!     "expires_at_ms": 1798761600000 } }
+//! ```
+//!
+//! With the file set, every request under `/api`, except the API docs, must
+//! carry a key in `X-API-Key`, and the key's name is who the audit log
+//! records. Each request needs one scope, which the roles grant:
+//!
+//! - `read`, granted to every role: `GET` requests, and the `POST`s that only
+//!   parse, normalize, validate, encode or decode, and GraphQL queries.
+//! - `send`, granted to traders and admins: requests that send to a
+//!   counterparty or change orders, subscriptions, quotes or inbound traffic.
+//!   A trader may only send on its assigned sessions (`"*"` for all), which
+//!   the handlers check with [`Caller::check_session`] once they know the
+//!   session.
+//! - `admin`, granted to admins: everything under `/api/v1/admin`, session
+//!   state changes such as forced logouts, queue drains, replays, and
+//!   templates, schemas and upstreams registered at runtime.
+//!
+//! The file is checked for changes every 5 seconds and re-read, so keys are
+//! rotated without a restart: add the new key under a new name, move clients
+//! over, then remove the old one. A change that cannot be read is logged and
+//! the keys already loaded stay in force. An unreadable file at startup stops
+//! it.
+//!
+//! Requests may instead carry an OIDC bearer token, whose claims map to a
+//! role and sessions; see [`crate::oidc`]. Without `FIX_API_KEYS_FILE` or an
+//! OIDC issuer, requests are not authenticated.
+//!
+//! gRPC calls carry the same credentials in their `x-api-key` or
+//! `authorization` metadata and are checked by [`identify`] and
+//! [`check_scope`] too; see [`crate::grpc`].
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{header, request::Parts, HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeSet, HashMap},
+    convert::Infallible,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+use tracing::warn;
+
+use crate::{clock::epoch_ms, problem::ApiError, timeline::EventKind, AppState};
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+const RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+
+/// `POST`s that change nothing, so `read` is enough.
+const READ_ONLY_POSTS: [&str; 9] = [
+    "/api/v1/fix/parse",
+    "/api/v1/fix/parse/bulk",
+    "/api/v1/fix/normalize",
+    "/api/v1/fix/validate",
+    "/api/v1/fix/fixml/encode",
+    "/api/v1/fix/fixml/decode",
+    "/api/v1/fix/sbe/encode",
+    "/api/v1/fix/sbe/decode",
+    "/api/v1/graphql",
+];
+
+/// Changes to the gateway's own configuration and stored traffic.
+const ADMIN_PATHS: [&str; 4] = [
+    "/api/v1/fix/sbe/schemas",
+    "/api/v1/fix/fast/templates",
+    "/api/v1/fix/replay",
+    "/api/v1/federation/upstreams",
+];
+
+/// What a request does, and so which roles may make it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Read,
+    Send,
+    Admin,
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Read => "read",
+            Self::Send => "send",
+            Self::Admin => "admin",
+        })
+    }
+}
+
+/// The scope a request needs.
+pub fn required_scope(method: &Method, path: &str) -> Scope {
+    let path = path.trim_end_matches('/');
+    let segments: Vec<&str> = path.split('/').collect();
+    match segments[..] {
+        ["", "api", "v1", "admin", ..] => return Scope::Admin,
+        _ if method == Method::GET || method == Method::HEAD => return Scope::Read,
+        _ if method == Method::POST && READ_ONLY_POSTS.contains(&path) => return Scope::Read,
+        ["", "api", "v1", "fix", "sessions", _, "state" | "queue"] => return Scope::Admin,
+        ["", "api", "v1", "fix", "templates", ..] => return Scope::Admin,
+        _ => {}
+    }
+    if ADMIN_PATHS.contains(&path) {
+        Scope::Admin
+    } else {
+        Scope::Send
+    }
+}
+
+/// Whether requests to `path` need a key: the API, but not its docs.
+fn is_protected(path: &str) -> bool {
+    let api = path == "/api" || path.starts_with("/api/");
+    let docs = path == "/api/docs" || path.starts_with("/api/docs/");
+    api && !docs
+}
+
+/// Ordered by what they grant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Trader,
+    Admin,
+}
+
+impl Role {
+    pub fn grants(self, scope: Scope) -> bool {
+        match scope {
+            Scope::Read => true,
+            Scope::Send => self >= Self::Trader,
+            Scope::Admin => self == Self::Admin,
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(Self::Viewer),
+            "trader" => Ok(Self::Trader),
+            "admin" => Ok(Self::Admin),
+            other => Err(format!("unknown role `{other}`")),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Viewer => "viewer",
+            Self::Trader => "trader",
+            Self::Admin => "admin",
+        })
+    }
+}
+
+/// Assigned to a trader, it allows every session.
+pub const ALL_SESSIONS: &str = "*";
+
+/// Who made an authenticated request, in its extensions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    pub name: String,
+    pub role: Role,
+    /// The sessions a trader may send on.
+    pub sessions: BTreeSet<String>,
+}
+
+impl Principal {
+    pub fn may_send_on(&self, session_id: &str) -> bool {
+        match self.role {
+            Role::Viewer => false,
+            Role::Trader => {
+                self.sessions.contains(ALL_SESSIONS) || self.sessions.contains(session_id)
+            }
+            Role::Admin => true,
+        }
+    }
+}
+
+/// The principal of a request, absent when authentication is off.
+#[derive(Debug, Clone)]
+pub struct Caller(pub Option<Principal>);
+
+impl Caller {
+    /// Fails unless the caller may send on `session_id`.
+    pub fn check_session(&self, session_id: &str) -> Result<(), ApiError> {
+        match &self.0 {
+            Some(principal) if !principal.may_send_on(session_id) => {
+                let name = &principal.name;
+                warn!(principal = %name, session_id, "Rejected send on an unassigned session");
+                Err(ApiError::new(StatusCode::FORBIDDEN, "session_forbidden")
+                    .detail(format!("{name} may not send on {session_id}")))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Caller {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(parts.extensions.get::<Principal>().cloned()))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyEntry {
+    sha256: String,
+    role: Role,
+    #[serde(default)]
+    sessions: BTreeSet<String>,
+    #[serde(default)]
+    expires_at_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+struct Key {
+    principal: Principal,
+    expires_at_ms: Option<u64>,
+}
+
+fn decode_sha256(hex: &str) -> Option<[u8; 32]> {
+    let hex = hex.trim().as_bytes();
+    if hex.len() != 64 {
+        return None;
+    }
+    let mut digest = [0u8; 32];
+    for (byte, pair) in digest.iter_mut().zip(hex.chunks(2)) {
+        let pair = std::str::from_utf8(pair).ok()?;
+        *byte = u8::from_str_radix(pair, 16).ok()?;
+    }
+    Some(digest)
+}
+
+/// Keys by the SHA-256 of the key.
+fn parse(json: &str) -> Result<HashMap<[u8; 32], Key>, String> {
+    let entries: HashMap<String, KeyEntry> =
+        serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let mut keys = HashMap::with_capacity(entries.len());
+    for (name, entry) in entries {
+        let digest = decode_sha256(&entry.sha256)
+            .ok_or_else(|| format!("{name}: sha256 must be 64 hex digits"))?;
+        if entry.role == Role::Trader && entry.sessions.is_empty() {
+            return Err(format!("{name}: a trader needs sessions"));
+        }
+        let key = Key {
+            principal: Principal {
+                name: name.clone(),
+                role: entry.role,
+                sessions: entry.sessions,
+            },
+            expires_at_ms: entry.expires_at_ms,
+        };
+        if let Some(other) = keys.insert(digest, key) {
+            return Err(format!("{name} and {} have the same key", other.principal.name));
+        }
+    }
+    Ok(keys)
+}
+
+#[derive(Debug, Default)]
+pub struct ApiKeys {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    keys: HashMap<[u8; 32], Key>,
+}
+
+impl ApiKeys {
+    /// Loads `file`, or the file in `FIX_API_KEYS_FILE`; without either,
+    /// nothing is authenticated.
+    pub fn from_env(file: Option<&str>) -> Result<Self, String> {
+        match file.map(str::to_string).or_else(|| std::env::var("FIX_API_KEYS_FILE").ok()) {
+            Some(path) if !path.trim().is_empty() => Self::open(Path::new(path.trim())),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    fn open(path: &Path) -> Result<Self, String> {
+        let mut keys = Self {
+            path: Some(path.to_path_buf()),
+            ..Self::default()
+        };
+        keys.reload()?;
+        Ok(keys)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// What is authenticated, for startup reporting.
+    pub fn describe(&self) -> String {
+        match &self.path {
+            None => "api keys off (requests are not authenticated)".to_string(),
+            Some(path) => format!("api keys={} ({})", self.keys.len(), path.display()),
+        }
+    }
+
+    /// Re-reads the file if it changed since it was last read; `Ok(true)`
+    /// if it was.
+    fn reload(&mut self) -> Result<bool, String> {
+        let Some(path) = &self.path else {
+            return Ok(false);
+        };
+        let named = |e: &dyn fmt::Display| format!("{}: {e}", path.display());
+        let modified = std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .map_err(|e| named(&e))?;
+        if self.modified == Some(modified) {
+            return Ok(false);
+        }
+        let json = std::fs::read_to_string(path).map_err(|e| named(&e))?;
+        self.keys = parse(&json).map_err(|e| named(&e))?;
+        self.modified = Some(modified);
+        Ok(true)
+    }
+
+    /// The principal of an unexpired key.
+    pub fn authenticate(&self, key: &str, now_ms: u64) -> Option<&Principal> {
+        let digest: [u8; 32] = Sha256::digest(key.as_bytes()).into();
+        self.keys
+            .get(&digest)
+            .filter(|k| k.expires_at_ms.is_none_or(|at| now_ms < at))
+            .map(|k| &k.principal)
+    }
+}
+
+/// Picks up changes to the key file.
+pub async fn run_reloader(state: AppState) {
+    let mut ticker = tokio::time::interval(RELOAD_INTERVAL);
+    loop {
+        ticker.tick().await;
+        let (result, describe) = {
+            let mut keys = state.api_keys.lock().await;
+            if !keys.is_enabled() {
+                return;
+            }
+            (keys.reload(), keys.describe())
+        };
+        match result {
+            Ok(true) => {
+                let mut timeline = state.timeline.lock().await;
+                timeline.record(EventKind::ConfigLoaded, None, describe);
+            }
+            Ok(false) => {}
+            Err(e) => warn!(error = %e, "Keeping the API keys loaded before"),
+        }
+    }
+}
+
+fn header_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+fn unauthorized(detail: &str) -> ApiError {
+    ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized").detail(detail)
+}
+
+/// Authenticates a request by its `X-API-Key` or bearer token: its
+/// principal, or `None` when authentication is off. Fails with 401 when it
+/// carries neither or one that is not valid.
+pub async fn identify(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<Option<Principal>, ApiError> {
+    let key = header_value(headers, API_KEY_HEADER);
+    let bearer = header_value(headers, header::AUTHORIZATION.as_str())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string());
+    let (keys_enabled, key_principal) = {
+        let keys = state.api_keys.lock().await;
+        let principal = key.and_then(|key| keys.authenticate(key, epoch_ms())).cloned();
+        (keys.is_enabled(), principal)
+    };
+    if !keys_enabled && state.oidc.is_none() {
+        return Ok(None);
+    }
+
+    let authenticated = match (&state.oidc, bearer, key) {
+        (Some(oidc), Some(token), _) => oidc.authenticate(&state.http, &token, epoch_ms()).await,
+        (_, _, Some(_)) if keys_enabled => {
+            key_principal.ok_or_else(|| unauthorized("unknown or expired API key"))
+        }
+        _ => Err(unauthorized(match (state.oidc.is_some(), keys_enabled) {
+            (true, true) => "missing X-API-Key header or bearer token",
+            (true, false) => "missing bearer token",
+            _ => "missing X-API-Key header",
+        })),
+    };
+    authenticated.map(Some)
+}
+
+/// Fails with 403 unless `principal`'s role grants `scope`. `path` is what
+/// was called, for the log.
+pub fn check_scope(principal: &Principal, scope: Scope, path: &str) -> Result<(), ApiError> {
+    if principal.role.grants(scope) {
+        return Ok(());
+    }
+    let role = principal.role;
+    warn!(
+        path,
+        principal = %principal.name,
+        %role,
+        %scope,
+        "Rejected API request for its role"
+    );
+    Err(ApiError::new(StatusCode::FORBIDDEN, "forbidden")
+        .detail(format!("{} is a {role}, which does not grant {scope}", principal.name)))
+}
+
+/// Middleware authenticating requests to the API by their `X-API-Key` or
+/// bearer token.
+pub async fn authenticate(
+    State(state): State<AppState>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let path = req.uri().path();
+    if !is_protected(path) {
+        return next.run(req).await;
+    }
+    let scope = required_scope(req.method(), path);
+    let principal = match identify(&state, req.headers()).await {
+        Ok(Some(principal)) => principal,
+        Ok(None) => return next.run(req).await,
+        Err(error) if error.status() == StatusCode::UNAUTHORIZED => {
+            warn!(path, reason = error.message(), "Rejected unauthenticated API request");
+            let keys_enabled = state.api_keys.lock().await.is_enabled();
+            let challenge = match (state.oidc.is_some(), keys_enabled) {
+                (true, true) => "Bearer, ApiKey",
+                (true, false) => "Bearer",
+                _ => "ApiKey",
+            };
+            return ([(header::WWW_AUTHENTICATE, challenge)], error).into_response();
+        }
+        Err(error) => return error.into_response(),
+    };
+    if let Err(error) = check_scope(&principal, scope, path) {
+        return error.into_response();
+    }
+    req.extensions_mut().insert(principal);
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Write;
+
+    fn sha256_hex(key: &str) -> String {
+        Sha256::digest(key.as_bytes())
+            .iter()
+            .fold(String::new(), |mut hex, b| {
+                let _ = write!(hex, "{b:02x}");
+                hex
+            })
+    }
+
+    fn keys(json: &str) -> ApiKeys {
+        ApiKeys {
+            path: Some(PathBuf::from("keys.json")),
+            modified: None,
+            keys: parse(json).unwrap(),
+        }
+    }
+
+    #[test]
+    fn keys_authenticate_by_their_hash_until_they_expire() {
+        let keys = keys(&format!(
+            r#"{{ "oms": {{ "sha256": "{}", "role": "trader", "sessions": ["ALICE->BROKER_A"] }},
+                 "old": {{ "sha256": "{}", "role": "viewer", "expires_at_ms": 1000 }} }}"#,
+            sha256_hex("oms-secret"),
+            sha256_hex("old-secret").to_uppercase(),
+        ));
+        let oms = keys.authenticate("oms-secret", 0).unwrap();
+        assert_eq!(oms.name, "oms");
+        assert_eq!(oms.role, Role::Trader);
+        assert!(keys.authenticate(&sha256_hex("oms-secret"), 0).is_none());
+        assert!(keys.authenticate("old-secret", 999).is_some());
+        assert!(keys.authenticate("old-secret", 1000).is_none());
+    }
+
+    #[test]
+    fn malformed_key_files_are_rejected() {
+        let hash = sha256_hex("secret");
+        for json in [
+            r#"{ "oms": { "sha256": "abc", "role": "viewer" } }"#.to_string(),
+            format!(r#"{{ "oms": {{ "sha256": "{hash}" }} }}"#),
+            format!(r#"{{ "oms": {{ "sha256": "{hash}", "role": "superuser" }} }}"#),
+            format!(r#"{{ "oms": {{ "sha256": "{hash}", "role": "trader" }} }}"#),
+            format!(r#"{{ "oms": {{ "sha256": "{hash}", "role": "viewer", "key": "x" }} }}"#),
+            format!(
+                r#"{{ "a": {{ "sha256": "{hash}", "role": "viewer" }},
+                     "b": {{ "sha256": "{hash}", "role": "admin" }} }}"#
+            ),
+        ] {
+            assert!(parse(&json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn roles_grant_scopes_and_traders_their_sessions() {
+        let principal = |role, sessions: &[&str]| Principal {
+            name: "someone".to_string(),
+            role,
+            sessions: sessions.iter().map(|s| s.to_string()).collect(),
+        };
+        let viewer = principal(Role::Viewer, &[]);
+        let trader = principal(Role::Trader, &["ALICE->BROKER_A"]);
+        let admin = principal(Role::Admin, &[]);
+        assert!(viewer.role.grants(Scope::Read) && !viewer.role.grants(Scope::Send));
+        assert!(trader.role.grants(Scope::Send) && !trader.role.grants(Scope::Admin));
+        assert!(admin.role.grants(Scope::Admin));
+
+        assert!(trader.may_send_on("ALICE->BROKER_A"));
+        assert!(!trader.may_send_on("ALICE->BROKER_B"));
+        assert!(principal(Role::Trader, &[ALL_SESSIONS]).may_send_on("ALICE->BROKER_B"));
+        assert!(!viewer.may_send_on("ALICE->BROKER_A"));
+        assert!(admin.may_send_on("ALICE->BROKER_B"));
+
+        let err = Caller(Some(trader)).check_session("ALICE->BROKER_B").unwrap_err();
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+        assert!(Caller(None).check_session("ALICE->BROKER_B").is_ok());
+    }
+
+    #[test]
+    fn scopes_follow_what_a_request_can_change() {
+        let cases = [
+            (Method::GET, "/api/v1/orders", Scope::Read),
+            (Method::POST, "/api/v1/fix/validate", Scope::Read),
+            (Method::POST, "/api/v1/graphql", Scope::Read),
+            (Method::POST, "/api/v1/fix/send", Scope::Send),
+            (Method::POST, "/api/v2/orders/ORD-1/cancel", Scope::Send),
+            (Method::POST, "/api/v1/fix/inbound", Scope::Send),
+            (Method::GET, "/api/v1/admin/audit", Scope::Admin),
+            (Method::POST, "/api/v1/fix/sessions/ALICE->BROKER_A/state", Scope::Admin),
+            (Method::DELETE, "/api/v1/fix/sessions/ALICE->BROKER_A/queue", Scope::Admin),
+            (Method::GET, "/api/v1/fix/sessions/ALICE->BROKER_A/queue", Scope::Read),
+            (Method::DELETE, "/api/v1/fix/templates/nos", Scope::Admin),
+            (Method::POST, "/api/v1/federation/upstreams/", Scope::Admin),
+        ];
+        for (method, path, scope) in cases {
+            assert_eq!(required_scope(&method, path), scope, "{method} {path}");
+        }
+        assert!(is_protected("/api/v1/fix/send"));
+        assert!(!is_protected("/api/docs/openapi.json"));
+        assert!(!is_protected("/readyz"));
+        assert!(!is_protected("/apis"));
+    }
+
+    #[test]
+    fn changed_files_are_reloaded_and_bad_changes_keep_the_old_keys() {
+        let dir = std::env::temp_dir().join(format!("fix-auth-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("keys.json");
+        let write = |name: &str, key: &str| {
+            let hash = sha256_hex(key);
+            let json = format!(r#"{{ "{name}": {{ "sha256": "{hash}", "role": "viewer" }} }}"#);
+            std::fs::write(&path, json).unwrap();
+        };
+        write("first", "one");
+        let mut keys = ApiKeys::open(&path).unwrap();
+        assert!(keys.authenticate("one", 0).is_some());
+        assert_eq!(keys.reload(), Ok(false));
+
+        write("second", "two");
+        keys.modified = None;
+        assert_eq!(keys.reload(), Ok(true));
+        assert!(keys.authenticate("one", 0).is_none());
+        assert_eq!(keys.authenticate("two", 0).unwrap().name, "second");
+
+        std::fs::write(&path, "{ not json").unwrap();
+        keys.modified = None;
+        assert!(keys.reload().is_err());
+        assert!(keys.authenticate("two", 0).is_some());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,215 @@
+//! Batch send: several messages on one session in one request.
+//!
+//! `POST /api/v1/fix/send/batch` takes the messages of a basket and hands
+//! them to the session's task as one command, so they are sent in order and
+//! no other message on the session is sent between them. Each message goes through the same
+//! path as `/send` and is queued the same way if the session is not logged
+//! on. The first message rejected stops the batch; the messages after it are
+//! not sent. A batch takes one token per message from the client's
+//! [rate limit](crate::rate_limit) bucket, all before any is sent.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::{
+    attribution::Attribution,
+    auth::Caller,
+    check_send,
+    problem::ApiError,
+    rate_limit::{self, Client, BATCH_PATH},
+    session_id_of, templates, AppState, SendOptions, SendRequest, Submitted,
+};
+
+/// Most messages accepted in one batch.
+const MAX_BATCH_SIZE: usize = 1000;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchRequest {
+    /// Sent in this order; all on the same session.
+    messages: Vec<SendRequest>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchResponse {
+    session_id: String,
+    sent: usize,
+    queued: usize,
+    /// One result per message, in request order.
+    results: Vec<BatchResult>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum BatchStatus {
+    Sent,
+    Queued,
+    Rejected,
+    /// Not sent because an earlier message was rejected.
+    Skipped,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct BatchResult {
+    status: BatchStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sequence_number: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queue_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<usize>,
+    /// For a rejected message, the status `/send` would have returned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<u16>,
+    /// Why it was rejected, when known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+impl BatchResult {
+    fn new(status: BatchStatus) -> Self {
+        Self {
+            status,
+            sequence_number: None,
+            fix_message: None,
+            queue_id: None,
+            position: None,
+            error: None,
+            detail: None,
+        }
+    }
+}
+
+/// Sends the messages in order on their session, answering with each one's
+/// outcome.
+#[utoipa::path(
+    post,
+    path = "/api/v1/fix/send/batch",
+    tag = "fix",
+    params(SendOptions),
+    request_body = BatchRequest,
+    responses(
+        (status = 200, description = "The outcome of each message", body = BatchResponse),
+        (status = 400, description = "Empty, too large, or for several sessions"),
+        (status = 429, description = "The client is over its rate limit; see Retry-After"),
+    )
+)]
+pub async fn send_batch(
+    State(state): State<AppState>,
+    Query(opts): Query<SendOptions>,
+    caller: Caller,
+    Client(client): Client,
+    attribution: Attribution,
+    Json(mut req): Json<BatchRequest>,
+) -> Result<Json<BatchResponse>, ApiError> {
+    for message in &mut req.messages {
+        templates::resolve(&state, message).await?;
+    }
+    let session_id = batch_session(&req.messages)?;
+    rate_limit::admit(&state, &client, BATCH_PATH, req.messages.len()).await?;
+    caller.check_session(&session_id)?;
+    for message in &req.messages {
+        check_send(message)?;
+    }
+
+    let total = req.messages.len();
+    for message in &mut req.messages {
+        message.attribution = Some(attribution.clone());
+    }
+    let outcomes = state
+        .actors
+        .submit(&state, &session_id, &opts, req.messages)
+        .await?;
+    let mut results = Vec::with_capacity(total);
+    let mut rejected = false;
+    for outcome in outcomes {
+        let result = match outcome {
+            Ok(Submitted::Sent(sent)) => BatchResult {
+                sequence_number: Some(sent.sequence_number),
+                fix_message: Some(sent.fix_message),
+                ..BatchResult::new(BatchStatus::Sent)
+            },
+            Ok(Submitted::Queued(queued)) => BatchResult {
+                queue_id: Some(queued.queue_id),
+                position: Some(queued.position),
+                ..BatchResult::new(BatchStatus::Queued)
+            },
+            Err(e) => {
+                rejected = true;
+                BatchResult {
+                    error: Some(e.status().as_u16()),
+                    detail: e.message().map(str::to_string),
+                    ..BatchResult::new(BatchStatus::Rejected)
+                }
+            }
+        };
+        results.push(result);
+    }
+    results.resize_with(total, || BatchResult::new(BatchStatus::Skipped));
+
+    let count = |status| results.iter().filter(|r| r.status == status).count();
+    let (sent, queued) = (count(BatchStatus::Sent), count(BatchStatus::Queued));
+    info!(
+        session_id = %session_id,
+        messages = results.len(),
+        sent,
+        queued,
+        rejected,
+        "FIX batch sent"
+    );
+    Ok(Json(BatchResponse {
+        session_id,
+        sent,
+        queued,
+        results,
+    }))
+}
+
+/// The session every message in the batch is for.
+fn batch_session(messages: &[SendRequest]) -> Result<String, StatusCode> {
+    if messages.is_empty() || messages.len() > MAX_BATCH_SIZE {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let session_id = session_id_of(&messages[0]);
+    if messages.iter().any(|m| session_id_of(m) != session_id) {
+        info!(session_id = %session_id, "FIX batch rejected: messages for several sessions");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(session_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn message(target: &str) -> SendRequest {
+        let fields = HashMap::from([("TargetCompID".to_string(), target.to_string())]);
+        SendRequest {
+            msg_type: "NewOrderSingle".to_string(),
+            fields,
+            groups: Default::default(),
+            metadata: HashMap::new(),
+            reply_to: None,
+            ttl_ms: None,
+            template: None,
+            attribution: None,
+        }
+    }
+
+    #[test]
+    fn batches_are_for_one_session() {
+        let batch = [message("BROKER_A"), message("BROKER_A")];
+        assert_eq!(batch_session(&batch), Ok("ALICE->BROKER_A".to_string()));
+        let mixed = [message("BROKER_A"), message("BROKER_B")];
+        assert_eq!(batch_session(&mixed), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(batch_session(&[]), Err(StatusCode::BAD_REQUEST));
+    }
+}
@@ -0,0 +1,68 @@
+//! The codec's per-message paths, for the Criterion benchmarks in `benches/`.
+//!
+//! Benchmarks link against the library and see only its public items, so
+//! each path is wrapped here behind types of its own or of `std`.
+
+use bytes::BytesMut;
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    config::TimestampPrecision,
+    dictionary::{Dictionaries, Dictionary},
+    encode, message, parse_raw, validation, wire_fields,
+};
+
+/// Parses a message as `POST /api/v1/fix/parse` does; the number of fields.
+pub fn parse(raw: &[u8]) -> usize {
+    parse_raw(raw).field_count
+}
+
+/// Encodes a NewOrderSingle from `ALICE` to `BROKER_A` into `out`, as
+/// `/send` does.
+pub fn build(
+    out: &mut BytesMut,
+    seq_num: u64,
+    fields: &HashMap<String, String>,
+    group_fields: &[(u32, String)],
+) {
+    let header = encode::Header {
+        version: "FIX.4.4",
+        msg_type: "D",
+        sender: "ALICE",
+        target: "BROKER_A",
+        seq_num,
+        sending_time: encode::SendingTime::now(TimestampPrecision::Millis),
+    };
+    let fields = encode::body_fields(None, fields).expect("fields are keyed by tag");
+    encode::write_message(out, &header, &fields, group_fields);
+}
+
+/// The CheckSum(10) of SOH-delimited bytes.
+pub fn checksum(bytes: &[u8]) -> u8 {
+    message::checksum(bytes, message::SOH)
+}
+
+/// A message's fields in wire order, parsed ahead of validating them.
+pub struct Fields(Vec<(u32, String)>);
+
+impl Fields {
+    pub fn from_wire(raw: &[u8]) -> Self {
+        Self(wire_fields(raw).0)
+    }
+}
+
+/// Validates messages against the FIX 4.4 dictionary, as
+/// `POST /api/v1/fix/validate` does before rule packs.
+pub struct Validator(Arc<Dictionary>);
+
+impl Validator {
+    pub fn fix44() -> Self {
+        let dict = Dictionaries::from_env().get("FIX.4.4");
+        Self(dict.expect("FIX.4.4 has a dictionary"))
+    }
+
+    /// The number of errors found.
+    pub fn validate(&self, fields: &Fields) -> usize {
+        validation::validate(&self.0, &fields.0, true).len()
+    }
+}
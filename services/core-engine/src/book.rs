@@ -0,0 +1,718 @@
+//! Order books built from market data.
+//!
+//! A MarketDataSnapshotFullRefresh (35=W) received on `/inbound` replaces the
+//! book for its symbol and MDReqID (262). Its NoMDEntries bids (MDEntryType 0)
+//! and offers (1) are aggregated by price into levels, best first, with their
+//! sizes and NumberOfOrders (346) added up. Other entry types (trades,
+//! statistics, ...) are not part of the book and are only counted.
+//!
+//! A MarketDataIncrementalRefresh (35=X) then updates the book entry by
+//! entry, by MDUpdateAction (279): new, change or delete. Entries carrying an
+//! MDEntryID (278) are tracked individually (order-by-order books). Entries
+//! without one set or remove the whole level at their price (price-level
+//! books). Each entry's RptSeq (83) must follow the book's last one. On a gap
+//! the book is marked stale and stops taking updates until the next snapshot,
+//! which the caller re-requests.
+//!
+//! Every book a snapshot or refresh changes is also broadcast to the
+//! WebSocket clients of `/api/v1/marketdata/stream`, conflated if they ask
+//! for it (see [`crate::conflation`]), and the current books can be read with
+//! `GET /api/v1/marketdata/book/{symbol}`.
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::StatusCode,
+    response::{Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    clock,
+    conflation::{self, Conflator},
+    AppState,
+};
+
+/// Book updates buffered per stream client before it is lagged.
+const STREAM_CAPACITY: usize = 1024;
+/// Levels per side returned when no `depth` is given.
+const DEFAULT_DEPTH: usize = 10;
+
+/// One price level of a book side.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Level {
+    pub price: f64,
+    pub size: f64,
+    /// Orders at the level, if the venue reports NumberOfOrders.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orders: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Bid,
+    Ask,
+}
+
+impl Side {
+    fn from_entry_type(entry_type: &str) -> Option<Self> {
+        match entry_type {
+            "0" => Some(Self::Bid),
+            "1" => Some(Self::Ask),
+            _ => None,
+        }
+    }
+}
+
+/// An entry known by its MDEntryID, and what it adds to its level.
+#[derive(Debug, Clone)]
+struct KnownEntry {
+    side: Side,
+    price: f64,
+    size: f64,
+    orders: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct OrderBook {
+    pub symbol: String,
+    /// The subscription the book belongs to; `None` for unsolicited data.
+    pub md_req_id: Option<String>,
+    /// Highest price first.
+    pub bids: Vec<Level>,
+    /// Lowest price first.
+    pub asks: Vec<Level>,
+    /// Entries of the last snapshot that are not bids or offers.
+    pub other_entries: usize,
+    /// Snapshots applied to the book.
+    pub snapshots: u64,
+    /// Incremental entries applied to the book.
+    pub updates: u64,
+    /// RptSeq of the last snapshot or entry applied.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpt_seq: Option<u64>,
+    /// RptSeq gaps seen.
+    pub gaps: u64,
+    /// A gap was seen and no snapshot has arrived since; the book is not
+    /// being updated.
+    pub stale: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stale_since_ms: Option<u64>,
+    pub updated_at_ms: u64,
+    #[serde(skip)]
+    entries: HashMap<String, KnownEntry>,
+}
+
+impl OrderBook {
+    pub fn empty(symbol: &str, md_req_id: Option<&str>, at_ms: u64) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            md_req_id: md_req_id.map(str::to_string),
+            bids: Vec::new(),
+            asks: Vec::new(),
+            other_entries: 0,
+            snapshots: 0,
+            updates: 0,
+            rpt_seq: None,
+            gaps: 0,
+            stale: false,
+            stale_since_ms: None,
+            updated_at_ms: at_ms,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Builds a book from a snapshot's fields, in wire order. Returns `None`
+    /// if the snapshot names no Symbol.
+    pub fn from_snapshot(fields: &[(u32, &str)], at_ms: u64) -> Option<Self> {
+        let (header, entries) = split_entries(fields);
+        let value = |tag: u32| header.iter().find(|(t, _)| *t == tag).map(|(_, v)| *v);
+        let mut book = Self::empty(value(55)?, value(262), at_ms);
+        book.snapshots = 1;
+        book.rpt_seq = value(83).and_then(|v| v.trim().parse().ok());
+        for entry in entries {
+            let Some(side) = Side::from_entry_type(entry.entry_type.unwrap_or_default()) else {
+                book.other_entries += 1;
+                continue;
+            };
+            if let Some(price) = entry.price {
+                let (size, orders) = (entry.size.unwrap_or(0.0), entry.orders);
+                book.add(side, price, size, orders);
+                if let Some(id) = entry.entry_id {
+                    let known = KnownEntry {
+                        side,
+                        price,
+                        size,
+                        orders,
+                    };
+                    book.entries.insert(id.to_string(), known);
+                }
+            }
+        }
+        Some(book)
+    }
+
+    /// Applies one incremental entry. Returns `false`, leaving the book
+    /// stale, if its RptSeq skips ahead of the book's.
+    fn apply(&mut self, entry: &Entry<'_>, at_ms: u64) -> bool {
+        if let Some(rpt_seq) = entry.rpt_seq {
+            match self.rpt_seq {
+                // Already applied, e.g. replayed after a resend.
+                Some(last) if rpt_seq <= last => return true,
+                Some(last) if rpt_seq > last + 1 => {
+                    warn!(
+                        symbol = %self.symbol,
+                        md_req_id = self.md_req_id.as_deref().unwrap_or_default(),
+                        expected = last + 1,
+                        received = rpt_seq,
+                        "Market data gap, book is stale until the next snapshot"
+                    );
+                    self.gaps += 1;
+                    self.stale = true;
+                    self.stale_since_ms = Some(at_ms);
+                    return false;
+                }
+                _ => self.rpt_seq = Some(rpt_seq),
+            }
+        }
+        self.updated_at_ms = at_ms;
+        let Some(side) = Side::from_entry_type(entry.entry_type.unwrap_or_default()) else {
+            return true;
+        };
+        self.updates += 1;
+        let (size, orders) = (entry.size.unwrap_or(0.0), entry.orders);
+        match (entry.action, entry.entry_id) {
+            (Some("0" | "1"), Some(id)) => {
+                let previous = self.entries.remove(id);
+                if let Some(old) = &previous {
+                    self.remove(old.side, old.price, old.size, old.orders);
+                }
+                if let Some(price) = entry.price.or(previous.map(|p| p.price)) {
+                    self.add(side, price, size, orders);
+                    let known = KnownEntry {
+                        side,
+                        price,
+                        size,
+                        orders,
+                    };
+                    self.entries.insert(id.to_string(), known);
+                }
+            }
+            (Some("2"), Some(id)) => {
+                if let Some(old) = self.entries.remove(id) {
+                    self.remove(old.side, old.price, old.size, old.orders);
+                }
+            }
+            (Some("0" | "1"), None) => {
+                if let Some(price) = entry.price {
+                    self.set(side, price, size, orders);
+                }
+            }
+            (Some("2"), None) => {
+                if let Some(price) = entry.price {
+                    self.levels(side).retain(|l| l.price != price);
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn levels(&mut self, side: Side) -> &mut Vec<Level> {
+        match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        }
+    }
+
+    /// Index of the level at `price`, or where it would go.
+    fn position(&mut self, side: Side, price: f64) -> usize {
+        self.levels(side).partition_point(|l| match side {
+            Side::Bid => l.price > price,
+            Side::Ask => l.price < price,
+        })
+    }
+
+    /// Replaces the level at a price.
+    fn set(&mut self, side: Side, price: f64, size: f64, orders: Option<u32>) {
+        let at = self.position(side, price);
+        let levels = self.levels(side);
+        let level = Level {
+            price,
+            size,
+            orders,
+        };
+        match levels.get_mut(at).filter(|l| l.price == price) {
+            Some(existing) => *existing = level,
+            None => levels.insert(at, level),
+        }
+    }
+
+    /// Takes an entry's size out of its level, dropping the level once empty.
+    fn remove(&mut self, side: Side, price: f64, size: f64, orders: Option<u32>) {
+        let at = self.position(side, price);
+        let levels = self.levels(side);
+        if let Some(level) = levels.get_mut(at).filter(|l| l.price == price) {
+            level.size -= size;
+            level.orders = level.orders.map(|n| n.saturating_sub(orders.unwrap_or(0)));
+            if level.size <= 0.0 {
+                levels.remove(at);
+            }
+        }
+    }
+
+    /// Adds size at a price, merging it into an existing level.
+    fn add(&mut self, side: Side, price: f64, size: f64, orders: Option<u32>) {
+        let at = self.position(side, price);
+        let levels = self.levels(side);
+        match levels.get_mut(at).filter(|l| l.price == price) {
+            Some(level) => {
+                level.size += size;
+                level.orders = match (level.orders, orders) {
+                    (None, None) => None,
+                    (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+                };
+            }
+            None => levels.insert(
+                at,
+                Level {
+                    price,
+                    size,
+                    orders,
+                },
+            ),
+        }
+    }
+}
+
+/// One NoMDEntries instance.
+#[derive(Debug, Default)]
+struct Entry<'a> {
+    action: Option<&'a str>,
+    entry_type: Option<&'a str>,
+    entry_id: Option<&'a str>,
+    symbol: Option<&'a str>,
+    price: Option<f64>,
+    size: Option<f64>,
+    orders: Option<u32>,
+    rpt_seq: Option<u64>,
+}
+
+/// Splits a market data message into the fields before NoMDEntries (268)
+/// and its entries. Each entry starts with the group's first field, the
+/// delimiter.
+fn split_entries<'a>(fields: &'a [(u32, &'a str)]) -> (&'a [(u32, &'a str)], Vec<Entry<'a>>) {
+    let Some(at) = fields.iter().position(|(tag, _)| *tag == 268) else {
+        return (fields, Vec::new());
+    };
+    let group = &fields[at + 1..];
+    let delimiter = group.first().map(|(tag, _)| *tag);
+    let mut entries: Vec<Entry> = Vec::new();
+    for &(tag, value) in group {
+        if Some(tag) == delimiter {
+            entries.push(Entry::default());
+        }
+        let Some(entry) = entries.last_mut() else {
+            continue;
+        };
+        let number = || value.trim().parse::<f64>().ok();
+        match tag {
+            279 => entry.action = Some(value),
+            269 => entry.entry_type = Some(value),
+            278 => entry.entry_id = Some(value),
+            55 => entry.symbol = Some(value),
+            270 => entry.price = number(),
+            271 => entry.size = number(),
+            346 => entry.orders = value.trim().parse().ok(),
+            83 => entry.rpt_seq = value.trim().parse().ok(),
+            _ => {}
+        }
+    }
+    (&fields[..at], entries)
+}
+
+/// Books an incremental refresh touched.
+#[derive(Debug, Default)]
+pub struct IncrementalOutcome {
+    /// Each touched book after the refresh, in the order first touched.
+    pub books: Vec<OrderBook>,
+    /// Symbols whose books went stale on this refresh.
+    pub gaps: Vec<String>,
+}
+
+/// Books by MDReqID and symbol.
+pub struct Books {
+    books: HashMap<(Option<String>, String), OrderBook>,
+    updates: broadcast::Sender<OrderBook>,
+}
+
+impl Default for Books {
+    fn default() -> Self {
+        Self {
+            books: HashMap::new(),
+            updates: broadcast::channel(STREAM_CAPACITY).0,
+        }
+    }
+}
+
+impl Books {
+    /// The most recently updated book for `symbol`, or the one for
+    /// `md_req_id` if given.
+    pub fn get(&self, symbol: &str, md_req_id: Option<&str>) -> Option<&OrderBook> {
+        self.books
+            .values()
+            .filter(|b| b.symbol == symbol)
+            .filter(|b| md_req_id.is_none_or(|id| b.md_req_id.as_deref() == Some(id)))
+            .max_by_key(|b| b.updated_at_ms)
+    }
+
+    /// Changed books, from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<OrderBook> {
+        self.updates.subscribe()
+    }
+
+    /// Current books, oldest update first.
+    fn all(&self) -> Vec<OrderBook> {
+        let mut books: Vec<OrderBook> = self.books.values().cloned().collect();
+        books.sort_by_key(|b| b.updated_at_ms);
+        books
+    }
+
+    fn publish(&self, book: &OrderBook) {
+        // No stream clients is not an error.
+        let _ = self.updates.send(book.clone());
+    }
+
+    /// Replaces the book a snapshot is for, returning it. The book's
+    /// counters carry over and it is no longer stale.
+    pub fn apply_snapshot(&mut self, mut book: OrderBook) -> OrderBook {
+        let key = (book.md_req_id.clone(), book.symbol.clone());
+        if let Some(previous) = self.books.get(&key) {
+            book.snapshots += previous.snapshots;
+            book.updates = previous.updates;
+            book.gaps = previous.gaps;
+        }
+        self.books.insert(key, book.clone());
+        self.publish(&book);
+        book
+    }
+
+    /// Applies an incremental refresh's fields, in wire order, to the books
+    /// of the symbols it names. An entry without a Symbol is for the one
+    /// before it. Entries for a stale book are dropped.
+    pub fn apply_incremental(&mut self, fields: &[(u32, &str)], at_ms: u64) -> IncrementalOutcome {
+        let (header, entries) = split_entries(fields);
+        let md_req_id = header.iter().find(|(t, _)| *t == 262).map(|(_, v)| *v);
+        let mut outcome = IncrementalOutcome::default();
+        let mut touched: Vec<String> = Vec::new();
+        let mut symbol = header.iter().find(|(t, _)| *t == 55).map(|(_, v)| *v);
+        for entry in &entries {
+            symbol = entry.symbol.or(symbol);
+            let Some(symbol) = symbol else {
+                continue;
+            };
+            let key = (md_req_id.map(str::to_string), symbol.to_string());
+            let book = self
+                .books
+                .entry(key)
+                .or_insert_with(|| OrderBook::empty(symbol, md_req_id, at_ms));
+            if book.stale {
+                continue;
+            }
+            if !book.apply(entry, at_ms) {
+                outcome.gaps.push(symbol.to_string());
+            }
+            if !touched.iter().any(|s| s == symbol) {
+                touched.push(symbol.to_string());
+            }
+        }
+        outcome.books = touched
+            .iter()
+            .filter_map(|s| self.books.get(&(md_req_id.map(str::to_string), s.clone())))
+            .cloned()
+            .collect();
+        for book in &outcome.books {
+            self.publish(book);
+        }
+        outcome
+    }
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BookQuery {
+    /// Levels per side; 10 if omitted.
+    pub depth: Option<usize>,
+    pub md_req_id: Option<String>,
+}
+
+/// A book cut to the levels asked for.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BookView {
+    #[serde(flatten)]
+    book: OrderBook,
+    /// Time since the book last changed.
+    age_ms: u64,
+}
+
+impl BookView {
+    pub fn new(mut book: OrderBook, depth: usize, now_ms: u64) -> Self {
+        book.bids.truncate(depth);
+        book.asks.truncate(depth);
+        Self {
+            age_ms: now_ms.saturating_sub(book.updated_at_ms),
+            book,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/marketdata/book/{symbol}",
+    tag = "marketdata",
+    params(("symbol" = String, Path, description = "The instrument's symbol."), BookQuery),
+    responses(
+        (status = 200, description = "OK", body = BookView),
+        (status = 404, description = "No book for the symbol"),
+    )
+)]
+pub async fn get(
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+    Query(query): Query<BookQuery>,
+) -> Result<Json<BookView>, StatusCode> {
+    let book = state
+        .books
+        .lock()
+        .await
+        .get(&symbol, query.md_req_id.as_deref())
+        .cloned()
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let depth = query.depth.unwrap_or(DEFAULT_DEPTH);
+    Ok(Json(BookView::new(book, depth, clock::epoch_ms())))
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StreamQuery {
+    /// Only books for this symbol; all if omitted.
+    pub symbol: Option<String>,
+    pub depth: Option<usize>,
+    /// Updates per second per book; `FIX_MD_CONFLATION_RATE` if omitted, and
+    /// 0 for every update.
+    pub max_rate: Option<u32>,
+}
+
+/// Upgrades to a WebSocket that sends the current books, then every change.
+#[utoipa::path(
+    get,
+    path = "/api/v1/marketdata/stream",
+    tag = "marketdata",
+    params(StreamQuery),
+    responses(
+        (status = 101, description = "Switched to a WebSocket of order book updates"),
+    )
+)]
+pub async fn stream(
+    State(state): State<AppState>,
+    Query(query): Query<StreamQuery>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    ws.on_upgrade(move |socket| stream_books(state, query, socket))
+}
+
+async fn stream_books(state: AppState, query: StreamQuery, mut socket: WebSocket) {
+    let depth = query.depth.unwrap_or(DEFAULT_DEPTH);
+    let wanted = |book: &OrderBook| query.symbol.as_ref().is_none_or(|s| *s == book.symbol);
+    // Subscribed before the current books are read, so no change is missed.
+    let (mut updates, current) = {
+        let books = state.books.lock().await;
+        (books.subscribe(), books.all())
+    };
+    let max_rate = query.max_rate.unwrap_or(state.conflation.max_rate);
+    let mut conflator = Conflator::new(max_rate);
+    let mut held = Vec::new();
+    info!(symbol = ?query.symbol, depth, max_rate, "Book stream client connected");
+    for book in current.into_iter().filter(|b| wanted(b)) {
+        if send_book(&mut socket, book, depth).await.is_err() {
+            return;
+        }
+    }
+    loop {
+        tokio::select! {
+            update = conflation::next_book(&mut updates, &mut conflator, &mut held) => {
+                match update {
+                    Some(book) if wanted(&book) => {
+                        if send_book(&mut socket, book, depth).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(_) => {}
+                    None => break,
+                }
+            }
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    info!(
+        symbol = ?query.symbol,
+        coalesced = conflator.map_or(0, |c| c.coalesced),
+        "Book stream client disconnected"
+    );
+}
+
+async fn send_book(
+    socket: &mut WebSocket,
+    book: OrderBook,
+    depth: usize,
+) -> Result<(), axum::Error> {
+    let view = BookView::new(book, depth, clock::epoch_ms());
+    let text = serde_json::to_string(&view).unwrap_or_default();
+    socket.send(Message::Text(text)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots_aggregate_entries_into_sorted_levels() {
+        let fields = [
+            (35, "W"),
+            (262, "MD1"),
+            (55, "AAPL"),
+            (268, "6"),
+            (269, "0"),
+            (270, "150.00"),
+            (271, "100"),
+            (346, "2"),
+            (269, "1"),
+            (270, "150.10"),
+            (271, "50"),
+            (269, "0"),
+            (270, "150.05"),
+            (271, "30"),
+            (269, "0"),
+            (270, "150"),
+            (271, "20"),
+            (346, "1"),
+            (269, "2"),
+            (270, "150.02"),
+            (271, "5"),
+            (269, "1"),
+            (270, "150.20"),
+            (271, "70"),
+        ];
+        let book = OrderBook::from_snapshot(&fields, 7).unwrap();
+        assert_eq!(book.md_req_id.as_deref(), Some("MD1"));
+        let bids: Vec<(f64, f64)> = book.bids.iter().map(|l| (l.price, l.size)).collect();
+        assert_eq!(bids, [(150.05, 30.0), (150.0, 120.0)]);
+        assert_eq!(book.bids[1].orders, Some(3));
+        let asks: Vec<f64> = book.asks.iter().map(|l| l.price).collect();
+        assert_eq!(asks, [150.10, 150.20]);
+        assert_eq!(book.other_entries, 1);
+
+        let mut books = Books::default();
+        books.apply_snapshot(book.clone());
+        assert_eq!(books.apply_snapshot(book).snapshots, 2);
+        assert_eq!(books.books.len(), 1);
+        assert!(books.get("AAPL", Some("MD1")).is_some());
+        assert!(books.get("AAPL", Some("MD2")).is_none());
+        assert!(OrderBook::from_snapshot(&[(35, "W"), (268, "0")], 7).is_none());
+    }
+
+    #[test]
+    fn incremental_refreshes_update_levels_and_stop_at_gaps() {
+        let mut books = Books::default();
+        let snapshot = [
+            (262, "MD1"),
+            (55, "AAPL"),
+            (83, "10"),
+            (268, "2"),
+            (269, "0"),
+            (270, "100"),
+            (271, "5"),
+            (278, "B1"),
+            (269, "1"),
+            (270, "101"),
+            (271, "7"),
+        ];
+        books.apply_snapshot(OrderBook::from_snapshot(&snapshot, 1).unwrap());
+
+        // A new bid order joins 100, B1 moves to 99 and the 101 level goes.
+        let refresh = [
+            (262, "MD1"),
+            (268, "4"),
+            (279, "0"),
+            (269, "0"),
+            (278, "B2"),
+            (55, "AAPL"),
+            (83, "11"),
+            (270, "100"),
+            (271, "3"),
+            (279, "1"),
+            (269, "0"),
+            (278, "B1"),
+            (83, "12"),
+            (270, "99"),
+            (271, "5"),
+            (279, "2"),
+            (269, "1"),
+            (83, "13"),
+            (270, "101"),
+            // Already applied.
+            (279, "2"),
+            (269, "0"),
+            (278, "B2"),
+            (83, "11"),
+        ];
+        let outcome = books.apply_incremental(&refresh, 2);
+        assert!(outcome.gaps.is_empty());
+        let book = &outcome.books[0];
+        let bids: Vec<(f64, f64)> = book.bids.iter().map(|l| (l.price, l.size)).collect();
+        assert_eq!(bids, [(100.0, 3.0), (99.0, 5.0)]);
+        assert!(book.asks.is_empty());
+        assert_eq!((book.rpt_seq, book.updates), (Some(13), 3));
+
+        // RptSeq 15 skips 14: the book goes stale and ignores what follows.
+        let gap = [
+            (262, "MD1"),
+            (268, "2"),
+            (279, "0"),
+            (269, "1"),
+            (55, "AAPL"),
+            (83, "15"),
+            (270, "102"),
+            (271, "1"),
+            (279, "0"),
+            (269, "1"),
+            (83, "16"),
+            (270, "103"),
+            (271, "1"),
+        ];
+        let outcome = books.apply_incremental(&gap, 3);
+        assert_eq!(outcome.gaps, ["AAPL"]);
+        let book = &outcome.books[0];
+        assert!(book.stale && book.asks.is_empty());
+        assert_eq!((book.gaps, book.stale_since_ms), (1, Some(3)));
+
+        // The next snapshot restores it.
+        let restored = books.apply_snapshot(OrderBook::from_snapshot(&snapshot, 4).unwrap());
+        assert!(!restored.stale);
+        assert_eq!(
+            (restored.snapshots, restored.updates, restored.gaps),
+            (2, 3, 1)
+        );
+    }
+}
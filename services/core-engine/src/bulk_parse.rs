@@ -0,0 +1,231 @@
+//! Bulk parsing of FIX logs.
+//!
+//! `POST /api/v1/fix/parse/bulk` takes a log as the request body, e.g.
+//! `curl --data-binary @FIX.4.4-ALICE-BROKER_A.messages.log`, and parses
+//! every message in it as `/parse` would. A message starts at `8=FIX` and
+//! runs to the next one or the end of its line, so prefixes written by the
+//! logger, such as timestamps, are skipped, and a line may hold several
+//! messages. BodyLength and CheckSum are checked too. The answer has the
+//! result for each message and statistics over the whole log, for
+//! forensics.
+
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    message::SOH, parse_flagged, problem::ApiError, structure, AppState, ParseResponse,
+};
+
+/// Largest log accepted unless `FIX_MAX_BULK_BODY_BYTES` says otherwise.
+pub const MAX_LOG_BYTES: usize = 64 * 1024 * 1024;
+/// Most messages parsed from one log.
+const MAX_MESSAGES: usize = 200_000;
+
+const BEGIN_STRING: &[u8] = b"8=FIX";
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BulkParseOptions {
+    /// Flag tags repeated outside repeating groups; overrides `FIX_REJECT_DUPLICATE_TAGS`.
+    reject_duplicates: Option<bool>,
+    /// Flag tags missing from each message's data dictionary; overrides
+    /// `FIX_REJECT_UNKNOWN_TAGS`.
+    reject_unknown_tags: Option<bool>,
+    /// Return only the statistics, without the per-message results.
+    #[serde(default)]
+    summary: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LogMessage {
+    /// 1-based line of the log the message is on.
+    line: usize,
+    #[serde(flatten)]
+    message: ParseResponse,
+}
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct LogStats {
+    messages: usize,
+    /// Non-blank lines with no FIX message on them.
+    skipped_lines: usize,
+    /// Messages with at least one error.
+    invalid_messages: usize,
+    errors: usize,
+    /// Message counts by MsgType name, e.g. `NewOrderSingle`.
+    by_msg_type: BTreeMap<String, usize>,
+    /// Message counts by `SenderCompID->TargetCompID`.
+    by_session: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BulkParseResponse {
+    stats: LogStats,
+    /// In log order; omitted with `?summary=true`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    messages: Vec<LogMessage>,
+}
+
+/// The messages in a log, with the line each is on, and the number of
+/// non-blank lines holding none.
+pub fn split(log: &[u8]) -> (Vec<(usize, &[u8])>, usize) {
+    let mut messages = Vec::new();
+    let mut skipped = 0;
+    for (i, line) in log.split(|&b| b == b'\n').enumerate() {
+        let line = line.trim_ascii_end();
+        // The first message may follow any prefix that does not end in a
+        // digit (which would make it a tag such as 58=FIX...); later ones
+        // follow a field delimiter.
+        let first = find(line, 0, |prev| prev.is_none_or(|b| !b.is_ascii_digit()));
+        let Some(mut start) = first else {
+            if !line.trim_ascii().is_empty() {
+                skipped += 1;
+            }
+            continue;
+        };
+        while let Some(next) = find(line, start + 1, |prev| matches!(prev, Some(SOH | b'|'))) {
+            messages.push((i + 1, line[start..next].trim_ascii_end()));
+            start = next;
+        }
+        messages.push((i + 1, &line[start..]));
+    }
+    (messages, skipped)
+}
+
+/// The first BeginString at or after `from` whose preceding byte passes.
+fn find(line: &[u8], from: usize, preceded: impl Fn(Option<u8>) -> bool) -> Option<usize> {
+    (from..line.len()).find(|&i| {
+        line[i..].starts_with(BEGIN_STRING) && preceded(i.checked_sub(1).map(|p| line[p]))
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/fix/parse/bulk",
+    tag = "fix",
+    params(BulkParseOptions),
+    request_body(content = String, content_type = "text/plain", description = "A FIX log"),
+    responses(
+        (
+            status = 200,
+            description = "Each message and the log's statistics",
+            body = BulkParseResponse
+        ),
+        (
+            status = 400,
+            description = "No FIX messages, or no data dictionary to check tags against"
+        ),
+        (status = 413, description = "Log too large"),
+    )
+)]
+pub async fn parse(
+    State(state): State<AppState>,
+    Query(opts): Query<BulkParseOptions>,
+    body: Bytes,
+) -> Result<Json<BulkParseResponse>, ApiError> {
+    let (raw_messages, skipped_lines) = split(&body);
+    if raw_messages.is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "no_messages")
+            .detail("the log holds no FIX messages"));
+    }
+    if raw_messages.len() > MAX_MESSAGES {
+        return Err(ApiError::new(StatusCode::PAYLOAD_TOO_LARGE, "too_many_messages")
+            .detail(format!("at most {MAX_MESSAGES} messages are parsed from one log")));
+    }
+
+    let parsed = parse_messages(raw_messages, skipped_lines, opts.summary, |raw| {
+        parse_flagged(&state, raw, opts.reject_duplicates, opts.reject_unknown_tags)
+    })?;
+    let stats = &parsed.stats;
+    info!(
+        bytes = body.len(),
+        messages = stats.messages,
+        skipped_lines = stats.skipped_lines,
+        invalid_messages = stats.invalid_messages,
+        "FIX log parsed"
+    );
+    Ok(Json(parsed))
+}
+
+/// Parses each message [`split`] found with `parse`, checking its framing,
+/// and counts them; with `summary`, only the statistics are kept.
+pub fn parse_messages<E>(
+    raw_messages: Vec<(usize, &[u8])>,
+    skipped_lines: usize,
+    summary: bool,
+    mut parse: impl FnMut(&[u8]) -> Result<ParseResponse, E>,
+) -> Result<BulkParseResponse, E> {
+    let mut stats = LogStats {
+        skipped_lines,
+        ..LogStats::default()
+    };
+    let mut messages = Vec::with_capacity(if summary { 0 } else { raw_messages.len() });
+    for (line, raw) in raw_messages {
+        let mut message = parse(raw)?;
+        message.errors.extend(structure::check_framing(raw));
+        stats.count(&message);
+        if !summary {
+            messages.push(LogMessage { line, message });
+        }
+    }
+    Ok(BulkParseResponse { stats, messages })
+}
+
+impl LogStats {
+    fn count(&mut self, message: &ParseResponse) {
+        self.messages += 1;
+        if !message.errors.is_empty() {
+            self.invalid_messages += 1;
+            self.errors += message.errors.len();
+        }
+        *self.by_msg_type.entry(message.msg_type.clone()).or_default() += 1;
+        let session = format!(
+            "{}->{}",
+            message.value(49).unwrap_or_default(),
+            message.value(56).unwrap_or_default()
+        );
+        *self.by_session.entry(session).or_default() += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_raw;
+
+    const LOG: &[u8] = b"20260223-09:30:00.001 : 8=FIX.4.4|9=5|35=0|49=ALICE|56=BROKER_A|10=000|\r\n\
+        \n\
+        session ALICE->BROKER_A logged on\n\
+        8=FIX.4.4|35=D|49=ALICE|56=BROKER_A|58=8=FIX is text|10=000|8=FIX.4.4|35=F|49=ALICE|56=BROKER_A|10=000|\n";
+
+    #[test]
+    fn logs_are_split_into_messages() {
+        let (messages, skipped) = split(LOG);
+        assert_eq!(skipped, 1);
+        let lines: Vec<usize> = messages.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, [1, 4, 4]);
+        assert_eq!(messages[0].1, b"8=FIX.4.4|9=5|35=0|49=ALICE|56=BROKER_A|10=000|");
+        assert!(messages[1].1.ends_with(b"58=8=FIX is text|10=000|"));
+        assert!(messages[2].1.starts_with(b"8=FIX.4.4|35=F"));
+    }
+
+    #[test]
+    fn stats_count_by_msg_type_and_session() {
+        let (messages, _) = split(LOG);
+        let mut stats = LogStats::default();
+        for (_, raw) in &messages {
+            stats.count(&parse_raw(raw));
+        }
+        assert_eq!(stats.messages, 3);
+        assert_eq!(stats.by_session["ALICE->BROKER_A"], 3);
+        assert_eq!(stats.by_msg_type.values().sum::<usize>(), 3);
+    }
+}
@@ -0,0 +1,335 @@
+//! The command line.
+//!
+//! Without a subcommand, or with `serve`, the binary runs the engine as
+//! before. The other subcommands use the engine's codec from the shell, so an
+//! operator can look into a message or a log without running the engine or
+//! reaching for curl:
+//!
+//! - `parse <file>` parses every message in a file or log, as
+//!   `POST /api/v1/fix/parse/bulk` does, and prints the result as JSON.
+//! - `validate <file>` validates every message in a file against its data
+//!   dictionary, as `POST /api/v1/fix/validate` does with `raw_message`.
+//! - `checksum <msg>` prints a message's CheckSum(10).
+//! - `config check` checks the configuration (see [`crate::preflight`]);
+//!   `--check-config` is the same.
+//!
+//! A file may be `-` for standard input. Each exits `0` on success, `1` if
+//! what it checked failed and `2` if it could not run.
+
+use clap::{Args, Parser, Subcommand};
+use serde::Serialize;
+use std::{
+    convert::Infallible,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    bulk_parse,
+    dictionary::{self, Dictionaries},
+    drop_superseded_ordering, message, msg_type_code, parse_raw,
+    rules::{Advisories, ConditionalRules, RulePacks},
+    validation::CustomTagRanges,
+    wire_fields, ValidateResponse, ValidationProfile,
+};
+
+/// Exit status when what was checked failed.
+const FAILED: i32 = 1;
+/// Exit status when the command could not run, as for a usage error.
+const UNUSABLE: i32 = 2;
+
+#[derive(Debug, Parser)]
+#[command(name = "fix-engine", version, about = "ALICE FIX engine")]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// The same as `config check`.
+    #[arg(long, hide = true)]
+    check_config: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Subcommand)]
+pub enum Command {
+    /// Run the engine (the default).
+    Serve,
+    /// Parse every FIX message in a file or log and print them as JSON.
+    Parse {
+        /// A file of FIX messages, such as a session log, or `-` for stdin.
+        file: PathBuf,
+        /// Print only the statistics, without the messages.
+        #[arg(long)]
+        summary: bool,
+    },
+    /// Validate every FIX message in a file against its data dictionary.
+    Validate(ValidateArgs),
+    /// Print the CheckSum(10) of a message, `|` or SOH delimited.
+    Checksum {
+        /// The message, up to its CheckSum(10) field or whole.
+        msg: String,
+    },
+    /// Work with the configuration.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq, Subcommand)]
+pub enum ConfigCommand {
+    /// Check the configuration, dictionaries, secrets and certificates, and
+    /// exit.
+    Check,
+}
+
+#[derive(Debug, PartialEq, Eq, Args)]
+pub struct ValidateArgs {
+    /// A file of FIX messages, such as a session log, or `-` for stdin.
+    file: PathBuf,
+    /// The FIX version to validate against, instead of each message's
+    /// BeginString.
+    #[arg(long)]
+    version: Option<String>,
+    /// A data dictionary file to validate against, instead of the one for the
+    /// version.
+    #[arg(long)]
+    dictionary: Option<String>,
+    /// A rule pack from `FIX_RULE_PACKS` to apply too; may be repeated.
+    #[arg(long = "rule-pack")]
+    rule_packs: Vec<String>,
+    /// Flag tags the dictionary does not define.
+    #[arg(long)]
+    reject_unknown_tags: bool,
+    /// Fail on warnings as well as errors.
+    #[arg(long)]
+    strict: bool,
+}
+
+impl Cli {
+    /// What to do; `serve` unless told otherwise.
+    pub fn command(self) -> Command {
+        match self.command {
+            _ if self.check_config => Command::Config {
+                command: ConfigCommand::Check,
+            },
+            Some(command) => command,
+            None => Command::Serve,
+        }
+    }
+}
+
+/// `parse <file>`.
+pub fn parse(file: &Path, summary: bool) -> i32 {
+    let log = match read(file) {
+        Ok(log) => log,
+        Err(e) => return unusable(e),
+    };
+    let (raw_messages, skipped_lines) = bulk_parse::split(&log);
+    if raw_messages.is_empty() {
+        eprintln!("{}: no FIX messages", file.display());
+        return FAILED;
+    }
+    let parse = |raw: &[u8]| Ok::<_, Infallible>(parse_raw(raw));
+    let parsed = bulk_parse::parse_messages(raw_messages, skipped_lines, summary, parse)
+        .unwrap_or_else(|never| match never {});
+    print(&parsed);
+    0
+}
+
+/// One message's validation, with where it is in the file.
+#[derive(Debug, Serialize)]
+struct Validated {
+    line: usize,
+    #[serde(flatten)]
+    result: ValidateResponse,
+}
+
+/// `validate <file>`.
+pub fn validate(args: &ValidateArgs) -> i32 {
+    let log = match read(&args.file) {
+        Ok(log) => log,
+        Err(e) => return unusable(e),
+    };
+    let (raw_messages, _) = bulk_parse::split(&log);
+    if raw_messages.is_empty() {
+        eprintln!("{}: no FIX messages", args.file.display());
+        return FAILED;
+    }
+    let dictionary = match &args.dictionary {
+        Some(path) => match dictionary::load(path) {
+            Ok(dict) => Some(Arc::new(dict)),
+            Err(e) => return unusable(format!("{path}: {e}")),
+        },
+        None => None,
+    };
+    let dictionaries = Dictionaries::from_env();
+    let packs = RulePacks::from_env();
+    let mut rule_packs = Vec::new();
+    for name in &args.rule_packs {
+        match packs.get(name) {
+            Some(pack) => rule_packs.push(pack.clone()),
+            None => return unusable(format!("rule pack {name} is not loaded")),
+        }
+    }
+    let conditional_rules = Arc::new(ConditionalRules::from_env());
+    let advisories = Arc::new(Advisories::from_env());
+    let custom_tags = Arc::new(CustomTagRanges::from_env());
+
+    let mut results = Vec::with_capacity(raw_messages.len());
+    let mut all_valid = true;
+    for (line, raw) in raw_messages {
+        let (fields, mut errors) = wire_fields(raw);
+        let begin_string = fields
+            .iter()
+            .find(|(tag, _)| *tag == 8)
+            .map(|(_, v)| v.clone());
+        let version = args.version.clone().or(begin_string).unwrap_or_default();
+        let Some(dict) = dictionary.clone().or_else(|| dictionaries.get(&version)) else {
+            eprintln!("line {line}: no data dictionary for `{version}`");
+            all_valid = false;
+            continue;
+        };
+        let profile = ValidationProfile {
+            dict,
+            rule_packs: rule_packs.clone(),
+            reject_unknown_tags: args.reject_unknown_tags,
+            conditional_rules: conditional_rules.clone(),
+            advisories: advisories.clone(),
+            custom_tags: custom_tags.clone(),
+        };
+        let findings = profile.validate(&fields, true);
+        errors.extend(findings.errors);
+        drop_superseded_ordering(&mut errors);
+        let warnings = findings.warnings;
+        let valid = errors.is_empty() && (!args.strict || warnings.is_empty());
+        all_valid &= valid;
+        let result = ValidateResponse {
+            valid,
+            version,
+            msg_type: msg_type_code(&fields),
+            errors,
+            warnings,
+        };
+        results.push(Validated { line, result });
+    }
+    print(&results);
+    if all_valid {
+        0
+    } else {
+        FAILED
+    }
+}
+
+/// `checksum <msg>`: prints the checksum, failing if the message carries a
+/// different one.
+pub fn checksum(msg: &str) -> i32 {
+    let (computed, declared) = checksum_of(msg.trim_end().as_bytes());
+    println!("{computed:03}");
+    match declared {
+        Some(declared) if declared != format!("{computed:03}") => {
+            eprintln!("CheckSum(10) is '{declared}' but the computed checksum is {computed:03}");
+            FAILED
+        }
+        _ => 0,
+    }
+}
+
+/// The checksum of the bytes before a message's CheckSum(10) field, or of
+/// all of it, and the CheckSum it carries.
+fn checksum_of(raw: &[u8]) -> (u8, Option<String>) {
+    let delimiter = message::detect_delimiter(raw);
+    let segments = message::scan(raw, delimiter);
+    let trailer = segments
+        .iter()
+        .rev()
+        .find(|s| message::lenient_tag(s.tag) == Some(10));
+    match trailer {
+        Some(trailer) => {
+            let declared = String::from_utf8_lossy(trailer.value.unwrap_or_default());
+            let computed = message::checksum(&raw[..trailer.offset], delimiter);
+            (computed, Some(declared.into_owned()))
+        }
+        None => (message::checksum(raw, delimiter), None),
+    }
+}
+
+/// The contents of `file`, or of stdin for `-`.
+fn read(file: &Path) -> Result<Vec<u8>, String> {
+    let contents = if file == Path::new("-") {
+        let mut contents = Vec::new();
+        std::io::stdin().read_to_end(&mut contents).map(|_| contents)
+    } else {
+        std::fs::read(file)
+    };
+    contents.map_err(|e| format!("{}: {e}", file.display()))
+}
+
+fn print(value: &impl Serialize) {
+    let json = serde_json::to_string_pretty(value).expect("results serialize to JSON");
+    println!("{json}");
+}
+
+fn unusable(error: String) -> i32 {
+    eprintln!("{error}");
+    UNUSABLE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::SOH;
+
+    fn command(args: &[&str]) -> Command {
+        Cli::try_parse_from(args).unwrap().command()
+    }
+
+    #[test]
+    fn subcommands_default_to_serving() {
+        assert_eq!(command(&["fix-engine"]), Command::Serve);
+        assert_eq!(command(&["fix-engine", "serve"]), Command::Serve);
+        let check = Command::Config {
+            command: ConfigCommand::Check,
+        };
+        assert_eq!(command(&["fix-engine", "config", "check"]), check);
+        assert_eq!(command(&["fix-engine", "--check-config"]), check);
+        assert_eq!(
+            command(&["fix-engine", "parse", "-", "--summary"]),
+            Command::Parse {
+                file: PathBuf::from("-"),
+                summary: true,
+            }
+        );
+        let Command::Validate(args) = command(&[
+            "fix-engine",
+            "validate",
+            "orders.log",
+            "--rule-pack",
+            "BROKER_A",
+            "--rule-pack",
+            "HOUSE",
+            "--strict",
+        ]) else {
+            panic!("not validate");
+        };
+        assert_eq!(args.rule_packs, ["BROKER_A", "HOUSE"]);
+        assert!(args.strict && !args.reject_unknown_tags);
+        assert!(Cli::try_parse_from(["fix-engine", "checksum"]).is_err());
+    }
+
+    #[test]
+    fn checksums_cover_the_bytes_before_the_trailer() {
+        let body = b"8=FIX.4.4|9=5|35=0|";
+        let sum = body
+            .iter()
+            .map(|&b| if b == b'|' { SOH } else { b })
+            .fold(0u8, |sum, b| sum.wrapping_add(b));
+        assert_eq!(checksum_of(body), (sum, None));
+
+        let framed = format!("8=FIX.4.4|9=5|35=0|10={sum:03}|");
+        assert_eq!(checksum_of(framed.as_bytes()), (sum, Some(format!("{sum:03}"))));
+        let soh = framed.replace('|', "\x01");
+        assert_eq!(checksum_of(soh.as_bytes()).0, sum);
+        let wrong = checksum_of(b"8=FIX.4.4|9=5|35=0|10=000|");
+        assert_eq!(wrong, (sum, Some("000".to_string())));
+    }
+}
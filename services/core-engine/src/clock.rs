@@ -0,0 +1,151 @@
+//! Wall-clock helpers and FIX date/time field conversions.
+
+pub fn epoch_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Formats epoch milliseconds as a FIX UTCTimestamp (`YYYYMMDD-HH:MM:SS.sss`).
+pub fn format_utc_timestamp(ms: u64) -> String {
+    let (days, day_ms) = (ms / 86_400_000, ms % 86_400_000);
+    let (y, m, d) = civil_from_days(days as i64);
+    let secs = day_ms / 1000;
+    format!(
+        "{y:04}{m:02}{d:02}-{:02}:{:02}:{:02}.{:03}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        day_ms % 1000
+    )
+}
+
+/// Parses a FIX UTCTimestamp (`YYYYMMDD-HH:MM:SS[.sss…]`) into epoch milliseconds.
+pub fn parse_utc_timestamp(s: &str) -> Option<u64> {
+    let (date, time) = s.split_once('-')?;
+    let day_ms = parse_date(date)?;
+
+    let (hms, frac) = match time.split_once('.') {
+        Some((hms, frac)) => (hms, Some(frac)),
+        None => (time, None),
+    };
+    let mut parts = hms.split(':');
+    let h: u64 = parse_digits(parts.next()?, 2)?;
+    let m: u64 = parse_digits(parts.next()?, 2)?;
+    let sec: u64 = parse_digits(parts.next()?, 2)?;
+    if parts.next().is_some() || h > 23 || m > 59 || sec > 60 {
+        return None;
+    }
+
+    let millis = match frac {
+        Some(f) if !f.is_empty() && f.len() <= 9 && f.bytes().all(|b| b.is_ascii_digit()) => {
+            let padded = format!("{f:0<3}");
+            padded[..3].parse::<u64>().ok()?
+        }
+        Some(_) => return None,
+        None => 0,
+    };
+
+    Some(day_ms + ((h * 60 + m) * 60 + sec) * 1000 + millis)
+}
+
+/// Parses an RFC 3339 / PostgreSQL `timestamptz` text value
+/// (`YYYY-MM-DD[T ]HH:MM:SS[.sss…](Z|±HH[:MM])`) into epoch milliseconds.
+pub fn parse_rfc3339(s: &str) -> Option<u64> {
+    let s = s.trim();
+    if s.len() < 20 || !s.is_char_boundary(19) {
+        return None;
+    }
+    let (date_time, rest) = s.split_at(19);
+    let (date, time) = (&date_time[..10], &date_time[11..]);
+    if !matches!(&date_time[10..11], "T" | "t" | " ") || date.matches('-').count() != 2 {
+        return None;
+    }
+
+    let (frac, zone) = match rest.strip_prefix('.') {
+        Some(r) => {
+            let end = r.find(|c: char| !c.is_ascii_digit()).unwrap_or(r.len());
+            (Some(&r[..end]), &r[end..])
+        }
+        None => (None, rest),
+    };
+    let fix = match frac {
+        Some(f) => format!("{}-{time}.{f}", date.replace('-', "")),
+        None => format!("{}-{time}", date.replace('-', "")),
+    };
+    let utc_ms = parse_utc_timestamp(&fix)?;
+
+    let offset_ms: i64 = match zone {
+        "Z" | "z" => 0,
+        _ => {
+            let sign = match zone.as_bytes().first()? {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            let (h, m) = match zone[1..].split_once(':') {
+                Some((h, m)) => (h, m),
+                None if zone.len() == 3 => (&zone[1..], "00"),
+                None => return None,
+            };
+            let h: i64 = parse_digits(h, 2)?;
+            let m: i64 = parse_digits(m, 2)?;
+            sign * (h * 60 + m) * 60_000
+        }
+    };
+    u64::try_from(utc_ms as i64 - offset_ms).ok()
+}
+
+/// Parses a FIX date (`YYYYMMDD`) and returns the epoch milliseconds of the
+/// last millisecond of that day. LocalMktDate values are treated as UTC.
+pub fn parse_date_end_of_day(s: &str) -> Option<u64> {
+    parse_date(s).map(|start| start + 86_400_000 - 1)
+}
+
+/// Parses a FIX date (`YYYYMMDD`) and returns the epoch milliseconds of its
+/// UTC midnight.
+pub fn parse_date(s: &str) -> Option<u64> {
+    if s.len() != 8 {
+        return None;
+    }
+    let y: i64 = parse_digits(&s[0..4], 4)?;
+    let m: u32 = parse_digits(&s[4..6], 2)?;
+    let d: u32 = parse_digits(&s[6..8], 2)?;
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+        return None;
+    }
+    let days = days_from_civil(y, m, d);
+    u64::try_from(days).ok().map(|d| d * 86_400_000)
+}
+
+fn parse_digits<T: std::str::FromStr>(s: &str, len: usize) -> Option<T> {
+    if s.len() != len || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = i64::from((m + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(d) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Proleptic Gregorian date for a count of days since 1970-01-01.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (yoe + era * 400 + i64::from(m <= 2), m, d)
+}
@@ -0,0 +1,210 @@
+//! Group commit: syncing the message logs and the journal in batches.
+//!
+//! A message stored, and the journal entry for its sequence number, must be
+//! on disk before the send or receive that made them is acknowledged. An
+//! fsync for each would cap a session at a few hundred messages a second, so
+//! writes only append to their file and hand it to the group commit task.
+//! The task takes the first write waiting, gathers those arriving within
+//! `FIX_STORE_COMMIT_WINDOW_US` (default 1ms) up to `FIX_STORE_COMMIT_MAX`
+//! writes, syncs each file in the group once, and then answers every write
+//! in it. A writer awaits its [`Durable`] before acknowledging anything, so
+//! nothing acknowledged is lost in a crash; a failed sync fails each write
+//! it covered.
+//!
+//! Each session has its own log, so a group holds one sync per session it
+//! wrote to, plus one for the journal. Without the task, as in tests, every
+//! write is synced as it is made.
+
+use std::{fs::File, io, sync::Arc, time::Duration};
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::Instant,
+};
+
+const DEFAULT_WINDOW_US: u64 = 1000;
+const DEFAULT_MAX_WRITES: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommitConfig {
+    /// How long the first write of a group waits for others.
+    window: Duration,
+    /// Writes in a group, beyond which it is synced at once.
+    max_writes: usize,
+}
+
+impl Default for CommitConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_micros(DEFAULT_WINDOW_US),
+            max_writes: DEFAULT_MAX_WRITES,
+        }
+    }
+}
+
+impl CommitConfig {
+    /// Reads `FIX_STORE_COMMIT_WINDOW_US` and `FIX_STORE_COMMIT_MAX`.
+    pub fn from_env() -> Self {
+        let var = |name: &str| {
+            let value = std::env::var(name).ok()?;
+            value.trim().parse::<u64>().ok()
+        };
+        let mut config = Self::default();
+        if let Some(us) = var("FIX_STORE_COMMIT_WINDOW_US") {
+            config.window = Duration::from_micros(us);
+        }
+        if let Some(max) = var("FIX_STORE_COMMIT_MAX").filter(|&m| m > 0) {
+            config.max_writes = max as usize;
+        }
+        config
+    }
+
+    /// The settings in a line, for the timeline.
+    pub fn describe(&self) -> String {
+        format!(
+            "group commit window_us={} max_writes={}",
+            self.window.as_micros(),
+            self.max_writes
+        )
+    }
+}
+
+/// A write waiting for its file to be synced.
+struct Pending {
+    file: Arc<File>,
+    synced: oneshot::Sender<io::Result<()>>,
+}
+
+/// Hands appended files to the group commit task, or syncs them at once
+/// without one.
+#[derive(Clone, Default)]
+pub struct GroupCommit(Option<mpsc::UnboundedSender<Pending>>);
+
+impl GroupCommit {
+    /// Starts the group commit task. The channel to it needs no bound: each
+    /// write in it has a caller awaiting it.
+    pub fn start(config: CommitConfig) -> Self {
+        let (sender, writes) = mpsc::unbounded_channel();
+        tokio::spawn(run(config, writes));
+        Self(Some(sender))
+    }
+
+    /// Syncs `file`, to which a write was just appended.
+    pub fn sync(&self, file: &Arc<File>) -> Durable {
+        let Some(task) = &self.0 else {
+            return Durable::Synced(file.sync_data());
+        };
+        let (synced, receiver) = oneshot::channel();
+        let pending = Pending {
+            file: file.clone(),
+            synced,
+        };
+        match task.send(pending) {
+            Ok(()) => Durable::Waiting(receiver),
+            // The task is gone, as when the runtime is shutting down.
+            Err(mpsc::error::SendError(pending)) => Durable::Synced(pending.file.sync_data()),
+        }
+    }
+}
+
+/// A write that is on disk once [`Durable::wait`] returns `Ok`.
+pub enum Durable {
+    Synced(io::Result<()>),
+    Waiting(oneshot::Receiver<io::Result<()>>),
+}
+
+impl Durable {
+    /// A write with nothing to sync, as to an in-memory store.
+    pub fn done() -> Self {
+        Self::Synced(Ok(()))
+    }
+
+    pub async fn wait(self) -> io::Result<()> {
+        match self {
+            Self::Synced(result) => result,
+            Self::Waiting(receiver) => receiver
+                .await
+                .unwrap_or_else(|_| Err(io::Error::other("group commit stopped"))),
+        }
+    }
+}
+
+async fn run(config: CommitConfig, mut writes: mpsc::UnboundedReceiver<Pending>) {
+    let mut group = Vec::with_capacity(config.max_writes);
+    while let Some(first) = writes.recv().await {
+        group.push(first);
+        let deadline = Instant::now() + config.window;
+        while group.len() < config.max_writes {
+            match tokio::time::timeout_at(deadline, writes.recv()).await {
+                Ok(Some(pending)) => group.push(pending),
+                Ok(None) | Err(_) => break,
+            }
+        }
+        commit(&mut group).await;
+    }
+}
+
+/// Syncs each file written in the group once, then answers its writes.
+async fn commit(group: &mut Vec<Pending>) {
+    let mut files: Vec<Arc<File>> = Vec::new();
+    for pending in group.iter() {
+        if !files.iter().any(|f| Arc::ptr_eq(f, &pending.file)) {
+            files.push(pending.file.clone());
+        }
+    }
+    let synced = tokio::task::spawn_blocking(move || {
+        files
+            .into_iter()
+            .map(|file| (file.sync_data(), file))
+            .collect::<Vec<_>>()
+    })
+    .await
+    .unwrap_or_default();
+    for pending in group.drain(..) {
+        let result = match synced.iter().find(|(_, f)| Arc::ptr_eq(f, &pending.file)) {
+            Some((Ok(()), _)) => Ok(()),
+            Some((Err(e), _)) => Err(io::Error::new(e.kind(), e.to_string())),
+            None => Err(io::Error::other("sync did not run")),
+        };
+        // The writer may have given up waiting.
+        let _ = pending.synced.send(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[tokio::test]
+    async fn writes_are_synced_in_groups() {
+        let dir = std::env::temp_dir().join(format!("fix-commit-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let files: Vec<Arc<File>> = ["a.log", "b.log"]
+            .iter()
+            .map(|name| Arc::new(File::create(dir.join(name)).unwrap()))
+            .collect();
+        let config = CommitConfig {
+            window: Duration::from_millis(50),
+            max_writes: 3,
+        };
+        let commit = GroupCommit::start(config);
+
+        // Three writes fill a group before the window closes; the fourth
+        // waits for it.
+        let mut waiting = Vec::new();
+        for (i, file) in files.iter().cycle().take(4).enumerate() {
+            (&**file).write_all(format!("{i}\n").as_bytes()).unwrap();
+            waiting.push(commit.sync(file));
+        }
+        let started = Instant::now();
+        for durable in waiting {
+            durable.wait().await.unwrap();
+        }
+        assert!(started.elapsed() >= Duration::from_millis(40), "the last write waited");
+
+        let inline = GroupCommit::default().sync(&files[0]);
+        assert!(matches!(inline, Durable::Synced(Ok(()))));
+        assert!(Durable::done().wait().await.is_ok());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,834 @@
+//! The configuration file.
+//!
+//! `FIX_CONFIG` names a TOML (`.toml`) or YAML (`.yaml`, `.yml`) file
+//! defining the listeners, the FIX sessions, authentication and logging:
+//!
+//! ```toml
+//! [listeners]
+//! http = "0.0.0.0:8081"
+//! grpc = "0.0.0.0:50051"
+//!
+//! [defaults]
+//! heartbeat_secs = 30
+//! timestamp_precision = "micros"
+//! store = "file"
+//!
+//! [[sessions]]
+//! sender_comp_id = "ALICE"
+//! target_comp_id = "BROKER_A"
+//! fix_version = "FIX.4.4"
+//! transport = { role = "initiator", host = "fix.broker-a.example", port = 9876 }
+//! schedule = { start = "07:30", end = "16:30", days = ["mon", "tue", "wed", "thu", "fri"] }
+//! dictionary = "/etc/fix/broker-a.xml"
+//! rule_packs = ["BROKER_A"]
+//! heartbeat_secs = 60
+//!
+//! [rate_limit]
+//! per_sec = 50
+//! burst = 100
+//!
+//! [auth]
+//! api_keys_file = "/etc/fix/api-keys.json"
+//! oidc = { issuer = "https://id.example.com", audience = "fix-gateway" }
+//!
+//! [logging]
+//! level = "fix_engine=info,tower_http=debug"
+//! format = "json"
+//! ```
+//!
+//! The whole file is checked at startup: unknown keys, values of the wrong
+//! type and settings that contradict each other are reported together, each
+//! with where it is, and stop the gateway. A setting the file leaves out
+//! falls back to its environment variable (`FIX_ADDR`, `FIX_API_KEYS_FILE`,
+//! `FIX_SESSION_FRAMING`, `RUST_LOG`, ...), so the file can be adopted one
+//! section at a time. Sessions listed in the file replace the built-in ones.
+//!
+//! `[defaults]` holds settings for every session, each of which a session
+//! can set for itself. A session's setting is its own, else the default,
+//! else its environment variable's, else the built-in one; which it is, is
+//! noted as its [`Source`] for `GET /api/v1/fix/sessions/{id}/config`.
+//!
+//! Any setting of the file can be overridden by an `ALICE_FIX__` variable
+//! naming its path, sections and keys separated by `__` and sessions by
+//! their index: `ALICE_FIX__LISTENERS__HTTP=0.0.0.0:9000`,
+//! `ALICE_FIX__SESSIONS__0__HEARTBEAT_SECS=60`. The value is read as a TOML
+//! value (`60`, `true`, `["BROKER_A"]`), else as a string, and must have the
+//! setting's type; a string that reads as something else is quoted, as in
+//! `'"123"'`. Overrides apply with or without a file and are checked like
+//! it, each error naming its variable.
+//!
+//! The file is read again on reload (see [`crate::reload`]); the sessions,
+//! their rule packs, schedules, heartbeat intervals and timestamp precision
+//! and the rate limit change then, the rest at the next restart.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+use serde_json::{Map, Value};
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+
+use crate::{auth::Role, store::parse_time_of_day};
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The file read, if any.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    /// The `ALICE_FIX__*` variables applied on top of it.
+    #[serde(skip)]
+    overrides: Vec<String>,
+    pub listeners: Listeners,
+    pub defaults: SessionDefaults,
+    pub sessions: Vec<SessionConfig>,
+    pub rate_limit: RateLimitSettings,
+    pub auth: AuthConfig,
+    pub logging: LoggingConfig,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Listeners {
+    /// The REST API, instead of `FIX_ADDR`.
+    pub http: Option<SocketAddr>,
+    /// The gRPC API, instead of `FIX_GRPC_ADDR`.
+    pub grpc: Option<SocketAddr>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SessionConfig {
+    pub sender_comp_id: String,
+    pub target_comp_id: String,
+    #[serde(default = "default_fix_version")]
+    pub fix_version: String,
+    #[serde(default)]
+    pub transport: Transport,
+    pub schedule: Option<Schedule>,
+    /// Counterparty data dictionary file.
+    pub dictionary: Option<String>,
+    /// Instead of the session's `FIX_SESSION_RULE_PACKS` entry.
+    pub rule_packs: Option<Vec<String>>,
+    pub heartbeat_secs: Option<u64>,
+    pub timestamp_precision: Option<TimestampPrecision>,
+    pub store: Option<StoreBackend>,
+}
+
+fn default_fix_version() -> String {
+    "FIX.4.4".to_string()
+}
+
+impl SessionConfig {
+    pub fn session_id(&self) -> String {
+        format!("{}->{}", self.sender_comp_id, self.target_comp_id)
+    }
+}
+
+/// The settings of sessions that do not set their own.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionDefaults {
+    /// HeartBtInt(108) sent in Logons, in seconds.
+    pub heartbeat_secs: Option<u64>,
+    /// Data dictionary file, instead of the one for the FIX version.
+    pub dictionary: Option<String>,
+    pub timestamp_precision: Option<TimestampPrecision>,
+    pub store: Option<StoreBackend>,
+}
+
+/// Prefix of the variables overriding settings, e.g.
+/// `ALICE_FIX__DEFAULTS__HEARTBEAT_SECS=45`.
+const OVERRIDE_PREFIX: &str = "ALICE_FIX__";
+
+/// HeartBtInt(108) of sessions that no setting gives one.
+pub const DEFAULT_HEARTBEAT_SECS: u64 = 30;
+
+/// Fractional seconds in the SendingTime(52) a session stamps on the
+/// messages it sends.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampPrecision {
+    Seconds,
+    #[default]
+    Millis,
+    Micros,
+    Nanos,
+}
+
+/// Where a session's setting came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// The session's entry in the configuration file.
+    Session,
+    /// `[defaults]` in the configuration file.
+    Defaults,
+    /// An environment variable.
+    Environment,
+    /// None of them: the engine's default.
+    BuiltIn,
+}
+
+/// The first of a setting's `session`, `defaults` and `environment` values
+/// that is set, recording where it came from in `sources` as `name`.
+pub fn resolve<T>(
+    sources: &mut BTreeMap<&'static str, Source>,
+    name: &'static str,
+    session: Option<T>,
+    defaults: Option<T>,
+    environment: Option<T>,
+) -> Option<T> {
+    let (value, source) = match (session, defaults, environment) {
+        (Some(value), _, _) => (Some(value), Source::Session),
+        (None, Some(value), _) => (Some(value), Source::Defaults),
+        (None, None, Some(value)) => (Some(value), Source::Environment),
+        (None, None, None) => (None, Source::BuiltIn),
+    };
+    sources.insert(name, source);
+    value
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Transport {
+    pub role: Option<TransportRole>,
+    /// Where an initiator connects.
+    pub host: Option<String>,
+    /// Where an initiator connects, or the port an acceptor listens on.
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportRole {
+    /// We connect and log on to the counterparty.
+    Initiator,
+    /// The counterparty connects and logs on to us.
+    Acceptor,
+}
+
+/// When a session runs, in UTC.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Schedule {
+    /// `HH:MM` the session starts.
+    #[schema(example = "07:30")]
+    pub start: String,
+    /// `HH:MM` the session ends; before `start` for a session across
+    /// midnight.
+    #[schema(example = "16:30")]
+    pub end: String,
+    /// The days it starts on; every day if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<Weekday>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+/// Where a session's sequence numbers and state are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreBackend {
+    /// The journal and message store in `FIX_STORE_DIR`; the session
+    /// resumes its sequence numbers after a restart.
+    File,
+    /// Nowhere: the session starts from 1 after a restart.
+    Memory,
+}
+
+impl StoreBackend {
+    /// `file` with `FIX_STORE_DIR` set.
+    pub fn from_env() -> Option<Self> {
+        store_dir_set().then_some(Self::File)
+    }
+}
+
+fn store_dir_set() -> bool {
+    std::env::var("FIX_STORE_DIR").is_ok_and(|dir| !dir.trim().is_empty())
+}
+
+/// Each instead of its `FIX_RATE_LIMIT_*` variable.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitSettings {
+    pub per_sec: Option<f64>,
+    pub burst: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Instead of `FIX_API_KEYS_FILE`.
+    pub api_keys_file: Option<String>,
+    pub oidc: OidcSettings,
+}
+
+/// Each instead of its `FIX_OIDC_*` variable.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OidcSettings {
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    pub role_claim: Option<String>,
+    pub role_map: Option<HashMap<String, Role>>,
+    pub sessions_claim: Option<String>,
+    pub name_claim: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Log filter directives, instead of `RUST_LOG`.
+    pub level: Option<String>,
+    pub format: LogFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per line, for log shippers.
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Toml,
+    Yaml,
+}
+
+impl Config {
+    /// Reads the file in `FIX_CONFIG`, applies the `ALICE_FIX__*` overrides
+    /// and checks the result; without either, everything is configured by
+    /// the other environment variables.
+    pub fn from_env() -> Result<Self, String> {
+        let overrides = overrides_from_env();
+        let path = std::env::var("FIX_CONFIG")
+            .ok()
+            .filter(|p| !p.trim().is_empty())
+            .map(|p| PathBuf::from(p.trim()));
+        let (config, document) = match &path {
+            Some(path) => Self::read(path).map_err(|e| format!("{}: {e}", path.display()))?,
+            None => (Self::default(), Value::Object(Map::new())),
+        };
+        let mut config = config.with_overrides(document, &overrides)?;
+        let problems = config.validate(store_dir_set());
+        if !problems.is_empty() {
+            let origin = match &path {
+                Some(path) => path.display().to_string(),
+                None => format!("{OVERRIDE_PREFIX}* overrides"),
+            };
+            return Err(format!("{origin}:\n  {}", problems.join("\n  ")));
+        }
+        config.path = path;
+        Ok(config)
+    }
+
+    /// The file's settings, and the file as a document to apply overrides
+    /// to.
+    fn read(path: &Path) -> Result<(Self, Value), String> {
+        let format = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Format::Toml,
+            Some("yaml" | "yml") => Format::Yaml,
+            _ => return Err("not a .toml, .yaml or .yml file".to_string()),
+        };
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        // Parsed as settings first, so mistakes are reported with their line.
+        let config = Self::parse(&text, format)?;
+        let document = match format {
+            Format::Toml => toml::from_str(&text).map_err(|e| e.to_string())?,
+            Format::Yaml => match serde_yaml::from_str(&text).map_err(|e| e.to_string())? {
+                Value::Null => Value::Object(Map::new()),
+                document => document,
+            },
+        };
+        Ok((config, document))
+    }
+
+    fn parse(text: &str, format: Format) -> Result<Self, String> {
+        match format {
+            Format::Toml => toml::from_str(text).map_err(|e| e.to_string()),
+            Format::Yaml => serde_yaml::from_str(text).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// These settings with `overrides`, as `(variable, value)`, applied to
+    /// `document`, the file they were read from. Each override is checked
+    /// as it is applied, so an error names the variable at fault; the
+    /// settings are read from the document once all have been.
+    fn with_overrides(
+        self,
+        mut document: Value,
+        overrides: &[(String, String)],
+    ) -> Result<Self, String> {
+        if overrides.is_empty() {
+            return Ok(self);
+        }
+        for (name, text) in overrides {
+            let failed = |e: &dyn std::fmt::Display| format!("{name}: {e}");
+            let path: Vec<String> = name[OVERRIDE_PREFIX.len()..]
+                .split("__")
+                .map(str::to_ascii_lowercase)
+                .collect();
+            if path.iter().any(String::is_empty) {
+                return Err(failed(&"is not ALICE_FIX__SECTION__SETTING"));
+            }
+            let mut setting = &mut document;
+            for key in &path {
+                setting = child(setting, key).map_err(|e| failed(&e))?;
+            }
+            *setting = override_value(text);
+            Self::deserialize(&document).map_err(|e| failed(&e))?;
+        }
+        let mut config: Self = serde_json::from_value(document).map_err(|e| e.to_string())?;
+        config.path = self.path;
+        config.overrides = self.overrides;
+        config.overrides.extend(overrides.iter().map(|(name, _)| name.clone()));
+        Ok(config)
+    }
+
+    /// What is wrong with the settings, each prefixed with where it is.
+    fn validate(&self, store_dir: bool) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let (Some(http), Some(grpc)) = (self.listeners.http, self.listeners.grpc) {
+            if http == grpc {
+                problems.push(format!("listeners: http and grpc are both {http}"));
+            }
+        }
+
+        let defaults = &self.defaults;
+        check_overridable(
+            (defaults.heartbeat_secs, &defaults.dictionary, defaults.store),
+            store_dir,
+            &mut |detail| problems.push(format!("defaults: {detail}")),
+        );
+
+        let mut session_ids = HashSet::new();
+        for (i, session) in self.sessions.iter().enumerate() {
+            let mut problem = |detail: String| problems.push(format!("sessions[{i}]: {detail}"));
+            for (name, comp_id) in [
+                ("sender_comp_id", &session.sender_comp_id),
+                ("target_comp_id", &session.target_comp_id),
+            ] {
+                if let Err(e) = check_comp_id(comp_id) {
+                    problem(format!("{name} {e}"));
+                }
+            }
+            if !session_ids.insert(session.session_id()) {
+                problem(format!("{} is defined more than once", session.session_id()));
+            }
+            if !["FIX.4.", "FIX.5.", "FIXT.1."]
+                .iter()
+                .any(|prefix| session.fix_version.starts_with(prefix))
+            {
+                problem(format!(
+                    "fix_version `{}` is not a version such as FIX.4.4 or FIX.5.0",
+                    session.fix_version
+                ));
+            }
+
+            let transport = &session.transport;
+            let mismatch = match (transport.role, &transport.host, transport.port) {
+                (Some(TransportRole::Acceptor), Some(_), _) => {
+                    Some("an acceptor listens on transport.port and has no transport.host")
+                }
+                (_, Some(_), None) => Some("transport.host needs a transport.port"),
+                (Some(TransportRole::Initiator), None, Some(_)) => {
+                    Some("transport.port needs a transport.host to connect to")
+                }
+                _ => None,
+            };
+            if let Some(mismatch) = mismatch {
+                problem(mismatch.to_string());
+            }
+            if transport.port == Some(0) {
+                problem("transport.port must not be 0".to_string());
+            }
+
+            if let Some(schedule) = &session.schedule {
+                let start = parse_time_of_day(&schedule.start);
+                let end = parse_time_of_day(&schedule.end);
+                for (name, value, parsed) in
+                    [("start", &schedule.start, start), ("end", &schedule.end, end)]
+                {
+                    if parsed.is_none() {
+                        problem(format!("schedule.{name} `{value}` is not HH:MM"));
+                    }
+                }
+                if start.is_some() && start == end {
+                    problem("schedule.start and schedule.end are the same".to_string());
+                }
+            }
+            let overridable = (session.heartbeat_secs, &session.dictionary, session.store);
+            check_overridable(overridable, store_dir, &mut problem);
+        }
+
+        let oidc = &self.auth.oidc;
+        match (&oidc.issuer, &oidc.audience) {
+            (Some(_), None) => problems.push("auth.oidc: issuer needs an audience".to_string()),
+            (None, Some(_)) => problems.push("auth.oidc: audience needs an issuer".to_string()),
+            _ => {}
+        }
+        if let Some(path) = &self.auth.api_keys_file {
+            if !Path::new(path).is_file() {
+                problems.push(format!("auth.api_keys_file: {path} is not a file"));
+            }
+        }
+        if let Some(level) = &self.logging.level {
+            if let Err(e) = EnvFilter::try_new(level) {
+                problems.push(format!("logging.level `{level}`: {e}"));
+            }
+        }
+        problems
+    }
+
+    /// The file and what it defines, for startup reporting.
+    pub fn describe(&self) -> String {
+        let file = match &self.path {
+            Some(path) => format!(
+                "config file {} sessions={}",
+                path.display(),
+                self.sessions.len()
+            ),
+            None => "config file off (settings from the environment)".to_string(),
+        };
+        if self.overrides.is_empty() {
+            return file;
+        }
+        format!("{file} overrides={}", self.overrides.join(","))
+    }
+}
+
+/// The `ALICE_FIX__*` variables, by name.
+fn overrides_from_env() -> Vec<(String, String)> {
+    let mut overrides: Vec<(String, String)> = std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .filter(|(name, _)| name.starts_with(OVERRIDE_PREFIX))
+        .collect();
+    overrides.sort();
+    overrides
+}
+
+/// The setting `key` in `table`, an empty table if it is not set; in a
+/// list, `key` is an index.
+fn child<'a>(table: &'a mut Value, key: &str) -> Result<&'a mut Value, String> {
+    match table {
+        Value::Object(settings) => {
+            Ok(settings.entry(key).or_insert_with(|| Value::Object(Map::new())))
+        }
+        Value::Array(items) => {
+            let len = items.len();
+            let index: usize = key.parse().map_err(|_| format!("`{key}` is not an index"))?;
+            items
+                .get_mut(index)
+                .ok_or_else(|| format!("there is no entry {index} of {len}"))
+        }
+        _ => Err(format!("`{key}` is inside a setting that is not a table")),
+    }
+}
+
+/// An override's value: a TOML value such as `45`, `true`, `["A", "B"]` or
+/// `{ start = "07:30", end = "16:30" }`, or else the text as a string.
+fn override_value(text: &str) -> Value {
+    toml::from_str::<toml::Table>(&format!("value = {text}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .filter(|value| !value.is_datetime())
+        .and_then(|value| serde_json::to_value(value).ok())
+        .unwrap_or_else(|| Value::String(text.to_string()))
+}
+
+/// Checks the heartbeat interval, dictionary and store `[defaults]` or a
+/// session sets.
+fn check_overridable(
+    (heartbeat_secs, dictionary, store): (Option<u64>, &Option<String>, Option<StoreBackend>),
+    store_dir: bool,
+    problem: &mut impl FnMut(String),
+) {
+    if heartbeat_secs == Some(0) {
+        problem("heartbeat_secs must be positive".to_string());
+    }
+    if let Some(path) = dictionary {
+        if !Path::new(path).is_file() {
+            problem(format!("dictionary {path} is not a file"));
+        }
+    }
+    if store == Some(StoreBackend::File) && !store_dir {
+        problem("store `file` needs FIX_STORE_DIR".to_string());
+    }
+}
+
+/// A CompID usable in a session ID and in the `SESSION=...` lists of the
+/// environment variables.
+fn check_comp_id(comp_id: &str) -> Result<(), String> {
+    if comp_id.is_empty() {
+        return Err("must not be empty".to_string());
+    }
+    match comp_id
+        .chars()
+        .find(|c| !c.is_ascii_graphic() || matches!(c, '=' | ',' | '>'))
+    {
+        Some(c) => Err(format!("`{comp_id}` must not contain {c:?}")),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOML: &str = r#"
+[listeners]
+http = "127.0.0.1:8081"
+
+[defaults]
+heartbeat_secs = 30
+timestamp_precision = "micros"
+
+[[sessions]]
+sender_comp_id = "ALICE"
+target_comp_id = "BROKER_A"
+transport = { role = "initiator", host = "fix.broker-a.example", port = 9876 }
+schedule = { start = "07:30", end = "16:30", days = ["mon", "fri"] }
+rule_packs = ["BROKER_A"]
+heartbeat_secs = 60
+store = "memory"
+
+[[sessions]]
+sender_comp_id = "ALICE"
+target_comp_id = "CLIENT_X"
+fix_version = "FIX.4.2"
+transport = { role = "acceptor", port = 9880 }
+
+[rate_limit]
+per_sec = 50
+
+[auth.oidc]
+issuer = "https://id.example.com"
+audience = "fix-gateway"
+role_map = { fix-ops = "admin" }
+
+[logging]
+format = "json"
+"#;
+
+    const YAML: &str = r#"
+listeners:
+  http: 127.0.0.1:8081
+defaults:
+  heartbeat_secs: 30
+  timestamp_precision: micros
+sessions:
+  - sender_comp_id: ALICE
+    target_comp_id: BROKER_A
+    transport: { role: initiator, host: fix.broker-a.example, port: 9876 }
+    schedule: { start: "07:30", end: "16:30", days: [mon, fri] }
+    rule_packs: [BROKER_A]
+    heartbeat_secs: 60
+    store: memory
+  - sender_comp_id: ALICE
+    target_comp_id: CLIENT_X
+    fix_version: FIX.4.2
+    transport: { role: acceptor, port: 9880 }
+rate_limit:
+  per_sec: 50
+auth:
+  oidc:
+    issuer: https://id.example.com
+    audience: fix-gateway
+    role_map: { fix-ops: admin }
+logging:
+  format: json
+"#;
+
+    #[test]
+    fn toml_and_yaml_define_the_same_sessions() {
+        for (text, format) in [(TOML, Format::Toml), (YAML, Format::Yaml)] {
+            let config = Config::parse(text, format).unwrap();
+            assert_eq!(config.validate(false), Vec::<String>::new(), "{format:?}");
+            assert_eq!(config.listeners.http, "127.0.0.1:8081".parse().ok());
+            assert_eq!(config.listeners.grpc, None);
+            assert_eq!(config.defaults.heartbeat_secs, Some(30));
+            assert_eq!(config.defaults.timestamp_precision, Some(TimestampPrecision::Micros));
+
+            let [broker, client] = &config.sessions[..] else {
+                panic!("{format:?} defines {} sessions", config.sessions.len());
+            };
+            assert_eq!(broker.session_id(), "ALICE->BROKER_A");
+            assert_eq!(broker.fix_version, "FIX.4.4");
+            assert_eq!(broker.transport.role, Some(TransportRole::Initiator));
+            assert_eq!(broker.store, Some(StoreBackend::Memory));
+            assert_eq!(broker.rule_packs, Some(vec!["BROKER_A".to_string()]));
+            assert_eq!(broker.heartbeat_secs, Some(60));
+            let schedule = broker.schedule.as_ref().unwrap();
+            assert_eq!(schedule.days, [Weekday::Mon, Weekday::Fri]);
+            assert_eq!(client.transport.role, Some(TransportRole::Acceptor));
+            assert_eq!(client.transport.port, Some(9880));
+            assert_eq!(client.store, None);
+
+            assert_eq!(config.rate_limit.per_sec, Some(50.0));
+            assert_eq!(config.rate_limit.burst, None);
+            let role_map = config.auth.oidc.role_map.as_ref().unwrap();
+            assert_eq!(role_map["fix-ops"], Role::Admin);
+            assert_eq!(config.logging.format, LogFormat::Json);
+        }
+    }
+
+    #[test]
+    fn mistakes_are_reported_with_where_they_are() {
+        let typo = Config::parse("[listeners]\nhtpp = \"0.0.0.0:8081\"\n", Format::Toml);
+        assert!(typo.unwrap_err().contains("unknown field `htpp`"));
+        let role = "auth:\n  oidc:\n    role_map: { fix-ops: root }\n";
+        assert!(Config::parse(role, Format::Yaml).unwrap_err().contains("root"));
+
+        let config = Config::parse(
+            r#"
+[listeners]
+http = "0.0.0.0:8081"
+grpc = "0.0.0.0:8081"
+
+[defaults]
+heartbeat_secs = 0
+
+[[sessions]]
+sender_comp_id = "ALICE"
+target_comp_id = "BROKER A"
+fix_version = "4.4"
+transport = { role = "acceptor", host = "fix.example", port = 9876 }
+schedule = { start = "7.30", end = "16:30" }
+
+[[sessions]]
+sender_comp_id = "ALICE"
+target_comp_id = "BROKER_B"
+transport = { host = "fix.broker-b.example" }
+store = "file"
+
+[[sessions]]
+sender_comp_id = "ALICE"
+target_comp_id = "BROKER_B"
+
+[auth.oidc]
+issuer = "https://id.example.com"
+
+[logging]
+level = "fix_engine=loud"
+"#,
+            Format::Toml,
+        )
+        .unwrap();
+        let problems = config.validate(false);
+        assert_eq!(
+            problems[..10],
+            [
+                "listeners: http and grpc are both 0.0.0.0:8081",
+                "defaults: heartbeat_secs must be positive",
+                "sessions[0]: target_comp_id `BROKER A` must not contain ' '",
+                "sessions[0]: fix_version `4.4` is not a version such as FIX.4.4 or FIX.5.0",
+                "sessions[0]: an acceptor listens on transport.port and has no transport.host",
+                "sessions[0]: schedule.start `7.30` is not HH:MM",
+                "sessions[1]: transport.host needs a transport.port",
+                "sessions[1]: store `file` needs FIX_STORE_DIR",
+                "sessions[2]: ALICE->BROKER_B is defined more than once",
+                "auth.oidc: issuer needs an audience",
+            ]
+        );
+        assert_eq!(problems.len(), 11);
+        assert!(problems[10].starts_with("logging.level `fix_engine=loud`: "));
+    }
+
+    #[test]
+    fn overrides_apply_on_top_of_the_file_and_are_type_checked() {
+        let mut config = Config::parse(TOML, Format::Toml).unwrap();
+        config.path = Some(PathBuf::from("alice-fix.toml"));
+        let document: Value = toml::from_str(TOML).unwrap();
+        let vars = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect()
+        };
+        let overridden = config
+            .clone()
+            .with_overrides(
+                document.clone(),
+                &vars(&[
+                    ("ALICE_FIX__DEFAULTS__HEARTBEAT_SECS", "45"),
+                    ("ALICE_FIX__LISTENERS__GRPC", "127.0.0.1:50051"),
+                    ("ALICE_FIX__LOGGING__LEVEL", "debug"),
+                    ("ALICE_FIX__SESSIONS__1__RULE_PACKS", r#"["HOUSE"]"#),
+                    ("ALICE_FIX__SESSIONS__1__SCHEDULE", r#"{ start = "08:00", end = "17:00" }"#),
+                ]),
+            )
+            .unwrap();
+        assert_eq!(overridden.defaults.heartbeat_secs, Some(45));
+        assert_eq!(overridden.listeners.grpc, "127.0.0.1:50051".parse().ok());
+        assert_eq!(overridden.logging.level.as_deref(), Some("debug"));
+        // What no variable names stays as the file has it.
+        assert_eq!(overridden.listeners.http, config.listeners.http);
+        assert_eq!(overridden.defaults.timestamp_precision, Some(TimestampPrecision::Micros));
+        assert_eq!(overridden.logging.format, LogFormat::Json);
+        let client = &overridden.sessions[1];
+        assert_eq!(client.rule_packs, Some(vec!["HOUSE".to_string()]));
+        assert_eq!(client.schedule.as_ref().map(|s| s.start.as_str()), Some("08:00"));
+        let described = overridden.describe();
+        let first = " overrides=ALICE_FIX__DEFAULTS__HEARTBEAT_SECS,";
+        assert!(described.starts_with("config file alice-fix.toml "), "{described}");
+        assert!(described.contains(first), "{described}");
+        assert_eq!(overridden.overrides.len(), 5);
+        assert_eq!(override_value("07:30"), Value::String("07:30".to_string()));
+
+        for (name, value, error) in [
+            ("ALICE_FIX__DEFAULTS__HEARTBEAT_SECS", "soon", "invalid type: string \"soon\""),
+            ("ALICE_FIX__LISTENERS__HTPP", "0.0.0.0:8081", "unknown field `htpp`"),
+            ("ALICE_FIX__SESSIONS__2__STORE", "memory", "there is no entry 2 of 2"),
+            ("ALICE_FIX__SESSIONS__X__STORE", "memory", "`x` is not an index"),
+            ("ALICE_FIX__LOGGING__FORMAT__STYLE", "json", "`style` is inside a setting"),
+            ("ALICE_FIX__LISTENERS____HTTP", "0.0.0.0:8081", "is not ALICE_FIX__SECTION__SETTING"),
+        ] {
+            let err = config
+                .clone()
+                .with_overrides(document.clone(), &vars(&[(name, value)]))
+                .unwrap_err();
+            assert!(err.starts_with(&format!("{name}: ")) && err.contains(error), "{err}");
+        }
+    }
+
+    #[test]
+    fn settings_come_from_the_session_then_defaults_then_environment() {
+        let mut sources = BTreeMap::new();
+        let heartbeat = resolve(&mut sources, "heartbeat_secs", Some(60), Some(30), None);
+        let store = resolve(&mut sources, "store", None, None, StoreBackend::from_env());
+        let precision = resolve(
+            &mut sources,
+            "timestamp_precision",
+            None,
+            Some(TimestampPrecision::Nanos),
+            Some(TimestampPrecision::Seconds),
+        );
+        let dictionary = resolve::<String>(&mut sources, "dictionary", None, None, None);
+        assert_eq!(heartbeat, Some(60));
+        assert_eq!(precision, Some(TimestampPrecision::Nanos));
+        assert_eq!(dictionary, None);
+        let expected = if store_dir_set() {
+            (Some(StoreBackend::File), Source::Environment)
+        } else {
+            (None, Source::BuiltIn)
+        };
+        assert_eq!((store, sources["store"]), expected);
+        assert_eq!(sources["heartbeat_secs"], Source::Session);
+        assert_eq!(sources["timestamp_precision"], Source::Defaults);
+        assert_eq!(sources["dictionary"], Source::BuiltIn);
+    }
+}
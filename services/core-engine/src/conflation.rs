@@ -0,0 +1,257 @@
+//! Conflation of order book updates for slow consumers.
+//!
+//! A busy symbol can change its book thousands of times a second, more than
+//! a browser or a Kafka topic needs or can take. A [`Conflator`] passes at
+//! most `max_rate` updates per second for each book. Updates arriving faster
+//! are held back, each replacing the one before, and the latest is sent once
+//! the book's interval has passed. A consumer therefore always ends up with
+//! the book's current state, and nothing upstream slows down or drops.
+//!
+//! `FIX_MD_CONFLATION_RATE` sets the rate for WebSocket stream clients, which
+//! can ask for their own with `?max_rate=`, and for the sinks named in
+//! `FIX_BOOK_SINKS`, which receive `book` events. Without a rate, every update
+//! is passed on.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+use crate::{
+    book::{BookView, OrderBook},
+    clock::epoch_ms,
+    sinks::{self, Sink, SinkEvent},
+    AppState,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct ConflationConfig {
+    /// Book updates per second per book; 0 passes every update.
+    pub max_rate: u32,
+    /// Sinks that receive book updates.
+    pub sinks: Vec<Sink>,
+}
+
+impl ConflationConfig {
+    /// Reads `FIX_MD_CONFLATION_RATE` and `FIX_BOOK_SINKS`, a comma-separated
+    /// list of sink names from `FIX_SINKS`.
+    pub fn from_env(all_sinks: &sinks::Sinks) -> Self {
+        let max_rate = std::env::var("FIX_MD_CONFLATION_RATE")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(0);
+        let mut sinks = Vec::new();
+        for name in std::env::var("FIX_BOOK_SINKS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+        {
+            match all_sinks.get(name) {
+                Some(sink) => sinks.push(sink.clone()),
+                None => warn!(name, "Ignoring FIX_BOOK_SINKS entry not in FIX_SINKS"),
+            }
+        }
+        Self { max_rate, sinks }
+    }
+}
+
+type BookKey = (Option<String>, String);
+
+fn key(book: &OrderBook) -> BookKey {
+    (book.md_req_id.clone(), book.symbol.clone())
+}
+
+/// Limits each book to one update per interval, keeping the latest.
+#[derive(Debug)]
+pub struct Conflator {
+    interval_ms: u64,
+    last_sent: HashMap<BookKey, u64>,
+    pending: HashMap<BookKey, OrderBook>,
+    /// Updates replaced by a later one before they were sent.
+    pub coalesced: u64,
+}
+
+impl Conflator {
+    /// A conflator passing `max_rate` updates per second per book, or `None`
+    /// for a rate of 0.
+    pub fn new(max_rate: u32) -> Option<Self> {
+        (max_rate > 0).then(|| Self {
+            interval_ms: (1000 / u64::from(max_rate)).max(1),
+            last_sent: HashMap::new(),
+            pending: HashMap::new(),
+            coalesced: 0,
+        })
+    }
+
+    /// How often [`due`](Self::due) should be called: a quarter of the
+    /// interval, so held updates go out close to on time.
+    pub fn tick(&self) -> Duration {
+        Duration::from_millis((self.interval_ms / 4).max(1))
+    }
+
+    /// Takes an update, returning it if its book may be sent now. Otherwise
+    /// it is held until [`due`](Self::due).
+    pub fn offer(&mut self, book: OrderBook, now_ms: u64) -> Option<OrderBook> {
+        let key = key(&book);
+        let held = self.pending.remove(&key).is_some();
+        match self.last_sent.get(&key) {
+            Some(&last) if now_ms < last + self.interval_ms => {
+                self.coalesced += u64::from(held);
+                self.pending.insert(key, book);
+                None
+            }
+            _ => {
+                self.coalesced += u64::from(held);
+                self.last_sent.insert(key, now_ms);
+                Some(book)
+            }
+        }
+    }
+
+    /// Held updates whose book's interval has passed, oldest update first.
+    pub fn due(&mut self, now_ms: u64) -> Vec<OrderBook> {
+        let ready: Vec<BookKey> = self
+            .pending
+            .keys()
+            .filter(|k| {
+                self.last_sent
+                    .get(*k)
+                    .is_none_or(|&last| now_ms >= last + self.interval_ms)
+            })
+            .cloned()
+            .collect();
+        let mut books: Vec<OrderBook> = ready
+            .into_iter()
+            .filter_map(|k| {
+                self.last_sent.insert(k.clone(), now_ms);
+                self.pending.remove(&k)
+            })
+            .collect();
+        books.sort_by_key(|b| b.updated_at_ms);
+        books
+    }
+}
+
+/// Receives the next book to pass on from `updates`, through `conflator` if
+/// there is one. Returns `None` once the channel is closed.
+pub async fn next_book(
+    updates: &mut broadcast::Receiver<OrderBook>,
+    conflator: &mut Option<Conflator>,
+    held: &mut Vec<OrderBook>,
+) -> Option<OrderBook> {
+    loop {
+        if !held.is_empty() {
+            return Some(held.remove(0));
+        }
+        let Some(c) = conflator else {
+            match updates.recv().await {
+                Ok(book) => return Some(book),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!(missed, "Book consumer lagging, updates dropped");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        };
+        // Checked on every pass, so a book updating faster than the tick
+        // cannot hold back the others.
+        *held = c.due(epoch_ms());
+        if !held.is_empty() {
+            continue;
+        }
+        let tick = tokio::time::sleep(c.tick());
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(book) => {
+                    if let Some(book) = c.offer(book, epoch_ms()) {
+                        return Some(book);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!(missed, "Book consumer lagging, updates dropped");
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            },
+            _ = tick => *held = c.due(epoch_ms()),
+        }
+    }
+}
+
+/// Publishes book updates to the sinks in `FIX_BOOK_SINKS`, conflated to
+/// `FIX_MD_CONFLATION_RATE`. Each event carries the whole book, keyed by
+/// symbol.
+pub async fn run_sink_feed(state: AppState) {
+    let config = state.conflation.clone();
+    if config.sinks.is_empty() {
+        return;
+    }
+    let mut updates = state.books.lock().await.subscribe();
+    let mut conflator = Conflator::new(config.max_rate);
+    let mut held = Vec::new();
+    let mut last_report = Instant::now();
+    info!(
+        sinks = config.sinks.len(),
+        max_rate = config.max_rate,
+        "Book sink feed started"
+    );
+    while let Some(book) = next_book(&mut updates, &mut conflator, &mut held).await {
+        let now_ms = epoch_ms();
+        let record = serde_json::to_value(BookView::new(book.clone(), usize::MAX, now_ms))
+            .unwrap_or_default();
+        let event = SinkEvent::book(&book.symbol, now_ms, record);
+        for sink in &config.sinks {
+            if let Err(e) = sinks::deliver(&state.http, sink, &event).await {
+                warn!(sink = %sink.name, error = %e, "Book sink publish failed");
+            }
+        }
+        if let Some(c) = &conflator {
+            if last_report.elapsed() >= Duration::from_secs(60) && c.coalesced > 0 {
+                info!(coalesced = c.coalesced, "Book sink feed conflating");
+                last_report = Instant::now();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(symbol: &str, updates: u64, at_ms: u64) -> OrderBook {
+        let mut book = OrderBook::empty(symbol, Some("MD1"), at_ms);
+        book.updates = updates;
+        book
+    }
+
+    #[test]
+    fn conflation_keeps_the_latest_update_per_book() {
+        // 10 per second: one update per 100 ms per book.
+        let mut c = Conflator::new(10).unwrap();
+        assert!(Conflator::new(0).is_none());
+
+        assert_eq!(c.offer(book("AAPL", 1, 0), 0).map(|b| b.updates), Some(1));
+        assert!(c.offer(book("AAPL", 2, 10), 10).is_none());
+        assert!(c.offer(book("AAPL", 3, 20), 20).is_none());
+        // Other books have their own interval.
+        assert!(c.offer(book("MSFT", 1, 30), 30).is_some());
+        assert!(c.due(99).is_empty());
+
+        let due = c.due(100);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].updates, 3);
+        assert_eq!(c.coalesced, 1);
+        assert!(c.due(150).is_empty());
+
+        // Sent at 100, so the next may go at 200.
+        assert!(c.offer(book("AAPL", 4, 150), 150).is_none());
+        assert_eq!(
+            c.offer(book("AAPL", 5, 200), 200).map(|b| b.updates),
+            Some(5)
+        );
+        assert_eq!(c.coalesced, 2);
+        assert!(c.due(1_000).is_empty());
+    }
+}
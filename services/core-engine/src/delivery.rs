@@ -0,0 +1,398 @@
+//! Delivery tracking for asynchronous sends.
+//!
+//! `/send?async=true` answers `202 Accepted` with a gateway-assigned message
+//! ID as soon as the request passes its basic checks, and sends the message
+//! in the background. The message is then followed through its stages:
+//! queued while the session is not logged on, transmitted once it has a
+//! sequence number, and finally acknowledged by an ExecutionReport for its
+//! ClOrdID, or rejected by an OrderCancelReject, a session-level Reject or a
+//! BusinessMessageReject. `GET /api/v1/fix/messages/{id}/status` reports
+//! where it is. The most recent messages are kept.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use tracing::{info, warn, Instrument};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    clock::epoch_ms, lookup_field, submit, telemetry, AppState, ParseResponse, SendOptions,
+    SendRequest, Submitted,
+};
+
+/// Messages tracked before the oldest are forgotten.
+const MAX_TRACKED: usize = 100_000;
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeliveryOptions {
+    /// Answer 202 with a message ID and send in the background.
+    #[serde(default, rename = "async")]
+    pub asynchronous: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Accepted by `/send`, not yet sent or queued.
+    Accepted,
+    /// Waiting in the session's outbound queue.
+    Queued,
+    /// Sent with a sequence number; no answer yet.
+    Transmitted,
+    /// Answered by an ExecutionReport.
+    Acknowledged,
+    /// Refused by the counterparty.
+    Rejected,
+    /// The gateway could not send it.
+    Failed,
+}
+
+impl Stage {
+    fn is_final(self) -> bool {
+        matches!(self, Self::Acknowledged | Self::Rejected | Self::Failed)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StageChange {
+    pub stage: Stage,
+    pub at_ms: u64,
+}
+
+/// The message that answered a transmitted one.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Answer {
+    pub msg_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sequence_number: Option<u64>,
+    /// Text(58), or the ExecType(150) of an ExecutionReport.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Delivery {
+    pub message_id: String,
+    pub session_id: String,
+    pub msg_type: String,
+    pub stage: Stage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sequence_number: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cl_ord_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub answer: Option<Answer>,
+    /// For a failed message, the status `/send` would have returned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<u16>,
+    /// Every stage reached, in order.
+    pub stages: Vec<StageChange>,
+}
+
+#[derive(Default)]
+pub struct DeliveryTracker {
+    by_id: HashMap<String, Delivery>,
+    /// Message IDs oldest first, for eviction.
+    order: VecDeque<String>,
+    by_queue_id: HashMap<String, String>,
+    /// By session and ClOrdID.
+    by_cl_ord_id: HashMap<(String, String), String>,
+    /// By session and MsgSeqNum.
+    by_seq: HashMap<(String, u64), String>,
+}
+
+impl DeliveryTracker {
+    pub fn get(&self, message_id: &str) -> Option<&Delivery> {
+        self.by_id.get(message_id)
+    }
+
+    /// Starts tracking a message accepted by `/send`, returning its ID.
+    pub fn accept(&mut self, session_id: &str, msg_type: &str, now_ms: u64) -> String {
+        let message_id = format!("M-{}", uuid::Uuid::new_v4());
+        if self.order.len() == MAX_TRACKED {
+            if let Some(oldest) = self.order.pop_front() {
+                self.forget(&oldest);
+            }
+        }
+        self.order.push_back(message_id.clone());
+        self.by_id.insert(
+            message_id.clone(),
+            Delivery {
+                message_id: message_id.clone(),
+                session_id: session_id.to_string(),
+                msg_type: msg_type.to_string(),
+                stage: Stage::Accepted,
+                queue_id: None,
+                sequence_number: None,
+                cl_ord_id: None,
+                answer: None,
+                error: None,
+                stages: vec![StageChange {
+                    stage: Stage::Accepted,
+                    at_ms: now_ms,
+                }],
+            },
+        );
+        message_id
+    }
+
+    pub fn queued(&mut self, message_id: &str, queue_id: &str, now_ms: u64) {
+        if let Some(d) = self.advance(message_id, Stage::Queued, now_ms) {
+            d.queue_id = Some(queue_id.to_string());
+            self.by_queue_id.insert(queue_id.to_string(), message_id.to_string());
+        }
+    }
+
+    pub fn transmitted(
+        &mut self,
+        message_id: &str,
+        sequence_number: u64,
+        cl_ord_id: Option<&str>,
+        now_ms: u64,
+    ) {
+        let Some(d) = self.advance(message_id, Stage::Transmitted, now_ms) else {
+            return;
+        };
+        d.sequence_number = Some(sequence_number);
+        d.cl_ord_id = cl_ord_id.map(str::to_string);
+        let session_id = d.session_id.clone();
+        self.by_seq
+            .insert((session_id.clone(), sequence_number), message_id.to_string());
+        if let Some(cl_ord_id) = cl_ord_id {
+            self.by_cl_ord_id
+                .insert((session_id, cl_ord_id.to_string()), message_id.to_string());
+        }
+    }
+
+    pub fn failed(&mut self, message_id: &str, status: StatusCode, now_ms: u64) {
+        if let Some(d) = self.advance(message_id, Stage::Failed, now_ms) {
+            d.error = Some(status.as_u16());
+        }
+    }
+
+    /// A queued message was sent by the queue flush.
+    pub fn queue_sent(
+        &mut self,
+        queue_id: &str,
+        sequence_number: u64,
+        cl_ord_id: Option<&str>,
+        now_ms: u64,
+    ) {
+        if let Some(message_id) = self.by_queue_id.remove(queue_id) {
+            self.transmitted(&message_id, sequence_number, cl_ord_id, now_ms);
+        }
+    }
+
+    /// A queued message was dropped without being sent.
+    pub fn queue_dropped(&mut self, queue_id: &str, status: StatusCode, now_ms: u64) {
+        if let Some(message_id) = self.by_queue_id.remove(queue_id) {
+            self.failed(&message_id, status, now_ms);
+        }
+    }
+
+    /// Applies a message from the counterparty of `session_id` that may
+    /// answer a transmitted one.
+    pub fn on_inbound(&mut self, session_id: &str, parsed: &ParseResponse, now_ms: u64) {
+        let session_id = session_id.to_string();
+        let message_id = match parsed.msg_type.as_str() {
+            "ExecutionReport" | "OrderCancelReject" => parsed
+                .value(11)
+                .and_then(|id| self.by_cl_ord_id.get(&(session_id, id.to_string()))),
+            "SessionLevelReject" | "BusinessMessageReject" => parsed
+                .value(45)
+                .and_then(|v| v.parse().ok())
+                .and_then(|seq| self.by_seq.get(&(session_id, seq))),
+            _ => None,
+        };
+        let Some(message_id) = message_id.cloned() else {
+            return;
+        };
+        if self.by_id.get(&message_id).is_none_or(|d| d.stage != Stage::Transmitted) {
+            return;
+        }
+        // A venue refuses an order with an ExecutionReport of ExecType Rejected.
+        let report = parsed.msg_type == "ExecutionReport";
+        let stage = if report && parsed.value(150) != Some("8") {
+            Stage::Acknowledged
+        } else {
+            Stage::Rejected
+        };
+        let answer = Answer {
+            msg_type: parsed.msg_type.clone(),
+            sequence_number: parsed.value(34).and_then(|v| v.parse().ok()),
+            detail: parsed
+                .value(58)
+                .or(if report { parsed.value(150) } else { None })
+                .map(str::to_string),
+        };
+        info!(
+            message_id = %message_id,
+            stage = ?stage,
+            answer = %answer.msg_type,
+            "FIX message delivery answered"
+        );
+        if let Some(d) = self.advance(&message_id, stage, now_ms) {
+            d.answer = Some(answer);
+        }
+    }
+
+    /// Moves a message to `stage` unless it has already reached a final one.
+    fn advance(&mut self, message_id: &str, stage: Stage, now_ms: u64) -> Option<&mut Delivery> {
+        let d = self.by_id.get_mut(message_id)?;
+        if d.stage.is_final() {
+            return None;
+        }
+        d.stage = stage;
+        d.stages.push(StageChange { stage, at_ms: now_ms });
+        Some(d)
+    }
+
+    fn forget(&mut self, message_id: &str) {
+        let Some(d) = self.by_id.remove(message_id) else {
+            return;
+        };
+        if let Some(queue_id) = d.queue_id {
+            self.by_queue_id.remove(&queue_id);
+        }
+        if let Some(seq) = d.sequence_number {
+            self.by_seq.remove(&(d.session_id.clone(), seq));
+        }
+        if let Some(cl_ord_id) = d.cl_ord_id {
+            self.by_cl_ord_id.remove(&(d.session_id, cl_ord_id));
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AcceptedResponse {
+    pub message_id: String,
+    pub session_id: String,
+    pub stage: Stage,
+}
+
+/// Starts tracking a `/send?async=true` request and sends it in the
+/// background.
+pub async fn accept(
+    state: &AppState,
+    opts: SendOptions,
+    req: SendRequest,
+    session_id: String,
+) -> AcceptedResponse {
+    let message_id = state
+        .deliveries
+        .lock()
+        .await
+        .accept(&session_id, &req.msg_type, epoch_ms());
+    info!(message_id = %message_id, session_id = %session_id, "FIX message accepted");
+
+    let state = state.clone();
+    let id = message_id.clone();
+    tokio::spawn(
+        async move {
+            let outcome = submit(&state, &opts, req).await;
+            let mut deliveries = state.deliveries.lock().await;
+            match outcome {
+                Ok(Submitted::Sent(sent)) => {
+                    let cl_ord_id = lookup_field(&sent.fields, "ClOrdID", 11).map(String::as_str);
+                    deliveries.transmitted(&id, sent.sequence_number, cl_ord_id, epoch_ms());
+                }
+                Ok(Submitted::Queued(queued)) => {
+                    deliveries.queued(&id, &queued.queue_id, epoch_ms())
+                }
+                Err(e) => {
+                    let status = e.status();
+                    warn!(
+                        message_id = %id,
+                        status = %status,
+                        detail = e.message().unwrap_or_default(),
+                        "Accepted FIX message not sent"
+                    );
+                    deliveries.failed(&id, status, epoch_ms());
+                }
+            }
+        }
+        .instrument(telemetry::background_span()),
+    );
+
+    AcceptedResponse {
+        message_id,
+        session_id,
+        stage: Stage::Accepted,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/fix/messages/{message_id}/status",
+    tag = "fix",
+    params(("message_id" = String, Path, description = "The ID returned by an asynchronous send.")),
+    responses(
+        (status = 200, description = "OK", body = Delivery),
+        (status = 404, description = "Unknown message ID"),
+    )
+)]
+pub async fn status(
+    State(state): State<AppState>,
+    Path(message_id): Path<String>,
+) -> Result<Json<Delivery>, StatusCode> {
+    state
+        .deliveries
+        .lock()
+        .await
+        .get(&message_id)
+        .cloned()
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_raw;
+
+    const SESSION: &str = "ALICE->BROKER_A";
+
+    #[test]
+    fn orders_are_acknowledged_by_their_execution_report() {
+        let mut tracker = DeliveryTracker::default();
+        let id = tracker.accept(SESSION, "NewOrderSingle", 1);
+        tracker.transmitted(&id, 7, Some("O1"), 2);
+
+        let other = parse_raw(b"8=FIX.4.4|35=8|34=3|11=O2|150=0|10=000|");
+        tracker.on_inbound(SESSION, &other, 3);
+        assert_eq!(tracker.get(&id).unwrap().stage, Stage::Transmitted);
+
+        let ack = parse_raw(b"8=FIX.4.4|35=8|34=4|11=O1|150=0|10=000|");
+        tracker.on_inbound(SESSION, &ack, 4);
+        let d = tracker.get(&id).unwrap();
+        assert_eq!(d.stage, Stage::Acknowledged);
+        assert_eq!(d.answer.as_ref().unwrap().sequence_number, Some(4));
+        let stages: Vec<Stage> = d.stages.iter().map(|s| s.stage).collect();
+        assert_eq!(stages, [Stage::Accepted, Stage::Transmitted, Stage::Acknowledged]);
+    }
+
+    #[test]
+    fn session_rejects_match_by_ref_seq_num() {
+        let mut tracker = DeliveryTracker::default();
+        let id = tracker.accept(SESSION, "NewOrderSingle", 1);
+        tracker.queued(&id, "Q-1", 2);
+        tracker.queue_sent("Q-1", 9, None, 3);
+
+        let reject = parse_raw(b"8=FIX.4.4|35=3|34=5|45=9|58=Invalid tag|10=000|");
+        tracker.on_inbound(SESSION, &reject, 4);
+        let d = tracker.get(&id).unwrap();
+        assert_eq!(d.stage, Stage::Rejected);
+        assert_eq!(d.answer.as_ref().unwrap().detail.as_deref(), Some("Invalid tag"));
+
+        // A final stage is kept.
+        tracker.failed(&id, StatusCode::BAD_GATEWAY, 5);
+        assert_eq!(tracker.get(&id).unwrap().stage, Stage::Rejected);
+    }
+}
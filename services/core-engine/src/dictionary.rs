@@ -0,0 +1,714 @@
+//! FIX data dictionaries in QuickFIX XML format.
+//!
+//! A dictionary defines every field's tag and name, the standard header and
+//! trailer, and each message's fields and repeating groups. Components are
+//! expanded in place when loading, so a message definition is a flat list of
+//! fields and groups; a required field inside an optional component is
+//! treated as optional.
+//!
+//! `/api/v1/fix/dictionary/{version}` serves the fields of a loaded
+//! dictionary and `/api/v1/fix/dictionary/{version}/messages/{msg_type}` a
+//! message's layout, so UIs can build field pickers from the engine's own
+//! dictionaries.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use dashmap::DashMap;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::Arc,
+};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{problem::ApiError, AppState};
+
+/// Used for every supported version without a configured dictionary.
+const BUNDLED: &str = include_str!("../dictionaries/FIX44.xml");
+const BUNDLED_VERSIONS: &[&str] = &["FIX.4.2", "FIX.4.4", "FIX.5.0"];
+
+/// Components nested deeper than this are assumed to be recursive.
+const MAX_COMPONENT_DEPTH: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct FieldDef {
+    pub name: String,
+    /// Dictionary type, e.g. `CHAR` or `MULTIPLEVALUESTRING`.
+    pub field_type: String,
+    /// Enumerated values; empty if any value is allowed.
+    pub values: Vec<String>,
+    /// Description of each of `values`, e.g. `BUY`; empty where the
+    /// dictionary gives none.
+    pub descriptions: Vec<String>,
+}
+
+impl FieldDef {
+    /// Whether the value is a space-separated list of enum values.
+    pub fn is_multi_value(&self) -> bool {
+        self.field_type.starts_with("MULTIPLE")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Member {
+    Field { tag: u32, required: bool },
+    Group(GroupDef),
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupDef {
+    /// The NoXXX field.
+    pub count_tag: u32,
+    pub required: bool,
+    /// The first member, which must open every instance.
+    pub delimiter: u32,
+    pub members: Vec<Member>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MessageDef {
+    pub name: String,
+    pub msg_type: String,
+    pub members: Vec<Member>,
+}
+
+impl MessageDef {
+    /// The repeating group counted by `count_tag`, at any depth.
+    pub fn group(&self, count_tag: u32) -> Option<&GroupDef> {
+        fn find(members: &[Member], count_tag: u32) -> Option<&GroupDef> {
+            members.iter().find_map(|m| match m {
+                Member::Group(g) if g.count_tag == count_tag => Some(g),
+                Member::Group(g) => find(&g.members, count_tag),
+                Member::Field { .. } => None,
+            })
+        }
+        find(&self.members, count_tag)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Dictionary {
+    /// BeginString the dictionary describes, e.g. `FIX.4.4` or `FIX.5.0SP2`.
+    pub version: String,
+    pub header: Vec<Member>,
+    pub trailer: Vec<Member>,
+    fields: HashMap<u32, FieldDef>,
+    tags_by_name: HashMap<String, u32>,
+    messages: Vec<MessageDef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DictionaryError(String);
+
+impl fmt::Display for DictionaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+struct Loader<'a, 'input> {
+    tags_by_name: &'a HashMap<String, u32>,
+    components: HashMap<&'a str, roxmltree::Node<'a, 'input>>,
+}
+
+impl Loader<'_, '_> {
+    fn tag(&self, name: &str) -> Result<u32, DictionaryError> {
+        self.tags_by_name
+            .get(name)
+            .copied()
+            .ok_or_else(|| DictionaryError(format!("field {name} is not defined in <fields>")))
+    }
+
+    /// Members of a message, group or component, with components expanded.
+    fn members(
+        &self,
+        node: roxmltree::Node,
+        parent_required: bool,
+        depth: usize,
+    ) -> Result<Vec<Member>, DictionaryError> {
+        if depth > MAX_COMPONENT_DEPTH {
+            return Err(DictionaryError("components nest too deeply".to_string()));
+        }
+        let mut members = Vec::new();
+        for child in node.children().filter(|c| c.is_element()) {
+            let name = child.attribute("name").unwrap_or_default();
+            let required = parent_required && child.attribute("required") == Some("Y");
+            match child.tag_name().name() {
+                "field" => members.push(Member::Field {
+                    tag: self.tag(name)?,
+                    required,
+                }),
+                "group" => {
+                    // Fields inside a group are required per instance, not per message.
+                    let group_members = self.members(child, true, depth + 1)?;
+                    let delimiter = match group_members.first() {
+                        Some(Member::Field { tag, .. }) => *tag,
+                        Some(Member::Group(g)) => g.count_tag,
+                        None => return Err(DictionaryError(format!("group {name} is empty"))),
+                    };
+                    members.push(Member::Group(GroupDef {
+                        count_tag: self.tag(name)?,
+                        required,
+                        delimiter,
+                        members: group_members,
+                    }));
+                }
+                "component" => {
+                    let component = self.components.get(name).ok_or_else(|| {
+                        DictionaryError(format!("component {name} is not defined"))
+                    })?;
+                    members.extend(self.members(*component, required, depth + 1)?);
+                }
+                _ => {}
+            }
+        }
+        Ok(members)
+    }
+}
+
+impl Dictionary {
+    pub fn from_xml(xml: &str) -> Result<Self, DictionaryError> {
+        let doc = roxmltree::Document::parse(xml).map_err(|e| DictionaryError(e.to_string()))?;
+        let root = doc.root_element();
+        if !root.has_tag_name("fix") {
+            return Err(DictionaryError("root element is not <fix>".to_string()));
+        }
+        let section = |name: &str| root.children().find(|c| c.has_tag_name(name));
+
+        let version = {
+            let attr = |a: &str| root.attribute(a).unwrap_or("0");
+            let sp = attr("servicepack");
+            let sp = if sp == "0" {
+                String::new()
+            } else {
+                format!("SP{sp}")
+            };
+            format!("{}.{}.{}{sp}", attr("type"), attr("major"), attr("minor"))
+        };
+
+        let mut fields = HashMap::new();
+        let mut tags_by_name = HashMap::new();
+        for node in section("fields")
+            .into_iter()
+            .flat_map(|s| s.children().filter(|c| c.has_tag_name("field")))
+        {
+            let name = node.attribute("name").unwrap_or_default().to_string();
+            let tag: u32 = node
+                .attribute("number")
+                .and_then(|n| n.parse().ok())
+                .ok_or_else(|| DictionaryError(format!("field {name} has no number")))?;
+            let (values, descriptions) = node
+                .children()
+                .filter(|c| c.has_tag_name("value"))
+                .filter_map(|c| {
+                    let value = c.attribute("enum")?.to_string();
+                    Some((value, c.attribute("description").unwrap_or_default().to_string()))
+                })
+                .unzip();
+            tags_by_name.insert(name.clone(), tag);
+            fields.insert(
+                tag,
+                FieldDef {
+                    name,
+                    field_type: node.attribute("type").unwrap_or_default().to_string(),
+                    values,
+                    descriptions,
+                },
+            );
+        }
+
+        let loader = Loader {
+            tags_by_name: &tags_by_name,
+            components: section("components")
+                .into_iter()
+                .flat_map(|s| s.children().filter(|c| c.has_tag_name("component")))
+                .map(|c| (c.attribute("name").unwrap_or_default(), c))
+                .collect(),
+        };
+        let header = match section("header") {
+            Some(h) => loader.members(h, true, 0)?,
+            None => Vec::new(),
+        };
+        let trailer = match section("trailer") {
+            Some(t) => loader.members(t, true, 0)?,
+            None => Vec::new(),
+        };
+
+        let mut messages = Vec::new();
+        for node in section("messages")
+            .into_iter()
+            .flat_map(|s| s.children().filter(|c| c.has_tag_name("message")))
+        {
+            let name = node.attribute("name").unwrap_or_default().to_string();
+            let members = loader
+                .members(node, true, 0)
+                .map_err(|e| DictionaryError(format!("message {name}: {e}")))?;
+            messages.push(MessageDef {
+                msg_type: node.attribute("msgtype").unwrap_or_default().to_string(),
+                name,
+                members,
+            });
+        }
+
+        Ok(Self {
+            version,
+            header,
+            trailer,
+            fields,
+            tags_by_name,
+            messages,
+        })
+    }
+
+    pub fn field(&self, tag: u32) -> Option<&FieldDef> {
+        self.fields.get(&tag)
+    }
+
+    pub fn tag_for(&self, name: &str) -> Option<u32> {
+        self.tags_by_name.get(name).copied()
+    }
+
+    /// Looks a message up by MsgType code or by name.
+    pub fn message(&self, msg_type: &str) -> Option<&MessageDef> {
+        self.messages
+            .iter()
+            .find(|m| m.msg_type == msg_type)
+            .or_else(|| self.messages.iter().find(|m| m.name == msg_type))
+    }
+
+    /// Display name for a tag: the dictionary name, or the tag number.
+    pub fn name_of(&self, tag: u32) -> String {
+        self.field(tag)
+            .map_or_else(|| tag.to_string(), |f| f.name.clone())
+    }
+}
+
+// ── Registry ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone)]
+pub struct Dictionaries {
+    by_version: HashMap<String, Arc<Dictionary>>,
+    /// Counterparty dictionaries, used for their session whatever the version.
+    /// Changed by a reload that adds or removes sessions.
+    by_session: DashMap<String, Arc<Dictionary>>,
+}
+
+/// Reads and parses the dictionary file at `path`.
+pub fn load(path: &str) -> Result<Dictionary, DictionaryError> {
+    std::fs::read_to_string(path)
+        .map_err(|e| DictionaryError(e.to_string()))
+        .and_then(|xml| Dictionary::from_xml(&xml))
+}
+
+/// Reads `FIX_SESSION_DICTIONARIES` (comma-separated `session_id=path`
+/// entries).
+pub fn session_dictionaries_from_env() -> HashMap<String, String> {
+    let mut paths = HashMap::new();
+    let Ok(spec) = std::env::var("FIX_SESSION_DICTIONARIES") else {
+        return paths;
+    };
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        match entry.split_once('=') {
+            Some((id, path)) if !path.trim().is_empty() => {
+                paths.insert(id.trim().to_string(), path.trim().to_string());
+            }
+            _ => warn!(entry, "Ignoring malformed FIX_SESSION_DICTIONARIES entry"),
+        }
+    }
+    paths
+}
+
+impl Dictionaries {
+    /// The bundled dictionary for FIX.4.2/4.4/5.0, overridden per version by
+    /// the files listed in `FIX_DATA_DICTIONARIES` (comma-separated paths),
+    /// plus the per-session files in `FIX_SESSION_DICTIONARIES`.
+    pub fn from_env() -> Self {
+        let bundled = Arc::new(Dictionary::from_xml(BUNDLED).expect("bundled dictionary is valid"));
+        let mut by_version: HashMap<String, Arc<Dictionary>> = BUNDLED_VERSIONS
+            .iter()
+            .map(|v| (v.to_string(), bundled.clone()))
+            .collect();
+
+        let paths = std::env::var("FIX_DATA_DICTIONARIES").unwrap_or_default();
+        for path in paths.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match load(path) {
+                Ok(dict) => {
+                    info!(
+                        path,
+                        version = %dict.version,
+                        messages = dict.messages.len(),
+                        fields = dict.fields.len(),
+                        "Data dictionary loaded"
+                    );
+                    by_version.insert(dict.version.clone(), Arc::new(dict));
+                }
+                Err(e) => warn!(path, error = %e, "Failed to load data dictionary"),
+            }
+        }
+
+        let dictionaries = Self {
+            by_version,
+            by_session: DashMap::new(),
+        };
+        for (session_id, path) in session_dictionaries_from_env() {
+            if let Err(e) = dictionaries.load_session(&session_id, &path) {
+                warn!(
+                    session_id = %session_id,
+                    path = %path,
+                    error = %e,
+                    "Failed to load session data dictionary"
+                );
+            }
+        }
+        dictionaries
+    }
+
+    /// Loads the file at `path` as `session_id`'s own dictionary.
+    pub fn load_session(&self, session_id: &str, path: &str) -> Result<(), DictionaryError> {
+        let dict = load(path)?;
+        info!(
+            session_id = %session_id,
+            path = %path,
+            version = %dict.version,
+            "Session data dictionary loaded"
+        );
+        self.insert_session(session_id, dict);
+        Ok(())
+    }
+
+    /// Makes `dict` `session_id`'s own dictionary.
+    pub fn insert_session(&self, session_id: &str, dict: Dictionary) {
+        self.by_session.insert(session_id.to_string(), Arc::new(dict));
+    }
+
+    /// Drops `session_id`'s own dictionary, if it has one.
+    pub fn remove_session(&self, session_id: &str) {
+        self.by_session.remove(session_id);
+    }
+
+    /// The session's own dictionary, if it has one.
+    pub fn for_session(&self, session_id: &str) -> Option<Arc<Dictionary>> {
+        self.by_session.get(session_id).map(|d| d.value().clone())
+    }
+
+    /// The dictionary for a BeginString; `4.4` is accepted for `FIX.4.4`.
+    pub fn get(&self, version: &str) -> Option<Arc<Dictionary>> {
+        let key = if version.starts_with("FIX") {
+            version.to_string()
+        } else {
+            format!("FIX.{version}")
+        };
+        self.by_version.get(&key).cloned()
+    }
+
+    pub fn versions(&self) -> Vec<&str> {
+        let mut versions: Vec<&str> = self.by_version.keys().map(String::as_str).collect();
+        versions.sort_unstable();
+        versions
+    }
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EnumValue {
+    value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FieldInfo {
+    tag: u32,
+    name: String,
+    /// Dictionary type, e.g. `CHAR` or `PRICE`.
+    #[serde(rename = "type")]
+    field_type: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    values: Vec<EnumValue>,
+    /// Messages that require the field, by name; `StandardHeader` and
+    /// `StandardTrailer` for fields every message requires.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    required_in: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MessageSummary {
+    msg_type: String,
+    name: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DictionaryResponse {
+    version: String,
+    /// By tag.
+    fields: Vec<FieldInfo>,
+    messages: Vec<MessageSummary>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LayoutMember {
+    Field {
+        tag: u32,
+        name: String,
+        #[serde(rename = "type")]
+        field_type: String,
+        required: bool,
+    },
+    Group {
+        /// The NoXXX field.
+        tag: u32,
+        name: String,
+        /// Required per message; members' `required` is per instance.
+        required: bool,
+        /// The tag that opens every instance.
+        delimiter: u32,
+        #[schema(value_type = Vec<Object>)]
+        members: Vec<LayoutMember>,
+    },
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MessageLayout {
+    version: String,
+    msg_type: String,
+    name: String,
+    header: Vec<LayoutMember>,
+    /// Fields and groups in dictionary order, components expanded.
+    body: Vec<LayoutMember>,
+    trailer: Vec<LayoutMember>,
+}
+
+impl Dictionary {
+    fn describe(&self) -> DictionaryResponse {
+        let mut required_in: HashMap<u32, Vec<String>> = HashMap::new();
+        let sections = [
+            ("StandardHeader", &self.header),
+            ("StandardTrailer", &self.trailer),
+        ];
+        let messages = self.messages.iter().map(|m| (m.name.as_str(), &m.members));
+        for (name, members) in sections.into_iter().chain(messages) {
+            for member in members {
+                let tag = match member {
+                    Member::Field {
+                        tag,
+                        required: true,
+                    } => *tag,
+                    Member::Group(g) if g.required => g.count_tag,
+                    _ => continue,
+                };
+                required_in.entry(tag).or_default().push(name.to_string());
+            }
+        }
+
+        let fields: BTreeMap<u32, &FieldDef> = self.fields.iter().map(|(t, f)| (*t, f)).collect();
+        DictionaryResponse {
+            version: self.version.clone(),
+            fields: fields
+                .into_iter()
+                .map(|(tag, def)| FieldInfo {
+                    tag,
+                    name: def.name.clone(),
+                    field_type: def.field_type.clone(),
+                    values: def
+                        .values
+                        .iter()
+                        .zip(&def.descriptions)
+                        .map(|(value, description)| EnumValue {
+                            value: value.clone(),
+                            description: (!description.is_empty()).then(|| description.clone()),
+                        })
+                        .collect(),
+                    required_in: required_in.remove(&tag).unwrap_or_default(),
+                })
+                .collect(),
+            messages: self
+                .messages
+                .iter()
+                .map(|m| MessageSummary {
+                    msg_type: m.msg_type.clone(),
+                    name: m.name.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    fn layout(&self, members: &[Member]) -> Vec<LayoutMember> {
+        members
+            .iter()
+            .map(|member| match member {
+                Member::Field { tag, required } => LayoutMember::Field {
+                    tag: *tag,
+                    name: self.name_of(*tag),
+                    field_type: self
+                        .field(*tag)
+                        .map(|f| f.field_type.clone())
+                        .unwrap_or_default(),
+                    required: *required,
+                },
+                Member::Group(g) => LayoutMember::Group {
+                    tag: g.count_tag,
+                    name: self.name_of(g.count_tag),
+                    required: g.required,
+                    delimiter: g.delimiter,
+                    members: self.layout(&g.members),
+                },
+            })
+            .collect()
+    }
+}
+
+fn find_dictionary(state: &AppState, version: &str) -> Result<Arc<Dictionary>, ApiError> {
+    state.dictionaries.get(version).ok_or_else(|| {
+        ApiError::new(StatusCode::NOT_FOUND, "unknown_dictionary").detail(format!(
+            "no data dictionary for {version}; loaded: {}",
+            state.dictionaries.versions().join(", ")
+        ))
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/fix/dictionary/{version}",
+    tag = "fix",
+    params(("version" = String, Path, description = "BeginString, e.g. `FIX.4.4` or `4.4`.")),
+    responses(
+        (status = 200, description = "OK", body = DictionaryResponse),
+        (status = 404, description = "No dictionary for the version"),
+    )
+)]
+pub async fn get(
+    State(state): State<AppState>,
+    Path(version): Path<String>,
+) -> Result<Json<DictionaryResponse>, ApiError> {
+    Ok(Json(find_dictionary(&state, &version)?.describe()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/fix/dictionary/{version}/messages/{msg_type}",
+    tag = "fix",
+    params(
+        ("version" = String, Path, description = "BeginString, e.g. `FIX.4.4` or `4.4`."),
+        ("msg_type" = String, Path, description = "MsgType or its name, e.g. `D`."),
+    ),
+    responses(
+        (status = 200, description = "OK", body = MessageLayout),
+        (status = 404, description = "No dictionary for the version, or no such message"),
+    )
+)]
+pub async fn message(
+    State(state): State<AppState>,
+    Path((version, msg_type)): Path<(String, String)>,
+) -> Result<Json<MessageLayout>, ApiError> {
+    let dict = find_dictionary(&state, &version)?;
+    let def = dict.message(&msg_type).ok_or_else(|| {
+        ApiError::new(StatusCode::NOT_FOUND, "unknown_msg_type").detail(format!(
+            "the {} dictionary defines no message {msg_type}",
+            dict.version
+        ))
+    })?;
+    Ok(Json(MessageLayout {
+        version: dict.version.clone(),
+        msg_type: def.msg_type.clone(),
+        name: def.name.clone(),
+        header: dict.layout(&dict.header),
+        body: dict.layout(&def.members),
+        trailer: dict.layout(&dict.trailer),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_dictionary_expands_components() {
+        let dict = Dictionary::from_xml(BUNDLED).unwrap();
+        assert_eq!(dict.version, "FIX.4.4");
+
+        let nos = dict.message("D").unwrap();
+        let required: Vec<u32> = nos
+            .members
+            .iter()
+            .filter_map(|m| match m {
+                Member::Field {
+                    tag,
+                    required: true,
+                } => Some(*tag),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(required, vec![11, 55, 54, 38]);
+        assert!(dict.message("NewOrderSingle").is_some());
+
+        // Parties is an optional component, so its group is optional too.
+        let Some(Member::Group(parties)) = nos
+            .members
+            .iter()
+            .find(|m| matches!(m, Member::Group(g) if g.count_tag == 453))
+        else {
+            panic!("NoPartyIDs missing");
+        };
+        assert!(!parties.required);
+        assert_eq!(parties.delimiter, 448);
+
+        let side = dict.field(54).unwrap();
+        assert_eq!(side.field_type, "CHAR");
+        assert!(side.values.iter().any(|v| v == "2"));
+        assert!(dict.field(55).unwrap().values.is_empty());
+    }
+
+    #[test]
+    fn rejects_undefined_fields() {
+        let xml = r#"<fix type="FIX" major="4" minor="2" servicepack="0">
+            <messages><message name="X" msgtype="X"><field name="Nope" required="Y"/></message></messages>
+            <fields/></fix>"#;
+        let e = Dictionary::from_xml(xml).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "message X: field Nope is not defined in <fields>"
+        );
+    }
+
+    #[test]
+    fn fields_list_the_messages_requiring_them() {
+        let dict = Dictionary::from_xml(BUNDLED).unwrap();
+        let described = dict.describe();
+        let field = |tag: u32| described.fields.iter().find(|f| f.tag == tag).unwrap();
+
+        let side = field(54);
+        assert_eq!(side.field_type, "CHAR");
+        assert_eq!(side.values[0].value, "1");
+        assert_eq!(side.values[0].description.as_deref(), Some("BUY"));
+        assert!(side.required_in.iter().any(|m| m == "NewOrderSingle"));
+        assert!(field(49).required_in.iter().any(|m| m == "StandardHeader"));
+        assert!(described.fields.windows(2).all(|w| w[0].tag < w[1].tag));
+    }
+
+    #[test]
+    fn layouts_nest_groups() {
+        let dict = Dictionary::from_xml(BUNDLED).unwrap();
+        let layout = dict.layout(&dict.message("D").unwrap().members);
+        let parties = layout.iter().find_map(|m| match m {
+            LayoutMember::Group {
+                tag: 453,
+                name,
+                delimiter,
+                members,
+                ..
+            } => Some((name, delimiter, members)),
+            _ => None,
+        });
+        let (name, delimiter, members) = parties.expect("NoPartyIDs missing");
+        assert_eq!(name, "NoPartyIDs");
+        assert_eq!(*delimiter, 448);
+        assert!(matches!(members[0], LayoutMember::Field { tag: 448, .. }));
+    }
+}
@@ -0,0 +1,500 @@
+//! Encoding outbound messages into reused buffers.
+//!
+//! [`write_message`] writes a tag=value message straight into a `BytesMut`.
+//! BodyLength(9) is worked out from the field lengths first, so each field is
+//! written once, in place, with no intermediate strings. The fields of a
+//! `/send` message, keyed by name or tag, are turned into tags with the
+//! session's dictionary by [`body_fields`] beforehand. `/send` encodes into
+//! the buffer owned by the session's task (see [`crate::actor`]), so once
+//! that buffer has grown to fit the session's messages, encoding allocates
+//! nothing.
+//!
+//! A [`Template`] holds a session's header encoded once, up to the MsgSeqNum,
+//! for the fast order path; its messages are written from borrowed [`Part`]s.
+//!
+//! SendingTime(52) is the [`SendingTime`] given, to the session's
+//! `timestamp_precision`, and CheckSum(10) is computed over the bytes written.
+
+use bytes::{BufMut, BytesMut};
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    clock::civil_from_days,
+    config::TimestampPrecision,
+    dictionary::Dictionary,
+    message::{self, SOH},
+};
+
+/// The length of the CheckSum(10) field that ends every message.
+const TRAILER_LEN: usize = field_len(2, 3);
+
+/// SendingTime(52) of a message: a moment, and the precision it is written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendingTime {
+    since_epoch: Duration,
+    precision: TimestampPrecision,
+}
+
+impl SendingTime {
+    pub fn now(precision: TimestampPrecision) -> Self {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self::at(since_epoch, precision)
+    }
+
+    pub fn at(since_epoch: Duration, precision: TimestampPrecision) -> Self {
+        Self {
+            since_epoch,
+            precision,
+        }
+    }
+
+    /// The length of the value: `YYYYMMDD-HH:MM:SS`, then the fraction.
+    fn len(&self) -> usize {
+        17 + match self.precision {
+            TimestampPrecision::Seconds => 0,
+            TimestampPrecision::Millis => 4,
+            TimestampPrecision::Micros => 7,
+            TimestampPrecision::Nanos => 10,
+        }
+    }
+
+    /// Writes the value without formatting through a `String`.
+    fn put(&self, out: &mut BytesMut) {
+        let secs = self.since_epoch.as_secs();
+        let (y, m, d) = civil_from_days((secs / 86_400) as i64);
+        let secs = secs % 86_400;
+        put_padded(out, y as u64, 4);
+        put_padded(out, u64::from(m), 2);
+        put_padded(out, u64::from(d), 2);
+        out.put_u8(b'-');
+        put_padded(out, secs / 3600, 2);
+        out.put_u8(b':');
+        put_padded(out, secs / 60 % 60, 2);
+        out.put_u8(b':');
+        put_padded(out, secs % 60, 2);
+        let nanos = u64::from(self.since_epoch.subsec_nanos());
+        let (fraction, width) = match self.precision {
+            TimestampPrecision::Seconds => return,
+            TimestampPrecision::Millis => (nanos / 1_000_000, 3),
+            TimestampPrecision::Micros => (nanos / 1_000, 6),
+            TimestampPrecision::Nanos => (nanos, 9),
+        };
+        out.put_u8(b'.');
+        put_padded(out, fraction, width);
+    }
+}
+
+/// The header and trailer fields the builder writes itself, which fields
+/// given by the caller neither override nor repeat.
+const RESERVED: [(u32, &str); 8] = [
+    (8, "BeginString"),
+    (9, "BodyLength"),
+    (10, "CheckSum"),
+    (34, "MsgSeqNum"),
+    (35, "MsgType"),
+    (49, "SenderCompID"),
+    (52, "SendingTime"),
+    (56, "TargetCompID"),
+];
+
+fn is_reserved(tag: u32) -> bool {
+    RESERVED.iter().any(|(reserved, _)| *reserved == tag)
+}
+
+/// A message's fields, keyed by tag number or by a name in `dict`, as the
+/// tag/value pairs of its body in tag order. The header and trailer fields
+/// the builder writes are left out. A name `dict` does not know, or a field
+/// given both by name and by tag, is an error.
+pub fn body_fields(
+    dict: Option<&Dictionary>,
+    fields: &HashMap<String, String>,
+) -> Result<Vec<(u32, String)>, String> {
+    let mut out = Vec::with_capacity(fields.len());
+    for (key, value) in fields {
+        let tag = key
+            .parse::<u32>()
+            .ok()
+            .or_else(|| RESERVED.iter().find(|(_, name)| name == key).map(|(tag, _)| *tag))
+            .or_else(|| dict?.tag_for(key))
+            .ok_or_else(|| format!("unknown field '{key}'"))?;
+        if !is_reserved(tag) {
+            out.push((tag, value.clone()));
+        }
+    }
+    out.sort_by_key(|(tag, _)| *tag);
+    if let Some(pair) = out.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+        return Err(format!("field {} is given twice", pair[0].0));
+    }
+    Ok(out)
+}
+
+/// The standard header of a message to build.
+pub struct Header<'a> {
+    pub version: &'a str,
+    pub msg_type: &'a str,
+    pub sender: &'a str,
+    pub target: &'a str,
+    pub seq_num: u64,
+    pub sending_time: SendingTime,
+}
+
+/// Replaces `out`'s contents with the message: the header, the caller's
+/// `fields` other than header and trailer fields, then `group_fields` in
+/// order.
+pub fn write_message(
+    out: &mut BytesMut,
+    header: &Header<'_>,
+    fields: &[(u32, String)],
+    group_fields: &[(u32, String)],
+) {
+    let extra = || fields.iter().filter(|(tag, _)| !is_reserved(*tag));
+    let body_length = field_len(2, header.msg_type.len())
+        + field_len(2, header.sender.len())
+        + field_len(2, header.target.len())
+        + field_len(2, digits(header.seq_num))
+        + field_len(2, header.sending_time.len())
+        + extra()
+            .chain(group_fields)
+            .map(|(tag, v)| field_len(digits(u64::from(*tag)), v.len()))
+            .sum::<usize>();
+
+    out.clear();
+    out.reserve(
+        field_len(1, header.version.len())
+            + field_len(1, digits(body_length as u64))
+            + body_length
+            + TRAILER_LEN,
+    );
+    put_field(out, b"8", header.version.as_bytes());
+    out.put_slice(b"9=");
+    put_number(out, body_length as u64);
+    out.put_u8(SOH);
+    put_field(out, b"35", header.msg_type.as_bytes());
+    put_field(out, b"49", header.sender.as_bytes());
+    put_field(out, b"56", header.target.as_bytes());
+    out.put_slice(b"34=");
+    put_number(out, header.seq_num);
+    out.put_u8(SOH);
+    put_sending_time(out, header.sending_time);
+    for (tag, value) in extra().chain(group_fields) {
+        put_number(out, u64::from(*tag));
+        out.put_u8(b'=');
+        out.put_slice(value.as_bytes());
+        out.put_u8(SOH);
+    }
+    put_checksum(out);
+}
+
+/// A session's header for messages of one type, encoded once: BeginString(8),
+/// then MsgType(35) to the `34=` the MsgSeqNum follows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    version: String,
+    msg_type: String,
+    sender: String,
+    target: String,
+    /// `8=<version>|`.
+    begin: Vec<u8>,
+    /// `35=<type>|49=<sender>|56=<target>|34=`.
+    head: Vec<u8>,
+}
+
+/// Part of the body of a message written from a [`Template`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Part<'a> {
+    Field(u32, &'a [u8]),
+    /// A field whose value is a whole number.
+    Number(u32, u64),
+    /// Fields already encoded, each ending in SOH.
+    Encoded(&'a [u8]),
+}
+
+impl Part<'_> {
+    fn len(&self) -> usize {
+        match *self {
+            Self::Field(tag, value) => field_len(digits(u64::from(tag)), value.len()),
+            Self::Number(tag, n) => field_len(digits(u64::from(tag)), digits(n)),
+            Self::Encoded(fields) => fields.len(),
+        }
+    }
+
+    fn put(&self, out: &mut BytesMut) {
+        match *self {
+            Self::Field(tag, value) => {
+                put_number(out, u64::from(tag));
+                out.put_u8(b'=');
+                out.put_slice(value);
+                out.put_u8(SOH);
+            }
+            Self::Number(tag, n) => {
+                put_number(out, u64::from(tag));
+                out.put_u8(b'=');
+                put_number(out, n);
+                out.put_u8(SOH);
+            }
+            Self::Encoded(fields) => out.put_slice(fields),
+        }
+    }
+}
+
+impl Template {
+    pub fn new(version: &str, msg_type: &str, sender: &str, target: &str) -> Self {
+        let mut begin = BytesMut::new();
+        put_field(&mut begin, b"8", version.as_bytes());
+        let mut head = BytesMut::new();
+        put_field(&mut head, b"35", msg_type.as_bytes());
+        put_field(&mut head, b"49", sender.as_bytes());
+        put_field(&mut head, b"56", target.as_bytes());
+        head.put_slice(b"34=");
+        Self {
+            version: version.to_string(),
+            msg_type: msg_type.to_string(),
+            sender: sender.to_string(),
+            target: target.to_string(),
+            begin: begin.to_vec(),
+            head: head.to_vec(),
+        }
+    }
+
+    /// Whether this is the header of messages of `msg_type` on a session
+    /// configured as given.
+    pub fn is_for(&self, version: &str, msg_type: &str, sender: &str, target: &str) -> bool {
+        self.version == version
+            && self.msg_type == msg_type
+            && self.sender == sender
+            && self.target == target
+    }
+
+    /// Replaces `out`'s contents with the message: the header with
+    /// `seq_num` and `sending_time`, then `body` in order. Allocates nothing
+    /// once `out` has grown to fit.
+    pub fn write(
+        &self,
+        out: &mut BytesMut,
+        seq_num: u64,
+        sending_time: SendingTime,
+        body: &[Part<'_>],
+    ) {
+        let body_length = self.head.len()
+            + digits(seq_num)
+            + 1
+            + field_len(2, sending_time.len())
+            + body.iter().map(Part::len).sum::<usize>();
+
+        out.clear();
+        out.reserve(
+            self.begin.len() + field_len(1, digits(body_length as u64)) + body_length + TRAILER_LEN,
+        );
+        out.put_slice(&self.begin);
+        out.put_slice(b"9=");
+        put_number(out, body_length as u64);
+        out.put_u8(SOH);
+        out.put_slice(&self.head);
+        put_number(out, seq_num);
+        out.put_u8(SOH);
+        put_sending_time(out, sending_time);
+        for part in body {
+            part.put(out);
+        }
+        put_checksum(out);
+    }
+}
+
+/// The length of a `tag=value` field with its delimiter.
+const fn field_len(tag_len: usize, value_len: usize) -> usize {
+    tag_len + value_len + 2
+}
+
+/// The number of decimal digits in `n`.
+fn digits(n: u64) -> usize {
+    n.checked_ilog10().map_or(1, |log| log as usize + 1)
+}
+
+fn put_field(out: &mut BytesMut, tag: &[u8], value: &[u8]) {
+    out.put_slice(tag);
+    out.put_u8(b'=');
+    out.put_slice(value);
+    out.put_u8(SOH);
+}
+
+fn put_sending_time(out: &mut BytesMut, sending_time: SendingTime) {
+    out.put_slice(b"52=");
+    sending_time.put(out);
+    out.put_u8(SOH);
+}
+
+/// Ends the message with CheckSum(10) of everything written before it.
+fn put_checksum(out: &mut BytesMut) {
+    let checksum = message::checksum(out, SOH);
+    out.put_slice(b"10=");
+    put_padded(out, u64::from(checksum), 3);
+    out.put_u8(SOH);
+}
+
+/// Writes `n` in `width` decimal digits, zero-padded on the left.
+fn put_padded(out: &mut BytesMut, mut n: u64, width: usize) {
+    let mut buf = [b'0'; 20];
+    for digit in buf[20 - width..].iter_mut().rev() {
+        *digit = b'0' + (n % 10) as u8;
+        n /= 10;
+    }
+    out.put_slice(&buf[20 - width..]);
+}
+
+/// Writes `n` in decimal without formatting through a `String`.
+fn put_number(out: &mut BytesMut, mut n: u64) {
+    let mut buf = [0u8; 20];
+    let mut start = buf.len();
+    loop {
+        start -= 1;
+        buf[start] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    out.put_slice(&buf[start..]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2026-02-23 09:30:00.123456789 UTC.
+    fn at(precision: TimestampPrecision) -> SendingTime {
+        SendingTime::at(Duration::new(1_771_839_000, 123_456_789), precision)
+    }
+
+    fn header(seq_num: u64) -> Header<'static> {
+        Header {
+            version: "FIX.4.4",
+            msg_type: "D",
+            sender: "ALICE",
+            target: "BROKER_A",
+            seq_num,
+            sending_time: at(TimestampPrecision::Millis),
+        }
+    }
+
+    #[test]
+    fn messages_are_framed_with_their_body_length() {
+        let fields = [
+            (9, "1".to_string()),
+            (49, "ignored".to_string()),
+            (55, "AAPL".to_string()),
+            (10, "000".to_string()),
+        ];
+        let groups = [(453, "1".to_string()), (448, "DESK".to_string())];
+        let mut out = BytesMut::new();
+        write_message(&mut out, &header(1234), &fields, &groups);
+
+        let body = "35=D\x0149=ALICE\x0156=BROKER_A\x0134=1234\x01\
+                    52=20260223-09:30:00.123\x0155=AAPL\x01453=1\x01448=DESK\x01";
+        let framed = format!("8=FIX.4.4\x019={}\x01{body}", body.len());
+        let checksum = message::checksum(framed.as_bytes(), SOH);
+        assert_eq!(out, format!("{framed}10={checksum:03}\x01").as_bytes());
+    }
+
+    #[test]
+    fn named_fields_are_written_as_tags() {
+        let dict = crate::dictionary::Dictionaries::from_env().get("FIX.4.4").unwrap();
+        let fields: HashMap<String, String> = [
+            ("Symbol", "AAPL"),
+            ("Side", "1"),
+            ("OrderQty", "10"),
+            ("OrdType", "2"),
+            ("Price", "1.5"),
+            ("11", "ORD-1"),
+            ("SenderCompID", "ignored"),
+            ("9", "5"),
+            ("CheckSum", "000"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let body = body_fields(Some(&dict), &fields).unwrap();
+        let mut out = BytesMut::new();
+        write_message(&mut out, &header(7), &body, &[]);
+
+        let (parsed, errors) = crate::wire_fields(&out);
+        assert!(errors.is_empty(), "{errors:?}");
+        let tags: Vec<u32> = parsed.iter().map(|(tag, _)| *tag).collect();
+        assert_eq!(tags, [8, 9, 35, 49, 56, 34, 52, 11, 38, 40, 44, 54, 55, 10]);
+        assert_eq!(parsed[3], (49, "ALICE".to_string()));
+        assert_eq!(parsed[12], (55, "AAPL".to_string()));
+
+        let unknown = HashMap::from([("Sym".to_string(), "AAPL".to_string())]);
+        assert_eq!(body_fields(Some(&dict), &unknown), Err("unknown field 'Sym'".to_string()));
+        assert!(body_fields(None, &HashMap::from([("Symbol".to_string(), "AAPL".to_string())]))
+            .is_err());
+        let header = HashMap::from([("BeginString".to_string(), "FIX.4.2".to_string())]);
+        assert_eq!(body_fields(None, &header), Ok(Vec::new()));
+        let twice = HashMap::from([
+            ("Symbol".to_string(), "AAPL".to_string()),
+            ("55".to_string(), "MSFT".to_string()),
+        ]);
+        assert_eq!(body_fields(Some(&dict), &twice), Err("field 55 is given twice".to_string()));
+    }
+
+    #[test]
+    fn sending_times_follow_the_precision() {
+        for (precision, expected) in [
+            (TimestampPrecision::Seconds, "20260223-09:30:00"),
+            (TimestampPrecision::Millis, "20260223-09:30:00.123"),
+            (TimestampPrecision::Micros, "20260223-09:30:00.123456"),
+            (TimestampPrecision::Nanos, "20260223-09:30:00.123456789"),
+        ] {
+            let mut out = BytesMut::new();
+            at(precision).put(&mut out);
+            assert_eq!(out, expected.as_bytes());
+            assert_eq!(at(precision).len(), expected.len());
+        }
+    }
+
+    #[test]
+    fn buffers_are_reused_without_growing() {
+        let fields = [(58, "x".repeat(200))];
+        let mut out = BytesMut::new();
+        write_message(&mut out, &header(10), &fields, &[]);
+        let (ptr, capacity) = (out.as_ptr(), out.capacity());
+
+        write_message(&mut out, &header(11), &fields, &[]);
+        assert_eq!((out.as_ptr(), out.capacity()), (ptr, capacity));
+        assert!(out.starts_with(b"8=FIX.4.4\x019=261\x01"));
+        assert_eq!(digits(0), 1);
+        assert_eq!(digits(u64::MAX), 20);
+    }
+
+    #[test]
+    fn templates_write_what_the_builder_does() {
+        let groups = [
+            (55, "AAPL".to_string()),
+            (38, "100".to_string()),
+            (40, "1".to_string()),
+        ];
+        let mut built = BytesMut::new();
+        write_message(&mut built, &header(1234), &[], &groups);
+
+        let template = Template::new("FIX.4.4", "D", "ALICE", "BROKER_A");
+        assert!(template.is_for("FIX.4.4", "D", "ALICE", "BROKER_A"));
+        assert!(!template.is_for("FIX.4.2", "D", "ALICE", "BROKER_A"));
+        let body = [
+            Part::Field(55, b"AAPL"),
+            Part::Number(38, 100),
+            Part::Encoded(b"40=1\x01"),
+        ];
+        let mut out = BytesMut::new();
+        let sending_time = at(TimestampPrecision::Millis);
+        template.write(&mut out, 1234, sending_time, &body);
+        assert_eq!(out, built);
+
+        let (ptr, capacity) = (out.as_ptr(), out.capacity());
+        template.write(&mut out, 1235, sending_time, &body);
+        assert_eq!((out.as_ptr(), out.capacity()), (ptr, capacity));
+    }
+}
@@ -0,0 +1,620 @@
+//! Time-in-force expiry tracking for GTC/GTD orders.
+//!
+//! Orders sent with TimeInForce GTC (59=1) or GTD (59=6) are tracked until a
+//! terminal ExecutionReport arrives. Once the venue-side expiry has passed by
+//! more than the configured grace period with no ExecutionReport, the tracker
+//! raises an alert and, if configured, issues an OrderCancelRequest itself.
+//!
+//! An order sent with a `ttl_ms` is tracked whatever its TimeInForce, and the
+//! gateway cancels it itself once the TTL passes without a terminal
+//! ExecutionReport. This gives IOC-like behaviour on venues that lack the
+//! TimeInForce needed.
+
+use axum::{extract::State, response::Json};
+use serde::Serialize;
+use std::{collections::HashMap, time::Duration};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{clock, fix_json::OutputEncoding, AppState, SendOptions, SendRequest, Submitted};
+
+/// Terminal entries are kept for reconciliation this long after the last
+/// ExecutionReport, and overdue or cancelled ones this long after they became so.
+const RETENTION_MS: u64 = 24 * 60 * 60 * 1000;
+
+// ── Configuration ─────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpiryAction {
+    /// Log the overdue order and flag it for reconciliation.
+    Alert,
+    /// Additionally send an OrderCancelRequest for the overdue order.
+    Cancel,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExpiryConfig {
+    pub action: ExpiryAction,
+    /// How long after the expected expiry to wait for the venue's ExecutionReport.
+    pub grace_ms: u64,
+    /// Optional lifetime cap for GTC orders (many venues expire GTC after N days).
+    pub gtc_max_age_ms: Option<u64>,
+}
+
+impl ExpiryConfig {
+    /// Reads `FIX_EXPIRY_ACTION` (`alert`|`cancel`), `FIX_EXPIRY_GRACE_MS` and
+    /// `FIX_GTC_MAX_AGE_DAYS`.
+    pub fn from_env() -> Self {
+        let action = match std::env::var("FIX_EXPIRY_ACTION").as_deref() {
+            Ok("cancel") => ExpiryAction::Cancel,
+            _ => ExpiryAction::Alert,
+        };
+        let grace_ms = std::env::var("FIX_EXPIRY_GRACE_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(5_000);
+        let gtc_max_age_ms = std::env::var("FIX_GTC_MAX_AGE_DAYS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(|days| days * 86_400_000);
+        Self {
+            action,
+            grace_ms,
+            gtc_max_age_ms,
+        }
+    }
+}
+
+// ── Tracker ───────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ExpiryState {
+    /// Working at the venue, expiry not yet reached.
+    Live,
+    /// Expiry plus grace has passed without a terminal ExecutionReport.
+    Overdue,
+    /// Overdue or past its TTL, and the gateway has sent its own
+    /// OrderCancelRequest.
+    CancelSent,
+    /// The venue confirmed the expiry (OrdStatus=C).
+    Expired,
+    /// The venue reported another terminal status (filled, canceled, rejected).
+    Closed,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TrackedOrder {
+    pub cl_ord_id: String,
+    pub session_id: String,
+    pub sender_comp_id: String,
+    pub target_comp_id: String,
+    pub fix_version: String,
+    pub symbol: String,
+    pub side: String,
+    pub order_qty: Option<String>,
+    pub time_in_force: String,
+    pub sent_at_ms: u64,
+    pub expires_at_ms: Option<u64>,
+    /// When the gateway cancels the order if it is still working.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl_expires_at_ms: Option<u64>,
+    pub state: ExpiryState,
+    /// When the order entered its current state.
+    pub state_changed_at_ms: u64,
+    pub venue_ord_status: Option<String>,
+    pub last_exec_at_ms: Option<u64>,
+    pub cancel_cl_ord_id: Option<String>,
+    /// OrderCancelRequests the gateway has sent for the order.
+    pub cancel_attempts: u32,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+}
+
+/// Session identity an order was sent on.
+pub struct SessionRef<'a> {
+    pub fix_version: &'a str,
+    pub sender: &'a str,
+    pub target: &'a str,
+}
+
+pub struct ExpiryTracker {
+    config: ExpiryConfig,
+    orders: HashMap<String, TrackedOrder>,
+}
+
+impl ExpiryTracker {
+    pub fn new(config: ExpiryConfig) -> Self {
+        Self {
+            config,
+            orders: HashMap::new(),
+        }
+    }
+
+    /// Starts tracking a NewOrderSingle if its TimeInForce is GTC or GTD, or
+    /// it was sent with a TTL.
+    pub fn track_new_order(
+        &mut self,
+        fields: &HashMap<String, String>,
+        metadata: &HashMap<String, String>,
+        session: SessionRef<'_>,
+        ttl_ms: Option<u64>,
+        now_ms: u64,
+    ) {
+        let tif = match crate::lookup_field(fields, "TimeInForce", 59).map(String::as_str) {
+            Some("1") => "GTC",
+            Some("6") => "GTD",
+            _ if ttl_ms.is_none() => return,
+            None | Some("0") => "DAY",
+            Some("3") => "IOC",
+            Some("4") => "FOK",
+            Some(other) => other,
+        };
+        let Some(cl_ord_id) = crate::lookup_field(fields, "ClOrdID", 11) else {
+            return;
+        };
+
+        let expires_at_ms = if tif == "GTD" {
+            crate::lookup_field(fields, "ExpireTime", 126)
+                .and_then(|v| clock::parse_utc_timestamp(v))
+                .or_else(|| {
+                    crate::lookup_field(fields, "ExpireDate", 432)
+                        .and_then(|v| clock::parse_date_end_of_day(v))
+                })
+        } else if tif == "GTC" {
+            self.config.gtc_max_age_ms.map(|age| now_ms + age)
+        } else {
+            None
+        };
+
+        let order = TrackedOrder {
+            cl_ord_id: cl_ord_id.clone(),
+            session_id: format!("{}->{}", session.sender, session.target),
+            sender_comp_id: session.sender.to_string(),
+            target_comp_id: session.target.to_string(),
+            fix_version: session.fix_version.to_string(),
+            symbol: crate::lookup_field(fields, "Symbol", 55)
+                .cloned()
+                .unwrap_or_default(),
+            side: crate::lookup_field(fields, "Side", 54)
+                .cloned()
+                .unwrap_or_default(),
+            order_qty: crate::lookup_field(fields, "OrderQty", 38).cloned(),
+            time_in_force: tif.to_string(),
+            sent_at_ms: now_ms,
+            expires_at_ms,
+            ttl_expires_at_ms: ttl_ms.map(|ttl| now_ms + ttl),
+            state: ExpiryState::Live,
+            state_changed_at_ms: now_ms,
+            venue_ord_status: None,
+            last_exec_at_ms: None,
+            cancel_cl_ord_id: None,
+            cancel_attempts: 0,
+            metadata: metadata.clone(),
+        };
+        self.orders.insert(order.cl_ord_id.clone(), order);
+    }
+
+    /// Applies an inbound ExecutionReport to the order it refers to, matching
+    /// on ClOrdID, OrigClOrdID, or a gateway-issued cancel ClOrdID.
+    pub fn on_execution_report(
+        &mut self,
+        cl_ord_id: Option<&str>,
+        orig_cl_ord_id: Option<&str>,
+        ord_status: Option<&str>,
+        now_ms: u64,
+    ) {
+        let key = [cl_ord_id, orig_cl_ord_id]
+            .into_iter()
+            .flatten()
+            .find(|id| self.orders.contains_key(*id))
+            .map(str::to_string)
+            .or_else(|| {
+                let id = cl_ord_id?;
+                self.orders
+                    .values()
+                    .find(|o| o.cancel_cl_ord_id.as_deref() == Some(id))
+                    .map(|o| o.cl_ord_id.clone())
+            });
+        let Some(order) = key.and_then(|k| self.orders.get_mut(&k)) else {
+            return;
+        };
+
+        order.last_exec_at_ms = Some(now_ms);
+        order.venue_ord_status = ord_status.map(str::to_string);
+
+        let Some(next) = terminal_state(ord_status) else {
+            return;
+        };
+        if matches!(order.state, ExpiryState::Overdue | ExpiryState::CancelSent) {
+            info!(
+                cl_ord_id = %order.cl_ord_id,
+                ord_status = ?ord_status,
+                "Overdue order reconciled by venue ExecutionReport"
+            );
+        }
+        order.state = next;
+        order.state_changed_at_ms = now_ms;
+    }
+
+    /// Applies an inbound OrderCancelReject for one of the gateway's own
+    /// OrderCancelRequests. The order is closed if the reject reports it
+    /// done, and otherwise returns to `Live`, so the next sweep cancels it
+    /// again.
+    pub fn on_cancel_reject(
+        &mut self,
+        cl_ord_id: Option<&str>,
+        ord_status: Option<&str>,
+        now_ms: u64,
+    ) {
+        let Some(cl_ord_id) = cl_ord_id else {
+            return;
+        };
+        let Some(order) = self.orders.values_mut().find(|o| {
+            o.state == ExpiryState::CancelSent && o.cancel_cl_ord_id.as_deref() == Some(cl_ord_id)
+        }) else {
+            return;
+        };
+        order.venue_ord_status = ord_status.map(str::to_string);
+        order.state = match terminal_state(ord_status) {
+            Some(next) => {
+                order.last_exec_at_ms = Some(now_ms);
+                next
+            }
+            None => ExpiryState::Live,
+        };
+        order.state_changed_at_ms = now_ms;
+        warn!(
+            cl_ord_id = %order.cl_ord_id,
+            cancel_cl_ord_id = %cl_ord_id,
+            ord_status = ?ord_status,
+            state = ?order.state,
+            "Expiry OrderCancelRequest rejected"
+        );
+    }
+
+    /// Flags orders whose expiry has passed without a terminal ExecutionReport
+    /// and returns those the gateway should cancel itself, which always
+    /// includes orders past their TTL, each with the ClOrdID to cancel it
+    /// with. They stay `Live` until [`Self::cancel_sent`], so one whose
+    /// cancel could not be sent is returned again by the next sweep.
+    pub fn sweep(&mut self, now_ms: u64) -> Vec<TrackedOrder> {
+        let grace_ms = self.config.grace_ms;
+        let action = self.config.action;
+        let mut to_cancel = Vec::new();
+
+        self.orders.retain(|_, o| match o.state {
+            ExpiryState::Live => true,
+            ExpiryState::Overdue | ExpiryState::CancelSent => {
+                o.state_changed_at_ms + RETENTION_MS > now_ms
+            }
+            ExpiryState::Expired | ExpiryState::Closed => {
+                o.last_exec_at_ms.unwrap_or(o.sent_at_ms) + RETENTION_MS > now_ms
+            }
+        });
+
+        for order in self.orders.values_mut() {
+            if order.state != ExpiryState::Live {
+                continue;
+            }
+            if let Some(ttl_expires_at) = order.ttl_expires_at_ms.filter(|&t| t <= now_ms) {
+                info!(
+                    cl_ord_id = %order.cl_ord_id,
+                    session_id = %order.session_id,
+                    ttl_expires_at_ms = ttl_expires_at,
+                    "Order TTL elapsed, cancelling"
+                );
+                to_cancel.push(TrackedOrder {
+                    cancel_cl_ord_id: Some(cancel_cl_ord_id(order, "TTL")),
+                    ..order.clone()
+                });
+                continue;
+            }
+            let Some(expires_at) = order.expires_at_ms else {
+                continue;
+            };
+            if expires_at + grace_ms > now_ms {
+                continue;
+            }
+
+            warn!(
+                cl_ord_id = %order.cl_ord_id,
+                session_id = %order.session_id,
+                expires_at_ms = expires_at,
+                metadata = ?order.metadata,
+                "Order past venue expiry with no ExecutionReport"
+            );
+            match action {
+                ExpiryAction::Alert => {
+                    order.state = ExpiryState::Overdue;
+                    order.state_changed_at_ms = now_ms;
+                }
+                ExpiryAction::Cancel => to_cancel.push(TrackedOrder {
+                    cancel_cl_ord_id: Some(cancel_cl_ord_id(order, "EXP")),
+                    ..order.clone()
+                }),
+            }
+        }
+        to_cancel
+    }
+
+    /// Records that the gateway's OrderCancelRequest for a swept order was
+    /// sent, or queued until its session is logged on.
+    pub fn cancel_sent(&mut self, cl_ord_id: &str, cancel_cl_ord_id: &str, now_ms: u64) {
+        if let Some(order) = self.orders.get_mut(cl_ord_id) {
+            if order.state == ExpiryState::Live {
+                order.state = ExpiryState::CancelSent;
+                order.state_changed_at_ms = now_ms;
+                order.cancel_cl_ord_id = Some(cancel_cl_ord_id.to_string());
+                order.cancel_attempts += 1;
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> Vec<TrackedOrder> {
+        let mut orders: Vec<TrackedOrder> = self.orders.values().cloned().collect();
+        orders.sort_by_key(|o| o.sent_at_ms);
+        orders
+    }
+}
+
+/// The state an OrdStatus (39) reporting the order done leaves it in.
+fn terminal_state(ord_status: Option<&str>) -> Option<ExpiryState> {
+    match ord_status {
+        Some("C") => Some(ExpiryState::Expired),
+        Some("2" | "4" | "8") => Some(ExpiryState::Closed),
+        _ => None,
+    }
+}
+
+/// The ClOrdID for the gateway's next OrderCancelRequest for `order`. A retry
+/// after a rejected cancel is numbered, since venues refuse a reused ClOrdID.
+fn cancel_cl_ord_id(order: &TrackedOrder, suffix: &str) -> String {
+    match order.cancel_attempts {
+        0 => format!("{}-{suffix}", order.cl_ord_id),
+        sent => format!("{}-{suffix}-{}", order.cl_ord_id, sent + 1),
+    }
+}
+
+// ── Background sweeper ────────────────────────────────────────────────────────
+
+/// Periodically sweeps the tracker and sends OrderCancelRequests for overdue
+/// orders and those past their TTL. Each goes through its session's task as
+/// a `/send` would, so it is queued while the session is not logged on.
+pub async fn run_sweeper(state: AppState) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    let opts = SendOptions {
+        encoding: OutputEncoding::default(),
+        validate: false,
+        strict: None,
+    };
+    loop {
+        ticker.tick().await;
+        let to_cancel = state.expiries.lock().await.sweep(clock::epoch_ms());
+
+        for order in to_cancel {
+            let cancel_cl_ord_id = order.cancel_cl_ord_id.clone().unwrap_or_default();
+            let req = cancel_request(&order, clock::epoch_ms());
+            let sequence_number = match crate::submit(&state, &opts, req).await {
+                Ok(Submitted::Sent(sent)) => Some(sent.sequence_number),
+                Ok(Submitted::Queued(_)) => None,
+                Err(e) => {
+                    warn!(
+                        cl_ord_id = %order.cl_ord_id,
+                        session_id = %order.session_id,
+                        status = %e.status(),
+                        "Expiry OrderCancelRequest not sent, retrying at the next sweep"
+                    );
+                    continue;
+                }
+            };
+            state
+                .expiries
+                .lock()
+                .await
+                .cancel_sent(&order.cl_ord_id, &cancel_cl_ord_id, clock::epoch_ms());
+            info!(
+                cl_ord_id = %order.cl_ord_id,
+                session_id = %order.session_id,
+                cancel_cl_ord_id = %cancel_cl_ord_id,
+                sequence_number,
+                queued = sequence_number.is_none(),
+                "Expiry OrderCancelRequest sent"
+            );
+        }
+    }
+}
+
+/// The gateway's OrderCancelRequest (35=F) for a swept order.
+fn cancel_request(order: &TrackedOrder, now_ms: u64) -> SendRequest {
+    let mut fields: HashMap<String, String> = [
+        ("BeginString", order.fix_version.clone()),
+        ("SenderCompID", order.sender_comp_id.clone()),
+        ("TargetCompID", order.target_comp_id.clone()),
+        ("OrigClOrdID", order.cl_ord_id.clone()),
+        ("ClOrdID", order.cancel_cl_ord_id.clone().unwrap_or_default()),
+        ("Symbol", order.symbol.clone()),
+        ("Side", order.side.clone()),
+        ("TransactTime", clock::format_utc_timestamp(now_ms)),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value))
+    .collect();
+    if let Some(qty) = &order.order_qty {
+        fields.insert("OrderQty".to_string(), qty.clone());
+    }
+    SendRequest {
+        msg_type: "F".to_string(),
+        fields,
+        groups: Default::default(),
+        metadata: order.metadata.clone(),
+        reply_to: None,
+        ttl_ms: None,
+        template: None,
+        attribution: None,
+    }
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExpiriesResponse {
+    orders: Vec<TrackedOrder>,
+    count: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/fix/expiries",
+    tag = "orders",
+    responses(
+        (status = 200, description = "OK", body = ExpiriesResponse),
+    )
+)]
+pub async fn list(State(state): State<AppState>) -> Json<ExpiriesResponse> {
+    let orders = state.expiries.lock().await.snapshot();
+    let count = orders.len();
+    Json(ExpiriesResponse { orders, count })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_past_their_ttl_are_cancelled_without_waiting_for_grace() {
+        let mut tracker = ExpiryTracker::new(ExpiryConfig {
+            action: ExpiryAction::Alert,
+            grace_ms: 5_000,
+            gtc_max_age_ms: None,
+        });
+        let session = || SessionRef {
+            fix_version: "FIX.4.4",
+            sender: "ALICE",
+            target: "BROKER_A",
+        };
+        let fields = |id: &str| -> HashMap<String, String> {
+            [("11", id), ("55", "AAPL"), ("54", "1"), ("38", "100")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        let none = HashMap::new();
+        tracker.track_new_order(&fields("O1"), &none, session(), Some(500), 1_000);
+        tracker.track_new_order(&fields("O2"), &none, session(), Some(500), 1_000);
+        // A DAY order without a TTL is not tracked.
+        tracker.track_new_order(&fields("O3"), &none, session(), None, 1_000);
+        assert_eq!(tracker.snapshot().len(), 2);
+        assert_eq!(tracker.snapshot()[0].time_in_force, "DAY");
+
+        tracker.on_execution_report(Some("O2"), None, Some("2"), 1_200);
+        assert!(tracker.sweep(1_499).is_empty());
+        let cancelled = tracker.sweep(1_500);
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(cancelled[0].cancel_cl_ord_id.as_deref(), Some("O1-TTL"));
+        // Until its cancel is sent, the order is live and swept again.
+        let state_of = |tracker: &ExpiryTracker, id: &str| tracker.orders[id].state;
+        assert_eq!(state_of(&tracker, "O1"), ExpiryState::Live);
+        assert_eq!(tracker.sweep(1_600).len(), 1);
+        tracker.cancel_sent("O1", "O1-TTL", 1_600);
+        assert_eq!(state_of(&tracker, "O1"), ExpiryState::CancelSent);
+        assert!(tracker.sweep(2_000).is_empty());
+
+        // The venue's cancel confirmation closes it.
+        tracker.on_execution_report(Some("O1-TTL"), Some("O1"), Some("4"), 1_600);
+        assert!(tracker
+            .snapshot()
+            .iter()
+            .all(|o| o.state == ExpiryState::Closed));
+    }
+
+    #[test]
+    fn rejected_cancels_are_retried_and_stale_entries_dropped() {
+        let mut tracker = ExpiryTracker::new(ExpiryConfig {
+            action: ExpiryAction::Alert,
+            grace_ms: 0,
+            gtc_max_age_ms: None,
+        });
+        let session = || SessionRef {
+            fix_version: "FIX.4.4",
+            sender: "ALICE",
+            target: "BROKER_A",
+        };
+        let fields = |id: &str, tif: &str| -> HashMap<String, String> {
+            [("11", id), ("55", "AAPL"), ("54", "1"), ("59", tif), ("126", "19700101-00:00:01")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        let none = HashMap::new();
+        tracker.track_new_order(&fields("O1", "0"), &none, session(), Some(500), 1_000);
+        tracker.track_new_order(&fields("O2", "0"), &none, session(), Some(500), 1_000);
+        tracker.track_new_order(&fields("O3", "6"), &none, session(), None, 1_000);
+        for order in tracker.sweep(1_500) {
+            let id = order.cancel_cl_ord_id.unwrap();
+            tracker.cancel_sent(&order.cl_ord_id, &id, 1_500);
+        }
+        let state_of = |tracker: &ExpiryTracker, id: &str| tracker.orders[id].state;
+        assert_eq!(state_of(&tracker, "O3"), ExpiryState::Overdue);
+
+        // Still working: back to live, and cancelled again under a new ClOrdID.
+        tracker.on_cancel_reject(Some("O1-TTL"), Some("0"), 1_600);
+        assert_eq!(state_of(&tracker, "O1"), ExpiryState::Live);
+        let retry = tracker.sweep(1_700);
+        assert_eq!(retry.len(), 1);
+        assert_eq!(retry[0].cancel_cl_ord_id.as_deref(), Some("O1-TTL-2"));
+        tracker.cancel_sent("O1", "O1-TTL-2", 1_700);
+        // Already filled: closed.
+        tracker.on_cancel_reject(Some("O2-TTL"), Some("2"), 1_600);
+        assert_eq!(state_of(&tracker, "O2"), ExpiryState::Closed);
+
+        // Each is dropped a day after it entered its state.
+        tracker.sweep(1_600 + RETENTION_MS);
+        assert_eq!(tracker.orders.keys().collect::<Vec<_>>(), ["O1"]);
+        tracker.sweep(1_700 + RETENTION_MS);
+        assert!(tracker.orders.is_empty());
+    }
+
+    #[test]
+    fn overdue_orders_are_cancelled_with_an_order_cancel_request() {
+        let mut tracker = ExpiryTracker::new(ExpiryConfig {
+            action: ExpiryAction::Cancel,
+            grace_ms: 5_000,
+            gtc_max_age_ms: Some(10_000),
+        });
+        let session = SessionRef {
+            fix_version: "FIX.4.4",
+            sender: "ALICE",
+            target: "BROKER_A",
+        };
+        let fields: HashMap<String, String> =
+            [("11", "O1"), ("55", "AAPL"), ("54", "2"), ("38", "100"), ("59", "1")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+        let metadata = HashMap::from([("strategy".to_string(), "S1".to_string())]);
+        tracker.track_new_order(&fields, &metadata, session, None, 1_000);
+
+        assert!(tracker.sweep(15_999).is_empty());
+        let overdue = tracker.sweep(16_000);
+        assert_eq!(overdue.len(), 1);
+        assert_eq!(overdue[0].cancel_cl_ord_id.as_deref(), Some("O1-EXP"));
+
+        let cancel = cancel_request(&overdue[0], 16_000);
+        assert_eq!(cancel.msg_type, "F");
+        for (name, value) in [
+            ("SenderCompID", "ALICE"),
+            ("TargetCompID", "BROKER_A"),
+            ("OrigClOrdID", "O1"),
+            ("ClOrdID", "O1-EXP"),
+            ("Symbol", "AAPL"),
+            ("Side", "2"),
+            ("OrderQty", "100"),
+        ] {
+            assert_eq!(cancel.fields[name], value, "{name}");
+        }
+        let transact_time = clock::parse_utc_timestamp(&cancel.fields["TransactTime"]);
+        assert_eq!(transact_time, Some(16_000));
+        assert_eq!(cancel.metadata, metadata);
+    }
+}
@@ -0,0 +1,558 @@
+//! Archival export of each trading day's messages.
+//!
+//! With `FIX_EXPORT_DIR` set, every trading day is exported once it is over
+//! (days end at `FIX_TRADING_DAY_END`, UTC, as for retention) into
+//! `<dir>/<YYYYMMDD>/`:
+//! - `<session>.log.gz`: the session's messages as a gzip'd FIX log, one per
+//!   line after its recording time, as QuickFIX writes message logs.
+//! - `messages.parquet`: one row per message, with the header and common
+//!   order fields pulled out into typed columns (see [`FIELD_COLUMNS`]).
+//!
+//! `FIX_EXPORT_FORMATS` (`fix_gz`, `parquet`, comma-separated) picks which
+//! are written; both by default. A day is written under a temporary name and
+//! renamed into place once complete, so a day directory is always a finished
+//! export. Only messages still in the store are exported, so retention must
+//! keep at least the previous trading day.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use flate2::{write::GzEncoder, Compression};
+use parquet::{
+    basic::Compression as ParquetCompression,
+    data_type::{ByteArray, ByteArrayType, DataType, DoubleType, Int64Type},
+    file::{
+        properties::WriterProperties,
+        writer::{SerializedFileWriter, SerializedRowGroupWriter},
+    },
+    schema::parser::parse_message_type,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fs::{self, File},
+    io::{self, Write},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+use tracing::{info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    audit::{self, Actor, AuditAction},
+    clock::{self, epoch_ms, format_utc_timestamp},
+    message::{detect_delimiter, lenient_tag, scan},
+    records::{Direction, StoredMessage},
+    store::{self, file_stem},
+    AppState,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+
+const DAY_MS: u64 = 86_400_000;
+/// How long after a day ends its export starts, so late messages land first.
+const EXPORT_DELAY_MS: u64 = 60_000;
+const ROW_GROUP_SIZE: usize = 100_000;
+
+/// Parquet columns taken from message fields: name, tag, and whether the
+/// value is numeric (a DOUBLE, null if unparsable) rather than text.
+const FIELD_COLUMNS: &[(&str, u32, bool)] = &[
+    ("sending_time", 52, false),
+    ("sender_comp_id", 49, false),
+    ("target_comp_id", 56, false),
+    ("cl_ord_id", 11, false),
+    ("orig_cl_ord_id", 41, false),
+    ("order_id", 37, false),
+    ("exec_id", 17, false),
+    ("symbol", 55, false),
+    ("side", 54, false),
+    ("order_qty", 38, true),
+    ("price", 44, true),
+    ("ord_status", 39, false),
+    ("exec_type", 150, false),
+    ("last_qty", 32, true),
+    ("last_px", 31, true),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    FixGz,
+    Parquet,
+}
+
+impl Format {
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "fix_gz" => Some(Self::FixGz),
+            "parquet" => Some(Self::Parquet),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportConfig {
+    dir: PathBuf,
+    formats: Vec<Format>,
+    day_end_ms: u64,
+}
+
+impl ExportConfig {
+    /// Reads `FIX_EXPORT_DIR`, `FIX_EXPORT_FORMATS` and `FIX_TRADING_DAY_END`;
+    /// `None` unless an export directory is set.
+    pub fn from_env() -> Option<Self> {
+        let dir = std::env::var("FIX_EXPORT_DIR").ok()?;
+        if dir.trim().is_empty() {
+            return None;
+        }
+        let mut formats = Vec::new();
+        for name in std::env::var("FIX_EXPORT_FORMATS")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+        {
+            match Format::parse(name) {
+                Some(format) if !formats.contains(&format) => formats.push(format),
+                Some(_) => {}
+                None => warn!(format = %name, "Ignoring unknown FIX_EXPORT_FORMATS entry"),
+            }
+        }
+        if formats.is_empty() {
+            formats = vec![Format::FixGz, Format::Parquet];
+        }
+        let day_end_ms = std::env::var("FIX_TRADING_DAY_END")
+            .ok()
+            .and_then(|v| store::parse_time_of_day(&v))
+            .unwrap_or(0);
+        Some(Self {
+            dir: PathBuf::from(dir.trim()),
+            formats,
+            day_end_ms,
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        let formats: Vec<&str> = self
+            .formats
+            .iter()
+            .map(|f| match f {
+                Format::FixGz => "fix_gz",
+                Format::Parquet => "parquet",
+            })
+            .collect();
+        format!(
+            "archival export (dir: {} formats={})",
+            self.dir.display(),
+            formats.join(",")
+        )
+    }
+
+    /// Offset from a day label's UTC midnight to the start of that trading
+    /// day. Days are labelled with the date most of their hours fall on: with
+    /// a 22:00 day end, 20260223 runs from 22:00 on the 22nd to 22:00 on the
+    /// 23rd; with 06:00, from 06:00 on the 23rd to 06:00 on the 24th.
+    fn start_offset_ms(&self) -> i64 {
+        let end = self.day_end_ms as i64;
+        if self.day_end_ms >= DAY_MS / 2 {
+            end - DAY_MS as i64
+        } else {
+            end
+        }
+    }
+
+    /// Recording-time bounds `[from, to)` of the day labelled `date_ms`.
+    fn bounds(&self, date_ms: u64) -> (u64, u64) {
+        let start = (date_ms as i64 + self.start_offset_ms()).max(0) as u64;
+        (start, start + DAY_MS)
+    }
+
+    /// The label of the latest trading day that had ended at `now_ms`.
+    fn last_completed(&self, now_ms: u64) -> u64 {
+        let days =
+            (now_ms as i64 - self.start_offset_ms() - DAY_MS as i64).div_euclid(DAY_MS as i64);
+        days.max(0) as u64 * DAY_MS
+    }
+
+    /// Writes the formats configured for one day's messages into
+    /// `<dir>/<YYYYMMDD>/`, replacing any earlier export of that day.
+    fn write(&self, date_ms: u64, messages: &[StoredMessage]) -> io::Result<Export> {
+        let day = format_utc_timestamp(date_ms)[..8].to_string();
+        let tmp = self.dir.join(format!("{day}.tmp"));
+        if tmp.exists() {
+            fs::remove_dir_all(&tmp)?;
+        }
+        fs::create_dir_all(&tmp)?;
+        let mut files = Vec::new();
+        for format in &self.formats {
+            match format {
+                Format::FixGz => files.extend(write_fix_logs(&tmp, messages)?),
+                Format::Parquet => {
+                    let name = "messages.parquet".to_string();
+                    write_parquet(File::create(tmp.join(&name))?, messages)?;
+                    files.push(name);
+                }
+            }
+        }
+        let path = self.dir.join(&day);
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
+        }
+        fs::rename(&tmp, &path)?;
+        files.sort();
+        let (from_ms, to_ms) = self.bounds(date_ms);
+        Ok(Export {
+            files: files.into_iter().map(|f| format!("{day}/{f}")).collect(),
+            trading_day: day,
+            from_ms,
+            to_ms,
+            messages: messages.len(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Export {
+    trading_day: String,
+    from_ms: u64,
+    to_ms: u64,
+    messages: usize,
+    /// Relative to the export directory.
+    files: Vec<String>,
+}
+
+// ── Writers ───────────────────────────────────────────────────────────────────
+
+/// One gzip'd log per session in `dir`; returns the file names.
+fn write_fix_logs(dir: &std::path::Path, messages: &[StoredMessage]) -> io::Result<Vec<String>> {
+    let mut logs: HashMap<String, GzEncoder<File>> = HashMap::new();
+    for m in messages {
+        let log = match logs.entry(format!("{}.log.gz", file_stem(&m.session_id))) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => {
+                let file = File::create(dir.join(e.key()))?;
+                e.insert(GzEncoder::new(file, Compression::default()))
+            }
+        };
+        let raw = BASE64.decode(&m.raw_base64).unwrap_or_default();
+        write!(log, "{} : ", format_utc_timestamp(m.recorded_at_ms))?;
+        log.write_all(&raw)?;
+        log.write_all(b"\n")?;
+    }
+    let mut names = Vec::new();
+    for (name, log) in logs {
+        log.finish()?.sync_all()?;
+        names.push(name);
+    }
+    Ok(names)
+}
+
+fn parquet_schema() -> String {
+    let mut columns = vec![
+        "REQUIRED BYTE_ARRAY session_id (UTF8);".to_string(),
+        "REQUIRED BYTE_ARRAY direction (UTF8);".to_string(),
+        "REQUIRED BYTE_ARRAY msg_type (UTF8);".to_string(),
+        "OPTIONAL INT64 msg_seq_num;".to_string(),
+        "REQUIRED INT64 recorded_at (TIMESTAMP(MILLIS, true));".to_string(),
+    ];
+    for (name, _, numeric) in FIELD_COLUMNS {
+        columns.push(if *numeric {
+            format!("OPTIONAL DOUBLE {name};")
+        } else {
+            format!("OPTIONAL BYTE_ARRAY {name} (UTF8);")
+        });
+    }
+    columns.push("REQUIRED BYTE_ARRAY raw;".to_string());
+    format!("message fix_message {{ {} }}", columns.join(" "))
+}
+
+fn text(s: impl AsRef<[u8]>) -> ByteArray {
+    ByteArray::from(s.as_ref().to_vec())
+}
+
+fn write_parquet(file: File, messages: &[StoredMessage]) -> io::Result<()> {
+    let schema = parse_message_type(&parquet_schema()).map_err(io::Error::other)?;
+    let props = WriterProperties::builder()
+        .set_compression(ParquetCompression::GZIP(Default::default()))
+        .build();
+    let mut writer = SerializedFileWriter::new(file, Arc::new(schema), Arc::new(props))
+        .map_err(io::Error::other)?;
+    for chunk in messages.chunks(ROW_GROUP_SIZE) {
+        let raws: Vec<Vec<u8>> = chunk
+            .iter()
+            .map(|m| BASE64.decode(&m.raw_base64).unwrap_or_default())
+            .collect();
+        let fields: Vec<HashMap<u32, &[u8]>> = raws.iter().map(|raw| field_map(raw)).collect();
+        let mut group = writer.next_row_group().map_err(io::Error::other)?;
+        write_column::<ByteArrayType>(
+            &mut group,
+            chunk.iter().map(|m| Some(text(&m.session_id))).collect(),
+            true,
+        )?;
+        write_column::<ByteArrayType>(
+            &mut group,
+            chunk
+                .iter()
+                .map(|m| {
+                    Some(text(match m.direction {
+                        Direction::Inbound => "inbound",
+                        Direction::Outbound => "outbound",
+                    }))
+                })
+                .collect(),
+            true,
+        )?;
+        write_column::<ByteArrayType>(
+            &mut group,
+            chunk.iter().map(|m| Some(text(&m.msg_type))).collect(),
+            true,
+        )?;
+        write_column::<Int64Type>(
+            &mut group,
+            chunk
+                .iter()
+                .map(|m| m.sequence_number.map(|s| s as i64))
+                .collect(),
+            false,
+        )?;
+        write_column::<Int64Type>(
+            &mut group,
+            chunk
+                .iter()
+                .map(|m| Some(m.recorded_at_ms as i64))
+                .collect(),
+            true,
+        )?;
+        for (_, tag, numeric) in FIELD_COLUMNS {
+            let values = fields.iter().map(|f| f.get(tag).copied());
+            if *numeric {
+                write_column::<DoubleType>(
+                    &mut group,
+                    values
+                        .map(|v| std::str::from_utf8(v?).ok()?.parse().ok())
+                        .collect(),
+                    false,
+                )?;
+            } else {
+                write_column::<ByteArrayType>(
+                    &mut group,
+                    values.map(|v| v.map(text)).collect(),
+                    false,
+                )?;
+            }
+        }
+        write_column::<ByteArrayType>(
+            &mut group,
+            raws.iter().map(|raw| Some(text(raw))).collect(),
+            true,
+        )?;
+        group.close().map_err(io::Error::other)?;
+    }
+    writer.into_inner().map_err(io::Error::other)?.sync_all()
+}
+
+/// Writes the next column of a row group; `None` values are nulls.
+fn write_column<T: DataType>(
+    group: &mut SerializedRowGroupWriter<'_, File>,
+    values: Vec<Option<T::T>>,
+    required: bool,
+) -> io::Result<()> {
+    let mut column = group
+        .next_column()
+        .map_err(io::Error::other)?
+        .ok_or_else(|| io::Error::other("more columns written than the schema defines"))?;
+    let defs: Vec<i16> = values.iter().map(|v| i16::from(v.is_some())).collect();
+    let present: Vec<T::T> = values.into_iter().flatten().collect();
+    column
+        .typed::<T>()
+        .write_batch(&present, (!required).then_some(&defs[..]), None)
+        .map_err(io::Error::other)?;
+    column.close().map_err(io::Error::other)
+}
+
+/// The first value of each tag in a raw message.
+fn field_map(raw: &[u8]) -> HashMap<u32, &[u8]> {
+    let mut fields = HashMap::new();
+    for segment in scan(raw, detect_delimiter(raw)) {
+        if let (Some(tag), Some(value)) = (lenient_tag(segment.tag), segment.value) {
+            fields.entry(tag).or_insert(value);
+        }
+    }
+    fields
+}
+
+// ── Jobs ──────────────────────────────────────────────────────────────────────
+
+async fn export_day(
+    state: &AppState,
+    config: &ExportConfig,
+    date_ms: u64,
+) -> Result<Export, StatusCode> {
+    let (from_ms, to_ms) = config.bounds(date_ms);
+    let messages = state.messages.lock().await.between(from_ms, to_ms);
+    let export = config.write(date_ms, &messages).map_err(|e| {
+        warn!(date_ms, error = %e, "Archival export failed");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    info!(
+        trading_day = %export.trading_day,
+        messages = export.messages,
+        files = export.files.len(),
+        "Trading day exported"
+    );
+    Ok(export)
+}
+
+/// Exports each trading day shortly after it ends. At startup the last
+/// completed day is exported if a previous run did not get to it.
+pub async fn run_exporter(state: AppState) {
+    let Some(config) = state.export.clone() else {
+        return;
+    };
+    loop {
+        let now = epoch_ms();
+        let day = config.last_completed(now.saturating_sub(EXPORT_DELAY_MS));
+        let dir = config.dir.join(&format_utc_timestamp(day)[..8]);
+        if !dir.exists() {
+            let _ = export_day(&state, &config, day).await;
+        }
+        let next = config.bounds(day + DAY_MS).1 + EXPORT_DELAY_MS;
+        tokio::time::sleep(Duration::from_millis(next.saturating_sub(now))).await;
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    /// `YYYYMMDD`; defaults to the last completed trading day.
+    trading_day: Option<String>,
+}
+
+/// Exports a trading day now, replacing any earlier export of it.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/store/export",
+    tag = "admin",
+    params(ExportQuery),
+    responses(
+        (status = 200, description = "OK", body = Export),
+        (status = 400, description = "Invalid date"),
+        (status = 404, description = "Export not configured"),
+    )
+)]
+pub async fn export(
+    State(state): State<AppState>,
+    actor: Actor,
+    Query(q): Query<ExportQuery>,
+) -> Result<Json<Export>, StatusCode> {
+    let Some(config) = state.export.as_deref() else {
+        warn!("Archival export requested but FIX_EXPORT_DIR is not set");
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let day = match &q.trading_day {
+        Some(day) => clock::parse_date(day).ok_or(StatusCode::BAD_REQUEST)?,
+        None => config.last_completed(epoch_ms()),
+    };
+    let export = export_day(&state, config, day).await?;
+    audit::record(
+        &state,
+        &actor,
+        AuditAction::StoreExported,
+        None,
+        format!(
+            "trading day {} messages={}",
+            export.trading_day, export.messages
+        ),
+    )
+    .await;
+    Ok(Json(export))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    fn stored(session_id: &str, seq: u64, body: &str, ms: u64) -> StoredMessage {
+        StoredMessage {
+            session_id: session_id.to_string(),
+            direction: Direction::Outbound,
+            msg_type: "D".to_string(),
+            sequence_number: Some(seq),
+            raw_base64: BASE64.encode(format!("8=FIX.4.4\x0135=D\x0134={seq}\x01{body}")),
+            recorded_at_ms: ms,
+            execution: None,
+            attribution: None,
+        }
+    }
+
+    #[test]
+    fn trading_days_are_labelled_by_their_main_date() {
+        let day = |d: u64, hour: u64| d * DAY_MS + hour * 3_600_000;
+        let config = |end_hour: u64| ExportConfig {
+            dir: PathBuf::new(),
+            formats: vec![Format::FixGz],
+            day_end_ms: end_hour * 3_600_000,
+        };
+        assert_eq!(config(0).bounds(day(10, 0)), (day(10, 0), day(11, 0)));
+        assert_eq!(config(22).bounds(day(10, 0)), (day(9, 22), day(10, 22)));
+        assert_eq!(config(6).bounds(day(10, 0)), (day(10, 6), day(11, 6)));
+        assert_eq!(config(22).last_completed(day(10, 21)), day(9, 0));
+        assert_eq!(config(22).last_completed(day(10, 22)), day(10, 0));
+        assert_eq!(config(6).last_completed(day(11, 5)), day(9, 0));
+        assert_eq!(config(0).last_completed(day(11, 0)), day(10, 0));
+    }
+
+    #[test]
+    fn exports_gzip_logs_and_parquet() {
+        let dir = std::env::temp_dir().join(format!("fix-export-{}", uuid::Uuid::new_v4()));
+        let config = ExportConfig {
+            dir: dir.clone(),
+            formats: vec![Format::FixGz, Format::Parquet],
+            day_end_ms: 0,
+        };
+        let messages = [
+            stored(
+                "ALICE->BROKER_A",
+                1,
+                "11=O1\x0155=AAPL\x0138=100\x01",
+                1_000,
+            ),
+            stored("ALICE->BROKER_B", 1, "11=O2\x0144=abc\x01", 2_000),
+        ];
+        let export = config.write(0, &messages).unwrap();
+        assert_eq!(export.trading_day, "19700101");
+        assert_eq!(
+            export.files,
+            [
+                "19700101/ALICE-_BROKER_A.log.gz",
+                "19700101/ALICE-_BROKER_B.log.gz",
+                "19700101/messages.parquet",
+            ]
+        );
+
+        let mut log = String::new();
+        io::Read::read_to_string(
+            &mut flate2::read::GzDecoder::new(File::open(dir.join(&export.files[0])).unwrap()),
+            &mut log,
+        )
+        .unwrap();
+        assert_eq!(
+            log,
+            "19700101-00:00:01.000 : 8=FIX.4.4\x0135=D\x0134=1\x0111=O1\x0155=AAPL\x0138=100\x01\n"
+        );
+
+        let reader =
+            SerializedFileReader::new(File::open(dir.join(&export.files[2])).unwrap()).unwrap();
+        let metadata = reader.metadata();
+        assert_eq!(metadata.file_metadata().num_rows(), 2);
+        let columns = metadata.file_metadata().schema_descr().num_columns();
+        assert_eq!(columns, 6 + FIELD_COLUMNS.len());
+
+        // Re-exporting a day replaces it.
+        config.write(0, &messages[..1]).unwrap();
+        assert!(!dir.join("19700101/ALICE-_BROKER_B.log.gz").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
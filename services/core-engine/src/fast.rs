@@ -0,0 +1,1354 @@
+//! FAST (FIX Adapted for STreaming) 1.1 decoder.
+//!
+//! Templates are loaded from FAST XML template files at startup from
+//! `FIX_FAST_TEMPLATES` or at runtime through the API. A field's `id` is its
+//! FIX tag and a sequence's `<length>` id is its NoXXX count tag, so each
+//! decoded message is normalized into a framed tag=value message. Fields
+//! without an `id` are decoded (to keep the operator state right) but dropped.
+//!
+//! Supported: all field types and operators, static template references,
+//! and global and template dictionaries. Dynamic template references are not.
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    audit::{self, Actor, AuditAction},
+    fix_json::{self, OutputEncoding},
+    message::{Field, FixMessage, SOH},
+    parse_raw, sbe,
+    structure::{HEADER_TAGS, TRAILER_TAGS},
+    AppState, ParseResponse,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InputFormat {
+    /// A single tag=value message.
+    #[default]
+    TagValue,
+    /// A capture of FAST-encoded messages.
+    Fast,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FastError(String);
+
+impl fmt::Display for FastError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn err<T>(msg: impl Into<String>) -> Result<T, FastError> {
+    Err(FastError(msg.into()))
+}
+
+// ── Templates ─────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    UInt(u64),
+    Int(i64),
+    Bytes(Vec<u8>),
+    Decimal(i32, i64),
+}
+
+impl Value {
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Value::UInt(v) => v.to_string().into_bytes(),
+            Value::Int(v) => v.to_string().into_bytes(),
+            Value::Bytes(b) => b.clone(),
+            Value::Decimal(e, m) => sbe::format_decimal((*m).into(), *e).into_bytes(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScalarType {
+    UInt32,
+    Int32,
+    UInt64,
+    Int64,
+    Ascii,
+    Unicode,
+    ByteVector,
+    Decimal,
+}
+
+impl ScalarType {
+    fn zero(self) -> Value {
+        match self {
+            ScalarType::UInt32 | ScalarType::UInt64 => Value::UInt(0),
+            ScalarType::Int32 | ScalarType::Int64 => Value::Int(0),
+            ScalarType::Ascii | ScalarType::Unicode | ScalarType::ByteVector => {
+                Value::Bytes(Vec::new())
+            }
+            ScalarType::Decimal => Value::Decimal(0, 0),
+        }
+    }
+
+    fn parse(self, text: &str) -> Result<Value, FastError> {
+        let bad = || FastError(format!("invalid initial value {text:?}"));
+        Ok(match self {
+            ScalarType::UInt32 | ScalarType::UInt64 => {
+                Value::UInt(text.trim().parse().map_err(|_| bad())?)
+            }
+            ScalarType::Int32 | ScalarType::Int64 => {
+                Value::Int(text.trim().parse().map_err(|_| bad())?)
+            }
+            ScalarType::Ascii | ScalarType::Unicode | ScalarType::ByteVector => {
+                Value::Bytes(text.as_bytes().to_vec())
+            }
+            ScalarType::Decimal => {
+                let text = text.trim();
+                let (int, frac) = text.split_once('.').unwrap_or((text, ""));
+                let mantissa = format!("{int}{frac}").parse().map_err(|_| bad())?;
+                Value::Decimal(-(frac.len() as i32), mantissa)
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OpKind {
+    None,
+    Constant,
+    Default,
+    Copy,
+    Increment,
+    Delta,
+    Tail,
+}
+
+#[derive(Debug, Clone)]
+struct Operator {
+    kind: OpKind,
+    initial: Option<Value>,
+    /// Dictionary entry holding the previous value.
+    key: String,
+}
+
+impl Operator {
+    fn uses_pmap_bit(&self, optional: bool) -> bool {
+        match self.kind {
+            OpKind::None | OpKind::Delta => false,
+            OpKind::Constant => optional,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Scalar {
+    name: String,
+    tag: Option<u32>,
+    ty: ScalarType,
+    optional: bool,
+    op: Operator,
+}
+
+#[derive(Debug, Clone)]
+enum Instruction {
+    Scalar(Scalar),
+    /// A decimal with separate exponent and mantissa operators.
+    SplitDecimal {
+        tag: Option<u32>,
+        exponent: Scalar,
+        mantissa: Scalar,
+    },
+    Sequence {
+        length: Scalar,
+        body: Vec<Instruction>,
+    },
+    Group {
+        optional: bool,
+        body: Vec<Instruction>,
+    },
+    TemplateRef(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Template {
+    id: u32,
+    name: String,
+    body: Vec<Instruction>,
+}
+
+fn parse_operator(
+    node: roxmltree::Node,
+    ty: ScalarType,
+    default_key: &str,
+    dictionary: &str,
+) -> Result<Operator, FastError> {
+    let Some(op) = node.children().find(|c| {
+        c.is_element()
+            && matches!(
+                c.tag_name().name(),
+                "constant" | "default" | "copy" | "increment" | "delta" | "tail"
+            )
+    }) else {
+        return Ok(Operator {
+            kind: OpKind::None,
+            initial: None,
+            key: String::new(),
+        });
+    };
+    let kind = match op.tag_name().name() {
+        "constant" => OpKind::Constant,
+        "default" => OpKind::Default,
+        "copy" => OpKind::Copy,
+        "increment" => OpKind::Increment,
+        "delta" => OpKind::Delta,
+        _ => OpKind::Tail,
+    };
+    let initial = op.attribute("value").map(|v| ty.parse(v)).transpose()?;
+    if kind == OpKind::Constant && initial.is_none() {
+        return err(format!("constant {default_key} has no value"));
+    }
+    let dictionary = op.attribute("dictionary").unwrap_or(dictionary);
+    let key = op.attribute("key").unwrap_or(default_key);
+    Ok(Operator {
+        kind,
+        initial,
+        key: format!("{dictionary}/{key}"),
+    })
+}
+
+fn parse_scalar(
+    node: roxmltree::Node,
+    ty: ScalarType,
+    optional: bool,
+    dictionary: &str,
+) -> Result<Scalar, FastError> {
+    let name = node.attribute("name").unwrap_or_default().to_string();
+    let tag = match node.attribute("id") {
+        Some(id) => Some(
+            id.trim()
+                .parse()
+                .map_err(|_| FastError(format!("field {name}: invalid id {id:?}")))?,
+        ),
+        None => None,
+    };
+    let op = parse_operator(node, ty, node.attribute("key").unwrap_or(&name), dictionary)?;
+    Ok(Scalar {
+        name,
+        tag,
+        ty,
+        optional,
+        op,
+    })
+}
+
+fn parse_body(node: roxmltree::Node, dictionary: &str) -> Result<Vec<Instruction>, FastError> {
+    let mut body = Vec::new();
+    for child in node.children().filter(|c| c.is_element()) {
+        let optional = child.attribute("presence") == Some("optional");
+        let ty = match child.tag_name().name() {
+            "uInt32" => ScalarType::UInt32,
+            "int32" => ScalarType::Int32,
+            "uInt64" => ScalarType::UInt64,
+            "int64" => ScalarType::Int64,
+            "string" if child.attribute("charset") == Some("unicode") => ScalarType::Unicode,
+            "string" => ScalarType::Ascii,
+            "byteVector" => ScalarType::ByteVector,
+            "decimal" => {
+                let part = |name: &str| child.children().find(|c| c.has_tag_name(name));
+                if part("exponent").is_none() && part("mantissa").is_none() {
+                    ScalarType::Decimal
+                } else {
+                    let field = parse_scalar(child, ScalarType::Decimal, optional, dictionary)?;
+                    let component = |name: &str, ty, optional| -> Result<Scalar, FastError> {
+                        let key = format!("{}.{name}", field.name);
+                        let op = match part(name) {
+                            Some(p) => parse_operator(p, ty, &key, dictionary)?,
+                            None => parse_operator(child, ty, &key, dictionary)?,
+                        };
+                        Ok(Scalar {
+                            name: key,
+                            tag: None,
+                            ty,
+                            optional,
+                            op,
+                        })
+                    };
+                    body.push(Instruction::SplitDecimal {
+                        tag: field.tag,
+                        exponent: component("exponent", ScalarType::Int32, optional)?,
+                        mantissa: component("mantissa", ScalarType::Int64, false)?,
+                    });
+                    continue;
+                }
+            }
+            "sequence" => {
+                let dictionary = child.attribute("dictionary").unwrap_or(dictionary);
+                let name = child.attribute("name").unwrap_or_default();
+                let length = match child.children().find(|c| c.has_tag_name("length")) {
+                    Some(l) => parse_scalar(l, ScalarType::UInt32, optional, dictionary)?,
+                    None => Scalar {
+                        name: format!("{name}.length"),
+                        tag: None,
+                        ty: ScalarType::UInt32,
+                        optional,
+                        op: Operator {
+                            kind: OpKind::None,
+                            initial: None,
+                            key: String::new(),
+                        },
+                    },
+                };
+                body.push(Instruction::Sequence {
+                    length,
+                    body: parse_body(child, dictionary)?,
+                });
+                continue;
+            }
+            "group" => {
+                let dictionary = child.attribute("dictionary").unwrap_or(dictionary);
+                body.push(Instruction::Group {
+                    optional,
+                    body: parse_body(child, dictionary)?,
+                });
+                continue;
+            }
+            "templateRef" => match child.attribute("name") {
+                Some(name) => {
+                    body.push(Instruction::TemplateRef(name.to_string()));
+                    continue;
+                }
+                None => return err("dynamic template references are not supported"),
+            },
+            // typeRef, length (handled by sequence), operators
+            _ => continue,
+        };
+        body.push(Instruction::Scalar(parse_scalar(
+            child, ty, optional, dictionary,
+        )?));
+    }
+    Ok(body)
+}
+
+/// Parses a `<templates>` document (or a single `<template>`).
+pub fn parse_templates(xml: &str) -> Result<Vec<Template>, FastError> {
+    let doc = roxmltree::Document::parse(xml).map_err(|e| FastError(e.to_string()))?;
+    let root = doc.root_element();
+    let nodes: Vec<_> = match root.tag_name().name() {
+        "templates" => root
+            .children()
+            .filter(|c| c.has_tag_name("template"))
+            .collect(),
+        "template" => vec![root],
+        _ => return err("root element is not templates"),
+    };
+    let default_dictionary = root.attribute("dictionary").unwrap_or("global");
+
+    let mut templates = Vec::new();
+    for node in nodes {
+        let name = node.attribute("name").unwrap_or_default().to_string();
+        let id = node
+            .attribute("id")
+            .and_then(|id| id.trim().parse().ok())
+            .ok_or_else(|| FastError(format!("template {name} has no numeric id")))?;
+        let dictionary = match node.attribute("dictionary").unwrap_or(default_dictionary) {
+            "template" => format!("template:{id}"),
+            "type" | "global" => "global".to_string(),
+            // User-defined dictionaries are named as-is.
+            other => other.to_string(),
+        };
+        let body = parse_body(node, &dictionary)
+            .map_err(|e| FastError(format!("template {name}: {e}")))?;
+        templates.push(Template { id, name, body });
+    }
+    Ok(templates)
+}
+
+// ── Wire ──────────────────────────────────────────────────────────────────────
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    /// Bytes up to and including the next one with the stop bit set.
+    fn stop_bit_bytes(&mut self) -> Result<&[u8], FastError> {
+        let start = self.pos;
+        let len = self.buf[start..]
+            .iter()
+            .position(|b| b & 0x80 != 0)
+            .ok_or_else(|| FastError(format!("unterminated entity at byte {start}")))?;
+        self.pos += len + 1;
+        Ok(&self.buf[start..=start + len])
+    }
+
+    fn uint(&mut self) -> Result<u64, FastError> {
+        let start = self.pos;
+        let bytes = self.stop_bit_bytes()?;
+        if bytes.len() > 10 {
+            return err(format!("integer at byte {start} overflows"));
+        }
+        Ok(bytes
+            .iter()
+            .fold(0u64, |v, b| (v << 7) | u64::from(b & 0x7f)))
+    }
+
+    fn int(&mut self) -> Result<i64, FastError> {
+        let start = self.pos;
+        let bytes = self.stop_bit_bytes()?;
+        if bytes.len() > 10 {
+            return err(format!("integer at byte {start} overflows"));
+        }
+        let init = if bytes[0] & 0x40 != 0 { -1i64 } else { 0 };
+        Ok(bytes
+            .iter()
+            .fold(init, |v, b| (v << 7) | i64::from(b & 0x7f)))
+    }
+
+    /// Nullable unsigned integers are offset by one so zero can mean NULL.
+    fn uint_nullable(&mut self, nullable: bool) -> Result<Option<u64>, FastError> {
+        let v = self.uint()?;
+        Ok(match (nullable, v) {
+            (false, v) => Some(v),
+            (true, 0) => None,
+            (true, v) => Some(v - 1),
+        })
+    }
+
+    fn int_nullable(&mut self, nullable: bool) -> Result<Option<i64>, FastError> {
+        let v = self.int()?;
+        Ok(match (nullable, v) {
+            (false, v) => Some(v),
+            (true, 0) => None,
+            (true, v) if v > 0 => Some(v - 1),
+            (true, v) => Some(v),
+        })
+    }
+
+    fn ascii(&mut self, nullable: bool) -> Result<Option<Vec<u8>>, FastError> {
+        let mut bytes = self.stop_bit_bytes()?.to_vec();
+        if let Some(last) = bytes.last_mut() {
+            *last &= 0x7f;
+        }
+        Ok(match (nullable, bytes.as_slice()) {
+            (true, [0]) => None,
+            (true, [0, 0]) | (false, [0]) => Some(Vec::new()),
+            (false, [0, 0]) => Some(vec![0]),
+            _ => Some(bytes),
+        })
+    }
+
+    fn byte_vector(&mut self, nullable: bool) -> Result<Option<Vec<u8>>, FastError> {
+        let Some(len) = self.uint_nullable(nullable)? else {
+            return Ok(None);
+        };
+        let start = self.pos;
+        let end = usize::try_from(len)
+            .ok()
+            .and_then(|len| start.checked_add(len))
+            .filter(|&end| end <= self.buf.len())
+            .ok_or_else(|| FastError(format!("byte vector at byte {start} is truncated")))?;
+        self.pos = end;
+        Ok(Some(self.buf[start..end].to_vec()))
+    }
+
+    fn value(&mut self, ty: ScalarType, nullable: bool) -> Result<Option<Value>, FastError> {
+        Ok(match ty {
+            ScalarType::UInt32 | ScalarType::UInt64 => {
+                self.uint_nullable(nullable)?.map(Value::UInt)
+            }
+            ScalarType::Int32 | ScalarType::Int64 => self.int_nullable(nullable)?.map(Value::Int),
+            ScalarType::Ascii => self.ascii(nullable)?.map(Value::Bytes),
+            ScalarType::Unicode | ScalarType::ByteVector => {
+                self.byte_vector(nullable)?.map(Value::Bytes)
+            }
+            ScalarType::Decimal => match self.int_nullable(nullable)? {
+                Some(exponent) => Some(Value::Decimal(exponent as i32, self.int()?)),
+                None => None,
+            },
+        })
+    }
+}
+
+struct PresenceMap {
+    bits: Vec<bool>,
+    next: usize,
+}
+
+impl PresenceMap {
+    fn read(r: &mut Reader) -> Result<Self, FastError> {
+        let bits = r
+            .stop_bit_bytes()?
+            .iter()
+            .flat_map(|b| (0..7).rev().map(move |i| b & (1 << i) != 0))
+            .collect();
+        Ok(Self { bits, next: 0 })
+    }
+
+    /// Bits beyond the encoded map are zero.
+    fn next(&mut self) -> bool {
+        let bit = self.bits.get(self.next).copied().unwrap_or(false);
+        self.next += 1;
+        bit
+    }
+}
+
+// ── Decoder ───────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone)]
+enum Previous {
+    Empty,
+    Assigned(Value),
+}
+
+const TEMPLATE_ID_KEY: &str = "global/templateID";
+
+struct Decoder<'a> {
+    templates: &'a FastTemplates,
+    dictionary: HashMap<String, Previous>,
+}
+
+fn add(base: &Value, delta: i64) -> Value {
+    match base {
+        Value::UInt(v) => Value::UInt(v.wrapping_add_signed(delta)),
+        Value::Int(v) => Value::Int(v.wrapping_add(delta)),
+        other => other.clone(),
+    }
+}
+
+impl Decoder<'_> {
+    /// The value to use when the operator takes it from the dictionary
+    /// rather than the stream (copy, increment and tail with the bit clear).
+    fn previous(
+        &mut self,
+        f: &Scalar,
+        next: impl Fn(Value) -> Value,
+    ) -> Result<Option<Value>, FastError> {
+        match self.dictionary.get(&f.op.key) {
+            Some(Previous::Assigned(v)) => {
+                let v = next(v.clone());
+                self.dictionary
+                    .insert(f.op.key.clone(), Previous::Assigned(v.clone()));
+                Ok(Some(v))
+            }
+            Some(Previous::Empty) if f.optional => Ok(None),
+            Some(Previous::Empty) => err(format!("{}: previous value is empty [ERR D6]", f.name)),
+            None => match &f.op.initial {
+                Some(v) => {
+                    self.dictionary
+                        .insert(f.op.key.clone(), Previous::Assigned(v.clone()));
+                    Ok(Some(v.clone()))
+                }
+                None if f.optional => {
+                    self.dictionary.insert(f.op.key.clone(), Previous::Empty);
+                    Ok(None)
+                }
+                None => err(format!("{}: no previous or initial value [ERR D5]", f.name)),
+            },
+        }
+    }
+
+    fn store(&mut self, f: &Scalar, value: &Option<Value>) {
+        let entry = match value {
+            Some(v) => Previous::Assigned(v.clone()),
+            None => Previous::Empty,
+        };
+        self.dictionary.insert(f.op.key.clone(), entry);
+    }
+
+    fn base(&self, f: &Scalar) -> Result<Value, FastError> {
+        match self.dictionary.get(&f.op.key) {
+            Some(Previous::Assigned(v)) => Ok(v.clone()),
+            Some(Previous::Empty) => err(format!("{}: delta base is empty [ERR D6]", f.name)),
+            None => Ok(f.op.initial.clone().unwrap_or_else(|| f.ty.zero())),
+        }
+    }
+
+    fn scalar(
+        &mut self,
+        r: &mut Reader,
+        pmap: &mut PresenceMap,
+        f: &Scalar,
+    ) -> Result<Option<Value>, FastError> {
+        let nullable = f.optional;
+        match f.op.kind {
+            OpKind::None => r.value(f.ty, nullable),
+            OpKind::Constant => Ok((!f.optional || pmap.next())
+                .then(|| f.op.initial.clone())
+                .flatten()),
+            OpKind::Default => {
+                if pmap.next() {
+                    r.value(f.ty, nullable)
+                } else if f.op.initial.is_some() || f.optional {
+                    Ok(f.op.initial.clone())
+                } else {
+                    err(format!("{}: mandatory default has no value", f.name))
+                }
+            }
+            OpKind::Copy => {
+                if pmap.next() {
+                    let v = r.value(f.ty, nullable)?;
+                    self.store(f, &v);
+                    Ok(v)
+                } else {
+                    self.previous(f, |v| v)
+                }
+            }
+            OpKind::Increment => {
+                if pmap.next() {
+                    let v = r.value(f.ty, nullable)?;
+                    self.store(f, &v);
+                    Ok(v)
+                } else {
+                    self.previous(f, |v| add(&v, 1))
+                }
+            }
+            OpKind::Tail => {
+                if !pmap.next() {
+                    return self.previous(f, |v| v);
+                }
+                let Some(Value::Bytes(tail)) = r.value(f.ty, nullable)? else {
+                    self.store(f, &None);
+                    return Ok(None);
+                };
+                let Value::Bytes(mut base) = self.base(f).unwrap_or_else(|_| f.ty.zero()) else {
+                    return err(format!("{}: tail applies to strings only", f.name));
+                };
+                base.truncate(base.len().saturating_sub(tail.len()));
+                base.extend_from_slice(&tail);
+                let v = Some(Value::Bytes(base));
+                self.store(f, &v);
+                Ok(v)
+            }
+            OpKind::Delta => {
+                let v = match f.ty {
+                    ScalarType::UInt32
+                    | ScalarType::Int32
+                    | ScalarType::UInt64
+                    | ScalarType::Int64 => {
+                        let Some(delta) = r.int_nullable(nullable)? else {
+                            return Ok(None);
+                        };
+                        add(&self.base(f)?, delta)
+                    }
+                    ScalarType::Decimal => {
+                        let Some(exp_delta) = r.int_nullable(nullable)? else {
+                            return Ok(None);
+                        };
+                        let mant_delta = r.int()?;
+                        let Value::Decimal(e, m) = self.base(f)? else {
+                            return err(format!("{}: delta base is not a decimal", f.name));
+                        };
+                        Value::Decimal(e + exp_delta as i32, m.wrapping_add(mant_delta))
+                    }
+                    ScalarType::Ascii | ScalarType::Unicode | ScalarType::ByteVector => {
+                        let Some(subtract) = r.int_nullable(nullable)? else {
+                            return Ok(None);
+                        };
+                        let diff = match f.ty {
+                            ScalarType::Ascii => r.ascii(false)?,
+                            _ => r.byte_vector(false)?,
+                        }
+                        .unwrap_or_default();
+                        let Value::Bytes(base) = self.base(f)? else {
+                            return err(format!("{}: delta base is not a string", f.name));
+                        };
+                        // Negative lengths remove from the front, offset by one so -0 is expressible.
+                        let remove = if subtract < 0 {
+                            -(subtract + 1)
+                        } else {
+                            subtract
+                        } as usize;
+                        if remove > base.len() {
+                            return err(format!(
+                                "{}: subtraction length exceeds base [ERR D7]",
+                                f.name
+                            ));
+                        }
+                        if subtract < 0 {
+                            let mut v = diff;
+                            v.extend_from_slice(&base[remove..]);
+                            Value::Bytes(v)
+                        } else {
+                            let mut v = base[..base.len() - remove].to_vec();
+                            v.extend_from_slice(&diff);
+                            Value::Bytes(v)
+                        }
+                    }
+                };
+                let v = Some(v);
+                self.store(f, &v);
+                Ok(v)
+            }
+        }
+    }
+
+    fn needs_pmap(&self, body: &[Instruction]) -> bool {
+        body.iter().any(|i| match i {
+            Instruction::Scalar(f) => f.op.uses_pmap_bit(f.optional),
+            Instruction::SplitDecimal {
+                exponent, mantissa, ..
+            } => exponent.op.uses_pmap_bit(exponent.optional) || mantissa.op.uses_pmap_bit(false),
+            Instruction::Sequence { length, .. } => length.op.uses_pmap_bit(length.optional),
+            Instruction::Group { optional, .. } => *optional,
+            Instruction::TemplateRef(name) => self
+                .templates
+                .by_name(name)
+                .is_some_and(|t| self.needs_pmap(&t.body)),
+        })
+    }
+
+    fn body(
+        &mut self,
+        r: &mut Reader,
+        pmap: &mut PresenceMap,
+        body: &[Instruction],
+        out: &mut Vec<Field>,
+    ) -> Result<(), FastError> {
+        let emit = |tag: Option<u32>, value: &Value, out: &mut Vec<Field>| {
+            if let Some(tag) = tag {
+                out.push(Field {
+                    tag,
+                    value: value.to_bytes(),
+                });
+            }
+        };
+        for instruction in body {
+            match instruction {
+                Instruction::Scalar(f) => {
+                    if let Some(v) = self.scalar(r, pmap, f)? {
+                        emit(f.tag, &v, out);
+                    }
+                }
+                Instruction::SplitDecimal {
+                    tag,
+                    exponent,
+                    mantissa,
+                } => {
+                    let Some(Value::Int(e)) = self.scalar(r, pmap, exponent)? else {
+                        continue;
+                    };
+                    let Some(Value::Int(m)) = self.scalar(r, pmap, mantissa)? else {
+                        return err(format!("{}: mantissa missing", mantissa.name));
+                    };
+                    emit(*tag, &Value::Decimal(e as i32, m), out);
+                }
+                Instruction::Sequence { length, body } => {
+                    let Some(Value::UInt(count)) = self.scalar(r, pmap, length)? else {
+                        continue;
+                    };
+                    emit(length.tag, &Value::UInt(count), out);
+                    let own_pmap = self.needs_pmap(body);
+                    for _ in 0..count {
+                        if own_pmap {
+                            let mut element_pmap = PresenceMap::read(r)?;
+                            self.body(r, &mut element_pmap, body, out)?;
+                        } else {
+                            self.body(r, pmap, body, out)?;
+                        }
+                    }
+                }
+                Instruction::Group { optional, body } => {
+                    if *optional && !pmap.next() {
+                        continue;
+                    }
+                    if self.needs_pmap(body) {
+                        let mut group_pmap = PresenceMap::read(r)?;
+                        self.body(r, &mut group_pmap, body, out)?;
+                    } else {
+                        self.body(r, pmap, body, out)?;
+                    }
+                }
+                Instruction::TemplateRef(name) => {
+                    let templates = self.templates;
+                    let template = templates
+                        .by_name(name)
+                        .ok_or_else(|| FastError(format!("unknown template {name} [ERR D8]")))?;
+                    self.body(r, pmap, &template.body, out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn message(&mut self, r: &mut Reader) -> Result<(String, Vec<Field>), FastError> {
+        let mut pmap = PresenceMap::read(r)?;
+        let template_id = if pmap.next() {
+            let id = r.uint()?;
+            self.dictionary.insert(
+                TEMPLATE_ID_KEY.to_string(),
+                Previous::Assigned(Value::UInt(id)),
+            );
+            id
+        } else {
+            match self.dictionary.get(TEMPLATE_ID_KEY) {
+                Some(Previous::Assigned(Value::UInt(id))) => *id,
+                _ => return err("first message has no template id [ERR D5]"),
+            }
+        };
+        let templates = self.templates;
+        let template = u32::try_from(template_id)
+            .ok()
+            .and_then(|id| templates.templates.get(&id))
+            .ok_or_else(|| FastError(format!("unknown template id {template_id} [ERR D9]")))?;
+        let mut fields = Vec::new();
+        self.body(r, &mut pmap, &template.body, &mut fields)?;
+        Ok((template.name.clone(), fields))
+    }
+}
+
+/// Orders decoded fields as a tag=value message and frames it. BeginString
+/// comes from the template if it carries one, else FIXT.1.1 when
+/// ApplVerID(1128) is present, else FIX.4.4.
+fn normalize(fields: Vec<Field>) -> FixMessage {
+    let begin_string = fields
+        .iter()
+        .find(|f| f.tag == 8)
+        .map(|f| f.value_str().into_owned())
+        .unwrap_or_else(|| {
+            if fields.iter().any(|f| f.tag == 1128) {
+                "FIXT.1.1".to_string()
+            } else {
+                "FIX.4.4".to_string()
+            }
+        });
+    let (mut header, body): (Vec<Field>, Vec<Field>) = fields
+        .into_iter()
+        .filter(|f| !matches!(f.tag, 8 | 9) && !TRAILER_TAGS.contains(&f.tag))
+        .partition(|f| HEADER_TAGS.contains(&f.tag));
+    header.sort_by_key(|f| f.tag != 35);
+    header.extend(body);
+    FixMessage::framed(&begin_string, header)
+}
+
+/// Decodes every message in a capture, skipping `preamble` bytes (e.g. a
+/// packet sequence number) before each one.
+pub fn decode_stream(
+    templates: &FastTemplates,
+    bytes: &[u8],
+    preamble: usize,
+) -> Result<Vec<(String, FixMessage)>, FastError> {
+    let mut decoder = Decoder {
+        templates,
+        dictionary: HashMap::new(),
+    };
+    let mut r = Reader { buf: bytes, pos: 0 };
+    let mut messages = Vec::new();
+    while r.pos < bytes.len() {
+        r.pos += preamble;
+        if r.pos >= bytes.len() {
+            break;
+        }
+        let start = r.pos;
+        let (name, fields) = decoder
+            .message(&mut r)
+            .map_err(|e| FastError(format!("message at byte {start}: {e}")))?;
+        messages.push((name, normalize(fields)));
+    }
+    Ok(messages)
+}
+
+// ── Registry ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Default)]
+pub struct FastTemplates {
+    templates: HashMap<u32, Template>,
+}
+
+impl FastTemplates {
+    /// Loads the template files listed in `FIX_FAST_TEMPLATES` (comma-separated paths).
+    pub fn from_env() -> Self {
+        let mut templates = Self::default();
+        let Ok(paths) = std::env::var("FIX_FAST_TEMPLATES") else {
+            return templates;
+        };
+        for path in paths.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match std::fs::read_to_string(path)
+                .map_err(|e| FastError(e.to_string()))
+                .and_then(|xml| parse_templates(&xml))
+            {
+                Ok(loaded) => {
+                    info!(path, templates = loaded.len(), "FAST templates loaded");
+                    templates.add(loaded);
+                }
+                Err(e) => warn!(path, error = %e, "Failed to load FAST templates"),
+            }
+        }
+        templates
+    }
+
+    /// Adds templates, replacing any with the same id.
+    pub fn add(&mut self, templates: Vec<Template>) {
+        for t in templates {
+            self.templates.insert(t.id, t);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.templates.len()
+    }
+
+    fn by_name(&self, name: &str) -> Option<&Template> {
+        self.templates.values().find(|t| t.name == name)
+    }
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TemplateSummary {
+    id: u32,
+    name: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TemplatesResponse {
+    templates: Vec<TemplateSummary>,
+    count: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct ParsedCapture {
+    messages: Vec<serde_json::Value>,
+    count: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/fix/fast/templates",
+    tag = "fix",
+    responses(
+        (status = 200, description = "OK", body = TemplatesResponse),
+    )
+)]
+pub async fn list_templates(State(state): State<AppState>) -> Json<TemplatesResponse> {
+    let mut templates: Vec<TemplateSummary> = state
+        .fast
+        .lock()
+        .await
+        .templates
+        .values()
+        .map(|t| TemplateSummary {
+            id: t.id,
+            name: t.name.clone(),
+        })
+        .collect();
+    templates.sort_by_key(|t| t.id);
+    let count = templates.len();
+    Json(TemplatesResponse { templates, count })
+}
+
+/// Loads templates from the XML request body.
+#[utoipa::path(
+    post,
+    path = "/api/v1/fix/fast/templates",
+    tag = "fix",
+    request_body(content = String, content_type = "application/xml"),
+    responses(
+        (status = 200, description = "Loaded templates", body = TemplatesResponse),
+        (status = 400, description = "Invalid templates"),
+    )
+)]
+pub async fn load_templates(
+    State(state): State<AppState>,
+    actor: Actor,
+    body: String,
+) -> Result<Json<TemplatesResponse>, StatusCode> {
+    let loaded = parse_templates(&body).map_err(|e| {
+        info!(error = %e, "FAST templates rejected");
+        StatusCode::BAD_REQUEST
+    })?;
+    let templates: Vec<TemplateSummary> = loaded
+        .iter()
+        .map(|t| TemplateSummary {
+            id: t.id,
+            name: t.name.clone(),
+        })
+        .collect();
+    state.fast.lock().await.add(loaded);
+    let count = templates.len();
+    let ids: Vec<String> = templates.iter().map(|t| t.id.to_string()).collect();
+    audit::record(
+        &state,
+        &actor,
+        AuditAction::FastTemplatesLoaded,
+        None,
+        format!("templates {}", ids.join(",")),
+    )
+    .await;
+    Ok(Json(TemplatesResponse { templates, count }))
+}
+
+/// `/parse` for `format=fast`: every message in the capture, each parsed as
+/// its normalized tag=value form.
+pub async fn parse_capture(
+    state: &AppState,
+    bytes: &[u8],
+    preamble: usize,
+    encoding: OutputEncoding,
+) -> Result<Response, StatusCode> {
+    let decoded = {
+        let templates = state.fast.lock().await;
+        decode_stream(&templates, bytes, preamble).map_err(|e| {
+            info!(error = %e, "FAST capture rejected");
+            StatusCode::UNPROCESSABLE_ENTITY
+        })?
+    };
+
+    let messages: Vec<ParseResponse> = decoded
+        .iter()
+        .map(|(_, msg)| parse_raw(&msg.encode_with(SOH)))
+        .collect();
+    info!(
+        messages = messages.len(),
+        raw_length = bytes.len(),
+        "FAST capture parsed"
+    );
+
+    let messages: Vec<serde_json::Value> = messages
+        .iter()
+        .map(|m| match encoding {
+            OutputEncoding::Native => serde_json::to_value(m).unwrap_or_default(),
+            OutputEncoding::FixJson => fix_json::encode(&m.fields),
+        })
+        .collect();
+    let count = messages.len();
+    Ok(Json(ParsedCapture { messages, count }).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATES: &str = r#"<templates xmlns="http://www.fixprotocol.org/ns/fast/td/1.1">
+  <template name="MDIncRefresh" id="1">
+    <string name="ApplVerID" id="1128"><constant value="9"/></string>
+    <string name="MessageType" id="35"><constant value="X"/></string>
+    <uInt32 name="MsgSeqNum" id="34"><increment/></uInt32>
+    <uInt64 name="SendingTime" id="52"><delta/></uInt64>
+    <sequence name="MDEntries">
+      <length name="NoMDEntries" id="268"/>
+      <uInt32 name="MDUpdateAction" id="279"><copy value="0"/></uInt32>
+      <string name="Symbol" id="55"><copy/></string>
+      <decimal name="MDEntryPx" id="270" presence="optional"><delta/></decimal>
+      <int32 name="MDEntrySize" id="271" presence="optional"><default/></int32>
+    </sequence>
+  </template>
+</templates>"#;
+
+    /// Every operator in a template dictionary, with a static reference to a
+    /// shared header, an optional group and a split decimal.
+    const OPERATORS: &str = r#"<templates xmlns="http://www.fixprotocol.org/ns/fast/td/1.1" dictionary="template">
+  <template name="Header" id="90">
+    <uInt32 name="MsgSeqNum" id="34"><increment/></uInt32>
+  </template>
+  <template name="SecurityStatus" id="2">
+    <string name="MessageType" id="35"><constant value="f"/></string>
+    <templateRef name="Header"/>
+    <string name="Symbol" id="55"><delta/></string>
+    <string name="SecurityID" id="48"><tail/></string>
+    <uInt32 name="SecurityTradingStatus" id="326"><default value="17"/></uInt32>
+    <decimal name="HighPx" id="332">
+      <exponent><copy value="-2"/></exponent>
+      <mantissa><delta/></mantissa>
+    </decimal>
+    <string name="Text" id="58" presence="optional"><copy/></string>
+    <group name="Instrument" presence="optional">
+      <string name="SecurityExchange" id="207"><copy/></string>
+    </group>
+    <byteVector name="RawData" id="96" presence="optional"/>
+  </template>
+  <template name="Snapshot" id="3" dictionary="md">
+    <uInt32 name="RptSeq" id="83"><increment key="seq"/></uInt32>
+    <sequence name="MDEntries" dictionary="global">
+      <length name="NoMDEntries" id="268"><copy/></length>
+      <int64 name="MDEntrySize" id="271"/>
+    </sequence>
+  </template>
+</templates>"#;
+
+    /// SecurityStatus with every field on the wire: pmap bits for the
+    /// template id, MsgSeqNum, SecurityID, SecurityTradingStatus, the
+    /// exponent, Text and the group.
+    const FULL: &[u8] = &[
+        0xff, 0x82, // pmap, template 2
+        0xe4, // MsgSeqNum 100
+        0x80, 0x56, 0x4f, 0x44, 0x2e, 0xcc, // Symbol: remove 0, append "VOD.L"
+        0x47, 0x42, 0x30, 0x30, 0x30, 0xb1, // SecurityID tail "GB0001"
+        0x82, // SecurityTradingStatus 2
+        0xfe, 0x27, 0xa1, // HighPx exponent -2, mantissa +5025
+        0x48, 0x41, 0x4c, 0xd4, // Text "HALT"
+        0xc0, 0x58, 0x4c, 0x4f, 0xce, // group pmap, SecurityExchange "XLON"
+        0x83, 0x61, 0x62, // RawData "ab"
+    ];
+
+    /// The next SecurityStatus, mostly from the dictionary: only the
+    /// SecurityID tail bit is set.
+    const NEXT: &[u8] = &[
+        0x90, // pmap
+        0xfc, 0x42, 0xd0, // Symbol: remove 3 from the front, prepend "BP"
+        0xb2, // SecurityID tail "2"
+        0x99, // HighPx mantissa +25
+        0x80, // RawData NULL
+    ];
+
+    fn templates(xml: &str) -> FastTemplates {
+        let mut templates = FastTemplates::default();
+        templates.add(parse_templates(xml).unwrap());
+        templates
+    }
+
+    fn decode_error(xml: &str, bytes: &[u8]) -> String {
+        decode_stream(&templates(xml), bytes, 0)
+            .unwrap_err()
+            .to_string()
+    }
+
+    fn display(msg: &FixMessage) -> String {
+        String::from_utf8(msg.encode_with(b'|')).unwrap()
+    }
+
+    #[test]
+    fn decodes_with_operator_state_across_messages() {
+        let mut templates = FastTemplates::default();
+        templates.add(parse_templates(TEMPLATES).unwrap());
+
+        let bytes = [
+            // message 1: pmap (template id, MsgSeqNum), template 1, seq 7, time +1000
+            0xe0, 0x81, 0x87, 0x07, 0xe8, // 2 entries
+            0x82, // entry 1: pmap (action, symbol, size), action 1, "ES"
+            0xf0, 0x81, 0x45, 0xd3, // price: exponent -2, mantissa +450025; size 10
+            0xfe, 0x1b, 0x3b, 0xe9, 0x8b, // entry 2: empty pmap, price mantissa +25
+            0x80, 0x81,
+            0x99, // message 2: empty pmap, seq 8 by increment, time +5, no entries
+            0x80, 0x85, 0x80,
+        ];
+
+        let messages = decode_stream(&templates, &bytes, 0).unwrap();
+        assert_eq!(messages.len(), 2);
+        let first = display(&messages[0].1);
+        assert!(first.starts_with("8=FIXT.1.1|"), "{first}");
+        assert!(
+            first.contains("|35=X|1128=9|34=7|52=1000|268=2|279=1|55=ES|270=4500.25|271=10|279=1|55=ES|270=4500.5|"),
+            "{first}"
+        );
+        let second = display(&messages[1].1);
+        assert!(
+            second.contains("|35=X|1128=9|34=8|52=1005|268=0|10="),
+            "{second}"
+        );
+    }
+
+    #[test]
+    fn decodes_stop_bit_integers_and_strings() {
+        let mut r = Reader {
+            buf: &[0x39, 0x45, 0xa3, 0x7f, 0xfe, 0x80, 0x00, 0x80, 0x41, 0xc2],
+            pos: 0,
+        };
+        assert_eq!(r.uint().unwrap(), 942755);
+        assert_eq!(r.int().unwrap(), -2);
+        assert_eq!(r.ascii(true).unwrap(), None);
+        assert_eq!(r.ascii(true).unwrap(), Some(Vec::new()));
+        assert_eq!(r.ascii(false).unwrap(), Some(b"AB".to_vec()));
+    }
+
+    #[test]
+    fn templates_resolve_dictionaries_and_keys() {
+        fn keys(body: &[Instruction], out: &mut Vec<String>) {
+            for instruction in body {
+                match instruction {
+                    Instruction::Scalar(f) => out.push(f.op.key.clone()),
+                    Instruction::SplitDecimal {
+                        exponent, mantissa, ..
+                    } => out.extend([exponent.op.key.clone(), mantissa.op.key.clone()]),
+                    Instruction::Sequence { length, body } => {
+                        out.push(length.op.key.clone());
+                        keys(body, out);
+                    }
+                    Instruction::Group { body, .. } => keys(body, out),
+                    Instruction::TemplateRef(name) => out.push(format!("ref {name}")),
+                }
+            }
+            out.retain(|k| !k.is_empty());
+        }
+
+        let parsed = parse_templates(OPERATORS).unwrap();
+        let by_id = |id| {
+            let mut out = Vec::new();
+            keys(&parsed.iter().find(|t| t.id == id).unwrap().body, &mut out);
+            out
+        };
+        assert_eq!(by_id(90), ["template:90/MsgSeqNum"]);
+        assert_eq!(
+            by_id(2),
+            [
+                "template:2/MessageType",
+                "ref Header",
+                "template:2/Symbol",
+                "template:2/SecurityID",
+                "template:2/SecurityTradingStatus",
+                "template:2/HighPx.exponent",
+                "template:2/HighPx.mantissa",
+                "template:2/Text",
+                "template:2/SecurityExchange",
+            ]
+        );
+        assert_eq!(by_id(3), ["md/seq", "global/NoMDEntries"]);
+
+        let single =
+            parse_templates(r#"<template name="T" id="7"><uInt32 name="A" id="1"/></template>"#)
+                .unwrap();
+        assert_eq!((single.len(), single[0].id), (1, 7));
+        let mut templates = templates(OPERATORS);
+        templates.add(single);
+        assert_eq!(templates.len(), 4);
+    }
+
+    #[test]
+    fn bad_templates_are_rejected() {
+        let error = |body: &str| {
+            parse_templates(&format!(r#"<template name="T" id="1">{body}</template>"#))
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(parse_templates("<templates>").is_err());
+        assert_eq!(
+            parse_templates("<schema/>").unwrap_err().to_string(),
+            "root element is not templates"
+        );
+        assert_eq!(
+            parse_templates(r#"<templates><template name="T" id="one"/></templates>"#)
+                .unwrap_err()
+                .to_string(),
+            "template T has no numeric id"
+        );
+        assert_eq!(
+            error(r#"<uInt32 name="A"><constant/></uInt32>"#),
+            "template T: constant A has no value"
+        );
+        assert_eq!(
+            error(r#"<uInt32 name="A" id="x"/>"#),
+            "template T: field A: invalid id \"x\""
+        );
+        assert_eq!(
+            error(r#"<uInt32 name="A"><default value="-1"/></uInt32>"#),
+            "template T: invalid initial value \"-1\""
+        );
+        assert_eq!(
+            error("<templateRef/>"),
+            "template T: dynamic template references are not supported"
+        );
+    }
+
+    #[test]
+    fn presence_map_bits_are_read_in_order_and_default_to_zero() {
+        let mut r = Reader {
+            buf: &[0x15, 0xc0, 0xff],
+            pos: 0,
+        };
+        let mut pmap = PresenceMap::read(&mut r).unwrap();
+        assert_eq!(r.pos, 2);
+        let bits: Vec<bool> = (0..16).map(|_| pmap.next()).collect();
+        let set: Vec<usize> = (0..16).filter(|&i| bits[i]).collect();
+        assert_eq!(set, [2, 4, 6, 7]);
+    }
+
+    #[test]
+    fn operators_carry_state_between_messages() {
+        let templates = templates(OPERATORS);
+        let messages = decode_stream(&templates, &[FULL, NEXT].concat(), 0).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].0, "SecurityStatus");
+
+        let first = display(&messages[0].1);
+        assert!(first.starts_with("8=FIX.4.4|"), "{first}");
+        assert!(
+            first.contains("|35=f|34=100|55=VOD.L|48=GB0001|326=2|332=50.25|58=HALT|207=XLON|"),
+            "{first}"
+        );
+        assert!(first.contains("96=ab|"), "{first}");
+
+        // Increment, tail, default, copy and both delta forms, with the
+        // optional group and byte vector absent.
+        let second = display(&messages[1].1);
+        assert!(
+            second.contains("|35=f|34=101|55=BP.L|48=GB0002|326=17|332=50.5|58=HALT|10="),
+            "{second}"
+        );
+    }
+
+    #[test]
+    fn preambles_are_skipped_before_each_message() {
+        let bytes = [&[0, 0, 0, 1], FULL, &[0, 0, 0, 2], NEXT].concat();
+        let messages = decode_stream(&templates(OPERATORS), &bytes, 4).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(display(&messages[1].1).contains("|34=101|"));
+    }
+
+    #[test]
+    fn malformed_input_is_reported_with_its_offset() {
+        assert_eq!(
+            decode_error(OPERATORS, &[0xc0, 0x02]),
+            "message at byte 0: unterminated entity at byte 1"
+        );
+        assert_eq!(
+            decode_error(OPERATORS, &[0xc0, 0x85]),
+            "message at byte 0: unknown template id 5 [ERR D9]"
+        );
+        assert_eq!(
+            decode_error(OPERATORS, &[0x80]),
+            "message at byte 0: first message has no template id [ERR D5]"
+        );
+        // The group's mandatory copy field has nothing to copy.
+        assert_eq!(
+            decode_error(OPERATORS, &[0xf1, 0x82, 0x81, 0x80, 0x80, 0xc1, 0x80, 0x80]),
+            "message at byte 0: SecurityExchange: no previous or initial value [ERR D5]"
+        );
+        // Removing 9 bytes from "BP.L".
+        let bytes = [FULL, NEXT, &[0x80, 0x89, 0x80]].concat();
+        assert_eq!(
+            decode_error(OPERATORS, &bytes),
+            format!(
+                "message at byte {}: Symbol: subtraction length exceeds base [ERR D7]",
+                FULL.len() + NEXT.len()
+            )
+        );
+        assert_eq!(
+            decode_error(
+                r#"<template name="T" id="1"><templateRef name="Missing"/></template>"#,
+                &[0xc0, 0x81]
+            ),
+            "message at byte 0: unknown template Missing [ERR D8]"
+        );
+        for len in 1..FULL.len() {
+            assert!(
+                decode_stream(&templates(OPERATORS), &FULL[..len], 0).is_err(),
+                "decoded the first {len} bytes"
+            );
+        }
+
+        let mut r = Reader {
+            buf: &[0x85, 0x61],
+            pos: 0,
+        };
+        assert_eq!(
+            r.byte_vector(false).unwrap_err().to_string(),
+            "byte vector at byte 1 is truncated"
+        );
+        let mut r = Reader {
+            buf: &[[0x01; 10].as_slice(), &[0x81]].concat(),
+            pos: 0,
+        };
+        assert_eq!(
+            r.uint().unwrap_err().to_string(),
+            "integer at byte 0 overflows"
+        );
+    }
+}
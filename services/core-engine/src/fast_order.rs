@@ -0,0 +1,447 @@
+//! Low-latency order entry: `POST /api/v1/orders/fast`.
+//!
+//! Sends a NewOrderSingle (35=D) for desks that count microseconds. The body
+//! is a compact JSON order of fixed shape or, with `Content-Type:
+//! application/octet-stream`, the order's fields already encoded. Strings
+//! in the JSON are borrowed from the request body, so they must be sent
+//! without escapes. The session's task keeps the session's header encoded
+//! in a [`Template`], built on the first fast order and again only when the
+//! session's configuration changes, and writes the order behind it into the
+//! session's encode buffer. Once that buffer has grown to fit, parsing and
+//! encoding the order allocate nothing; what follows does, as on `/send`.
+//!
+//! The order is checked against the session's security definitions, which
+//! fill in its SecurityID as on `/send`; only that allocates, for the
+//! SecurityID. It skips templates, venue metadata and validation, and an
+//! order for a target federated upstream answers 422 rather than being
+//! forwarded. Everything after encoding is as on `/send`: the order
+//! takes its MsgSeqNum through the journal, is stored before it is written,
+//! so with `FIX_STORE_DIR` set it waits for the group commit, and is tracked
+//! as an order, with its expiry and for cancel-on-disconnect. A session that
+//! is not logged on, or still has queued messages to send first, answers 409
+//! instead of queueing the order, so orders keep the session's order.
+
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
+    response::Json,
+};
+use bytes::BytesMut;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::{info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    allocate_seq_num,
+    attribution::Attribution,
+    auth::Caller,
+    clock::epoch_ms,
+    encode::{Header, Part, SendingTime, Template},
+    expiry::SessionRef,
+    journal_seq_num,
+    latency::{self, Stage},
+    lookup_field,
+    message::SOH,
+    order_entry::{OrdType, Side, TimeInForce},
+    orders::OrderContext,
+    parse_raw,
+    problem::ApiError,
+    securities::OrderTerms,
+    store, track_new_order, unsend_seq_num, AppState,
+};
+
+/// Parts of an order's body: the JSON order's fields each take one, and a
+/// SecurityID and SecurityIDSource filled in take the last two.
+const PARTS: usize = 9;
+
+/// Tags of the header and trailer, which the template writes.
+const HEADER_TAGS: [u32; 8] = [8, 9, 35, 49, 56, 34, 52, 10];
+
+/// Tags an encoded body must have: ClOrdID, Symbol, Side, OrderQty, OrdType.
+const REQUIRED_TAGS: [u32; 5] = [11, 55, 54, 38, 40];
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FastQuery {
+    /// `SENDER->TARGET`.
+    session_id: String,
+}
+
+/// The JSON order.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FastOrder<'a> {
+    cl_ord_id: &'a str,
+    symbol: &'a str,
+    side: Side,
+    /// Whole units.
+    qty: u64,
+    /// `MARKET` or `LIMIT`.
+    ord_type: OrdType,
+    /// A decimal, written as given; for `LIMIT` orders only.
+    #[serde(borrow)]
+    price: Option<&'a str>,
+    #[serde(default)]
+    time_in_force: TimeInForce,
+}
+
+impl<'a> FastOrder<'a> {
+    /// The order's body, or why the fast path does not send it.
+    fn parts(&self) -> Result<[Part<'a>; PARTS], &'static str> {
+        if self.cl_ord_id.is_empty() || self.symbol.is_empty() {
+            return Err("cl_ord_id and symbol must not be empty");
+        }
+        if self.qty == 0 {
+            return Err("qty must be positive");
+        }
+        if !matches!(self.ord_type, OrdType::Market | OrdType::Limit) {
+            return Err("only MARKET and LIMIT orders are sent on the fast path");
+        }
+        if self.ord_type.has_price() != self.price.is_some() {
+            return Err("price is required for LIMIT orders, and only for those");
+        }
+        if self.price.is_some_and(|p| !is_decimal(p)) {
+            return Err("price must be a decimal");
+        }
+        if self.time_in_force == TimeInForce::GoodTillDate {
+            return Err("GOOD_TILL_DATE orders are not sent on the fast path");
+        }
+        Ok([
+            Part::Field(11, self.cl_ord_id.as_bytes()),
+            Part::Field(55, self.symbol.as_bytes()),
+            Part::Field(54, self.side.code().as_bytes()),
+            Part::Number(38, self.qty),
+            Part::Field(40, self.ord_type.code().as_bytes()),
+            self.price
+                .map_or(Part::Encoded(b""), |p| Part::Field(44, p.as_bytes())),
+            Part::Field(59, self.time_in_force.code().as_bytes()),
+            Part::Encoded(b""),
+            Part::Encoded(b""),
+        ])
+    }
+
+    fn terms(&self) -> OrderTerms<'a> {
+        OrderTerms {
+            symbol: self.symbol,
+            price: self.price,
+            stop_px: None,
+            order_qty: Some(self.qty as f64),
+        }
+    }
+}
+
+fn is_decimal(s: &str) -> bool {
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, "0"));
+    let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    digits(whole) && digits(fraction)
+}
+
+/// Checks an encoded body: whole `tag=value` fields, each ending in SOH,
+/// with the fields a NewOrderSingle needs and none of the header's.
+fn check_encoded(body: &[u8]) -> Result<(), &'static str> {
+    let Some(fields) = body.strip_suffix(&[SOH]) else {
+        return Err("the body must be fields each ending in SOH");
+    };
+    let mut missing = REQUIRED_TAGS.len();
+    let mut seen = [false; REQUIRED_TAGS.len()];
+    for field in fields.split(|&b| b == SOH) {
+        let tag = field
+            .iter()
+            .position(|&b| b == b'=')
+            .and_then(|eq| std::str::from_utf8(&field[..eq]).ok())
+            .and_then(|tag| tag.parse::<u32>().ok())
+            .ok_or("the body must be fields each ending in SOH")?;
+        if HEADER_TAGS.contains(&tag) {
+            return Err("header and trailer fields are written by the gateway");
+        }
+        if let Some(i) = REQUIRED_TAGS.iter().position(|&t| t == tag) {
+            if !std::mem::replace(&mut seen[i], true) {
+                missing -= 1;
+            }
+        }
+    }
+    if missing > 0 {
+        return Err("ClOrdID(11), Symbol(55), Side(54), OrderQty(38) and OrdType(40) are required");
+    }
+    Ok(())
+}
+
+/// The value of `tag` in a body [`check_encoded`] accepted.
+fn encoded_value(body: &[u8], tag: u32) -> Option<&str> {
+    body.split(|&b| b == SOH).find_map(|field| {
+        let eq = field.iter().position(|&b| b == b'=')?;
+        if std::str::from_utf8(&field[..eq]).ok()?.parse::<u32>().ok()? != tag {
+            return None;
+        }
+        std::str::from_utf8(&field[eq + 1..]).ok()
+    })
+}
+
+/// A fast order, as handed to the session's task.
+pub struct FastRequest {
+    body: Bytes,
+    /// The body is encoded fields rather than JSON.
+    encoded: bool,
+    attribution: Attribution,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FastAck {
+    sequence_number: u64,
+    /// From the request being received to the order being handed to the
+    /// session's writer.
+    latency_ns: u64,
+}
+
+/// Sends a NewOrderSingle on the fast path.
+#[utoipa::path(
+    post,
+    path = "/api/v1/orders/fast",
+    tag = "orders",
+    params(FastQuery),
+    request_body(
+        content = String,
+        content_type = "application/json",
+        description = "A fixed-shape order, or with application/octet-stream its encoded fields"
+    ),
+    responses(
+        (status = 200, description = "Sent", body = FastAck),
+        (status = 400, description = "Malformed order"),
+        (status = 404, description = "Unknown session"),
+        (status = 409, description = "The session is not logged on, or has queued messages"),
+        (status = 422, description = "An order the fast path does not send, or refused by its security definition"),
+        (status = 503, description = "The session's outbound channel is full"),
+        (status = 429, description = "The client is over its rate limit; see Retry-After"),
+    )
+)]
+pub async fn place(
+    State(state): State<AppState>,
+    Query(query): Query<FastQuery>,
+    caller: Caller,
+    attribution: Attribution,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<FastAck>, ApiError> {
+    caller.check_session(&query.session_id)?;
+    let encoded = headers
+        .get(CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"application/octet-stream"));
+    let request = FastRequest {
+        body,
+        encoded,
+        attribution,
+    };
+    let ack = state.actors.fast(&state, &query.session_id, request).await?;
+    Ok(Json(ack))
+}
+
+/// Sends a fast order on the session's task, writing it behind the
+/// session's `template` into `buffer`.
+pub async fn send_in_task(
+    state: &AppState,
+    session_id: &str,
+    request: FastRequest,
+    template: &mut Option<Template>,
+    buffer: &mut BytesMut,
+) -> Result<FastAck, ApiError> {
+    let handle = state.sessions.get(session_id).ok_or_else(|| {
+        ApiError::new(StatusCode::NOT_FOUND, "unknown_session")
+            .detail(format!("no session {session_id}"))
+    })?;
+    let session = handle.snapshot();
+    if session.state != "ACTIVE" {
+        return Err(ApiError::new(StatusCode::CONFLICT, "session_not_active")
+            .detail(format!("{session_id} is {}", session.state)));
+    }
+    // Queued messages go first; the fast path does not overtake them.
+    if !state.outbound_queue.lock().await.is_empty_for(session_id) {
+        return Err(ApiError::new(StatusCode::CONFLICT, "messages_queued")
+            .detail(format!("{session_id} has queued messages to send first")));
+    }
+    // A federated target's orders go upstream through `/send`; sent here
+    // they would go out on the local session instead.
+    let target = &session.target_comp_id;
+    if state.federation.lock().await.is_federated(target) {
+        return Err(ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "federated_target")
+            .detail(format!("orders for {target} are forwarded upstream; use /send")));
+    }
+
+    let invalid = |reason: &'static str| {
+        info!(session_id = %session_id, reason, "Fast order rejected");
+        ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_order").detail(reason)
+    };
+    let order: FastOrder;
+    let mut parts = [Part::Encoded(b""); PARTS];
+    let (terms, has_security_id) = if request.encoded {
+        check_encoded(&request.body).map_err(invalid)?;
+        parts[0] = Part::Encoded(&request.body);
+        let body = &request.body[..];
+        let terms = OrderTerms {
+            symbol: encoded_value(body, 55).unwrap_or_default(),
+            price: encoded_value(body, 44),
+            stop_px: encoded_value(body, 99),
+            order_qty: encoded_value(body, 38).and_then(|q| q.parse().ok()),
+        };
+        (terms, encoded_value(body, 48).is_some())
+    } else {
+        order = serde_json::from_slice(&request.body).map_err(|e| {
+            ApiError::new(StatusCode::BAD_REQUEST, "malformed_order").detail(e.to_string())
+        })?;
+        parts = order.parts().map_err(invalid)?;
+        (order.terms(), false)
+    };
+
+    // Checked as `/send` checks a NewOrderSingle, before a MsgSeqNum is taken.
+    let security_id = {
+        let securities = state.securities.lock().await;
+        let definition = securities.check_terms(session_id, &terms).map_err(|e| {
+            info!(session_id = %session_id, error = %e, "Fast order rejected");
+            ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "security_check_failed")
+                .detail(e.to_string())
+        })?;
+        definition
+            .filter(|_| !has_security_id)
+            .and_then(|d| Some((d.security_id.clone()?, d.security_id_source.clone())))
+    };
+    if let Some((id, source)) = &security_id {
+        parts[PARTS - 2] = Part::Field(48, id.as_bytes());
+        if let Some(source) = source {
+            parts[PARTS - 1] = Part::Field(22, source.as_bytes());
+        }
+    }
+
+    let (version, sender, target) = (
+        &session.fix_version,
+        &session.sender_comp_id,
+        &session.target_comp_id,
+    );
+    let template = match template.take() {
+        Some(cached) if cached.is_for(version, "D", sender, target) => template.insert(cached),
+        _ => template.insert(Template::new(version, "D", sender, target)),
+    };
+
+    // Taken before the MsgSeqNum, so a full channel consumes none.
+    let slot = state.outbound.reserve(session_id).await.map_err(|e| {
+        warn!(session_id = %session_id, error = %e, "Fast order rejected");
+        ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "outbound_full").detail(e.to_string())
+    })?;
+    let now_ms = epoch_ms();
+    let seq_num = allocate_seq_num(state, session_id).await?;
+    let sending_time = SendingTime::now(session.timestamp_precision);
+    let header = Header {
+        version,
+        msg_type: "D",
+        sender,
+        target,
+        seq_num,
+        sending_time,
+    };
+    if let Err(status) = journal_seq_num(state, session_id, seq_num, now_ms).await {
+        unsend_seq_num(state, session_id, slot, &header).await;
+        return Err(status.into());
+    }
+    template.write(buffer, seq_num, sending_time, &parts);
+    let attribution = Some(request.attribution);
+    let stored = store::record_sent(state, session_id, "D", Some(seq_num), buffer, attribution);
+    if let Err(status) = stored.await {
+        unsend_seq_num(state, session_id, slot, &header).await;
+        return Err(status.into());
+    }
+
+    // Tracked as `/send` tracks a NewOrderSingle, before it is written.
+    let fields: HashMap<String, String> = parse_raw(buffer)
+        .fields
+        .into_iter()
+        .map(|f| (f.tag.to_string(), f.value))
+        .collect();
+    let metadata = HashMap::new();
+    let cl_ord_id = lookup_field(&fields, "ClOrdID", 11);
+    if let Some(cl_ord_id) = cl_ord_id {
+        state.order_traces.lock().await.record(cl_ord_id);
+    }
+    let order = cl_ord_id.map(|cl_ord_id| {
+        OrderContext::from_fields(cl_ord_id, session_id, &fields, &metadata, now_ms)
+    });
+    let on = SessionRef {
+        fix_version: version,
+        sender,
+        target,
+    };
+    track_new_order(state, on, order, &fields, &metadata, None, now_ms).await;
+
+    let latency = latency::received_at().elapsed();
+    slot.send(buffer.split().freeze());
+
+    state
+        .latency
+        .lock()
+        .await
+        .record(session_id, Stage::Send, latency);
+    info!(session_id = %session_id, sequence_number = seq_num, "Fast order sent");
+    Ok(FastAck {
+        sequence_number: seq_num,
+        latency_ns: u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TimestampPrecision;
+
+    /// The message before its CheckSum(10).
+    fn body_of(out: &BytesMut) -> &[u8] {
+        &out[..out.len() - b"10=000\x01".len()]
+    }
+
+    #[test]
+    fn json_orders_become_their_body() {
+        let json = br#"{"cl_ord_id":"O1","symbol":"AAPL","side":"BUY","qty":100,
+                        "ord_type":"LIMIT","price":"150.25","time_in_force":"IOC"}"#;
+        let order: FastOrder = serde_json::from_slice(json).unwrap();
+        let template = Template::new("FIX.4.4", "D", "ALICE", "BROKER_A");
+        let mut out = BytesMut::new();
+        let sending_time = SendingTime::now(TimestampPrecision::Millis);
+        template.write(&mut out, 7, sending_time, &order.parts().unwrap());
+        let body = "11=O1\x0155=AAPL\x0154=1\x0138=100\x0140=2\x0144=150.25\x0159=3\x01";
+        assert!(body_of(&out).ends_with(body.as_bytes()));
+
+        let market = br#"{"cl_ord_id":"O2","symbol":"AAPL","side":"SELL","qty":5,
+                          "ord_type":"MARKET"}"#;
+        let order: FastOrder = serde_json::from_slice(market).unwrap();
+        template.write(&mut out, 8, sending_time, &order.parts().unwrap());
+        assert!(body_of(&out).ends_with(b"38=5\x0140=1\x0159=0\x01"));
+
+        for (json, reason) in [
+            (r#""ord_type":"MARKET","price":"1""#, "price is required"),
+            (r#""ord_type":"LIMIT","price":"1e3""#, "price must be a decimal"),
+            (r#""ord_type":"STOP""#, "only MARKET and LIMIT"),
+        ] {
+            let json = format!(r#"{{"cl_ord_id":"O3","symbol":"X","side":"BUY","qty":1,{json}}}"#);
+            let order: FastOrder = serde_json::from_str(&json).unwrap();
+            assert!(order.parts().unwrap_err().starts_with(reason), "{json}");
+        }
+        // Strings are borrowed, so cannot hold escapes.
+        let escaped = br#"{"cl_ord_id":"O\"4","symbol":"X","side":"BUY","qty":1,
+                           "ord_type":"MARKET"}"#;
+        assert!(serde_json::from_slice::<FastOrder>(escaped).is_err());
+    }
+
+    #[test]
+    fn encoded_bodies_are_whole_fields_of_an_order() {
+        let order = b"11=O1\x0155=AAPL\x0154=1\x0138=100\x0140=1\x01";
+        assert_eq!(check_encoded(order), Ok(()));
+        assert_eq!(encoded_value(order, 55), Some("AAPL"));
+        assert_eq!(encoded_value(order, 44), None);
+        for body in [
+            &b"11=O1\x0155=AAPL\x0154=1\x0138=100\x0140=1"[..],
+            b"11=O1\x0155=AAPL\x0154=1\x0138=100\x01\x0140=1\x01",
+            b"11=O1\x0155=AAPL\x0154=1\x0111=O1\x0140=1\x01",
+            b"11=O1\x0155=AAPL\x0154=1\x0138=100\x0140=1\x0134=9\x01",
+        ] {
+            assert!(check_encoded(body).is_err(), "{body:?}");
+        }
+    }
+}
@@ -0,0 +1,476 @@
+//! Gateway-to-gateway federation.
+//!
+//! A regional gateway can register an upstream ALICE gateway as the venue for
+//! a TargetCompID. Orders for that target are forwarded to the upstream's
+//! REST API with a `reply_to` URL and an `Idempotency-Key`, then sequenced
+//! and tracked locally; one that cannot be sequenced or stored here once
+//! forwarded is cancelled upstream.
+//! The upstream relays ExecutionReports for those orders back to the origin's
+//! `/api/v1/fix/inbound`, so the regional gateway sees normal FIX semantics.
+//! It relays only to the downstream gateways in `FIX_DOWNSTREAMS`: a
+//! `reply_to` naming any other URL is ignored, so a client cannot make the
+//! gateway post reports, and its relay API key, wherever it likes.
+
+use axum::{extract::State, http::StatusCode, response::Json};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, fmt::Write as _};
+use tracing::{info, warn, Instrument};
+use utoipa::ToSchema;
+
+use crate::{
+    audit::{self, Actor, AuditAction},
+    clock::{epoch_ms, format_utc_timestamp},
+    idempotency, lookup_field, msg_type_to_num,
+    secrets::Secret,
+    telemetry, AppState, Groups,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Upstream {
+    pub target_comp_id: String,
+    /// Base URL of the upstream gateway, e.g. `http://hub-eu:8080`.
+    pub url: String,
+    /// Sent as `X-API-Key` when the upstream sits behind the API gateway.
+    #[serde(default, skip_serializing)]
+    #[schema(value_type = Option<String>)]
+    pub api_key: Option<Secret>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UpstreamAck {
+    pub url: String,
+    pub session_id: String,
+    pub sequence_number: u64,
+}
+
+#[derive(Default)]
+pub struct Federation {
+    upstreams: HashMap<String, Upstream>,
+    /// Base URL under which this gateway is reachable by its upstreams.
+    public_url: Option<String>,
+    /// Base URLs of the downstream gateways ExecutionReports may be relayed to.
+    downstreams: Vec<String>,
+    /// API key presented when relaying ExecutionReports to a downstream gateway.
+    relay_api_key: Option<Secret>,
+}
+
+impl Federation {
+    /// Reads `FIX_UPSTREAMS` (`TARGET=url` entries, comma-separated),
+    /// `FIX_DOWNSTREAMS` (URLs, comma-separated) and `FIX_PUBLIC_URL`;
+    /// `relay_api_key` is the resolved `FIX_FEDERATION_API_KEY`.
+    pub fn from_env(relay_api_key: Option<Secret>) -> Self {
+        let downstreams = std::env::var("FIX_DOWNSTREAMS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|url| url.trim().trim_end_matches('/'))
+            .filter(|url| !url.is_empty())
+            .map(str::to_string)
+            .collect();
+        let mut federation = Self {
+            public_url: std::env::var("FIX_PUBLIC_URL").ok(),
+            downstreams,
+            relay_api_key,
+            ..Self::default()
+        };
+        if let Ok(spec) = std::env::var("FIX_UPSTREAMS") {
+            for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                match entry.split_once('=') {
+                    Some((target, url)) if !target.is_empty() && !url.is_empty() => federation
+                        .register(Upstream {
+                            target_comp_id: target.to_string(),
+                            url: url.to_string(),
+                            api_key: None,
+                        }),
+                    _ => warn!(entry, "Ignoring malformed FIX_UPSTREAMS entry"),
+                }
+            }
+        }
+        federation
+    }
+
+    pub fn register(&mut self, mut upstream: Upstream) {
+        upstream.url = upstream.url.trim_end_matches('/').to_string();
+        self.upstreams
+            .insert(upstream.target_comp_id.clone(), upstream);
+    }
+
+    pub fn upstream_for(&self, target_comp_id: &str) -> Option<Upstream> {
+        self.upstreams.get(target_comp_id).cloned()
+    }
+
+    /// Whether orders for the target are forwarded upstream.
+    pub fn is_federated(&self, target_comp_id: &str) -> bool {
+        self.upstreams.contains_key(target_comp_id)
+    }
+
+    pub fn public_url(&self) -> Option<String> {
+        self.public_url.clone()
+    }
+
+    /// The configured downstream gateway `reply_to` names, if it is one.
+    fn downstream(&self, reply_to: &str) -> Option<&str> {
+        let reply_to = reply_to.trim_end_matches('/');
+        self.downstreams
+            .iter()
+            .find(|url| *url == reply_to)
+            .map(String::as_str)
+    }
+
+    pub fn upstream_count(&self) -> usize {
+        self.upstreams.len()
+    }
+
+    fn list(&self) -> Vec<Upstream> {
+        let mut upstreams: Vec<Upstream> = self.upstreams.values().cloned().collect();
+        upstreams.sort_by(|a, b| a.target_comp_id.cmp(&b.target_comp_id));
+        upstreams
+    }
+}
+
+// ── Forwarding ────────────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+struct UpstreamSent {
+    session_id: String,
+    sequence_number: u64,
+}
+
+/// Forwards an order to the upstream gateway's `/api/v1/fix/send`.
+pub async fn forward(
+    client: &reqwest::Client,
+    upstream: &Upstream,
+    msg_type: &str,
+    fields: &HashMap<String, String>,
+    groups: &Groups,
+    metadata: &HashMap<String, String>,
+    reply_to: Option<String>,
+) -> Result<UpstreamAck, String> {
+    let body = serde_json::json!({
+        "msg_type": msg_type,
+        "fields": fields,
+        "groups": groups,
+        "metadata": metadata,
+        "reply_to": reply_to,
+    });
+    send(client, upstream, idempotency_key(msg_type, fields), &body).await
+}
+
+/// Cancels an order forwarded to the upstream gateway that could not then be
+/// sequenced or stored here, so the upstream venue does not work an order
+/// the client was told was rejected. Only a NewOrderSingle can be cancelled;
+/// anything else forwarded is left to the upstream.
+pub async fn withdraw(
+    client: &reqwest::Client,
+    upstream: &Upstream,
+    msg_type: &str,
+    fields: &HashMap<String, String>,
+) {
+    let cl_ord_id = lookup_field(fields, "ClOrdID", 11);
+    let Some(cancel) = cancel_fields(msg_type, fields, epoch_ms()) else {
+        warn!(
+            url = %upstream.url,
+            msg_type,
+            cl_ord_id = ?cl_ord_id,
+            "Forwarded message not stored here and cannot be cancelled upstream"
+        );
+        return;
+    };
+    let body = serde_json::json!({ "msg_type": "F", "fields": cancel });
+    match send(client, upstream, idempotency_key("F", &cancel), &body).await {
+        Ok(_) => info!(url = %upstream.url, cl_ord_id = ?cl_ord_id, "Forwarded order cancelled"),
+        Err(e) => warn!(
+            url = %upstream.url,
+            cl_ord_id = ?cl_ord_id,
+            error = %e,
+            "Forwarded order could not be cancelled upstream"
+        ),
+    }
+}
+
+/// The OrderCancelRequest (35=F) for a forwarded NewOrderSingle, if it is
+/// one with a ClOrdID.
+fn cancel_fields(
+    msg_type: &str,
+    fields: &HashMap<String, String>,
+    now_ms: u64,
+) -> Option<HashMap<String, String>> {
+    if msg_type_to_num(msg_type) != "D" {
+        return None;
+    }
+    let cl_ord_id = lookup_field(fields, "ClOrdID", 11)?;
+    let mut cancel = HashMap::from([
+        ("OrigClOrdID".to_string(), cl_ord_id.clone()),
+        ("ClOrdID".to_string(), format!("{cl_ord_id}-CXL")),
+        ("TransactTime".to_string(), format_utc_timestamp(now_ms)),
+    ]);
+    for (name, tag) in [
+        ("SenderCompID", 49),
+        ("TargetCompID", 56),
+        ("Symbol", 55),
+        ("Side", 54),
+        ("OrderQty", 38),
+    ] {
+        if let Some(value) = lookup_field(fields, name, tag) {
+            cancel.insert(name.to_string(), value.clone());
+        }
+    }
+    Some(cancel)
+}
+
+/// The `Idempotency-Key` a message is forwarded with: the same for every
+/// forward of one ClOrdID on one session, so the upstream acts on it once
+/// however often it is forwarded.
+fn idempotency_key(msg_type: &str, fields: &HashMap<String, String>) -> String {
+    let field = |name, tag| lookup_field(fields, name, tag).map_or("", String::as_str);
+    let Some(cl_ord_id) = lookup_field(fields, "ClOrdID", 11) else {
+        return uuid::Uuid::new_v4().simple().to_string();
+    };
+    let identity = [
+        field("SenderCompID", 49),
+        field("TargetCompID", 56),
+        &msg_type_to_num(msg_type),
+        cl_ord_id,
+    ]
+    .join("\x01");
+    Sha256::digest(identity.as_bytes())
+        .iter()
+        .fold(String::new(), |mut hex, b| {
+            let _ = write!(hex, "{b:02x}");
+            hex
+        })
+}
+
+/// Posts `body` to the upstream gateway's `/api/v1/fix/send`.
+async fn send(
+    client: &reqwest::Client,
+    upstream: &Upstream,
+    idempotency_key: String,
+    body: &serde_json::Value,
+) -> Result<UpstreamAck, String> {
+    let mut req = client
+        .post(format!("{}/api/v1/fix/send", upstream.url))
+        .headers(telemetry::headers())
+        .header(idempotency::HEADER, idempotency_key)
+        .json(body);
+    if let Some(key) = &upstream.api_key {
+        req = req.header("X-API-Key", key.expose());
+    }
+
+    let resp = req.send().await.map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("upstream returned {}", resp.status()));
+    }
+    let sent: UpstreamSent = resp.json().await.map_err(|e| e.to_string())?;
+    Ok(UpstreamAck {
+        url: upstream.url.clone(),
+        session_id: sent.session_id,
+        sequence_number: sent.sequence_number,
+    })
+}
+
+/// Relays an inbound message to the downstream gateway an order came from,
+/// if its `reply_to` is one of the configured downstreams.
+pub fn relay(state: &AppState, reply_to: &str, raw: &[u8]) {
+    let client = state.http.clone();
+    let federation = state.federation.clone();
+    let reply_to = reply_to.to_string();
+    let body = serde_json::json!({ "raw_base64": BASE64.encode(raw) });
+
+    tokio::spawn(
+        async move {
+            let (url, api_key) = {
+                let federation = federation.lock().await;
+                let Some(downstream) = federation.downstream(&reply_to) else {
+                    warn!(reply_to = %reply_to, "Relay refused, not a configured downstream");
+                    return;
+                };
+                let url = format!("{downstream}/api/v1/fix/inbound");
+                (url, federation.relay_api_key.clone())
+            };
+            let mut req = client.post(&url).json(&body);
+            if let Some(key) = &api_key {
+                req = req.header("X-API-Key", key.expose());
+            }
+            match req.send().await {
+                Ok(resp) if resp.status().is_success() => {
+                    info!(url = %url, "ExecutionReport relayed downstream")
+                }
+                Ok(resp) => warn!(url = %url, status = %resp.status(), "Downstream relay rejected"),
+                Err(e) => warn!(url = %url, error = %e, "Downstream relay failed"),
+            }
+        }
+        .instrument(telemetry::background_span()),
+    );
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UpstreamsResponse {
+    upstreams: Vec<Upstream>,
+    count: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/federation/upstreams",
+    tag = "federation",
+    responses(
+        (status = 200, description = "OK", body = UpstreamsResponse),
+    )
+)]
+pub async fn list_upstreams(State(state): State<AppState>) -> Json<UpstreamsResponse> {
+    let upstreams = state.federation.lock().await.list();
+    let count = upstreams.len();
+    Json(UpstreamsResponse { upstreams, count })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/federation/upstreams",
+    tag = "federation",
+    request_body = Upstream,
+    responses(
+        (status = 200, description = "Registered", body = Upstream),
+        (status = 400, description = "Invalid upstream"),
+    )
+)]
+pub async fn register_upstream(
+    State(state): State<AppState>,
+    actor: Actor,
+    Json(upstream): Json<Upstream>,
+) -> Result<Json<Upstream>, StatusCode> {
+    if upstream.target_comp_id.trim().is_empty() || !upstream.url.starts_with("http") {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    info!(
+        target_comp_id = %upstream.target_comp_id,
+        url = %upstream.url,
+        "Upstream gateway registered"
+    );
+    let registered = {
+        let mut federation = state.federation.lock().await;
+        federation.register(upstream.clone());
+        federation
+            .upstream_for(&upstream.target_comp_id)
+            .unwrap_or(upstream)
+    };
+    audit::record(
+        &state,
+        &actor,
+        AuditAction::UpstreamRegistered,
+        None,
+        format!("{} at {}", registered.target_comp_id, registered.url),
+    )
+    .await;
+    Ok(Json(registered))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::HeaderMap, routing::post, Router};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn reports_are_relayed_only_to_configured_downstreams() {
+        let federation = Federation {
+            downstreams: vec!["http://gw-apac:8080".to_string()],
+            ..Federation::default()
+        };
+        assert_eq!(
+            federation.downstream("http://gw-apac:8080/"),
+            Some("http://gw-apac:8080")
+        );
+        assert_eq!(federation.downstream("http://gw-apac:8080/api"), None);
+        assert_eq!(federation.downstream("http://attacker.example"), None);
+        assert_eq!(
+            Federation::default().downstream("http://gw-apac:8080"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn forwarded_orders_carry_an_idempotency_key_and_are_withdrawn_by_a_cancel() {
+        type Received = Arc<Mutex<Vec<(Option<String>, serde_json::Value)>>>;
+        let received = Received::default();
+        let app = Router::new().route(
+            "/api/v1/fix/send",
+            post({
+                let received = received.clone();
+                move |headers: HeaderMap, Json(body): Json<serde_json::Value>| async move {
+                    let key = headers
+                        .get(idempotency::HEADER)
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_string);
+                    received.lock().unwrap().push((key, body));
+                    Json(serde_json::json!({ "session_id": "HUB->EU", "sequence_number": 7 }))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let upstream = Upstream {
+            target_comp_id: "EU".to_string(),
+            url,
+            api_key: None,
+        };
+        let fields: HashMap<String, String> = [
+            ("SenderCompID", "ALICE"),
+            ("TargetCompID", "EU"),
+            ("ClOrdID", "O1"),
+            ("Symbol", "VOD.L"),
+            ("Side", "1"),
+            ("OrderQty", "100"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let client = reqwest::Client::new();
+        let (groups, metadata) = (Groups::default(), HashMap::new());
+        let reply_to = Some("http://gw-apac:8080".to_string());
+        for _ in 0..2 {
+            let ack = forward(
+                &client,
+                &upstream,
+                "D",
+                &fields,
+                &groups,
+                &metadata,
+                reply_to.clone(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                (ack.session_id.as_str(), ack.sequence_number),
+                ("HUB->EU", 7)
+            );
+        }
+        withdraw(&client, &upstream, "NewOrderSingle", &fields).await;
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 3);
+        let (first, second, cancel) = (&received[0], &received[1], &received[2]);
+        assert!(first.0.is_some());
+        assert_eq!(first.0, second.0, "a forward and its retry share a key");
+        assert_eq!(first.1["reply_to"], "http://gw-apac:8080");
+        assert_ne!(cancel.0, first.0);
+        assert_eq!(cancel.1["msg_type"], "F");
+        assert_eq!(cancel.1["fields"]["OrigClOrdID"], "O1");
+        assert_eq!(cancel.1["fields"]["ClOrdID"], "O1-CXL");
+        assert_eq!(cancel.1["fields"]["OrderQty"], "100");
+        assert!(cancel.1["fields"]["TransactTime"].is_string());
+    }
+
+    #[test]
+    fn only_new_orders_are_withdrawn() {
+        let fields = HashMap::from([("11".to_string(), "O1".to_string())]);
+        assert!(cancel_fields("D", &fields, 0).is_some());
+        assert!(cancel_fields("G", &fields, 0).is_none());
+        assert!(cancel_fields("D", &HashMap::new(), 0).is_none());
+    }
+}
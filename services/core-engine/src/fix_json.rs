@@ -0,0 +1,244 @@
+//! FIX JSON Encoding (FIX Trading Community).
+//!
+//! A message becomes an object with `Header`, `Body` and `Trailer` members,
+//! each keyed by field name. Repeating groups are arrays of objects under the
+//! NoXXX field name, tags without a known name are keyed by their number, and
+//! every value is a string. BodyLength and CheckSum describe the tag=value
+//! framing only and are omitted.
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use utoipa::ToSchema;
+
+use crate::{
+    structure::{self, HEADER_TAGS, TRAILER_TAGS},
+    ParsedField,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputEncoding {
+    /// The engine's own response format.
+    #[default]
+    Native,
+    /// FIX JSON Encoding.
+    FixJson,
+}
+
+pub fn encode(fields: &[ParsedField]) -> Value {
+    let msg_type = fields
+        .iter()
+        .find(|f| f.tag == 35)
+        .map(|f| f.value.as_str())
+        .unwrap_or_default();
+
+    let mut header = Map::new();
+    let mut body = Map::new();
+    let mut trailer = Map::new();
+
+    let mut i = 0;
+    while i < fields.len() {
+        let field = &fields[i];
+        i += 1;
+        if matches!(field.tag, 9 | 10) {
+            continue;
+        }
+        let section = if HEADER_TAGS.contains(&field.tag) {
+            &mut header
+        } else if TRAILER_TAGS.contains(&field.tag) {
+            &mut trailer
+        } else {
+            &mut body
+        };
+
+        let value = match structure::group_for(msg_type, field.tag) {
+            Some(group) => {
+                let mut instances: Vec<Value> = Vec::new();
+                let mut current = Map::new();
+                while i < fields.len() && group.members.contains(&fields[i].tag) {
+                    let member = &fields[i];
+                    if member.tag == group.delimiter && !current.is_empty() {
+                        instances.push(Value::Object(std::mem::take(&mut current)));
+                    }
+                    current.insert(key(member), member.value.clone().into());
+                    i += 1;
+                }
+                if !current.is_empty() {
+                    instances.push(Value::Object(current));
+                }
+                Value::Array(instances)
+            }
+            None => field.value.clone().into(),
+        };
+        section.insert(key(field), value);
+    }
+
+    serde_json::json!({
+        "Header": header,
+        "Body": body,
+        "Trailer": trailer,
+    })
+}
+
+fn key(field: &ParsedField) -> String {
+    if field.name == "Unknown" {
+        field.tag.to_string()
+    } else {
+        field.name.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        message::{Field, FixMessage, SOH},
+        parse_raw,
+    };
+
+    fn parse(fields: &[(u32, &str)]) -> Vec<ParsedField> {
+        let fields = fields
+            .iter()
+            .map(|(tag, value)| Field {
+                tag: *tag,
+                value: value.as_bytes().to_vec(),
+            })
+            .collect();
+        parse_raw(&FixMessage::framed("FIX.4.4", fields).encode_with(SOH)).fields
+    }
+
+    /// Flattens an encoded section back to `(key, value)` pairs in the order
+    /// they appear, a group as its count followed by its members.
+    fn flatten(section: &Value) -> Vec<(String, String)> {
+        let mut pairs = Vec::new();
+        for (key, value) in section.as_object().unwrap() {
+            match value {
+                Value::Array(instances) => {
+                    pairs.push((key.clone(), instances.len().to_string()));
+                    for instance in instances {
+                        pairs.extend(flatten(instance));
+                    }
+                }
+                Value::String(s) => pairs.push((key.clone(), s.clone())),
+                other => panic!("{key} encoded as {other}"),
+            }
+        }
+        pairs
+    }
+
+    /// Encodes the message and checks that every field but BodyLength and
+    /// CheckSum comes back out of its section, and nothing else does.
+    fn assert_round_trips(fields: &[ParsedField]) -> Value {
+        let json = encode(fields);
+        let mut decoded: Vec<(String, String)> = ["Header", "Body", "Trailer"]
+            .iter()
+            .flat_map(|section| flatten(&json[section]))
+            .collect();
+        let mut expected: Vec<(String, String)> = fields
+            .iter()
+            .filter(|f| !matches!(f.tag, 9 | 10))
+            .map(|f| (key(f), f.value.clone()))
+            .collect();
+        decoded.sort();
+        expected.sort();
+        assert_eq!(decoded, expected);
+        json
+    }
+
+    #[test]
+    fn orders_round_trip_through_header_body_and_trailer() {
+        let fields = parse(&[
+            (35, "D"),
+            (49, "ALICE"),
+            (56, "BROKER_A"),
+            (34, "12"),
+            (52, "20260223-09:30:00.000"),
+            (11, "ORD-1"),
+            (55, "AAPL"),
+            (54, "1"),
+            (38, "100"),
+            (40, "2"),
+            (44, "150.25"),
+            (453, "2"),
+            (448, "DESK-1"),
+            (447, "D"),
+            (452, "1"),
+            (448, "TRADER-9"),
+            (447, "D"),
+            (452, "11"),
+        ]);
+        let json = assert_round_trips(&fields);
+
+        assert_eq!(json["Header"]["MsgType"], "D");
+        assert_eq!(json["Header"]["BeginString"], "FIX.4.4");
+        assert_eq!(json["Header"]["SenderCompID"], "ALICE");
+        assert!(json["Header"].get("BodyLength").is_none());
+        assert!(json["Body"].get("MsgType").is_none());
+        assert_eq!(json["Body"]["Price"], "150.25");
+        assert_eq!(json["Trailer"], serde_json::json!({}));
+        assert_eq!(
+            json["Body"]["NoPartyIDs"],
+            serde_json::json!([
+                { "PartyID": "DESK-1", "447": "D", "452": "1" },
+                { "PartyID": "TRADER-9", "447": "D", "452": "11" },
+            ])
+        );
+    }
+
+    #[test]
+    fn incremental_refresh_entries_open_with_md_update_action() {
+        let fields = parse(&[
+            (35, "X"),
+            (49, "FEED"),
+            (56, "ALICE"),
+            (34, "3"),
+            (52, "20260223-09:30:00.000"),
+            (268, "2"),
+            (279, "0"),
+            (269, "0"),
+            (55, "AAPL"),
+            (270, "150.25"),
+            (279, "2"),
+            (269, "1"),
+            (55, "AAPL"),
+            (270, "150.30"),
+        ]);
+        let json = assert_round_trips(&fields);
+
+        let entries = json["Body"]["NoMDEntries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["MDUpdateAction"], "0");
+        assert_eq!(entries[1]["MDUpdateAction"], "2");
+        assert_eq!(entries[1]["270"], "150.30");
+    }
+
+    #[test]
+    fn unknown_tags_are_keyed_by_number() {
+        let fields = parse(&[
+            (35, "D"),
+            (49, "ALICE"),
+            (56, "BROKER_A"),
+            (34, "12"),
+            (52, "20260223-09:30:00.000"),
+            (11, "ORD-1"),
+            (5001, "desk-note"),
+            (453, "1"),
+            (448, "DESK-1"),
+            (9999, "custom"),
+            (452, "1"),
+            (89, "c2lnbmVk"),
+        ]);
+        let json = assert_round_trips(&fields);
+
+        assert_eq!(json["Body"]["5001"], "desk-note");
+        // A tag outside the group ends it; the members after it are
+        // top-level body fields.
+        assert_eq!(
+            json["Body"]["NoPartyIDs"],
+            serde_json::json!([{ "PartyID": "DESK-1" }])
+        );
+        assert_eq!(json["Body"]["9999"], "custom");
+        assert_eq!(json["Body"]["452"], "1");
+        assert_eq!(json["Trailer"]["89"], "c2lnbmVk");
+    }
+}
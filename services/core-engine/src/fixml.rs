@@ -0,0 +1,658 @@
+//! FIXML import and export.
+//!
+//! Covers the order and post-trade messages our counterparties exchange in
+//! FIXML. Fields are mapped to their FIXML attribute abbreviations, grouped
+//! into the `Hdr`, `Instrmt` and `OrdQty` components, and Parties/Allocs
+//! repeating groups become repeated `Pty`/`Alloc` elements. Tags without a
+//! FIXML mapping are reported as unmapped rather than guessed. Decoded fields
+//! are named from the session's data dictionary, or the version's.
+
+use axum::{extract::State, http::StatusCode, response::Json};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::{
+    dictionary::Dictionaries,
+    message::{Field, FixMessage},
+    parse_raw, structure, AppState, ParseRequest, ParsedField,
+};
+
+const MESSAGES: &[(&str, &str)] = &[
+    ("D", "Order"),
+    ("8", "ExecRpt"),
+    ("F", "OrdCxlReq"),
+    ("G", "OrdCxlRplcReq"),
+    ("9", "OrdCxlRej"),
+    ("H", "OrdStatReq"),
+    ("J", "AllocInstrctn"),
+    ("P", "AllocInstrctnAck"),
+    ("AS", "AllocRpt"),
+    ("AE", "TrdCaptRpt"),
+    ("AR", "TrdCaptRptAck"),
+];
+
+const HEADER: &[(u32, &str)] = &[
+    (49, "SID"),
+    (56, "TID"),
+    (34, "SeqNum"),
+    (52, "Snt"),
+    (50, "SSub"),
+    (57, "TSub"),
+    (115, "OBID"),
+    (128, "DlvrTo"),
+    (43, "PosDup"),
+    (97, "PosRsnd"),
+    (122, "OrigSnt"),
+];
+
+/// Tag-to-attribute mappings of one element.
+type Attrs = &'static [(u32, &'static str)];
+
+const COMPONENTS: &[(&str, Attrs)] = &[
+    (
+        "Instrmt",
+        &[
+            (55, "Sym"),
+            (65, "Sfx"),
+            (48, "ID"),
+            (22, "Src"),
+            (167, "SecTyp"),
+            (200, "MMY"),
+            (207, "Exch"),
+            (461, "CFI"),
+        ],
+    ),
+    ("OrdQty", &[(38, "Qty"), (152, "Cash")]),
+];
+
+/// Repeating groups: count tag, FIXML element per instance, member attributes.
+const GROUPS: &[(u32, &str, Attrs)] = &[
+    (453, "Pty", &[(448, "ID"), (447, "Src"), (452, "R")]),
+    (
+        78,
+        "Alloc",
+        &[(79, "Acct"), (80, "Qty"), (661, "AcctIDSrc")],
+    ),
+];
+
+const FIELDS: &[(u32, &str)] = &[
+    (1, "Acct"),
+    (6, "AvgPx"),
+    (11, "ClOrdID"),
+    (14, "CumQty"),
+    (15, "Ccy"),
+    (17, "ExecID"),
+    (18, "ExecInst"),
+    (21, "HandlInst"),
+    (31, "LastPx"),
+    (32, "LastQty"),
+    (37, "OrdID"),
+    (39, "Stat"),
+    (40, "Typ"),
+    (41, "OrigClOrdID"),
+    (44, "Px"),
+    (53, "Qty"),
+    (54, "Side"),
+    (58, "Txt"),
+    (59, "TmInForce"),
+    (60, "TxnTm"),
+    (64, "SettlDt"),
+    (70, "AllocID"),
+    (71, "TransTyp"),
+    (75, "TrdDt"),
+    (87, "Stat"),
+    (99, "StopPx"),
+    (102, "CxlRejRsn"),
+    (103, "RejRsn"),
+    (126, "ExpireTm"),
+    (150, "ExecTyp"),
+    (151, "LeavesQty"),
+    (375, "CntraBrkr"),
+    (432, "ExpireDt"),
+    (434, "CxlRejRspTo"),
+    (487, "TransTyp"),
+    (528, "Cpcty"),
+    (571, "RptID"),
+    (626, "AllocTyp"),
+    (755, "RptID"),
+];
+
+fn message_element(msg_type: &str) -> Option<&'static str> {
+    MESSAGES
+        .iter()
+        .find(|(t, _)| *t == msg_type)
+        .map(|(_, e)| *e)
+}
+
+fn field_abbr(tag: u32) -> Option<&'static str> {
+    FIELDS.iter().find(|(t, _)| *t == tag).map(|(_, a)| *a)
+}
+
+fn component_of(tag: u32) -> Option<(&'static str, &'static str)> {
+    COMPONENTS.iter().find_map(|(component, attrs)| {
+        attrs
+            .iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, abbr)| (*component, *abbr))
+    })
+}
+
+/// Abbreviations shared by several tags (e.g. `Stat`, `TransTyp`) depend on the message.
+fn field_tag(msg_type: &str, abbr: &str) -> Option<u32> {
+    match (msg_type, abbr) {
+        ("J" | "P" | "AS", "Stat") => Some(87),
+        ("J" | "P" | "AS", "TransTyp") => Some(71),
+        ("AS", "RptID") => Some(755),
+        ("AE" | "AR", "TransTyp") => Some(487),
+        ("J" | "AS", "Qty") => Some(53),
+        _ => FIELDS.iter().find(|(_, a)| *a == abbr).map(|(t, _)| *t),
+    }
+}
+
+fn fixml_version(begin_string: &str) -> &str {
+    match begin_string {
+        "FIXT.1.1" => "5.0 SP2",
+        other => other.trim_start_matches("FIX."),
+    }
+}
+
+fn begin_string(version: &str) -> String {
+    if version.starts_with("5.0") {
+        "FIXT.1.1".to_string()
+    } else {
+        format!("FIX.{version}")
+    }
+}
+
+const TIMESTAMP_TAGS: &[u32] = &[52, 60, 122, 126];
+const DATE_TAGS: &[u32] = &[64, 75, 432];
+
+/// FIXML uses XML Schema dates (`2026-02-23`, `2026-02-23T09:30:00.000`)
+/// where tag=value uses `20260223` and `20260223-09:30:00.000`.
+fn to_fixml_value(tag: u32, value: &str) -> String {
+    let date = |d: &str| format!("{}-{}-{}", &d[..4], &d[4..6], &d[6..8]);
+    let is_date = |d: &str| d.len() == 8 && d.bytes().all(|b| b.is_ascii_digit());
+    if DATE_TAGS.contains(&tag) && is_date(value) {
+        return date(value);
+    }
+    if TIMESTAMP_TAGS.contains(&tag) {
+        if let Some((d, t)) = value.split_once('-').filter(|(d, _)| is_date(d)) {
+            return format!("{}T{t}", date(d));
+        }
+    }
+    value.to_string()
+}
+
+fn from_fixml_value(tag: u32, value: &str) -> String {
+    if DATE_TAGS.contains(&tag) || TIMESTAMP_TAGS.contains(&tag) {
+        if let Some((d, t)) = value.split_once('T') {
+            return format!("{}-{t}", d.replace('-', ""));
+        }
+        if value.len() == 10 {
+            return value.replace('-', "");
+        }
+    }
+    value.to_string()
+}
+
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn attrs(out: &mut String, attrs: &[(&str, String)]) {
+    for (name, value) in attrs {
+        let _ = write!(out, " {name}=\"{}\"", escape(value));
+    }
+}
+
+// ── Encoding ──────────────────────────────────────────────────────────────────
+
+/// Converts tag=value fields to a FIXML document, returning it with the tags
+/// that have no FIXML mapping.
+pub fn encode(fields: &[ParsedField]) -> Result<(String, Vec<u32>), StatusCode> {
+    let value = |tag: u32| {
+        fields
+            .iter()
+            .find(|f| f.tag == tag)
+            .map(|f| f.value.as_str())
+    };
+    let msg_type = value(35).unwrap_or_default();
+    let element = message_element(msg_type).ok_or(StatusCode::UNPROCESSABLE_ENTITY)?;
+
+    let mut top: Vec<(&str, String)> = Vec::new();
+    let mut header: Vec<(&str, String)> = Vec::new();
+    let mut components: Vec<(&str, Vec<(&str, String)>)> = Vec::new();
+    let mut groups: Vec<(&str, Vec<(&str, String)>)> = Vec::new();
+    let mut unmapped = Vec::new();
+
+    let mut i = 0;
+    while i < fields.len() {
+        let field = &fields[i];
+        i += 1;
+        if matches!(field.tag, 8 | 9 | 10 | 35) {
+            continue;
+        }
+        if let Some((_, element, members)) = GROUPS.iter().find(|(t, _, _)| *t == field.tag) {
+            let Some(def) = structure::group_for(msg_type, field.tag) else {
+                continue;
+            };
+            let mut open = false;
+            while i < fields.len() && def.members.contains(&fields[i].tag) {
+                let member = &fields[i];
+                if member.tag == def.delimiter || !open {
+                    groups.push((element, Vec::new()));
+                    open = true;
+                }
+                match members.iter().find(|(t, _)| *t == member.tag) {
+                    Some((_, abbr)) => {
+                        if let Some((_, instance)) = groups.last_mut() {
+                            instance.push((abbr, to_fixml_value(member.tag, &member.value)));
+                        }
+                    }
+                    None => unmapped.push(member.tag),
+                }
+                i += 1;
+            }
+        } else if let Some((_, abbr)) = HEADER.iter().find(|(t, _)| *t == field.tag) {
+            header.push((abbr, to_fixml_value(field.tag, &field.value)));
+        } else if let Some((component, abbr)) = component_of(field.tag) {
+            match components.iter_mut().find(|(c, _)| *c == component) {
+                Some((_, attrs)) => attrs.push((abbr, field.value.clone())),
+                None => components.push((component, vec![(abbr, field.value.clone())])),
+            }
+        } else if let Some(abbr) = field_abbr(field.tag) {
+            top.push((abbr, to_fixml_value(field.tag, &field.value)));
+        } else {
+            unmapped.push(field.tag);
+        }
+    }
+
+    let mut out = String::new();
+    out.push_str("<FIXML");
+    let version = fixml_version(value(8).unwrap_or("FIX.4.4")).to_string();
+    attrs(&mut out, &[("v", version)]);
+    let _ = write!(out, "><{element}");
+    attrs(&mut out, &top);
+    out.push('>');
+    if !header.is_empty() {
+        out.push_str("<Hdr");
+        attrs(&mut out, &header);
+        out.push_str("/>");
+    }
+    for (name, list) in components.iter().chain(groups.iter()) {
+        let _ = write!(out, "<{name}");
+        attrs(&mut out, list);
+        out.push_str("/>");
+    }
+    let _ = write!(out, "</{element}></FIXML>");
+    Ok((out, unmapped))
+}
+
+// ── Decoding ──────────────────────────────────────────────────────────────────
+
+/// Converts one FIXML message element to a framed tag=value message,
+/// returning it with the attributes that have no tag mapping.
+fn decode_message(version: &str, node: roxmltree::Node) -> Option<(FixMessage, Vec<String>)> {
+    let name = node.tag_name().name();
+    let msg_type = MESSAGES.iter().find(|(_, e)| *e == name)?.0;
+    let mut unmapped = Vec::new();
+    let field = |tag: u32, value: &str| Field {
+        tag,
+        value: from_fixml_value(tag, value).into_bytes(),
+    };
+
+    let mut header = vec![field(35, msg_type)];
+    let mut body = Vec::new();
+
+    for attr in node.attributes() {
+        match field_tag(msg_type, attr.name()) {
+            Some(tag) => body.push(field(tag, attr.value())),
+            None => unmapped.push(format!("{name}@{}", attr.name())),
+        }
+    }
+
+    let children: Vec<_> = node.children().filter(|c| c.is_element()).collect();
+    for child in &children {
+        let child_name = child.tag_name().name();
+        if child_name == "Hdr" {
+            for attr in child.attributes() {
+                match HEADER.iter().find(|(_, a)| *a == attr.name()) {
+                    Some((tag, _)) => header.push(field(*tag, attr.value())),
+                    None => unmapped.push(format!("Hdr@{}", attr.name())),
+                }
+            }
+        } else if let Some((_, members)) = COMPONENTS.iter().find(|(c, _)| *c == child_name) {
+            for attr in child.attributes() {
+                match members.iter().find(|(_, a)| *a == attr.name()) {
+                    Some((tag, _)) => body.push(field(*tag, attr.value())),
+                    None => unmapped.push(format!("{child_name}@{}", attr.name())),
+                }
+            }
+        } else if !GROUPS.iter().any(|(_, e, _)| *e == child_name) {
+            unmapped.push(child_name.to_string());
+        }
+    }
+
+    for (count_tag, element, members) in GROUPS {
+        let instances: Vec<_> = children
+            .iter()
+            .filter(|c| c.tag_name().name() == *element)
+            .collect();
+        if instances.is_empty() {
+            continue;
+        }
+        body.push(field(*count_tag, &instances.len().to_string()));
+        for instance in instances {
+            // Member order follows the group definition so the delimiter leads.
+            for (tag, abbr) in members.iter() {
+                if let Some(value) = instance.attribute(*abbr) {
+                    body.push(field(*tag, value));
+                }
+            }
+            for attr in instance.attributes() {
+                if !members.iter().any(|(_, a)| *a == attr.name()) {
+                    unmapped.push(format!("{element}@{}", attr.name()));
+                }
+            }
+        }
+    }
+
+    header.extend(body);
+    Some((FixMessage::framed(&begin_string(version), header), unmapped))
+}
+
+/// Converts a FIXML document, one message or a `Batch` of them, to framed
+/// tag=value messages.
+pub fn decode(fixml: &str, dictionaries: &Dictionaries) -> Result<Vec<DecodedMessage>, StatusCode> {
+    let doc = roxmltree::Document::parse(fixml).map_err(|e| {
+        info!(error = %e, "FIXML document rejected");
+        StatusCode::BAD_REQUEST
+    })?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "FIXML" {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let version = root.attribute("v").unwrap_or("4.4");
+    // `5.0 SP2` is named by its FIX version alone.
+    let fix_version = version.split(' ').next().unwrap_or(version);
+
+    let elements: Vec<_> = root
+        .children()
+        .filter(|c| c.is_element())
+        .flat_map(|c| {
+            if c.tag_name().name() == "Batch" {
+                c.children().filter(|m| m.is_element()).collect()
+            } else {
+                vec![c]
+            }
+        })
+        .collect();
+
+    let mut messages = Vec::new();
+    for element in elements {
+        let (msg, unmapped) =
+            decode_message(version, element).ok_or(StatusCode::UNPROCESSABLE_ENTITY)?;
+        let wire = msg.encode_with(msg.delimiter);
+        let mut parsed = parse_raw(&wire);
+        let session_id = format!(
+            "{}->{}",
+            parsed.value(49).unwrap_or_default(),
+            parsed.value(56).unwrap_or_default()
+        );
+        let dict = dictionaries
+            .for_session(&session_id)
+            .or_else(|| dictionaries.get(fix_version));
+        if let Some(dict) = dict {
+            for field in &mut parsed.fields {
+                if let Some(def) = dict.field(field.tag) {
+                    field.name.clone_from(&def.name);
+                }
+            }
+        }
+        messages.push(DecodedMessage {
+            msg_type: parsed.msg_type,
+            fix_message: String::from_utf8_lossy(&wire).into_owned(),
+            display: String::from_utf8_lossy(&msg.encode_with(b'|')).into_owned(),
+            fields: parsed.fields,
+            unmapped,
+        });
+    }
+    if messages.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(messages)
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = FixmlEncodeResponse)]
+pub struct EncodeResponse {
+    msg_type: String,
+    fixml: String,
+    /// Tags left out because FIXML has no mapping for them here.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unmapped: Vec<u32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = FixmlDecodeRequest)]
+pub struct DecodeRequest {
+    fixml: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = FixmlDecodedMessage)]
+pub struct DecodedMessage {
+    msg_type: String,
+    /// SOH-delimited wire form, with BodyLength and CheckSum computed
+    fix_message: String,
+    /// `|`-delimited form for logs and display
+    display: String,
+    fields: Vec<ParsedField>,
+    /// Attributes or elements with no tag mapping, e.g. `Order@Foo`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unmapped: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = FixmlDecodeResponse)]
+pub struct DecodeResponse {
+    messages: Vec<DecodedMessage>,
+    count: usize,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/fix/fixml/encode",
+    tag = "fix",
+    request_body = ParseRequest,
+    responses(
+        (status = 200, description = "FIXML document", body = EncodeResponse),
+        (status = 400, description = "Not a FIX message"),
+    )
+)]
+pub async fn encode_handler(
+    State(_state): State<AppState>,
+    Json(req): Json<ParseRequest>,
+) -> Result<Json<EncodeResponse>, StatusCode> {
+    let raw = req.bytes().ok_or(StatusCode::BAD_REQUEST)?;
+    let parsed = parse_raw(&raw);
+    let (fixml, unmapped) = encode(&parsed.fields)?;
+
+    info!(
+        msg_type = %parsed.msg_type,
+        unmapped = unmapped.len(),
+        "FIX message encoded as FIXML"
+    );
+    Ok(Json(EncodeResponse {
+        msg_type: parsed.msg_type,
+        fixml,
+        unmapped,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/fix/fixml/decode",
+    tag = "fix",
+    request_body = DecodeRequest,
+    responses(
+        (status = 200, description = "Decoded messages", body = DecodeResponse),
+        (status = 400, description = "Not well-formed XML"),
+        (status = 422, description = "Not a FIXML document"),
+    )
+)]
+pub async fn decode_handler(
+    State(state): State<AppState>,
+    Json(req): Json<DecodeRequest>,
+) -> Result<Json<DecodeResponse>, StatusCode> {
+    let messages = decode(&req.fixml, &state.dictionaries)?;
+    let count = messages.len();
+    Ok(Json(DecodeResponse { messages, count }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::SOH;
+
+    fn wire(fields: &[(u32, &str)]) -> Vec<u8> {
+        let fields = fields
+            .iter()
+            .map(|(tag, value)| Field {
+                tag: *tag,
+                value: value.as_bytes().to_vec(),
+            })
+            .collect();
+        FixMessage::framed("FIX.4.4", fields).encode_with(SOH)
+    }
+
+    /// Encodes the message as FIXML and decodes it again.
+    fn round_trip(raw: &[u8]) -> (String, DecodedMessage) {
+        let (fixml, unmapped) = encode(&parse_raw(raw).fields).unwrap();
+        assert_eq!(unmapped, Vec::<u32>::new());
+        let mut messages = decode(&fixml, &Dictionaries::from_env()).unwrap();
+        assert_eq!(messages.len(), 1);
+        (fixml, messages.remove(0))
+    }
+
+    #[test]
+    fn orders_round_trip_with_their_parties() {
+        let raw = wire(&[
+            (35, "D"),
+            (49, "ALICE"),
+            (56, "BROKER_A"),
+            (34, "12"),
+            (52, "20260223-09:30:00.000"),
+            (11, "ORD-1"),
+            (1, "ACC-7"),
+            (54, "1"),
+            (60, "20260223-09:30:00.000"),
+            (40, "2"),
+            (44, "150.25"),
+            (55, "AAPL"),
+            (48, "US0378331005"),
+            (22, "4"),
+            (38, "100"),
+            (453, "2"),
+            (448, "DESK-1"),
+            (447, "D"),
+            (452, "1"),
+            (448, "TRADER-9"),
+            (447, "D"),
+            (452, "11"),
+        ]);
+        let (fixml, decoded) = round_trip(&raw);
+        assert!(fixml.starts_with(r#"<FIXML v="4.4"><Order ClOrdID="ORD-1" Acct="ACC-7""#));
+        assert!(fixml.contains(r#"TxnTm="2026-02-23T09:30:00.000""#), "{fixml}");
+        assert!(fixml.contains(r#"<Instrmt Sym="AAPL" ID="US0378331005" Src="4"/>"#));
+        assert!(fixml.contains(r#"<Pty ID="TRADER-9" Src="D" R="11"/>"#), "{fixml}");
+
+        assert_eq!(decoded.msg_type, "NewOrderSingle");
+        assert_eq!(decoded.fix_message.as_bytes(), raw);
+        let name = |tag: u32| {
+            let field = decoded.fields.iter().find(|f| f.tag == tag).unwrap();
+            field.name.as_str()
+        };
+        assert_eq!(name(447), "PartyIDSource");
+        assert_eq!(name(452), "PartyRole");
+        assert_eq!(name(448), "PartyID");
+    }
+
+    #[test]
+    fn shared_abbreviations_map_back_by_message_type() {
+        let raw = wire(&[
+            (35, "J"),
+            (49, "ALICE"),
+            (56, "BROKER_A"),
+            (70, "AL-1"),
+            (71, "0"),
+            (626, "1"),
+            (54, "1"),
+            (53, "300"),
+            (6, "10.5"),
+            (75, "20260223"),
+            (55, "IBM"),
+            (78, "2"),
+            (79, "ACC-A"),
+            (80, "100"),
+            (79, "ACC-B"),
+            (80, "200"),
+        ]);
+        let (fixml, decoded) = round_trip(&raw);
+        assert!(fixml.contains(r#"TransTyp="0""#) && fixml.contains(r#"Qty="300""#));
+        assert!(fixml.contains(r#"TrdDt="2026-02-23""#), "{fixml}");
+        assert!(fixml.contains(r#"<Alloc Acct="ACC-B" Qty="200"/>"#), "{fixml}");
+        // TransTyp is TradeReportTransType(487) on trade capture reports.
+        assert_eq!(field_tag("AE", "TransTyp"), Some(487));
+        assert_eq!(decoded.fix_message.as_bytes(), raw);
+    }
+
+    #[test]
+    fn unmapped_tags_and_attributes_are_reported() {
+        let raw = wire(&[(35, "F"), (11, "C-2"), (41, "C-1"), (5000, "x"), (55, "IBM")]);
+        let (fixml, unmapped) = encode(&parse_raw(&raw).fields).unwrap();
+        assert_eq!(unmapped, vec![5000]);
+        assert!(!fixml.contains("x\""), "{fixml}");
+        let heartbeat = wire(&[(35, "0")]);
+        assert_eq!(
+            encode(&parse_raw(&heartbeat).fields).unwrap_err(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+
+        let dictionaries = Dictionaries::from_env();
+        let batch = r#"<FIXML v="4.4"><Batch>
+            <Order ClOrdID="A" Foo="x"><Hdr SID="ALICE" Bar="y"/><Extra/></Order>
+            <OrdCxlReq ClOrdID="B" OrigClOrdID="A"/>
+        </Batch></FIXML>"#;
+        let messages = decode(batch, &dictionaries).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].unmapped, ["Order@Foo", "Hdr@Bar", "Extra"]);
+        assert_eq!(messages[0].display, "8=FIX.4.4|9=19|35=D|49=ALICE|11=A|10=214|");
+        assert_eq!(messages[1].msg_type, "OrderCancelRequest");
+        assert!(messages[1].unmapped.is_empty());
+
+        for (doc, status) in [
+            ("not xml", StatusCode::BAD_REQUEST),
+            ("<Order/>", StatusCode::BAD_REQUEST),
+            ("<FIXML v=\"4.4\"/>", StatusCode::BAD_REQUEST),
+            ("<FIXML v=\"4.4\"><Quote/></FIXML>", StatusCode::UNPROCESSABLE_ENTITY),
+        ] {
+            assert_eq!(decode(doc, &dictionaries).unwrap_err(), status, "{doc}");
+        }
+    }
+}
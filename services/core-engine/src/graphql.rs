@@ -0,0 +1,543 @@
+//! GraphQL query API over sessions, orders and stored messages.
+//!
+//! `POST /api/v1/graphql` answers queries that join the three, which the REST
+//! endpoints can only answer in several round trips, e.g. the orders
+//! rejected on a session in the last hour with their ExecutionReports:
+//!
+//! ```graphql
+//! {
+//!   orders(filter: { sessionId: "ALICE->BROKER_A", states: [REJECTED],
+//!                    updatedFromMs: 1700000000000 }) {
+//!     clOrdId symbol side
+//!     executionReports { sequenceNumber recordedAtMs fixMessage }
+//!   }
+//! }
+//! ```
+//!
+//! `GET` on the same path serves GraphiQL. The API is read-only; every
+//! resolver reads the same stores as the REST handlers.
+
+use async_graphql::{
+    http::GraphiQLSource, Context, EmptyMutation, EmptySubscription, InputObject, Object, Schema,
+};
+use axum::{
+    extract::State,
+    response::{Html, Json},
+    routing::get,
+    Router,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use std::collections::HashMap;
+
+use crate::{
+    orders::{Correlation, Fill, LastExec, OrderContext, OrderState, StateChange},
+    records::{Direction, StoredMessage},
+    store::{self, MessageQuery},
+    AppState, FixSession,
+};
+
+/// Nesting allowed in one query, e.g. orders → session → messages → order.
+const MAX_DEPTH: usize = 10;
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+pub type FixSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+pub fn schema(state: AppState) -> FixSchema {
+    Schema::build(Query, EmptyMutation, EmptySubscription)
+        .data(state)
+        .limit_depth(MAX_DEPTH)
+        .finish()
+}
+
+/// `/api/v1/graphql`, with its schema as the router state.
+pub fn router<S: Clone + Send + Sync + 'static>(state: AppState) -> Router<S> {
+    Router::new()
+        .route("/api/v1/graphql", get(graphiql).post(execute))
+        .with_state(schema(state))
+}
+
+async fn execute(
+    State(schema): State<FixSchema>,
+    Json(req): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    Json(schema.execute(req).await)
+}
+
+async fn graphiql() -> Html<String> {
+    Html(GraphiQLSource::build().endpoint("/api/v1/graphql").finish())
+}
+
+fn app_state<'a>(ctx: &Context<'a>) -> &'a AppState {
+    ctx.data_unchecked::<AppState>()
+}
+
+/// The number of results to return for a `limit` argument.
+fn page_size(limit: Option<usize>) -> async_graphql::Result<usize> {
+    match limit.unwrap_or(DEFAULT_LIMIT) {
+        0 => Err("limit must be positive".into()),
+        limit if limit > MAX_LIMIT => Err(format!("limit must be at most {MAX_LIMIT}").into()),
+        limit => Ok(limit),
+    }
+}
+
+// ── Filters ───────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Default, Clone, InputObject)]
+pub struct OrderFilter {
+    pub session_id: Option<String>,
+    /// Orders in any of these states.
+    pub states: Option<Vec<OrderState>>,
+    pub symbol: Option<String>,
+    /// Side (54) value, e.g. `1` for Buy.
+    pub side: Option<String>,
+    pub account: Option<String>,
+    /// Sending time range `[sentFromMs, sentToMs)`.
+    pub sent_from_ms: Option<u64>,
+    pub sent_to_ms: Option<u64>,
+    /// Time of the last state change, `[updatedFromMs, updatedToMs)`.
+    pub updated_from_ms: Option<u64>,
+    pub updated_to_ms: Option<u64>,
+}
+
+impl OrderFilter {
+    fn matches(&self, o: &OrderContext) -> bool {
+        let eq = |want: &Option<String>, have: &Option<String>| {
+            want.as_ref().is_none_or(|w| have.as_ref() == Some(w))
+        };
+        let within = |from: Option<u64>, to: Option<u64>, at: u64| {
+            from.is_none_or(|from| at >= from) && to.is_none_or(|to| at < to)
+        };
+        self.session_id.as_ref().is_none_or(|id| *id == o.session_id)
+            && self.states.as_ref().is_none_or(|s| s.contains(&o.state))
+            && eq(&self.symbol, &o.symbol)
+            && eq(&self.side, &o.side)
+            && eq(&self.account, &o.account)
+            && within(self.sent_from_ms, self.sent_to_ms, o.sent_at_ms)
+            && within(self.updated_from_ms, self.updated_to_ms, o.updated_at_ms)
+    }
+}
+
+#[derive(Debug, Default, Clone, InputObject)]
+pub struct MessageFilter {
+    pub session_id: Option<String>,
+    pub direction: Option<Direction>,
+    /// MsgType value (`D`) or name (`NewOrderSingle`).
+    pub msg_type: Option<String>,
+    pub cl_ord_id: Option<String>,
+    pub symbol: Option<String>,
+    /// Recording time range `[fromMs, toMs)`.
+    pub from_ms: Option<u64>,
+    pub to_ms: Option<u64>,
+    /// Messages sent by this API key or OIDC user.
+    pub principal: Option<String>,
+}
+
+impl From<MessageFilter> for MessageQuery {
+    fn from(f: MessageFilter) -> Self {
+        MessageQuery {
+            session_id: f.session_id,
+            direction: f.direction,
+            msg_type: f.msg_type,
+            cl_ord_id: f.cl_ord_id,
+            symbol: f.symbol,
+            from_ms: f.from_ms,
+            to_ms: f.to_ms,
+            principal: f.principal,
+            ..MessageQuery::default()
+        }
+    }
+}
+
+/// Orders matching `filter`, oldest first.
+async fn find_orders(state: &AppState, filter: &OrderFilter, limit: usize) -> Vec<Order> {
+    let orders = state.orders.lock().await;
+    let mut matching: Vec<&OrderContext> = orders.all().filter(|o| filter.matches(o)).collect();
+    matching.sort_by(|a, b| (a.sent_at_ms, &a.cl_ord_id).cmp(&(b.sent_at_ms, &b.cl_ord_id)));
+    matching
+        .into_iter()
+        .take(limit)
+        .map(|o| Order(o.clone()))
+        .collect()
+}
+
+/// Messages matching `q`, oldest first.
+async fn find_messages(state: &AppState, q: &MessageQuery, limit: usize) -> Vec<Message> {
+    state
+        .messages
+        .lock()
+        .await
+        .matching(q)
+        .take(limit)
+        .map(|m| Message(m.clone()))
+        .collect()
+}
+
+// ── Query root ────────────────────────────────────────────────────────────────
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    /// Sessions in registration order, optionally only those in one of
+    /// `states` or with one counterparty.
+    async fn sessions(
+        &self,
+        ctx: &Context<'_>,
+        states: Option<Vec<String>>,
+        counterparty: Option<String>,
+    ) -> async_graphql::Result<Vec<Session>> {
+        let sessions = app_state(ctx).sessions.snapshots();
+        Ok(sessions
+            .iter()
+            .filter(|s| {
+                states.as_ref().is_none_or(|states| {
+                    states.iter().any(|st| st.eq_ignore_ascii_case(&s.state))
+                })
+            })
+            .filter(|s| counterparty.as_ref().is_none_or(|c| *c == s.target_comp_id))
+            .map(|s| Session(FixSession::clone(s)))
+            .collect())
+    }
+
+    async fn session(
+        &self,
+        ctx: &Context<'_>,
+        session_id: String,
+    ) -> async_graphql::Result<Option<Session>> {
+        let session = app_state(ctx).sessions.snapshot(&session_id);
+        Ok(session.map(|s| Session(FixSession::clone(&s))))
+    }
+
+    /// Orders sent through the gateway, oldest first.
+    async fn orders(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<OrderFilter>,
+        limit: Option<usize>,
+    ) -> async_graphql::Result<Vec<Order>> {
+        let limit = page_size(limit)?;
+        Ok(find_orders(app_state(ctx), &filter.unwrap_or_default(), limit).await)
+    }
+
+    /// An order by its ClOrdID or the ClOrdID of a cancel request for it.
+    async fn order(&self, ctx: &Context<'_>, cl_ord_id: String) -> Option<Order> {
+        let orders = app_state(ctx).orders.lock().await;
+        orders.get(&cl_ord_id).map(|o| Order(o.clone()))
+    }
+
+    /// Stored messages, oldest first.
+    async fn messages(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<MessageFilter>,
+        limit: Option<usize>,
+    ) -> async_graphql::Result<Vec<Message>> {
+        let q = MessageQuery::from(filter.unwrap_or_default());
+        Ok(find_messages(app_state(ctx), &q, page_size(limit)?).await)
+    }
+}
+
+// ── Objects ───────────────────────────────────────────────────────────────────
+
+pub struct Session(FixSession);
+
+#[Object]
+impl Session {
+    async fn session_id(&self) -> &str {
+        &self.0.session_id
+    }
+
+    async fn sender_comp_id(&self) -> &str {
+        &self.0.sender_comp_id
+    }
+
+    async fn target_comp_id(&self) -> &str {
+        &self.0.target_comp_id
+    }
+
+    async fn fix_version(&self) -> &str {
+        &self.0.fix_version
+    }
+
+    async fn state(&self) -> &str {
+        &self.0.state
+    }
+
+    async fn msg_seq_num(&self) -> u64 {
+        self.0.msg_seq_num
+    }
+
+    async fn expected_seq_num(&self) -> u64 {
+        self.0.expected_seq_num
+    }
+
+    async fn connected_at(&self) -> u64 {
+        self.0.connected_at
+    }
+
+    /// The session's orders, oldest first; `filter.sessionId` is ignored.
+    async fn orders(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<OrderFilter>,
+        limit: Option<usize>,
+    ) -> async_graphql::Result<Vec<Order>> {
+        let filter = OrderFilter {
+            session_id: Some(self.0.session_id.clone()),
+            ..filter.unwrap_or_default()
+        };
+        Ok(find_orders(app_state(ctx), &filter, page_size(limit)?).await)
+    }
+
+    /// The session's stored messages, oldest first; `filter.sessionId` is
+    /// ignored.
+    async fn messages(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<MessageFilter>,
+        limit: Option<usize>,
+    ) -> async_graphql::Result<Vec<Message>> {
+        let q = MessageQuery {
+            session_id: Some(self.0.session_id.clone()),
+            ..MessageQuery::from(filter.unwrap_or_default())
+        };
+        Ok(find_messages(app_state(ctx), &q, page_size(limit)?).await)
+    }
+}
+
+pub struct Order(OrderContext);
+
+#[Object]
+impl Order {
+    async fn cl_ord_id(&self) -> &str {
+        &self.0.cl_ord_id
+    }
+
+    async fn session_id(&self) -> &str {
+        &self.0.session_id
+    }
+
+    async fn symbol(&self) -> Option<&str> {
+        self.0.symbol.as_deref()
+    }
+
+    async fn side(&self) -> Option<&str> {
+        self.0.side.as_deref()
+    }
+
+    async fn order_qty(&self) -> Option<&str> {
+        self.0.order_qty.as_deref()
+    }
+
+    async fn ord_type(&self) -> Option<&str> {
+        self.0.ord_type.as_deref()
+    }
+
+    async fn price(&self) -> Option<&str> {
+        self.0.price.as_deref()
+    }
+
+    async fn account(&self) -> Option<&str> {
+        self.0.account.as_deref()
+    }
+
+    async fn time_in_force(&self) -> Option<&str> {
+        self.0.time_in_force.as_deref()
+    }
+
+    async fn metadata(&self) -> &HashMap<String, String> {
+        &self.0.metadata
+    }
+
+    async fn state(&self) -> OrderState {
+        self.0.state
+    }
+
+    async fn list_id(&self) -> Option<&str> {
+        self.0.list_id.as_deref()
+    }
+
+    async fn orig_cl_ord_id(&self) -> Option<&str> {
+        self.0.orig_cl_ord_id.as_deref()
+    }
+
+    async fn sent_at_ms(&self) -> u64 {
+        self.0.sent_at_ms
+    }
+
+    async fn updated_at_ms(&self) -> u64 {
+        self.0.updated_at_ms
+    }
+
+    async fn cum_qty(&self) -> f64 {
+        self.0.cum_qty
+    }
+
+    async fn leaves_qty(&self) -> Option<f64> {
+        self.0.leaves_qty
+    }
+
+    async fn avg_px(&self) -> Option<f64> {
+        self.0.avg_px
+    }
+
+    async fn last_exec(&self) -> Option<&LastExec> {
+        self.0.last_exec.as_ref()
+    }
+
+    /// Every state the order has been in, oldest first.
+    async fn history(&self) -> &[StateChange] {
+        &self.0.history
+    }
+
+    async fn fills(&self) -> &[Fill] {
+        &self.0.fills
+    }
+
+    async fn session(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Session>> {
+        let session = app_state(ctx).sessions.snapshot(&self.0.session_id);
+        Ok(session.map(|s| Session(FixSession::clone(&s))))
+    }
+
+    /// The ExecutionReports correlated with the order, oldest first.
+    async fn execution_reports(&self, ctx: &Context<'_>) -> Vec<Message> {
+        let messages = app_state(ctx).messages.lock().await;
+        messages
+            .for_session(&self.0.session_id)
+            .filter(|m| report_order(m) == Some(self.0.cl_ord_id.as_str()))
+            .map(|m| Message(m.clone()))
+            .collect()
+    }
+
+    /// The messages sent or received with the order's ClOrdID (11), oldest
+    /// first.
+    async fn messages(&self, ctx: &Context<'_>) -> Vec<Message> {
+        let q = MessageQuery {
+            session_id: Some(self.0.session_id.clone()),
+            cl_ord_id: Some(self.0.cl_ord_id.clone()),
+            ..MessageQuery::default()
+        };
+        find_messages(app_state(ctx), &q, MAX_LIMIT).await
+    }
+}
+
+/// The ClOrdID of the order an ExecutionReport was correlated with.
+fn report_order(m: &StoredMessage) -> Option<&str> {
+    let order = m.execution.as_ref()?.order.as_ref()?;
+    Some(order.cl_ord_id.as_str())
+}
+
+pub struct Message(StoredMessage);
+
+#[Object]
+impl Message {
+    async fn session_id(&self) -> &str {
+        &self.0.session_id
+    }
+
+    async fn direction(&self) -> Direction {
+        self.0.direction
+    }
+
+    async fn msg_type(&self) -> &str {
+        &self.0.msg_type
+    }
+
+    async fn msg_type_name(&self) -> String {
+        crate::fix_msg_type_name(&self.0.msg_type)
+    }
+
+    async fn sequence_number(&self) -> Option<u64> {
+        self.0.sequence_number
+    }
+
+    async fn recorded_at_ms(&self) -> u64 {
+        self.0.recorded_at_ms
+    }
+
+    /// The message as sent or received.
+    async fn fix_message(&self) -> String {
+        String::from_utf8_lossy(&self.raw()).into_owned()
+    }
+
+    /// The first value of `tag`.
+    async fn field(&self, tag: u32) -> Option<String> {
+        let raw = self.raw();
+        store::field_value(&raw, tag).map(|v| String::from_utf8_lossy(v).into_owned())
+    }
+
+    /// For an outbound message a client sent, the API key or OIDC user that
+    /// sent it.
+    async fn principal(&self) -> Option<&str> {
+        self.0.attribution.as_ref()?.principal.as_deref()
+    }
+
+    /// For an outbound message a client sent, the address it came from.
+    async fn source_ip(&self) -> Option<String> {
+        self.0.attribution.as_ref()?.source_ip.map(|ip| ip.to_string())
+    }
+
+    /// For an ExecutionReport, how it relates to the orders the gateway sent.
+    async fn correlation(&self) -> Option<Correlation> {
+        self.0.execution.as_ref().map(|e| e.correlation)
+    }
+
+    /// For an ExecutionReport, the order it was correlated with, as it is now.
+    async fn order(&self, ctx: &Context<'_>) -> Option<Order> {
+        let cl_ord_id = report_order(&self.0)?;
+        let orders = app_state(ctx).orders.lock().await;
+        orders.get(cl_ord_id).map(|o| Order(o.clone()))
+    }
+}
+
+impl Message {
+    fn raw(&self) -> Vec<u8> {
+        BASE64.decode(&self.0.raw_base64).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(session_id: &str, state: OrderState, updated_at_ms: u64) -> OrderContext {
+        let no_fields = HashMap::new();
+        let mut o = OrderContext::from_fields("O1", session_id, &no_fields, &no_fields, 0);
+        o.state = state;
+        o.updated_at_ms = updated_at_ms;
+        o
+    }
+
+    #[test]
+    fn order_filters_combine() {
+        let filter = OrderFilter {
+            session_id: Some("ALICE->BROKER_A".to_string()),
+            states: Some(vec![OrderState::Rejected]),
+            updated_from_ms: Some(1_000),
+            ..OrderFilter::default()
+        };
+        assert!(filter.matches(&order("ALICE->BROKER_A", OrderState::Rejected, 1_500)));
+        assert!(!filter.matches(&order("ALICE->BROKER_A", OrderState::Rejected, 500)));
+        assert!(!filter.matches(&order("ALICE->BROKER_A", OrderState::Filled, 1_500)));
+        assert!(!filter.matches(&order("ALICE->BROKER_B", OrderState::Rejected, 1_500)));
+        assert!(OrderFilter::default().matches(&order("ALICE->BROKER_B", OrderState::New, 0)));
+    }
+
+    #[test]
+    fn limits_are_bounded() {
+        assert_eq!(page_size(None).unwrap(), DEFAULT_LIMIT);
+        assert!(page_size(Some(0)).is_err());
+        assert!(page_size(Some(MAX_LIMIT + 1)).is_err());
+    }
+
+    #[test]
+    fn schema_exposes_the_query_root() {
+        let sdl = Schema::build(Query, EmptyMutation, EmptySubscription)
+            .finish()
+            .sdl();
+        for field in ["sessions(", "orders(", "messages(", "executionReports"] {
+            assert!(sdl.contains(field), "{field} missing from schema");
+        }
+    }
+}
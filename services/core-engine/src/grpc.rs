@@ -0,0 +1,524 @@
+//! gRPC API, for internal consumers that only speak gRPC.
+//!
+//! Built with the `grpc` feature and served on `FIX_GRPC_ADDR` alongside the
+//! REST API. Send, Parse, Validate, ListSessions and StreamTraffic mirror the
+//! REST endpoints of the same names and share their code. MessageFlow
+//! combines sending and the traffic stream on one bidirectional stream: each
+//! send is answered by an event carrying its correlation ID, and matching
+//! traffic is interleaved once the client has set a filter.
+//!
+//! Calls are authenticated like REST requests, by an API key in `x-api-key`
+//! or a bearer token in `authorization` metadata. Every call needs the
+//! `read` scope; each send needs `send`, a session the principal may send
+//! on, and a token from its client's rate limit bucket, as does `Validate`.
+
+use axum::http::StatusCode;
+use futures_util::stream::{self, Stream};
+use std::{net::SocketAddr, pin::Pin, sync::Arc};
+use tokio::sync::{broadcast, mpsc};
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+use tracing::{info, warn};
+
+use crate::{
+    attribution::Attribution,
+    auth::{self, Caller, Scope},
+    fix_json::OutputEncoding,
+    parse_checked, parse_raw,
+    problem::ApiError,
+    rate_limit,
+    records::Direction,
+    traffic::{Filter, StreamQuery, Traffic},
+    AppState, ErrorCategory, ParseResponse, SendOptions, Submitted, ValidationError,
+};
+
+pub mod proto {
+    tonic::include_proto!("alice.fix.v1");
+}
+
+use proto::{
+    fix_gateway_server::{FixGateway, FixGatewayServer},
+    flow_event::Event,
+    flow_request,
+    send_response::Outcome,
+};
+
+/// Events buffered per MessageFlow client before its sends wait.
+const FLOW_CAPACITY: usize = 1024;
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+/// Serves the gRPC API on `addr`.
+pub async fn serve(state: AppState, addr: SocketAddr) {
+    info!("ALICE FIX Engine gRPC listening on {}", addr);
+    let service = FixGatewayServer::new(GrpcService { state });
+    if let Err(e) = Server::builder().add_service(service).serve(addr).await {
+        warn!(addr = %addr, error = %e, "gRPC server stopped");
+    }
+}
+
+struct GrpcService {
+    state: AppState,
+}
+
+/// Who made a call.
+#[derive(Clone)]
+struct Call {
+    caller: Caller,
+    /// The client its rate limit counts it against.
+    client: String,
+    attribution: Attribution,
+}
+
+impl GrpcService {
+    /// Authenticates a call to `method` as the REST API does its requests,
+    /// and checks that it grants `scope`.
+    async fn authenticate<T>(
+        &self,
+        request: &Request<T>,
+        method: &str,
+        scope: Scope,
+    ) -> Result<Call, Status> {
+        let headers = request.metadata().clone().into_headers();
+        let principal = auth::identify(&self.state, &headers).await.map_err(|e| {
+            warn!(method, reason = e.message(), "Rejected unauthenticated gRPC call");
+            problem(e)
+        })?;
+        if let Some(principal) = &principal {
+            auth::check_scope(principal, scope, method).map_err(problem)?;
+        }
+        let source_ip = request.remote_addr().map(|addr| addr.ip());
+        Ok(Call {
+            client: rate_limit::client(principal.as_ref(), source_ip),
+            attribution: Attribution::new(principal.as_ref(), source_ip, &headers),
+            caller: Caller(principal),
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl FixGateway for GrpcService {
+    type StreamTrafficStream = ResponseStream<proto::TrafficMessage>;
+    type MessageFlowStream = ResponseStream<proto::FlowEvent>;
+
+    async fn send(
+        &self,
+        request: Request<proto::SendRequest>,
+    ) -> Result<Response<proto::SendResponse>, Status> {
+        let call = self.authenticate(&request, "Send", Scope::Send).await?;
+        submit(&self.state, request.into_inner(), &call)
+            .await
+            .map(Response::new)
+    }
+
+    async fn parse(
+        &self,
+        request: Request<proto::ParseRequest>,
+    ) -> Result<Response<proto::ParsedMessage>, Status> {
+        self.authenticate(&request, "Parse", Scope::Read).await?;
+        let raw = request.into_inner().raw_message;
+        if raw.trim_ascii().is_empty() {
+            return Err(status(StatusCode::BAD_REQUEST));
+        }
+        let parsed = parse_checked(&self.state, &raw, None, None).map_err(status)?;
+        Ok(Response::new(parsed_message(parsed)))
+    }
+
+    async fn validate(
+        &self,
+        request: Request<proto::ValidateRequest>,
+    ) -> Result<Response<proto::ValidateResponse>, Status> {
+        let call = self.authenticate(&request, "Validate", Scope::Read).await?;
+        rate_limit::admit(&self.state, &call.client, "Validate", 1)
+            .await
+            .map_err(problem)?;
+        let req = request.into_inner();
+        let req = crate::ValidateRequest {
+            message: req.message,
+            raw_message: req.raw_message,
+            version: req.version,
+            session_id: req.session_id,
+            reject_unknown_tags: req.reject_unknown_tags,
+            strict: req.strict,
+        };
+        let validated = crate::validate_message(&self.state, req).await.map_err(status)?;
+        Ok(Response::new(proto::ValidateResponse {
+            valid: validated.valid,
+            version: validated.version,
+            msg_type: validated.msg_type,
+            errors: validated.errors.into_iter().map(validation_error).collect(),
+            warnings: validated.warnings.into_iter().map(validation_error).collect(),
+        }))
+    }
+
+    async fn list_sessions(
+        &self,
+        request: Request<proto::ListSessionsRequest>,
+    ) -> Result<Response<proto::ListSessionsResponse>, Status> {
+        self.authenticate(&request, "ListSessions", Scope::Read).await?;
+        let sessions = self
+            .state
+            .sessions
+            .snapshots()
+            .iter()
+            .map(|s| proto::Session {
+                session_id: s.session_id.clone(),
+                sender_comp_id: s.sender_comp_id.clone(),
+                target_comp_id: s.target_comp_id.clone(),
+                fix_version: s.fix_version.clone(),
+                state: s.state.clone(),
+                msg_seq_num: s.msg_seq_num,
+                expected_seq_num: s.expected_seq_num,
+                connected_at: s.connected_at,
+            })
+            .collect();
+        Ok(Response::new(proto::ListSessionsResponse { sessions }))
+    }
+
+    async fn stream_traffic(
+        &self,
+        request: Request<proto::TrafficFilter>,
+    ) -> Result<Response<Self::StreamTrafficStream>, Status> {
+        self.authenticate(&request, "StreamTraffic", Scope::Read).await?;
+        let filter = traffic_filter(request.into_inner());
+        info!(filter = ?filter, "gRPC traffic stream client connected");
+        let traffic = Some((self.state.traffic.subscribe(), filter));
+        let messages = stream::unfold(traffic, |mut traffic| async move {
+            let next = next_traffic(&mut traffic).await?;
+            Some((Ok(traffic_message(&next)), traffic))
+        });
+        Ok(Response::new(Box::pin(messages)))
+    }
+
+    async fn message_flow(
+        &self,
+        request: Request<Streaming<proto::FlowRequest>>,
+    ) -> Result<Response<Self::MessageFlowStream>, Status> {
+        // Its sends are checked for the `send` scope one by one.
+        let call = self.authenticate(&request, "MessageFlow", Scope::Read).await?;
+        let (sender, receiver) = mpsc::channel(FLOW_CAPACITY);
+        let flow = run_flow(self.state.clone(), request.into_inner(), call, sender);
+        tokio::spawn(flow);
+        let events = stream::unfold(receiver, |mut receiver| async move {
+            let event = receiver.recv().await?;
+            Some((event, receiver))
+        });
+        Ok(Response::new(Box::pin(events)))
+    }
+}
+
+// ── Message flow ─────────────────────────────────────────────────────────────
+
+/// A flow's traffic subscription and filter, once it has set one.
+type FlowTraffic = Option<(broadcast::Receiver<Arc<Traffic>>, Filter)>;
+
+/// Answers a MessageFlow client's requests and forwards its traffic until
+/// either side closes the stream. Its sends are made as `call`.
+async fn run_flow(
+    state: AppState,
+    mut requests: Streaming<proto::FlowRequest>,
+    call: Call,
+    events: mpsc::Sender<Result<proto::FlowEvent, Status>>,
+) {
+    let mut traffic: FlowTraffic = None;
+    info!("gRPC message flow client connected");
+    loop {
+        let event = tokio::select! {
+            request = requests.message() => match request {
+                Ok(Some(request)) => {
+                    flow_request(&state, request, &call, &mut traffic).await
+                }
+                Ok(None) | Err(_) => break,
+            },
+            next = next_traffic(&mut traffic) => match next {
+                Some(next) => Some(proto::FlowEvent {
+                    correlation_id: String::new(),
+                    event: Some(Event::Traffic(traffic_message(&next))),
+                }),
+                None => break,
+            },
+        };
+        if let Some(event) = event {
+            if events.send(Ok(event)).await.is_err() {
+                break;
+            }
+        }
+    }
+    info!("gRPC message flow client disconnected");
+}
+
+/// Sends a message or replaces the flow's filter. Only sends are answered.
+async fn flow_request(
+    state: &AppState,
+    request: proto::FlowRequest,
+    call: &Call,
+    traffic: &mut FlowTraffic,
+) -> Option<proto::FlowEvent> {
+    let event = match request.request {
+        Some(flow_request::Request::Filter(filter)) => {
+            let filter = traffic_filter(filter);
+            info!(filter = ?filter, "gRPC message flow filter set");
+            let receiver = match traffic.take() {
+                Some((receiver, _)) => receiver,
+                None => state.traffic.subscribe(),
+            };
+            *traffic = Some((receiver, filter));
+            return None;
+        }
+        Some(flow_request::Request::Send(req)) => {
+            match submit(state, req, call).await {
+                Ok(response) => Event::Sent(response),
+                Err(e) => flow_error(&e),
+            }
+        }
+        None => flow_error(&Status::invalid_argument("neither send nor filter given")),
+    };
+    Some(proto::FlowEvent {
+        correlation_id: request.correlation_id,
+        event: Some(event),
+    })
+}
+
+fn flow_error(status: &Status) -> Event {
+    Event::Error(proto::FlowError {
+        code: status.code() as i32,
+        message: status.message().to_string(),
+    })
+}
+
+/// The next message matching the filter; never resolves before a filter is
+/// set, and resolves to `None` once the feed is closed.
+async fn next_traffic(traffic: &mut FlowTraffic) -> Option<Arc<Traffic>> {
+    let Some((receiver, filter)) = traffic else {
+        return std::future::pending().await;
+    };
+    loop {
+        match receiver.recv().await {
+            Ok(next) if filter.matches(&next) => return Some(next),
+            Ok(_) => {}
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                warn!(missed, "gRPC traffic client lagging, messages dropped");
+            }
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
+
+// ── Conversions ──────────────────────────────────────────────────────────────
+
+/// Sends a message, or queues it if its session is not logged on, as `/send`
+/// does, once `call` may send it.
+async fn submit(
+    state: &AppState,
+    req: proto::SendRequest,
+    call: &Call,
+) -> Result<proto::SendResponse, Status> {
+    if let Some(principal) = &call.caller.0 {
+        auth::check_scope(principal, Scope::Send, "Send").map_err(problem)?;
+    }
+    let (opts, mut req) = send_request(req);
+    let session_id = crate::session_id_of(&req);
+    call.caller.check_session(&session_id).map_err(problem)?;
+    rate_limit::admit(state, &call.client, "Send", 1)
+        .await
+        .map_err(problem)?;
+    req.attribution = Some(call.attribution.clone());
+    let outcome = match crate::submit(state, &opts, req).await.map_err(problem)? {
+        Submitted::Sent(sent) => Outcome::Sent(proto::SentMessage {
+            session_id: sent.session_id,
+            msg_type: sent.msg_type,
+            sequence_number: sent.sequence_number,
+            fix_message: sent.fix_message,
+            sent_at_ms: sent.sent_at_ms,
+        }),
+        Submitted::Queued(queued) => Outcome::Queued(proto::QueuedMessage {
+            session_id: queued.session_id,
+            queue_id: queued.queue_id,
+            position: queued.position as u64,
+        }),
+    };
+    Ok(proto::SendResponse {
+        outcome: Some(outcome),
+    })
+}
+
+fn send_request(req: proto::SendRequest) -> (SendOptions, crate::SendRequest) {
+    let opts = SendOptions {
+        encoding: OutputEncoding::default(),
+        validate: req.validate,
+        strict: req.strict,
+    };
+    let groups = req
+        .groups
+        .into_iter()
+        .map(|(name, group)| {
+            let instances = group.instances.into_iter().map(|i| i.fields).collect();
+            (name, instances)
+        })
+        .collect();
+    let req = crate::SendRequest {
+        msg_type: req.msg_type,
+        fields: req.fields,
+        groups,
+        metadata: req.metadata,
+        reply_to: None,
+        ttl_ms: req.ttl_ms,
+        template: None,
+        attribution: None,
+    };
+    (opts, req)
+}
+
+fn traffic_filter(filter: proto::TrafficFilter) -> Filter {
+    let direction = match filter.direction() {
+        proto::Direction::Unspecified => None,
+        proto::Direction::Inbound => Some(Direction::Inbound),
+        proto::Direction::Outbound => Some(Direction::Outbound),
+    };
+    Filter::new(StreamQuery {
+        session_id: filter.session_id,
+        direction,
+        msg_type: Some(filter.msg_types.join(",")),
+    })
+}
+
+fn traffic_message(traffic: &Traffic) -> proto::TrafficMessage {
+    let direction = match traffic.direction {
+        Direction::Inbound => proto::Direction::Inbound,
+        Direction::Outbound => proto::Direction::Outbound,
+    };
+    proto::TrafficMessage {
+        session_id: traffic.session_id.clone(),
+        direction: direction.into(),
+        sequence_number: traffic.sequence_number,
+        at_ms: traffic.at_ms,
+        resend: traffic.resend,
+        raw: traffic.raw.clone(),
+        message: Some(parsed_message(parse_raw(&traffic.raw))),
+    }
+}
+
+fn parsed_message(parsed: ParseResponse) -> proto::ParsedMessage {
+    proto::ParsedMessage {
+        msg_type: parsed.msg_type,
+        fields: parsed
+            .fields
+            .into_iter()
+            .map(|f| proto::ParsedField {
+                tag: f.tag,
+                name: f.name,
+                value: f.value,
+                decoded: f.decoded,
+            })
+            .collect(),
+        raw_length: parsed.raw_length as u64,
+        errors: parsed.errors.into_iter().map(validation_error).collect(),
+    }
+}
+
+fn validation_error(e: ValidationError) -> proto::ValidationError {
+    proto::ValidationError {
+        field: e.field,
+        tag: e.tag,
+        message: e.message,
+        category: category_name(e.category),
+        path: e.path,
+    }
+}
+
+/// The category as the REST API spells it, e.g. `invalid_value`.
+fn category_name(category: ErrorCategory) -> String {
+    match serde_json::to_value(category) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+/// The gRPC status for a REST status code the shared handlers return.
+fn status(code: StatusCode) -> Status {
+    let message = code.canonical_reason().unwrap_or_default();
+    match code {
+        StatusCode::BAD_REQUEST => Status::invalid_argument(message),
+        StatusCode::UNAUTHORIZED => Status::unauthenticated(message),
+        StatusCode::FORBIDDEN => Status::permission_denied(message),
+        StatusCode::NOT_FOUND => Status::not_found(message),
+        StatusCode::CONFLICT => Status::already_exists(message),
+        StatusCode::UNPROCESSABLE_ENTITY => Status::failed_precondition(message),
+        StatusCode::TOO_MANY_REQUESTS => Status::resource_exhausted(message),
+        StatusCode::SERVICE_UNAVAILABLE | StatusCode::BAD_GATEWAY => Status::unavailable(message),
+        _ => Status::internal(message),
+    }
+}
+
+/// The gRPC status for a failed send, carrying the problem's detail.
+fn problem(error: ApiError) -> Status {
+    let code = status(error.status()).code();
+    match error.message() {
+        Some(detail) => Status::new(code, detail),
+        None => status(error.status()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn send_requests_carry_groups_and_options() {
+        let order = proto::GroupInstance {
+            fields: HashMap::from([("ClOrdID".to_string(), "O1".to_string())]),
+        };
+        let req = proto::SendRequest {
+            msg_type: "NewOrderList".to_string(),
+            groups: HashMap::from([(
+                "NoOrders".to_string(),
+                proto::Group {
+                    instances: vec![order],
+                },
+            )]),
+            validate: true,
+            strict: Some(false),
+            ..proto::SendRequest::default()
+        };
+        let (opts, req) = send_request(req);
+        assert!(opts.validate);
+        assert_eq!(opts.strict, Some(false));
+        assert_eq!(req.groups["NoOrders"][0]["ClOrdID"], "O1");
+    }
+
+    #[test]
+    fn unspecified_direction_streams_both_ways() {
+        let traffic = |direction| Traffic {
+            session_id: "ALICE->BROKER_A".to_string(),
+            direction,
+            msg_type: "8".to_string(),
+            sequence_number: Some(1),
+            raw: Vec::new(),
+            at_ms: 0,
+            resend: false,
+        };
+        let both = traffic_filter(proto::TrafficFilter {
+            msg_types: vec!["ExecutionReport".to_string()],
+            ..proto::TrafficFilter::default()
+        });
+        assert!(both.matches(&traffic(Direction::Inbound)));
+        assert!(both.matches(&traffic(Direction::Outbound)));
+
+        let inbound = traffic_filter(proto::TrafficFilter {
+            direction: proto::Direction::Inbound.into(),
+            ..proto::TrafficFilter::default()
+        });
+        assert!(inbound.matches(&traffic(Direction::Inbound)));
+        assert!(!inbound.matches(&traffic(Direction::Outbound)));
+    }
+
+    #[test]
+    fn rest_statuses_map_to_grpc_codes() {
+        assert_eq!(status(StatusCode::BAD_REQUEST).code(), tonic::Code::InvalidArgument);
+        assert_eq!(status(StatusCode::UNPROCESSABLE_ENTITY).code(), tonic::Code::FailedPrecondition);
+        assert_eq!(status(StatusCode::BAD_GATEWAY).code(), tonic::Code::Unavailable);
+        assert_eq!(status(StatusCode::FORBIDDEN).code(), tonic::Code::PermissionDenied);
+        assert_eq!(status(StatusCode::TOO_MANY_REQUESTS).code(), tonic::Code::ResourceExhausted);
+        assert_eq!(category_name(ErrorCategory::InvalidValue), "invalid_value");
+    }
+}
@@ -0,0 +1,341 @@
+//! Liveness and readiness probes.
+//!
+//! `GET /livez` answers as long as the process serves requests; a failing
+//! liveness probe means the gateway should be restarted. `GET /health` is the
+//! same, for existing monitors.
+//!
+//! `GET /readyz` answers 503 when the gateway cannot do its job, so a load
+//! balancer or Kubernetes stops routing to it until it recovers. It checks:
+//!
+//! - `store`: the message store directory takes a synced write.
+//! - `sessions`: every session in `FIX_REQUIRED_SESSIONS` is `ACTIVE`. An
+//!   entry may carry a UTC window, e.g. `ALICE->BROKER_A=07:30-16:30`, outside
+//!   which the session is not required.
+//! - `queues`: no session has `FIX_READY_MAX_QUEUE_DEPTH` or more messages
+//!   waiting in its outbound queue (default 80% of `FIX_OUTBOUND_QUEUE_DEPTH`).
+//!
+//! Every check is listed with its outcome and detail, in the response when
+//! ready and in the problem's `checks` member when not.
+
+use axum::{extract::State, http::StatusCode, response::Json};
+use serde::Serialize;
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::{
+    clock::epoch_ms,
+    outbound_queue::OutboundQueue,
+    problem::{ApiError, Problem},
+    registry::SessionRegistry,
+    store::parse_time_of_day,
+    AppState,
+};
+
+const DAY_MS: u64 = 86_400_000;
+/// Default queue depth threshold, as a percentage of the queue's capacity.
+const DEFAULT_QUEUE_PERCENT: usize = 80;
+
+/// A session that must be logged on, within its window if it has one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RequiredSession {
+    session_id: String,
+    /// Start and exclusive end, in milliseconds since midnight UTC. The end
+    /// is before the start for a window across midnight.
+    window: Option<(u64, u64)>,
+}
+
+impl RequiredSession {
+    /// `SESSION_ID` or `SESSION_ID=HH:MM-HH:MM`.
+    fn parse(entry: &str) -> Option<Self> {
+        let (session_id, window) = match entry.split_once('=') {
+            Some((session_id, window)) => {
+                let (start, end) = window.split_once('-')?;
+                let (start, end) = (parse_time_of_day(start)?, parse_time_of_day(end)?);
+                if start == end {
+                    return None;
+                }
+                (session_id, Some((start, end)))
+            }
+            None => (entry, None),
+        };
+        let session_id = session_id.trim();
+        (!session_id.is_empty()).then(|| Self {
+            session_id: session_id.to_string(),
+            window,
+        })
+    }
+
+    fn required_at(&self, now_ms: u64) -> bool {
+        let Some((start, end)) = self.window else {
+            return true;
+        };
+        let time = now_ms % DAY_MS;
+        if start < end {
+            (start..end).contains(&time)
+        } else {
+            time >= start || time < end
+        }
+    }
+}
+
+pub struct ReadinessConfig {
+    required: Vec<RequiredSession>,
+    max_queue_depth: usize,
+}
+
+impl ReadinessConfig {
+    /// Reads `FIX_REQUIRED_SESSIONS` (comma-separated) and
+    /// `FIX_READY_MAX_QUEUE_DEPTH`, which defaults to 80% of `queue_capacity`.
+    pub fn from_env(queue_capacity: usize) -> Self {
+        let var = |name| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let mut required = Vec::new();
+        if let Some(spec) = var("FIX_REQUIRED_SESSIONS") {
+            for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                match RequiredSession::parse(entry) {
+                    Some(session) => required.push(session),
+                    None => warn!(entry, "Ignoring malformed FIX_REQUIRED_SESSIONS entry"),
+                }
+            }
+        }
+        let max_queue_depth = var("FIX_READY_MAX_QUEUE_DEPTH")
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|&d| d > 0)
+            .unwrap_or_else(|| (queue_capacity * DEFAULT_QUEUE_PERCENT / 100).max(1));
+        Self {
+            required,
+            max_queue_depth,
+        }
+    }
+
+    /// What readiness requires, for startup reporting.
+    pub fn describe(&self) -> String {
+        let required: Vec<&str> = self.required.iter().map(|r| r.session_id.as_str()).collect();
+        format!(
+            "readiness required_sessions=[{}] max_queue_depth={}",
+            required.join(","),
+            self.max_queue_depth
+        )
+    }
+}
+
+// ── Checks ────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Check {
+    /// `store`, `sessions` or `queues`.
+    name: String,
+    status: CheckStatus,
+    detail: String,
+}
+
+impl Check {
+    fn new(name: &str, result: Result<String, String>) -> Self {
+        let (status, detail) = match result {
+            Ok(detail) => (CheckStatus::Pass, detail),
+            Err(detail) => (CheckStatus::Fail, detail),
+        };
+        Self {
+            name: name.to_string(),
+            status,
+            detail,
+        }
+    }
+}
+
+async fn check_store(state: &AppState) -> Result<String, String> {
+    match state.messages.lock().await.check_writable() {
+        Ok(()) => Ok("writable".to_string()),
+        Err(e) => Err(format!("not writable: {e}")),
+    }
+}
+
+fn check_sessions(
+    required: &[RequiredSession],
+    sessions: &SessionRegistry,
+    now_ms: u64,
+) -> Result<String, String> {
+    let due: Vec<&RequiredSession> = required.iter().filter(|r| r.required_at(now_ms)).collect();
+    let down: Vec<String> = due
+        .iter()
+        .filter_map(|r| match sessions.snapshot(&r.session_id) {
+            Some(s) if s.state == "ACTIVE" => None,
+            Some(s) => Some(format!("{} is {}", r.session_id, s.state)),
+            None => Some(format!("{} is not configured", r.session_id)),
+        })
+        .collect();
+    if !down.is_empty() {
+        return Err(down.join("; "));
+    }
+    Ok(format!(
+        "{} required ACTIVE, {} outside their window",
+        due.len(),
+        required.len() - due.len()
+    ))
+}
+
+fn check_queues(queue: &OutboundQueue, max_depth: usize) -> Result<String, String> {
+    match queue.deepest() {
+        Some((session_id, depth)) if depth >= max_depth => Err(format!(
+            "{session_id} has {depth} messages queued (limit {max_depth})"
+        )),
+        Some((session_id, depth)) => Ok(format!(
+            "deepest {session_id} with {depth} (limit {max_depth})"
+        )),
+        None => Ok(format!("empty (limit {max_depth})")),
+    }
+}
+
+async fn run_checks(state: &AppState) -> Vec<Check> {
+    let store = check_store(state).await;
+    let sessions = check_sessions(&state.readiness.required, &state.sessions, epoch_ms());
+    let max_depth = state.readiness.max_queue_depth;
+    let queues = check_queues(&*state.outbound_queue.lock().await, max_depth);
+    vec![
+        Check::new("store", store),
+        Check::new("sessions", sessions),
+        Check::new("queues", queues),
+    ]
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HealthResponse {
+    status: String,
+    uptime_secs: u64,
+    service: String,
+    version: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/livez",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "The process is serving requests", body = HealthResponse),
+    )
+)]
+pub async fn livez(State(state): State<AppState>) -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok".to_string(),
+        uptime_secs: state.start_time.elapsed().as_secs(),
+        service: "alice-fix-engine".to_string(),
+        version: "1.0.0".to_string(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "Same as /livez", body = HealthResponse),
+    )
+)]
+pub async fn health(state: State<AppState>) -> Json<HealthResponse> {
+    livez(state).await
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReadinessResponse {
+    ready: bool,
+    checks: Vec<Check>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "Ready for traffic", body = ReadinessResponse),
+        (
+            status = 503,
+            description = "Not ready; the problem's `checks` member lists every check",
+            body = Problem,
+            content_type = "application/problem+json"
+        ),
+    )
+)]
+pub async fn readyz(State(state): State<AppState>) -> Result<Json<ReadinessResponse>, ApiError> {
+    let checks = run_checks(&state).await;
+    let failed: Vec<&str> = checks
+        .iter()
+        .filter(|c| c.status == CheckStatus::Fail)
+        .map(|c| c.name.as_str())
+        .collect();
+    if failed.is_empty() {
+        return Ok(Json(ReadinessResponse {
+            ready: true,
+            checks,
+        }));
+    }
+    Err(ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "not_ready")
+        .detail(format!("failed: {}", failed.join(", ")))
+        .extension("checks", &checks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR_MS: u64 = 3_600_000;
+
+    fn required(entry: &str) -> RequiredSession {
+        RequiredSession::parse(entry).unwrap()
+    }
+
+    #[test]
+    fn required_sessions_parse_with_optional_windows() {
+        assert_eq!(required("ALICE->BROKER_A").window, None);
+        assert_eq!(
+            required(" ALICE->BROKER_A=07:30-16:30").window,
+            Some((7 * HOUR_MS + 1_800_000, 16 * HOUR_MS + 1_800_000))
+        );
+        for malformed in ["=07:30-16:30", "ALICE->BROKER_A=07:30", "ALICE->BROKER_A=9-17"] {
+            assert_eq!(RequiredSession::parse(malformed), None, "{malformed}");
+        }
+        assert_eq!(RequiredSession::parse("ALICE->BROKER_A=08:00-08:00"), None);
+    }
+
+    #[test]
+    fn windows_may_cross_midnight() {
+        let day = required("ALICE->BROKER_A=08:00-17:00");
+        assert!(day.required_at(3 * DAY_MS + 8 * HOUR_MS));
+        assert!(!day.required_at(3 * DAY_MS + 17 * HOUR_MS));
+        let night = required("ALICE->BROKER_A=22:00-06:00");
+        assert!(night.required_at(23 * HOUR_MS));
+        assert!(night.required_at(DAY_MS + 5 * HOUR_MS));
+        assert!(!night.required_at(12 * HOUR_MS));
+    }
+
+    #[test]
+    fn sessions_fail_when_a_required_one_is_down_in_its_window() {
+        let mut sessions = crate::seed_sessions(&crate::config::Config::default());
+        sessions[1].state = "DISCONNECTED".to_string();
+        let a = sessions[0].session_id.clone();
+        let b = sessions[1].session_id.clone();
+        let sessions = SessionRegistry::new(sessions);
+        let noon = 12 * HOUR_MS;
+
+        let all_day = [required(&a), required(&b)];
+        let err = check_sessions(&all_day, &sessions, noon).unwrap_err();
+        assert_eq!(err, format!("{b} is DISCONNECTED"));
+
+        let b_overnight = [required(&a), required(&format!("{b}=22:00-06:00"))];
+        assert_eq!(
+            check_sessions(&b_overnight, &sessions, noon),
+            Ok("1 required ACTIVE, 1 outside their window".to_string())
+        );
+        assert!(check_sessions(&[required("ALICE->NOBODY")], &sessions, noon).is_err());
+    }
+}
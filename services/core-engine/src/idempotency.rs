@@ -0,0 +1,381 @@
+//! Idempotency keys for `/send`.
+//!
+//! A request with an `Idempotency-Key` header is acted on once. The first
+//! successful response for the key is remembered for
+//! `FIX_IDEMPOTENCY_WINDOW_SECS`, and a retry with the same key gets that
+//! response again, marked `Idempotent-Replayed: true`, instead of sending the
+//! message a second time. A retry while the first request is still being
+//! handled gets `409 Conflict`, and reusing a key for a different message
+//! gets `422`. Failed requests are not remembered, so they can be retried
+//! with the same key.
+//!
+//! Keys belong to the principal that sent them, so two clients choosing the
+//! same key do not see each other's responses. The send runs on a task of
+//! its own that holds the key until the send is done, so a request that
+//! times out or whose client goes away still has its response remembered,
+//! and a retry gets `409` until then rather than sending the message again.
+
+use axum::{
+    body::{self, Body, Bytes},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, VecDeque},
+    future::Future,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+use tokio::sync::Mutex;
+use tracing::{error, info, Instrument, Span};
+
+use crate::{
+    clock::epoch_ms,
+    latency,
+    problem::{self, ApiError},
+    AppState,
+    SendRequest,
+};
+
+pub const HEADER: &str = "idempotency-key";
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+const DEFAULT_WINDOW_SECS: u64 = 86_400;
+const MAX_KEY_LEN: usize = 255;
+
+enum Entry {
+    /// The first request with the key is still being handled.
+    InFlight { fingerprint: u64 },
+    Done {
+        fingerprint: u64,
+        status: StatusCode,
+        body: Bytes,
+    },
+}
+
+/// A key, with the principal that sent it: empty without authentication.
+type Owned = (String, String);
+
+pub struct IdempotencyKeys {
+    window_ms: u64,
+    entries: HashMap<Owned, Entry>,
+    /// Completed keys with their completion time, oldest first, for expiry.
+    done: VecDeque<(u64, Owned)>,
+}
+
+/// What to do with a request carrying a key.
+#[derive(Debug, PartialEq)]
+pub enum Claim {
+    /// The key is new: handle the request.
+    New,
+    /// The key was used before: answer with this.
+    Replay(StatusCode, Bytes),
+}
+
+impl IdempotencyKeys {
+    /// Reads `FIX_IDEMPOTENCY_WINDOW_SECS`.
+    pub fn from_env() -> Self {
+        let secs = std::env::var("FIX_IDEMPOTENCY_WINDOW_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_WINDOW_SECS);
+        Self::new(secs * 1000)
+    }
+
+    pub fn new(window_ms: u64) -> Self {
+        Self {
+            window_ms,
+            entries: HashMap::new(),
+            done: VecDeque::new(),
+        }
+    }
+
+    /// Claims `owner`'s `key` for a request whose message has `fingerprint`.
+    pub fn claim(
+        &mut self,
+        owner: &str,
+        key: &str,
+        fingerprint: u64,
+        now_ms: u64,
+    ) -> Result<Claim, StatusCode> {
+        self.expire(now_ms);
+        let owned = (owner.to_string(), key.to_string());
+        match self.entries.get(&owned) {
+            None => {
+                self.entries.insert(owned, Entry::InFlight { fingerprint });
+                Ok(Claim::New)
+            }
+            Some(Entry::InFlight { .. }) => Err(StatusCode::CONFLICT),
+            Some(Entry::Done { fingerprint: f, .. }) if *f != fingerprint => {
+                Err(StatusCode::UNPROCESSABLE_ENTITY)
+            }
+            Some(Entry::Done { status, body, .. }) => Ok(Claim::Replay(*status, body.clone())),
+        }
+    }
+
+    /// Remembers the response to the request that claimed `owner`'s `key`.
+    pub fn complete(
+        &mut self,
+        owner: &str,
+        key: &str,
+        status: StatusCode,
+        body: Bytes,
+        now_ms: u64,
+    ) {
+        let owned = (owner.to_string(), key.to_string());
+        if let Some(entry) = self.entries.get_mut(&owned) {
+            if let Entry::InFlight { fingerprint } = *entry {
+                *entry = Entry::Done {
+                    fingerprint,
+                    status,
+                    body,
+                };
+                self.done.push_back((now_ms, owned));
+            }
+        }
+    }
+
+    /// Forgets `owner`'s claimed key whose request failed.
+    pub fn release(&mut self, owner: &str, key: &str) {
+        let owned = (owner.to_string(), key.to_string());
+        if matches!(self.entries.get(&owned), Some(Entry::InFlight { .. })) {
+            self.entries.remove(&owned);
+        }
+    }
+
+    fn expire(&mut self, now_ms: u64) {
+        while let Some((at_ms, _)) = self.done.front() {
+            if now_ms.saturating_sub(*at_ms) < self.window_ms {
+                break;
+            }
+            if let Some((_, key)) = self.done.pop_front() {
+                self.entries.remove(&key);
+            }
+        }
+    }
+}
+
+/// The request's idempotency key, if it has one. A key that is empty, too
+/// long or not visible ASCII is `400`.
+pub fn key(headers: &HeaderMap) -> Result<Option<String>, ApiError> {
+    let Some(value) = headers.get(HEADER) else {
+        return Ok(None);
+    };
+    let key = value.to_str().map(str::trim).unwrap_or_default();
+    if key.is_empty() || key.len() > MAX_KEY_LEN {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "invalid_idempotency_key").detail(
+            format!("Idempotency-Key must be 1 to {MAX_KEY_LEN} visible ASCII characters"),
+        ));
+    }
+    Ok(Some(key.to_string()))
+}
+
+/// A hash of the message a request sends, to tell a retry from a different
+/// message reusing the key.
+pub fn fingerprint(req: &SendRequest) -> u64 {
+    let sorted = |m: &HashMap<String, String>| -> BTreeMap<String, String> {
+        m.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    };
+    let mut hasher = DefaultHasher::new();
+    req.msg_type.hash(&mut hasher);
+    sorted(&req.fields).hash(&mut hasher);
+    for (name, instances) in &req.groups {
+        name.hash(&mut hasher);
+        for instance in instances {
+            sorted(instance).hash(&mut hasher);
+        }
+    }
+    sorted(&req.metadata).hash(&mut hasher);
+    req.ttl_ms.hash(&mut hasher);
+    req.template.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A claimed key whose request is being handled. Dropping it without
+/// [`Pending::complete`] releases the key.
+pub struct Pending {
+    keys: Arc<Mutex<IdempotencyKeys>>,
+    owner: String,
+    key: String,
+    completed: bool,
+}
+
+/// Claims `owner`'s key for the request: `Ok(Err(response))` is the
+/// remembered response to answer with.
+pub async fn claim(
+    state: &AppState,
+    owner: Option<&str>,
+    key: String,
+    req: &SendRequest,
+) -> Result<Result<Pending, Response>, ApiError> {
+    let owner = owner.unwrap_or_default().to_string();
+    let claim = state
+        .idempotency
+        .lock()
+        .await
+        .claim(&owner, &key, fingerprint(req), epoch_ms())
+        .map_err(|status| {
+            info!(idempotency_key = %key, status = status.as_u16(), "Idempotency key refused");
+            match status {
+                StatusCode::CONFLICT => ApiError::new(status, "idempotency_key_in_use")
+                    .detail("a request with this Idempotency-Key is still in progress"),
+                _ => ApiError::new(status, "idempotency_key_reused")
+                    .detail("this Idempotency-Key was used for a different message"),
+            }
+        })?;
+    Ok(match claim {
+        Claim::New => Ok(Pending {
+            keys: state.idempotency.clone(),
+            owner,
+            key,
+            completed: false,
+        }),
+        Claim::Replay(status, body) => {
+            info!(idempotency_key = %key, "Replaying response for idempotency key");
+            Err((
+                status,
+                [
+                    (header::CONTENT_TYPE.as_str(), "application/json"),
+                    (REPLAYED_HEADER, "true"),
+                ],
+                body,
+            )
+                .into_response())
+        }
+    })
+}
+
+/// Runs `send` on a task of its own, which keeps `pending`'s key claimed
+/// until `send` is done and then remembers its response. The request
+/// awaiting it can time out or be dropped without the key being released
+/// while the message may still go out.
+pub async fn run<F>(pending: Pending, send: F) -> Result<Response, ApiError>
+where
+    F: Future<Output = Result<Response, ApiError>> + Send + 'static,
+{
+    let (correlation_id, received_at) = (problem::current_correlation_id(), latency::received_at());
+    let task = async move {
+        match send.await {
+            Ok(response) => Ok(pending.complete(response).await),
+            Err(e) => Err(e),
+        }
+    };
+    let task = latency::received(received_at, task.instrument(Span::current()));
+    tokio::spawn(problem::correlated(correlation_id, task))
+        .await
+        .unwrap_or_else(|e| {
+            error!(error = %e, "Idempotent send task failed");
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "send_failed"))
+        })
+}
+
+impl Pending {
+    /// Remembers a successful response for the key, and returns it.
+    pub async fn complete(mut self, response: Response) -> Response {
+        if !response.status().is_success() {
+            return response;
+        }
+        let (parts, body) = response.into_parts();
+        let Ok(bytes) = body::to_bytes(body, usize::MAX).await else {
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        };
+        self.keys
+            .lock()
+            .await
+            .complete(&self.owner, &self.key, parts.status, bytes.clone(), epoch_ms());
+        self.completed = true;
+        Response::from_parts(parts, Body::from(bytes))
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        // Dropping cannot wait for the lock, so if it is held the key is
+        // released by a task once it is free.
+        match self.keys.try_lock() {
+            Ok(mut keys) => keys.release(&self.owner, &self.key),
+            Err(_) => {
+                let keys = self.keys.clone();
+                let (owner, key) = (std::mem::take(&mut self.owner), std::mem::take(&mut self.key));
+                tokio::spawn(async move { keys.lock().await.release(&owner, &key) });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "retry-7f3a";
+
+    #[test]
+    fn completed_keys_replay_their_response() {
+        let mut keys = IdempotencyKeys::new(60_000);
+        assert_eq!(keys.claim("desk-a", KEY, 1, 0), Ok(Claim::New));
+        assert_eq!(keys.claim("desk-a", KEY, 1, 10), Err(StatusCode::CONFLICT));
+
+        keys.complete("desk-a", KEY, StatusCode::OK, Bytes::from_static(b"{\"seq\":1}"), 20);
+        assert_eq!(
+            keys.claim("desk-a", KEY, 1, 30),
+            Ok(Claim::Replay(StatusCode::OK, Bytes::from_static(b"{\"seq\":1}")))
+        );
+        assert_eq!(keys.claim("desk-a", KEY, 2, 30), Err(StatusCode::UNPROCESSABLE_ENTITY));
+        // Another principal's key of the same name is its own.
+        assert_eq!(keys.claim("desk-b", KEY, 2, 30), Ok(Claim::New));
+    }
+
+    #[test]
+    fn keys_expire_after_the_window_and_failures_release_them() {
+        let mut keys = IdempotencyKeys::new(60_000);
+        keys.claim("", KEY, 1, 0).unwrap();
+        keys.complete("", KEY, StatusCode::OK, Bytes::new(), 0);
+        assert_eq!(keys.claim("", KEY, 2, 60_000), Ok(Claim::New));
+
+        keys.release("", KEY);
+        assert_eq!(keys.claim("", KEY, 3, 60_001), Ok(Claim::New));
+    }
+
+    #[tokio::test]
+    async fn a_send_outliving_its_request_keeps_the_key_and_its_response() {
+        let keys = Arc::new(Mutex::new(IdempotencyKeys::new(60_000)));
+        keys.lock().await.claim("", KEY, 1, epoch_ms()).unwrap();
+        let pending = Pending {
+            keys: keys.clone(),
+            owner: String::new(),
+            key: KEY.to_string(),
+            completed: false,
+        };
+        let (go, wait) = tokio::sync::oneshot::channel::<()>();
+        let send = async move {
+            let _ = wait.await;
+            Ok((StatusCode::OK, "{\"seq\":1}").into_response())
+        };
+        // The request gives up, as on a timeout, before the send is done.
+        let request = tokio::time::timeout(std::time::Duration::from_millis(10), run(pending, send));
+        assert!(request.await.is_err());
+        assert_eq!(keys.lock().await.claim("", KEY, 1, epoch_ms()), Err(StatusCode::CONFLICT));
+
+        go.send(()).unwrap();
+        for _ in 0..100 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(
+            keys.lock().await.claim("", KEY, 1, epoch_ms()),
+            Ok(Claim::Replay(StatusCode::OK, Bytes::from_static(b"{\"seq\":1}")))
+        );
+    }
+
+    #[test]
+    fn keys_are_validated() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(key(&headers).unwrap(), None);
+        headers.insert(HEADER, KEY.parse().unwrap());
+        assert_eq!(key(&headers).unwrap(), Some(KEY.to_string()));
+        headers.insert(HEADER, " ".parse().unwrap());
+        assert_eq!(key(&headers).unwrap_err().status(), StatusCode::BAD_REQUEST);
+    }
+}
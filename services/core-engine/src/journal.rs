@@ -0,0 +1,263 @@
+//! Write-ahead journal of session state.
+//!
+//! With `FIX_STORE_DIR` set, every change to a session's sequence numbers or
+//! state is appended to `sessions.journal` there and synced to disk, by the
+//! group commit, before the change is acknowledged: the next outbound
+//! MsgSeqNum before `/send` returns, the next expected inbound MsgSeqNum
+//! before `/inbound` returns, and state transitions. After a crash the
+//! journal gives each session's exact numbers, so the gateway neither reuses
+//! a MsgSeqNum nor asks the counterparty to resend what it already
+//! processed. Sessions configured with `store = "memory"` are not
+//! journalled.
+//!
+//! The journal is rewritten as one entry per session when it is opened and
+//! again every [`COMPACT_AFTER`] appends.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tracing::{info, warn};
+
+use crate::{
+    commit::{Durable, GroupCommit},
+    records,
+};
+
+const FILE_NAME: &str = "sessions.journal";
+const COMPACT_AFTER: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub session_id: String,
+    /// Next outbound MsgSeqNum.
+    pub msg_seq_num: u64,
+    /// Next inbound MsgSeqNum expected from the counterparty.
+    pub expected_seq_num: u64,
+    pub state: String,
+    pub at_ms: u64,
+}
+
+#[derive(Default)]
+pub struct SessionJournal {
+    path: Option<PathBuf>,
+    file: Option<Arc<File>>,
+    commit: GroupCommit,
+    latest: HashMap<String, JournalEntry>,
+    appended: usize,
+    /// Sessions whose changes are not journalled.
+    excluded: HashSet<String>,
+}
+
+impl SessionJournal {
+    /// A journal in `FIX_STORE_DIR`, or a disabled one if unset.
+    pub fn from_env() -> io::Result<Self> {
+        match std::env::var("FIX_STORE_DIR") {
+            Ok(dir) if !dir.trim().is_empty() => Self::open(Path::new(dir.trim())),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    fn open(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(FILE_NAME);
+        let mut journal = Self {
+            path: Some(path.clone()),
+            ..Self::default()
+        };
+        if path.exists() {
+            for entry in load_journal(&path)? {
+                journal.apply(entry);
+            }
+        }
+        journal.compact()?;
+        Ok(journal)
+    }
+
+    /// Where the journal is kept, for startup reporting.
+    pub fn describe(&self) -> String {
+        match &self.path {
+            Some(path) => format!(
+                "session journal (file: {}) sessions={}",
+                path.display(),
+                self.latest.len()
+            ),
+            None => "session journal (disabled)".to_string(),
+        }
+    }
+
+    /// A session's last journalled numbers and state.
+    pub fn get(&self, session_id: &str) -> Option<&JournalEntry> {
+        self.latest.get(session_id)
+    }
+
+    /// Stops journalling `session_id`, a session kept in memory only, and
+    /// forgets what was journalled for it.
+    pub fn exclude(&mut self, session_id: &str) {
+        self.latest.remove(session_id);
+        self.excluded.insert(session_id.to_string());
+    }
+
+    /// Syncs appends through the group commit task rather than one by one.
+    pub fn set_group_commit(&mut self, commit: GroupCommit) {
+        self.commit = commit;
+    }
+
+    /// Records a change; with a file it is on disk once the returned
+    /// [`Durable`] resolves, which the caller awaits without the journal lock.
+    pub fn append(&mut self, entry: JournalEntry) -> io::Result<Durable> {
+        if self.excluded.contains(&entry.session_id) {
+            return Ok(Durable::done());
+        }
+        let mut durable = Durable::done();
+        if let Some(file) = &self.file {
+            let mut line = records::encode(&entry).to_string();
+            line.push('\n');
+            (&**file).write_all(line.as_bytes())?;
+            durable = self.commit.sync(file);
+            self.appended += 1;
+        }
+        self.apply(entry);
+        // Compaction syncs the rewritten journal itself, so a sync still
+        // waiting on the replaced file loses nothing.
+        if self.appended >= COMPACT_AFTER {
+            if let Err(e) = self.compact() {
+                warn!(error = %e, "Failed to compact the session journal");
+            }
+        }
+        Ok(durable)
+    }
+
+    /// Sequence numbers only move forward, so entries journalled out of order
+    /// by concurrent sends cannot wind a session back; the state is the one
+    /// written last.
+    fn apply(&mut self, entry: JournalEntry) {
+        match self.latest.get_mut(&entry.session_id) {
+            Some(latest) => {
+                latest.msg_seq_num = latest.msg_seq_num.max(entry.msg_seq_num);
+                latest.expected_seq_num = latest.expected_seq_num.max(entry.expected_seq_num);
+                latest.state = entry.state;
+                latest.at_ms = entry.at_ms;
+            }
+            None => {
+                self.latest.insert(entry.session_id.clone(), entry);
+            }
+        }
+    }
+
+    /// Rewrites the journal as one entry per session, via a temporary file
+    /// renamed over it so a crash leaves either the old journal or the new.
+    fn compact(&mut self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let tmp = path.with_extension("journal.tmp");
+        let mut file = File::create(&tmp)?;
+        let mut entries: Vec<&JournalEntry> = self.latest.values().collect();
+        entries.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        for entry in entries {
+            let mut line = records::encode(entry).to_string();
+            line.push('\n');
+            file.write_all(line.as_bytes())?;
+        }
+        file.sync_data()?;
+        fs::rename(&tmp, path)?;
+        self.file = Some(Arc::new(OpenOptions::new().append(true).open(path)?));
+        self.appended = 0;
+        Ok(())
+    }
+}
+
+/// Reads journal entries, skipping a line torn by a crash mid-write.
+fn load_journal(path: &Path) -> io::Result<Vec<JournalEntry>> {
+    let mut entries = Vec::new();
+    for (n, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line)
+            .map_err(|e| records::RecordError::Malformed(e.to_string()))
+            .and_then(records::decode::<JournalEntry>)
+        {
+            Ok(entry) => entries.push(entry),
+            Err(e) => warn!(
+                path = %path.display(),
+                line = n + 1,
+                error = %e,
+                "Skipping unreadable journal entry"
+            ),
+        }
+    }
+    info!(path = %path.display(), entries = entries.len(), "Session journal loaded");
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(
+        session_id: &str,
+        msg_seq_num: u64,
+        expected_seq_num: u64,
+        state: &str,
+    ) -> JournalEntry {
+        JournalEntry {
+            session_id: session_id.to_string(),
+            msg_seq_num,
+            expected_seq_num,
+            state: state.to_string(),
+            at_ms: 0,
+        }
+    }
+
+    #[test]
+    fn recovers_numbers_after_a_torn_write() {
+        let dir = std::env::temp_dir().join(format!("fix-journal-{}", uuid::Uuid::new_v4()));
+        {
+            let mut journal = SessionJournal::open(&dir).unwrap();
+            journal
+                .append(entry("ALICE->BROKER_A", 5, 3, "ACTIVE"))
+                .unwrap();
+            journal
+                .append(entry("ALICE->BROKER_A", 7, 3, "ACTIVE"))
+                .unwrap();
+            // Journalled after a later send by a concurrent request.
+            journal
+                .append(entry("ALICE->BROKER_A", 6, 4, "DISCONNECTED"))
+                .unwrap();
+            journal
+                .append(entry("ALICE->BROKER_B", 2, 9, "ACTIVE"))
+                .unwrap();
+        }
+        let path = dir.join(FILE_NAME);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"session_id":"ALICE->BROKER_B","msg_seq"#)
+            .unwrap();
+
+        let mut journal = SessionJournal::open(&dir).unwrap();
+        assert_eq!(
+            journal.get("ALICE->BROKER_A"),
+            Some(&entry("ALICE->BROKER_A", 7, 4, "DISCONNECTED"))
+        );
+        assert_eq!(
+            journal.get("ALICE->BROKER_B"),
+            Some(&entry("ALICE->BROKER_B", 2, 9, "ACTIVE"))
+        );
+        // Opening compacted it to one entry per session.
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+        journal.exclude("ALICE->BROKER_B");
+        journal
+            .append(entry("ALICE->BROKER_B", 3, 9, "ACTIVE"))
+            .unwrap();
+        assert_eq!(journal.get("ALICE->BROKER_B"), None);
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,226 @@
+//! Per-session latency histograms.
+//!
+//! Two latencies are measured for every session, in HDR histograms with
+//! nanosecond resolution:
+//!
+//! - `send`: from the gateway receiving the HTTP request (`/send`, a batch,
+//!   a v2 order, ...) to the message being written out. Messages sent later
+//!   from the outbound queue or with `?async=true` are timed from when their
+//!   send starts.
+//! - `inbound`: from an `/api/v1/fix/inbound` request being received to the
+//!   message being parsed.
+//!
+//! `GET /api/v1/fix/stats/latency` returns their percentiles since startup
+//! or the last reset.
+
+use axum::{
+    extract::{Query, Request, State},
+    middleware::Next,
+    response::{Json, Response},
+};
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    future::Future,
+    time::{Duration, Instant},
+};
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::AppState;
+
+/// Longest latency recorded exactly; longer ones count as this.
+const MAX_TRACKED_NS: u64 = 60_000_000_000;
+const SIGNIFICANT_DIGITS: u8 = 3;
+
+tokio::task_local! {
+    static RECEIVED_AT: Instant;
+}
+
+/// Notes when each request was received, for [`received_at`].
+pub async fn mark_receipt(request: Request, next: Next) -> Response {
+    RECEIVED_AT.scope(Instant::now(), next.run(request)).await
+}
+
+/// When the HTTP request being handled was received, or now outside one.
+pub fn received_at() -> Instant {
+    RECEIVED_AT.try_with(|at| *at).unwrap_or_else(|_| Instant::now())
+}
+
+/// Runs `task` for a request received at `at`, as a session's task does
+/// with the sends it is handed.
+pub async fn received<F: Future>(at: Instant, task: F) -> F::Output {
+    RECEIVED_AT.scope(at, task).await
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Send,
+    Inbound,
+}
+
+struct SessionLatency {
+    send: Histogram<u64>,
+    inbound: Histogram<u64>,
+}
+
+impl SessionLatency {
+    fn new() -> Self {
+        let histogram = || {
+            Histogram::new_with_bounds(1, MAX_TRACKED_NS, SIGNIFICANT_DIGITS)
+                .expect("histogram bounds are valid")
+        };
+        Self {
+            send: histogram(),
+            inbound: histogram(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LatencyStats {
+    by_session: HashMap<String, SessionLatency>,
+}
+
+impl LatencyStats {
+    pub fn record(&mut self, session_id: &str, stage: Stage, elapsed: Duration) {
+        let session = self
+            .by_session
+            .entry(session_id.to_string())
+            .or_insert_with(SessionLatency::new);
+        let histogram = match stage {
+            Stage::Send => &mut session.send,
+            Stage::Inbound => &mut session.inbound,
+        };
+        let ns = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        histogram.saturating_record(ns.clamp(1, MAX_TRACKED_NS));
+    }
+
+    fn summaries(&self, session_id: Option<&str>) -> Vec<SessionLatencySummary> {
+        let mut summaries: Vec<SessionLatencySummary> = self
+            .by_session
+            .iter()
+            .filter(|(id, _)| session_id.is_none_or(|s| s == id.as_str()))
+            .map(|(id, latency)| SessionLatencySummary {
+                session_id: id.clone(),
+                send: Percentiles::of(&latency.send),
+                inbound: Percentiles::of(&latency.inbound),
+            })
+            .collect();
+        summaries.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        summaries
+    }
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+/// Latencies in microseconds.
+#[derive(Debug, Default, PartialEq, Serialize, ToSchema)]
+pub struct Percentiles {
+    count: u64,
+    p50_us: f64,
+    p99_us: f64,
+    p999_us: f64,
+    max_us: f64,
+    mean_us: f64,
+}
+
+impl Percentiles {
+    fn of(histogram: &Histogram<u64>) -> Self {
+        if histogram.is_empty() {
+            return Self::default();
+        }
+        let us = |ns: u64| ns as f64 / 1000.0;
+        Self {
+            count: histogram.len(),
+            p50_us: us(histogram.value_at_quantile(0.5)),
+            p99_us: us(histogram.value_at_quantile(0.99)),
+            p999_us: us(histogram.value_at_quantile(0.999)),
+            max_us: us(histogram.max()),
+            mean_us: histogram.mean() / 1000.0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SessionLatencySummary {
+    session_id: String,
+    /// HTTP request received to message written out.
+    send: Percentiles,
+    /// Inbound message received to parsed.
+    inbound: Percentiles,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LatencyResponse {
+    sessions: Vec<SessionLatencySummary>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LatencyQuery {
+    /// Only this `SenderCompID->TargetCompID` session.
+    session_id: Option<String>,
+    /// Clear the histograms after reading them, to start a new window.
+    #[serde(default)]
+    reset: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/fix/stats/latency",
+    tag = "fix",
+    params(LatencyQuery),
+    responses(
+        (status = 200, description = "Latency percentiles by session", body = LatencyResponse),
+    )
+)]
+pub async fn get(
+    State(state): State<AppState>,
+    Query(query): Query<LatencyQuery>,
+) -> Json<LatencyResponse> {
+    let mut stats = state.latency.lock().await;
+    let sessions = stats.summaries(query.session_id.as_deref());
+    if query.reset {
+        match &query.session_id {
+            Some(session_id) => {
+                stats.by_session.remove(session_id);
+            }
+            None => stats.by_session.clear(),
+        }
+        info!(session_id = ?query.session_id, "Latency histograms reset");
+    }
+    Json(LatencyResponse { sessions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_are_per_session_and_stage() {
+        let mut stats = LatencyStats::default();
+        for us in 1..=1000 {
+            stats.record("ALICE->BROKER_A", Stage::Send, Duration::from_micros(us));
+        }
+        stats.record("ALICE->BROKER_A", Stage::Inbound, Duration::from_micros(5));
+        stats.record("ALICE->BROKER_B", Stage::Send, Duration::from_secs(120));
+
+        let summaries = stats.summaries(None);
+        assert_eq!(summaries.len(), 2);
+        let a = &summaries[0];
+        assert_eq!(a.send.count, 1000);
+        assert!((a.send.p50_us - 500.0).abs() < 1.0);
+        assert!((a.send.p99_us - 990.0).abs() < 1.0);
+        assert!((a.send.p999_us - 999.0).abs() < 1.0);
+        assert_eq!(a.inbound.count, 1);
+
+        // Beyond the tracked range, latencies count as the maximum.
+        let b = &summaries[1];
+        assert!((b.send.max_us - 60_000_000.0).abs() / 60_000_000.0 < 0.001);
+        assert_eq!(b.inbound, Percentiles::default());
+
+        assert_eq!(stats.summaries(Some("ALICE->BROKER_B")).len(), 1);
+    }
+}
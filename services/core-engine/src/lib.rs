@@ -0,0 +1,3300 @@
+//! The ALICE FIX engine.
+//!
+//! The `fix-engine` binary calls [`run`]. Everything else is private to the
+//! crate; [`bench`] exposes the codec to the benchmarks in `benches/`.
+
+mod actor;
+mod alerts;
+mod attribution;
+mod audit;
+mod auth;
+mod batch;
+#[doc(hidden)]
+pub mod bench;
+mod book;
+mod bulk_parse;
+mod cli;
+mod clock;
+mod commit;
+mod config;
+mod conflation;
+mod delivery;
+mod dictionary;
+mod encode;
+mod expiry;
+mod export;
+mod fast;
+mod fast_order;
+mod federation;
+mod fix_json;
+mod fixml;
+mod graphql;
+mod health;
+mod idempotency;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod journal;
+mod latency;
+mod limits;
+mod marketdata;
+mod message;
+mod oidc;
+mod openapi;
+mod order_entry;
+mod orders;
+mod outbound;
+mod outbound_queue;
+mod preflight;
+mod preview;
+mod problem;
+mod quotes;
+mod rate_limit;
+mod records;
+mod registry;
+mod reload;
+mod replay;
+mod rules;
+mod sbe;
+mod secrets;
+mod securities;
+mod session_events;
+mod session_list;
+mod sinks;
+mod state_store;
+mod store;
+mod structure;
+mod telemetry;
+mod templates;
+mod throughput;
+mod timeline;
+mod tls;
+mod traffic;
+mod validation;
+mod venues;
+
+use axum::{
+    async_trait,
+    extract::{rejection::QueryRejection, DefaultBodyLimit, FromRequestParts, Path, Query, State},
+    http::{request::Parts, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
+    Router,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use bytes::BytesMut;
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+use tower_http::{
+    request_id::{PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+use tracing::{info, info_span, warn, Instrument};
+use utoipa::{IntoParams, ToSchema};
+
+use actor::SessionActors;
+use alerts::{AlertConfig, Alerts};
+use attribution::Attribution;
+use audit::{AuditAction, AuditLog};
+use auth::{ApiKeys, Caller};
+use cli::{Cli, Command, ConfigCommand};
+use clock::epoch_ms;
+use commit::{CommitConfig, GroupCommit};
+use config::{
+    resolve, Config, Schedule, Source, StoreBackend, TimestampPrecision, TransportRole,
+    DEFAULT_HEARTBEAT_SECS,
+};
+use dictionary::{Dictionaries, Dictionary, Member};
+use conflation::ConflationConfig;
+use delivery::{DeliveryOptions, DeliveryTracker};
+use expiry::{ExpiryConfig, ExpiryTracker, SessionRef};
+use export::ExportConfig;
+use fast::{FastTemplates, InputFormat};
+use health::ReadinessConfig;
+use idempotency::IdempotencyKeys;
+use preview::{Preview, PreviewOptions};
+use problem::{ApiError, MakeCorrelationId, Problem};
+use journal::{JournalEntry, SessionJournal};
+use latency::{LatencyStats, Stage};
+use federation::{Federation, UpstreamAck};
+use fix_json::OutputEncoding;
+use limits::{Limits, LimitsConfig};
+use message::{decode_text, is_encoded_text, FixMessage, MESSAGE_ENCODING, SOH};
+use oidc::{Oidc, OidcConfig};
+use book::{Books, OrderBook};
+use marketdata::{MarketDataStore, Subscription};
+use orders::{CancelOnDisconnect, Correlation, MassCancelScope, OrderContext, OrderStore};
+use outbound::{OutboundChannels, OutboundConfig};
+use outbound_queue::{OutboundQueue, QueuedMessage};
+use quotes::{Quote, QuoteStore};
+use rate_limit::{RateLimitConfig, RateLimiter};
+use records::{Direction, StoredMessage};
+use registry::SessionRegistry;
+use rules::{Advisories, ConditionalRules, RulePack, RulePacks};
+use sbe::SbeSchemas;
+use secrets::{Secret, Secrets};
+use securities::{SecurityCache, SecurityDefinition, SecurityListDownload};
+use session_events::SessionEvents;
+use sinks::{RedriveJob, SinkEvent, Sinks};
+use state_store::{SessionSnapshot, SessionStateStore};
+use store::MessageStore;
+use telemetry::OrderTraces;
+use templates::Templates;
+use throughput::Throughput;
+use timeline::{EventKind, Timeline};
+use tls::TlsConfig;
+use traffic::TrafficFeed;
+use validation::{CustomTagRanges, Findings};
+use venues::VenueProfiles;
+
+// ── AppState ──────────────────────────────────────────────────────────────────
+
+#[derive(Clone)]
+struct AppState {
+    start_time: Arc<Instant>,
+    /// The FIX sessions, by session ID
+    sessions: Arc<SessionRegistry>,
+    /// GTC/GTD orders awaiting venue-side expiry, and orders with a TTL
+    expiries: Arc<Mutex<ExpiryTracker>>,
+    /// Default for flagging duplicate non-group tags on /parse
+    reject_duplicate_tags: bool,
+    /// Default for flagging tags the data dictionary does not define
+    reject_unknown_tags: bool,
+    /// Custom tag ranges accepted when unknown tags are flagged
+    custom_tags: Arc<CustomTagRanges>,
+    /// Default for failing validation on warnings
+    strict_validation: bool,
+    /// Parameters of orders sent, used to enrich inbound ExecutionReports
+    orders: Arc<Mutex<OrderStore>>,
+    /// Traces recent orders were sent in, joined by their ExecutionReports
+    order_traces: Arc<Mutex<OrderTraces>>,
+    /// Per-counterparty settings keyed by TargetCompID
+    venues: Arc<VenueProfiles>,
+    /// Upstream gateways acting as venues for federated targets
+    federation: Arc<Mutex<Federation>>,
+    /// Messages sent and received, for re-drives
+    messages: Arc<Mutex<MessageStore>>,
+    /// Messages waiting for their session to be logged on
+    outbound_queue: Arc<Mutex<OutboundQueue>>,
+    /// Bounded channels of messages for each session's writer
+    outbound: Arc<OutboundChannels>,
+    /// Each session's task, which runs its sends in order
+    actors: Arc<SessionActors>,
+    /// Delivery stages of messages sent with `?async=true`
+    deliveries: Arc<Mutex<DeliveryTracker>>,
+    /// Responses remembered by `Idempotency-Key` for /send retries
+    idempotency: Arc<Mutex<IdempotencyKeys>>,
+    /// Send and inbound latency histograms by session
+    latency: Arc<Mutex<LatencyStats>>,
+    /// Rolling message, byte, reject and resend counts by session
+    throughput: Arc<Mutex<Throughput>>,
+    /// Market data subscriptions by MDReqID
+    market_data: Arc<Mutex<MarketDataStore>>,
+    /// Order books from market data snapshots, by MDReqID and symbol
+    books: Arc<Mutex<Books>>,
+    /// Quote requests by QuoteReqID and quotes by QuoteID
+    quotes: Arc<Mutex<QuoteStore>>,
+    /// Venues' security definitions, by session and symbol
+    securities: Arc<Mutex<SecurityCache>>,
+    /// Shared sequence numbers and session state, for standby takeover
+    session_state: Option<Arc<SessionStateStore>>,
+    /// Write-ahead log of session sequence numbers and state
+    journal: Arc<Mutex<SessionJournal>>,
+    /// Nightly archival export of the store, if configured
+    export: Option<Arc<ExportConfig>>,
+    /// Downstream webhook / Kafka sinks
+    sinks: Arc<Sinks>,
+    /// Book update conflation for stream clients and book sinks
+    conflation: Arc<ConflationConfig>,
+    /// Live feed of sent and received messages for stream clients
+    traffic: Arc<TrafficFeed>,
+    /// Session lifecycle events for Server-Sent Events clients
+    session_events: Arc<SessionEvents>,
+    /// Re-drive jobs started since startup
+    redrives: Arc<Mutex<Vec<RedriveJob>>>,
+    /// SBE message schemas for binary conversion
+    sbe: Arc<Mutex<SbeSchemas>>,
+    /// FAST templates for decoding market data captures
+    fast: Arc<Mutex<FastTemplates>>,
+    /// Data dictionaries by BeginString, for validation
+    dictionaries: Arc<Dictionaries>,
+    /// Conditionally-required field rules, for validation
+    conditional_rules: Arc<ConditionalRules>,
+    /// Counterparty rule packs, applied to the sessions that select them
+    rule_packs: Arc<Mutex<RulePacks>>,
+    /// Named message templates /send requests can start from
+    templates: Arc<Mutex<Templates>>,
+    /// Recommended and deprecated field warnings, for validation
+    advisories: Arc<Advisories>,
+    /// Startup/shutdown events for post-incident review
+    timeline: Arc<Mutex<Timeline>>,
+    /// Who made which admin change, for compliance
+    audit: Arc<Mutex<AuditLog>>,
+    /// Operational alert webhooks and what they track
+    alerts: Arc<Alerts>,
+    /// What `/readyz` requires of sessions and queues
+    readiness: Arc<ReadinessConfig>,
+    /// API keys and their roles, re-read when their file changes
+    api_keys: Arc<Mutex<ApiKeys>>,
+    /// Bearer tokens accepted from this OIDC issuer, if configured
+    oidc: Option<Arc<Oidc>>,
+    /// Token buckets of the clients of the send and validate endpoints, if
+    /// they are rate limited
+    rate_limiter: Arc<Mutex<Option<RateLimiter>>>,
+    /// The configuration file in force, as last loaded or reloaded
+    config: Arc<Mutex<Config>>,
+    /// Body size limits, timeouts and the permits of requests in progress
+    limits: Arc<Limits>,
+    /// Shared HTTP client for federation traffic
+    http: reqwest::Client,
+}
+
+// ── Domain types ──────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+struct FixSession {
+    session_id: String,
+    sender_comp_id: String,
+    target_comp_id: String,
+    fix_version: String,
+    state: String,
+    msg_seq_num: u64,
+    /// Next MsgSeqNum expected from the counterparty
+    expected_seq_num: u64,
+    connected_at: u64,
+    /// Rule packs applied on top of dictionary validation
+    rule_packs: Vec<String>,
+    /// Counterparty data dictionary file, used instead of the one for
+    /// `fix_version`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dictionary: Option<String>,
+    /// Flag tags the dictionary does not define, for venues that reject them
+    reject_unknown_tags: bool,
+    /// The counterparty sends us orders; its OrderStatusRequests are answered
+    acceptor: bool,
+    /// Cancels queued for open orders when the session drops without a logout
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cancel_on_disconnect: Option<CancelOnDisconnect>,
+    /// Counterparty host an initiator connects to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<String>,
+    /// Port an initiator connects to, or an acceptor listens on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    port: Option<u16>,
+    /// When the session runs, in UTC
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schedule: Option<Schedule>,
+    /// Where the session's sequence numbers and state are kept
+    store: StoreBackend,
+    /// HeartBtInt(108) sent in Logons, in seconds
+    heartbeat_secs: u64,
+    /// Fractional seconds in the SendingTime(52) the session sends
+    timestamp_precision: TimestampPrecision,
+    /// Where each setting came from, by name
+    #[serde(skip)]
+    sources: BTreeMap<&'static str, Source>,
+}
+
+impl FixSession {
+    /// The dictionary file set for the session in the configuration file.
+    /// Those in `FIX_SESSION_DICTIONARIES` are loaded with the others.
+    fn dictionary_file(&self) -> Option<&str> {
+        let from_file = matches!(self.sources["dictionary"], Source::Session | Source::Defaults);
+        self.dictionary.as_deref().filter(|_| from_file)
+    }
+}
+
+// ── Request / Response types ──────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct SendRequest {
+    /// May be omitted when `template` is given.
+    #[serde(default)]
+    msg_type: String,
+    #[serde(default)]
+    fields: HashMap<String, String>,
+    /// Repeating groups, e.g. NoOrders for a NewOrderList.
+    #[serde(default)]
+    #[schema(value_type = BTreeMap<String, Vec<HashMap<String, String>>>)]
+    groups: Groups,
+    /// Opaque client metadata (strategy ID, parent order ID, user tag, ...)
+    /// stored with the order and echoed in related events.
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+    /// Base URL of the downstream gateway that forwarded this order; its
+    /// ExecutionReports are relayed there.
+    #[serde(default)]
+    reply_to: Option<String>,
+    /// For a NewOrderSingle, cancel it if no terminal ExecutionReport arrives
+    /// within this many milliseconds of sending.
+    #[serde(default)]
+    ttl_ms: Option<u64>,
+    /// A stored message template to start from; `fields`, `groups` and
+    /// `metadata` override its defaults.
+    #[serde(default)]
+    template: Option<String>,
+    /// Who sent the message, stored with it; set by the handler.
+    #[serde(skip)]
+    attribution: Option<Attribution>,
+}
+
+/// Repeating groups for `/send`, by the name or tag of their NoXXX field.
+/// Each instance is a field map like `fields`.
+type Groups = BTreeMap<String, Vec<HashMap<String, String>>>;
+
+/// `/send` response for a message queued until its session is logged on.
+#[derive(Debug, Serialize, ToSchema)]
+struct QueuedResponse {
+    session_id: String,
+    queue_id: String,
+    /// 1-based position in the session's outbound queue.
+    position: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct SentMessage {
+    session_id: String,
+    msg_type: String,
+    sequence_number: u64,
+    fix_message: String,
+    fields: HashMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    #[schema(value_type = BTreeMap<String, Vec<HashMap<String, String>>>)]
+    groups: Groups,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    metadata: HashMap<String, String>,
+    /// Acknowledgement from the upstream gateway, for federated targets.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    upstream: Option<UpstreamAck>,
+    /// The sent message in FIX JSON Encoding, with `?encoding=fix_json`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    fix_json: Option<serde_json::Value>,
+    sent_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct SendOptions {
+    #[serde(default)]
+    encoding: OutputEncoding,
+    /// Reject the message with 422 unless it passes the session's validation
+    /// profile.
+    #[serde(default)]
+    validate: bool,
+    /// With `validate`, warnings reject the message too; overrides
+    /// `FIX_STRICT_VALIDATION`.
+    strict: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct ParseRequest {
+    #[serde(default)]
+    raw_message: String,
+    /// Base64 of the wire bytes, for messages whose Encoded* fields carry
+    /// non-UTF-8 text (e.g. Shift-JIS). Takes precedence over `raw_message`.
+    #[serde(default)]
+    raw_base64: Option<String>,
+}
+
+impl ParseRequest {
+    /// The message bytes, or `None` if the input is blank or not valid base64.
+    fn bytes(&self) -> Option<Cow<'_, [u8]>> {
+        let bytes = match &self.raw_base64 {
+            Some(b64) => Cow::Owned(BASE64.decode(b64.trim()).ok()?),
+            None => Cow::Borrowed(self.raw_message.as_bytes()),
+        };
+        (!bytes.trim_ascii().is_empty()).then_some(bytes)
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ParseOptions {
+    /// Flag tags repeated outside repeating groups; overrides `FIX_REJECT_DUPLICATE_TAGS`.
+    reject_duplicates: Option<bool>,
+    /// Flag tags missing from the message's data dictionary; overrides
+    /// `FIX_REJECT_UNKNOWN_TAGS`.
+    reject_unknown_tags: Option<bool>,
+    /// `fix_json` returns the message in FIX JSON Encoding instead.
+    #[serde(default)]
+    encoding: OutputEncoding,
+    /// `fast` decodes `raw_base64` as a capture of FAST-encoded messages.
+    #[serde(default)]
+    format: InputFormat,
+    /// Bytes to skip before each FAST message, e.g. a packet sequence number.
+    #[serde(default)]
+    preamble_bytes: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ParsedField {
+    tag: u32,
+    name: String,
+    value: String,
+    /// Encoded* field text decoded with the message's MessageEncoding(347).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decoded: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ParseResponse {
+    msg_type: String,
+    fields: Vec<ParsedField>,
+    field_count: usize,
+    raw_length: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<ValidationError>,
+}
+
+impl ParseResponse {
+    /// Value of the first occurrence of `tag`.
+    fn value(&self, tag: u32) -> Option<&str> {
+        self.fields.iter().find(|f| f.tag == tag).map(|f| f.value.as_str())
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct InboundResponse {
+    #[serde(flatten)]
+    message: ParseResponse,
+    /// Locally known context of the order an ExecutionReport refers to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    order: Option<OrderContext>,
+    /// For ExecutionReports, whether the report answers a request the
+    /// gateway sent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    correlation: Option<Correlation>,
+    /// Messages sent in answer to a ResendRequest, in order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    resent: Vec<String>,
+    /// For an OrderMassCancelReport, the orders its request covered.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    mass_cancelled: Vec<OrderContext>,
+    /// The ExecutionReport sent in answer to an OrderStatusRequest.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status_report: Option<String>,
+    /// For market data and its rejects, the subscription named by MDReqID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subscription: Option<Subscription>,
+    /// For market data, the order books it set or updated.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    books: Vec<OrderBook>,
+    /// For a Quote, the quote; for a QuoteCancel, the quotes it cancelled.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    quotes: Vec<Quote>,
+    /// For a SecurityList answering our request, the download's progress.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    security_list: Option<SecurityListDownload>,
+    /// For a SecurityDefinition, the definition cached.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    security: Option<SecurityDefinition>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct NormalizeResponse {
+    msg_type: String,
+    /// SOH-delimited wire form
+    fix_message: String,
+    /// Exact wire bytes; unlike `fix_message`, safe for non-UTF-8 Encoded* text
+    fix_message_base64: String,
+    /// `|`-delimited form for logs and display
+    display: String,
+    field_count: usize,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct ValidateRequest {
+    #[serde(default)]
+    message: HashMap<String, String>,
+    /// Wire-form message; enables field ordering checks and takes precedence over `message`.
+    #[serde(default)]
+    raw_message: Option<String>,
+    /// Defaults to the session's FIX version when `session_id` is given.
+    #[serde(default)]
+    version: Option<String>,
+    /// Validates with this session's profile: its dictionary and rule packs.
+    #[serde(default)]
+    session_id: Option<String>,
+    /// Flag tags the dictionary does not define; overrides the session's and
+    /// `FIX_REJECT_UNKNOWN_TAGS`.
+    #[serde(default)]
+    reject_unknown_tags: Option<bool>,
+    /// Warnings also fail validation; overrides `FIX_STRICT_VALIDATION`.
+    #[serde(default)]
+    strict: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum ErrorCategory {
+    /// A required field is missing.
+    Required,
+    /// Fields are present but out of header/body/trailer or group order.
+    Ordering,
+    /// A tag outside any repeating group occurs more than once.
+    Duplicate,
+    /// BodyLength, CheckSum or field delimiting is wrong on the wire.
+    Framing,
+    /// MsgType is not defined in the data dictionary.
+    InvalidMsgType,
+    /// A value is not one of the field's enumerated values.
+    InvalidValue,
+    /// A value does not match the field's data type.
+    InvalidType,
+    /// A tag is neither in the data dictionary nor an allowed custom range.
+    UnknownTag,
+    /// A NoXXX count does not match the number of group instances.
+    GroupCount,
+    /// A recommended field is missing (warning).
+    Recommended,
+    /// A deprecated field is present (warning).
+    Deprecated,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ValidationError {
+    field: String,
+    tag: u32,
+    message: String,
+    category: ErrorCategory,
+    /// Location inside repeating groups, e.g. `NoMDEntries[2].MDEntryType`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ValidateResponse {
+    valid: bool,
+    version: String,
+    msg_type: String,
+    errors: Vec<ValidationError>,
+    /// Fail validation only in strict mode.
+    warnings: Vec<ValidationError>,
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/fix/send",
+    tag = "fix",
+    params(
+        SendOptions,
+        DeliveryOptions,
+        PreviewOptions,
+        (
+            "Idempotency-Key" = Option<String>,
+            Header,
+            description = "Makes retries safe: a repeat with the same key gets the \
+                           first response instead of sending again"
+        ),
+    ),
+    request_body = SendRequest,
+    responses(
+        (
+            status = 200,
+            description = "Sent, or only built and validated with `?dry_run=true`",
+            content(
+                (SentMessage = "application/json"),
+                (Preview = "application/json"),
+            )
+        ),
+        (
+            status = 202,
+            description = "Queued until the session is logged on, or accepted for \
+                           asynchronous send with `?async=true`",
+            content(
+                (QueuedResponse = "application/json"),
+                (delivery::AcceptedResponse = "application/json"),
+            )
+        ),
+        (
+            status = 400,
+            description = "Invalid message or idempotency key",
+            body = Problem,
+            content_type = "application/problem+json"
+        ),
+        (
+            status = 409,
+            description = "A request with the same idempotency key is in progress",
+            body = Problem,
+            content_type = "application/problem+json"
+        ),
+        (
+            status = 422,
+            description = "Rejected by the session's validation, with the field-level \
+                           errors, or the idempotency key was used for a different message",
+            body = Problem,
+            content_type = "application/problem+json"
+        ),
+        (
+            status = 429,
+            description = "The client is over its rate limit; see Retry-After",
+            body = Problem,
+            content_type = "application/problem+json"
+        ),
+        (status = 502, description = "Rejected by the upstream gateway"),
+        (status = 503, description = "Upstream gateway unreachable"),
+    )
+)]
+async fn send(
+    State(state): State<AppState>,
+    SendQuery {
+        opts,
+        delivery,
+        dry,
+    }: SendQuery,
+    caller: Caller,
+    attribution: Attribution,
+    headers: HeaderMap,
+    Json(mut req): Json<SendRequest>,
+) -> Result<Response, ApiError> {
+    templates::resolve(&state, &mut req).await?;
+    caller.check_session(&session_id_of(&req))?;
+    req.attribution = Some(attribution);
+    // A dry run sends nothing, so it neither uses nor uses up an idempotency key.
+    if dry.dry_run {
+        return Ok(Json(preview::preview(&state, &opts, req).await?).into_response());
+    }
+    let owner = caller.0.as_ref().map(|principal| principal.name.as_str());
+    let pending = match idempotency::key(&headers)? {
+        Some(key) => match idempotency::claim(&state, owner, key, &req).await? {
+            Ok(pending) => Some(pending),
+            Err(replay) => return Ok(replay),
+        },
+        None => None,
+    };
+    let sent = dispatch(state, opts, delivery, req);
+    match pending {
+        Some(pending) => idempotency::run(pending, sent).await,
+        None => sent.await,
+    }
+}
+
+/// Sends or queues a `/send` message, or accepts it for sending in the
+/// background, and answers for it.
+async fn dispatch(
+    state: AppState,
+    opts: SendOptions,
+    delivery: DeliveryOptions,
+    req: SendRequest,
+) -> Result<Response, ApiError> {
+    Ok(if delivery.asynchronous {
+        check_send(&req)?;
+        let session_id = session_id_of(&req);
+        let accepted = delivery::accept(&state, opts, req, session_id).await;
+        (StatusCode::ACCEPTED, Json(accepted)).into_response()
+    } else {
+        match submit(&state, &opts, req).await? {
+            Submitted::Sent(sent) => Json(sent).into_response(),
+            Submitted::Queued(queued) => (StatusCode::ACCEPTED, Json(queued)).into_response(),
+        }
+    })
+}
+
+/// The query parameters of `/send`, each set read from the same query string.
+struct SendQuery {
+    opts: SendOptions,
+    delivery: DeliveryOptions,
+    dry: PreviewOptions,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for SendQuery {
+    type Rejection = QueryRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(opts) = Query::from_request_parts(parts, state).await?;
+        let Query(delivery) = Query::from_request_parts(parts, state).await?;
+        let Query(dry) = Query::from_request_parts(parts, state).await?;
+        Ok(Self {
+            opts,
+            delivery,
+            dry,
+        })
+    }
+}
+
+/// What became of a message given to `/send`.
+enum Submitted {
+    Sent(Box<SentMessage>),
+    Queued(QueuedResponse),
+}
+
+/// Sends a message, or queues it if its session is not logged on.
+async fn submit(
+    state: &AppState,
+    opts: &SendOptions,
+    req: SendRequest,
+) -> Result<Submitted, ApiError> {
+    check_send(&req)?;
+    let session_id = session_id_of(&req);
+    let mut results = state
+        .actors
+        .submit(state, &session_id, opts, vec![req])
+        .await?;
+    // One message, so one result.
+    results.remove(0)
+}
+
+/// Checks a message given to `/send` for mistakes the caller can fix,
+/// before anything is queued or sent.
+fn check_send(req: &SendRequest) -> Result<(), ApiError> {
+    if req.msg_type.trim().is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "missing_msg_type")
+            .detail("msg_type is required"));
+    }
+    if req.ttl_ms.is_some_and(|ttl| ttl == 0 || msg_type_to_num(&req.msg_type) != "D") {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "invalid_ttl")
+            .detail("ttl_ms must be positive and is only supported on a NewOrderSingle (D)"));
+    }
+    Ok(())
+}
+
+/// `submit` on the session's task, with its encode buffer.
+async fn submit_in_task(
+    state: &AppState,
+    opts: &SendOptions,
+    req: SendRequest,
+    buffer: &mut BytesMut,
+) -> Result<Submitted, ApiError> {
+    if let Some(queued) = queue_if_offline(state, opts, &req).await? {
+        return Ok(Submitted::Queued(queued));
+    }
+    send_in_task(state, opts, req, buffer)
+        .await
+        .map(|sent| Submitted::Sent(Box::new(sent)))
+}
+
+/// The SenderCompID and TargetCompID of a message that leaves them out:
+/// the first of the sessions seeded without a configuration file.
+const DEFAULT_SENDER: &str = "ALICE";
+const DEFAULT_TARGET: &str = "BROKER_A";
+
+/// The `SenderCompID->TargetCompID` session a message is sent on.
+fn session_id_of(req: &SendRequest) -> String {
+    format!(
+        "{}->{}",
+        req.fields.get("SenderCompID").map_or(DEFAULT_SENDER, String::as_str),
+        req.fields.get("TargetCompID").map_or(DEFAULT_TARGET, String::as_str)
+    )
+}
+
+/// Queues the message if its session is known but not logged on, or still
+/// has messages waiting ahead of it.
+async fn queue_if_offline(
+    state: &AppState,
+    opts: &SendOptions,
+    req: &SendRequest,
+) -> Result<Option<QueuedResponse>, StatusCode> {
+    let session_id = session_id_of(req);
+    let session = find_session(state, &session_id);
+    let mut queue = state.outbound_queue.lock().await;
+    if session.is_none_or(|s| s.state == "ACTIVE") && queue.is_empty_for(&session_id) {
+        return Ok(None);
+    }
+    let message = QueuedMessage {
+        queue_id: String::new(),
+        session_id: session_id.clone(),
+        msg_type: req.msg_type.clone(),
+        fields: req.fields.clone(),
+        groups: req.groups.clone(),
+        metadata: req.metadata.clone(),
+        reply_to: req.reply_to.clone(),
+        ttl_ms: req.ttl_ms,
+        validate: opts.validate,
+        strict: opts.strict,
+        queued_at_ms: epoch_ms(),
+        attribution: req.attribution.clone(),
+    };
+    let (queue_id, position) = queue.push(message).map_err(|e| {
+        warn!(session_id = %session_id, error = %e, "FIX message rejected");
+        StatusCode::SERVICE_UNAVAILABLE
+    })?;
+    info!(session_id = %session_id, queue_id = %queue_id, position, "FIX message queued");
+    Ok(Some(QueuedResponse {
+        session_id,
+        queue_id,
+        position,
+    }))
+}
+
+/// Sends a message now: the body of `/send`, also used to transmit queued
+/// messages once their session is logged on.
+async fn send_message(
+    state: &AppState,
+    opts: &SendOptions,
+    req: SendRequest,
+) -> Result<SentMessage, StatusCode> {
+    let session_id = session_id_of(&req);
+    state
+        .actors
+        .send(state, &session_id, opts, req)
+        .await
+        .map_err(|e| e.status())
+}
+
+/// `send_message` on the session's task. The message is encoded into
+/// `buffer`, the session's encode buffer.
+async fn send_in_task(
+    state: &AppState,
+    opts: &SendOptions,
+    mut req: SendRequest,
+    buffer: &mut BytesMut,
+) -> Result<SentMessage, ApiError> {
+    let received_at = latency::received_at();
+
+    // Orders for a federated target are forwarded as the client sent them;
+    // the upstream gateway fills in its own venue's fields.
+    let (upstream, public_url) = {
+        let federation = state.federation.lock().await;
+        let target = req.fields.get("TargetCompID").map_or(DEFAULT_TARGET, String::as_str);
+        (federation.upstream_for(target), federation.public_url())
+    };
+    let forward = upstream.map(|upstream| (upstream, req.fields.clone(), req.groups.clone()));
+
+    let build_span = info_span!("fix.build", msg_type = %req.msg_type);
+    let Outgoing {
+        session_id,
+        fix_version,
+        sender,
+        target,
+        msg_type_num,
+        fields,
+        groups,
+        body_fields,
+        group_fields,
+        ..
+    } = prepare(state, opts, &mut req).instrument(build_span).await?;
+
+    // Taken before the MsgSeqNum, so a full channel consumes none.
+    let slot = state.outbound.reserve(&session_id).await.map_err(|e| {
+        warn!(session_id = %session_id, error = %e, "FIX message rejected");
+        ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "outbound_full").detail(e.to_string())
+    })?;
+
+    // Forwarded once every local check has passed, and before the MsgSeqNum,
+    // so a rejection upstream consumes none. Should the message then fail
+    // here, it is withdrawn upstream, so the upstream venue never has an
+    // order the client was told was rejected.
+    let upstream_ack = match &forward {
+        Some((upstream, client_fields, client_groups)) => {
+            let ack = federation::forward(
+                &state.http,
+                upstream,
+                &req.msg_type,
+                client_fields,
+                client_groups,
+                &req.metadata,
+                public_url,
+            )
+            .instrument(info_span!("fix.forward", url = %upstream.url))
+            .await
+            .map_err(|e| {
+                warn!(url = %upstream.url, error = %e, "Upstream gateway forward failed");
+                ApiError::new(StatusCode::BAD_GATEWAY, "upstream_rejected").detail(e.to_string())
+            })?;
+            Some(ack)
+        }
+        None => None,
+    };
+    let now_ms = epoch_ms();
+    let seq_num = match allocate_seq_num(state, &session_id).await {
+        Ok(seq_num) => seq_num,
+        Err(status) => {
+            withdraw_forward(state, &req.msg_type, &forward).await;
+            return Err(status.into());
+        }
+    };
+    let header = encode::Header {
+        version: &fix_version,
+        msg_type: &msg_type_num,
+        sender: &sender,
+        target: &target,
+        seq_num,
+        sending_time: sending_time(state, &session_id),
+    };
+    let journalled = journal_seq_num(state, &session_id, seq_num, now_ms)
+        .instrument(info_span!("fix.persist", session_id = %session_id))
+        .await;
+    if let Err(status) = journalled {
+        unsend_seq_num(state, &session_id, slot, &header).await;
+        withdraw_forward(state, &req.msg_type, &forward).await;
+        return Err(status.into());
+    }
+    encode::write_message(buffer, &header, &body_fields, &group_fields);
+
+    // Stored before anything else learns of it, so a message that could not
+    // be stored is neither sent nor tracked.
+    let attribution = req.attribution.take().map(|a| a.with_metadata(&req.metadata));
+    let stored = store::record_sent(
+        state,
+        &session_id,
+        &msg_type_num,
+        Some(seq_num),
+        buffer,
+        attribution,
+    )
+    .instrument(info_span!("fix.transmit", sequence_number = seq_num))
+    .await;
+    if let Err(status) = stored {
+        unsend_seq_num(state, &session_id, slot, &header).await;
+        withdraw_forward(state, &req.msg_type, &forward).await;
+        return Err(status.into());
+    }
+
+    let cl_ord_id = lookup_field(&fields, "ClOrdID", 11);
+    if let Some(cl_ord_id) = cl_ord_id {
+        state.order_traces.lock().await.record(cl_ord_id);
+    }
+
+    if let Some(cl_ord_id) = cl_ord_id {
+        let mut orders = state.orders.lock().await;
+        match msg_type_num.as_str() {
+            "F" | "G" => {
+                if let Some(orig) = lookup_field(&fields, "OrigClOrdID", 41) {
+                    let changed = if msg_type_num == "F" {
+                        orders.cancel_requested(cl_ord_id, orig, now_ms)
+                    } else {
+                        orders.replace_requested(cl_ord_id, orig, &fields, now_ms)
+                    };
+                    publish_orders(state, &changed, now_ms);
+                }
+            }
+            "q" => {
+                let scope = MassCancelScope::from_fields(&fields);
+                let changed = orders.mass_cancel_requested(cl_ord_id, &session_id, &scope, now_ms);
+                info!(
+                    session_id = %session_id,
+                    cl_ord_id = %cl_ord_id,
+                    orders = changed.len(),
+                    "Order mass cancel requested"
+                );
+                publish_orders(state, &changed, now_ms);
+            }
+            _ => {}
+        }
+    }
+
+    let list_id = lookup_field(&fields, "ListID", 66);
+    if let Some(list_id) = list_id {
+        let mut orders = state.orders.lock().await;
+        let changed = match msg_type_num.as_str() {
+            "E" => {
+                let list = groups
+                    .iter()
+                    .filter(|(k, _)| *k == "NoOrders" || *k == "73")
+                    .flat_map(|(_, list)| list);
+                orders.list_submitted(list_id, &session_id, list, &req.metadata, now_ms)
+            }
+            "K" => orders.list_cancel_requested(list_id, now_ms),
+            _ => Vec::new(),
+        };
+        publish_orders(state, &changed, now_ms);
+    }
+
+    if msg_type_num == "D" {
+        let session = SessionRef {
+            fix_version: &fix_version,
+            sender: &sender,
+            target: &target,
+        };
+        let order = cl_ord_id.map(|cl_ord_id| {
+            let mut order =
+                OrderContext::from_fields(cl_ord_id, &session_id, &fields, &req.metadata, now_ms);
+            order.reply_to = req.reply_to.clone();
+            order
+        });
+        track_new_order(state, session, order, &fields, &req.metadata, req.ttl_ms, now_ms).await;
+    }
+
+    info!(
+        msg_type = %req.msg_type,
+        sequence_number = seq_num,
+        fix_version = %fix_version,
+        "FIX message sent"
+    );
+
+    state
+        .latency
+        .lock()
+        .await
+        .record(&session_id, Stage::Send, received_at.elapsed());
+
+    let fix_json = (opts.encoding == OutputEncoding::FixJson)
+        .then(|| fix_json::encode(&parse_raw(buffer).fields));
+    let fix_message = String::from_utf8_lossy(buffer).into_owned();
+    // The buffer's allocation comes back to it once the writer is done.
+    slot.send(buffer.split().freeze());
+
+    Ok(SentMessage {
+        session_id,
+        msg_type: req.msg_type,
+        sequence_number: seq_num,
+        fix_message,
+        fields,
+        groups,
+        metadata: req.metadata,
+        upstream: upstream_ack,
+        fix_json,
+        sent_at_ms: now_ms,
+    })
+}
+
+/// Withdraws what `send_in_task` forwarded to an upstream gateway, if
+/// anything, for a message that then failed here.
+async fn withdraw_forward(
+    state: &AppState,
+    msg_type: &str,
+    forward: &Option<(federation::Upstream, HashMap<String, String>, Groups)>,
+) {
+    if let Some((upstream, fields, _)) = forward {
+        federation::withdraw(&state.http, upstream, msg_type, fields)
+            .instrument(info_span!("fix.withdraw", url = %upstream.url))
+            .await;
+    }
+}
+
+/// Allocates the session's next outbound MsgSeqNum: one atomic add, or the
+/// shared state store's counter if configured. The number is journalled with
+/// [`journal_seq_num`] before the message is sent, and given up with
+/// [`unsend_seq_num`] if the message will not be sent after all.
+async fn allocate_seq_num(state: &AppState, session_id: &str) -> Result<u64, StatusCode> {
+    let handle = state.sessions.get(session_id).ok_or(StatusCode::NOT_FOUND)?;
+    let Some(store) = &state.session_state else {
+        return Ok(handle.allocate_seq_num());
+    };
+    let floor = handle.last_seq_num();
+    let id = session_id.to_string();
+    let allocated = store
+        .blocking(move |store| store.next_seq_num(&id, floor))
+        .await;
+    let seq_num = allocated.map_err(|e| {
+        warn!(session_id = %session_id, error = %e, "Sequence number allocation failed");
+        StatusCode::SERVICE_UNAVAILABLE
+    })?;
+    handle.advance_seq_num(seq_num);
+    Ok(seq_num)
+}
+
+/// Journals the session moving past `seq_num`, before the message is sent,
+/// then saves the new state to the shared state store if configured.
+/// Concurrent sends journal without waiting for each other; the journal
+/// keeps the highest number whatever order their entries land in.
+async fn journal_seq_num(
+    state: &AppState,
+    session_id: &str,
+    seq_num: u64,
+    now_ms: u64,
+) -> Result<(), StatusCode> {
+    let handle = state.sessions.get(session_id).ok_or(StatusCode::NOT_FOUND)?;
+    let mut next = FixSession::clone(&handle.snapshot());
+    next.msg_seq_num = next.msg_seq_num.max(seq_num + 1);
+    journal(state, journal_entry(&next, now_ms)).await?;
+    let snapshot = handle.update(|s| {
+        s.msg_seq_num = s.msg_seq_num.max(seq_num + 1);
+        SessionSnapshot {
+            msg_seq_num: s.msg_seq_num,
+            state: Some(s.state.clone()),
+        }
+    });
+    if let Some(store) = &state.session_state {
+        let id = session_id.to_string();
+        let saved = store
+            .blocking(move |store| store.save(&id, &snapshot, now_ms))
+            .await;
+        if let Err(e) = saved {
+            warn!(session_id = %session_id, error = %e, "Failed to save session state");
+        }
+    }
+    Ok(())
+}
+
+/// Gives up `header`'s MsgSeqNum, allocated for a message that will not be
+/// sent after all. The number is given back if no later one has been
+/// allocated since; otherwise a SequenceReset-GapFill (35=4, 123=Y) is sent
+/// as that number on `slot`, so the counterparty sees no gap.
+async fn unsend_seq_num(
+    state: &AppState,
+    session_id: &str,
+    slot: outbound::Slot,
+    header: &encode::Header<'_>,
+) {
+    let seq_num = header.seq_num;
+    let Some(handle) = state.sessions.get(session_id) else {
+        return;
+    };
+    let mut given_back = handle.release_seq_num(seq_num);
+    if let Some(store) = &state.session_state {
+        let id = session_id.to_string();
+        let released = store
+            .blocking(move |store| store.release_seq_num(&id, seq_num))
+            .await;
+        given_back = matches!(released, Ok(true));
+    }
+    if given_back {
+        return;
+    }
+    let gap_fill = encode::Header {
+        msg_type: "4",
+        ..*header
+    };
+    let body = [(123, "Y".to_string()), (36, (seq_num + 1).to_string())];
+    let fix_message = build_fix_message(&gap_fill, &[], &body);
+    slot.send(fix_message.into());
+    warn!(
+        session_id = %session_id,
+        sequence_number = seq_num,
+        "Unsent sequence number gap filled"
+    );
+}
+
+/// A message built and checked for sending, still without a sequence number.
+struct Outgoing {
+    session_id: String,
+    fix_version: String,
+    sender: String,
+    target: String,
+    msg_type_num: String,
+    fields: HashMap<String, String>,
+    groups: Groups,
+    /// `fields` as tag/value pairs, as they are written.
+    body_fields: Vec<(u32, String)>,
+    /// `groups` as tag/value pairs, in wire order.
+    group_fields: Vec<(u32, String)>,
+    /// Validation warnings that do not reject the message.
+    warnings: Vec<ValidationError>,
+}
+
+/// Builds the message `req` describes, filling in the fields the gateway
+/// supplies, and checks it as `opts` asks. Takes `req`'s fields and groups.
+async fn prepare(
+    state: &AppState,
+    opts: &SendOptions,
+    req: &mut SendRequest,
+) -> Result<Outgoing, ApiError> {
+    // Build FIX wire message (SOH-delimited)
+    let fix_version = req.fields
+        .get("BeginString")
+        .cloned()
+        .unwrap_or_else(|| "FIX.4.4".to_string());
+
+    let sender = req.fields
+        .get("SenderCompID")
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_SENDER.to_string());
+
+    let target = req.fields
+        .get("TargetCompID")
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_TARGET.to_string());
+
+    let msg_type_num = msg_type_to_num(&req.msg_type);
+
+    let mut fields = std::mem::take(&mut req.fields);
+    if let Some(profile) = state.venues.get(&target) {
+        profile.apply_metadata(&req.metadata, &mut fields);
+    }
+
+    // A mass cancel without a MassCancelRequestType cancels the Symbol given,
+    // or everything.
+    if msg_type_num == "q" && lookup_field(&fields, "MassCancelRequestType", 530).is_none() {
+        let request_type = if lookup_field(&fields, "Symbol", 55).is_some() { "1" } else { "7" };
+        fields.insert("530".to_string(), request_type.to_string());
+    }
+
+    let session_id = format!("{}->{}", sender, target);
+
+    // A NewOrderList numbers its orders and counts them unless told otherwise.
+    let mut groups = std::mem::take(&mut req.groups);
+    if msg_type_num == "E" {
+        let mut total = 0;
+        for (_, list) in groups.iter_mut().filter(|(k, _)| *k == "NoOrders" || *k == "73") {
+            for (i, order) in list.iter_mut().enumerate() {
+                if lookup_field(order, "ListSeqNo", 67).is_none() {
+                    order.insert("67".to_string(), (i + 1).to_string());
+                }
+            }
+            total += list.len();
+        }
+        if lookup_field(&fields, "TotNoOrders", 68).is_none() {
+            fields.insert("68".to_string(), total.to_string());
+        }
+    }
+    // Orders are checked against the venue's security definitions, which
+    // also supply their SecurityID.
+    if matches!(msg_type_num.as_str(), "D" | "G") {
+        let securities = state.securities.lock().await;
+        if let Err(e) = securities.check_order(&session_id, &mut fields) {
+            info!(session_id = %session_id, error = %e, "FIX message rejected");
+            return Err(ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "security_check_failed")
+                .detail(e.to_string()));
+        }
+    }
+    let dict = state
+        .dictionaries
+        .for_session(&session_id)
+        .or_else(|| state.dictionaries.get(&fix_version));
+    let group_fields = group_fields(dict.as_deref(), &msg_type_num, &groups).map_err(|e| {
+        info!(session_id = %session_id, error = %e, "FIX message rejected");
+        ApiError::new(StatusCode::BAD_REQUEST, "invalid_groups").detail(e.to_string())
+    })?;
+    let body_fields = encode::body_fields(dict.as_deref(), &fields).map_err(|e| {
+        info!(session_id = %session_id, error = %e, "FIX message rejected");
+        ApiError::new(StatusCode::BAD_REQUEST, "invalid_fields").detail(e)
+    })?;
+
+    let mut warnings = Vec::new();
+    if opts.validate {
+        let session = find_session(state, &session_id);
+        let profile = validation_profile(state, session.as_ref(), &fix_version).await?;
+        // The fields as they are written, checked before a sequence number
+        // is allocated, so the header fields the gateway fills in are
+        // represented by placeholders.
+        let mut draft: Vec<(u32, String)> = [
+            (8, fix_version.as_str()),
+            (9, "0"),
+            (34, "1"),
+            (35, msg_type_num.as_str()),
+            (49, sender.as_str()),
+            (52, "20260223-00:00:00.000"),
+            (56, target.as_str()),
+        ]
+        .into_iter()
+        .map(|(tag, value)| (tag, value.to_string()))
+        .collect();
+        draft.extend(body_fields.iter().cloned());
+        draft.sort_by_key(|(tag, _)| *tag);
+        let mut errors = Vec::new();
+        if profile.reject_unknown_tags {
+            errors.extend(validation::unknown_field_names(&profile.dict, &fields));
+        }
+        draft.extend(group_fields.iter().cloned());
+        let findings = profile.validate(&draft, false);
+        errors.extend(findings.errors);
+        for warning in &findings.warnings {
+            info!(session_id = %session_id, warning = %warning.message, "FIX message warning");
+        }
+        if opts.strict.unwrap_or(state.strict_validation) {
+            errors.extend(findings.warnings);
+        } else {
+            warnings = findings.warnings;
+        }
+        if let Some(first) = errors.first() {
+            info!(
+                session_id = %session_id,
+                errors = errors.len(),
+                first = %first.message,
+                "FIX message rejected by validation"
+            );
+            let detail = match errors.len() {
+                1 => "1 validation error".to_string(),
+                n => format!("{n} validation errors"),
+            };
+            return Err(ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "validation_failed")
+                .detail(detail)
+                .errors(errors));
+        }
+    }
+
+    if msg_type_num == "G" {
+        let orders = state.orders.lock().await;
+        let check = orders.check_replace(
+            lookup_field(&fields, "ClOrdID", 11).map(String::as_str),
+            lookup_field(&fields, "OrigClOrdID", 41).map(String::as_str),
+        );
+        if let Err(e) = check {
+            info!(session_id = %session_id, error = %e, "OrderCancelReplaceRequest rejected");
+            return Err(ApiError::new(e.status(), e.code()).detail(e.to_string()));
+        }
+    }
+
+    Ok(Outgoing {
+        session_id,
+        fix_version,
+        sender,
+        target,
+        msg_type_num,
+        fields,
+        groups,
+        body_fields,
+        group_fields,
+        warnings,
+    })
+}
+
+/// Sends a session's queued messages in order while it stays logged on, and
+/// returns how many were sent. A queued message the send path rejects as
+/// invalid is dropped; any other failure stops the flush with the message
+/// back at the front of the queue.
+async fn flush_queue(state: &AppState, session_id: &str) -> usize {
+    let mut sent = 0;
+    loop {
+        if find_session(state, session_id).is_none_or(|s| s.state != "ACTIVE") {
+            break;
+        }
+        let Some(next) = state.outbound_queue.lock().await.take_front(session_id) else {
+            break;
+        };
+        let opts = SendOptions {
+            encoding: OutputEncoding::default(),
+            validate: next.validate,
+            strict: next.strict,
+        };
+        let req = SendRequest {
+            msg_type: next.msg_type.clone(),
+            fields: next.fields.clone(),
+            groups: next.groups.clone(),
+            metadata: next.metadata.clone(),
+            reply_to: next.reply_to.clone(),
+            ttl_ms: next.ttl_ms,
+            template: None,
+            attribution: next.attribution.clone(),
+        };
+        match send_message(state, &opts, req).await {
+            Ok(message) => {
+                sent += 1;
+                let cl_ord_id = lookup_field(&message.fields, "ClOrdID", 11);
+                let mut deliveries = state.deliveries.lock().await;
+                deliveries.queue_sent(
+                    &next.queue_id,
+                    message.sequence_number,
+                    cl_ord_id.map(String::as_str),
+                    epoch_ms(),
+                );
+            }
+            Err(status) if status.is_client_error() => {
+                state
+                    .deliveries
+                    .lock()
+                    .await
+                    .queue_dropped(&next.queue_id, status, epoch_ms());
+                warn!(
+                    session_id = %session_id,
+                    queue_id = %next.queue_id,
+                    status = %status,
+                    "Queued FIX message rejected, dropping it"
+                );
+            }
+            Err(status) => {
+                warn!(
+                    session_id = %session_id,
+                    queue_id = %next.queue_id,
+                    status = %status,
+                    "Queued FIX message not sent, flush stopped"
+                );
+                state.outbound_queue.lock().await.restore_front(next);
+                break;
+            }
+        }
+    }
+    if sent > 0 {
+        info!(session_id = %session_id, sent, "Outbound queue flushed");
+    }
+    sent
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/fix/parse",
+    tag = "fix",
+    params(ParseOptions),
+    request_body = ParseRequest,
+    responses(
+        (status = 200, description = "Parsed message", body = ParseResponse),
+        (status = 400, description = "Not a FIX message"),
+    )
+)]
+async fn parse(
+    State(state): State<AppState>,
+    Query(opts): Query<ParseOptions>,
+    Json(req): Json<ParseRequest>,
+) -> Result<Response, StatusCode> {
+    let raw = req.bytes().ok_or(StatusCode::BAD_REQUEST)?;
+    if opts.format == InputFormat::Fast {
+        return fast::parse_capture(&state, &raw, opts.preamble_bytes, opts.encoding).await;
+    }
+    let parsed = parse_checked(&state, &raw, opts.reject_duplicates, opts.reject_unknown_tags)?;
+    Ok(match opts.encoding {
+        OutputEncoding::Native => Json(parsed).into_response(),
+        OutputEncoding::FixJson => Json(fix_json::encode(&parsed.fields)).into_response(),
+    })
+}
+
+/// Parses a message and flags its duplicate and unknown tags, as configured
+/// unless the caller overrides it.
+fn parse_checked(
+    state: &AppState,
+    raw: &[u8],
+    reject_duplicates: Option<bool>,
+    reject_unknown_tags: Option<bool>,
+) -> Result<ParseResponse, StatusCode> {
+    let parsed = parse_flagged(state, raw, reject_duplicates, reject_unknown_tags)?;
+    info!(
+        msg_type = %parsed.msg_type,
+        field_count = parsed.field_count,
+        raw_length = parsed.raw_length,
+        errors = parsed.errors.len(),
+        "FIX message parsed"
+    );
+    Ok(parsed)
+}
+
+/// `parse_checked` without logging the message.
+fn parse_flagged(
+    state: &AppState,
+    raw: &[u8],
+    reject_duplicates: Option<bool>,
+    reject_unknown_tags: Option<bool>,
+) -> Result<ParseResponse, StatusCode> {
+    let mut parsed = parse_raw(raw);
+
+    if reject_duplicates.unwrap_or(state.reject_duplicate_tags) {
+        for (_, tag) in structure::duplicate_tags(&parsed.fields) {
+            parsed.errors.push(ValidationError {
+                path: None,
+                field: tag_to_name(tag),
+                tag,
+                message: format!("Duplicate tag {tag} outside a repeating group."),
+                category: ErrorCategory::Duplicate,
+            });
+        }
+    }
+
+    if reject_unknown_tags.unwrap_or(state.reject_unknown_tags) {
+        let version = parsed.value(8).unwrap_or_default();
+        let session_id = format!(
+            "{}->{}",
+            parsed.value(49).unwrap_or_default(),
+            parsed.value(56).unwrap_or_default()
+        );
+        let dict = state
+            .dictionaries
+            .for_session(&session_id)
+            .or_else(|| state.dictionaries.get(version))
+            .ok_or_else(|| {
+                info!(version, "No data dictionary to check tags against");
+                StatusCode::BAD_REQUEST
+            })?;
+        let tags = parsed.fields.iter().map(|f| f.tag);
+        parsed
+            .errors
+            .extend(validation::unknown_tags(&dict, tags, &state.custom_tags));
+    }
+    Ok(parsed)
+}
+
+/// Accepts a message received from a counterparty and routes it to the
+/// engine's order tracking. ExecutionReports are returned enriched with the
+/// context of the order they refer to.
+#[utoipa::path(
+    post,
+    path = "/api/v1/fix/inbound",
+    tag = "fix",
+    request_body = ParseRequest,
+    responses(
+        (status = 200, description = "OK", body = InboundResponse),
+        (status = 400, description = "Not a FIX message"),
+    )
+)]
+async fn inbound(
+    State(state): State<AppState>,
+    caller: Caller,
+    Json(req): Json<ParseRequest>,
+) -> Result<Json<InboundResponse>, ApiError> {
+    let received_at = latency::received_at();
+    let raw = req.bytes().ok_or(StatusCode::BAD_REQUEST)?;
+    let parsed = parse_raw(&raw);
+    let mut order = None;
+
+    // The counterparty is the message's sender.
+    let session_id = format!(
+        "{}->{}",
+        parsed.value(56).unwrap_or_default(),
+        parsed.value(49).unwrap_or_default()
+    );
+    caller.check_session(&session_id)?;
+    state
+        .latency
+        .lock()
+        .await
+        .record(&session_id, Stage::Inbound, received_at.elapsed());
+    state
+        .deliveries
+        .lock()
+        .await
+        .on_inbound(&session_id, &parsed, epoch_ms());
+    // ExecutionReports and OrderCancelRejects are correlated first, so the
+    // stored and streamed message carries its order.
+    let mut execution = None;
+    if parsed.msg_type == "ExecutionReport" {
+        let report = orders::ExecutionReport {
+            exec_id: parsed.value(17),
+            exec_ref_id: parsed.value(19),
+            cl_ord_id: parsed.value(11),
+            orig_cl_ord_id: parsed.value(41),
+            exec_type: parsed.value(150),
+            ord_status: parsed.value(39),
+            last_qty: parsed.value(32),
+            last_px: parsed.value(31),
+            cum_qty: parsed.value(14),
+            leaves_qty: parsed.value(151),
+            avg_px: parsed.value(6),
+        };
+        // Traced as part of the order's trace, for end-to-end latency.
+        let span = info_span!(
+            "fix.execution_report",
+            session_id = %session_id,
+            cl_ord_id = report.cl_ord_id.unwrap_or_default(),
+            exec_type = report.exec_type.unwrap_or_default(),
+        );
+        if let Some(cl_ord_id) = report.cl_ord_id {
+            state.order_traces.lock().await.join(cl_ord_id, &span);
+        }
+        let correlated = {
+            let mut orders = state.orders.lock().await;
+            span.in_scope(|| orders.on_execution_report(&report, epoch_ms()))
+        };
+        publish_orders(&state, &correlated.changed, epoch_ms());
+        if correlated.context.correlation != Correlation::Matched {
+            info!(
+                session_id = %session_id,
+                cl_ord_id = report.cl_ord_id.unwrap_or_default(),
+                exec_type = report.exec_type.unwrap_or_default(),
+                correlation = ?correlated.context.correlation,
+                "ExecutionReport not matched to a request"
+            );
+        }
+        order = correlated.context.order.clone();
+        execution = Some(correlated.context);
+
+        if let Some(reply_to) = order.as_ref().and_then(|o| o.reply_to.as_deref()) {
+            federation::relay(&state, reply_to, &raw);
+        }
+
+        let mut tracker = state.expiries.lock().await;
+        tracker.on_execution_report(
+            parsed.value(11),
+            parsed.value(41),
+            parsed.value(39),
+            epoch_ms(),
+        );
+    } else if parsed.msg_type == "OrderCancelReject" {
+        let reject = orders::CancelReject {
+            cl_ord_id: parsed.value(11),
+            orig_cl_ord_id: parsed.value(41),
+            ord_status: parsed.value(39),
+            response_to: parsed.value(434),
+        };
+        let correlated = state
+            .orders
+            .lock()
+            .await
+            .on_cancel_reject(&reject, epoch_ms());
+        publish_orders(&state, &correlated.changed, epoch_ms());
+        info!(
+            session_id = %session_id,
+            cl_ord_id = reject.cl_ord_id.unwrap_or_default(),
+            response_to = reject.response_to.unwrap_or_default(),
+            reason = parsed.value(58).unwrap_or_default(),
+            correlation = ?correlated.context.correlation,
+            "Order cancel request rejected"
+        );
+        state
+            .expiries
+            .lock()
+            .await
+            .on_cancel_reject(reject.cl_ord_id, reject.ord_status, epoch_ms());
+        order = correlated.context.order.clone();
+        execution = Some(correlated.context);
+
+        if let Some(reply_to) = order.as_ref().and_then(|o| o.reply_to.as_deref()) {
+            federation::relay(&state, reply_to, &raw);
+        }
+    }
+    let mut mass_cancelled = Vec::new();
+    if parsed.msg_type == "OrderMassCancelReport" {
+        let report = orders::MassCancelReport {
+            cl_ord_id: parsed.value(11),
+            response: parsed.value(531),
+            affected: parsed
+                .fields
+                .iter()
+                .filter(|f| f.tag == 41)
+                .map(|f| f.value.as_str())
+                .collect(),
+        };
+        let outcome = state
+            .orders
+            .lock()
+            .await
+            .on_mass_cancel_report(&report, epoch_ms());
+        publish_orders(&state, &outcome.changed, epoch_ms());
+        info!(
+            session_id = %session_id,
+            cl_ord_id = report.cl_ord_id.unwrap_or_default(),
+            response = report.response.unwrap_or_default(),
+            known = outcome.known,
+            orders = outcome.orders.len(),
+            "Order mass cancel report received"
+        );
+        mass_cancelled = outcome.orders;
+    }
+    if parsed.msg_type == "ListStatus" {
+        let mut status = orders::ListStatus {
+            list_id: parsed.value(66),
+            list_status_type: parsed.value(429),
+            list_order_status: parsed.value(431),
+            orders: Vec::new(),
+        };
+        // Each NoOrders instance starts with its ClOrdID.
+        for field in parsed.fields.iter().skip_while(|f| f.tag != 73) {
+            let value = Some(field.value.as_str());
+            match (field.tag, status.orders.last_mut()) {
+                (11, _) => status.orders.push(orders::ListOrderStatus {
+                    cl_ord_id: &field.value,
+                    ..Default::default()
+                }),
+                (39, Some(entry)) => entry.ord_status = value,
+                (14, Some(entry)) => entry.cum_qty = value,
+                (151, Some(entry)) => entry.leaves_qty = value,
+                (6, Some(entry)) => entry.avg_px = value,
+                _ => {}
+            }
+        }
+        let (list, changed) = state
+            .orders
+            .lock()
+            .await
+            .on_list_status(&status, epoch_ms());
+        publish_orders(&state, &changed, epoch_ms());
+        info!(
+            session_id = %session_id,
+            list_id = status.list_id.unwrap_or_default(),
+            list_state = ?list.map(|l| l.state),
+            orders = changed.len(),
+            "ListStatus received"
+        );
+    }
+    let mut subscription = None;
+    if let Some(md_req_id) = parsed.value(262) {
+        let mut market_data = state.market_data.lock().await;
+        subscription = match parsed.msg_type.as_str() {
+            "MarketDataSnapshotFullRefresh" => market_data.on_data(md_req_id, true, epoch_ms()),
+            "MarketDataIncrementalRefresh" => market_data.on_data(md_req_id, false, epoch_ms()),
+            "MarketDataRequestReject" => {
+                let reason = parsed.value(281);
+                info!(
+                    session_id = %session_id,
+                    md_req_id = %md_req_id,
+                    reason = reason.unwrap_or_default(),
+                    text = parsed.value(58).unwrap_or_default(),
+                    "MarketDataRequest rejected"
+                );
+                market_data.on_reject(md_req_id, reason, parsed.value(58), epoch_ms())
+            }
+            _ => None,
+        };
+    }
+    let mut books = Vec::new();
+    let md_fields: Vec<(u32, &str)> =
+        parsed.fields.iter().map(|f| (f.tag, f.value.as_str())).collect();
+    if parsed.msg_type == "MarketDataSnapshotFullRefresh" {
+        match OrderBook::from_snapshot(&md_fields, epoch_ms()) {
+            Some(mut snapshot) => {
+                // A snapshot re-requested after a gap restores the original book.
+                if let Some(id) = &snapshot.md_req_id {
+                    let market_data = state.market_data.lock().await;
+                    snapshot.md_req_id = Some(market_data.book_id(id));
+                }
+                books.push(state.books.lock().await.apply_snapshot(snapshot));
+            }
+            None => info!(session_id = %session_id, "Market data snapshot without a Symbol"),
+        }
+    } else if parsed.msg_type == "MarketDataIncrementalRefresh" {
+        let outcome = state
+            .books
+            .lock()
+            .await
+            .apply_incremental(&md_fields, epoch_ms());
+        if let Some(md_req_id) = parsed.value(262) {
+            for symbol in &outcome.gaps {
+                if let Err(status) = marketdata::recover(&state, md_req_id, symbol).await {
+                    warn!(
+                        session_id = %session_id,
+                        md_req_id = %md_req_id,
+                        symbol = %symbol,
+                        status = %status,
+                        "Market data snapshot re-request failed"
+                    );
+                }
+            }
+        }
+        books = outcome.books;
+    }
+    let mut quotes = Vec::new();
+    if matches!(parsed.msg_type.as_str(), "Quote" | "QuoteCancel") {
+        let mut store = state.quotes.lock().await;
+        if parsed.msg_type == "Quote" {
+            quotes.extend(store.on_quote(&session_id, &md_fields, epoch_ms()));
+        } else {
+            let scope = store.scope_of(&md_fields);
+            quotes = store.cancel(&session_id, &scope, epoch_ms());
+            info!(
+                session_id = %session_id,
+                scope = ?scope,
+                cancelled = quotes.len(),
+                "QuoteCancel received"
+            );
+        }
+    }
+    let (mut security_list, mut security) = (None, None);
+    if matches!(parsed.msg_type.as_str(), "SecurityList" | "SecurityDefinition") {
+        let mut cache = state.securities.lock().await;
+        if parsed.msg_type == "SecurityList" {
+            security_list = cache.on_security_list(&session_id, &md_fields, epoch_ms());
+            if let Some(download) = &security_list {
+                info!(
+                    session_id = %session_id,
+                    security_req_id = %download.security_req_id,
+                    state = ?download.state,
+                    received = download.received,
+                    "SecurityList received"
+                );
+            }
+        } else {
+            security = cache.on_definition(&session_id, &md_fields, epoch_ms());
+        }
+    }
+    let correlation = execution.as_ref().map(|e| e.correlation);
+
+    store::record(
+        &state,
+        &session_id,
+        Direction::Inbound,
+        parsed.value(35).unwrap_or_default(),
+        parsed.value(34).and_then(|s| s.parse().ok()),
+        &raw,
+        execution,
+    )
+    .await?;
+    if let Some(entry) = advance_expected_seq_num(&state, &session_id, &parsed)? {
+        journal(&state, entry).await?;
+    }
+
+    let mut resent = Vec::new();
+    if parsed.value(35) == Some("2") {
+        let sent: Vec<StoredMessage> = state
+            .messages
+            .lock()
+            .await
+            .for_session(&session_id)
+            .cloned()
+            .collect();
+        let header = replay::ResendHeader {
+            begin_string: parsed.value(8).unwrap_or_default(),
+            sender_comp_id: parsed.value(56).unwrap_or_default(),
+            target_comp_id: parsed.value(49).unwrap_or_default(),
+        };
+        let begin = parsed.value(7).and_then(|v| v.parse().ok()).unwrap_or(1);
+        let end = parsed.value(16).and_then(|v| v.parse().ok()).unwrap_or(0);
+        for r in replay::fulfil_resend(&sent, begin, end, &header, epoch_ms()) {
+            replay::emit(&state, &session_id, &r.msg_type, Some(r.seq), &r.raw).await;
+            resent.push(String::from_utf8_lossy(&r.raw).into_owned());
+        }
+        info!(
+            session_id = %session_id,
+            begin,
+            end,
+            resent = resent.len(),
+            "ResendRequest fulfilled"
+        );
+    }
+
+    let mut status_report = None;
+    if parsed.msg_type == "OrderStatusRequest"
+        && find_session(&state, &session_id).is_some_and(|s| s.acceptor)
+    {
+        let request = orders::StatusRequest {
+            cl_ord_id: parsed.value(11),
+            order_id: parsed.value(37),
+            ord_status_req_id: parsed.value(790),
+            symbol: parsed.value(55),
+            side: parsed.value(54),
+        };
+        let mut fields = state
+            .orders
+            .lock()
+            .await
+            .status_report(&request, &uuid::Uuid::new_v4().to_string());
+        for (name, tag) in [("BeginString", 8), ("SenderCompID", 56), ("TargetCompID", 49)] {
+            fields.insert(name.to_string(), parsed.value(tag).unwrap_or_default().to_string());
+        }
+        let req = SendRequest {
+            msg_type: "ExecutionReport".to_string(),
+            fields,
+            groups: Groups::new(),
+            metadata: HashMap::new(),
+            reply_to: None,
+            ttl_ms: None,
+            template: None,
+            attribution: None,
+        };
+        let opts = SendOptions {
+            encoding: OutputEncoding::default(),
+            validate: false,
+            strict: None,
+        };
+        let sent = send_message(&state, &opts, req).await?;
+        info!(
+            session_id = %session_id,
+            cl_ord_id = request.cl_ord_id.unwrap_or_default(),
+            sequence_number = sent.sequence_number,
+            "OrderStatusRequest answered"
+        );
+        status_report = Some(sent.fix_message);
+    }
+
+    info!(
+        msg_type = %parsed.msg_type,
+        field_count = parsed.field_count,
+        order_known = order.is_some(),
+        "FIX message received"
+    );
+
+    Ok(Json(InboundResponse {
+        message: parsed,
+        order,
+        correlation,
+        resent,
+        mass_cancelled,
+        status_report,
+        subscription,
+        books,
+        quotes,
+        security_list,
+        security,
+    }))
+}
+
+/// Parses a message into the lossless model and re-encodes it. Field order
+/// and unknown tags are preserved, so re-encoding with the input's own
+/// delimiter reproduces the input byte for byte.
+#[utoipa::path(
+    post,
+    path = "/api/v1/fix/normalize",
+    tag = "fix",
+    request_body = ParseRequest,
+    responses(
+        (status = 200, description = "OK", body = NormalizeResponse),
+        (status = 400, description = "Not a FIX message"),
+    )
+)]
+async fn normalize(
+    State(_state): State<AppState>,
+    Json(req): Json<ParseRequest>,
+) -> Result<Json<NormalizeResponse>, StatusCode> {
+    let raw = req.bytes().ok_or(StatusCode::BAD_REQUEST)?;
+    let msg = FixMessage::parse(&raw).map_err(|e| {
+        info!(error = %e, "FIX message rejected by normalize");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let msg_type = msg
+        .fields
+        .iter()
+        .find(|f| f.tag == 35)
+        .map(|f| fix_msg_type_name(&f.value_str()))
+        .unwrap_or_else(|| "Unknown".to_string());
+
+    let wire = msg.encode_with(SOH);
+    Ok(Json(NormalizeResponse {
+        msg_type,
+        fix_message: String::from_utf8_lossy(&wire).into_owned(),
+        fix_message_base64: BASE64.encode(&wire),
+        display: String::from_utf8_lossy(&msg.encode_with(b'|')).into_owned(),
+        field_count: msg.fields.len(),
+    }))
+}
+
+const SESSION_STATES: [&str; 4] = ["ACTIVE", "LOGON_SENT", "LOGOUT_SENT", "DISCONNECTED"];
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct SessionStateRequest {
+    state: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct SessionStateResponse {
+    #[serde(flatten)]
+    session: FixSession,
+    /// Queued messages sent because the session became `ACTIVE`.
+    flushed: usize,
+    /// Cancels queued for open orders because the session dropped.
+    cancels_queued: usize,
+    /// The security list requested because the session logged on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    security_list: Option<SecurityListDownload>,
+}
+
+/// Reports a session's transport state. Becoming `ACTIVE` sends the messages
+/// queued while it was down. Becoming `DISCONNECTED` other than after
+/// `LOGOUT_SENT` queues the session's cancel-on-disconnect cancels.
+#[utoipa::path(
+    post,
+    path = "/api/v1/fix/sessions/{session_id}/state",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "The session ID, e.g. `ALICE->BROKER_A`.")),
+    request_body = SessionStateRequest,
+    responses(
+        (status = 200, description = "OK", body = SessionStateResponse),
+        (status = 400, description = "Unknown state"),
+        (status = 404, description = "Unknown session"),
+    )
+)]
+async fn set_session_state(
+    State(state): State<AppState>,
+    actor: audit::Actor,
+    Path(session_id): Path<String>,
+    Json(req): Json<SessionStateRequest>,
+) -> Result<Json<SessionStateResponse>, StatusCode> {
+    if !SESSION_STATES.contains(&req.state.as_str()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let now_ms = epoch_ms();
+    let (previous, entry, cancel_mode) = state
+        .sessions
+        .update(&session_id, |session| {
+            let previous = std::mem::replace(&mut session.state, req.state.clone());
+            (previous, journal_entry(session, now_ms), session.cancel_on_disconnect)
+        })
+        .ok_or(StatusCode::NOT_FOUND)?;
+    journal(&state, entry).await?;
+    let dropped = req.state == "DISCONNECTED"
+        && !matches!(previous.as_str(), "DISCONNECTED" | "LOGOUT_SENT");
+    let cancels_queued = match cancel_mode.filter(|_| dropped) {
+        Some(mode) => queue_disconnect_cancels(&state, &session_id, mode, epoch_ms()).await,
+        None => 0,
+    };
+    if previous != req.state {
+        let kind = match req.state.as_str() {
+            "ACTIVE" => Some(EventKind::SessionConnected),
+            "DISCONNECTED" => Some(EventKind::SessionDisconnected),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            let mut timeline = state.timeline.lock().await;
+            timeline.record(kind, Some(&session_id), format!("was {previous}"));
+        }
+    }
+    session_events::on_state_change(&state, &session_id, &previous, &req.state).await;
+    alerts::on_state_change(&state, &session_id, dropped, &req.state).await;
+    audit::record(
+        &state,
+        &actor,
+        AuditAction::SessionStateChanged,
+        Some(&session_id),
+        format!("{previous} -> {}", req.state),
+    )
+    .await;
+
+    let flushed = flush_queue(&state, &session_id).await;
+    let security_list = match find_session(&state, &session_id) {
+        Some(session) if req.state == "ACTIVE" && previous != "ACTIVE" => {
+            securities::on_logon(&state, &session).await
+        }
+        _ => None,
+    };
+    let session = find_session(&state, &session_id).ok_or(StatusCode::NOT_FOUND)?;
+    if let Some(store) = &state.session_state {
+        let snapshot = SessionSnapshot {
+            msg_seq_num: session.msg_seq_num,
+            state: Some(session.state.clone()),
+        };
+        let id = session_id.clone();
+        let now_ms = epoch_ms();
+        let saved = store
+            .blocking(move |store| store.save(&id, &snapshot, now_ms))
+            .await;
+        if let Err(e) = saved {
+            warn!(session_id = %session_id, error = %e, "Failed to save session state");
+        }
+    }
+    Ok(Json(SessionStateResponse {
+        session,
+        flushed,
+        cancels_queued,
+        security_list,
+    }))
+}
+
+/// Queues cancels for a dropped session's open orders, to go out when it is
+/// logged on again, and moves the orders to `PENDING_CANCEL` as each is
+/// queued. Returns how many were queued.
+async fn queue_disconnect_cancels(
+    state: &AppState,
+    session_id: &str,
+    mode: CancelOnDisconnect,
+    now_ms: u64,
+) -> usize {
+    let cancels = state
+        .orders
+        .lock()
+        .await
+        .disconnect_cancels(session_id, mode, now_ms);
+    let (sender, target) = session_id.split_once("->").unwrap_or((session_id, ""));
+    let msg_type = match mode {
+        CancelOnDisconnect::Orders => "OrderCancelRequest",
+        CancelOnDisconnect::Mass => "OrderMassCancelRequest",
+    };
+    let mut queued = 0;
+    for mut fields in cancels {
+        fields.insert("SenderCompID".to_string(), sender.to_string());
+        fields.insert("TargetCompID".to_string(), target.to_string());
+        let message = QueuedMessage {
+            queue_id: String::new(),
+            session_id: session_id.to_string(),
+            msg_type: msg_type.to_string(),
+            fields: fields.clone(),
+            groups: Groups::new(),
+            metadata: HashMap::new(),
+            reply_to: None,
+            ttl_ms: None,
+            validate: false,
+            strict: None,
+            queued_at_ms: now_ms,
+            attribution: None,
+        };
+        let pushed = state.outbound_queue.lock().await.push(message);
+        if let Err(e) = pushed {
+            warn!(session_id = %session_id, error = %e, "Cancel-on-disconnect stopped");
+            break;
+        }
+        queued += 1;
+        let mut orders = state.orders.lock().await;
+        let changed = match (mode, fields.get("11"), fields.get("41")) {
+            (CancelOnDisconnect::Orders, Some(cl_ord_id), Some(orig)) => {
+                orders.cancel_requested(cl_ord_id, orig, now_ms)
+            }
+            (CancelOnDisconnect::Mass, Some(cl_ord_id), _) => {
+                let scope = MassCancelScope::from_fields(&fields);
+                orders.mass_cancel_requested(cl_ord_id, session_id, &scope, now_ms)
+            }
+            _ => Vec::new(),
+        };
+        drop(orders);
+        publish_orders(state, &changed, now_ms);
+    }
+    if queued > 0 {
+        info!(session_id = %session_id, queued, "Cancel-on-disconnect cancels queued");
+    }
+    queued
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/fix/validate",
+    tag = "fix",
+    request_body = ValidateRequest,
+    responses(
+        (status = 200, description = "OK", body = ValidateResponse),
+        (status = 429, description = "The client is over its rate limit; see Retry-After"),
+    )
+)]
+async fn validate(
+    State(state): State<AppState>,
+    Json(req): Json<ValidateRequest>,
+) -> Result<Json<ValidateResponse>, StatusCode> {
+    validate_message(&state, req).await.map(Json)
+}
+
+async fn validate_message(
+    state: &AppState,
+    req: ValidateRequest,
+) -> Result<ValidateResponse, StatusCode> {
+    let session = match &req.session_id {
+        Some(id) => Some(find_session(state, id).ok_or(StatusCode::NOT_FOUND)?),
+        None => None,
+    };
+    let version = req
+        .version
+        .or_else(|| session.as_ref().map(|s| s.fix_version.clone()))
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let mut profile = validation_profile(state, session.as_ref(), &version).await?;
+    if let Some(reject) = req.reject_unknown_tags {
+        profile.reject_unknown_tags = reject;
+    }
+
+    let mut errors: Vec<ValidationError> = Vec::new();
+
+    let (fields, framed) = match &req.raw_message {
+        Some(raw) if raw.trim().is_empty() => return Err(StatusCode::BAD_REQUEST),
+        Some(raw) => {
+            let (fields, structural) = wire_fields(raw.as_bytes());
+            errors.extend(structural);
+            (fields, true)
+        }
+        None => {
+            if profile.reject_unknown_tags {
+                errors.extend(validation::unknown_field_names(&profile.dict, &req.message));
+            }
+            (validation::fields_from_map(&profile.dict, &req.message), false)
+        }
+    };
+
+    let msg_type = msg_type_code(&fields);
+    let findings = profile.validate(&fields, framed);
+    errors.extend(findings.errors);
+    let warnings = findings.warnings;
+    drop_superseded_ordering(&mut errors);
+
+    let strict = req.strict.unwrap_or(state.strict_validation);
+    let valid = errors.is_empty() && (!strict || warnings.is_empty());
+
+    info!(
+        version = %version,
+        session_id = req.session_id.as_deref().unwrap_or_default(),
+        msg_type = %msg_type,
+        valid,
+        errors = errors.len(),
+        warnings = warnings.len(),
+        "FIX message validated"
+    );
+
+    Ok(ValidateResponse {
+        valid,
+        version,
+        msg_type,
+        errors,
+        warnings,
+    })
+}
+
+/// A raw message's fields, with its framing and wire-order errors.
+fn wire_fields(raw: &[u8]) -> (Vec<(u32, String)>, Vec<ValidationError>) {
+    let parsed = parse_raw(raw);
+    let mut errors = structure::check_framing(raw);
+    errors.extend(structure::check_ordering(&parsed.fields));
+    let fields = parsed.fields.into_iter().map(|f| (f.tag, f.value)).collect();
+    (fields, errors)
+}
+
+/// MsgType (35) as sent, e.g. `D`.
+fn msg_type_code(fields: &[(u32, String)]) -> String {
+    fields
+        .iter()
+        .find(|(tag, _)| *tag == 35)
+        .map(|(_, v)| v.clone())
+        .unwrap_or_else(|| "Unknown".to_string())
+}
+
+/// Drops wire-order errors that a group delimiter finding with a path
+/// supersedes.
+fn drop_superseded_ordering(errors: &mut Vec<ValidationError>) {
+    let located: Vec<u32> = errors
+        .iter()
+        .filter(|e| e.path.is_some() && e.category == ErrorCategory::Ordering)
+        .map(|e| e.tag)
+        .collect();
+    errors.retain(|e| {
+        e.path.is_some() || e.category != ErrorCategory::Ordering || !located.contains(&e.tag)
+    });
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────────
+
+fn find_session(state: &AppState, session_id: &str) -> Option<FixSession> {
+    let session = state.sessions.snapshot(session_id)?;
+    Some(FixSession::clone(&session))
+}
+
+/// Tracks a NewOrderSingle just sent, on `/send` or the fast path: the
+/// order, for execution reports and cancel-on-disconnect, and its expiry.
+async fn track_new_order(
+    state: &AppState,
+    session: SessionRef<'_>,
+    order: Option<OrderContext>,
+    fields: &HashMap<String, String>,
+    metadata: &HashMap<String, String>,
+    ttl_ms: Option<u64>,
+    now_ms: u64,
+) {
+    if let Some(order) = order {
+        sinks::publish(
+            state,
+            SinkEvent::order(&order.cl_ord_id, now_ms, records::encode(&order)),
+        );
+        state.orders.lock().await.record(order);
+    }
+    let mut tracker = state.expiries.lock().await;
+    tracker.track_new_order(fields, metadata, session, ttl_ms, now_ms);
+}
+
+/// Publishes orders whose state changed to the sinks.
+fn publish_orders(state: &AppState, orders: &[OrderContext], now_ms: u64) {
+    for order in orders {
+        sinks::publish(
+            state,
+            SinkEvent::order(&order.cl_ord_id, now_ms, records::encode(order)),
+        );
+    }
+}
+
+fn journal_entry(session: &FixSession, now_ms: u64) -> JournalEntry {
+    JournalEntry {
+        session_id: session.session_id.clone(),
+        msg_seq_num: session.msg_seq_num,
+        expected_seq_num: session.expected_seq_num,
+        state: session.state.clone(),
+        at_ms: now_ms,
+    }
+}
+
+/// Makes a session change durable, failing the request if it cannot be.
+async fn journal(state: &AppState, entry: JournalEntry) -> Result<(), StatusCode> {
+    let session_id = entry.session_id.clone();
+    let appended = state.journal.lock().await.append(entry);
+    let synced = match appended {
+        Ok(durable) => durable.wait().await,
+        Err(e) => Err(e),
+    };
+    synced.map_err(|e| {
+        warn!(session_id = %session_id, error = %e, "Failed to journal session state");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+/// Moves a known session's expected inbound MsgSeqNum past a received
+/// message, or to NewSeqNo (36) for a SequenceReset, alerting a gap if the
+/// message skipped some. Returns the change to journal, if any.
+fn advance_expected_seq_num(
+    state: &AppState,
+    session_id: &str,
+    parsed: &ParseResponse,
+) -> Result<Option<JournalEntry>, StatusCode> {
+    let next = match parsed.value(35) {
+        Some("4") => parsed.value(36).and_then(|v| v.parse::<u64>().ok()),
+        _ => parsed
+            .value(34)
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(|seq| seq + 1),
+    };
+    let Some(next) = next else {
+        return Ok(None);
+    };
+    let now_ms = epoch_ms();
+    let advanced = state.sessions.update(session_id, |session| {
+        let expected = session.expected_seq_num;
+        if next <= expected {
+            return None;
+        }
+        let gap = (parsed.value(35) != Some("4") && next - 1 > expected).then_some(expected);
+        session.expected_seq_num = next;
+        Some((journal_entry(session, now_ms), gap))
+    });
+    let Some((entry, gap)) = advanced.flatten() else {
+        return Ok(None);
+    };
+    if let Some(expected) = gap {
+        alerts::sequence_gap(state, session_id, expected, next - 2);
+    }
+    Ok(Some(entry))
+}
+
+/// What a message is validated against: a session's own dictionary (or the
+/// one for `version`), the rule packs the session selects and the engine's
+/// conditional rules and advisories.
+struct ValidationProfile {
+    dict: Arc<Dictionary>,
+    rule_packs: Vec<RulePack>,
+    reject_unknown_tags: bool,
+    conditional_rules: Arc<ConditionalRules>,
+    advisories: Arc<Advisories>,
+    custom_tags: Arc<CustomTagRanges>,
+}
+
+async fn validation_profile(
+    state: &AppState,
+    session: Option<&FixSession>,
+    version: &str,
+) -> Result<ValidationProfile, StatusCode> {
+    let dict = session
+        .and_then(|s| state.dictionaries.for_session(&s.session_id))
+        .or_else(|| state.dictionaries.get(version))
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let mut rule_packs = Vec::new();
+    if let Some(session) = session {
+        let packs = state.rule_packs.lock().await;
+        for name in &session.rule_packs {
+            match packs.get(name) {
+                Some(pack) => rule_packs.push(pack.clone()),
+                None => warn!(
+                    session_id = %session.session_id,
+                    pack = %name,
+                    "Selected rule pack is not loaded"
+                ),
+            }
+        }
+    }
+    Ok(ValidationProfile {
+        dict,
+        rule_packs,
+        reject_unknown_tags: state.reject_unknown_tags
+            || session.is_some_and(|s| s.reject_unknown_tags),
+        conditional_rules: state.conditional_rules.clone(),
+        advisories: state.advisories.clone(),
+        custom_tags: state.custom_tags.clone(),
+    })
+}
+
+impl ValidationProfile {
+    /// Dictionary, conditional-rule, rule-pack and advisory findings for `fields`.
+    fn validate(&self, fields: &[(u32, String)], framed: bool) -> Findings {
+        let mut findings = Findings {
+            errors: validation::validate(&self.dict, fields, framed),
+            warnings: self.advisories.check(&self.dict, fields),
+        };
+        findings
+            .errors
+            .extend(self.conditional_rules.check(&self.dict, fields));
+        for pack in &self.rule_packs {
+            findings.extend(pack.validate(&self.dict, fields));
+        }
+        if self.reject_unknown_tags {
+            findings.errors.extend(validation::unknown_tags(
+                &self.dict,
+                fields.iter().map(|(tag, _)| *tag),
+                &self.custom_tags,
+            ));
+        }
+        findings
+    }
+}
+
+fn parse_raw(raw: &[u8]) -> ParseResponse {
+    let raw_length = raw.len();
+    let mut fields: Vec<ParsedField> = Vec::new();
+    let mut msg_type = "Unknown".to_string();
+    let mut encoding: Option<String> = None;
+
+    // FIX messages use SOH (0x01) as field delimiter; accept both \x01 and |
+    let delimiter = message::detect_delimiter(raw);
+
+    for segment in message::scan(raw, delimiter) {
+        let (Some(tag), Some(value)) = (message::lenient_tag(segment.tag), segment.value) else {
+            continue;
+        };
+        let text = String::from_utf8_lossy(value).into_owned();
+        if tag == 35 {
+            msg_type = fix_msg_type_name(&text);
+        }
+        if tag == MESSAGE_ENCODING {
+            encoding = Some(text.clone());
+        }
+        let decoded = if is_encoded_text(tag) {
+            decode_text(encoding.as_deref(), value)
+        } else {
+            None
+        };
+        fields.push(ParsedField {
+            tag,
+            name: tag_to_name(tag),
+            value: text,
+            decoded,
+        });
+    }
+
+    let field_count = fields.len();
+    ParseResponse {
+        msg_type,
+        fields,
+        field_count,
+        raw_length,
+        errors: Vec::new(),
+    }
+}
+
+/// Looks up a field supplied either by name or by tag number.
+fn lookup_field<'a>(
+    fields: &'a HashMap<String, String>,
+    name: &str,
+    tag: u32,
+) -> Option<&'a String> {
+    fields.get(name).or_else(|| fields.get(tag.to_string().as_str()))
+}
+
+fn msg_type_to_num(name: &str) -> String {
+    match name {
+        "NewOrderSingle" => "D",
+        "ExecutionReport" => "8",
+        "OrderCancelRequest" => "F",
+        "OrderCancelReplaceRequest" => "G",
+        "OrderCancelReject" => "9",
+        "OrderStatusRequest" => "H",
+        "OrderMassCancelRequest" => "q",
+        "OrderMassCancelReport" => "r",
+        "NewOrderList" => "E",
+        "ListStatus" => "N",
+        "ListCancelRequest" => "K",
+        "MarketDataRequest" => "V",
+        "MarketDataSnapshotFullRefresh" => "W",
+        "MarketDataIncrementalRefresh" => "X",
+        "MarketDataRequestReject" => "Y",
+        "QuoteRequest" => "R",
+        "Quote" => "S",
+        "QuoteCancel" => "Z",
+        "SecurityListRequest" => "x",
+        "SecurityList" => "y",
+        "SecurityDefinition" => "d",
+        "Heartbeat" => "0",
+        "Logon" => "A",
+        "Logout" => "5",
+        "TestRequest" => "1",
+        "ResendRequest" => "2",
+        "SessionLevelReject" => "3",
+        "BusinessMessageReject" => "j",
+        _ => name,
+    }
+    .to_string()
+}
+
+fn fix_msg_type_name(code: &str) -> String {
+    match code {
+        "D" => "NewOrderSingle",
+        "8" => "ExecutionReport",
+        "F" => "OrderCancelRequest",
+        "G" => "OrderCancelReplaceRequest",
+        "9" => "OrderCancelReject",
+        "H" => "OrderStatusRequest",
+        "q" => "OrderMassCancelRequest",
+        "r" => "OrderMassCancelReport",
+        "E" => "NewOrderList",
+        "N" => "ListStatus",
+        "K" => "ListCancelRequest",
+        "V" => "MarketDataRequest",
+        "W" => "MarketDataSnapshotFullRefresh",
+        "X" => "MarketDataIncrementalRefresh",
+        "Y" => "MarketDataRequestReject",
+        "R" => "QuoteRequest",
+        "S" => "Quote",
+        "Z" => "QuoteCancel",
+        "x" => "SecurityListRequest",
+        "y" => "SecurityList",
+        "d" => "SecurityDefinition",
+        "0" => "Heartbeat",
+        "A" => "Logon",
+        "5" => "Logout",
+        "1" => "TestRequest",
+        "2" => "ResendRequest",
+        "3" => "SessionLevelReject",
+        "j" => "BusinessMessageReject",
+        _ => "Unknown",
+    }
+    .to_string()
+}
+
+fn tag_to_name(tag: u32) -> String {
+    match tag {
+        1 => "Account",
+        8 => "BeginString",
+        9 => "BodyLength",
+        10 => "CheckSum",
+        11 => "ClOrdID",
+        17 => "ExecID",
+        20 => "ExecTransType",
+        21 => "HandlInst",
+        22 => "SecurityIDSource",
+        48 => "SecurityID",
+        34 => "MsgSeqNum",
+        35 => "MsgType",
+        37 => "OrderID",
+        38 => "OrderQty",
+        39 => "OrdStatus",
+        40 => "OrdType",
+        41 => "OrigClOrdID",
+        44 => "Price",
+        49 => "SenderCompID",
+        52 => "SendingTime",
+        54 => "Side",
+        55 => "Symbol",
+        56 => "TargetCompID",
+        58 => "Text",
+        59 => "TimeInForce",
+        60 => "TransactTime",
+        62 => "ValidUntilTime",
+        73 => "NoOrders",
+        78 => "NoAllocs",
+        79 => "AllocAccount",
+        95 => "RawDataLength",
+        96 => "RawData",
+        117 => "QuoteID",
+        126 => "ExpireTime",
+        131 => "QuoteReqID",
+        132 => "BidPx",
+        133 => "OfferPx",
+        134 => "BidSize",
+        135 => "OfferSize",
+        146 => "NoRelatedSym",
+        167 => "SecurityType",
+        267 => "NoMDEntryTypes",
+        268 => "NoMDEntries",
+        269 => "MDEntryType",
+        279 => "MDUpdateAction",
+        295 => "NoQuoteEntries",
+        298 => "QuoteCancelType",
+        320 => "SecurityReqID",
+        393 => "TotNoRelatedSym",
+        347 => "MessageEncoding",
+        354 => "EncodedTextLen",
+        355 => "EncodedText",
+        432 => "ExpireDate",
+        448 => "PartyID",
+        453 => "NoPartyIDs",
+        559 => "SecurityListRequestType",
+        560 => "SecurityRequestResult",
+        561 => "RoundLot",
+        893 => "LastFragment",
+        969 => "MinPriceIncrement",
+        _ => "Unknown",
+    }
+    .to_string()
+}
+
+/// Lays out `/send` repeating groups as wire fields: each NoXXX count
+/// followed by its instances. Instance fields follow the dictionary's order
+/// for the group, so the delimiter comes first; fields it does not list, and
+/// groups it does not define, follow in tag order.
+fn group_fields(
+    dict: Option<&Dictionary>,
+    msg_type: &str,
+    groups: &Groups,
+) -> Result<Vec<(u32, String)>, String> {
+    let message = dict.and_then(|d| d.message(msg_type));
+    let tag_of = |key: &str| key.parse::<u32>().ok().or_else(|| dict?.tag_for(key));
+    let mut out = Vec::new();
+    for (key, instances) in groups {
+        let count_tag = tag_of(key).ok_or_else(|| format!("unknown group '{key}'"))?;
+        let order: Vec<u32> = message
+            .and_then(|m| m.group(count_tag))
+            .map(|g| {
+                g.members
+                    .iter()
+                    .filter_map(|m| match m {
+                        Member::Field { tag, .. } => Some(*tag),
+                        Member::Group(_) => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        out.push((count_tag, instances.len().to_string()));
+        for instance in instances {
+            let mut fields = Vec::with_capacity(instance.len());
+            for (k, v) in instance {
+                let tag = tag_of(k).ok_or_else(|| format!("unknown field '{k}' in '{key}'"))?;
+                fields.push((tag, v.clone()));
+            }
+            fields.sort_by_key(|(tag, _)| {
+                (order.iter().position(|t| t == tag).unwrap_or(usize::MAX), *tag)
+            });
+            out.extend(fields);
+        }
+    }
+    Ok(out)
+}
+
+fn build_fix_message(
+    header: &encode::Header<'_>,
+    extra_fields: &[(u32, String)],
+    group_fields: &[(u32, String)],
+) -> String {
+    let mut out = BytesMut::new();
+    encode::write_message(&mut out, header, extra_fields, group_fields);
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// SendingTime(52) for a message the session sends now, to the session's
+/// `timestamp_precision`.
+fn sending_time(state: &AppState, session_id: &str) -> encode::SendingTime {
+    let precision = state
+        .sessions
+        .snapshot(session_id)
+        .map(|s| s.timestamp_precision)
+        .unwrap_or_default();
+    encode::SendingTime::now(precision)
+}
+
+/// Resumes sessions' sequence numbers and state from the journal and the
+/// stored history, or from the shared session state if another instance has
+/// sent since. Run at startup, and for the sessions a reload adds.
+fn resume_sessions(
+    sessions: &mut [FixSession],
+    messages: &MessageStore,
+    journal: &mut SessionJournal,
+    session_state: Option<&SessionStateStore>,
+) {
+    let last_sent = messages.last_seq_nums(Direction::Outbound);
+    let last_received = messages.last_seq_nums(Direction::Inbound);
+    for s in sessions.iter_mut() {
+        // Sessions kept in memory start over, from the shared state if any.
+        if s.store == StoreBackend::Memory {
+            journal.exclude(&s.session_id);
+        } else {
+            if let Some(&seq) = last_sent.get(&s.session_id) {
+                s.msg_seq_num = seq + 1;
+            }
+            if let Some(&seq) = last_received.get(&s.session_id) {
+                s.expected_seq_num = seq + 1;
+            }
+        }
+        if let Some(entry) = journal.get(&s.session_id) {
+            s.msg_seq_num = s.msg_seq_num.max(entry.msg_seq_num);
+            s.expected_seq_num = s.expected_seq_num.max(entry.expected_seq_num);
+            s.state = entry.state.clone();
+        }
+        match session_state.map(|store| store.load(&s.session_id)) {
+            Some(Ok(Some(snapshot))) => {
+                s.msg_seq_num = s.msg_seq_num.max(snapshot.msg_seq_num);
+                if let Some(saved) = snapshot.state {
+                    s.state = saved;
+                }
+            }
+            Some(Err(e)) => {
+                warn!(session_id = %s.session_id, error = %e, "Failed to load session state")
+            }
+            _ => {}
+        }
+        if let Some(store) = session_state {
+            match store.last_seq_num(&s.session_id) {
+                Ok(seq) => s.msg_seq_num = s.msg_seq_num.max(seq + 1),
+                Err(e) => {
+                    let id = &s.session_id;
+                    warn!(session_id = %id, error = %e, "Failed to load the shared sequence number")
+                }
+            }
+        }
+    }
+}
+
+/// The sessions defined in the configuration file, or the built-in ones,
+/// each setting resolved from the session, the file's defaults, the
+/// environment or the built-in default, in that order.
+fn seed_sessions(config: &Config) -> Vec<FixSession> {
+    let now = epoch_ms() / 1000;
+    let mut rule_packs = rules::session_rule_packs_from_env();
+    let mut dictionaries = dictionary::session_dictionaries_from_env();
+    let strict_tags = std::env::var("FIX_SESSION_REJECT_UNKNOWN_TAGS").unwrap_or_default();
+    let strict_tags: Vec<&str> = strict_tags.split(',').map(str::trim).collect();
+    let acceptors = std::env::var("FIX_ACCEPTOR_SESSIONS").unwrap_or_default();
+    let acceptors: Vec<&str> = acceptors.split(',').map(str::trim).collect();
+    let mut cancel_on_disconnect = orders::cancel_on_disconnect_from_env();
+    let new_session = |sender: &str, target: &str, fix_version: &str| FixSession {
+        session_id: format!("{sender}->{target}"),
+        sender_comp_id: sender.to_string(),
+        target_comp_id: target.to_string(),
+        fix_version: fix_version.to_string(),
+        state: "ACTIVE".to_string(),
+        msg_seq_num: 1,
+        expected_seq_num: 1,
+        connected_at: now,
+        rule_packs: Vec::new(),
+        dictionary: None,
+        reject_unknown_tags: false,
+        acceptor: false,
+        cancel_on_disconnect: None,
+        host: None,
+        port: None,
+        schedule: None,
+        store: StoreBackend::Memory,
+        heartbeat_secs: DEFAULT_HEARTBEAT_SECS,
+        timestamp_precision: TimestampPrecision::default(),
+        sources: BTreeMap::new(),
+    };
+    let mut sessions = if config.sessions.is_empty() {
+        vec![
+            new_session(DEFAULT_SENDER, DEFAULT_TARGET, "FIX.4.4"),
+            new_session("ALICE", "MARKET_DATA", "FIX.5.0"),
+        ]
+    } else {
+        config
+            .sessions
+            .iter()
+            .map(|c| new_session(&c.sender_comp_id, &c.target_comp_id, &c.fix_version))
+            .collect()
+    };
+    let defaults = &config.defaults;
+    for session in &mut sessions {
+        let id = session.session_id.clone();
+        let defined = config.sessions.iter().find(|c| c.session_id() == id);
+        let transport = defined.map(|c| &c.transport);
+        let env_listed = |list: &[&str]| list.contains(&id.as_str()).then_some(true);
+        let sources = &mut session.sources;
+        let fix_version = defined.map(|c| c.fix_version.clone());
+        resolve(sources, "fix_version", fix_version, None, None);
+        session.acceptor = resolve(
+            sources,
+            "acceptor",
+            transport.and_then(|t| t.role).map(|r| r == TransportRole::Acceptor),
+            None,
+            env_listed(&acceptors),
+        )
+        .unwrap_or(false);
+        let host = transport.and_then(|t| t.host.clone());
+        session.host = resolve(sources, "host", host, None, None);
+        session.port = resolve(sources, "port", transport.and_then(|t| t.port), None, None);
+        let schedule = defined.and_then(|c| c.schedule.clone());
+        session.schedule = resolve(sources, "schedule", schedule, None, None);
+        session.rule_packs = resolve(
+            sources,
+            "rule_packs",
+            defined.and_then(|c| c.rule_packs.clone()),
+            None,
+            rule_packs.remove(&id),
+        )
+        .unwrap_or_default();
+        session.dictionary = resolve(
+            sources,
+            "dictionary",
+            defined.and_then(|c| c.dictionary.clone()),
+            defaults.dictionary.clone(),
+            dictionaries.remove(&id),
+        );
+        session.heartbeat_secs = resolve(
+            sources,
+            "heartbeat_secs",
+            defined.and_then(|c| c.heartbeat_secs),
+            defaults.heartbeat_secs,
+            None,
+        )
+        .unwrap_or(DEFAULT_HEARTBEAT_SECS);
+        session.timestamp_precision = resolve(
+            sources,
+            "timestamp_precision",
+            defined.and_then(|c| c.timestamp_precision),
+            defaults.timestamp_precision,
+            None,
+        )
+        .unwrap_or_default();
+        session.store = resolve(
+            sources,
+            "store",
+            defined.and_then(|c| c.store),
+            defaults.store,
+            StoreBackend::from_env(),
+        )
+        .unwrap_or(StoreBackend::Memory);
+        session.reject_unknown_tags = resolve(
+            sources,
+            "reject_unknown_tags",
+            None,
+            None,
+            env_listed(&strict_tags),
+        )
+        .unwrap_or(false);
+        session.cancel_on_disconnect = resolve(
+            sources,
+            "cancel_on_disconnect",
+            None,
+            None,
+            cancel_on_disconnect.remove(&id),
+        );
+    }
+    sessions
+}
+
+// ── Main ──────────────────────────────────────────────────────────────────────
+
+/// Runs the command given on the command line, exiting with its status.
+pub async fn run() {
+    let code = match Cli::parse().command() {
+        Command::Serve => return serve().await,
+        Command::Parse { file, summary } => cli::parse(&file, summary),
+        Command::Validate(args) => cli::validate(&args),
+        Command::Checksum { msg } => cli::checksum(&msg),
+        Command::Config {
+            command: ConfigCommand::Check,
+        } => i32::from(!preflight::run().await),
+    };
+    std::process::exit(code);
+}
+
+/// Runs the engine until it is stopped.
+async fn serve() {
+    // Read before anything else, as it configures logging too.
+    let config = Config::from_env().unwrap_or_else(|e| panic!("invalid configuration: {e}"));
+    let tracer_provider = telemetry::init(&config.logging);
+
+    let mut timeline = Timeline::default();
+    timeline.record(
+        EventKind::ProcessStarted,
+        None,
+        format!("fix-engine {}", env!("CARGO_PKG_VERSION")),
+    );
+    timeline.record(EventKind::ConfigLoaded, None, config.describe());
+
+    let reject_unknown_tags = std::env::var("FIX_REJECT_UNKNOWN_TAGS")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    let custom_tags = CustomTagRanges::from_env();
+    let strict_validation = std::env::var("FIX_STRICT_VALIDATION")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    let reject_duplicate_tags = std::env::var("FIX_REJECT_DUPLICATE_TAGS")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    // Every outbound HTTP call is bounded, so a slow upstream gateway, sink
+    // or identity provider holds up what waits on it, such as the sends
+    // queued behind a federated order in its session's task, for at most
+    // FIX_HTTP_TIMEOUT_MS.
+    let http_timeout = std::env::var("FIX_HTTP_TIMEOUT_MS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|&ms| ms > 0)
+        .map_or(Duration::from_secs(5), Duration::from_millis);
+    let http = reqwest::Client::builder()
+        .connect_timeout(http_timeout.min(Duration::from_secs(2)))
+        .timeout(http_timeout)
+        .build()
+        .unwrap_or_else(|e| panic!("invalid HTTP client: {e}"));
+    // Settings holding secrets may name where they are instead; one that
+    // cannot be read stops startup.
+    let secrets = Secrets::from_env(&http)
+        .await
+        .unwrap_or_else(|e| panic!("invalid secrets configuration: {e}"));
+    let secret = |result: Result<Option<Secret>, String>| {
+        result.unwrap_or_else(|e| panic!("unreadable secret {e}"))
+    };
+    let expiry_config = ExpiryConfig::from_env();
+    let venues = VenueProfiles::from_env();
+    let federation = Federation::from_env(secret(secrets.var("FIX_FEDERATION_API_KEY").await));
+    let sinks = Sinks::from_env();
+    let alert_config = AlertConfig::from_env(secret(secrets.var("FIX_ALERT_SECRET").await));
+    let conflation = ConflationConfig::from_env(&sinks);
+    let sbe_schemas = SbeSchemas::from_env();
+    let fast_templates = FastTemplates::from_env();
+    let mut seeded = seed_sessions(&config);
+    let dictionaries = Dictionaries::from_env();
+    for s in &seeded {
+        if let Some(path) = s.dictionary_file() {
+            let id = &s.session_id;
+            dictionaries
+                .load_session(id, path)
+                .unwrap_or_else(|e| panic!("invalid configuration: {id} dictionary {path}: {e}"));
+        }
+    }
+    let conditional_rules = ConditionalRules::from_env();
+    let rule_packs = RulePacks::from_env();
+    let templates = Templates::from_env();
+    let advisories = Advisories::from_env();
+    timeline.record(
+        EventKind::ConfigLoaded,
+        None,
+        format!(
+            "expiry_action={:?} upstreams={} sinks={} sbe_schemas={} fast_templates={} \
+             dictionaries={} conditional_rules={} rule_packs={} templates={} advisories={} \
+             reject_duplicate_tags={} reject_unknown_tags={} strict_validation={}",
+            expiry_config.action,
+            federation.upstream_count(),
+            sinks.len(),
+            sbe_schemas.len(),
+            fast_templates.len(),
+            dictionaries.versions().join("/"),
+            conditional_rules.len(),
+            rule_packs.len(),
+            templates.len(),
+            advisories.len(),
+            reject_duplicate_tags,
+            reject_unknown_tags,
+            strict_validation
+        ),
+    );
+    timeline.record(EventKind::ConfigLoaded, None, secrets.describe());
+    timeline.record(EventKind::ConfigLoaded, None, alert_config.describe());
+    // A TLS setup that cannot be used stops startup rather than serving
+    // order entry over plain HTTP.
+    let tls = match TlsConfig::from_env() {
+        Ok(Some(tls)) => tls
+            .server_config(&secrets)
+            .await
+            .map(|config| Some((tls.describe(), config))),
+        Ok(None) => Ok(None),
+        Err(e) => Err(e),
+    }
+    .unwrap_or_else(|e| panic!("invalid TLS configuration: {e}"));
+    timeline.record(
+        EventKind::ConfigLoaded,
+        None,
+        tls.as_ref()
+            .map_or("tls off (plain HTTP)", |(describe, _)| describe.as_str()),
+    );
+    let api_keys = ApiKeys::from_env(config.auth.api_keys_file.as_deref())
+        .unwrap_or_else(|e| panic!("invalid API keys file: {e}"));
+    let oidc = OidcConfig::from_env(&config.auth.oidc)
+        .unwrap_or_else(|e| panic!("invalid OIDC configuration: {e}"))
+        .map(Oidc::new);
+    if !api_keys.is_enabled() && oidc.is_none() {
+        warn!("Neither API keys nor OIDC are configured, so the REST API is open to anyone");
+    }
+    timeline.record(EventKind::ConfigLoaded, None, api_keys.describe());
+    if let Some(oidc) = &oidc {
+        timeline.record(EventKind::ConfigLoaded, None, oidc.describe());
+    }
+    let rate_limit = RateLimitConfig::from_env(&config.rate_limit)
+        .unwrap_or_else(|e| panic!("invalid rate limit configuration: {e}"));
+    timeline.record(
+        EventKind::ConfigLoaded,
+        None,
+        rate_limit.as_ref().map_or_else(|| "rate limit off".to_string(), RateLimitConfig::describe),
+    );
+    let limits = LimitsConfig::from_env()
+        .unwrap_or_else(|e| panic!("invalid request limit configuration: {e}"));
+    timeline.record(EventKind::ConfigLoaded, None, limits.describe());
+    let (body_bytes, bulk_body_bytes) = (limits.body_bytes(), limits.bulk_body_bytes());
+
+    let orders = OrderStore::from_env();
+    timeline.record(EventKind::StoreOpened, None, orders.describe());
+    let expiries = ExpiryTracker::new(expiry_config);
+    timeline.record(EventKind::StoreOpened, None, "expiry tracker (in-memory)");
+    let commit_config = CommitConfig::from_env();
+    timeline.record(EventKind::ConfigLoaded, None, commit_config.describe());
+    let group_commit = GroupCommit::start(commit_config);
+    let mut messages = MessageStore::from_env().unwrap_or_else(|e| {
+        warn!(error = %e, "Message store unavailable, keeping messages in memory");
+        MessageStore::default()
+    });
+    messages.set_group_commit(group_commit.clone());
+    timeline.record(
+        EventKind::StoreOpened,
+        None,
+        format!("{} messages={}", messages.describe(), messages.len()),
+    );
+
+    let session_state = SessionStateStore::from_env().and_then(|store| match store {
+        Ok(store) => {
+            timeline.record(EventKind::StoreOpened, None, store.describe());
+            Some(Arc::new(store))
+        }
+        Err(e) => {
+            warn!(error = %e, "Invalid FIX_STATE_REDIS_URL, session state stays local");
+            None
+        }
+    });
+
+    let mut journal = SessionJournal::from_env().unwrap_or_else(|e| {
+        warn!(error = %e, "Session journal unavailable, session state is not journalled");
+        SessionJournal::default()
+    });
+    journal.set_group_commit(group_commit);
+    timeline.record(EventKind::StoreOpened, None, journal.describe());
+
+    let audit = AuditLog::from_env().unwrap_or_else(|e| {
+        warn!(error = %e, "Audit log file unavailable, admin actions are kept in memory");
+        AuditLog::default()
+    });
+    timeline.record(EventKind::StoreOpened, None, audit.describe());
+
+    resume_sessions(&mut seeded, &messages, &mut journal, session_state.as_deref());
+    for s in &seeded {
+        let schedule = s.schedule.as_ref().map_or(String::new(), |schedule| {
+            format!(" schedule={}-{}", schedule.start, schedule.end)
+        });
+        timeline.record(
+            EventKind::SessionScheduled,
+            Some(&s.session_id),
+            format!(
+                "{} {} -> {} store={:?} heartbeat={}s{schedule}",
+                s.fix_version,
+                s.sender_comp_id,
+                s.target_comp_id,
+                s.store,
+                s.heartbeat_secs
+            ),
+        );
+        if s.state == "ACTIVE" {
+            timeline.record(EventKind::SessionConnected, Some(&s.session_id), "");
+        }
+    }
+
+    let export = ExportConfig::from_env().map(Arc::new);
+    if let Some(export) = &export {
+        timeline.record(EventKind::StoreOpened, None, export.describe());
+    }
+
+    let outbound_queue = OutboundQueue::from_env().unwrap_or_else(|e| {
+        warn!(error = %e, "Outbound queue store unavailable, queueing in memory");
+        OutboundQueue::default()
+    });
+    timeline.record(
+        EventKind::StoreOpened,
+        None,
+        format!(
+            "outbound queue messages={} max_depth={}",
+            outbound_queue.len(),
+            outbound_queue.max_depth()
+        ),
+    );
+    let queued_sessions = outbound_queue.sessions();
+    let readiness = ReadinessConfig::from_env(outbound_queue.max_depth());
+    timeline.record(EventKind::ConfigLoaded, None, readiness.describe());
+    let outbound = OutboundConfig::from_env();
+    timeline.record(EventKind::ConfigLoaded, None, outbound.describe());
+
+    let state = AppState {
+        start_time: Arc::new(Instant::now()),
+        sessions: Arc::new(SessionRegistry::new(seeded)),
+        reject_duplicate_tags,
+        reject_unknown_tags,
+        custom_tags: Arc::new(custom_tags),
+        strict_validation,
+        expiries: Arc::new(Mutex::new(expiries)),
+        orders: Arc::new(Mutex::new(orders)),
+        order_traces: Arc::new(Mutex::new(OrderTraces::default())),
+        venues: Arc::new(venues),
+        federation: Arc::new(Mutex::new(federation)),
+        messages: Arc::new(Mutex::new(messages)),
+        outbound_queue: Arc::new(Mutex::new(outbound_queue)),
+        outbound: Arc::new(OutboundChannels::new(outbound)),
+        actors: Arc::new(SessionActors::default()),
+        deliveries: Arc::new(Mutex::new(DeliveryTracker::default())),
+        idempotency: Arc::new(Mutex::new(IdempotencyKeys::from_env())),
+        latency: Arc::new(Mutex::new(LatencyStats::default())),
+        throughput: Arc::new(Mutex::new(Throughput::new(epoch_ms()))),
+        market_data: Arc::new(Mutex::new(MarketDataStore::default())),
+        books: Arc::new(Mutex::new(Books::default())),
+        quotes: Arc::new(Mutex::new(QuoteStore::default())),
+        securities: Arc::new(Mutex::new(SecurityCache::from_env())),
+        session_state,
+        journal: Arc::new(Mutex::new(journal)),
+        export,
+        sinks: Arc::new(sinks),
+        conflation: Arc::new(conflation),
+        traffic: Arc::new(TrafficFeed::default()),
+        session_events: Arc::new(SessionEvents::default()),
+        sbe: Arc::new(Mutex::new(sbe_schemas)),
+        fast: Arc::new(Mutex::new(fast_templates)),
+        dictionaries: Arc::new(dictionaries),
+        conditional_rules: Arc::new(conditional_rules),
+        rule_packs: Arc::new(Mutex::new(rule_packs)),
+        templates: Arc::new(Mutex::new(templates)),
+        advisories: Arc::new(advisories),
+        redrives: Arc::new(Mutex::new(Vec::new())),
+        timeline: Arc::new(Mutex::new(timeline)),
+        audit: Arc::new(Mutex::new(audit)),
+        alerts: Arc::new(Alerts::new(alert_config)),
+        readiness: Arc::new(readiness),
+        api_keys: Arc::new(Mutex::new(api_keys)),
+        oidc: oidc.map(Arc::new),
+        rate_limiter: Arc::new(Mutex::new(rate_limit.map(RateLimiter::new))),
+        config: Arc::new(Mutex::new(config.clone())),
+        limits: Arc::new(Limits::new(limits)),
+        http,
+    };
+
+    tokio::spawn(expiry::run_sweeper(state.clone()));
+    tokio::spawn(quotes::run_expiry(state.clone()));
+    tokio::spawn(conflation::run_sink_feed(state.clone()));
+    tokio::spawn(store::run_compactor(state.clone()));
+    tokio::spawn(export::run_exporter(state.clone()));
+    tokio::spawn(alerts::run_watchdog(state.clone()));
+    tokio::spawn(auth::run_reloader(state.clone()));
+    tokio::spawn(oidc::run_refresher(state.clone()));
+    #[cfg(unix)]
+    tokio::spawn(reload::on_sighup(state.clone()));
+    // Queues left by a previous run go out once their sessions are logged on.
+    for session_id in queued_sessions {
+        let state = state.clone();
+        tokio::spawn(async move { flush_queue(&state, &session_id).await });
+    }
+
+    let app = Router::new()
+        .route("/livez", get(health::livez))
+        .route("/readyz", get(health::readyz))
+        .route("/health", get(health::health))
+        .route("/api/v1/fix/send", post(send))
+        .route("/api/v1/fix/send/batch", post(batch::send_batch))
+        .route(
+            "/api/v1/fix/templates",
+            get(templates::list).post(templates::register),
+        )
+        .route(
+            "/api/v1/fix/templates/:name",
+            get(templates::get).delete(templates::delete),
+        )
+        .route("/api/v1/fix/parse", post(parse))
+        .route(
+            "/api/v1/fix/parse/bulk",
+            post(bulk_parse::parse).layer(DefaultBodyLimit::max(bulk_body_bytes)),
+        )
+        .route("/api/v1/fix/normalize", post(normalize))
+        .route("/api/v1/fix/fixml/encode", post(fixml::encode_handler))
+        .route("/api/v1/fix/fixml/decode", post(fixml::decode_handler))
+        .route(
+            "/api/v1/fix/sbe/schemas",
+            get(sbe::list_schemas).post(sbe::load_schema),
+        )
+        .route("/api/v1/fix/sbe/encode", post(sbe::encode_handler))
+        .route("/api/v1/fix/sbe/decode", post(sbe::decode_handler))
+        .route(
+            "/api/v1/fix/fast/templates",
+            get(fast::list_templates).post(fast::load_templates),
+        )
+        .route("/api/v1/fix/sessions", get(session_list::list))
+        .route("/api/v1/fix/sessions/events", get(session_events::stream))
+        .route("/api/v1/fix/sessions/:session_id", get(session_list::get))
+        .route("/api/v1/fix/sessions/:session_id/config", get(session_list::config))
+        .route("/api/v1/fix/sessions/:session_id/state", post(set_session_state))
+        .route(
+            "/api/v1/fix/sessions/:session_id/queue",
+            get(outbound_queue::list).delete(outbound_queue::drain),
+        )
+        .route("/api/v1/fix/sessions/:session_id/stats", get(throughput::get))
+        .route("/api/v1/fix/validate", post(validate))
+        .route("/api/v1/fix/dictionary/:version", get(dictionary::get))
+        .route(
+            "/api/v1/fix/dictionary/:version/messages/:msg_type",
+            get(dictionary::message),
+        )
+        .route("/api/v1/fix/inbound", post(inbound))
+        .route("/api/v1/fix/messages", get(store::search))
+        .route("/api/v1/fix/messages/:message_id/status", get(delivery::status))
+        .route("/api/v1/fix/stream", get(traffic::stream))
+        .route("/api/v1/fix/stats/latency", get(latency::get))
+        .route("/api/v1/orders", get(orders::list))
+        .route("/api/v1/orders/fast", post(fast_order::place))
+        .route("/api/v1/orders/:cl_ord_id", get(orders::get))
+        .route("/api/v1/orders/:cl_ord_id/fills", get(orders::fills))
+        .route("/api/v1/lists/:list_id", get(orders::get_list))
+        .route("/api/v2/orders", post(order_entry::place))
+        .route("/api/v2/orders/:cl_ord_id/cancel", post(order_entry::cancel))
+        .route("/api/v2/orders/:cl_ord_id/replace", post(order_entry::replace))
+        .route("/api/v1/marketdata/subscribe", post(marketdata::subscribe))
+        .route("/api/v1/marketdata/subscriptions", get(marketdata::list))
+        .route("/api/v1/marketdata/subscriptions/:md_req_id", get(marketdata::get))
+        .route("/api/v1/marketdata/book/:symbol", get(book::get))
+        .route("/api/v1/marketdata/stream", get(book::stream))
+        .route("/api/v1/quotes", get(quotes::list_quotes))
+        .route("/api/v1/quotes/request", post(quotes::request))
+        .route("/api/v1/quotes/cancel", post(quotes::cancel))
+        .route("/api/v1/quotes/requests", get(quotes::list_requests))
+        .route("/api/v1/quotes/requests/:quote_req_id", get(quotes::get_request))
+        .route("/api/v1/quotes/:quote_id", get(quotes::get_quote))
+        .route("/api/v1/securities", get(securities::list))
+        .route("/api/v1/securities/request", post(securities::request))
+        .route("/api/v1/securities/lists", get(securities::downloads))
+        .route("/api/v1/securities/:symbol", get(securities::get))
+        .route("/api/v1/fix/replay", post(replay::replay))
+        .route("/api/v1/fix/expiries", get(expiry::list))
+        .route(
+            "/api/v1/federation/upstreams",
+            get(federation::list_upstreams).post(federation::register_upstream),
+        )
+        .route("/api/v1/admin/timeline", get(timeline::list))
+        .route("/api/v1/admin/audit", get(audit::list))
+        .route(
+            "/api/v1/admin/redrives",
+            get(sinks::list_redrives).post(sinks::start_redrive),
+        )
+        .route("/api/v1/admin/records/upgrade", post(records::upgrade))
+        .route("/api/v1/admin/reload", post(reload::reload))
+        .route("/api/v1/admin/store/compact", post(store::compact))
+        .route("/api/v1/admin/store/export", post(export::export))
+        .route(
+            "/api/v1/admin/rule-packs",
+            get(rules::list_packs).post(rules::register_pack),
+        )
+        .merge(graphql::router(state.clone()))
+        .merge(openapi::swagger_ui())
+        .layer(axum::middleware::from_fn_with_state(state.clone(), rate_limit::limit))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), auth::authenticate))
+        .layer(axum::middleware::from_fn_with_state(state.clone(), limits::enforce))
+        .layer(DefaultBodyLimit::max(body_bytes))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(telemetry::request_span)
+                .on_request(telemetry::on_request)
+                .on_response(telemetry::on_response)
+                .on_failure(()),
+        )
+        .layer(axum::middleware::from_fn(problem::problem_details))
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeCorrelationId))
+        .layer(axum::middleware::from_fn(latency::mark_receipt))
+        .with_state(state.clone());
+
+    #[cfg(feature = "grpc")]
+    {
+        let addr = config.listeners.grpc.unwrap_or_else(|| {
+            let addr_str =
+                std::env::var("FIX_GRPC_ADDR").unwrap_or_else(|_| "0.0.0.0:50051".to_string());
+            addr_str.parse().expect("invalid FIX_GRPC_ADDR")
+        });
+        tokio::spawn(grpc::serve(state.clone(), addr));
+    }
+
+    let addr = config.listeners.http.unwrap_or_else(|| {
+        let addr_str = std::env::var("FIX_ADDR").unwrap_or_else(|_| "0.0.0.0:8081".to_string());
+        addr_str.parse().expect("invalid FIX_ADDR")
+    });
+
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    info!("ALICE FIX Engine listening on {}://{}", scheme, addr);
+
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .expect("failed to bind");
+
+    record_event(&state, EventKind::ListenerBound, format!("{scheme}://{addr}")).await;
+
+    let served = match tls {
+        Some((_, config)) => {
+            tls::serve(listener, config, app, shutdown_signal(state.clone())).await
+        }
+        None => {
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+                .with_graceful_shutdown(shutdown_signal(state.clone()))
+                .await
+        }
+    };
+    served.expect("server error");
+
+    record_event(&state, EventKind::DrainCompleted, "in-flight requests finished").await;
+    if let Some(provider) = tracer_provider {
+        telemetry::shutdown(provider);
+    }
+}
+
+async fn record_event(state: &AppState, kind: EventKind, detail: impl Into<String>) {
+    state.timeline.lock().await.record(kind, None, detail);
+}
+
+/// Resolves on Ctrl-C or SIGTERM, marking the start of the drain.
+async fn shutdown_signal(state: AppState) {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl-C handler");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    let signal = tokio::select! {
+        _ = ctrl_c => "SIGINT",
+        _ = terminate => "SIGTERM",
+    };
+    record_event(&state, EventKind::DrainInitiated, signal).await;
+}
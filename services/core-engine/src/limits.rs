@@ -0,0 +1,246 @@
+//! Request hardening: body size limits, timeouts and a concurrency cap.
+//!
+//! - Request bodies are limited to `FIX_MAX_BODY_BYTES` (default 2 MiB), and
+//!   logs for `/api/v1/fix/parse/bulk` to `FIX_MAX_BULK_BODY_BYTES` (default
+//!   64 MiB). A larger body is answered `413` before it is read in full.
+//! - A request that takes longer than `FIX_REQUEST_TIMEOUT_MS` (default
+//!   30 s) is abandoned and answered `504 request_timeout`.
+//!   `FIX_ROUTE_TIMEOUTS` sets other timeouts by route, as listed in the API
+//!   docs, e.g. `/api/v1/fix/send=5000,/api/v1/fix/parse/bulk=120000`; `0`
+//!   turns a timeout off. The timeout covers reading the body and producing
+//!   the response head, so streams and WebSockets are not cut off.
+//! - With `FIX_MAX_CONCURRENT_REQUESTS` set, a request arriving while that
+//!   many are in progress is answered `503 overloaded` at once, with
+//!   `Retry-After`, rather than waiting. The health probes are always served.
+//!
+//! So a huge or slow body, or a flood of sends, costs the engine a bounded
+//! amount of memory and work rather than taking it down.
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::sync::Semaphore;
+use tracing::warn;
+
+use crate::{bulk_parse, problem::ApiError, AppState};
+
+const DEFAULT_BODY_BYTES: usize = 2 * 1024 * 1024;
+const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+
+/// Served however busy the engine is, so it is not restarted for being
+/// loaded.
+const PROBE_PATHS: [&str; 3] = ["/livez", "/readyz", "/health"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitsConfig {
+    body_bytes: usize,
+    bulk_body_bytes: usize,
+    /// `None` for no timeout.
+    timeout: Option<Duration>,
+    /// By route, overriding `timeout`.
+    route_timeouts: HashMap<String, Option<Duration>>,
+    max_concurrent: Option<usize>,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            body_bytes: DEFAULT_BODY_BYTES,
+            bulk_body_bytes: bulk_parse::MAX_LOG_BYTES,
+            timeout: Some(Duration::from_millis(DEFAULT_TIMEOUT_MS)),
+            route_timeouts: HashMap::new(),
+            max_concurrent: None,
+        }
+    }
+}
+
+impl LimitsConfig {
+    /// Reads `FIX_MAX_BODY_BYTES`, `FIX_MAX_BULK_BODY_BYTES`,
+    /// `FIX_REQUEST_TIMEOUT_MS`, `FIX_ROUTE_TIMEOUTS` and
+    /// `FIX_MAX_CONCURRENT_REQUESTS`.
+    pub fn from_env() -> Result<Self, String> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let mut config = Self::default();
+        let number = |name: &str, value: &str| {
+            value
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| format!("{name} must be a whole number, not `{value}`"))
+        };
+        let positive = |name: &str, value: &str| match number(name, value)? {
+            0 => Err(format!("{name} must be positive")),
+            n => Ok(n),
+        };
+        if let Some(v) = var("FIX_MAX_BODY_BYTES") {
+            config.body_bytes = positive("FIX_MAX_BODY_BYTES", &v)? as usize;
+        }
+        if let Some(v) = var("FIX_MAX_BULK_BODY_BYTES") {
+            config.bulk_body_bytes = positive("FIX_MAX_BULK_BODY_BYTES", &v)? as usize;
+        }
+        if let Some(v) = var("FIX_REQUEST_TIMEOUT_MS") {
+            config.timeout = timeout(number("FIX_REQUEST_TIMEOUT_MS", &v)?);
+        }
+        if let Some(v) = var("FIX_MAX_CONCURRENT_REQUESTS") {
+            config.max_concurrent = Some(positive("FIX_MAX_CONCURRENT_REQUESTS", &v)? as usize);
+        }
+        if let Some(spec) = var("FIX_ROUTE_TIMEOUTS") {
+            config.route_timeouts = parse_route_timeouts(&spec)?;
+        }
+        Ok(config)
+    }
+
+    pub fn body_bytes(&self) -> usize {
+        self.body_bytes
+    }
+
+    pub fn bulk_body_bytes(&self) -> usize {
+        self.bulk_body_bytes
+    }
+
+    /// How long a request to `route` may take, if it is limited.
+    fn timeout_for(&self, route: &str) -> Option<Duration> {
+        match self.route_timeouts.get(route) {
+            Some(timeout) => *timeout,
+            None => self.timeout,
+        }
+    }
+
+    /// The limits, for startup reporting.
+    pub fn describe(&self) -> String {
+        let ms = |t: Option<Duration>| {
+            t.map_or("off".to_string(), |t| format!("{}ms", t.as_millis()))
+        };
+        let concurrent = self
+            .max_concurrent
+            .map_or("unlimited".to_string(), |n| n.to_string());
+        format!(
+            "request limits body={}B bulk_body={}B timeout={} route_timeouts={} max_concurrent={}",
+            self.body_bytes,
+            self.bulk_body_bytes,
+            ms(self.timeout),
+            self.route_timeouts.len(),
+            concurrent
+        )
+    }
+}
+
+/// A timeout of `ms`, or none for 0.
+fn timeout(ms: u64) -> Option<Duration> {
+    (ms > 0).then(|| Duration::from_millis(ms))
+}
+
+/// `ROUTE=MS` entries, comma-separated.
+fn parse_route_timeouts(spec: &str) -> Result<HashMap<String, Option<Duration>>, String> {
+    let mut timeouts = HashMap::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let parsed = entry.rsplit_once('=').and_then(|(route, ms)| {
+            let route = route.trim();
+            let ms = ms.trim().parse::<u64>().ok()?;
+            route.starts_with('/').then(|| (route.to_string(), timeout(ms)))
+        });
+        match parsed {
+            Some((route, timeout)) => {
+                timeouts.insert(route, timeout);
+            }
+            None => return Err(format!("FIX_ROUTE_TIMEOUTS entry `{entry}` is not ROUTE=MS")),
+        }
+    }
+    Ok(timeouts)
+}
+
+/// The limits in force, with the permits of the requests in progress.
+#[derive(Debug)]
+pub struct Limits {
+    config: LimitsConfig,
+    permits: Option<Arc<Semaphore>>,
+}
+
+impl Limits {
+    pub fn new(config: LimitsConfig) -> Self {
+        let permits = config.max_concurrent.map(|n| Arc::new(Semaphore::new(n)));
+        Self { config, permits }
+    }
+}
+
+/// Middleware shedding requests over the concurrency cap and abandoning
+/// those that outlast their timeout.
+pub async fn enforce(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let limits = &state.limits;
+    let path = req.uri().path().to_string();
+    let _permit = match &limits.permits {
+        Some(permits) if !PROBE_PATHS.contains(&path.as_str()) => {
+            match permits.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => {
+                    warn!(path = %path, "Shed API request over the concurrency limit");
+                    let error = ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "overloaded")
+                        .detail("too many requests in progress; retry shortly");
+                    return ([(header::RETRY_AFTER, "1")], error).into_response();
+                }
+            }
+        }
+        _ => None,
+    };
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(path.as_str(), MatchedPath::as_str);
+    let Some(timeout) = limits.config.timeout_for(route) else {
+        return next.run(req).await;
+    };
+    match tokio::time::timeout(timeout, next.run(req)).await {
+        Ok(response) => response,
+        Err(_) => {
+            let ms = timeout.as_millis();
+            warn!(path = %path, timeout_ms = ms, "API request timed out");
+            ApiError::new(StatusCode::GATEWAY_TIMEOUT, "request_timeout")
+                .detail(format!("the request took longer than {ms}ms"))
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_override_the_default_timeout() {
+        let config = LimitsConfig {
+            route_timeouts: parse_route_timeouts(
+                "/api/v1/fix/send=5000, /api/v1/fix/parse/bulk=120000,/api/v1/fix/replay=0",
+            )
+            .unwrap(),
+            ..LimitsConfig::default()
+        };
+        let ms = |route| config.timeout_for(route).map(|t| t.as_millis());
+        assert_eq!(ms("/api/v1/fix/send"), Some(5_000));
+        assert_eq!(ms("/api/v1/fix/parse/bulk"), Some(120_000));
+        assert_eq!(ms("/api/v1/fix/replay"), None);
+        assert_eq!(ms("/api/v1/orders/:cl_ord_id"), Some(30_000));
+
+        for bad in ["/api/v1/fix/send", "/api/v1/fix/send=soon", "send=5000"] {
+            assert!(parse_route_timeouts(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn limits_describe_themselves() {
+        let config = LimitsConfig {
+            timeout: None,
+            max_concurrent: Some(512),
+            ..LimitsConfig::default()
+        };
+        assert_eq!(
+            config.describe(),
+            "request limits body=2097152B bulk_body=67108864B timeout=off route_timeouts=0 \
+             max_concurrent=512"
+        );
+        let limits = Limits::new(config);
+        assert_eq!(limits.permits.unwrap().available_permits(), 512);
+    }
+}
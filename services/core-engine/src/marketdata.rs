@@ -0,0 +1,613 @@
+//! Market data subscriptions.
+//!
+//! `POST /api/v1/marketdata/subscribe` builds a MarketDataRequest (35=V) from
+//! typed parameters, with the NoMDEntryTypes (267) and NoRelatedSym (146)
+//! repeating groups filled in, and sends it like `/send` does (queued if the
+//! session is not logged on). Each request is tracked under its MDReqID (262):
+//! the first MarketDataSnapshotFullRefresh (35=W) or
+//! MarketDataIncrementalRefresh (35=X) for it makes it `ACTIVE`, and a
+//! MarketDataRequestReject (35=Y) makes it `REJECTED`. An unsubscribe
+//! (SubscriptionRequestType 2) names an existing MDReqID and repeats its
+//! symbols and entry types, as FIX requires.
+//!
+//! When a subscription's incremental refreshes skip a RptSeq, [`recover`]
+//! re-requests a snapshot of the symbol as `<MDReqID>-R<n>`, and that
+//! snapshot restores the subscription's book.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::{
+    auth::Caller, clock::epoch_ms, find_session, problem::ApiError, queue_if_offline,
+    send_message, AppState, FixSession, Groups, OutputEncoding, QueuedResponse, SendOptions,
+    SendRequest, SentMessage,
+};
+
+// ── Request parameters ────────────────────────────────────────────────────────
+
+/// SubscriptionRequestType (263).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SubscriptionType {
+    /// One snapshot, no updates.
+    Snapshot,
+    /// A snapshot followed by updates.
+    #[default]
+    Subscribe,
+    /// Ends an earlier subscription.
+    Unsubscribe,
+}
+
+impl SubscriptionType {
+    fn code(self) -> &'static str {
+        match self {
+            Self::Snapshot => "0",
+            Self::Subscribe => "1",
+            Self::Unsubscribe => "2",
+        }
+    }
+}
+
+/// MDUpdateType (265).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum UpdateType {
+    FullRefresh,
+    IncrementalRefresh,
+}
+
+impl UpdateType {
+    fn code(self) -> &'static str {
+        match self {
+            Self::FullRefresh => "0",
+            Self::IncrementalRefresh => "1",
+        }
+    }
+}
+
+/// MDEntryType (269).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EntryType {
+    Bid,
+    Offer,
+    Trade,
+    IndexValue,
+    OpeningPrice,
+    ClosingPrice,
+    SettlementPrice,
+    TradingSessionHighPrice,
+    TradingSessionLowPrice,
+    TradingSessionVwapPrice,
+    Imbalance,
+    TradeVolume,
+    OpenInterest,
+}
+
+impl EntryType {
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Bid => "0",
+            Self::Offer => "1",
+            Self::Trade => "2",
+            Self::IndexValue => "3",
+            Self::OpeningPrice => "4",
+            Self::ClosingPrice => "5",
+            Self::SettlementPrice => "6",
+            Self::TradingSessionHighPrice => "7",
+            Self::TradingSessionLowPrice => "8",
+            Self::TradingSessionVwapPrice => "9",
+            Self::Imbalance => "A",
+            Self::TradeVolume => "B",
+            Self::OpenInterest => "C",
+        }
+    }
+}
+
+fn default_entry_types() -> Vec<EntryType> {
+    vec![EntryType::Bid, EntryType::Offer]
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct SubscribeRequest {
+    /// `SenderCompID->TargetCompID` of a configured session.
+    pub session_id: String,
+    /// Generated if omitted; required to unsubscribe.
+    #[serde(default)]
+    pub md_req_id: Option<String>,
+    #[serde(default)]
+    pub subscription_type: SubscriptionType,
+    /// MarketDepth (264): 0 for the full book, 1 for top of book, N levels.
+    #[serde(default)]
+    pub market_depth: u32,
+    #[serde(default)]
+    pub update_type: Option<UpdateType>,
+    /// Symbols for NoRelatedSym; an unsubscribe defaults to the
+    /// subscription's.
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    /// Entry types for NoMDEntryTypes; bids and offers if omitted.
+    #[serde(default)]
+    pub entry_types: Vec<EntryType>,
+}
+
+// ── Subscriptions ─────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SubscriptionState {
+    /// Sent (or queued), nothing received for it yet.
+    Pending,
+    /// Market data has been received for it.
+    Active,
+    /// A snapshot request whose snapshot has arrived.
+    Complete,
+    Rejected,
+    Unsubscribed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Subscription {
+    pub md_req_id: String,
+    pub session_id: String,
+    pub subscription_type: SubscriptionType,
+    pub market_depth: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_type: Option<UpdateType>,
+    pub symbols: Vec<String>,
+    pub entry_types: Vec<EntryType>,
+    pub state: SubscriptionState,
+    /// MDReqRejReason (281) and Text (58) of a MarketDataRequestReject.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reject_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reject_text: Option<String>,
+    /// Snapshots and incremental refreshes received.
+    pub messages: u64,
+    /// For a snapshot re-requested after a gap, the subscription whose book
+    /// it restores.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery_of: Option<String>,
+    /// Snapshots re-requested for the subscription after gaps.
+    #[serde(default)]
+    pub recoveries: u64,
+    pub requested_at_ms: u64,
+    pub updated_at_ms: u64,
+}
+
+impl Subscription {
+    /// The MarketDataRequest's body fields and repeating groups, keyed by tag
+    /// so they encode without a data dictionary for the session's version.
+    fn request(&self, subscription_type: SubscriptionType) -> (HashMap<String, String>, Groups) {
+        let mut fields = HashMap::from([
+            ("262".to_string(), self.md_req_id.clone()),
+            ("263".to_string(), subscription_type.code().to_string()),
+            ("264".to_string(), self.market_depth.to_string()),
+        ]);
+        if let Some(update_type) = self.update_type {
+            fields.insert("265".to_string(), update_type.code().to_string());
+        }
+        let instances = |tag: &str, values: Vec<&str>| -> Vec<HashMap<String, String>> {
+            values
+                .into_iter()
+                .map(|v| HashMap::from([(tag.to_string(), v.to_string())]))
+                .collect()
+        };
+        let groups = Groups::from([
+            (
+                "267".to_string(),
+                instances("269", self.entry_types.iter().map(|t| t.code()).collect()),
+            ),
+            (
+                "146".to_string(),
+                instances("55", self.symbols.iter().map(String::as_str).collect()),
+            ),
+        ]);
+        (fields, groups)
+    }
+}
+
+#[derive(Default)]
+pub struct MarketDataStore {
+    subscriptions: HashMap<String, Subscription>,
+}
+
+impl MarketDataStore {
+    pub fn get(&self, md_req_id: &str) -> Option<&Subscription> {
+        self.subscriptions.get(md_req_id)
+    }
+
+    /// Subscriptions, oldest first.
+    pub fn list(&self) -> Vec<Subscription> {
+        let mut items: Vec<Subscription> = self.subscriptions.values().cloned().collect();
+        items.sort_by(|a, b| {
+            (a.requested_at_ms, &a.md_req_id).cmp(&(b.requested_at_ms, &b.md_req_id))
+        });
+        items
+    }
+
+    /// The MDReqID whose book data for `md_req_id` belongs to: the
+    /// subscription a recovery snapshot was requested for, or itself.
+    pub fn book_id(&self, md_req_id: &str) -> String {
+        self.subscriptions
+            .get(md_req_id)
+            .and_then(|s| s.recovery_of.clone())
+            .unwrap_or_else(|| md_req_id.to_string())
+    }
+
+    fn record(&mut self, subscription: Subscription) {
+        self.subscriptions
+            .insert(subscription.md_req_id.clone(), subscription);
+    }
+
+    fn unsubscribed(&mut self, md_req_id: &str, at_ms: u64) -> Option<Subscription> {
+        let subscription = self.subscriptions.get_mut(md_req_id)?;
+        subscription.state = SubscriptionState::Unsubscribed;
+        subscription.updated_at_ms = at_ms;
+        Some(subscription.clone())
+    }
+
+    /// A snapshot (`snapshot`) or incremental refresh arrived for
+    /// `md_req_id`.
+    pub fn on_data(&mut self, md_req_id: &str, snapshot: bool, at_ms: u64) -> Option<Subscription> {
+        let subscription = self.subscriptions.get_mut(md_req_id)?;
+        subscription.messages += 1;
+        subscription.updated_at_ms = at_ms;
+        subscription.state = match (subscription.state, subscription.subscription_type) {
+            (SubscriptionState::Pending, SubscriptionType::Snapshot) if snapshot => {
+                SubscriptionState::Complete
+            }
+            (SubscriptionState::Pending, _) => SubscriptionState::Active,
+            (state, _) => state,
+        };
+        Some(subscription.clone())
+    }
+
+    pub fn on_reject(
+        &mut self,
+        md_req_id: &str,
+        reason: Option<&str>,
+        text: Option<&str>,
+        at_ms: u64,
+    ) -> Option<Subscription> {
+        let subscription = self.subscriptions.get_mut(md_req_id)?;
+        subscription.state = SubscriptionState::Rejected;
+        subscription.reject_reason = reason.map(str::to_string);
+        subscription.reject_text = text.map(str::to_string);
+        subscription.updated_at_ms = at_ms;
+        Some(subscription.clone())
+    }
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SubscribeResponse {
+    subscription: Subscription,
+    /// The MarketDataRequest as sent; absent if it was queued.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sequence_number: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queue_id: Option<String>,
+}
+
+/// Builds and sends a MarketDataRequest, returning `202 Accepted` if it was
+/// queued for the session.
+#[utoipa::path(
+    post,
+    path = "/api/v1/marketdata/subscribe",
+    tag = "marketdata",
+    request_body = SubscribeRequest,
+    responses(
+        (status = 200, description = "Sent", body = SubscribeResponse),
+        (
+            status = 202,
+            description = "Queued until the session is logged on",
+            body = SubscribeResponse
+        ),
+        (status = 404, description = "Unknown session"),
+        (status = 422, description = "Invalid request"),
+        (status = 429, description = "The client is over its rate limit; see Retry-After"),
+    )
+)]
+pub async fn subscribe(
+    State(state): State<AppState>,
+    caller: Caller,
+    Json(req): Json<SubscribeRequest>,
+) -> Result<Response, ApiError> {
+    caller.check_session(&req.session_id)?;
+    let session = find_session(&state, &req.session_id).ok_or(StatusCode::NOT_FOUND)?;
+    let now_ms = epoch_ms();
+    let subscription = match req.subscription_type {
+        SubscriptionType::Unsubscribe => {
+            let md_req_id = req
+                .md_req_id
+                .as_deref()
+                .ok_or(StatusCode::UNPROCESSABLE_ENTITY)?;
+            let mut existing = state
+                .market_data
+                .lock()
+                .await
+                .get(md_req_id)
+                .filter(|s| s.session_id == session.session_id)
+                .cloned()
+                .ok_or(StatusCode::NOT_FOUND)?;
+            if !req.symbols.is_empty() {
+                existing.symbols = req.symbols;
+            }
+            if !req.entry_types.is_empty() {
+                existing.entry_types = req.entry_types;
+            }
+            existing
+        }
+        subscription_type => {
+            if req.symbols.is_empty() || req.symbols.iter().any(|s| s.trim().is_empty()) {
+                return Err(StatusCode::UNPROCESSABLE_ENTITY.into());
+            }
+            Subscription {
+                md_req_id: req
+                    .md_req_id
+                    .unwrap_or_else(|| format!("MD-{}", uuid::Uuid::new_v4().simple())),
+                session_id: session.session_id.clone(),
+                subscription_type,
+                market_depth: req.market_depth,
+                update_type: req.update_type,
+                symbols: req.symbols,
+                entry_types: if req.entry_types.is_empty() {
+                    default_entry_types()
+                } else {
+                    req.entry_types
+                },
+                state: SubscriptionState::Pending,
+                reject_reason: None,
+                reject_text: None,
+                messages: 0,
+                recovery_of: None,
+                recoveries: 0,
+                requested_at_ms: now_ms,
+                updated_at_ms: now_ms,
+            }
+        }
+    };
+
+    let (sent, queued) = transmit(&state, &session, &subscription, req.subscription_type).await?;
+
+    let subscription = {
+        let mut store = state.market_data.lock().await;
+        match req.subscription_type {
+            SubscriptionType::Unsubscribe => store
+                .unsubscribed(&subscription.md_req_id, now_ms)
+                .unwrap_or(subscription),
+            _ => {
+                store.record(subscription.clone());
+                subscription
+            }
+        }
+    };
+    info!(
+        session_id = %subscription.session_id,
+        md_req_id = %subscription.md_req_id,
+        subscription_type = ?req.subscription_type,
+        symbols = subscription.symbols.len(),
+        queued = queued.is_some(),
+        "MarketDataRequest sent"
+    );
+    let status = if queued.is_some() {
+        StatusCode::ACCEPTED
+    } else {
+        StatusCode::OK
+    };
+    let response = SubscribeResponse {
+        subscription,
+        fix_message: sent.as_ref().map(|s| s.fix_message.clone()),
+        sequence_number: sent.as_ref().map(|s| s.sequence_number),
+        queue_id: queued.map(|q| q.queue_id),
+    };
+    Ok((status, Json(response)).into_response())
+}
+
+/// Sends a subscription's MarketDataRequest, or queues it if the session is
+/// not logged on.
+async fn transmit(
+    state: &AppState,
+    session: &FixSession,
+    subscription: &Subscription,
+    subscription_type: SubscriptionType,
+) -> Result<(Option<SentMessage>, Option<QueuedResponse>), StatusCode> {
+    let (mut fields, groups) = subscription.request(subscription_type);
+    for (name, value) in [
+        ("BeginString", &session.fix_version),
+        ("SenderCompID", &session.sender_comp_id),
+        ("TargetCompID", &session.target_comp_id),
+    ] {
+        fields.insert(name.to_string(), value.clone());
+    }
+    let send = SendRequest {
+        msg_type: "MarketDataRequest".to_string(),
+        fields,
+        groups,
+        metadata: HashMap::new(),
+        reply_to: None,
+        ttl_ms: None,
+        template: None,
+        attribution: None,
+    };
+    let opts = SendOptions {
+        encoding: OutputEncoding::default(),
+        validate: false,
+        strict: None,
+    };
+    if let Some(queued) = queue_if_offline(state, &opts, &send).await? {
+        return Ok((None, Some(queued)));
+    }
+    Ok((Some(send_message(state, &opts, send).await?), None))
+}
+
+/// Re-requests a snapshot of `symbol` after a gap in `md_req_id`'s
+/// incremental refreshes. Returns `None` if the gateway did not request
+/// `md_req_id`.
+pub async fn recover(
+    state: &AppState,
+    md_req_id: &str,
+    symbol: &str,
+) -> Result<Option<Subscription>, StatusCode> {
+    let now_ms = epoch_ms();
+    let recovery = {
+        let mut store = state.market_data.lock().await;
+        let Some(original) = store.subscriptions.get_mut(md_req_id) else {
+            return Ok(None);
+        };
+        original.recoveries += 1;
+        let recovery = Subscription {
+            md_req_id: format!("{}-R{}", original.md_req_id, original.recoveries),
+            subscription_type: SubscriptionType::Snapshot,
+            update_type: None,
+            symbols: vec![symbol.to_string()],
+            state: SubscriptionState::Pending,
+            reject_reason: None,
+            reject_text: None,
+            messages: 0,
+            recovery_of: Some(original.md_req_id.clone()),
+            recoveries: 0,
+            requested_at_ms: now_ms,
+            updated_at_ms: now_ms,
+            ..original.clone()
+        };
+        // Recorded first, so its snapshot is recognised however soon it comes.
+        store.record(recovery.clone());
+        recovery
+    };
+    let session = find_session(state, &recovery.session_id).ok_or(StatusCode::NOT_FOUND)?;
+    let (_, queued) = transmit(state, &session, &recovery, SubscriptionType::Snapshot).await?;
+    info!(
+        session_id = %recovery.session_id,
+        md_req_id = %md_req_id,
+        recovery = %recovery.md_req_id,
+        symbol = %symbol,
+        queued = queued.is_some(),
+        "Market data snapshot re-requested after a gap"
+    );
+    Ok(Some(recovery))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SubscriptionsResponse {
+    items: Vec<Subscription>,
+    count: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/marketdata/subscriptions",
+    tag = "marketdata",
+    responses(
+        (status = 200, description = "OK", body = SubscriptionsResponse),
+    )
+)]
+pub async fn list(State(state): State<AppState>) -> Json<SubscriptionsResponse> {
+    let items = state.market_data.lock().await.list();
+    let count = items.len();
+    Json(SubscriptionsResponse { items, count })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/marketdata/subscriptions/{md_req_id}",
+    tag = "marketdata",
+    params(("md_req_id" = String, Path, description = "The subscription's MDReqID.")),
+    responses(
+        (status = 200, description = "OK", body = Subscription),
+        (status = 404, description = "Unknown subscription"),
+    )
+)]
+pub async fn get(
+    State(state): State<AppState>,
+    Path(md_req_id): Path<String>,
+) -> Result<Json<Subscription>, StatusCode> {
+    state
+        .market_data
+        .lock()
+        .await
+        .get(&md_req_id)
+        .cloned()
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription(subscription_type: SubscriptionType) -> Subscription {
+        Subscription {
+            md_req_id: "MD1".to_string(),
+            session_id: "ALICE->MARKET_DATA".to_string(),
+            subscription_type,
+            market_depth: 5,
+            update_type: Some(UpdateType::IncrementalRefresh),
+            symbols: vec!["AAPL".to_string(), "MSFT".to_string()],
+            entry_types: vec![EntryType::Bid, EntryType::Offer, EntryType::Trade],
+            state: SubscriptionState::Pending,
+            reject_reason: None,
+            reject_text: None,
+            messages: 0,
+            recovery_of: None,
+            recoveries: 0,
+            requested_at_ms: 1,
+            updated_at_ms: 1,
+        }
+    }
+
+    #[test]
+    fn requests_carry_entry_type_and_symbol_groups() {
+        let sub = subscription(SubscriptionType::Subscribe);
+        let (fields, groups) = sub.request(SubscriptionType::Subscribe);
+        assert_eq!(fields["262"], "MD1");
+        assert_eq!(fields["263"], "1");
+        assert_eq!(fields["264"], "5");
+        assert_eq!(fields["265"], "1");
+        let values = |key: &str, tag: &str| -> Vec<String> {
+            groups[key].iter().map(|i| i[tag].clone()).collect()
+        };
+        assert_eq!(values("267", "269"), ["0", "1", "2"]);
+        assert_eq!(values("146", "55"), ["AAPL", "MSFT"]);
+
+        let (fields, _) = sub.request(SubscriptionType::Unsubscribe);
+        assert_eq!(fields["263"], "2");
+    }
+
+    #[test]
+    fn subscriptions_follow_refreshes_and_rejects() {
+        let mut store = MarketDataStore::default();
+        store.record(subscription(SubscriptionType::Subscribe));
+        let active = store.on_data("MD1", true, 2).unwrap();
+        assert_eq!(active.state, SubscriptionState::Active);
+        assert_eq!(store.on_data("MD1", false, 3).unwrap().messages, 2);
+        assert!(store.on_data("MD2", false, 3).is_none());
+
+        let mut snapshot = subscription(SubscriptionType::Snapshot);
+        snapshot.md_req_id = "MD2".to_string();
+        store.record(snapshot);
+        assert_eq!(
+            store.on_data("MD2", true, 4).unwrap().state,
+            SubscriptionState::Complete
+        );
+
+        let rejected = store.on_reject("MD1", Some("0"), Some("unknown symbol"), 5);
+        assert_eq!(rejected.unwrap().state, SubscriptionState::Rejected);
+        assert_eq!(
+            store.unsubscribed("MD2", 6).unwrap().state,
+            SubscriptionState::Unsubscribed
+        );
+        assert_eq!(store.list().len(), 2);
+    }
+}
@@ -0,0 +1,427 @@
+//! Lossless tag=value message model.
+//!
+//! [`FixMessage::parse`] accepts only canonical wire form (decimal tags without
+//! leading zeros, every field terminated by the delimiter). For every message
+//! it accepts, re-encoding with the parsed delimiter reproduces the input byte
+//! for byte: field order and unknown tags are kept exactly as received.
+//!
+//! Raw data fields (RawData, XmlData, the Encoded* text fields, ...) are read
+//! using the byte count from their preceding length field, so they may carry
+//! SOH bytes and non-UTF-8 text such as Shift-JIS.
+
+use std::{borrow::Cow, fmt};
+
+pub const SOH: u8 = 0x01;
+
+/// MessageEncoding(347): the charset of every Encoded* field in the message.
+pub const MESSAGE_ENCODING: u32 = 347;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub tag: u32,
+    pub value: Vec<u8>,
+}
+
+impl Field {
+    pub fn value_str(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixMessage {
+    /// Field delimiter found on the wire: SOH, or `|` for human-readable logs.
+    pub delimiter: u8,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    /// The last field is not followed by a delimiter.
+    Unterminated,
+    /// A field at the given byte offset has no `=`.
+    MissingEquals(usize),
+    /// A field at the given byte offset has a tag that is not a canonical positive integer.
+    InvalidTag(usize),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "message is empty"),
+            ParseError::Unterminated => write!(f, "last field is not terminated by a delimiter"),
+            ParseError::MissingEquals(at) => write!(f, "field at byte {at} has no '='"),
+            ParseError::InvalidTag(at) => write!(f, "field at byte {at} has an invalid tag"),
+        }
+    }
+}
+
+impl FixMessage {
+    /// Parses a SOH- or `|`-delimited message.
+    pub fn parse(raw: &[u8]) -> Result<Self, ParseError> {
+        if raw.is_empty() {
+            return Err(ParseError::Empty);
+        }
+        let delimiter = detect_delimiter(raw);
+
+        let mut fields = Vec::new();
+        for segment in scan(raw, delimiter) {
+            let value = segment
+                .value
+                .ok_or(ParseError::MissingEquals(segment.offset))?;
+            let tag = canonical_tag(segment.tag).ok_or(ParseError::InvalidTag(segment.offset))?;
+            if !segment.terminated {
+                return Err(ParseError::Unterminated);
+            }
+            fields.push(Field {
+                tag,
+                value: value.to_vec(),
+            });
+        }
+
+        Ok(Self { delimiter, fields })
+    }
+
+    /// Builds a SOH-delimited message from BeginString and the fields that
+    /// follow BodyLength (MsgType first), adding BodyLength and CheckSum.
+    pub fn framed(begin_string: &str, body: Vec<Field>) -> Self {
+        let mut msg = Self {
+            delimiter: SOH,
+            fields: body,
+        };
+        let body_length = msg.encode_with(SOH).len();
+        msg.fields.splice(
+            0..0,
+            [
+                Field {
+                    tag: 8,
+                    value: begin_string.as_bytes().to_vec(),
+                },
+                Field {
+                    tag: 9,
+                    value: body_length.to_string().into_bytes(),
+                },
+            ],
+        );
+        let checksum = checksum(&msg.encode_with(SOH), SOH);
+        msg.fields.push(Field {
+            tag: 10,
+            value: format!("{checksum:03}").into_bytes(),
+        });
+        msg
+    }
+
+    /// Encodes the message; passing `self.delimiter` reproduces the parsed input.
+    pub fn encode_with(&self, delimiter: u8) -> Vec<u8> {
+        let len = self
+            .fields
+            .iter()
+            .map(|f| f.value.len() + 12)
+            .sum::<usize>();
+        let mut out = Vec::with_capacity(len);
+        for field in &self.fields {
+            out.extend_from_slice(field.tag.to_string().as_bytes());
+            out.push(b'=');
+            out.extend_from_slice(&field.value);
+            out.push(delimiter);
+        }
+        out
+    }
+}
+
+/// SOH if the message contains one, otherwise `|`.
+pub fn detect_delimiter(raw: &[u8]) -> u8 {
+    if memchr::memchr(SOH, raw).is_some() {
+        SOH
+    } else {
+        b'|'
+    }
+}
+
+/// Bytes summed side by side by [`checksum`]; a multiple of every SIMD
+/// register width, so the compiler vectorizes the loop.
+const CHECKSUM_LANES: usize = 64;
+
+/// CheckSum(10) of the bytes before it: their sum modulo 256, a `delimiter`
+/// such as `|` counting as SOH.
+///
+/// The bytes are summed in independent wrapping lanes, which is exact modulo
+/// 256 and compiles to vector adds, and each delimiter other than SOH is then
+/// corrected for in one pass of [`memchr`].
+pub fn checksum(bytes: &[u8], delimiter: u8) -> u8 {
+    let mut lanes = [0u8; CHECKSUM_LANES];
+    let chunks = bytes.chunks_exact(CHECKSUM_LANES);
+    let tail = chunks.remainder();
+    for chunk in chunks {
+        for (lane, &b) in lanes.iter_mut().zip(chunk) {
+            *lane = lane.wrapping_add(b);
+        }
+    }
+    let sum = lanes
+        .iter()
+        .chain(tail)
+        .fold(0u8, |sum, &b| sum.wrapping_add(b));
+    if delimiter == SOH {
+        return sum;
+    }
+    // Truncating the count is exact modulo 256 too.
+    let delimiters = memchr::memchr_iter(delimiter, bytes).count() as u8;
+    sum.wrapping_sub(delimiters.wrapping_mul(delimiter.wrapping_sub(SOH)))
+}
+
+// ── Scanning ──────────────────────────────────────────────────────────────────
+
+/// One `tag=value` segment as found on the wire, before tag validation.
+pub struct Segment<'a> {
+    pub offset: usize,
+    pub tag: &'a [u8],
+    /// `None` if the segment has no `=`.
+    pub value: Option<&'a [u8]>,
+    /// Whether the segment is followed by the delimiter.
+    pub terminated: bool,
+}
+
+/// Splits `raw` into segments. A raw data field directly following its length
+/// field is read by byte count rather than up to the next delimiter.
+///
+/// Delimiters and `=` are found with [`memchr`], which compares a vector of
+/// bytes at a time.
+pub fn scan(raw: &[u8], delimiter: u8) -> Vec<Segment<'_>> {
+    let mut segments: Vec<Segment<'_>> = Vec::new();
+    let mut pos = 0;
+
+    while pos < raw.len() {
+        let rest = &raw[pos..];
+        let next_delim = memchr::memchr(delimiter, rest);
+        // Only an `=` before the delimiter belongs to this segment.
+        let eq = memchr::memchr(b'=', &rest[..next_delim.unwrap_or(rest.len())]);
+
+        let segment = match eq {
+            Some(eq) => {
+                let tag = &rest[..eq];
+                let value_start = eq + 1;
+                let data_len = lenient_tag(tag)
+                    .and_then(length_tag_for)
+                    .and_then(|len_tag| {
+                        segments
+                            .last()
+                            .filter(|prev| lenient_tag(prev.tag) == Some(len_tag))
+                    })
+                    .and_then(|prev| prev.value)
+                    .and_then(|v| std::str::from_utf8(v).ok()?.parse::<usize>().ok())
+                    .filter(|&n| {
+                        let end = value_start + n;
+                        end <= rest.len() && (end == rest.len() || rest[end] == delimiter)
+                    });
+                let value_end = match data_len {
+                    Some(n) => value_start + n,
+                    None => next_delim.unwrap_or(rest.len()),
+                };
+                Segment {
+                    offset: pos,
+                    tag,
+                    value: Some(&rest[value_start..value_end]),
+                    terminated: value_end < rest.len(),
+                }
+            }
+            _ => {
+                let end = next_delim.unwrap_or(rest.len());
+                Segment {
+                    offset: pos,
+                    tag: &rest[..end],
+                    value: None,
+                    terminated: end < rest.len(),
+                }
+            }
+        };
+
+        pos += segment.tag.len()
+            + segment.value.map_or(0, |v| v.len() + 1)
+            + usize::from(segment.terminated);
+        segments.push(segment);
+    }
+    segments
+}
+
+/// Tags are positive decimal integers with no sign, whitespace, or leading zeros.
+fn canonical_tag(s: &[u8]) -> Option<u32> {
+    if s.is_empty() || s[0] == b'0' || !s.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    std::str::from_utf8(s).ok()?.parse().ok()
+}
+
+/// Tag parsing for the tolerant parser: surrounding whitespace is ignored.
+pub fn lenient_tag(s: &[u8]) -> Option<u32> {
+    std::str::from_utf8(s).ok()?.trim().parse().ok()
+}
+
+// ── Raw data and encoded text ─────────────────────────────────────────────────
+
+/// The length field announcing the byte count of a raw data field.
+pub fn length_tag_for(data_tag: u32) -> Option<u32> {
+    match data_tag {
+        91 => Some(90),   // SecureData
+        96 => Some(95),   // RawData
+        213 => Some(212), // XmlData
+        349 => Some(348), // EncodedIssuer
+        351 => Some(350), // EncodedSecurityDesc
+        353 => Some(352), // EncodedListExecInst
+        355 => Some(354), // EncodedText
+        357 => Some(356), // EncodedSubject
+        361 => Some(360), // EncodedAllocText
+        363 => Some(362), // EncodedUnderlyingIssuer
+        365 => Some(364), // EncodedUnderlyingSecurityDesc
+        446 => Some(445), // EncodedListStatusText
+        619 => Some(618), // EncodedLegIssuer
+        622 => Some(621), // EncodedLegSecurityDesc
+        _ => None,
+    }
+}
+
+/// Encoded* fields hold text in the charset named by MessageEncoding(347).
+pub fn is_encoded_text(tag: u32) -> bool {
+    matches!(
+        tag,
+        349 | 351 | 353 | 355 | 357 | 361 | 363 | 365 | 446 | 619 | 622
+    )
+}
+
+/// Decodes an Encoded* value using the MessageEncoding(347) label
+/// (`Shift_JIS`, `EUC-JP`, `ISO-2022-JP`, `UTF-8`, ...). Without a label the
+/// bytes are taken as UTF-8. Returns `None` for unknown labels or bytes that
+/// are invalid in the declared charset.
+pub fn decode_text(encoding: Option<&str>, bytes: &[u8]) -> Option<String> {
+    let encoding = match encoding {
+        Some(label) => encoding_rs::Encoding::for_label(label.trim().as_bytes())?,
+        None => encoding_rs::UTF_8,
+    };
+    let (text, malformed) = encoding.decode_without_bom_handling(bytes);
+    (!malformed).then(|| text.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn field_strategy() -> impl Strategy<Value = Field> {
+        (1u32..=99_999, "[^\x01|]{0,24}").prop_map(|(tag, value)| Field {
+            tag,
+            value: value.into_bytes(),
+        })
+    }
+
+    fn message_strategy() -> impl Strategy<Value = FixMessage> {
+        (
+            prop_oneof![Just(SOH), Just(b'|')],
+            prop::collection::vec(field_strategy(), 1..40),
+        )
+            .prop_map(|(delimiter, fields)| FixMessage { delimiter, fields })
+    }
+
+    fn encode(msg: &FixMessage) -> Vec<u8> {
+        msg.encode_with(msg.delimiter)
+    }
+
+    /// [`checksum`] one byte at a time.
+    fn scalar_checksum(bytes: &[u8], delimiter: u8) -> u8 {
+        bytes
+            .iter()
+            .map(|&b| if b == delimiter { SOH } else { b })
+            .fold(0u8, |sum, b| sum.wrapping_add(b))
+    }
+
+    proptest! {
+        #[test]
+        fn encode_then_parse_is_identity(msg in message_strategy()) {
+            let wire = encode(&msg);
+            prop_assert_eq!(FixMessage::parse(&wire), Ok(msg));
+        }
+
+        #[test]
+        fn parse_then_encode_is_byte_identical(msg in message_strategy()) {
+            let wire = encode(&msg);
+            let reparsed = FixMessage::parse(&wire).unwrap();
+            prop_assert_eq!(encode(&reparsed), wire);
+        }
+
+        #[test]
+        fn accepted_input_round_trips(raw in "[0-9=|a-zA-Z.]{1,80}") {
+            if let Ok(msg) = FixMessage::parse(raw.as_bytes()) {
+                prop_assert_eq!(encode(&msg), raw.into_bytes());
+            }
+        }
+
+        #[test]
+        fn encoded_text_with_delimiters_round_trips(text in prop::collection::vec(any::<u8>(), 0..64)) {
+            let mut wire = format!("8=FIX.4.4\x0135=B\x01354={}\x01355=", text.len()).into_bytes();
+            wire.extend_from_slice(&text);
+            wire.extend_from_slice(b"\x0110=000\x01");
+            let msg = FixMessage::parse(&wire).unwrap();
+            prop_assert_eq!(&msg.fields[3].value, &text);
+            prop_assert_eq!(encode(&msg), wire);
+        }
+
+        #[test]
+        fn checksum_matches_the_scalar_sum(
+            bytes in prop::collection::vec(any::<u8>(), 0..600),
+            delimiter in prop_oneof![Just(SOH), Just(b'|'), any::<u8>()],
+        ) {
+            prop_assert_eq!(checksum(&bytes, delimiter), scalar_checksum(&bytes, delimiter));
+        }
+    }
+
+    #[test]
+    fn keeps_unknown_tags_and_order() {
+        let raw = b"8=FIX.4.4|9=5|35=D|9999=x|55=A=B|10=000|";
+        let msg = FixMessage::parse(raw).unwrap();
+        assert_eq!(msg.fields[3].tag, 9999);
+        assert_eq!(msg.fields[4].value, b"A=B");
+        assert_eq!(encode(&msg), raw);
+    }
+
+    #[test]
+    fn rejects_non_canonical_input() {
+        assert_eq!(FixMessage::parse(b"035=D|"), Err(ParseError::InvalidTag(0)));
+        assert_eq!(
+            FixMessage::parse(b"35=D|x|"),
+            Err(ParseError::MissingEquals(5))
+        );
+        assert_eq!(FixMessage::parse(b"35=D"), Err(ParseError::Unterminated));
+        assert_eq!(FixMessage::parse(b""), Err(ParseError::Empty));
+    }
+
+    #[test]
+    fn framing_sets_body_length_and_checksum() {
+        let msg = FixMessage::framed(
+            "FIX.4.4",
+            vec![
+                Field {
+                    tag: 35,
+                    value: b"0".to_vec(),
+                },
+                Field {
+                    tag: 34,
+                    value: b"1".to_vec(),
+                },
+            ],
+        );
+        assert_eq!(
+            encode(&msg),
+            b"8=FIX.4.4\x019=10\x0135=0\x0134=1\x0110=165\x01"
+        );
+    }
+
+    #[test]
+    fn decodes_shift_jis_text() {
+        // "東京" in Shift-JIS
+        let sjis = [0x93, 0x8c, 0x8b, 0x9e];
+        assert_eq!(
+            decode_text(Some("Shift_JIS"), &sjis).as_deref(),
+            Some("東京")
+        );
+        assert_eq!(decode_text(None, &sjis), None);
+    }
+}
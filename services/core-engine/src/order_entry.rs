@@ -0,0 +1,662 @@
+//! Typed order entry: `/api/v2/orders`.
+//!
+//! `POST /api/v2/orders` places an order from typed parameters — side, order
+//! type and time in force by name, quantities and prices as JSON numbers —
+//! and sends it as a NewOrderSingle (35=D) on the named session, so callers
+//! never handle tag numbers or code values.
+//! `POST /api/v2/orders/{cl_ord_id}/cancel` and `/replace` send an
+//! OrderCancelRequest (35=F) or OrderCancelReplaceRequest (35=G) for a
+//! tracked order, repeating the order's Symbol, Side and OrderQty as FIX
+//! requires. Each goes through the same path as `/send`: it is queued if the
+//! session is not logged on, and then answered with `202 Accepted`.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::{
+    attribution::Attribution,
+    auth::Caller,
+    clock::{epoch_ms, format_utc_timestamp},
+    find_session,
+    orders::OrderContext,
+    problem::ApiError,
+    submit, AppState, FixSession, OutputEncoding, SendOptions, SendRequest, Submitted,
+};
+
+// ── Parameters ────────────────────────────────────────────────────────────────
+
+/// Side (54).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Side {
+    Buy,
+    Sell,
+    BuyMinus,
+    SellPlus,
+    SellShort,
+    SellShortExempt,
+}
+
+impl Side {
+    const ALL: [Self; 6] = [
+        Self::Buy,
+        Self::Sell,
+        Self::BuyMinus,
+        Self::SellPlus,
+        Self::SellShort,
+        Self::SellShortExempt,
+    ];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Buy => "1",
+            Self::Sell => "2",
+            Self::BuyMinus => "3",
+            Self::SellPlus => "4",
+            Self::SellShort => "5",
+            Self::SellShortExempt => "6",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.code() == code)
+    }
+}
+
+/// OrdType (40).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrdType {
+    Market,
+    Limit,
+    Stop,
+    StopLimit,
+}
+
+impl OrdType {
+    const ALL: [Self; 4] = [Self::Market, Self::Limit, Self::Stop, Self::StopLimit];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Market => "1",
+            Self::Limit => "2",
+            Self::Stop => "3",
+            Self::StopLimit => "4",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.code() == code)
+    }
+
+    pub fn has_price(self) -> bool {
+        matches!(self, Self::Limit | Self::StopLimit)
+    }
+
+    fn has_stop_px(self) -> bool {
+        matches!(self, Self::Stop | Self::StopLimit)
+    }
+}
+
+/// TimeInForce (59).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TimeInForce {
+    #[default]
+    Day,
+    #[serde(alias = "GTC")]
+    GoodTillCancel,
+    #[serde(alias = "OPG")]
+    AtTheOpening,
+    #[serde(alias = "IOC")]
+    ImmediateOrCancel,
+    #[serde(alias = "FOK")]
+    FillOrKill,
+    #[serde(alias = "GTX")]
+    GoodTillCrossing,
+    /// Until `expire_at_ms`.
+    #[serde(alias = "GTD")]
+    GoodTillDate,
+}
+
+impl TimeInForce {
+    const ALL: [Self; 7] = [
+        Self::Day,
+        Self::GoodTillCancel,
+        Self::AtTheOpening,
+        Self::ImmediateOrCancel,
+        Self::FillOrKill,
+        Self::GoodTillCrossing,
+        Self::GoodTillDate,
+    ];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Day => "0",
+            Self::GoodTillCancel => "1",
+            Self::AtTheOpening => "2",
+            Self::ImmediateOrCancel => "3",
+            Self::FillOrKill => "4",
+            Self::GoodTillCrossing => "5",
+            Self::GoodTillDate => "6",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.code() == code)
+    }
+}
+
+/// The parameters of an order, as placed or as amended by a replace.
+#[derive(Debug, Clone, PartialEq)]
+struct OrderParams {
+    symbol: String,
+    side: Side,
+    qty: f64,
+    ord_type: OrdType,
+    price: Option<f64>,
+    stop_px: Option<f64>,
+    time_in_force: TimeInForce,
+    expire_at_ms: Option<u64>,
+    account: Option<String>,
+}
+
+impl OrderParams {
+    /// The order's fields by name, or why they would not make a valid order.
+    fn fields(&self) -> Result<HashMap<String, String>, &'static str> {
+        if self.symbol.trim().is_empty() {
+            return Err("symbol is empty");
+        }
+        if !(self.qty.is_finite() && self.qty > 0.0) {
+            return Err("qty must be positive");
+        }
+        if self.ord_type.has_price() != self.price.is_some() {
+            return Err("price is required for LIMIT and STOP_LIMIT orders, and only for those");
+        }
+        if self.ord_type.has_stop_px() != self.stop_px.is_some() {
+            return Err("stop_px is required for STOP and STOP_LIMIT orders, and only for those");
+        }
+        if [self.price, self.stop_px].into_iter().flatten().any(|p| !p.is_finite()) {
+            return Err("prices must be finite");
+        }
+        if (self.time_in_force == TimeInForce::GoodTillDate) != self.expire_at_ms.is_some() {
+            return Err("expire_at_ms is required for GOOD_TILL_DATE orders, and only for those");
+        }
+
+        let mut fields = HashMap::from([
+            ("Symbol".to_string(), self.symbol.clone()),
+            ("Side".to_string(), self.side.code().to_string()),
+            ("OrderQty".to_string(), self.qty.to_string()),
+            ("OrdType".to_string(), self.ord_type.code().to_string()),
+            ("TimeInForce".to_string(), self.time_in_force.code().to_string()),
+        ]);
+        for (name, value) in [
+            ("Price", self.price.map(|p| p.to_string())),
+            ("StopPx", self.stop_px.map(|p| p.to_string())),
+            ("ExpireTime", self.expire_at_ms.map(format_utc_timestamp)),
+            ("Account", self.account.clone()),
+        ] {
+            if let Some(value) = value {
+                fields.insert(name.to_string(), value);
+            }
+        }
+        Ok(fields)
+    }
+
+    /// The parameters of an order the gateway sent. StopPx and ExpireTime
+    /// are not tracked, so they are left for the caller to restate.
+    fn of(order: &OrderContext) -> Option<Self> {
+        let number = |v: &Option<String>| v.as_deref().and_then(|v| v.trim().parse::<f64>().ok());
+        Some(Self {
+            symbol: order.symbol.clone()?,
+            side: Side::from_code(order.side.as_deref()?)?,
+            qty: number(&order.order_qty)?,
+            ord_type: OrdType::from_code(order.ord_type.as_deref().unwrap_or("1"))?,
+            price: number(&order.price),
+            stop_px: None,
+            time_in_force: TimeInForce::from_code(order.time_in_force.as_deref().unwrap_or("0"))?,
+            expire_at_ms: None,
+            account: order.account.clone(),
+        })
+    }
+}
+
+// ── Requests ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PlaceOrder {
+    session_id: String,
+    /// Generated if omitted.
+    cl_ord_id: Option<String>,
+    symbol: String,
+    side: Side,
+    qty: f64,
+    ord_type: OrdType,
+    /// Required for `LIMIT` and `STOP_LIMIT` orders.
+    price: Option<f64>,
+    /// Required for `STOP` and `STOP_LIMIT` orders.
+    stop_px: Option<f64>,
+    #[serde(default)]
+    time_in_force: TimeInForce,
+    /// Required for `GOOD_TILL_DATE` orders.
+    expire_at_ms: Option<u64>,
+    account: Option<String>,
+    /// Opaque client metadata, as on `/send`.
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+    /// Cancel the order if it is not done within this many milliseconds.
+    ttl_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct CancelOrder {
+    /// The cancel request's ClOrdID; generated if omitted.
+    cl_ord_id: Option<String>,
+}
+
+/// The changes to make to an order; parameters left out keep their values.
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct ReplaceOrder {
+    /// The replacement's ClOrdID; generated if omitted.
+    cl_ord_id: Option<String>,
+    qty: Option<f64>,
+    ord_type: Option<OrdType>,
+    price: Option<f64>,
+    /// Restated on every replace of a `STOP` or `STOP_LIMIT` order.
+    stop_px: Option<f64>,
+    time_in_force: Option<TimeInForce>,
+    /// Restated on every replace of a `GOOD_TILL_DATE` order.
+    expire_at_ms: Option<u64>,
+}
+
+impl ReplaceOrder {
+    fn apply(&self, mut params: OrderParams) -> OrderParams {
+        if let Some(qty) = self.qty {
+            params.qty = qty;
+        }
+        if let Some(ord_type) = self.ord_type {
+            params.ord_type = ord_type;
+            if !ord_type.has_price() {
+                params.price = None;
+            }
+        }
+        if let Some(time_in_force) = self.time_in_force {
+            params.time_in_force = time_in_force;
+        }
+        params.price = self.price.or(params.price);
+        params.stop_px = self.stop_px;
+        params.expire_at_ms = self.expire_at_ms;
+        params
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OrderResponse {
+    /// The ClOrdID of the message sent: the order's, the cancel request's
+    /// or the replacement's.
+    cl_ord_id: String,
+    session_id: String,
+    /// The order as tracked after sending; absent while queued.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    order: Option<OrderContext>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sequence_number: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queue_id: Option<String>,
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+/// Places an order, sending a NewOrderSingle.
+#[utoipa::path(
+    post,
+    path = "/api/v2/orders",
+    tag = "orders",
+    request_body = PlaceOrder,
+    responses(
+        (status = 200, description = "Sent", body = OrderResponse),
+        (
+            status = 202,
+            description = "Queued until the session is logged on",
+            body = OrderResponse
+        ),
+        (status = 404, description = "Unknown session"),
+        (status = 409, description = "The ClOrdID is already in use"),
+        (status = 422, description = "Inconsistent order parameters"),
+        (status = 429, description = "The client is over its rate limit; see Retry-After"),
+    )
+)]
+pub async fn place(
+    State(state): State<AppState>,
+    caller: Caller,
+    attribution: Attribution,
+    Json(req): Json<PlaceOrder>,
+) -> Result<Response, ApiError> {
+    caller.check_session(&req.session_id)?;
+    let session = find_session(&state, &req.session_id).ok_or_else(|| {
+        ApiError::new(StatusCode::NOT_FOUND, "unknown_session")
+            .detail(format!("no session {}", req.session_id))
+    })?;
+    let params = OrderParams {
+        symbol: req.symbol,
+        side: req.side,
+        qty: req.qty,
+        ord_type: req.ord_type,
+        price: req.price,
+        stop_px: req.stop_px,
+        time_in_force: req.time_in_force,
+        expire_at_ms: req.expire_at_ms,
+        account: req.account,
+    };
+    let mut fields = params.fields().map_err(|reason| {
+        info!(session_id = %session.session_id, reason, "Order rejected");
+        ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_order").detail(reason)
+    })?;
+    let cl_ord_id = req
+        .cl_ord_id
+        .unwrap_or_else(|| format!("ORD-{}", uuid::Uuid::new_v4().simple()));
+    if tracked(&state, &cl_ord_id).await.is_some() {
+        return Err(ApiError::new(StatusCode::CONFLICT, "duplicate_cl_ord_id")
+            .detail(format!("ClOrdID {cl_ord_id} is already in use")));
+    }
+    fields.insert("ClOrdID".to_string(), cl_ord_id.clone());
+    let attribution = attribution.with_metadata(&req.metadata);
+    transmit(&state, &session, "NewOrderSingle", fields, attribution, req.ttl_ms, cl_ord_id).await
+}
+
+/// Cancels an order, sending an OrderCancelRequest.
+#[utoipa::path(
+    post,
+    path = "/api/v2/orders/{cl_ord_id}/cancel",
+    tag = "orders",
+    params(("cl_ord_id" = String, Path, description = "The order's ClOrdID.")),
+    request_body = CancelOrder,
+    responses(
+        (status = 200, description = "Sent", body = OrderResponse),
+        (
+            status = 202,
+            description = "Queued until the session is logged on",
+            body = OrderResponse
+        ),
+        (status = 404, description = "Unknown order, or its session is gone"),
+        (status = 409, description = "The order is already complete"),
+        (status = 429, description = "The client is over its rate limit; see Retry-After"),
+    )
+)]
+pub async fn cancel(
+    State(state): State<AppState>,
+    Path(cl_ord_id): Path<String>,
+    caller: Caller,
+    attribution: Attribution,
+    Json(req): Json<CancelOrder>,
+) -> Result<Response, ApiError> {
+    let (order, session) = open_order(&state, &cl_ord_id).await?;
+    caller.check_session(&session.session_id)?;
+    let mut fields = HashMap::from([
+        ("OrigClOrdID".to_string(), order.cl_ord_id.clone()),
+        ("Symbol".to_string(), order.symbol.clone().unwrap_or_default()),
+        ("Side".to_string(), order.side.clone().unwrap_or_default()),
+    ]);
+    for (name, value) in [("OrderQty", &order.order_qty), ("Account", &order.account)] {
+        if let Some(value) = value {
+            fields.insert(name.to_string(), value.clone());
+        }
+    }
+    let cancel_id = req
+        .cl_ord_id
+        .unwrap_or_else(|| format!("CXL-{}", uuid::Uuid::new_v4().simple()));
+    fields.insert("ClOrdID".to_string(), cancel_id.clone());
+    let attribution = attribution.with_metadata(&order.metadata);
+    transmit(&state, &session, "OrderCancelRequest", fields, attribution, None, cancel_id).await
+}
+
+/// Amends an order, sending an OrderCancelReplaceRequest.
+#[utoipa::path(
+    post,
+    path = "/api/v2/orders/{cl_ord_id}/replace",
+    tag = "orders",
+    params(("cl_ord_id" = String, Path, description = "The order's ClOrdID.")),
+    request_body = ReplaceOrder,
+    responses(
+        (status = 200, description = "Sent", body = OrderResponse),
+        (
+            status = 202,
+            description = "Queued until the session is logged on",
+            body = OrderResponse
+        ),
+        (status = 404, description = "Unknown order, or its session is gone"),
+        (status = 409, description = "The order is already complete"),
+        (status = 422, description = "Inconsistent order parameters"),
+        (status = 429, description = "The client is over its rate limit; see Retry-After"),
+    )
+)]
+pub async fn replace(
+    State(state): State<AppState>,
+    Path(cl_ord_id): Path<String>,
+    caller: Caller,
+    attribution: Attribution,
+    Json(req): Json<ReplaceOrder>,
+) -> Result<Response, ApiError> {
+    let (order, session) = open_order(&state, &cl_ord_id).await?;
+    caller.check_session(&session.session_id)?;
+    let params = OrderParams::of(&order).ok_or_else(|| {
+        ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_order")
+            .detail("the order's parameters cannot be represented in a replace")
+    })?;
+    let mut fields = req.apply(params).fields().map_err(|reason| {
+        info!(
+            session_id = %session.session_id,
+            cl_ord_id = %order.cl_ord_id,
+            reason,
+            "Replace rejected"
+        );
+        ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_order").detail(reason)
+    })?;
+    let replace_id = req
+        .cl_ord_id
+        .unwrap_or_else(|| format!("RPL-{}", uuid::Uuid::new_v4().simple()));
+    fields.insert("ClOrdID".to_string(), replace_id.clone());
+    fields.insert("OrigClOrdID".to_string(), order.cl_ord_id.clone());
+    let attribution = attribution.with_metadata(&order.metadata);
+    transmit(&state, &session, "OrderCancelReplaceRequest", fields, attribution, None, replace_id)
+        .await
+}
+
+async fn tracked(state: &AppState, cl_ord_id: &str) -> Option<OrderContext> {
+    state.orders.lock().await.get(cl_ord_id).cloned()
+}
+
+/// A tracked order that is not yet complete, with its session.
+async fn open_order(
+    state: &AppState,
+    cl_ord_id: &str,
+) -> Result<(OrderContext, FixSession), ApiError> {
+    let order = tracked(state, cl_ord_id).await.ok_or_else(|| {
+        ApiError::new(StatusCode::NOT_FOUND, "unknown_order")
+            .detail(format!("no order with ClOrdID {cl_ord_id}"))
+    })?;
+    if order.state.is_terminal() {
+        return Err(ApiError::new(StatusCode::CONFLICT, "order_completed")
+            .detail(format!("order {cl_ord_id} is already complete")));
+    }
+    let session = find_session(state, &order.session_id).ok_or(StatusCode::NOT_FOUND)?;
+    Ok((order, session))
+}
+
+/// Sends a message on `session` as `/send` would, with the order metadata in
+/// `attribution`, and answers with the order it concerns.
+async fn transmit(
+    state: &AppState,
+    session: &FixSession,
+    msg_type: &str,
+    mut fields: HashMap<String, String>,
+    attribution: Attribution,
+    ttl_ms: Option<u64>,
+    cl_ord_id: String,
+) -> Result<Response, ApiError> {
+    for (name, value) in [
+        ("BeginString", session.fix_version.clone()),
+        ("SenderCompID", session.sender_comp_id.clone()),
+        ("TargetCompID", session.target_comp_id.clone()),
+        ("TransactTime", format_utc_timestamp(epoch_ms())),
+    ] {
+        fields.insert(name.to_string(), value);
+    }
+    let send = SendRequest {
+        msg_type: msg_type.to_string(),
+        fields,
+        groups: Default::default(),
+        metadata: attribution.metadata.clone().into_iter().collect(),
+        reply_to: None,
+        ttl_ms,
+        template: None,
+        attribution: Some(attribution),
+    };
+    let opts = SendOptions {
+        encoding: OutputEncoding::default(),
+        validate: false,
+        strict: None,
+    };
+    let (status, sent, queue_id) = match submit(state, &opts, send).await? {
+        Submitted::Sent(sent) => (StatusCode::OK, Some(*sent), None),
+        Submitted::Queued(queued) => (StatusCode::ACCEPTED, None, Some(queued.queue_id)),
+    };
+    info!(
+        session_id = %session.session_id,
+        cl_ord_id = %cl_ord_id,
+        msg_type,
+        queued = queue_id.is_some(),
+        "Order request sent"
+    );
+    let order = match sent {
+        Some(_) => tracked(state, &cl_ord_id).await,
+        None => None,
+    };
+    let response = OrderResponse {
+        cl_ord_id,
+        session_id: session.session_id.clone(),
+        order,
+        fix_message: sent.as_ref().map(|s| s.fix_message.clone()),
+        sequence_number: sent.as_ref().map(|s| s.sequence_number),
+        queue_id,
+    };
+    Ok((status, Json(response)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit_buy() -> OrderParams {
+        OrderParams {
+            symbol: "AAPL".to_string(),
+            side: Side::Buy,
+            qty: 100.0,
+            ord_type: OrdType::Limit,
+            price: Some(150.25),
+            stop_px: None,
+            time_in_force: TimeInForce::Day,
+            expire_at_ms: None,
+            account: None,
+        }
+    }
+
+    #[test]
+    fn parameters_become_fix_fields() {
+        let fields = limit_buy().fields().unwrap();
+        for (name, value) in [
+            ("Symbol", "AAPL"),
+            ("Side", "1"),
+            ("OrderQty", "100"),
+            ("OrdType", "2"),
+            ("Price", "150.25"),
+            ("TimeInForce", "0"),
+        ] {
+            assert_eq!(fields.get(name).map(String::as_str), Some(value), "{name}");
+        }
+        assert!(!fields.contains_key("StopPx"));
+    }
+
+    #[test]
+    fn inconsistent_parameters_are_rejected() {
+        let market_with_price = OrderParams {
+            ord_type: OrdType::Market,
+            ..limit_buy()
+        };
+        assert!(market_with_price.fields().is_err());
+        let stop_without_stop_px = OrderParams {
+            ord_type: OrdType::StopLimit,
+            ..limit_buy()
+        };
+        assert!(stop_without_stop_px.fields().is_err());
+        let gtd_without_expiry = OrderParams {
+            time_in_force: TimeInForce::GoodTillDate,
+            ..limit_buy()
+        };
+        assert!(gtd_without_expiry.fields().is_err());
+        assert!(OrderParams { qty: 0.0, ..limit_buy() }.fields().is_err());
+    }
+
+    #[test]
+    fn names_and_aliases_deserialize() {
+        let req: PlaceOrder = serde_json::from_value(serde_json::json!({
+            "session_id": "ALICE->BROKER_A",
+            "symbol": "AAPL",
+            "side": "SELL_SHORT",
+            "qty": 10,
+            "ord_type": "MARKET",
+            "time_in_force": "IOC"
+        }))
+        .unwrap();
+        assert_eq!(req.side.code(), "5");
+        assert_eq!(req.time_in_force, TimeInForce::ImmediateOrCancel);
+        let bad_side = serde_json::json!({
+            "session_id": "ALICE->BROKER_A",
+            "symbol": "AAPL",
+            "side": "1",
+            "qty": 10,
+            "ord_type": "MARKET"
+        });
+        assert!(serde_json::from_value::<PlaceOrder>(bad_side).is_err());
+    }
+
+    #[test]
+    fn replaces_amend_the_order_as_sent() {
+        let no_metadata = HashMap::new();
+        let fields = HashMap::from([
+            ("Symbol".to_string(), "AAPL".to_string()),
+            ("Side".to_string(), "2".to_string()),
+            ("OrderQty".to_string(), "100".to_string()),
+            ("OrdType".to_string(), "2".to_string()),
+            ("Price".to_string(), "150".to_string()),
+        ]);
+        let order =
+            OrderContext::from_fields("O1", "ALICE->BROKER_A", &fields, &no_metadata, 0);
+        let params = OrderParams::of(&order).unwrap();
+        assert_eq!(params.side, Side::Sell);
+
+        let amend = ReplaceOrder {
+            qty: Some(50.0),
+            ..ReplaceOrder::default()
+        };
+        let amended = amend.apply(params.clone()).fields().unwrap();
+        assert_eq!(amended["OrderQty"], "50");
+        assert_eq!(amended["Price"], "150");
+
+        let to_market = ReplaceOrder {
+            ord_type: Some(OrdType::Market),
+            ..ReplaceOrder::default()
+        };
+        let market = to_market.apply(params).fields().unwrap();
+        assert!(!market.contains_key("Price"));
+    }
+}
@@ -0,0 +1,2110 @@
+//! Locally known order context, keyed by ClOrdID.
+//!
+//! Every order sent through the engine is recorded with its original
+//! parameters so inbound ExecutionReports can be enriched without the
+//! consumer having to look the order up again.
+//!
+//! Each order also carries its lifecycle [`OrderState`], moved by the
+//! requests sent for it (NewOrderSingle, OrderCancelRequest,
+//! OrderCancelReplaceRequest) and the ExecutionReports received, so API
+//! callers read the state instead of re-deriving it from raw messages. A
+//! cancel/replace is tracked as a new order under its own ClOrdID; the order
+//! it replaces ends in `REPLACED`.
+//!
+//! ExecutionReports also keep each order's CumQty, LeavesQty and AvgPx, and
+//! are classified by how they [correlate](Correlation) with what was sent.
+//! Each fill is kept too, so a trade bust (ExecType `H`) or correction (`G`)
+//! naming it by ExecRefID (19) can take it back out of the totals.
+//!
+//! The same state answers OrderStatusRequests (35=H) from counterparties of
+//! acceptor sessions, as ExecutionReports with ExecType `I`.
+//!
+//! Orders sent together in a NewOrderList (35=E) are also tracked as an
+//! [`OrderList`] under their ListID, whose state follows the ListStatus (35=N)
+//! messages received for it.
+//!
+//! An OrderCancelReject (35=9) returns the order from `PENDING_CANCEL` or
+//! `PENDING_REPLACE` to the state it reports, and a rejected replacement
+//! ends in `REJECTED`.
+//!
+//! A session configured with [`CancelOnDisconnect`] gets cancels for its
+//! open orders when it drops without logging out; they are queued and sent
+//! once it is logged on again.
+//!
+//! An order is kept for `FIX_ORDER_RETENTION_SECS` (default one day) after
+//! its last change once it is in a terminal state, then dropped with the
+//! ClOrdIDs aliased to it; open orders are kept however old they are.
+
+use async_graphql::{Enum, SimpleObject};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
+use tracing::{info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{lookup_field, AppState};
+
+/// How long a terminal order is kept after its last change, by default.
+const DEFAULT_RETENTION_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// How often [`OrderStore::record`] looks for orders past their retention.
+const PRUNE_INTERVAL_MS: u64 = 60 * 1000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema, Enum)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderState {
+    #[default]
+    PendingNew,
+    New,
+    PartiallyFilled,
+    Filled,
+    PendingCancel,
+    PendingReplace,
+    Canceled,
+    Replaced,
+    Rejected,
+    Expired,
+    DoneForDay,
+    Suspended,
+}
+
+impl OrderState {
+    /// The state an OrdStatus (39) value reports.
+    pub fn from_ord_status(value: &str) -> Option<Self> {
+        Some(match value {
+            "A" => Self::PendingNew,
+            "0" => Self::New,
+            "1" => Self::PartiallyFilled,
+            "2" => Self::Filled,
+            "6" => Self::PendingCancel,
+            "E" => Self::PendingReplace,
+            "4" => Self::Canceled,
+            "5" => Self::Replaced,
+            "8" => Self::Rejected,
+            "C" => Self::Expired,
+            "3" => Self::DoneForDay,
+            "9" => Self::Suspended,
+            _ => return None,
+        })
+    }
+
+    /// The OrdStatus (39) value that reports this state.
+    pub fn ord_status(self) -> &'static str {
+        match self {
+            Self::PendingNew => "A",
+            Self::New => "0",
+            Self::PartiallyFilled => "1",
+            Self::Filled => "2",
+            Self::PendingCancel => "6",
+            Self::PendingReplace => "E",
+            Self::Canceled => "4",
+            Self::Replaced => "5",
+            Self::Rejected => "8",
+            Self::Expired => "C",
+            Self::DoneForDay => "3",
+            Self::Suspended => "9",
+        }
+    }
+
+    /// The state an ExecType (150) reports, for ExecutionReports without an
+    /// OrdStatus. Trades (`F`) and other events need the OrdStatus.
+    fn from_exec_type(value: &str) -> Option<Self> {
+        match value {
+            // FIX 4.2 Partial fill and Fill.
+            "1" => Some(Self::PartiallyFilled),
+            "2" => Some(Self::Filled),
+            "F" | "G" | "H" | "I" | "D" => None,
+            _ => Self::from_ord_status(value),
+        }
+    }
+
+    /// Waiting for the venue to answer a cancel or cancel/replace.
+    fn is_awaiting_cancel(self) -> bool {
+        matches!(self, Self::PendingCancel | Self::PendingReplace)
+    }
+
+    /// No further ExecutionReport moves an order out of a terminal state.
+    pub fn is_terminal(self) -> bool {
+        matches!(
+            self,
+            Self::Filled | Self::Canceled | Self::Replaced | Self::Rejected | Self::Expired
+        )
+    }
+}
+
+/// An entry in an order's state history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema, SimpleObject)]
+pub struct StateChange {
+    pub state: OrderState,
+    pub at_ms: u64,
+    /// The message that caused it, e.g. `ExecutionReport 150=F 39=1`.
+    pub trigger: String,
+}
+
+/// The fields of an inbound ExecutionReport (35=8) that drive order state.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExecutionReport<'a> {
+    pub exec_id: Option<&'a str>,
+    /// ExecRefID (19): the fill a bust or correction applies to.
+    pub exec_ref_id: Option<&'a str>,
+    pub cl_ord_id: Option<&'a str>,
+    pub orig_cl_ord_id: Option<&'a str>,
+    pub exec_type: Option<&'a str>,
+    pub ord_status: Option<&'a str>,
+    pub last_qty: Option<&'a str>,
+    pub last_px: Option<&'a str>,
+    pub cum_qty: Option<&'a str>,
+    pub leaves_qty: Option<&'a str>,
+    pub avg_px: Option<&'a str>,
+}
+
+/// How an ExecutionReport relates to the orders the gateway sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, Enum)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Correlation {
+    Matched,
+    /// For a known order, but not prompted by a request from the gateway: a
+    /// restatement, or a cancel or replace the venue made on its own.
+    Unsolicited,
+    /// No order sent through the gateway has its ClOrdID or OrigClOrdID.
+    UnknownOrder,
+}
+
+/// The order an ExecutionReport or OrderCancelReject is about, as stored and
+/// streamed with the message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportContext {
+    pub correlation: Correlation,
+    /// The order after the report was applied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<OrderContext>,
+}
+
+/// The fields of an inbound OrderCancelReject (35=9).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CancelReject<'a> {
+    /// The rejected request's ClOrdID.
+    pub cl_ord_id: Option<&'a str>,
+    pub orig_cl_ord_id: Option<&'a str>,
+    /// The order's status after the rejection.
+    pub ord_status: Option<&'a str>,
+    /// CxlRejResponseTo (434): `1` for a cancel, `2` for a cancel/replace.
+    pub response_to: Option<&'a str>,
+}
+
+impl CancelReject<'_> {
+    fn trigger(&self) -> String {
+        let mut trigger = "OrderCancelReject".to_string();
+        for (tag, value) in [(434, self.response_to), (39, self.ord_status)] {
+            if let Some(value) = value {
+                trigger.push_str(&format!(" {tag}={value}"));
+            }
+        }
+        trigger
+    }
+}
+
+/// The fields of an inbound OrderStatusRequest (35=H).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StatusRequest<'a> {
+    pub cl_ord_id: Option<&'a str>,
+    pub order_id: Option<&'a str>,
+    /// OrdStatusReqID (790), echoed on the report.
+    pub ord_status_req_id: Option<&'a str>,
+    pub symbol: Option<&'a str>,
+    pub side: Option<&'a str>,
+}
+
+/// Which orders an OrderMassCancelRequest (35=q) covers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MassCancelScope {
+    /// MassCancelRequestType (530): `1` for one Symbol, `7` for all orders.
+    pub request_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub side: Option<String>,
+}
+
+impl MassCancelScope {
+    pub fn from_fields(fields: &HashMap<String, String>) -> Self {
+        let get = |name: &str, tag: u32| lookup_field(fields, name, tag).cloned();
+        Self {
+            request_type: get("MassCancelRequestType", 530).unwrap_or_default(),
+            symbol: get("Symbol", 55),
+            side: get("Side", 54),
+        }
+    }
+
+    /// Whether the scope covers `order`. Scopes other than by security and
+    /// all orders (underlying, product, ...) need reference data the gateway
+    /// does not have, so they cover no locally known order.
+    fn covers(&self, order: &OrderContext) -> bool {
+        let by_type = match self.request_type.as_str() {
+            "1" => self.symbol.is_some() && order.symbol == self.symbol,
+            "7" => true,
+            _ => false,
+        };
+        by_type
+            && self
+                .side
+                .as_ref()
+                .is_none_or(|side| order.side.as_ref() == Some(side))
+    }
+}
+
+/// An OrderMassCancelRequest the gateway sent, with the orders it covered.
+#[derive(Debug, Clone)]
+struct MassCancel {
+    orders: Vec<String>,
+    sent_at_ms: u64,
+}
+
+/// What a session's open orders get when it drops without logging out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CancelOnDisconnect {
+    /// One OrderCancelRequest per open order.
+    Orders,
+    /// One OrderMassCancelRequest for all of the session's orders.
+    Mass,
+}
+
+impl CancelOnDisconnect {
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "orders" => Some(Self::Orders),
+            "mass" => Some(Self::Mass),
+            _ => None,
+        }
+    }
+}
+
+/// Reads `FIX_CANCEL_ON_DISCONNECT`, e.g. `ALICE->BROKER_A=orders,ALICE->BROKER_B=mass`.
+pub fn cancel_on_disconnect_from_env() -> HashMap<String, CancelOnDisconnect> {
+    let mut modes = HashMap::new();
+    let Ok(spec) = std::env::var("FIX_CANCEL_ON_DISCONNECT") else {
+        return modes;
+    };
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        match entry
+            .rsplit_once('=')
+            .and_then(|(id, m)| Some((id, CancelOnDisconnect::parse(m)?)))
+        {
+            Some((id, mode)) => {
+                modes.insert(id.to_string(), mode);
+            }
+            None => warn!(entry, "Ignoring malformed FIX_CANCEL_ON_DISCONNECT entry"),
+        }
+    }
+    modes
+}
+
+/// The fields of an inbound OrderMassCancelReport (35=r).
+#[derive(Debug, Clone, Default)]
+pub struct MassCancelReport<'a> {
+    /// The request's ClOrdID.
+    pub cl_ord_id: Option<&'a str>,
+    /// MassCancelResponse (531): `0` if the request was rejected.
+    pub response: Option<&'a str>,
+    /// OrigClOrdIDs (41) of the NoAffectedOrders (534) group.
+    pub affected: Vec<&'a str>,
+}
+
+impl MassCancelReport<'_> {
+    fn trigger(&self) -> String {
+        match self.response {
+            Some(response) => format!("OrderMassCancelReport 531={response}"),
+            None => "OrderMassCancelReport".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MassCancelOutcome {
+    /// Whether the report answers a request the gateway sent.
+    pub known: bool,
+    /// The orders the request covered or the report lists, as they now stand.
+    pub orders: Vec<OrderContext>,
+    pub changed: Vec<OrderContext>,
+}
+
+/// ListOrderStatus (431): where the venue is with a list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[schema(as = OrderListState)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ListState {
+    /// Sent, with no ListStatus received yet.
+    #[default]
+    Submitted,
+    InBiddingProcess,
+    ReceivedForExecution,
+    Executing,
+    Cancelling,
+    Alert,
+    AllDone,
+    Rejected,
+}
+
+impl ListState {
+    fn from_list_order_status(value: &str) -> Option<Self> {
+        Some(match value {
+            "1" => Self::InBiddingProcess,
+            "2" => Self::ReceivedForExecution,
+            "3" => Self::Executing,
+            "4" => Self::Cancelling,
+            "5" => Self::Alert,
+            "6" => Self::AllDone,
+            "7" => Self::Rejected,
+            _ => return None,
+        })
+    }
+}
+
+/// A NewOrderList the gateway sent.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OrderList {
+    pub list_id: String,
+    pub session_id: String,
+    /// The list's orders, in the order sent.
+    pub cl_ord_ids: Vec<String>,
+    pub state: ListState,
+    /// ListStatusType (429) of the last ListStatus received.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub list_status_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cancel_requested_at_ms: Option<u64>,
+    pub sent_at_ms: u64,
+    pub updated_at_ms: u64,
+}
+
+/// One NoOrders (73) instance of an inbound ListStatus.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ListOrderStatus<'a> {
+    pub cl_ord_id: &'a str,
+    pub ord_status: Option<&'a str>,
+    pub cum_qty: Option<&'a str>,
+    pub leaves_qty: Option<&'a str>,
+    pub avg_px: Option<&'a str>,
+}
+
+/// The fields of an inbound ListStatus (35=N).
+#[derive(Debug, Clone, Default)]
+pub struct ListStatus<'a> {
+    pub list_id: Option<&'a str>,
+    pub list_status_type: Option<&'a str>,
+    pub list_order_status: Option<&'a str>,
+    pub orders: Vec<ListOrderStatus<'a>>,
+}
+
+/// Why an OrderCancelReplaceRequest cannot be sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplaceError {
+    MissingClOrdId,
+    MissingOrigClOrdId,
+    /// The replacement must have a ClOrdID of its own.
+    SameClOrdId,
+    /// The ClOrdID is already used by another order.
+    DuplicateClOrdId(String),
+    /// The order being replaced has already completed.
+    OrderCompleted(OrderState),
+}
+
+impl ReplaceError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::DuplicateClOrdId(_) | Self::OrderCompleted(_) => StatusCode::CONFLICT,
+            _ => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+
+    /// The error code of the problem `/send` answers with.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::MissingClOrdId | Self::MissingOrigClOrdId => "missing_cl_ord_id",
+            Self::SameClOrdId => "unchanged_cl_ord_id",
+            Self::DuplicateClOrdId(_) => "duplicate_cl_ord_id",
+            Self::OrderCompleted(_) => "order_completed",
+        }
+    }
+}
+
+impl fmt::Display for ReplaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingClOrdId => f.write_str("ClOrdID (11) is required"),
+            Self::MissingOrigClOrdId => f.write_str("OrigClOrdID (41) is required"),
+            Self::SameClOrdId => f.write_str("ClOrdID must differ from OrigClOrdID"),
+            Self::DuplicateClOrdId(id) => write!(f, "ClOrdID {id} is already in use"),
+            Self::OrderCompleted(state) => write!(f, "the order is already {state:?}"),
+        }
+    }
+}
+
+pub struct Correlated {
+    pub context: ReportContext,
+    pub changed: Vec<OrderContext>,
+}
+
+fn number(value: Option<&str>) -> Option<f64> {
+    value?.trim().parse().ok().filter(|n: &f64| n.is_finite())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema, Enum)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FillStatus {
+    #[default]
+    Active,
+    /// Amended by a trade correction; `qty` and `px` are the corrected values.
+    Corrected,
+    /// Cancelled by a trade bust, and no longer counted.
+    Busted,
+}
+
+/// One execution against an order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema, SimpleObject)]
+pub struct Fill {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exec_id: Option<String>,
+    pub qty: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub px: Option<f64>,
+    pub at_ms: u64,
+    #[serde(default)]
+    pub status: FillStatus,
+}
+
+/// The last ExecutionReport applied to an order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema, SimpleObject)]
+pub struct LastExec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exec_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exec_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_qty: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_px: Option<f64>,
+    pub at_ms: u64,
+}
+
+impl ExecutionReport<'_> {
+    fn last_exec(&self, at_ms: u64) -> LastExec {
+        LastExec {
+            exec_id: self.exec_id.map(str::to_string),
+            exec_type: self.exec_type.map(str::to_string),
+            last_qty: number(self.last_qty),
+            last_px: number(self.last_px),
+            at_ms,
+        }
+    }
+
+    fn trigger(&self) -> String {
+        let mut trigger = "ExecutionReport".to_string();
+        for (tag, value) in [(150, self.exec_type), (39, self.ord_status)] {
+            if let Some(value) = value {
+                trigger.push_str(&format!(" {tag}={value}"));
+            }
+        }
+        trigger
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OrderContext {
+    pub cl_ord_id: String,
+    pub session_id: String,
+    pub symbol: Option<String>,
+    pub side: Option<String>,
+    pub order_qty: Option<String>,
+    pub ord_type: Option<String>,
+    pub price: Option<String>,
+    pub account: Option<String>,
+    pub time_in_force: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+    /// Downstream gateway to relay this order's ExecutionReports to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<String>,
+    pub sent_at_ms: u64,
+    #[serde(default)]
+    pub state: OrderState,
+    /// For an order sent in a NewOrderList, its ListID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub list_id: Option<String>,
+    /// For a cancel/replace, the ClOrdID of the order it replaces.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orig_cl_ord_id: Option<String>,
+    /// Every state the order has been in, oldest first.
+    #[serde(default)]
+    pub history: Vec<StateChange>,
+    #[serde(default)]
+    pub updated_at_ms: u64,
+    /// Filled so far: the last CumQty (14) reported, or the LastQty (32) of
+    /// the fills seen when reports omit it.
+    #[serde(default)]
+    pub cum_qty: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leaves_qty: Option<f64>,
+    /// AvgPx (6) as reported, or the volume-weighted LastPx (31) of fills.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avg_px: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_exec: Option<LastExec>,
+    /// Executions, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fills: Vec<Fill>,
+}
+
+impl OrderContext {
+    /// Captures the order parameters from a NewOrderSingle's fields along
+    /// with the client metadata supplied on the send request.
+    pub fn from_fields(
+        cl_ord_id: &str,
+        session_id: &str,
+        fields: &HashMap<String, String>,
+        metadata: &HashMap<String, String>,
+        sent_at_ms: u64,
+    ) -> Self {
+        let get = |name: &str, tag: u32| lookup_field(fields, name, tag).cloned();
+        Self {
+            cl_ord_id: cl_ord_id.to_string(),
+            session_id: session_id.to_string(),
+            symbol: get("Symbol", 55),
+            side: get("Side", 54),
+            order_qty: get("OrderQty", 38),
+            ord_type: get("OrdType", 40),
+            price: get("Price", 44),
+            account: get("Account", 1),
+            time_in_force: get("TimeInForce", 59),
+            metadata: metadata.clone(),
+            reply_to: None,
+            sent_at_ms,
+            state: OrderState::PendingNew,
+            list_id: None,
+            orig_cl_ord_id: None,
+            history: vec![StateChange {
+                state: OrderState::PendingNew,
+                at_ms: sent_at_ms,
+                trigger: "NewOrderSingle".to_string(),
+            }],
+            updated_at_ms: sent_at_ms,
+            cum_qty: 0.0,
+            leaves_qty: None,
+            avg_px: None,
+            last_exec: None,
+            fills: Vec::new(),
+        }
+    }
+
+    /// Takes CumQty, LeavesQty and AvgPx from a report, deriving whichever
+    /// it omits from its fill, bust or correction. Returns whether any of
+    /// them moved.
+    fn apply_quantities(&mut self, report: &ExecutionReport<'_>, at_ms: u64) -> bool {
+        let (mut cum_qty, mut avg_px) = (self.cum_qty, self.avg_px);
+        // Each fill changed moves the totals from `before` to `after`.
+        let mut adjust = |before: (f64, Option<f64>), after: (f64, Option<f64>)| {
+            let total = cum_qty - before.0 + after.0;
+            avg_px = match (avg_px, before, after) {
+                _ if total <= 0.0 => None,
+                // Fills without a price leave the average alone.
+                (avg, (_, None), (_, None)) => avg,
+                (None, _, (_, px)) => px,
+                (Some(avg), (b_qty, b_px), (a_qty, a_px)) => Some(
+                    (avg * cum_qty - b_px.unwrap_or(avg) * b_qty + a_px.unwrap_or(avg) * a_qty)
+                        / total,
+                ),
+            };
+            cum_qty = total;
+        };
+        let last = number(report.last_qty).map(|qty| (qty, number(report.last_px)));
+        match report.exec_type {
+            Some("F" | "1" | "2") => {
+                if let Some((qty, px)) = last.filter(|(qty, _)| *qty > 0.0) {
+                    adjust((0.0, None), (qty, px));
+                    self.fills.push(Fill {
+                        exec_id: report.exec_id.map(str::to_string),
+                        qty,
+                        px,
+                        at_ms,
+                        status: FillStatus::Active,
+                    });
+                }
+            }
+            Some(exec_type @ ("G" | "H")) => {
+                let fill = report.exec_ref_id.and_then(|id| {
+                    self.fills.iter_mut().find(|f| {
+                        f.exec_id.as_deref() == Some(id) && f.status != FillStatus::Busted
+                    })
+                });
+                match fill {
+                    Some(fill) if exec_type == "H" => {
+                        adjust((fill.qty, fill.px), (0.0, None));
+                        fill.status = FillStatus::Busted;
+                    }
+                    Some(fill) => {
+                        let (qty, px) = last.unwrap_or((fill.qty, fill.px));
+                        let px = px.or(fill.px);
+                        adjust((fill.qty, fill.px), (qty, px));
+                        (fill.qty, fill.px) = (qty, px);
+                        fill.status = FillStatus::Corrected;
+                    }
+                    None => info!(
+                        cl_ord_id = %self.cl_ord_id,
+                        exec_ref_id = report.exec_ref_id.unwrap_or_default(),
+                        "Trade bust or correction for an unknown fill"
+                    ),
+                }
+            }
+            _ => {}
+        }
+        let cum_qty = number(report.cum_qty).unwrap_or(cum_qty);
+        let avg_px = number(report.avg_px).filter(|px| *px > 0.0).or(avg_px);
+        let leaves_qty = number(report.leaves_qty).or_else(|| {
+            if self.state.is_terminal() {
+                return Some(0.0);
+            }
+            let order_qty = number(self.order_qty.as_deref())?;
+            Some((order_qty - cum_qty).max(0.0))
+        });
+        let moved = (cum_qty, leaves_qty, avg_px) != (self.cum_qty, self.leaves_qty, self.avg_px);
+        (self.cum_qty, self.leaves_qty, self.avg_px) = (cum_qty, leaves_qty, avg_px);
+        moved
+    }
+
+    /// For an order awaiting a cancel or replace, the state it was in before
+    /// the request.
+    fn state_before_request(&self) -> Option<OrderState> {
+        if !self.state.is_awaiting_cancel() {
+            return None;
+        }
+        let before = self
+            .history
+            .iter()
+            .rev()
+            .find(|c| !c.state.is_awaiting_cancel());
+        Some(before.map_or(OrderState::New, |c| c.state))
+    }
+
+    /// Moves the order to `state`, unless it has already reached a terminal
+    /// one. Returns whether the state changed.
+    fn transition(&mut self, state: OrderState, at_ms: u64, trigger: String) -> bool {
+        if state == self.state {
+            self.updated_at_ms = at_ms;
+            return false;
+        }
+        if self.state.is_terminal() {
+            info!(
+                cl_ord_id = %self.cl_ord_id,
+                state = ?self.state,
+                ignored = ?state,
+                trigger = %trigger,
+                "Ignoring state change for a completed order"
+            );
+            return false;
+        }
+        self.set_state(state, at_ms, trigger)
+    }
+
+    /// Moves the order to `state`, even out of a terminal one.
+    fn set_state(&mut self, state: OrderState, at_ms: u64, trigger: String) -> bool {
+        if state == self.state {
+            return false;
+        }
+        self.state = state;
+        self.updated_at_ms = at_ms;
+        self.history.push(StateChange {
+            state,
+            at_ms,
+            trigger,
+        });
+        true
+    }
+}
+
+pub struct OrderStore {
+    orders: HashMap<String, OrderContext>,
+    /// ClOrdIDs of cancel/replace requests, mapped to the original order's ClOrdID.
+    aliases: HashMap<String, String>,
+    /// OrderMassCancelRequests awaiting their report, by ClOrdID.
+    mass_cancels: HashMap<String, MassCancel>,
+    lists: HashMap<String, OrderList>,
+    /// How long a terminal order is kept after its last change.
+    retention_ms: u64,
+    /// When [`Self::record`] next drops orders past their retention.
+    next_prune_ms: u64,
+}
+
+impl Default for OrderStore {
+    fn default() -> Self {
+        Self::with_retention(DEFAULT_RETENTION_MS)
+    }
+}
+
+impl OrderStore {
+    fn with_retention(retention_ms: u64) -> Self {
+        Self {
+            orders: HashMap::new(),
+            aliases: HashMap::new(),
+            mass_cancels: HashMap::new(),
+            lists: HashMap::new(),
+            retention_ms,
+            next_prune_ms: 0,
+        }
+    }
+
+    /// Reads `FIX_ORDER_RETENTION_SECS`.
+    pub fn from_env() -> Self {
+        let retention_ms = match std::env::var("FIX_ORDER_RETENTION_SECS") {
+            Ok(v) => match v.trim().parse::<u64>() {
+                Ok(secs) => secs.saturating_mul(1000),
+                Err(_) => {
+                    warn!(value = %v, "Ignoring invalid FIX_ORDER_RETENTION_SECS");
+                    DEFAULT_RETENTION_MS
+                }
+            },
+            Err(_) => DEFAULT_RETENTION_MS,
+        };
+        Self::with_retention(retention_ms)
+    }
+
+    /// For startup reporting.
+    pub fn describe(&self) -> String {
+        format!(
+            "order store (in-memory, terminal orders kept {}s)",
+            self.retention_ms / 1000
+        )
+    }
+
+    pub fn record(&mut self, order: OrderContext) {
+        if order.sent_at_ms >= self.next_prune_ms {
+            self.prune(order.sent_at_ms);
+            self.next_prune_ms = order.sent_at_ms + PRUNE_INTERVAL_MS;
+        }
+        self.orders.insert(order.cl_ord_id.clone(), order);
+    }
+
+    /// Drops orders that have been in a terminal state, unchanged, for
+    /// longer than the retention, and the ClOrdIDs aliased to them; lists
+    /// done or rejected as long; and mass cancel requests unanswered as long.
+    fn prune(&mut self, now_ms: u64) {
+        let cutoff = now_ms.saturating_sub(self.retention_ms);
+        self.orders
+            .retain(|_, o| !o.state.is_terminal() || o.updated_at_ms >= cutoff);
+        let orders = &self.orders;
+        self.aliases
+            .retain(|_, root| orders.contains_key(root.as_str()));
+        self.lists.retain(|_, l| {
+            let done = matches!(l.state, ListState::AllDone | ListState::Rejected);
+            !done || l.updated_at_ms >= cutoff
+        });
+        // A request unanswered for that long will not be answered.
+        self.mass_cancels.retain(|_, m| m.sent_at_ms >= cutoff);
+    }
+
+    /// Links a follow-up request's ClOrdID to the order named by its OrigClOrdID.
+    pub fn link(&mut self, cl_ord_id: &str, orig_cl_ord_id: &str) {
+        let root = self
+            .aliases
+            .get(orig_cl_ord_id)
+            .cloned()
+            .unwrap_or_else(|| orig_cl_ord_id.to_string());
+        self.aliases.insert(cl_ord_id.to_string(), root);
+    }
+
+    /// The ClOrdID of the order `id` names: itself, or the order a cancel
+    /// request's ClOrdID refers to.
+    fn resolve(&self, id: &str) -> Option<String> {
+        if self.orders.contains_key(id) {
+            return Some(id.to_string());
+        }
+        self.aliases
+            .get(id)
+            .filter(|root| self.orders.contains_key(root.as_str()))
+            .cloned()
+    }
+
+    pub fn get(&self, cl_ord_id: &str) -> Option<&OrderContext> {
+        self.orders.get(&self.resolve(cl_ord_id)?)
+    }
+
+    /// Every order, in no particular order.
+    pub fn all(&self) -> impl Iterator<Item = &OrderContext> {
+        self.orders.values()
+    }
+
+    fn transition(
+        &mut self,
+        cl_ord_id: &str,
+        state: OrderState,
+        at_ms: u64,
+        trigger: String,
+        changed: &mut Vec<OrderContext>,
+    ) {
+        if let Some(order) = self.orders.get_mut(cl_ord_id) {
+            if order.transition(state, at_ms, trigger) {
+                changed.push(order.clone());
+            }
+        }
+    }
+
+    /// An OrderCancelRequest was sent for `orig_cl_ord_id`. Returns the
+    /// orders whose state changed.
+    pub fn cancel_requested(
+        &mut self,
+        cl_ord_id: &str,
+        orig_cl_ord_id: &str,
+        at_ms: u64,
+    ) -> Vec<OrderContext> {
+        self.link(cl_ord_id, orig_cl_ord_id);
+        let mut changed = Vec::new();
+        if let Some(orig) = self.resolve(orig_cl_ord_id) {
+            let trigger = "OrderCancelRequest".to_string();
+            self.transition(
+                &orig,
+                OrderState::PendingCancel,
+                at_ms,
+                trigger,
+                &mut changed,
+            );
+        }
+        changed
+    }
+
+    /// Checks an OrderCancelReplaceRequest before it is sent. An OrigClOrdID
+    /// the gateway has not seen is allowed, since the order may have been
+    /// entered elsewhere.
+    pub fn check_replace(
+        &self,
+        cl_ord_id: Option<&str>,
+        orig_cl_ord_id: Option<&str>,
+    ) -> Result<(), ReplaceError> {
+        let cl_ord_id = cl_ord_id.ok_or(ReplaceError::MissingClOrdId)?;
+        let orig_cl_ord_id = orig_cl_ord_id.ok_or(ReplaceError::MissingOrigClOrdId)?;
+        if cl_ord_id == orig_cl_ord_id {
+            return Err(ReplaceError::SameClOrdId);
+        }
+        if self.resolve(cl_ord_id).is_some() {
+            return Err(ReplaceError::DuplicateClOrdId(cl_ord_id.to_string()));
+        }
+        match self.get(orig_cl_ord_id) {
+            Some(orig) if orig.state.is_terminal() => Err(ReplaceError::OrderCompleted(orig.state)),
+            _ => Ok(()),
+        }
+    }
+
+    /// An OrderCancelReplaceRequest was sent: the order it replaces becomes
+    /// `PENDING_REPLACE`, and the replacement is tracked under its own
+    /// ClOrdID with the amended parameters. Returns the orders whose state
+    /// changed.
+    pub fn replace_requested(
+        &mut self,
+        cl_ord_id: &str,
+        orig_cl_ord_id: &str,
+        fields: &HashMap<String, String>,
+        at_ms: u64,
+    ) -> Vec<OrderContext> {
+        let mut changed = Vec::new();
+        let Some(orig) = self.resolve(orig_cl_ord_id) else {
+            self.link(cl_ord_id, orig_cl_ord_id);
+            return changed;
+        };
+        let trigger = "OrderCancelReplaceRequest".to_string();
+        self.transition(
+            &orig,
+            OrderState::PendingReplace,
+            at_ms,
+            trigger.clone(),
+            &mut changed,
+        );
+
+        let mut replacement = self.orders[&orig].clone();
+        let get = |name: &str, tag: u32| lookup_field(fields, name, tag).cloned();
+        replacement.cl_ord_id = cl_ord_id.to_string();
+        replacement.orig_cl_ord_id = Some(orig);
+        for (slot, value) in [
+            (&mut replacement.order_qty, get("OrderQty", 38)),
+            (&mut replacement.ord_type, get("OrdType", 40)),
+            (&mut replacement.price, get("Price", 44)),
+            (&mut replacement.time_in_force, get("TimeInForce", 59)),
+        ] {
+            if value.is_some() {
+                *slot = value;
+            }
+        }
+        replacement.sent_at_ms = at_ms;
+        replacement.state = OrderState::PendingReplace;
+        replacement.updated_at_ms = at_ms;
+        replacement.history = vec![StateChange {
+            state: OrderState::PendingReplace,
+            at_ms,
+            trigger,
+        }];
+        changed.push(replacement.clone());
+        self.record(replacement);
+        changed
+    }
+
+    /// Applies an ExecutionReport to the order it reports on, and reports how
+    /// it correlates. `changed` lists the orders whose state or quantities
+    /// moved: for a replace, the replacement and the order it replaced.
+    pub fn on_execution_report(&mut self, report: &ExecutionReport<'_>, at_ms: u64) -> Correlated {
+        let Some(key) = [report.cl_ord_id, report.orig_cl_ord_id]
+            .into_iter()
+            .flatten()
+            .find_map(|id| self.resolve(id))
+        else {
+            return Correlated {
+                context: ReportContext {
+                    correlation: Correlation::UnknownOrder,
+                    order: None,
+                },
+                changed: Vec::new(),
+            };
+        };
+        let state = report
+            .ord_status
+            .and_then(OrderState::from_ord_status)
+            .or_else(|| report.exec_type.and_then(OrderState::from_exec_type));
+        let orig = report
+            .orig_cl_ord_id
+            .and_then(|id| self.resolve(id))
+            .filter(|orig| *orig != key);
+        let replaced = report.exec_type == Some("5") || report.ord_status == Some("5");
+        let replaced_orig = orig.filter(|_| replaced);
+
+        // Cancels and replaces the gateway did not request, and restatements,
+        // are the venue acting on its own.
+        let pending = |id: &str| self.orders.get(id).map(|o| o.state);
+        let unsolicited = match report.exec_type {
+            Some("D") => true,
+            Some("4") => pending(&key) != Some(OrderState::PendingCancel),
+            Some("5") => {
+                let replaced = replaced_orig.as_deref().unwrap_or(&key);
+                pending(replaced) != Some(OrderState::PendingReplace)
+            }
+            _ => false,
+        };
+
+        let mut changed = Vec::new();
+        if let Some(order) = replaced_orig
+            .as_ref()
+            .and_then(|id| self.orders.get_mut(id))
+        {
+            if order.transition(OrderState::Replaced, at_ms, report.trigger()) {
+                changed.push(order.clone());
+            }
+        }
+        let Some(order) = self.orders.get_mut(&key) else {
+            unreachable!("resolve only returns recorded ClOrdIDs");
+        };
+        let state = match replaced_orig {
+            // The replacement is live; FIX 4.2 reports it with 39=5.
+            Some(_) => Some(
+                state
+                    .filter(|s| !matches!(s, OrderState::Replaced | OrderState::PendingReplace))
+                    .unwrap_or(OrderState::New),
+            ),
+            None => state,
+        };
+        // Quantities on a late report for a completed order are stale too,
+        // except that a bust or correction can reopen a filled one.
+        let reopens =
+            matches!(report.exec_type, Some("G" | "H")) && order.state == OrderState::Filled;
+        let completed = order.state.is_terminal() && !reopens;
+        let mut moved = state.is_some_and(|state| {
+            if reopens {
+                order.set_state(state, at_ms, report.trigger())
+            } else {
+                order.transition(state, at_ms, report.trigger())
+            }
+        });
+        if !completed && order.apply_quantities(report, at_ms) {
+            order.updated_at_ms = at_ms;
+            moved = true;
+        }
+        if !completed {
+            order.last_exec = Some(report.last_exec(at_ms));
+        }
+        if moved {
+            changed.push(order.clone());
+        }
+        Correlated {
+            context: ReportContext {
+                correlation: if unsolicited {
+                    Correlation::Unsolicited
+                } else {
+                    Correlation::Matched
+                },
+                order: Some(order.clone()),
+            },
+            changed,
+        }
+    }
+
+    /// An OrderMassCancelRequest was sent: every open order of the session in
+    /// its scope becomes `PENDING_CANCEL`. Returns the orders whose state
+    /// changed.
+    pub fn mass_cancel_requested(
+        &mut self,
+        cl_ord_id: &str,
+        session_id: &str,
+        scope: &MassCancelScope,
+        at_ms: u64,
+    ) -> Vec<OrderContext> {
+        let mut covered: Vec<String> = self
+            .orders
+            .values()
+            .filter(|o| o.session_id == session_id && !o.state.is_terminal() && scope.covers(o))
+            .map(|o| o.cl_ord_id.clone())
+            .collect();
+        covered.sort();
+        let mut changed = Vec::new();
+        for id in &covered {
+            let trigger = format!("OrderMassCancelRequest {cl_ord_id}");
+            self.transition(id, OrderState::PendingCancel, at_ms, trigger, &mut changed);
+        }
+        let request = MassCancel {
+            orders: covered,
+            sent_at_ms: at_ms,
+        };
+        self.mass_cancels.insert(cl_ord_id.to_string(), request);
+        changed
+    }
+
+    /// Applies an OrderMassCancelReport to the orders its request covered.
+    /// If the request was rejected they return to their state before it.
+    /// If it was accepted, the orders the report lists as affected wait for
+    /// their cancel ExecutionReports and the rest of the request's orders
+    /// return to their earlier state; a report without the list leaves them
+    /// all waiting.
+    pub fn on_mass_cancel_report(
+        &mut self,
+        report: &MassCancelReport<'_>,
+        at_ms: u64,
+    ) -> MassCancelOutcome {
+        let mut outcome = MassCancelOutcome::default();
+        let Some(request) = report.cl_ord_id.and_then(|id| self.mass_cancels.remove(id)) else {
+            return outcome;
+        };
+        outcome.known = true;
+        let trigger = report.trigger();
+        let rejected = report.response == Some("0");
+        let affected: Vec<String> = report
+            .affected
+            .iter()
+            .filter_map(|id| self.resolve(id))
+            .collect();
+        let mut orders = request.orders;
+        for id in &affected {
+            if !orders.contains(id) {
+                orders.push(id.clone());
+            }
+        }
+        for id in &orders {
+            let Some(order) = self.orders.get_mut(id) else {
+                continue;
+            };
+            let state = if rejected || !(affected.is_empty() || affected.contains(id)) {
+                order.state_before_request()
+            } else {
+                Some(OrderState::PendingCancel)
+            };
+            if state.is_some_and(|state| order.transition(state, at_ms, trigger.clone())) {
+                outcome.changed.push(order.clone());
+            }
+            outcome.orders.push(order.clone());
+        }
+        outcome
+    }
+
+    /// A NewOrderList was sent: each of its orders is recorded as for a
+    /// NewOrderSingle, and the list under its ListID. Returns the orders.
+    pub fn list_submitted<'a>(
+        &mut self,
+        list_id: &str,
+        session_id: &str,
+        orders: impl IntoIterator<Item = &'a HashMap<String, String>>,
+        metadata: &HashMap<String, String>,
+        at_ms: u64,
+    ) -> Vec<OrderContext> {
+        let mut recorded = Vec::new();
+        for fields in orders {
+            let Some(cl_ord_id) = lookup_field(fields, "ClOrdID", 11) else {
+                continue;
+            };
+            let mut order =
+                OrderContext::from_fields(cl_ord_id, session_id, fields, metadata, at_ms);
+            order.list_id = Some(list_id.to_string());
+            order.history[0].trigger = "NewOrderList".to_string();
+            recorded.push(order.clone());
+            self.record(order);
+        }
+        let list = OrderList {
+            list_id: list_id.to_string(),
+            session_id: session_id.to_string(),
+            cl_ord_ids: recorded.iter().map(|o| o.cl_ord_id.clone()).collect(),
+            state: ListState::Submitted,
+            list_status_type: None,
+            cancel_requested_at_ms: None,
+            sent_at_ms: at_ms,
+            updated_at_ms: at_ms,
+        };
+        self.lists.insert(list_id.to_string(), list);
+        recorded
+    }
+
+    /// A ListCancelRequest was sent: the list's open orders become
+    /// `PENDING_CANCEL`. Returns the orders whose state changed.
+    pub fn list_cancel_requested(&mut self, list_id: &str, at_ms: u64) -> Vec<OrderContext> {
+        let mut changed = Vec::new();
+        let Some(list) = self.lists.get_mut(list_id) else {
+            return changed;
+        };
+        list.cancel_requested_at_ms = Some(at_ms);
+        list.updated_at_ms = at_ms;
+        for id in list.cl_ord_ids.clone() {
+            let trigger = "ListCancelRequest".to_string();
+            self.transition(&id, OrderState::PendingCancel, at_ms, trigger, &mut changed);
+        }
+        changed
+    }
+
+    /// Applies a ListStatus to its list and to the orders it reports on.
+    /// Returns the list, if known, and the orders that changed.
+    pub fn on_list_status(
+        &mut self,
+        status: &ListStatus<'_>,
+        at_ms: u64,
+    ) -> (Option<OrderList>, Vec<OrderContext>) {
+        let mut changed = Vec::new();
+        let Some(list) = status.list_id.and_then(|id| self.lists.get_mut(id)) else {
+            return (None, changed);
+        };
+        if let Some(state) = status
+            .list_order_status
+            .and_then(ListState::from_list_order_status)
+        {
+            list.state = state;
+        }
+        if let Some(status_type) = status.list_status_type {
+            list.list_status_type = Some(status_type.to_string());
+        }
+        list.updated_at_ms = at_ms;
+        let list = list.clone();
+
+        let trigger = match status.list_status_type {
+            Some(status_type) => format!("ListStatus 429={status_type}"),
+            None => "ListStatus".to_string(),
+        };
+        for entry in &status.orders {
+            let Some(order) = self
+                .resolve(entry.cl_ord_id)
+                .and_then(|id| self.orders.get_mut(&id))
+                .filter(|o| o.list_id.as_deref() == Some(list.list_id.as_str()))
+            else {
+                continue;
+            };
+            let completed = order.state.is_terminal();
+            let state = entry.ord_status.and_then(OrderState::from_ord_status);
+            let mut moved =
+                state.is_some_and(|state| order.transition(state, at_ms, trigger.clone()));
+            let report = ExecutionReport {
+                cum_qty: entry.cum_qty,
+                leaves_qty: entry.leaves_qty,
+                avg_px: entry.avg_px,
+                ..ExecutionReport::default()
+            };
+            if !completed && order.apply_quantities(&report, at_ms) {
+                order.updated_at_ms = at_ms;
+                moved = true;
+            }
+            if moved {
+                changed.push(order.clone());
+            }
+        }
+        (Some(list), changed)
+    }
+
+    pub fn list(&self, list_id: &str) -> Option<&OrderList> {
+        self.lists.get(list_id)
+    }
+
+    /// Applies an OrderCancelReject: a rejected replacement becomes
+    /// `REJECTED`, and the order the request named returns to the OrdStatus
+    /// (39) reported, or without one to its state before the request.
+    pub fn on_cancel_reject(&mut self, reject: &CancelReject<'_>, at_ms: u64) -> Correlated {
+        let trigger = reject.trigger();
+        let mut changed = Vec::new();
+        let replacement = reject
+            .cl_ord_id
+            .filter(|id| {
+                self.orders.get(*id).is_some_and(|o| {
+                    o.orig_cl_ord_id.is_some() && o.state == OrderState::PendingReplace
+                })
+            })
+            .map(str::to_string);
+        if let Some(id) = &replacement {
+            self.transition(
+                id,
+                OrderState::Rejected,
+                at_ms,
+                trigger.clone(),
+                &mut changed,
+            );
+        }
+        let target = reject
+            .orig_cl_ord_id
+            .and_then(|id| self.resolve(id))
+            .or_else(|| {
+                let replaced = replacement.as_ref()?;
+                self.orders[replaced].orig_cl_ord_id.clone()
+            })
+            .or_else(|| {
+                let id = reject.cl_ord_id?;
+                self.resolve(id).filter(|_| replacement.is_none())
+            });
+        let Some(order) = target.and_then(|id| self.orders.get_mut(&id)) else {
+            return Correlated {
+                context: ReportContext {
+                    correlation: Correlation::UnknownOrder,
+                    order: None,
+                },
+                changed,
+            };
+        };
+        let state = reject
+            .ord_status
+            .and_then(OrderState::from_ord_status)
+            .or_else(|| order.state_before_request());
+        if state.is_some_and(|state| order.transition(state, at_ms, trigger)) {
+            changed.push(order.clone());
+        }
+        Correlated {
+            context: ReportContext {
+                correlation: Correlation::Matched,
+                order: Some(order.clone()),
+            },
+            changed,
+        }
+    }
+
+    /// The fields of the ExecutionReport (ExecType=I) answering an
+    /// OrderStatusRequest: the order's state and quantities, or OrdStatus
+    /// Rejected with OrdRejReason 5 (unknown order) if there is no such order.
+    pub fn status_report(
+        &self,
+        request: &StatusRequest<'_>,
+        exec_id: &str,
+    ) -> HashMap<String, String> {
+        let order = request.cl_ord_id.and_then(|id| self.get(id));
+        let mut fields: HashMap<String, String> = HashMap::new();
+        let mut set = |tag: u32, value: Option<&str>| {
+            if let Some(value) = value {
+                fields.insert(tag.to_string(), value.to_string());
+            }
+        };
+        set(37, request.order_id.or(Some("NONE")));
+        set(11, request.cl_ord_id);
+        set(790, request.ord_status_req_id);
+        set(17, Some(exec_id));
+        set(150, Some("I"));
+        match order {
+            Some(order) => {
+                set(41, order.orig_cl_ord_id.as_deref());
+                set(39, Some(order.state.ord_status()));
+                set(55, order.symbol.as_deref().or(request.symbol));
+                set(54, order.side.as_deref().or(request.side));
+                set(38, order.order_qty.as_deref());
+                set(40, order.ord_type.as_deref());
+                set(44, order.price.as_deref());
+                set(14, Some(&order.cum_qty.to_string()));
+                let leaves = order.leaves_qty.or_else(|| {
+                    let order_qty = number(order.order_qty.as_deref())?;
+                    Some(if order.state.is_terminal() {
+                        0.0
+                    } else {
+                        (order_qty - order.cum_qty).max(0.0)
+                    })
+                });
+                set(151, Some(&leaves.unwrap_or(0.0).to_string()));
+                set(6, Some(&order.avg_px.unwrap_or(0.0).to_string()));
+            }
+            None => {
+                set(39, Some(OrderState::Rejected.ord_status()));
+                set(103, Some("5"));
+                set(55, request.symbol);
+                set(54, request.side);
+                set(14, Some("0"));
+                set(151, Some("0"));
+                set(6, Some("0"));
+            }
+        }
+        fields
+    }
+
+    /// Orders sent on `session_id` (all sessions if `None`) that match
+    /// `status`, oldest first.
+    pub fn blotter(&self, status: StatusFilter, session_id: Option<&str>) -> Vec<BlotterRow> {
+        let mut rows: Vec<&OrderContext> = self
+            .orders
+            .values()
+            .filter(|o| session_id.is_none_or(|id| o.session_id == id))
+            .filter(|o| match status {
+                StatusFilter::Open => !o.state.is_terminal(),
+                StatusFilter::Closed => o.state.is_terminal(),
+                StatusFilter::All => true,
+            })
+            .collect();
+        rows.sort_by(|a, b| (a.sent_at_ms, &a.cl_ord_id).cmp(&(b.sent_at_ms, &b.cl_ord_id)));
+        rows.into_iter().map(BlotterRow::from).collect()
+    }
+
+    /// The cancels to send for `session_id`'s open orders after it dropped,
+    /// as tag/value fields in the order they should go out. Orders already
+    /// awaiting a cancel or replace are left to that request.
+    pub fn disconnect_cancels(
+        &self,
+        session_id: &str,
+        mode: CancelOnDisconnect,
+        at_ms: u64,
+    ) -> Vec<HashMap<String, String>> {
+        let mut open: Vec<&OrderContext> = self
+            .orders
+            .values()
+            .filter(|o| o.session_id == session_id)
+            .filter(|o| !o.state.is_terminal() && !o.state.is_awaiting_cancel())
+            .collect();
+        if open.is_empty() {
+            return Vec::new();
+        }
+        open.sort_by(|a, b| (a.sent_at_ms, &a.cl_ord_id).cmp(&(b.sent_at_ms, &b.cl_ord_id)));
+        let field = |tag: u32, value: &str| (tag.to_string(), value.to_string());
+        match mode {
+            CancelOnDisconnect::Orders => open
+                .into_iter()
+                .map(|order| {
+                    let mut fields = HashMap::from([
+                        field(11, &format!("{}-COD", order.cl_ord_id)),
+                        field(41, &order.cl_ord_id),
+                    ]);
+                    for (tag, value) in [
+                        (55, &order.symbol),
+                        (54, &order.side),
+                        (38, &order.order_qty),
+                    ] {
+                        if let Some(value) = value {
+                            fields.insert(tag.to_string(), value.clone());
+                        }
+                    }
+                    fields
+                })
+                .collect(),
+            CancelOnDisconnect::Mass => vec![HashMap::from([
+                field(11, &format!("COD-{at_ms}")),
+                field(530, "7"),
+            ])],
+        }
+    }
+
+    /// Orders sent in `[from_ms, to_ms)`.
+    pub fn sent_between(&self, from_ms: u64, to_ms: u64) -> Vec<OrderContext> {
+        self.orders
+            .values()
+            .filter(|o| (from_ms..to_ms).contains(&o.sent_at_ms))
+            .cloned()
+            .collect()
+    }
+}
+
+// ── Blotter ───────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusFilter {
+    /// Orders not yet in a terminal state.
+    Open,
+    Closed,
+    #[default]
+    All,
+}
+
+/// One order as a trading UI lists it.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BlotterRow {
+    pub cl_ord_id: String,
+    pub session_id: String,
+    pub symbol: Option<String>,
+    pub side: Option<String>,
+    pub order_qty: Option<String>,
+    pub filled_qty: f64,
+    pub leaves_qty: Option<f64>,
+    pub price: Option<String>,
+    pub avg_px: Option<f64>,
+    pub state: OrderState,
+    pub last_exec: Option<LastExec>,
+    pub sent_at_ms: u64,
+    pub updated_at_ms: u64,
+}
+
+impl From<&OrderContext> for BlotterRow {
+    fn from(order: &OrderContext) -> Self {
+        Self {
+            cl_ord_id: order.cl_ord_id.clone(),
+            session_id: order.session_id.clone(),
+            symbol: order.symbol.clone(),
+            side: order.side.clone(),
+            order_qty: order.order_qty.clone(),
+            filled_qty: order.cum_qty,
+            leaves_qty: order.leaves_qty,
+            price: order.price.clone(),
+            avg_px: order.avg_px,
+            state: order.state,
+            last_exec: order.last_exec.clone(),
+            sent_at_ms: order.sent_at_ms,
+            updated_at_ms: order.updated_at_ms,
+        }
+    }
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BlotterQuery {
+    #[serde(default)]
+    status: StatusFilter,
+    session: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlotterResponse {
+    items: Vec<BlotterRow>,
+    count: usize,
+}
+
+/// The order blotter: every order, or the open or closed ones, optionally
+/// for one session.
+#[utoipa::path(
+    get,
+    path = "/api/v1/orders",
+    tag = "orders",
+    params(BlotterQuery),
+    responses(
+        (status = 200, description = "OK", body = BlotterResponse),
+    )
+)]
+pub async fn list(
+    State(state): State<AppState>,
+    Query(q): Query<BlotterQuery>,
+) -> Json<BlotterResponse> {
+    let items = state
+        .orders
+        .lock()
+        .await
+        .blotter(q.status, q.session.as_deref());
+    let count = items.len();
+    Json(BlotterResponse { items, count })
+}
+
+/// An order's parameters, state and state history, by its ClOrdID or the
+/// ClOrdID of a cancel request for it.
+#[utoipa::path(
+    get,
+    path = "/api/v1/orders/{cl_ord_id}",
+    tag = "orders",
+    params((
+        "cl_ord_id" = String,
+        Path,
+        description = "The order's ClOrdID, or that of a cancel request for it."
+    )),
+    responses(
+        (status = 200, description = "OK", body = OrderContext),
+        (status = 404, description = "Unknown order"),
+    )
+)]
+pub async fn get(
+    State(state): State<AppState>,
+    Path(cl_ord_id): Path<String>,
+) -> Result<Json<OrderContext>, StatusCode> {
+    state
+        .orders
+        .lock()
+        .await
+        .get(&cl_ord_id)
+        .cloned()
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ListResponse {
+    #[serde(flatten)]
+    list: OrderList,
+    orders: Vec<OrderContext>,
+}
+
+/// A NewOrderList's state, with its orders.
+#[utoipa::path(
+    get,
+    path = "/api/v1/lists/{list_id}",
+    tag = "orders",
+    params(("list_id" = String, Path, description = "The NewOrderList's ListID.")),
+    responses(
+        (status = 200, description = "OK", body = ListResponse),
+        (status = 404, description = "Unknown list"),
+    )
+)]
+pub async fn get_list(
+    State(state): State<AppState>,
+    Path(list_id): Path<String>,
+) -> Result<Json<ListResponse>, StatusCode> {
+    let store = state.orders.lock().await;
+    let list = store.list(&list_id).cloned().ok_or(StatusCode::NOT_FOUND)?;
+    let orders = list
+        .cl_ord_ids
+        .iter()
+        .filter_map(|id| store.get(id).cloned())
+        .collect();
+    Ok(Json(ListResponse { list, orders }))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FillsResponse {
+    cl_ord_id: String,
+    items: Vec<Fill>,
+    count: usize,
+    cum_qty: f64,
+    avg_px: Option<f64>,
+}
+
+/// An order's fills, including busted and corrected ones, with its totals.
+#[utoipa::path(
+    get,
+    path = "/api/v1/orders/{cl_ord_id}/fills",
+    tag = "orders",
+    params(("cl_ord_id" = String, Path, description = "The order's ClOrdID.")),
+    responses(
+        (status = 200, description = "OK", body = FillsResponse),
+        (status = 404, description = "Unknown order"),
+    )
+)]
+pub async fn fills(
+    State(state): State<AppState>,
+    Path(cl_ord_id): Path<String>,
+) -> Result<Json<FillsResponse>, StatusCode> {
+    let orders = state.orders.lock().await;
+    let order = orders.get(&cl_ord_id).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(FillsResponse {
+        cl_ord_id: order.cl_ord_id.clone(),
+        items: order.fills.clone(),
+        count: order.fills.len(),
+        cum_qty: order.cum_qty,
+        avg_px: order.avg_px,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report<'a>(
+        cl_ord_id: &'a str,
+        orig_cl_ord_id: Option<&'a str>,
+        exec_type: &'a str,
+        ord_status: &'a str,
+    ) -> ExecutionReport<'a> {
+        ExecutionReport {
+            cl_ord_id: Some(cl_ord_id),
+            orig_cl_ord_id,
+            exec_type: Some(exec_type),
+            ord_status: Some(ord_status),
+            ..ExecutionReport::default()
+        }
+    }
+
+    fn order(cl_ord_id: &str, qty: &str) -> OrderContext {
+        let fields: HashMap<String, String> = [("55", "AAPL"), ("54", "1"), ("38", qty)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        OrderContext::from_fields(cl_ord_id, "S", &fields, &HashMap::new(), 1)
+    }
+
+    fn states(store: &OrderStore, cl_ord_id: &str) -> Vec<OrderState> {
+        store.orders[cl_ord_id]
+            .history
+            .iter()
+            .map(|c| c.state)
+            .collect()
+    }
+
+    #[test]
+    fn order_lifecycle_follows_requests_and_reports() {
+        use OrderState::*;
+        let fields: HashMap<String, String> =
+            [("55", "AAPL"), ("54", "1"), ("38", "100"), ("44", "10.00")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+        let mut store = OrderStore::default();
+        store.record(OrderContext::from_fields(
+            "O1",
+            "S",
+            &fields,
+            &HashMap::new(),
+            1,
+        ));
+        store.on_execution_report(&report("O1", None, "0", "0"), 2);
+        store.on_execution_report(&report("O1", None, "F", "1"), 3);
+        store.on_execution_report(&report("O1", None, "F", "1"), 4);
+
+        // Replace O1 with O2 for a larger quantity.
+        let amend = HashMap::from([("38".to_string(), "200".to_string())]);
+        let changed = store.replace_requested("O2", "O1", &amend, 5);
+        assert_eq!(changed.len(), 2);
+        assert_eq!(store.orders["O2"].order_qty.as_deref(), Some("200"));
+        assert_eq!(store.orders["O2"].price.as_deref(), Some("10.00"));
+        store.on_execution_report(&report("O2", Some("O1"), "5", "1"), 6);
+        assert_eq!(
+            states(&store, "O1"),
+            [PendingNew, New, PartiallyFilled, PendingReplace, Replaced]
+        );
+        assert_eq!(states(&store, "O2"), [PendingReplace, PartiallyFilled]);
+
+        // Cancel O2; the report names the cancel request's ClOrdID.
+        store.cancel_requested("C1", "O2", 7);
+        assert_eq!(store.get("C1").map(|o| o.state), Some(PendingCancel));
+        store.on_execution_report(&report("C1", Some("O2"), "4", "4"), 8);
+        assert_eq!(store.orders["O2"].state, Canceled);
+
+        // Late reports do not reopen completed orders.
+        assert!(store
+            .on_execution_report(&report("O2", None, "F", "1"), 9)
+            .changed
+            .is_empty());
+        assert_eq!(store.orders["O2"].state, Canceled);
+        assert_eq!(store.orders["O2"].updated_at_ms, 8);
+    }
+
+    #[test]
+    fn reports_accumulate_fills_and_flag_unmatched_orders() {
+        let mut store = OrderStore::default();
+        store.record(order("O1", "300"));
+        let fill = |qty, px| ExecutionReport {
+            last_qty: Some(qty),
+            last_px: Some(px),
+            ..report("O1", None, "F", "1")
+        };
+
+        // Without CumQty/AvgPx on the reports, fills are accumulated.
+        store.on_execution_report(&fill("100", "10"), 2);
+        let matched = store.on_execution_report(&fill("200", "11.5"), 3);
+        assert_eq!(matched.context.correlation, Correlation::Matched);
+        let o1 = matched.context.order.unwrap();
+        assert_eq!(
+            (o1.cum_qty, o1.leaves_qty, o1.avg_px),
+            (300.0, Some(0.0), Some(11.0))
+        );
+
+        // Reported values win over derived ones.
+        let reported = ExecutionReport {
+            cum_qty: Some("250"),
+            leaves_qty: Some("50"),
+            avg_px: Some("10.9"),
+            ..report("O1", None, "D", "1")
+        };
+        let restated = store.on_execution_report(&reported, 4);
+        assert_eq!(restated.context.correlation, Correlation::Unsolicited);
+        assert_eq!(restated.changed[0].leaves_qty, Some(50.0));
+
+        // A cancel the gateway never asked for.
+        let cancel = store.on_execution_report(&report("O1", None, "4", "4"), 5);
+        assert_eq!(cancel.context.correlation, Correlation::Unsolicited);
+        let o1 = &store.orders["O1"];
+        assert_eq!((o1.cum_qty, o1.leaves_qty), (250.0, Some(0.0)));
+
+        let unknown = store.on_execution_report(&report("X9", None, "0", "0"), 6);
+        assert_eq!(unknown.context.correlation, Correlation::UnknownOrder);
+        assert!(unknown.context.order.is_none());
+    }
+
+    #[test]
+    fn cancel_rejects_restore_the_order_and_reject_the_replacement() {
+        use OrderState::*;
+        let mut store = OrderStore::default();
+        store.record(order("O1", "100"));
+        store.on_execution_report(&report("O1", None, "0", "0"), 2);
+
+        assert_eq!(
+            store.check_replace(Some("O1"), Some("O1")),
+            Err(ReplaceError::SameClOrdId)
+        );
+        assert_eq!(
+            store.check_replace(Some("O2"), None),
+            Err(ReplaceError::MissingOrigClOrdId)
+        );
+        assert_eq!(store.check_replace(Some("O2"), Some("O1")), Ok(()));
+        store.replace_requested("O2", "O1", &HashMap::new(), 3);
+        assert_eq!(
+            store.check_replace(Some("O2"), Some("O1")),
+            Err(ReplaceError::DuplicateClOrdId("O2".to_string()))
+        );
+
+        // The venue refuses the replace; O1 is still working.
+        let reject = CancelReject {
+            cl_ord_id: Some("O2"),
+            orig_cl_ord_id: Some("O1"),
+            ord_status: Some("0"),
+            response_to: Some("2"),
+        };
+        let rejected = store.on_cancel_reject(&reject, 4);
+        assert_eq!(rejected.context.correlation, Correlation::Matched);
+        assert_eq!(rejected.changed.len(), 2);
+        assert_eq!(states(&store, "O1"), [PendingNew, New, PendingReplace, New]);
+        assert_eq!(states(&store, "O2"), [PendingReplace, Rejected]);
+        assert_eq!(
+            store.orders["O2"].history[1].trigger,
+            "OrderCancelReject 434=2 39=0"
+        );
+
+        // Without an OrdStatus, a rejected cancel returns to the prior state.
+        store.on_execution_report(&report("O1", None, "F", "1"), 5);
+        store.cancel_requested("C1", "O1", 6);
+        let reject = CancelReject {
+            cl_ord_id: Some("C1"),
+            response_to: Some("1"),
+            ..CancelReject::default()
+        };
+        store.on_cancel_reject(&reject, 7);
+        assert_eq!(store.orders["O1"].state, PartiallyFilled);
+
+        // Too late to cancel: the reject reports the fill.
+        store.cancel_requested("C2", "O1", 8);
+        let reject = CancelReject {
+            cl_ord_id: Some("C2"),
+            orig_cl_ord_id: Some("O1"),
+            ord_status: Some("2"),
+            response_to: Some("1"),
+        };
+        store.on_cancel_reject(&reject, 9);
+        assert_eq!(store.orders["O1"].state, Filled);
+        assert_eq!(
+            store.check_replace(Some("O3"), Some("O1")),
+            Err(ReplaceError::OrderCompleted(Filled))
+        );
+
+        let unknown = store.on_cancel_reject(&CancelReject::default(), 10);
+        assert_eq!(unknown.context.correlation, Correlation::UnknownOrder);
+    }
+
+    #[test]
+    fn status_requests_are_answered_from_order_state() {
+        let mut store = OrderStore::default();
+        store.record(order("O1", "100"));
+        let fill = ExecutionReport {
+            last_qty: Some("40"),
+            last_px: Some("10.5"),
+            ..report("O1", None, "F", "1")
+        };
+        store.on_execution_report(&fill, 2);
+        store.cancel_requested("C1", "O1", 3);
+
+        let request = StatusRequest {
+            cl_ord_id: Some("C1"),
+            ord_status_req_id: Some("R1"),
+            ..StatusRequest::default()
+        };
+        let fields = store.status_report(&request, "E1");
+        let get = |tag: &str| fields.get(tag).map(String::as_str);
+        assert_eq!(get("150"), Some("I"));
+        assert_eq!(get("39"), Some("6"));
+        assert_eq!(get("790"), Some("R1"));
+        assert_eq!(get("55"), Some("AAPL"));
+        assert_eq!(
+            (get("14"), get("151"), get("6")),
+            (Some("40"), Some("60"), Some("10.5"))
+        );
+
+        let request = StatusRequest {
+            cl_ord_id: Some("X9"),
+            symbol: Some("MSFT"),
+            side: Some("2"),
+            ..StatusRequest::default()
+        };
+        let fields = store.status_report(&request, "E2");
+        assert_eq!(fields["39"], "8");
+        assert_eq!(fields["103"], "5");
+        assert_eq!(fields["55"], "MSFT");
+        assert_eq!(fields["37"], "NONE");
+    }
+
+    #[test]
+    fn mass_cancel_reports_map_back_onto_covered_orders() {
+        use OrderState::*;
+        let mut store = OrderStore::default();
+        for id in ["O1", "O2", "O3"] {
+            store.record(order(id, "100"));
+            store.on_execution_report(&report(id, None, "0", "0"), 2);
+        }
+        let mut sell = order("O4", "100");
+        sell.side = Some("2".to_string());
+        store.record(sell);
+        let mut other = order("O5", "100");
+        other.symbol = Some("MSFT".to_string());
+        store.record(other);
+        store.on_execution_report(&report("O3", None, "F", "2"), 3);
+
+        // By symbol and side: the filled order and the others are untouched.
+        let scope = MassCancelScope {
+            request_type: "1".to_string(),
+            symbol: Some("AAPL".to_string()),
+            side: Some("1".to_string()),
+        };
+        let changed = store.mass_cancel_requested("M1", "S", &scope, 4);
+        let ids: Vec<&str> = changed.iter().map(|o| o.cl_ord_id.as_str()).collect();
+        assert_eq!(ids, ["O1", "O2"]);
+
+        // The venue cancels only O1; O2 goes back to working.
+        let accepted = MassCancelReport {
+            cl_ord_id: Some("M1"),
+            response: Some("1"),
+            affected: vec!["O1"],
+        };
+        let outcome = store.on_mass_cancel_report(&accepted, 5);
+        assert!(outcome.known);
+        assert_eq!(outcome.orders.len(), 2);
+        assert_eq!(store.orders["O1"].state, PendingCancel);
+        assert_eq!(states(&store, "O2"), [PendingNew, New, PendingCancel, New]);
+        let cancel = store.on_execution_report(&report("O1", None, "4", "4"), 6);
+        assert_eq!(cancel.context.correlation, Correlation::Matched);
+
+        // A rejected cancel-all restores every order it covered.
+        let all = MassCancelScope {
+            request_type: "7".to_string(),
+            symbol: None,
+            side: None,
+        };
+        assert_eq!(store.mass_cancel_requested("M2", "S", &all, 7).len(), 3);
+        let rejected = MassCancelReport {
+            cl_ord_id: Some("M2"),
+            response: Some("0"),
+            affected: Vec::new(),
+        };
+        assert_eq!(store.on_mass_cancel_report(&rejected, 8).changed.len(), 3);
+        assert_eq!(store.orders["O4"].state, PendingNew);
+        assert_eq!(store.orders["O5"].state, PendingNew);
+        assert!(!store.on_mass_cancel_report(&rejected, 9).known);
+    }
+
+    #[test]
+    fn blotter_lists_open_orders_with_their_last_execution() {
+        let mut store = OrderStore::default();
+        store.record(order("O1", "100"));
+        store.record(order("O2", "100"));
+        let mut other = order("O3", "100");
+        other.session_id = "T".to_string();
+        store.record(other);
+        let fill = ExecutionReport {
+            exec_id: Some("E1"),
+            last_qty: Some("40"),
+            last_px: Some("10"),
+            ..report("O1", None, "F", "1")
+        };
+        store.on_execution_report(&fill, 2);
+        store.on_execution_report(&report("O2", None, "8", "8"), 3);
+
+        let open = store.blotter(StatusFilter::Open, Some("S"));
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].cl_ord_id, "O1");
+        assert_eq!((open[0].filled_qty, open[0].leaves_qty), (40.0, Some(60.0)));
+        let last = open[0].last_exec.as_ref().unwrap();
+        assert_eq!(
+            (last.exec_id.as_deref(), last.last_qty, last.at_ms),
+            (Some("E1"), Some(40.0), 2)
+        );
+        let closed = store.blotter(StatusFilter::Closed, None);
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].state, OrderState::Rejected);
+        assert_eq!(store.blotter(StatusFilter::All, None).len(), 3);
+    }
+
+    #[test]
+    fn busts_and_corrections_adjust_fill_totals() {
+        let mut store = OrderStore::default();
+        store.record(order("O1", "300"));
+        let fill = |exec_id, qty, px, status| ExecutionReport {
+            exec_id: Some(exec_id),
+            last_qty: Some(qty),
+            last_px: Some(px),
+            ..report("O1", None, "F", status)
+        };
+        store.on_execution_report(&fill("E1", "100", "10", "1"), 2);
+        store.on_execution_report(&fill("E2", "200", "13", "2"), 3);
+        let o1 = &store.orders["O1"];
+        assert_eq!(
+            (o1.state, o1.cum_qty, o1.avg_px),
+            (OrderState::Filled, 300.0, Some(12.0))
+        );
+
+        // Busting E2 reopens the filled order.
+        let bust = ExecutionReport {
+            exec_id: Some("E3"),
+            exec_ref_id: Some("E2"),
+            ..report("O1", None, "H", "1")
+        };
+        store.on_execution_report(&bust, 4);
+        let o1 = &store.orders["O1"];
+        assert_eq!(o1.state, OrderState::PartiallyFilled);
+        assert_eq!(
+            (o1.cum_qty, o1.leaves_qty, o1.avg_px),
+            (100.0, Some(200.0), Some(10.0))
+        );
+        assert_eq!(o1.fills[1].status, FillStatus::Busted);
+
+        // E1 is corrected to 50 at 12.
+        let correct = ExecutionReport {
+            exec_id: Some("E4"),
+            exec_ref_id: Some("E1"),
+            last_qty: Some("50"),
+            last_px: Some("12"),
+            ..report("O1", None, "G", "1")
+        };
+        store.on_execution_report(&correct, 5);
+        let o1 = &store.orders["O1"];
+        assert_eq!((o1.cum_qty, o1.avg_px), (50.0, Some(12.0)));
+        assert_eq!(
+            (o1.fills[0].qty, o1.fills[0].status),
+            (50.0, FillStatus::Corrected)
+        );
+
+        // A bust of a fill already busted changes nothing.
+        assert!(store.on_execution_report(&bust, 6).changed.is_empty());
+    }
+
+    #[test]
+    fn order_lists_follow_list_status() {
+        use OrderState::*;
+        let orders: Vec<HashMap<String, String>> = [("L1-1", "AAPL"), ("L1-2", "MSFT")]
+            .into_iter()
+            .map(|(id, symbol)| {
+                [("11", id), ("55", symbol), ("54", "1"), ("38", "100")]
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect()
+            })
+            .collect();
+        let mut store = OrderStore::default();
+        let recorded = store.list_submitted("L1", "S", &orders, &HashMap::new(), 1);
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(store.orders["L1-2"].list_id.as_deref(), Some("L1"));
+
+        let status = ListStatus {
+            list_id: Some("L1"),
+            list_status_type: Some("4"),
+            list_order_status: Some("3"),
+            orders: vec![
+                ListOrderStatus {
+                    cl_ord_id: "L1-1",
+                    ord_status: Some("1"),
+                    cum_qty: Some("40"),
+                    leaves_qty: Some("60"),
+                    avg_px: Some("10"),
+                },
+                ListOrderStatus {
+                    cl_ord_id: "L1-2",
+                    ord_status: Some("0"),
+                    ..ListOrderStatus::default()
+                },
+            ],
+        };
+        let (list, changed) = store.on_list_status(&status, 2);
+        assert_eq!(list.map(|l| l.state), Some(ListState::Executing));
+        assert_eq!(changed.len(), 2);
+        assert_eq!(store.orders["L1-1"].cum_qty, 40.0);
+        assert_eq!(store.orders["L1-1"].history[1].trigger, "ListStatus 429=4");
+
+        let changed = store.list_cancel_requested("L1", 3);
+        assert_eq!(changed.len(), 2);
+        assert_eq!(store.orders["L1-2"].state, PendingCancel);
+        assert_eq!(store.list("L1").unwrap().cancel_requested_at_ms, Some(3));
+        assert!(store.on_list_status(&ListStatus::default(), 4).0.is_none());
+    }
+
+    #[test]
+    fn disconnect_cancels_cover_open_orders_not_already_cancelling() {
+        let mut store = OrderStore::default();
+        for id in ["O1", "O2", "O3", "O4"] {
+            store.record(order(id, "100"));
+        }
+        let mut elsewhere = order("O5", "100");
+        elsewhere.session_id = "T".to_string();
+        store.record(elsewhere);
+        store.on_execution_report(&report("O2", None, "F", "2"), 2);
+        store.cancel_requested("C3", "O3", 2);
+
+        let cancels = store.disconnect_cancels("S", CancelOnDisconnect::Orders, 3);
+        let ids: Vec<(&str, &str)> = cancels
+            .iter()
+            .map(|f| (f["11"].as_str(), f["41"].as_str()))
+            .collect();
+        assert_eq!(ids, [("O1-COD", "O1"), ("O4-COD", "O4")]);
+        assert_eq!(cancels[0]["55"], "AAPL");
+        assert_eq!(cancels[0]["38"], "100");
+
+        let mass = store.disconnect_cancels("S", CancelOnDisconnect::Mass, 3);
+        assert_eq!(mass.len(), 1);
+        assert_eq!(
+            (mass[0]["11"].as_str(), mass[0]["530"].as_str()),
+            ("COD-3", "7")
+        );
+        assert!(store
+            .disconnect_cancels("U", CancelOnDisconnect::Mass, 3)
+            .is_empty());
+    }
+
+    #[test]
+    fn terminal_orders_are_dropped_after_their_retention() {
+        let mut store = OrderStore::with_retention(1_000);
+        store.record(order("DONE", "100"));
+        store.record(order("OPEN", "100"));
+        store.cancel_requested("C1", "DONE", 2);
+        store.on_execution_report(&report("C1", Some("DONE"), "4", "4"), 3);
+        assert_eq!(store.get("C1").map(|o| o.state), Some(OrderState::Canceled));
+        let list_order = HashMap::from([("11".to_string(), "L1-1".to_string())]);
+        store.list_submitted("L1", "S", [&list_order], &HashMap::new(), 3);
+        let done = ListStatus {
+            list_id: Some("L1"),
+            list_order_status: Some("6"),
+            ..ListStatus::default()
+        };
+        assert_eq!(store.on_list_status(&done, 3).0.map(|l| l.state), Some(ListState::AllDone));
+        let all = MassCancelScope {
+            request_type: "7".to_string(),
+            symbol: None,
+            side: None,
+        };
+        store.mass_cancel_requested("M1", "OTHER", &all, 3);
+
+        // Within the retention, and not yet due a prune: all are kept.
+        let mut late = order("NEXT", "100");
+        late.sent_at_ms = 1_003;
+        store.record(late);
+        assert!(store.get("DONE").is_some());
+        assert!(store.list("L1").is_some() && store.mass_cancels.contains_key("M1"));
+
+        let mut later = order("LAST", "100");
+        later.sent_at_ms = PRUNE_INTERVAL_MS + 4;
+        store.record(later);
+        assert!(store.get("DONE").is_none());
+        assert!(store.get("C1").is_none(), "aliases go with their order");
+        assert!(store.get("OPEN").is_some(), "open orders are kept");
+        assert!(store.list("L1").is_none(), "completed lists are dropped");
+        assert!(store.get("L1-1").is_some(), "but not their open orders");
+        assert!(store.mass_cancels.is_empty(), "unanswered mass cancels are dropped");
+    }
+}
@@ -0,0 +1,465 @@
+//! Bounded outbound channels, one per session.
+//!
+//! A message sent on a session goes into the session's channel, and the
+//! session's writer takes it from there to the counterparty. A channel holds
+//! at most `FIX_OUTBOUND_CAPACITY` messages, so a slow counterparty cannot
+//! make the engine buffer without bound. A send takes a slot in the channel
+//! before its MsgSeqNum is allocated; when there is none, the session's
+//! policy in `FIX_OUTBOUND_FULL_POLICY` decides:
+//!
+//! - `block`, the default, waits up to `FIX_OUTBOUND_BLOCK_TIMEOUT_MS` for
+//!   the writer to make room, then fails the send with 503.
+//! - `reject` fails the send with 503 at once.
+//! - `drop_oldest` drops the oldest message waiting, for market data where
+//!   only the latest matters. The counterparty sees a gap in MsgSeqNums, as
+//!   after a message lost on the way.
+//!
+//! A send failed for want of a slot consumes no sequence number. The depth,
+//! high-water mark and dropped and rejected counts of a session's channel
+//! are in `GET /api/v1/fix/sessions/{session_id}/stats`.
+//!
+//! Sessions have no TCP transport yet, so their writers write to a sink.
+//!
+//! The channel's lock is a std one: it is never held across an await, and a
+//! [`Slot`] dropped unused gives its place back from `Drop`.
+
+use bytes::Bytes;
+use dashmap::DashMap;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    sync::Notify,
+    time::Instant,
+};
+use tracing::warn;
+use utoipa::ToSchema;
+
+const DEFAULT_CAPACITY: usize = 10_000;
+const DEFAULT_BLOCK_TIMEOUT_MS: u64 = 1000;
+
+/// What a send does when its session's channel is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FullPolicy {
+    #[default]
+    Block,
+    Reject,
+    DropOldest,
+}
+
+impl FromStr for FullPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "block" => Ok(Self::Block),
+            "reject" => Ok(Self::Reject),
+            "drop_oldest" => Ok(Self::DropOldest),
+            _ => Err(format!("unknown policy `{s}`, expected block, reject or drop_oldest")),
+        }
+    }
+}
+
+impl fmt::Display for FullPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Block => "block",
+            Self::Reject => "reject",
+            Self::DropOldest => "drop_oldest",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboundConfig {
+    capacity: usize,
+    block_timeout: Duration,
+    policy: FullPolicy,
+    /// Sessions with a policy of their own.
+    session_policies: HashMap<String, FullPolicy>,
+}
+
+impl Default for OutboundConfig {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_CAPACITY,
+            block_timeout: Duration::from_millis(DEFAULT_BLOCK_TIMEOUT_MS),
+            policy: FullPolicy::default(),
+            session_policies: HashMap::new(),
+        }
+    }
+}
+
+impl OutboundConfig {
+    /// Reads `FIX_OUTBOUND_CAPACITY`, `FIX_OUTBOUND_BLOCK_TIMEOUT_MS` and
+    /// `FIX_OUTBOUND_FULL_POLICY`, e.g. `block,ALICE->MARKET_DATA=drop_oldest`.
+    pub fn from_env() -> Self {
+        let var = |name: &str| {
+            let value = std::env::var(name).ok()?;
+            value.trim().parse::<u64>().ok()
+        };
+        let mut config = Self::default();
+        if let Some(capacity) = var("FIX_OUTBOUND_CAPACITY").filter(|&c| c > 0) {
+            config.capacity = capacity as usize;
+        }
+        if let Some(ms) = var("FIX_OUTBOUND_BLOCK_TIMEOUT_MS") {
+            config.block_timeout = Duration::from_millis(ms);
+        }
+        if let Ok(spec) = std::env::var("FIX_OUTBOUND_FULL_POLICY") {
+            config.set_policies(&spec);
+        }
+        config
+    }
+
+    /// Applies a policy spec: a policy for every session, a
+    /// `SESSION_ID=policy` entry for one, or a comma-separated mix.
+    fn set_policies(&mut self, spec: &str) {
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let parsed = match entry.split_once('=') {
+                Some((id, policy)) => policy.trim().parse().map(|policy| {
+                    self.session_policies.insert(id.trim().to_string(), policy);
+                }),
+                None => entry.parse().map(|policy| self.policy = policy),
+            };
+            if let Err(e) = parsed {
+                warn!(entry, error = %e, "Ignoring malformed FIX_OUTBOUND_FULL_POLICY entry");
+            }
+        }
+    }
+
+    fn policy_for(&self, session_id: &str) -> FullPolicy {
+        self.session_policies
+            .get(session_id)
+            .copied()
+            .unwrap_or(self.policy)
+    }
+
+    /// The settings in a line, for the timeline.
+    pub fn describe(&self) -> String {
+        let mut sessions: Vec<String> = self
+            .session_policies
+            .iter()
+            .map(|(id, policy)| format!("{id}={policy}"))
+            .collect();
+        sessions.sort();
+        format!(
+            "outbound channels capacity={} policy={} block_timeout_ms={} sessions=[{}]",
+            self.capacity,
+            self.policy,
+            self.block_timeout.as_millis(),
+            sessions.join(",")
+        )
+    }
+}
+
+/// A session's channel had no room for a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelFull {
+    pub capacity: usize,
+    pub policy: FullPolicy,
+}
+
+impl fmt::Display for ChannelFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "outbound channel is full ({} messages, policy {})",
+            self.capacity, self.policy
+        )
+    }
+}
+
+/// A session's channel, as `/stats` shows it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ChannelStats {
+    /// Messages waiting for the writer.
+    pub depth: usize,
+    pub capacity: usize,
+    pub policy: FullPolicy,
+    /// The most messages that have waited at once.
+    pub high_water: usize,
+    /// Messages dropped by `drop_oldest` to make room.
+    pub dropped: u64,
+    /// Sends failed for want of room.
+    pub rejected: u64,
+}
+
+#[derive(Debug, Default)]
+struct Frames {
+    queue: VecDeque<Bytes>,
+    /// Slots taken by sends still encoding their message.
+    reserved: usize,
+    high_water: usize,
+}
+
+#[derive(Debug)]
+pub struct Channel {
+    capacity: usize,
+    policy: FullPolicy,
+    block_timeout: Duration,
+    frames: Mutex<Frames>,
+    /// Signalled when a slot is given up or a message taken.
+    space: Notify,
+    /// Signalled when a message is queued.
+    queued: Notify,
+    dropped: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl Channel {
+    fn new(capacity: usize, policy: FullPolicy, block_timeout: Duration) -> Self {
+        Self {
+            capacity,
+            policy,
+            block_timeout,
+            frames: Mutex::default(),
+            space: Notify::new(),
+            queued: Notify::new(),
+            dropped: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    fn frames(&self) -> MutexGuard<'_, Frames> {
+        self.frames.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Takes a slot for a message, applying the policy if there is none.
+    pub async fn reserve(self: &Arc<Self>) -> Result<Slot, ChannelFull> {
+        let deadline = Instant::now() + self.block_timeout;
+        loop {
+            // Registered before looking, so room made in between wakes it.
+            let space = self.space.notified();
+            tokio::pin!(space);
+            space.as_mut().enable();
+            if self.take_slot()? {
+                return Ok(Slot(Some(self.clone())));
+            }
+            if tokio::time::timeout_at(deadline, space).await.is_err() {
+                return Err(self.full());
+            }
+        }
+    }
+
+    /// Whether a slot was taken; `false` to wait for one.
+    fn take_slot(&self) -> Result<bool, ChannelFull> {
+        let mut frames = self.frames();
+        if frames.queue.len() + frames.reserved < self.capacity {
+            frames.reserved += 1;
+            return Ok(true);
+        }
+        match self.policy {
+            FullPolicy::Reject => Err(self.full()),
+            FullPolicy::DropOldest if frames.queue.pop_front().is_some() => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                frames.reserved += 1;
+                Ok(true)
+            }
+            // Only sends still encoding hold the slots, and not for long.
+            FullPolicy::DropOldest | FullPolicy::Block => Ok(false),
+        }
+    }
+
+    fn full(&self) -> ChannelFull {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+        ChannelFull {
+            capacity: self.capacity,
+            policy: self.policy,
+        }
+    }
+
+    /// The oldest message waiting, once there is one.
+    async fn next(&self) -> Bytes {
+        loop {
+            let queued = self.queued.notified();
+            tokio::pin!(queued);
+            queued.as_mut().enable();
+            let frame = self.frames().queue.pop_front();
+            if let Some(frame) = frame {
+                self.space.notify_one();
+                return frame;
+            }
+            queued.await;
+        }
+    }
+
+    fn stats(&self) -> ChannelStats {
+        let frames = self.frames();
+        ChannelStats {
+            depth: frames.queue.len(),
+            capacity: self.capacity,
+            policy: self.policy,
+            high_water: frames.high_water,
+            dropped: self.dropped.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A place in a channel taken for a message, given back if dropped unused.
+#[derive(Debug)]
+pub struct Slot(Option<Arc<Channel>>);
+
+impl Slot {
+    /// Queues the message for the writer.
+    pub fn send(mut self, frame: Bytes) {
+        let Some(channel) = self.0.take() else {
+            return;
+        };
+        {
+            let mut frames = channel.frames();
+            frames.reserved -= 1;
+            frames.queue.push_back(frame);
+            frames.high_water = frames.high_water.max(frames.queue.len());
+        }
+        channel.queued.notify_one();
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        if let Some(channel) = self.0.take() {
+            channel.frames().reserved -= 1;
+            channel.space.notify_one();
+        }
+    }
+}
+
+/// Writes the channel's messages to `transport` in order, until a write
+/// fails.
+async fn write(
+    channel: Arc<Channel>,
+    session_id: String,
+    mut transport: impl AsyncWrite + Unpin,
+) {
+    loop {
+        let frame = channel.next().await;
+        if let Err(e) = transport.write_all(&frame).await {
+            warn!(session_id = %session_id, error = %e, "Outbound write failed, writer stopped");
+            return;
+        }
+    }
+}
+
+/// The sessions' channels, each opened with its writer on first use.
+#[derive(Debug, Default)]
+pub struct OutboundChannels {
+    config: OutboundConfig,
+    channels: DashMap<String, Arc<Channel>>,
+}
+
+impl OutboundChannels {
+    pub fn new(config: OutboundConfig) -> Self {
+        Self {
+            config,
+            channels: DashMap::new(),
+        }
+    }
+
+    fn channel(&self, session_id: &str) -> Arc<Channel> {
+        // Only the first send on a session pays for its ID.
+        if let Some(channel) = self.channels.get(session_id) {
+            return channel.clone();
+        }
+        self.channels
+            .entry(session_id.to_string())
+            .or_insert_with(|| {
+                let config = &self.config;
+                let policy = config.policy_for(session_id);
+                let channel = Arc::new(Channel::new(config.capacity, policy, config.block_timeout));
+                let writer = write(channel.clone(), session_id.to_string(), tokio::io::sink());
+                tokio::spawn(writer);
+                channel
+            })
+            .clone()
+    }
+
+    /// Takes a slot in the session's channel; see [`Channel::reserve`].
+    pub async fn reserve(&self, session_id: &str) -> Result<Slot, ChannelFull> {
+        let channel = self.channel(session_id);
+        channel.reserve().await
+    }
+
+    /// The session's channel, if anything has been sent on it.
+    pub fn stats(&self, session_id: &str) -> Option<ChannelStats> {
+        self.channels.get(session_id).map(|channel| channel.stats())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(capacity: usize, policy: FullPolicy, timeout_ms: u64) -> Arc<Channel> {
+        Arc::new(Channel::new(capacity, policy, Duration::from_millis(timeout_ms)))
+    }
+
+    async fn send(channel: &Arc<Channel>, frame: &'static str) -> Result<(), ChannelFull> {
+        channel.reserve().await?.send(Bytes::from(frame));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn full_channels_reject_or_drop_the_oldest() {
+        let rejecting = channel(2, FullPolicy::Reject, 0);
+        send(&rejecting, "1").await.unwrap();
+        send(&rejecting, "2").await.unwrap();
+        let full = send(&rejecting, "3").await.unwrap_err();
+        assert_eq!(full.to_string(), "outbound channel is full (2 messages, policy reject)");
+        assert_eq!(rejecting.next().await, "1");
+        send(&rejecting, "3").await.unwrap();
+
+        let dropping = channel(2, FullPolicy::DropOldest, 0);
+        for frame in ["1", "2", "3"] {
+            send(&dropping, frame).await.unwrap();
+        }
+        assert_eq!(dropping.next().await, "2");
+        let stats = dropping.stats();
+        assert_eq!((stats.depth, stats.high_water, stats.dropped), (1, 2, 1));
+        assert_eq!((rejecting.stats().rejected, stats.rejected), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn blocked_sends_wait_for_the_writer() {
+        let blocking = channel(1, FullPolicy::Block, 20);
+        send(&blocking, "1").await.unwrap();
+        assert!(send(&blocking, "2").await.is_err(), "times out");
+
+        // A slot given up unused makes room too.
+        let unused = channel(1, FullPolicy::Block, 1000);
+        let slot = unused.reserve().await.unwrap();
+        tokio::spawn(async move { drop(slot) });
+        send(&unused, "1").await.unwrap();
+
+        let blocking = channel(1, FullPolicy::Block, 1000);
+        send(&blocking, "1").await.unwrap();
+        let writer = blocking.clone();
+        tokio::spawn(async move { writer.next().await });
+        send(&blocking, "2").await.unwrap();
+        assert_eq!(blocking.stats().rejected, 0);
+    }
+
+    #[test]
+    fn policies_are_set_for_all_sessions_or_one() {
+        let mut config = OutboundConfig::default();
+        config.set_policies("reject, ALICE->MARKET_DATA=drop_oldest, ALICE->BROKER_B=later");
+        assert_eq!(config.policy_for("ALICE->BROKER_A"), FullPolicy::Reject);
+        assert_eq!(config.policy_for("ALICE->MARKET_DATA"), FullPolicy::DropOldest);
+        assert_eq!(config.policy_for("ALICE->BROKER_B"), FullPolicy::Reject);
+        assert_eq!(
+            config.describe(),
+            "outbound channels capacity=10000 policy=reject block_timeout_ms=1000 \
+             sessions=[ALICE->MARKET_DATA=drop_oldest]"
+        );
+    }
+}
@@ -0,0 +1,411 @@
+//! Outbound queue for sessions that are not logged on.
+//!
+//! `/send` queues messages for a known session whose state is not `ACTIVE`
+//! (or whose queue is not yet empty, so order is kept) instead of sending
+//! them. When the session becomes `ACTIVE` again the queue is transmitted in
+//! order through the normal send path, which assigns sequence numbers at that
+//! point. With `FIX_STORE_DIR` set, each session's queue is kept in
+//! `<session>.queue` there, rewritten on every change, so queued messages
+//! survive a restart.
+
+use axum::{
+    extract::{Path as UrlPath, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    attribution::Attribution,
+    audit::{self, Actor, AuditAction},
+    clock::epoch_ms,
+    records, store, AppState, Groups,
+};
+
+const DEFAULT_MAX_DEPTH: usize = 1000;
+
+/// A `/send` request held until its session is logged on.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct QueuedMessage {
+    pub queue_id: String,
+    pub session_id: String,
+    pub msg_type: String,
+    pub fields: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schema(value_type = BTreeMap<String, Vec<HashMap<String, String>>>)]
+    pub groups: Groups,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+    #[serde(default)]
+    pub reply_to: Option<String>,
+    /// The request's TTL, which starts when the message is sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_ms: Option<u64>,
+    /// The request's `?validate=` and `?strict=`, applied when it is sent.
+    #[serde(default)]
+    pub validate: bool,
+    #[serde(default)]
+    pub strict: Option<bool>,
+    pub queued_at_ms: u64,
+    /// Who asked for the message to be sent, stored with it when it is.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<Attribution>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueFull(pub usize);
+
+impl fmt::Display for QueueFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "outbound queue is full ({} messages)", self.0)
+    }
+}
+
+#[derive(Default)]
+pub struct OutboundQueue {
+    by_session: HashMap<String, VecDeque<QueuedMessage>>,
+    max_depth: usize,
+    dir: Option<PathBuf>,
+    next_id: u64,
+}
+
+impl OutboundQueue {
+    /// Reads `FIX_OUTBOUND_QUEUE_DEPTH` and loads queues left in `FIX_STORE_DIR`.
+    pub fn from_env() -> io::Result<Self> {
+        let max_depth = std::env::var("FIX_OUTBOUND_QUEUE_DEPTH")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&d| d > 0)
+            .unwrap_or(DEFAULT_MAX_DEPTH);
+        let dir = std::env::var("FIX_STORE_DIR")
+            .ok()
+            .filter(|d| !d.trim().is_empty())
+            .map(|d| PathBuf::from(d.trim()));
+        let mut queue = Self {
+            max_depth,
+            ..Self::default()
+        };
+        if let Some(dir) = dir {
+            queue.open(dir)?;
+        }
+        Ok(queue)
+    }
+
+    fn open(&mut self, dir: PathBuf) -> io::Result<()> {
+        fs::create_dir_all(&dir)?;
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "queue") {
+                for message in load_queue(&path)? {
+                    self.by_session
+                        .entry(message.session_id.clone())
+                        .or_default()
+                        .push_back(message);
+                }
+            }
+        }
+        self.next_id = self
+            .by_session
+            .values()
+            .flatten()
+            .filter_map(|m| m.queue_id.strip_prefix("Q-")?.parse().ok())
+            .max()
+            .unwrap_or(0);
+        self.dir = Some(dir);
+        Ok(())
+    }
+
+    /// Messages queued across all sessions.
+    pub fn len(&self) -> usize {
+        self.by_session.values().map(VecDeque::len).sum()
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// The session with the most messages waiting, and how many.
+    pub fn deepest(&self) -> Option<(&str, usize)> {
+        self.by_session
+            .iter()
+            .map(|(id, q)| (id.as_str(), q.len()))
+            .filter(|&(_, depth)| depth > 0)
+            .max_by_key(|&(_, depth)| depth)
+    }
+
+    /// Sessions with messages waiting.
+    pub fn sessions(&self) -> Vec<String> {
+        self.by_session
+            .iter()
+            .filter(|(_, q)| !q.is_empty())
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    pub fn is_empty_for(&self, session_id: &str) -> bool {
+        self.by_session
+            .get(session_id)
+            .is_none_or(VecDeque::is_empty)
+    }
+
+    /// Queues a message, assigning its `queue_id`, and returns that ID and
+    /// its 1-based position in the session's queue.
+    pub fn push(&mut self, mut message: QueuedMessage) -> Result<(String, usize), QueueFull> {
+        let queue = self
+            .by_session
+            .entry(message.session_id.clone())
+            .or_default();
+        if queue.len() >= self.max_depth {
+            return Err(QueueFull(self.max_depth));
+        }
+        self.next_id += 1;
+        message.queue_id = format!("Q-{}", self.next_id);
+        let (queue_id, session_id) = (message.queue_id.clone(), message.session_id.clone());
+        queue.push_back(message);
+        let position = queue.len();
+        self.persist(&session_id);
+        Ok((queue_id, position))
+    }
+
+    pub fn items(&self, session_id: &str) -> Vec<QueuedMessage> {
+        self.by_session
+            .get(session_id)
+            .map(|q| q.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Takes the next message to send off the front of a session's queue.
+    pub fn take_front(&mut self, session_id: &str) -> Option<QueuedMessage> {
+        let message = self.by_session.get_mut(session_id)?.pop_front()?;
+        self.persist(session_id);
+        Some(message)
+    }
+
+    /// Puts back a message taken with [`take_front`](Self::take_front) that
+    /// could not be sent.
+    pub fn restore_front(&mut self, message: QueuedMessage) {
+        let session_id = message.session_id.clone();
+        self.by_session
+            .entry(session_id.clone())
+            .or_default()
+            .push_front(message);
+        self.persist(&session_id);
+    }
+
+    /// Empties a session's queue, returning what it held.
+    pub fn drain(&mut self, session_id: &str) -> Vec<QueuedMessage> {
+        let drained = self
+            .by_session
+            .remove(session_id)
+            .map(Vec::from)
+            .unwrap_or_default();
+        self.persist(session_id);
+        drained
+    }
+
+    /// Rewrites a session's queue file, or removes it once the queue is
+    /// empty. The new contents are written to a temporary file and renamed
+    /// over the old one, so a crash leaves one version or the other.
+    fn persist(&self, session_id: &str) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let path = dir.join(format!("{}.queue", store::file_stem(session_id)));
+        let result = match self.by_session.get(session_id).filter(|q| !q.is_empty()) {
+            Some(queue) => write_queue(&path, queue),
+            None if path.exists() => fs::remove_file(&path),
+            None => Ok(()),
+        };
+        if let Err(e) = result {
+            warn!(session_id = %session_id, error = %e, "Failed to persist outbound queue");
+        }
+    }
+}
+
+fn write_queue(path: &Path, queue: &VecDeque<QueuedMessage>) -> io::Result<()> {
+    let tmp = path.with_extension("queue.tmp");
+    let mut file = File::create(&tmp)?;
+    for message in queue {
+        let mut line = records::encode(message).to_string();
+        line.push('\n');
+        file.write_all(line.as_bytes())?;
+    }
+    file.sync_data()?;
+    fs::rename(tmp, path)
+}
+
+fn load_queue(path: &Path) -> io::Result<Vec<QueuedMessage>> {
+    let mut messages = Vec::new();
+    for (n, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line)
+            .map_err(|e| records::RecordError::Malformed(e.to_string()))
+            .and_then(records::decode::<QueuedMessage>)
+        {
+            Ok(message) => messages.push(message),
+            Err(e) => warn!(
+                path = %path.display(),
+                line = n + 1,
+                error = %e,
+                "Skipping unreadable queued message"
+            ),
+        }
+    }
+    info!(path = %path.display(), messages = messages.len(), "Outbound queue loaded");
+    Ok(messages)
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QueueResponse {
+    session_id: String,
+    items: Vec<QueuedMessage>,
+    count: usize,
+    max_depth: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/fix/sessions/{session_id}/queue",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "The session ID, e.g. `ALICE->BROKER_A`.")),
+    responses(
+        (status = 200, description = "OK", body = QueueResponse),
+    )
+)]
+pub async fn list(
+    State(state): State<AppState>,
+    UrlPath(session_id): UrlPath<String>,
+) -> Json<QueueResponse> {
+    let queue = state.outbound_queue.lock().await;
+    let items = queue.items(&session_id);
+    let count = items.len();
+    Json(QueueResponse {
+        session_id,
+        items,
+        count,
+        max_depth: queue.max_depth(),
+    })
+}
+
+/// Discards a session's queued messages without sending them.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/fix/sessions/{session_id}/queue",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "The session ID, e.g. `ALICE->BROKER_A`.")),
+    responses(
+        (status = 200, description = "The discarded messages", body = QueueResponse),
+    )
+)]
+pub async fn drain(
+    State(state): State<AppState>,
+    actor: Actor,
+    UrlPath(session_id): UrlPath<String>,
+) -> Json<QueueResponse> {
+    let mut queue = state.outbound_queue.lock().await;
+    let items = queue.drain(&session_id);
+    let mut deliveries = state.deliveries.lock().await;
+    for item in &items {
+        deliveries.queue_dropped(&item.queue_id, StatusCode::GONE, epoch_ms());
+    }
+    drop(deliveries);
+    let count = items.len();
+    info!(session_id = %session_id, count, "Outbound queue drained");
+    audit::record(
+        &state,
+        &actor,
+        AuditAction::QueueDrained,
+        Some(&session_id),
+        format!("{count} messages discarded"),
+    )
+    .await;
+    Json(QueueResponse {
+        session_id,
+        items,
+        count,
+        max_depth: queue.max_depth(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(session_id: &str, cl_ord_id: &str) -> QueuedMessage {
+        QueuedMessage {
+            queue_id: String::new(),
+            session_id: session_id.to_string(),
+            msg_type: "NewOrderSingle".to_string(),
+            fields: HashMap::from([("11".to_string(), cl_ord_id.to_string())]),
+            groups: Groups::new(),
+            metadata: HashMap::new(),
+            reply_to: None,
+            ttl_ms: None,
+            validate: false,
+            strict: None,
+            queued_at_ms: 0,
+            attribution: None,
+        }
+    }
+
+    #[test]
+    fn queues_survive_reopen_in_order() {
+        let dir = std::env::temp_dir().join(format!("fix-queue-{}", uuid::Uuid::new_v4()));
+        let mut queue = OutboundQueue {
+            max_depth: 2,
+            ..OutboundQueue::default()
+        };
+        queue.open(dir.clone()).unwrap();
+        assert_eq!(
+            queue.push(queued("ALICE->BROKER_A", "O1")).map(|r| r.1),
+            Ok(1)
+        );
+        assert_eq!(
+            queue.push(queued("ALICE->BROKER_A", "O2")).map(|r| r.1),
+            Ok(2)
+        );
+        assert_eq!(
+            queue.push(queued("ALICE->BROKER_A", "O3")).map(|r| r.1),
+            Err(QueueFull(2))
+        );
+        assert_eq!(
+            queue.push(queued("ALICE->BROKER_B", "O4")).map(|r| r.1),
+            Ok(1)
+        );
+        let first = queue.take_front("ALICE->BROKER_A").unwrap();
+        assert_eq!(first.fields["11"], "O1");
+
+        let mut reopened = OutboundQueue {
+            max_depth: 2,
+            ..OutboundQueue::default()
+        };
+        reopened.open(dir.clone()).unwrap();
+        let items = reopened.items("ALICE->BROKER_A");
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].queue_id, "Q-2");
+        assert_eq!(items[0].fields["11"], "O2");
+        assert_eq!(reopened.len(), 2);
+        assert_eq!(
+            reopened.push(queued("ALICE->BROKER_A", "O5")),
+            Ok(("Q-4".to_string(), 2))
+        );
+
+        reopened.drain("ALICE->BROKER_B");
+        assert!(!dir.join("ALICE-_BROKER_B.queue").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,145 @@
+//! `config check` (or `--check-config`): checking the configuration without
+//! starting the engine.
+//!
+//! Everything startup would stop at is checked — the configuration file and
+//! its `ALICE_FIX__*` overrides, the listen addresses, data dictionaries,
+//! secrets, TLS certificates and keys, API keys, OIDC, rule packs and rate
+//! and request limits — and reported together, one line each. The process exits `0` if all passed and `1` otherwise.
+//! Nothing is bound or written, so a deploy can check a host's
+//! configuration before restarting the engine on it.
+
+use std::net::SocketAddr;
+
+use crate::{
+    auth::ApiKeys,
+    config::{Config, Source},
+    dictionary,
+    limits::LimitsConfig,
+    oidc::OidcConfig,
+    rate_limit::RateLimitConfig,
+    rules::RulePacks,
+    secrets::Secrets,
+    seed_sessions,
+    tls::TlsConfig,
+};
+
+/// The outcome of one check: what was found, or what is wrong.
+type Check = (&'static str, Result<String, String>);
+
+/// Runs the checks and prints their outcomes; whether all passed.
+pub async fn run() -> bool {
+    let checks = checks().await;
+    for (name, outcome) in &checks {
+        match outcome {
+            Ok(found) => println!("ok    {name}: {found}"),
+            Err(problem) => println!("FAIL  {name}: {problem}"),
+        }
+    }
+    let failed = checks.iter().filter(|(_, outcome)| outcome.is_err()).count();
+    if failed == 0 {
+        println!("configuration OK");
+    } else {
+        println!("{failed} of {} checks failed", checks.len());
+    }
+    failed == 0
+}
+
+async fn checks() -> Vec<Check> {
+    let mut checks = Vec::new();
+    // Later checks go on with the defaults, so one mistake does not hide
+    // the others.
+    let config = Config::from_env();
+    let outcome = config.as_ref().map(Config::describe).map_err(String::clone);
+    checks.push(("config", outcome));
+    let config = config.unwrap_or_default();
+    checks.push(("listeners", listeners(&config)));
+    checks.push(("dictionaries", dictionaries(&config)));
+
+    let http = reqwest::Client::new();
+    let secrets = Secrets::from_env(&http).await;
+    let outcome = secrets.as_ref().map(Secrets::describe).map_err(String::clone);
+    checks.push(("secrets", outcome));
+    let secrets = secrets.unwrap_or_default();
+    let (mut set, mut unreadable) = (Vec::new(), Vec::new());
+    for name in ["FIX_FEDERATION_API_KEY", "FIX_ALERT_SECRET"] {
+        match secrets.var(name).await {
+            Ok(Some(_)) => set.push(name),
+            Ok(None) => {}
+            Err(e) => unreadable.push(e),
+        }
+    }
+    let references = if unreadable.is_empty() {
+        Ok(format!("set=[{}]", set.join(",")))
+    } else {
+        Err(unreadable.join("; "))
+    };
+    checks.push(("secret references", references));
+
+    let tls = match TlsConfig::from_env() {
+        Ok(Some(tls)) => tls.server_config(&secrets).await.map(|_| tls.describe()),
+        Ok(None) => Ok("tls off (plain HTTP)".to_string()),
+        Err(e) => Err(e),
+    };
+    checks.push(("tls", tls));
+
+    let api_keys = ApiKeys::from_env(config.auth.api_keys_file.as_deref());
+    checks.push(("api keys", api_keys.map(|keys| keys.describe())));
+    let oidc = OidcConfig::from_env(&config.auth.oidc);
+    let oidc_off = || "oidc off".to_string();
+    let oidc = oidc.map(|o| o.as_ref().map_or_else(oidc_off, OidcConfig::describe));
+    checks.push(("oidc", oidc));
+    let packs = RulePacks::reread().map(|packs| format!("rule packs={}", packs.len()));
+    checks.push(("rule packs", packs));
+    let rate_limit = RateLimitConfig::from_env(&config.rate_limit);
+    let off = || "rate limit off".to_string();
+    let rate_limit = rate_limit.map(|r| r.as_ref().map_or_else(off, RateLimitConfig::describe));
+    checks.push(("rate limit", rate_limit));
+    let limits = LimitsConfig::from_env().map(|limits| limits.describe());
+    checks.push(("request limits", limits));
+    checks
+}
+
+/// The addresses the APIs would listen on.
+fn listeners(config: &Config) -> Result<String, String> {
+    let addr = |set: Option<SocketAddr>, var: &str, default: &str| match set {
+        Some(addr) => Ok(addr),
+        None => {
+            let addr = std::env::var(var).unwrap_or_else(|_| default.to_string());
+            addr.parse::<SocketAddr>().map_err(|e| format!("{var} `{addr}`: {e}"))
+        }
+    };
+    let http = addr(config.listeners.http, "FIX_ADDR", "0.0.0.0:8081")?;
+    let grpc = addr(config.listeners.grpc, "FIX_GRPC_ADDR", "0.0.0.0:50051")?;
+    Ok(format!("http={http} grpc={grpc}"))
+}
+
+/// Parses the dictionaries of `FIX_DATA_DICTIONARIES` and of each session.
+fn dictionaries(config: &Config) -> Result<String, String> {
+    let versions = std::env::var("FIX_DATA_DICTIONARIES").unwrap_or_default();
+    let mut paths: Vec<(String, String)> = versions
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|path| ("FIX_DATA_DICTIONARIES".to_string(), path.to_string()))
+        .collect();
+    for session in seed_sessions(config) {
+        if let Some(path) = session.dictionary {
+            let origin = match session.sources["dictionary"] {
+                Source::Environment => "FIX_SESSION_DICTIONARIES",
+                _ => "config",
+            };
+            paths.push((format!("{origin} {}", session.session_id), path));
+        }
+    }
+    let problems: Vec<String> = paths
+        .iter()
+        .filter_map(|(origin, path)| {
+            let e = dictionary::load(path).err()?;
+            Some(format!("{origin}: {path}: {e}"))
+        })
+        .collect();
+    if !problems.is_empty() {
+        return Err(problems.join("; "));
+    }
+    Ok(format!("dictionary files={}", paths.len()))
+}
@@ -0,0 +1,222 @@
+//! Dry runs of `/send`.
+//!
+//! With `?dry_run=true`, `/send` builds the message as it would be sent now,
+//! with the fields the gateway fills in, its real BodyLength and CheckSum and
+//! the MsgSeqNum the next message will get. Fields given by name are written
+//! by tag, and header or trailer fields given by the caller are left out, as
+//! on a real send, so the preview is the wire message itself. It also validates the message
+//! against the session's profile. It allocates no sequence number, tracks and
+//! records nothing, and transmits nothing, so an OMS can run pre-flight
+//! checks. A message that fails validation gets the same `422` as a real
+//! send. Messages for federated targets are not forwarded upstream.
+
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tracing::{info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    build_fix_message, check_send, encode, find_session, fix_json, fix_json::OutputEncoding,
+    parse_raw, prepare, problem::ApiError, sending_time, session_id_of, AppState, Groups,
+    Outgoing, SendOptions, SendRequest, ValidationError,
+};
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PreviewOptions {
+    /// Build and validate the message without sending it.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// `/send` response with `?dry_run=true`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Preview {
+    session_id: String,
+    msg_type: String,
+    /// The MsgSeqNum the message would get if sent now. Taken by the next
+    /// message sent, so a later send may get another.
+    sequence_number: u64,
+    fix_message: String,
+    fields: HashMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    #[schema(value_type = BTreeMap<String, Vec<HashMap<String, String>>>)]
+    groups: Groups,
+    /// Validation warnings; in strict mode they fail the dry run instead.
+    warnings: Vec<ValidationError>,
+    /// The session is not logged on, so the message would be queued and
+    /// numbered once it is sent.
+    queued: bool,
+    /// The message in FIX JSON Encoding, with `?encoding=fix_json`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    fix_json: Option<serde_json::Value>,
+}
+
+/// Builds and validates the message `req` describes, as `/send` would send it.
+pub async fn preview(
+    state: &AppState,
+    opts: &SendOptions,
+    mut req: SendRequest,
+) -> Result<Preview, ApiError> {
+    check_send(&req)?;
+    let opts = SendOptions {
+        encoding: opts.encoding,
+        validate: true,
+        strict: opts.strict,
+    };
+    let queued = would_queue(state, &session_id_of(&req)).await;
+    let Outgoing {
+        session_id,
+        fix_version,
+        sender,
+        target,
+        msg_type_num,
+        fields,
+        groups,
+        body_fields,
+        group_fields,
+        warnings,
+    } = prepare(state, &opts, &mut req).await?;
+    let sequence_number = next_seq_num(state, &session_id).await?;
+    let header = encode::Header {
+        version: &fix_version,
+        msg_type: &msg_type_num,
+        sender: &sender,
+        target: &target,
+        seq_num: sequence_number,
+        sending_time: sending_time(state, &session_id),
+    };
+    let (fix_message, fix_json) = render(&header, &body_fields, &group_fields, opts.encoding);
+    info!(
+        session_id = %session_id,
+        msg_type = %req.msg_type,
+        sequence_number,
+        warnings = warnings.len(),
+        "FIX message previewed"
+    );
+    Ok(Preview {
+        session_id,
+        msg_type: req.msg_type,
+        sequence_number,
+        fix_message,
+        fields,
+        groups,
+        warnings,
+        queued,
+        fix_json,
+    })
+}
+
+/// The previewed message as `/send` would write it, and in FIX JSON if
+/// `encoding` asks for it.
+fn render(
+    header: &encode::Header<'_>,
+    body_fields: &[(u32, String)],
+    group_fields: &[(u32, String)],
+    encoding: OutputEncoding,
+) -> (String, Option<serde_json::Value>) {
+    let fix_message = build_fix_message(header, body_fields, group_fields);
+    let fix_json = (encoding == OutputEncoding::FixJson)
+        .then(|| fix_json::encode(&parse_raw(fix_message.as_bytes()).fields));
+    (fix_message, fix_json)
+}
+
+/// Whether `/send` would queue a message for the session rather than send it.
+async fn would_queue(state: &AppState, session_id: &str) -> bool {
+    let session = find_session(state, session_id);
+    let queue = state.outbound_queue.lock().await;
+    session.is_some_and(|s| s.state != "ACTIVE") || !queue.is_empty_for(session_id)
+}
+
+/// The MsgSeqNum the session's next message will get, without allocating it.
+async fn next_seq_num(state: &AppState, session_id: &str) -> Result<u64, StatusCode> {
+    let local = state
+        .sessions
+        .get(session_id)
+        .map_or(0, |handle| handle.last_seq_num());
+    let last = match &state.session_state {
+        Some(store) => {
+            let id = session_id.to_string();
+            let last = store.blocking(move |store| store.last_seq_num(&id)).await;
+            last.map_err(|e| {
+                warn!(error = %e, "Sequence number lookup failed");
+                StatusCode::SERVICE_UNAVAILABLE
+            })?
+        }
+        None => 0,
+    };
+    Ok(local.max(last) + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::TimestampPrecision, dictionary::Dictionaries, wire_fields};
+    use std::time::Duration;
+
+    fn header() -> encode::Header<'static> {
+        encode::Header {
+            version: "FIX.4.4",
+            msg_type: "D",
+            sender: "ALICE",
+            target: "BROKER_A",
+            seq_num: 42,
+            sending_time: encode::SendingTime::at(
+                Duration::from_secs(1_771_839_000),
+                TimestampPrecision::Millis,
+            ),
+        }
+    }
+
+    fn fields(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn previews_are_wire_messages() {
+        let dict = Dictionaries::from_env().get("FIX.4.4").unwrap();
+        // Named fields, and header and trailer fields a caller tried to set.
+        let given = fields(&[
+            ("ClOrdID", "ORD-1"),
+            ("Symbol", "AAPL"),
+            ("Side", "1"),
+            ("OrderQty", "10"),
+            ("OrdType", "2"),
+            ("Price", "1.5"),
+            ("BodyLength", "5"),
+            ("10", "000"),
+            ("35", "8"),
+            ("BeginString", "FIX.4.2"),
+        ]);
+        let body = encode::body_fields(Some(&dict), &given).unwrap();
+        let (fix_message, fix_json) = render(&header(), &body, &[], OutputEncoding::FixJson);
+
+        let (parsed, errors) = wire_fields(fix_message.as_bytes());
+        assert!(errors.is_empty(), "{errors:?}");
+        let tags: Vec<u32> = parsed.iter().map(|(tag, _)| *tag).collect();
+        assert_eq!(tags, [8, 9, 35, 49, 56, 34, 52, 11, 38, 40, 44, 54, 55, 10]);
+        assert_eq!(parsed[0].1, "FIX.4.4");
+        assert_eq!(parsed[2].1, "D");
+        assert_ne!(parsed[1].1, "5");
+
+        let fix_json = fix_json.unwrap();
+        assert_eq!(fix_json["Header"]["MsgSeqNum"], "42");
+        assert_eq!(fix_json["Body"]["Symbol"], "AAPL");
+        assert_eq!(fix_json["Body"]["Price"], "1.5");
+    }
+
+    #[test]
+    fn groups_follow_the_body_and_fix_json_is_only_on_request() {
+        let body = encode::body_fields(None, &fields(&[("55", "AAPL"), ("1", "ACC1")])).unwrap();
+        let groups = [(453, "1".to_string()), (448, "DESK".to_string()), (447, "D".to_string())];
+        let (fix_message, fix_json) = render(&header(), &body, &groups, OutputEncoding::Native);
+
+        assert!(fix_json.is_none());
+        let (parsed, errors) = wire_fields(fix_message.as_bytes());
+        assert!(errors.is_empty(), "{errors:?}");
+        let body: Vec<u32> = parsed[7..].iter().map(|(tag, _)| *tag).collect();
+        assert_eq!(body, [1, 55, 453, 448, 447, 10]);
+    }
+}
@@ -0,0 +1,399 @@
+//! Error responses as RFC 7807 problem details.
+//!
+//! Every failed request is answered with an `application/problem+json` body:
+//!
+//! ```json
+//! { "type": "urn:alice-fix:error:validation_failed", "title": "Unprocessable Entity",
+//!   "status": 422, "code": "validation_failed", "detail": "1 validation error",
+//!   "errors": [ ... ], "instance": "/api/v1/fix/send", "correlation_id": "..." }
+//! ```
+//!
+//! Handlers that know why a request failed return an [`ApiError`] with a
+//! specific code, a detail message and, for validation failures, the
+//! field-level errors, and any extension members, such as the readiness
+//! probe's `checks`. The [`problem_details`] middleware gives each request a
+//! correlation ID, adds it and the request path to problem bodies, and turns
+//! the bare status codes of the remaining handlers and axum's extractor
+//! rejections into problem bodies too.
+//!
+//! The correlation ID is also the request ID: tower-http's request ID layers
+//! take it from `X-Request-ID`, or `X-Correlation-ID`, or generate one, and
+//! return it in both headers. It is a field of the request's log span, so
+//! every log line written while handling the request carries it.
+
+use axum::{
+    body::{self, Body},
+    extract::Request,
+    http::{self, header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::{borrow::Cow, future::Future};
+use tower_http::request_id::{MakeRequestId, RequestId};
+use utoipa::ToSchema;
+
+use crate::ValidationError;
+
+pub const CORRELATION_HEADER: &str = "x-correlation-id";
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+pub const CONTENT_TYPE: &str = "application/problem+json";
+
+/// Error bodies larger than this are not read back into a problem.
+const MAX_ERROR_BODY: usize = 64 * 1024;
+const MAX_CORRELATION_ID_LEN: usize = 128;
+
+/// An RFC 7807 problem details body.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Problem {
+    /// `urn:alice-fix:error:<code>`.
+    #[serde(rename = "type")]
+    kind: String,
+    /// The status's reason phrase.
+    title: String,
+    status: u16,
+    /// A stable, machine-readable error code, e.g. `validation_failed`.
+    code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    /// Field-level validation errors.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<ValidationError>,
+    /// The request path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instance: Option<String>,
+    /// Also returned in the `X-Correlation-ID` header and logged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    correlation_id: Option<String>,
+}
+
+/// The correlation ID of the request being handled, in its extensions.
+#[derive(Debug, Clone)]
+pub struct CorrelationId(pub String);
+
+tokio::task_local! {
+    static CURRENT: String;
+}
+
+/// The correlation ID of the request being handled, outside a handler.
+pub fn current_correlation_id() -> Option<String> {
+    CURRENT.try_with(Clone::clone).ok()
+}
+
+/// Runs `task` under the correlation ID `id`, if there is one, as a
+/// session's task does with the sends it is handed.
+pub async fn correlated<F: Future>(id: Option<String>, task: F) -> F::Output {
+    match id {
+        Some(id) => CURRENT.scope(id, task).await,
+        None => task.await,
+    }
+}
+
+/// A failed request.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    code: Cow<'static, str>,
+    detail: Option<String>,
+    errors: Vec<ValidationError>,
+    extensions: serde_json::Map<String, serde_json::Value>,
+    /// Seconds for the `Retry-After` header.
+    retry_after: Option<u64>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str) -> Self {
+        Self {
+            status,
+            code: Cow::Borrowed(code),
+            detail: None,
+            errors: Vec::new(),
+            extensions: serde_json::Map::new(),
+            retry_after: None,
+        }
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn errors(mut self, errors: Vec<ValidationError>) -> Self {
+        self.errors = errors;
+        self
+    }
+
+    /// Adds a member to the problem beyond the standard ones; it cannot
+    /// replace them.
+    pub fn extension(mut self, name: &'static str, value: impl Serialize) -> Self {
+        if let Ok(value) = serde_json::to_value(value) {
+            self.extensions.insert(name.to_string(), value);
+        }
+        self
+    }
+
+    /// Answers with `Retry-After`, and the same seconds as `retry_after_secs`.
+    pub fn retry_after(mut self, secs: u64) -> Self {
+        self.retry_after = Some(secs);
+        self.extension("retry_after_secs", secs)
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.detail.as_deref()
+    }
+
+    fn problem(self) -> Problem {
+        Problem {
+            kind: format!("urn:alice-fix:error:{}", self.code),
+            title: self.status.canonical_reason().unwrap_or("Error").to_string(),
+            status: self.status.as_u16(),
+            code: self.code.into_owned(),
+            detail: self.detail,
+            errors: self.errors,
+            instance: None,
+            correlation_id: None,
+        }
+    }
+}
+
+/// A bare status code gets the code named after its reason phrase, e.g.
+/// `not_found`.
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        Self {
+            status,
+            code: Cow::Owned(default_code(status)),
+            detail: None,
+            errors: Vec::new(),
+            extensions: serde_json::Map::new(),
+            retry_after: None,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(mut self) -> Response {
+        if let Some(secs) = self.retry_after.take() {
+            return ([(header::RETRY_AFTER, secs.to_string())], self).into_response();
+        }
+        let status = self.status;
+        let extensions = std::mem::take(&mut self.extensions);
+        if extensions.is_empty() {
+            return problem_response(status, &self.problem());
+        }
+        match serde_json::to_value(self.problem()) {
+            Ok(serde_json::Value::Object(mut problem)) => {
+                for (name, value) in extensions {
+                    problem.entry(name).or_insert(value);
+                }
+                problem_response(status, &problem)
+            }
+            _ => status.into_response(),
+        }
+    }
+}
+
+fn default_code(status: StatusCode) -> String {
+    status
+        .canonical_reason()
+        .unwrap_or("error")
+        .to_ascii_lowercase()
+        .replace([' ', '-'], "_")
+}
+
+fn problem_response(status: StatusCode, problem: &impl Serialize) -> Response {
+    match serde_json::to_vec(problem) {
+        Ok(json) => (status, [(header::CONTENT_TYPE, CONTENT_TYPE)], json).into_response(),
+        Err(_) => status.into_response(),
+    }
+}
+
+fn header_id<B>(req: &http::Request<B>, name: &str) -> Option<String> {
+    req.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_CORRELATION_ID_LEN)
+        .map(str::to_string)
+}
+
+/// Request IDs for tower-http's `SetRequestIdLayer`: the caller's
+/// `X-Correlation-ID`, or a new one.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MakeCorrelationId;
+
+impl MakeRequestId for MakeCorrelationId {
+    fn make_request_id<B>(&mut self, req: &http::Request<B>) -> Option<RequestId> {
+        let id = header_id(req, CORRELATION_HEADER)
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        HeaderValue::from_str(&id).ok().map(RequestId::new)
+    }
+}
+
+/// The request ID, or a new one if the caller's is unusable.
+fn correlation_id(req: &Request) -> String {
+    header_id(req, REQUEST_ID_HEADER)
+        .or_else(|| header_id(req, CORRELATION_HEADER))
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// Middleware giving each request a correlation ID and each error response a
+/// problem details body.
+pub async fn problem_details(mut req: Request, next: Next) -> Response {
+    let id = correlation_id(&req);
+    let path = req.uri().path().to_string();
+    req.extensions_mut().insert(CorrelationId(id.clone()));
+
+    let response = CURRENT.scope(id.clone(), next.run(req)).await;
+    let status = response.status();
+    let mut response = if status.is_client_error() || status.is_server_error() {
+        complete(response, &path, &id).await
+    } else {
+        response
+    };
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(CORRELATION_HEADER, value);
+    }
+    response
+}
+
+/// Adds the request path and correlation ID to an error response's problem,
+/// making one from the status and any plain-text message if the handler did
+/// not.
+async fn complete(response: Response, path: &str, id: &str) -> Response {
+    let (parts, body) = response.into_parts();
+    let status = parts.status;
+    let is_problem = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes() == CONTENT_TYPE.as_bytes());
+    let Ok(bytes) = body::to_bytes(body, MAX_ERROR_BODY).await else {
+        return Response::from_parts(parts, Body::empty());
+    };
+
+    let mut problem = if is_problem {
+        match serde_json::from_slice::<serde_json::Value>(&bytes) {
+            Ok(serde_json::Value::Object(problem)) => problem,
+            _ => return Response::from_parts(parts, Body::from(bytes)),
+        }
+    } else {
+        let text = String::from_utf8_lossy(&bytes).trim().to_string();
+        let mut error = ApiError::from(status);
+        if !text.is_empty() {
+            error = error.detail(text);
+        }
+        match serde_json::to_value(error.problem()) {
+            Ok(serde_json::Value::Object(problem)) => problem,
+            _ => return Response::from_parts(parts, Body::from(bytes)),
+        }
+    };
+    problem.insert("instance".to_string(), path.into());
+    problem.insert("correlation_id".to_string(), id.into());
+
+    let mut response = problem_response(status, &problem);
+    for (name, value) in &parts.headers {
+        if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+            response.headers_mut().insert(name, value.clone());
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorCategory;
+
+    async fn json(response: Response) -> serde_json::Value {
+        let bytes = body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn errors_render_as_problems() {
+        let error = ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "validation_failed")
+            .detail("1 validation error")
+            .errors(vec![ValidationError {
+                field: "OrderQty".to_string(),
+                tag: 38,
+                message: "NewOrderSingle requires OrderQty (tag 38).".to_string(),
+                category: ErrorCategory::Required,
+                path: None,
+            }]);
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], CONTENT_TYPE);
+        let body = json(response).await;
+        assert_eq!(body["type"], "urn:alice-fix:error:validation_failed");
+        assert_eq!(body["title"], "Unprocessable Entity");
+        assert_eq!(body["errors"][0]["tag"], 38);
+
+        assert_eq!(ApiError::from(StatusCode::NOT_FOUND).code, "not_found");
+        assert_eq!(
+            ApiError::from(StatusCode::INTERNAL_SERVER_ERROR).code,
+            "internal_server_error"
+        );
+    }
+
+    #[tokio::test]
+    async fn extensions_cannot_replace_standard_members() {
+        let response = ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "not_ready")
+            .extension("checks", ["store"])
+            .extension("status", "degraded")
+            .into_response();
+        let body = json(response).await;
+        assert_eq!(body["checks"][0], "store");
+        assert_eq!(body["status"], 503);
+    }
+
+    #[test]
+    fn request_ids_prefer_the_callers() {
+        let request = |headers: &[(&'static str, &str)]| {
+            let mut req = Request::new(Body::empty());
+            for (name, value) in headers {
+                req.headers_mut()
+                    .insert(*name, HeaderValue::from_str(value).unwrap());
+            }
+            req
+        };
+        let made = |req: &Request| {
+            let id = MakeCorrelationId.make_request_id(req).unwrap();
+            id.header_value().to_str().unwrap().to_string()
+        };
+        assert_eq!(made(&request(&[(CORRELATION_HEADER, "req-42")])), "req-42");
+        assert_eq!(made(&request(&[])).len(), 36);
+
+        let both = request(&[(REQUEST_ID_HEADER, "lb-7"), (CORRELATION_HEADER, "req-42")]);
+        assert_eq!(correlation_id(&both), "lb-7");
+        let too_long = "x".repeat(MAX_CORRELATION_ID_LEN + 1);
+        let fallback = request(&[(REQUEST_ID_HEADER, &too_long), (CORRELATION_HEADER, "req-42")]);
+        assert_eq!(correlation_id(&fallback), "req-42");
+    }
+
+    #[tokio::test]
+    async fn error_responses_are_completed() {
+        let bare = complete(StatusCode::NOT_FOUND.into_response(), "/api/v1/orders/X", "req-42");
+        let body = json(bare.await).await;
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["instance"], "/api/v1/orders/X");
+        assert_eq!(body["correlation_id"], "req-42");
+
+        // An extractor rejection's message becomes the detail.
+        let rejection = (StatusCode::BAD_REQUEST, "Failed to parse the request body as JSON");
+        let body = json(complete(rejection.into_response(), "/", "req-42").await).await;
+        assert_eq!(body["code"], "bad_request");
+        assert_eq!(body["detail"], "Failed to parse the request body as JSON");
+
+        let typed = ApiError::new(StatusCode::CONFLICT, "duplicate_cl_ord_id").detail("ORD-1");
+        let response = complete(typed.into_response(), "/api/v2/orders", "req-42").await;
+        assert_eq!(response.headers()[header::CONTENT_TYPE], CONTENT_TYPE);
+        let body = json(response).await;
+        assert_eq!(body["code"], "duplicate_cl_ord_id");
+        assert_eq!(body["detail"], "ORD-1");
+        assert_eq!(body["correlation_id"], "req-42");
+    }
+}
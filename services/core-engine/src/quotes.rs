@@ -0,0 +1,822 @@
+//! Request-for-quote workflow.
+//!
+//! `POST /api/v1/quotes/request` builds a QuoteRequest (35=R) with one
+//! NoRelatedSym (146) instance per instrument wanted and sends it like
+//! `/send` does (queued if the session is not logged on). The request is
+//! tracked under its QuoteReqID (131), and each Quote (35=S) received for it
+//! is tracked under its QuoteID (117). A quote whose ValidUntilTime (62) has
+//! passed becomes `EXPIRED`; quotes are checked once a second and whenever
+//! they are read.
+//!
+//! A QuoteCancel (35=Z), received or generated with
+//! `POST /api/v1/quotes/cancel`, makes the live quotes it covers `CANCELED`:
+//! one quote, all quotes for a request, the quotes for some symbols, or all
+//! of a session's quotes.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    auth::Caller,
+    clock::{epoch_ms, parse_utc_timestamp},
+    find_session,
+    problem::ApiError,
+    queue_if_offline, send_message, AppState, FixSession, Groups, OutputEncoding,
+    QueuedResponse, SendOptions, SendRequest, SentMessage,
+};
+
+// ── Quote requests ────────────────────────────────────────────────────────────
+
+/// One NoRelatedSym instance of a QuoteRequest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct QuoteLeg {
+    pub symbol: String,
+    /// Side (54) as its FIX code; a two-way quote is asked for if omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub side: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_qty: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct QuoteRequestBody {
+    /// `SenderCompID->TargetCompID` of a configured session.
+    pub session_id: String,
+    /// Generated if omitted.
+    #[serde(default)]
+    pub quote_req_id: Option<String>,
+    pub legs: Vec<QuoteLeg>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum QuoteRequestState {
+    /// Sent (or queued), no quote received for it yet.
+    Pending,
+    /// At least one quote has been received for it.
+    Quoted,
+    /// Its quotes were cancelled as a whole.
+    Canceled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct QuoteRequest {
+    pub quote_req_id: String,
+    pub session_id: String,
+    pub legs: Vec<QuoteLeg>,
+    pub state: QuoteRequestState,
+    /// QuoteIDs received for the request, in order.
+    pub quotes: Vec<String>,
+    pub requested_at_ms: u64,
+    pub updated_at_ms: u64,
+}
+
+impl QuoteRequest {
+    /// The QuoteRequest's body fields and NoRelatedSym group, keyed by tag so
+    /// they encode without a data dictionary for the session's version.
+    fn message(&self) -> (HashMap<String, String>, Groups) {
+        let fields = HashMap::from([("131".to_string(), self.quote_req_id.clone())]);
+        let legs = self
+            .legs
+            .iter()
+            .map(|leg| {
+                let mut instance = HashMap::from([("55".to_string(), leg.symbol.clone())]);
+                if let Some(side) = &leg.side {
+                    instance.insert("54".to_string(), side.clone());
+                }
+                if let Some(qty) = &leg.order_qty {
+                    instance.insert("38".to_string(), qty.clone());
+                }
+                instance
+            })
+            .collect();
+        (fields, Groups::from([("146".to_string(), legs)]))
+    }
+}
+
+// ── Quotes ────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum QuoteState {
+    Active,
+    /// Its ValidUntilTime has passed.
+    Expired,
+    Canceled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Quote {
+    pub quote_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quote_req_id: Option<String>,
+    pub session_id: String,
+    pub symbol: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bid_px: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offer_px: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bid_size: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offer_size: Option<String>,
+    /// ValidUntilTime (62); the quote does not expire without one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub valid_until_ms: Option<u64>,
+    pub state: QuoteState,
+    pub received_at_ms: u64,
+    pub updated_at_ms: u64,
+}
+
+/// The quotes a QuoteCancel covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CancelScope {
+    Quote(String),
+    Request(String),
+    Symbols(Vec<String>),
+    All,
+}
+
+#[derive(Default)]
+pub struct QuoteStore {
+    requests: HashMap<String, QuoteRequest>,
+    quotes: HashMap<String, Quote>,
+}
+
+impl QuoteStore {
+    pub fn request(&self, quote_req_id: &str) -> Option<&QuoteRequest> {
+        self.requests.get(quote_req_id)
+    }
+
+    pub fn quote(&self, quote_id: &str) -> Option<&Quote> {
+        self.quotes.get(quote_id)
+    }
+
+    /// Quote requests, oldest first.
+    pub fn requests(&self) -> Vec<QuoteRequest> {
+        let mut items: Vec<QuoteRequest> = self.requests.values().cloned().collect();
+        items.sort_by(|a, b| {
+            (a.requested_at_ms, &a.quote_req_id).cmp(&(b.requested_at_ms, &b.quote_req_id))
+        });
+        items
+    }
+
+    /// Quotes, oldest first, optionally only those for one request.
+    pub fn quotes(&self, quote_req_id: Option<&str>) -> Vec<Quote> {
+        let mut items: Vec<Quote> = self
+            .quotes
+            .values()
+            .filter(|q| quote_req_id.is_none_or(|id| q.quote_req_id.as_deref() == Some(id)))
+            .cloned()
+            .collect();
+        items.sort_by(|a, b| (a.received_at_ms, &a.quote_id).cmp(&(b.received_at_ms, &b.quote_id)));
+        items
+    }
+
+    fn record(&mut self, request: QuoteRequest) {
+        self.requests.insert(request.quote_req_id.clone(), request);
+    }
+
+    /// Records an inbound Quote from its tag/value pairs. Returns `None` if
+    /// it has no QuoteID. A quote sent again under the same QuoteID replaces
+    /// the earlier one.
+    pub fn on_quote(
+        &mut self,
+        session_id: &str,
+        fields: &[(u32, &str)],
+        at_ms: u64,
+    ) -> Option<Quote> {
+        let value = |tag: u32| {
+            fields
+                .iter()
+                .find(|(t, _)| *t == tag)
+                .map(|(_, v)| v.to_string())
+        };
+        let valid_until_ms = value(62).and_then(|v| parse_utc_timestamp(&v));
+        let quote = Quote {
+            quote_id: value(117)?,
+            quote_req_id: value(131),
+            session_id: session_id.to_string(),
+            symbol: value(55).unwrap_or_default(),
+            bid_px: value(132),
+            offer_px: value(133),
+            bid_size: value(134),
+            offer_size: value(135),
+            valid_until_ms,
+            state: if valid_until_ms.is_some_and(|until| until <= at_ms) {
+                QuoteState::Expired
+            } else {
+                QuoteState::Active
+            },
+            received_at_ms: at_ms,
+            updated_at_ms: at_ms,
+        };
+        if let Some(request) = quote
+            .quote_req_id
+            .as_deref()
+            .and_then(|id| self.requests.get_mut(id))
+        {
+            if !request.quotes.contains(&quote.quote_id) {
+                request.quotes.push(quote.quote_id.clone());
+            }
+            if request.state == QuoteRequestState::Pending {
+                request.state = QuoteRequestState::Quoted;
+            }
+            request.updated_at_ms = at_ms;
+        }
+        self.quotes.insert(quote.quote_id.clone(), quote.clone());
+        Some(quote)
+    }
+
+    /// Marks active quotes whose ValidUntilTime is at or before `now_ms`
+    /// expired, returning them.
+    pub fn expire(&mut self, now_ms: u64) -> Vec<Quote> {
+        let mut expired = Vec::new();
+        for quote in self.quotes.values_mut() {
+            if quote.state == QuoteState::Active
+                && quote.valid_until_ms.is_some_and(|until| until <= now_ms)
+            {
+                quote.state = QuoteState::Expired;
+                quote.updated_at_ms = now_ms;
+                expired.push(quote.clone());
+            }
+        }
+        expired
+    }
+
+    /// The scope of an inbound QuoteCancel: everything for QuoteCancelType
+    /// (298) 4, otherwise the quote or request it names if known, otherwise
+    /// its symbols.
+    pub fn scope_of(&self, fields: &[(u32, &str)]) -> CancelScope {
+        let value = |tag: u32| fields.iter().find(|(t, _)| *t == tag).map(|(_, v)| *v);
+        if value(298) == Some("4") {
+            return CancelScope::All;
+        }
+        if let Some(id) = value(117).filter(|id| self.quotes.contains_key(*id)) {
+            return CancelScope::Quote(id.to_string());
+        }
+        if let Some(id) = value(131).filter(|id| self.requests.contains_key(*id)) {
+            return CancelScope::Request(id.to_string());
+        }
+        CancelScope::Symbols(
+            fields
+                .iter()
+                .filter(|(t, _)| *t == 55)
+                .map(|(_, v)| v.to_string())
+                .collect(),
+        )
+    }
+
+    /// Cancels the session's active quotes in `scope`, returning them.
+    pub fn cancel(&mut self, session_id: &str, scope: &CancelScope, at_ms: u64) -> Vec<Quote> {
+        if let CancelScope::Request(id) = scope {
+            if let Some(request) = self.requests.get_mut(id) {
+                request.state = QuoteRequestState::Canceled;
+                request.updated_at_ms = at_ms;
+            }
+        }
+        let mut cancelled = Vec::new();
+        for quote in self.quotes.values_mut() {
+            let covered = quote.session_id == session_id
+                && match scope {
+                    CancelScope::Quote(id) => &quote.quote_id == id,
+                    CancelScope::Request(id) => quote.quote_req_id.as_ref() == Some(id),
+                    CancelScope::Symbols(symbols) => symbols.contains(&quote.symbol),
+                    CancelScope::All => true,
+                };
+            if covered && quote.state == QuoteState::Active {
+                quote.state = QuoteState::Canceled;
+                quote.updated_at_ms = at_ms;
+                cancelled.push(quote.clone());
+            }
+        }
+        cancelled
+    }
+
+    /// The QuoteCancel body fields and NoQuoteEntries (295) group for
+    /// `scope`. QuoteID (117) is required, so one is generated when the
+    /// cancel does not name a quote.
+    fn cancel_message(&self, scope: &CancelScope) -> (HashMap<String, String>, Groups) {
+        let (quote_id, quote_req_id, symbols) = match scope {
+            CancelScope::Quote(id) => {
+                let quote = self.quotes.get(id);
+                (
+                    Some(id.clone()),
+                    quote.and_then(|q| q.quote_req_id.clone()),
+                    quote.map(|q| vec![q.symbol.clone()]).unwrap_or_default(),
+                )
+            }
+            CancelScope::Request(id) => (
+                None,
+                Some(id.clone()),
+                self.requests
+                    .get(id)
+                    .map(|r| r.legs.iter().map(|l| l.symbol.clone()).collect())
+                    .unwrap_or_default(),
+            ),
+            CancelScope::Symbols(symbols) => (None, None, symbols.clone()),
+            CancelScope::All => (None, None, Vec::new()),
+        };
+        let mut fields = HashMap::from([
+            (
+                "117".to_string(),
+                quote_id.unwrap_or_else(|| format!("QC-{}", uuid::Uuid::new_v4().simple())),
+            ),
+            (
+                "298".to_string(),
+                if *scope == CancelScope::All { "4" } else { "1" }.to_string(),
+            ),
+        ]);
+        if let Some(id) = quote_req_id {
+            fields.insert("131".to_string(), id);
+        }
+        let mut groups = Groups::new();
+        if !symbols.is_empty() {
+            groups.insert(
+                "295".to_string(),
+                symbols
+                    .into_iter()
+                    .map(|s| HashMap::from([("55".to_string(), s)]))
+                    .collect(),
+            );
+        }
+        (fields, groups)
+    }
+}
+
+/// Expires quotes past their ValidUntilTime once a second.
+pub async fn run_expiry(state: AppState) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        ticker.tick().await;
+        let mut quotes = state.quotes.lock().await;
+        for quote in quotes.expire(epoch_ms()) {
+            info!(
+                session_id = %quote.session_id,
+                quote_id = %quote.quote_id,
+                quote_req_id = quote.quote_req_id.as_deref().unwrap_or_default(),
+                "Quote expired"
+            );
+        }
+    }
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QuoteRequestResponse {
+    request: QuoteRequest,
+    /// The QuoteRequest as sent; absent if it was queued.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sequence_number: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queue_id: Option<String>,
+}
+
+/// Builds and sends a QuoteRequest, returning `202 Accepted` if it was
+/// queued for the session.
+#[utoipa::path(
+    post,
+    path = "/api/v1/quotes/request",
+    tag = "quotes",
+    request_body = QuoteRequestBody,
+    responses(
+        (status = 200, description = "Sent", body = QuoteRequestResponse),
+        (
+            status = 202,
+            description = "Queued until the session is logged on",
+            body = QuoteRequestResponse
+        ),
+        (status = 404, description = "Unknown session"),
+        (status = 422, description = "Invalid request"),
+        (status = 429, description = "The client is over its rate limit; see Retry-After"),
+    )
+)]
+pub async fn request(
+    State(state): State<AppState>,
+    caller: Caller,
+    Json(req): Json<QuoteRequestBody>,
+) -> Result<Response, ApiError> {
+    caller.check_session(&req.session_id)?;
+    let session = find_session(&state, &req.session_id).ok_or(StatusCode::NOT_FOUND)?;
+    if req.legs.is_empty() || req.legs.iter().any(|l| l.symbol.trim().is_empty()) {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY.into());
+    }
+    let now_ms = epoch_ms();
+    let request = QuoteRequest {
+        quote_req_id: req
+            .quote_req_id
+            .unwrap_or_else(|| format!("QR-{}", uuid::Uuid::new_v4().simple())),
+        session_id: session.session_id.clone(),
+        legs: req.legs,
+        state: QuoteRequestState::Pending,
+        quotes: Vec::new(),
+        requested_at_ms: now_ms,
+        updated_at_ms: now_ms,
+    };
+    // Recorded first, so its quotes are recognised however soon they come.
+    state.quotes.lock().await.record(request.clone());
+
+    let (fields, groups) = request.message();
+    let (sent, queued) = transmit(&state, &session, "QuoteRequest", fields, groups).await?;
+    info!(
+        session_id = %request.session_id,
+        quote_req_id = %request.quote_req_id,
+        legs = request.legs.len(),
+        queued = queued.is_some(),
+        "QuoteRequest sent"
+    );
+    let status = if queued.is_some() {
+        StatusCode::ACCEPTED
+    } else {
+        StatusCode::OK
+    };
+    let response = QuoteRequestResponse {
+        request,
+        fix_message: sent.as_ref().map(|s| s.fix_message.clone()),
+        sequence_number: sent.as_ref().map(|s| s.sequence_number),
+        queue_id: queued.map(|q| q.queue_id),
+    };
+    Ok((status, Json(response)).into_response())
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = QuoteCancelRequest)]
+pub struct CancelBody {
+    pub session_id: String,
+    /// Cancels one quote; takes precedence over the other fields.
+    #[serde(default)]
+    pub quote_id: Option<String>,
+    /// Cancels every quote received for a request.
+    #[serde(default)]
+    pub quote_req_id: Option<String>,
+    /// Cancels the quotes for these symbols; with nothing else given, all of
+    /// the session's quotes are cancelled.
+    #[serde(default)]
+    pub symbols: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = QuoteCancelResponse)]
+pub struct CancelResponse {
+    cancelled: Vec<Quote>,
+    /// The QuoteCancel as sent; absent if it was queued.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sequence_number: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queue_id: Option<String>,
+}
+
+/// Builds and sends a QuoteCancel, returning `202 Accepted` if it was queued
+/// for the session. A quote or request that is not known for the session is
+/// `404`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/quotes/cancel",
+    tag = "quotes",
+    request_body = CancelBody,
+    responses(
+        (status = 200, description = "Sent", body = CancelResponse),
+        (
+            status = 202,
+            description = "Queued until the session is logged on",
+            body = CancelResponse
+        ),
+        (status = 404, description = "Unknown session, quote or request"),
+        (status = 429, description = "The client is over its rate limit; see Retry-After"),
+    )
+)]
+pub async fn cancel(
+    State(state): State<AppState>,
+    caller: Caller,
+    Json(req): Json<CancelBody>,
+) -> Result<Response, ApiError> {
+    caller.check_session(&req.session_id)?;
+    let session = find_session(&state, &req.session_id).ok_or(StatusCode::NOT_FOUND)?;
+    let (scope, fields, groups) = {
+        let store = state.quotes.lock().await;
+        let scope = match (req.quote_id, req.quote_req_id) {
+            (Some(id), _) => {
+                store
+                    .quote(&id)
+                    .filter(|q| q.session_id == session.session_id)
+                    .ok_or(StatusCode::NOT_FOUND)?;
+                CancelScope::Quote(id)
+            }
+            (None, Some(id)) => {
+                store
+                    .request(&id)
+                    .filter(|r| r.session_id == session.session_id)
+                    .ok_or(StatusCode::NOT_FOUND)?;
+                CancelScope::Request(id)
+            }
+            (None, None) if req.symbols.is_empty() => CancelScope::All,
+            (None, None) => CancelScope::Symbols(req.symbols),
+        };
+        let (fields, groups) = store.cancel_message(&scope);
+        (scope, fields, groups)
+    };
+
+    let (sent, queued) = transmit(&state, &session, "QuoteCancel", fields, groups).await?;
+    let cancelled = state
+        .quotes
+        .lock()
+        .await
+        .cancel(&session.session_id, &scope, epoch_ms());
+    info!(
+        session_id = %session.session_id,
+        scope = ?scope,
+        cancelled = cancelled.len(),
+        queued = queued.is_some(),
+        "QuoteCancel sent"
+    );
+    let status = if queued.is_some() {
+        StatusCode::ACCEPTED
+    } else {
+        StatusCode::OK
+    };
+    let response = CancelResponse {
+        cancelled,
+        fix_message: sent.as_ref().map(|s| s.fix_message.clone()),
+        sequence_number: sent.as_ref().map(|s| s.sequence_number),
+        queue_id: queued.map(|q| q.queue_id),
+    };
+    Ok((status, Json(response)).into_response())
+}
+
+/// Sends a quote workflow message, or queues it if the session is not logged
+/// on.
+async fn transmit(
+    state: &AppState,
+    session: &FixSession,
+    msg_type: &str,
+    mut fields: HashMap<String, String>,
+    groups: Groups,
+) -> Result<(Option<SentMessage>, Option<QueuedResponse>), StatusCode> {
+    for (name, value) in [
+        ("BeginString", &session.fix_version),
+        ("SenderCompID", &session.sender_comp_id),
+        ("TargetCompID", &session.target_comp_id),
+    ] {
+        fields.insert(name.to_string(), value.clone());
+    }
+    let send = SendRequest {
+        msg_type: msg_type.to_string(),
+        fields,
+        groups,
+        metadata: HashMap::new(),
+        reply_to: None,
+        ttl_ms: None,
+        template: None,
+        attribution: None,
+    };
+    let opts = SendOptions {
+        encoding: OutputEncoding::default(),
+        validate: false,
+        strict: None,
+    };
+    if let Some(queued) = queue_if_offline(state, &opts, &send).await? {
+        return Ok((None, Some(queued)));
+    }
+    Ok((Some(send_message(state, &opts, send).await?), None))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QuoteRequestsResponse {
+    items: Vec<QuoteRequest>,
+    count: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/quotes/requests",
+    tag = "quotes",
+    responses(
+        (status = 200, description = "OK", body = QuoteRequestsResponse),
+    )
+)]
+pub async fn list_requests(State(state): State<AppState>) -> Json<QuoteRequestsResponse> {
+    let items = state.quotes.lock().await.requests();
+    let count = items.len();
+    Json(QuoteRequestsResponse { items, count })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/quotes/requests/{quote_req_id}",
+    tag = "quotes",
+    params(("quote_req_id" = String, Path, description = "The request's QuoteReqID.")),
+    responses(
+        (status = 200, description = "OK", body = QuoteRequest),
+        (status = 404, description = "Unknown request"),
+    )
+)]
+pub async fn get_request(
+    State(state): State<AppState>,
+    Path(quote_req_id): Path<String>,
+) -> Result<Json<QuoteRequest>, StatusCode> {
+    state
+        .quotes
+        .lock()
+        .await
+        .request(&quote_req_id)
+        .cloned()
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QuotesQuery {
+    #[serde(default)]
+    quote_req_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QuotesResponse {
+    items: Vec<Quote>,
+    count: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/quotes",
+    tag = "quotes",
+    params(QuotesQuery),
+    responses(
+        (status = 200, description = "OK", body = QuotesResponse),
+    )
+)]
+pub async fn list_quotes(
+    State(state): State<AppState>,
+    Query(query): Query<QuotesQuery>,
+) -> Json<QuotesResponse> {
+    let mut store = state.quotes.lock().await;
+    store.expire(epoch_ms());
+    let items = store.quotes(query.quote_req_id.as_deref());
+    let count = items.len();
+    Json(QuotesResponse { items, count })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/quotes/{quote_id}",
+    tag = "quotes",
+    params(("quote_id" = String, Path, description = "The quote's QuoteID.")),
+    responses(
+        (status = 200, description = "OK", body = Quote),
+        (status = 404, description = "Unknown quote"),
+    )
+)]
+pub async fn get_quote(
+    State(state): State<AppState>,
+    Path(quote_id): Path<String>,
+) -> Result<Json<Quote>, StatusCode> {
+    let mut store = state.quotes.lock().await;
+    store.expire(epoch_ms());
+    store
+        .quote(&quote_id)
+        .cloned()
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store_with_request() -> QuoteStore {
+        let mut store = QuoteStore::default();
+        store.record(QuoteRequest {
+            quote_req_id: "QR1".to_string(),
+            session_id: "ALICE->BROKER_A".to_string(),
+            legs: vec![
+                QuoteLeg {
+                    symbol: "7203".to_string(),
+                    side: Some("1".to_string()),
+                    order_qty: Some("100".to_string()),
+                },
+                QuoteLeg {
+                    symbol: "6758".to_string(),
+                    side: None,
+                    order_qty: None,
+                },
+            ],
+            state: QuoteRequestState::Pending,
+            quotes: Vec::new(),
+            requested_at_ms: 1,
+            updated_at_ms: 1,
+        });
+        store
+    }
+
+    fn quote(id: &str, symbol: &str, valid_until: &str) -> Vec<(u32, String)> {
+        vec![
+            (117, id.to_string()),
+            (131, "QR1".to_string()),
+            (55, symbol.to_string()),
+            (132, "2500".to_string()),
+            (133, "2501".to_string()),
+            (62, valid_until.to_string()),
+        ]
+    }
+
+    fn pairs(fields: &[(u32, String)]) -> Vec<(u32, &str)> {
+        fields.iter().map(|(t, v)| (*t, v.as_str())).collect()
+    }
+
+    #[test]
+    fn quote_requests_carry_a_related_symbol_group() {
+        let store = store_with_request();
+        let (fields, groups) = store.request("QR1").unwrap().message();
+        assert_eq!(fields["131"], "QR1");
+        let legs = &groups["146"];
+        assert_eq!(legs.len(), 2);
+        assert_eq!(legs[0]["55"], "7203");
+        assert_eq!(legs[0]["54"], "1");
+        assert_eq!(legs[0]["38"], "100");
+        assert!(!legs[1].contains_key("54"));
+    }
+
+    #[test]
+    fn quotes_attach_to_their_request_and_expire() {
+        let mut store = store_with_request();
+        // 2026-02-23 00:00:00 UTC.
+        let t0 = 1_771_804_800_000;
+        let q1 = quote("Q1", "7203", "20260223-00:00:05.000");
+        let quote1 = store.on_quote("ALICE->BROKER_A", &pairs(&q1), t0).unwrap();
+        assert_eq!(quote1.state, QuoteState::Active);
+        assert_eq!(quote1.valid_until_ms, Some(t0 + 5_000));
+        let request = store.request("QR1").unwrap();
+        assert_eq!(request.state, QuoteRequestState::Quoted);
+        assert_eq!(request.quotes, ["Q1"]);
+
+        // Already past its ValidUntilTime when it arrives.
+        let q2 = quote("Q2", "6758", "20260222-23:59:59.000");
+        let stale = store.on_quote("ALICE->BROKER_A", &pairs(&q2), t0).unwrap();
+        assert_eq!(stale.state, QuoteState::Expired);
+
+        assert!(store.expire(t0 + 4_999).is_empty());
+        let expired = store.expire(t0 + 5_000);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].quote_id, "Q1");
+        assert!(store.expire(t0 + 6_000).is_empty());
+        assert_eq!(store.quotes(Some("QR1")).len(), 2);
+        assert!(store
+            .on_quote("ALICE->BROKER_A", &[(55, "7203")], t0)
+            .is_none());
+    }
+
+    #[test]
+    fn cancels_cover_their_scope_only() {
+        let mut store = store_with_request();
+        let t0 = 1_771_804_800_000;
+        for (id, symbol) in [("Q1", "7203"), ("Q2", "6758"), ("Q3", "9984")] {
+            let mut q = quote(id, symbol, "20260223-01:00:00.000");
+            if id == "Q3" {
+                q.retain(|(t, _)| *t != 131);
+            }
+            store.on_quote("ALICE->BROKER_A", &pairs(&q), t0);
+        }
+
+        let (fields, groups) = store.cancel_message(&CancelScope::Quote("Q1".to_string()));
+        assert_eq!(fields["117"], "Q1");
+        assert_eq!(fields["131"], "QR1");
+        assert_eq!(fields["298"], "1");
+        assert_eq!(groups["295"][0]["55"], "7203");
+        let (fields, groups) = store.cancel_message(&CancelScope::All);
+        assert!(fields["117"].starts_with("QC-"));
+        assert_eq!(fields["298"], "4");
+        assert!(groups.is_empty());
+
+        let cancelled = store.cancel("ALICE->BROKER_A", &CancelScope::Quote("Q1".to_string()), t0);
+        assert_eq!(cancelled.len(), 1);
+        let scope = store.scope_of(&[(117, "X9"), (131, "QR1"), (298, "1")]);
+        assert_eq!(scope, CancelScope::Request("QR1".to_string()));
+        let cancelled = store.cancel("ALICE->BROKER_A", &scope, t0);
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(cancelled[0].quote_id, "Q2");
+        assert_eq!(
+            store.request("QR1").unwrap().state,
+            QuoteRequestState::Canceled
+        );
+        assert!(store
+            .cancel("ALICE->BROKER_B", &CancelScope::All, t0)
+            .is_empty());
+        let scope = store.scope_of(&[(117, "X9"), (298, "1"), (295, "1"), (55, "9984")]);
+        assert_eq!(scope, CancelScope::Symbols(vec!["9984".to_string()]));
+        assert_eq!(store.cancel("ALICE->BROKER_A", &scope, t0).len(), 1);
+        assert_eq!(store.quote("Q3").unwrap().state, QuoteState::Canceled);
+    }
+}
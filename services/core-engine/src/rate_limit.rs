@@ -0,0 +1,330 @@
+//! Per-client rate limits on the REST API's send and validate endpoints.
+//!
+//! With `FIX_RATE_LIMIT_PER_SEC` set, each client gets a token bucket that
+//! refills at that many messages a second and holds up to
+//! `FIX_RATE_LIMIT_BURST` (default: one second's worth). Every request
+//! needing the send [scope](crate::auth::Scope), whichever API version, and
+//! every `/api/v1/fix/validate`, takes a token; a `/api/v1/fix/send/batch`
+//! takes one per message, once its body is read. One finding too few tokens
+//! is answered `429 rate_limited` with `Retry-After`, the whole seconds
+//! until they are back, and is not processed. So one misbehaving service
+//! exhausts only its own bucket. `/api/v1/fix/inbound` is not limited: it
+//! carries the counterparty's messages, and reports relayed by an upstream
+//! gateway, rather than sends.
+//!
+//! A client is the API key or token it authenticated with, or without
+//! authentication the address it connected from. gRPC `Send`, `Validate`
+//! and `MessageFlow` sends take tokens from the same buckets. Buckets that have refilled
+//! are forgotten, so the table only holds clients that sent recently.
+//!
+//! The `[rate_limit]` section of the configuration file takes the place of
+//! the variables, and a reload puts a change to it in force at once, keeping
+//! each client's bucket.
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{request::Parts, Extensions, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+};
+use tracing::warn;
+
+use crate::{
+    auth::{self, Principal, Scope},
+    clock::epoch_ms,
+    config::RateLimitSettings,
+    problem::ApiError,
+    AppState,
+};
+
+/// Charged by its handler, a token per message.
+pub const BATCH_PATH: &str = "/api/v1/fix/send/batch";
+
+/// Not a send, but parses and validates a message as one would.
+const VALIDATE_PATH: &str = "/api/v1/fix/validate";
+
+/// Sends of the counterparty's messages, not the client's.
+const INBOUND_PATH: &str = "/api/v1/fix/inbound";
+
+/// How often full buckets are dropped.
+const PRUNE_INTERVAL_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitConfig {
+    /// Tokens added per second.
+    per_sec: f64,
+    /// Tokens a bucket holds.
+    burst: f64,
+}
+
+impl RateLimitConfig {
+    /// The settings in the configuration file, each falling back to
+    /// `FIX_RATE_LIMIT_PER_SEC` or `FIX_RATE_LIMIT_BURST`; `None` leaves
+    /// clients unlimited.
+    pub fn from_env(file: &RateLimitSettings) -> Result<Option<Self>, String> {
+        let var = |name, set: Option<f64>| {
+            set.map(|n| n.to_string())
+                .or_else(|| std::env::var(name).ok().filter(|v| !v.trim().is_empty()))
+        };
+        Self::parse(
+            var("FIX_RATE_LIMIT_PER_SEC", file.per_sec),
+            var("FIX_RATE_LIMIT_BURST", file.burst),
+        )
+    }
+
+    fn parse(per_sec: Option<String>, burst: Option<String>) -> Result<Option<Self>, String> {
+        let positive = |name: &str, value: &str| match value.trim().parse::<f64>() {
+            Ok(n) if n > 0.0 && n.is_finite() => Ok(n),
+            _ => Err(format!("{name} must be a positive number, not `{value}`")),
+        };
+        let per_sec = match per_sec {
+            Some(v) => positive("FIX_RATE_LIMIT_PER_SEC", &v)?,
+            None if burst.is_some() => {
+                return Err("FIX_RATE_LIMIT_BURST requires FIX_RATE_LIMIT_PER_SEC".to_string())
+            }
+            None => return Ok(None),
+        };
+        let burst = match burst {
+            Some(v) => positive("FIX_RATE_LIMIT_BURST", &v)?,
+            None => per_sec,
+        };
+        if burst < 1.0 {
+            return Err(format!("FIX_RATE_LIMIT_BURST must be at least 1, not {burst}"));
+        }
+        Ok(Some(Self { per_sec, burst }))
+    }
+
+    /// The limit, for startup reporting.
+    pub fn describe(&self) -> String {
+        format!("rate limit {}/s per client, burst {}", self.per_sec, self.burst)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated_ms: u64,
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: HashMap<String, Bucket>,
+    pruned_ms: u64,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: HashMap::new(),
+            pruned_ms: 0,
+        }
+    }
+
+    pub fn config(&self) -> RateLimitConfig {
+        self.config
+    }
+
+    /// Limits clients as `config` says from now on.
+    pub fn reconfigure(&mut self, config: RateLimitConfig) {
+        self.config = config;
+    }
+
+    /// Takes `tokens` from `client`'s bucket, or returns how long until they
+    /// are there. More than the burst are taken from a full bucket, leaving
+    /// it owing the rest.
+    fn acquire(&mut self, client: &str, tokens: usize, now_ms: u64) -> Result<(), u64> {
+        if now_ms.saturating_sub(self.pruned_ms) >= PRUNE_INTERVAL_MS {
+            self.prune(now_ms);
+        }
+        let RateLimitConfig { per_sec, burst } = self.config;
+        let bucket = self.buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated_ms: now_ms,
+        });
+        let elapsed_secs = now_ms.saturating_sub(bucket.updated_ms) as f64 / 1000.0;
+        bucket.tokens = (bucket.tokens + elapsed_secs * per_sec).min(burst);
+        bucket.updated_ms = now_ms;
+        let tokens = tokens as f64;
+        let needed = tokens.min(burst);
+        if bucket.tokens >= needed {
+            bucket.tokens -= tokens;
+            Ok(())
+        } else {
+            Err(((needed - bucket.tokens) / per_sec * 1000.0).ceil() as u64)
+        }
+    }
+
+    /// Drops the buckets that would be full by now.
+    fn prune(&mut self, now_ms: u64) {
+        let RateLimitConfig { per_sec, burst } = self.config;
+        self.buckets.retain(|_, bucket| {
+            let elapsed_secs = now_ms.saturating_sub(bucket.updated_ms) as f64 / 1000.0;
+            bucket.tokens + elapsed_secs * per_sec < burst
+        });
+        self.pruned_ms = now_ms;
+    }
+}
+
+/// The client a request is counted against: its principal, or without one
+/// the address it came from.
+pub fn client(principal: Option<&Principal>, source_ip: Option<IpAddr>) -> String {
+    match (principal, source_ip) {
+        (Some(principal), _) => format!("principal:{}", principal.name),
+        (None, Some(ip)) => format!("ip:{ip}"),
+        (None, None) => "ip:unknown".to_string(),
+    }
+}
+
+/// The client a REST request is counted against.
+#[derive(Debug, Clone)]
+pub struct Client(pub String);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Client {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(request_client(&parts.extensions)))
+    }
+}
+
+/// The client of a REST request, from what authentication and the
+/// connection left in its extensions.
+fn request_client(extensions: &Extensions) -> String {
+    let source_ip = extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    client(extensions.get::<Principal>(), source_ip)
+}
+
+/// Takes `tokens` from `client`'s bucket, one per message. Over its limit,
+/// fails with `429` and `Retry-After`, the whole seconds until they are
+/// back. `path` is what was called, for the log.
+pub async fn admit(
+    state: &AppState,
+    client: &str,
+    path: &str,
+    tokens: usize,
+) -> Result<(), ApiError> {
+    let acquired = match state.rate_limiter.lock().await.as_mut() {
+        Some(limiter) => limiter.acquire(client, tokens, epoch_ms()),
+        None => Ok(()),
+    };
+    acquired.map_err(|wait_ms| {
+        let retry_after = wait_ms.div_ceil(1000).max(1);
+        warn!(%client, path, tokens, retry_after, "Rate limited API request");
+        ApiError::new(StatusCode::TOO_MANY_REQUESTS, "rate_limited")
+            .detail(format!("too many requests; retry in {retry_after}s"))
+            .retry_after(retry_after)
+    })
+}
+
+/// Whether the middleware takes a token for a request.
+fn is_limited(method: &Method, path: &str) -> bool {
+    let path = path.trim_end_matches('/');
+    match path {
+        BATCH_PATH | INBOUND_PATH => false,
+        VALIDATE_PATH => true,
+        _ => auth::required_scope(method, path) == Scope::Send,
+    }
+}
+
+/// Middleware answering `429` to clients over their limit. It runs after
+/// [`crate::auth::authenticate`], so authenticated clients are known.
+pub async fn limit(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    if !is_limited(req.method(), req.uri().path()) {
+        return next.run(req).await;
+    }
+    let client = request_client(req.extensions());
+    match admit(&state, &client, req.uri().path(), 1).await {
+        Ok(()) => next.run(req).await,
+        Err(error) => error.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(per_sec: Option<&str>, burst: Option<&str>) -> Result<Option<(f64, f64)>, String> {
+        RateLimitConfig::parse(per_sec.map(str::to_string), burst.map(str::to_string))
+            .map(|config| config.map(|c| (c.per_sec, c.burst)))
+    }
+
+    #[test]
+    fn configuration_needs_a_positive_rate() {
+        assert_eq!(parse(None, None), Ok(None));
+        assert_eq!(parse(Some("20"), None), Ok(Some((20.0, 20.0))));
+        assert_eq!(parse(Some("0.5"), Some("5")), Ok(Some((0.5, 5.0))));
+        assert!(parse(None, Some("5")).is_err());
+        assert!(parse(Some("0"), None).is_err());
+        assert!(parse(Some("fast"), None).is_err());
+        assert!(parse(Some("0.5"), None).is_err());
+    }
+
+    #[test]
+    fn buckets_refill_per_client() {
+        let mut limiter = RateLimiter::new(RateLimitConfig {
+            per_sec: 2.0,
+            burst: 3.0,
+        });
+        for _ in 0..3 {
+            assert_eq!(limiter.acquire("ip:10.0.0.1", 1, 1_000), Ok(()));
+        }
+        // Empty: a token is back after half a second.
+        assert_eq!(limiter.acquire("ip:10.0.0.1", 1, 1_000), Err(500));
+        assert_eq!(limiter.acquire("ip:10.0.0.1", 1, 1_250), Err(250));
+        // Other clients have their own buckets.
+        assert_eq!(limiter.acquire("principal:oms", 1, 1_250), Ok(()));
+
+        assert_eq!(limiter.acquire("ip:10.0.0.1", 1, 1_500), Ok(()));
+        assert_eq!(limiter.acquire("ip:10.0.0.1", 1, 1_500), Err(500));
+        // A reload keeps the bucket, refilling it at the new rate.
+        limiter.reconfigure(RateLimitConfig {
+            per_sec: 4.0,
+            burst: 3.0,
+        });
+        assert_eq!(limiter.acquire("ip:10.0.0.1", 1, 1_500), Err(250));
+        limiter.reconfigure(RateLimitConfig {
+            per_sec: 2.0,
+            burst: 3.0,
+        });
+        // Refilled buckets are pruned, and start full again.
+        limiter.acquire("ip:10.0.0.2", 1, 1_000 + PRUNE_INTERVAL_MS).unwrap();
+        assert_eq!(limiter.buckets.len(), 1);
+
+        // A batch takes a token per message, and one larger than the burst
+        // only from a full bucket, which then owes the rest.
+        assert_eq!(limiter.acquire("ip:10.0.0.3", 2, 0), Ok(()));
+        assert_eq!(limiter.acquire("ip:10.0.0.3", 5, 0), Err(1_000));
+        assert_eq!(limiter.acquire("ip:10.0.0.3", 5, 1_000), Ok(()));
+        assert_eq!(limiter.acquire("ip:10.0.0.3", 1, 1_000), Err(1_500));
+    }
+
+    #[test]
+    fn sends_are_limited_whichever_api_they_use() {
+        for path in [
+            "/api/v1/fix/send",
+            "/api/v1/orders/fast",
+            "/api/v2/orders",
+            "/api/v2/orders/O1/cancel",
+            "/api/v2/orders/O1/replace/",
+            "/api/v1/quotes/request",
+            "/api/v1/fix/validate",
+        ] {
+            assert!(is_limited(&Method::POST, path), "{path}");
+        }
+        for path in ["/api/v1/fix/send/batch", "/api/v1/fix/inbound", "/api/v1/fix/parse"] {
+            assert!(!is_limited(&Method::POST, path), "{path}");
+        }
+        assert!(!is_limited(&Method::GET, "/api/v1/orders"));
+    }
+}
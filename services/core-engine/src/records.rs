@@ -0,0 +1,308 @@
+//! Versioned formats for persisted records.
+//!
+//! Stored messages and orders are written as JSON objects carrying a
+//! `schema_version`. Reads go through [`decode`], which upgrades older records
+//! one version at a time to the current format, so history written by earlier
+//! releases stays readable after an upgrade. Records written by a newer release
+//! are accepted as long as they still carry the fields this release needs;
+//! fields it does not know are ignored.
+
+use async_graphql::Enum;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+use tracing::warn;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    attribution::Attribution,
+    audit::AuditEntry,
+    clock,
+    journal::JournalEntry,
+    orders::{OrderContext, ReportContext},
+    outbound_queue::QueuedMessage,
+    AppState,
+};
+
+const VERSION_KEY: &str = "schema_version";
+
+/// Upgrades a record in place from one version to the next.
+type Migration = fn(&mut Map<String, Value>) -> Result<(), String>;
+
+pub trait Record: Serialize + DeserializeOwned {
+    const SCHEMA_VERSION: u32;
+    /// `(n, f)`: `f` upgrades a version-`n` record to version `n + 1`.
+    /// Records without a `schema_version` are version 0.
+    const MIGRATIONS: &'static [(u32, Migration)];
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordError {
+    Malformed(String),
+    /// No migration path from the record's version to the current one.
+    Unsupported(u32),
+    Migration {
+        from: u32,
+        reason: String,
+    },
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::Malformed(e) => write!(f, "malformed record: {e}"),
+            RecordError::Unsupported(v) => write!(f, "no migration from schema version {v}"),
+            RecordError::Migration { from, reason } => {
+                write!(f, "migration from schema version {from} failed: {reason}")
+            }
+        }
+    }
+}
+
+/// Serializes a record in the current format, tagged with its schema version.
+pub fn encode<T: Record>(record: &T) -> Value {
+    let mut value = serde_json::to_value(record).unwrap_or(Value::Null);
+    if let Value::Object(map) = &mut value {
+        map.insert(VERSION_KEY.to_string(), T::SCHEMA_VERSION.into());
+    }
+    value
+}
+
+/// Reads a record of any known version, migrating it to the current format.
+pub fn decode<T: Record>(value: Value) -> Result<T, RecordError> {
+    let Value::Object(mut map) = value else {
+        return Err(RecordError::Malformed("not a JSON object".to_string()));
+    };
+    let mut version = match map.remove(VERSION_KEY) {
+        None => 0,
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| RecordError::Malformed(format!("invalid {VERSION_KEY}")))?,
+    };
+
+    while version < T::SCHEMA_VERSION {
+        let (_, migrate) = T::MIGRATIONS
+            .iter()
+            .find(|(from, _)| *from == version)
+            .ok_or(RecordError::Unsupported(version))?;
+        migrate(&mut map).map_err(|reason| RecordError::Migration {
+            from: version,
+            reason,
+        })?;
+        version += 1;
+    }
+    if version > T::SCHEMA_VERSION {
+        warn!(
+            version,
+            current = T::SCHEMA_VERSION,
+            "Reading record written by a newer release"
+        );
+    }
+
+    serde_json::from_value(Value::Object(map)).map_err(|e| RecordError::Malformed(e.to_string()))
+}
+
+// ── Record types ──────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, Enum)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+/// A message as sent or received on a session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredMessage {
+    pub session_id: String,
+    pub direction: Direction,
+    pub msg_type: String,
+    #[serde(default)]
+    pub sequence_number: Option<u64>,
+    /// Exact wire bytes; parsed fields are derived from these on read.
+    pub raw_base64: String,
+    pub recorded_at_ms: u64,
+    /// For an inbound ExecutionReport, the order it was correlated with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution: Option<ReportContext>,
+    /// For an outbound message a client sent, who sent it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<Attribution>,
+}
+
+impl Record for StoredMessage {
+    const SCHEMA_VERSION: u32 = 1;
+    const MIGRATIONS: &'static [(u32, Migration)] = &[(0, message_v0_to_v1)];
+}
+
+/// Version 0 is the unversioned `fix_messages` row shape: text `raw_message`,
+/// a `fields` object and a `sent_at` timestamptz.
+fn message_v0_to_v1(map: &mut Map<String, Value>) -> Result<(), String> {
+    let raw = match map.remove("raw_message") {
+        Some(Value::String(raw)) => raw.replace('|', "\x01"),
+        _ => return Err("raw_message is missing".to_string()),
+    };
+    map.insert("raw_base64".to_string(), BASE64.encode(raw).into());
+    map.remove("fields");
+
+    let recorded_at_ms = match map.remove("sent_at") {
+        Some(Value::String(ts)) => {
+            clock::parse_rfc3339(&ts).ok_or_else(|| format!("invalid sent_at {ts:?}"))?
+        }
+        _ => 0,
+    };
+    map.insert("recorded_at_ms".to_string(), recorded_at_ms.into());
+    Ok(())
+}
+
+impl Record for OrderContext {
+    const SCHEMA_VERSION: u32 = 1;
+    const MIGRATIONS: &'static [(u32, Migration)] = &[];
+}
+
+impl Record for QueuedMessage {
+    const SCHEMA_VERSION: u32 = 1;
+    const MIGRATIONS: &'static [(u32, Migration)] = &[];
+}
+
+impl Record for JournalEntry {
+    const SCHEMA_VERSION: u32 = 1;
+    const MIGRATIONS: &'static [(u32, Migration)] = &[];
+}
+
+impl Record for AuditEntry {
+    const SCHEMA_VERSION: u32 = 1;
+    const MIGRATIONS: &'static [(u32, Migration)] = &[];
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordKind {
+    Message,
+    Order,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UpgradeQuery {
+    kind: RecordKind,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UpgradeError {
+    line: usize,
+    message: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UpgradeResponse {
+    #[schema(value_type = Vec<Object>)]
+    records: Vec<Value>,
+    count: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<UpgradeError>,
+}
+
+/// Upgrades exported records (one JSON object per line) to the current format.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/records/upgrade",
+    tag = "admin",
+    params(UpgradeQuery),
+    request_body(content = String, content_type = "application/x-ndjson"),
+    responses(
+        (status = 200, description = "OK", body = UpgradeResponse),
+    )
+)]
+pub async fn upgrade(
+    State(_state): State<AppState>,
+    Query(q): Query<UpgradeQuery>,
+    body: String,
+) -> Result<Json<UpgradeResponse>, StatusCode> {
+    let mut records = Vec::new();
+    let mut errors = Vec::new();
+
+    for (i, line) in body.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let upgraded = serde_json::from_str::<Value>(line)
+            .map_err(|e| RecordError::Malformed(e.to_string()))
+            .and_then(|value| match q.kind {
+                RecordKind::Message => decode::<StoredMessage>(value).map(|r| encode(&r)),
+                RecordKind::Order => decode::<OrderContext>(value).map(|r| encode(&r)),
+            });
+        match upgraded {
+            Ok(record) => records.push(record),
+            Err(e) => errors.push(UpgradeError {
+                line: i + 1,
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    let count = records.len();
+    Ok(Json(UpgradeResponse {
+        records,
+        count,
+        errors,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn migrates_unversioned_message_rows() {
+        let row = json!({
+            "session_id": "ALICE->BROKER_A",
+            "direction": "outbound",
+            "msg_type": "D",
+            "sequence_number": 7,
+            "raw_message": "8=FIX.4.4|35=D|",
+            "fields": { "35": "D" },
+            "sent_at": "2026-02-23 09:30:00.250+09"
+        });
+        let msg: StoredMessage = decode(row).unwrap();
+        assert_eq!(msg.raw_base64, BASE64.encode("8=FIX.4.4\x0135=D\x01"));
+        assert_eq!(msg.recorded_at_ms, 1_771_806_600_250);
+        assert_eq!(msg.sequence_number, Some(7));
+    }
+
+    #[test]
+    fn current_version_round_trips_and_newer_fields_are_ignored() {
+        let msg = StoredMessage {
+            session_id: "S".to_string(),
+            direction: Direction::Inbound,
+            msg_type: "8".to_string(),
+            sequence_number: None,
+            raw_base64: String::new(),
+            recorded_at_ms: 1,
+            execution: None,
+            attribution: None,
+        };
+        let mut value = encode(&msg);
+        assert_eq!(value[VERSION_KEY], 1);
+        value[VERSION_KEY] = 2.into();
+        value["added_later"] = json!(true);
+        let read: StoredMessage = decode(value).unwrap();
+        assert_eq!(read.recorded_at_ms, 1);
+    }
+
+    #[test]
+    fn rejects_unknown_old_versions() {
+        let err = decode::<OrderContext>(json!({ "cl_ord_id": "A" })).unwrap_err();
+        assert_eq!(err, RecordError::Unsupported(0));
+    }
+}
@@ -0,0 +1,251 @@
+//! The FIX sessions, by session ID.
+//!
+//! [`SessionRegistry`] maps each `SENDER->TARGET` to a [`SessionHandle`] in a
+//! concurrent map, so finding a session is a hash lookup and handlers working
+//! on different sessions do not wait for each other. A handle holds its
+//! session as an immutable snapshot that is swapped atomically: readers take
+//! the current snapshot without locking, and writers swap in a changed copy,
+//! retrying if another writer swapped first. Lists come back in the order
+//! sessions were registered.
+//!
+//! Outbound MsgSeqNums are allocated from a counter beside the snapshot, so
+//! concurrent sends on a session each take the next number with one atomic
+//! add, and the snapshot's `msg_seq_num` catches up afterwards. A number
+//! whose message is not sent can be given back while it is still the last
+//! one allocated.
+
+use arc_swap::{ArcSwap, Guard};
+use dashmap::{mapref::entry::Entry, DashMap};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use crate::FixSession;
+
+#[derive(Debug, Default)]
+pub struct SessionRegistry {
+    sessions: DashMap<String, SessionHandle>,
+    registered: AtomicU64,
+}
+
+/// One session's shared state.
+#[derive(Debug, Clone)]
+pub struct SessionHandle(Arc<Handle>);
+
+#[derive(Debug)]
+struct Handle {
+    /// Where the session comes in the registration order.
+    position: u64,
+    /// The last outbound MsgSeqNum allocated, 0 if none.
+    last_seq_num: AtomicU64,
+    session: ArcSwap<FixSession>,
+}
+
+impl SessionHandle {
+    /// The session as it is now.
+    pub fn snapshot(&self) -> Arc<FixSession> {
+        self.0.session.load_full()
+    }
+
+    /// Changes the session, returning what `change` returns. `change` runs
+    /// again on the newer state if another change lands first, so it must
+    /// not do anything but change the session.
+    pub fn update<R>(&self, mut change: impl FnMut(&mut FixSession) -> R) -> R {
+        let mut current = self.0.session.load_full();
+        loop {
+            let mut next = FixSession::clone(&current);
+            let result = change(&mut next);
+            let previous = self.0.session.compare_and_swap(&current, Arc::new(next));
+            if Arc::ptr_eq(&previous, &current) {
+                return result;
+            }
+            current = Guard::into_inner(previous);
+        }
+    }
+
+    /// Allocates the session's next outbound MsgSeqNum. Each caller gets a
+    /// number of its own, and none is skipped.
+    pub fn allocate_seq_num(&self) -> u64 {
+        self.0.last_seq_num.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Gives back `seq`, so the next allocation takes it again, unless a
+    /// later number has been allocated since. Returns whether it was.
+    pub fn release_seq_num(&self, seq: u64) -> bool {
+        let last = &self.0.last_seq_num;
+        last.compare_exchange(seq, seq - 1, Ordering::Relaxed, Ordering::Relaxed).is_ok()
+    }
+
+    /// The last outbound MsgSeqNum allocated, 0 if none.
+    pub fn last_seq_num(&self) -> u64 {
+        self.0.last_seq_num.load(Ordering::Relaxed)
+    }
+
+    /// Records that `seq` was allocated elsewhere, as by the shared state
+    /// store, so numbering here carries on after it.
+    pub fn advance_seq_num(&self, seq: u64) {
+        self.0.last_seq_num.fetch_max(seq, Ordering::Relaxed);
+    }
+}
+
+impl SessionRegistry {
+    pub fn new(sessions: Vec<FixSession>) -> Self {
+        let registry = Self::default();
+        for session in sessions {
+            registry.insert(session);
+        }
+        registry
+    }
+
+    /// Registers `session`, replacing any with its ID in place.
+    pub fn insert(&self, session: FixSession) {
+        match self.sessions.entry(session.session_id.clone()) {
+            Entry::Occupied(entry) => {
+                let handle = &entry.get().0;
+                let last_seq_num = session.msg_seq_num.saturating_sub(1);
+                handle.last_seq_num.store(last_seq_num, Ordering::Relaxed);
+                handle.session.store(Arc::new(session));
+            }
+            Entry::Vacant(entry) => {
+                let handle = Handle {
+                    position: self.registered.fetch_add(1, Ordering::Relaxed),
+                    last_seq_num: AtomicU64::new(session.msg_seq_num.saturating_sub(1)),
+                    session: ArcSwap::from_pointee(session),
+                };
+                entry.insert(SessionHandle(Arc::new(handle)));
+            }
+        }
+    }
+
+    /// Unregisters a session; whether it was registered.
+    pub fn remove(&self, session_id: &str) -> bool {
+        self.sessions.remove(session_id).is_some()
+    }
+
+    pub fn get(&self, session_id: &str) -> Option<SessionHandle> {
+        self.sessions.get(session_id).map(|entry| entry.value().clone())
+    }
+
+    /// The session as it is now, if it is registered.
+    pub fn snapshot(&self, session_id: &str) -> Option<Arc<FixSession>> {
+        self.get(session_id).map(|handle| handle.snapshot())
+    }
+
+    /// Changes a registered session; see [`SessionHandle::update`].
+    pub fn update<R>(
+        &self,
+        session_id: &str,
+        change: impl FnMut(&mut FixSession) -> R,
+    ) -> Option<R> {
+        self.get(session_id).map(|handle| handle.update(change))
+    }
+
+    /// Every session as it is now, in registration order.
+    pub fn snapshots(&self) -> Vec<Arc<FixSession>> {
+        let mut handles: Vec<SessionHandle> = self
+            .sessions
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        handles.sort_by_key(|handle| handle.0.position);
+        handles.iter().map(SessionHandle::snapshot).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, seed_sessions};
+
+    #[test]
+    fn sessions_are_found_by_id_and_listed_in_registration_order() {
+        let seeded = seed_sessions(&Config::default());
+        let ids: Vec<String> = seeded.iter().map(|s| s.session_id.clone()).collect();
+        let registry = SessionRegistry::new(seeded);
+        let listed: Vec<String> = registry
+            .snapshots()
+            .iter()
+            .map(|s| s.session_id.clone())
+            .collect();
+        assert_eq!(listed, ids);
+
+        let before = registry.snapshot(&ids[0]).unwrap();
+        let seq = registry.update(&ids[0], |s| {
+            s.msg_seq_num += 1;
+            s.msg_seq_num
+        });
+        assert_eq!(seq, Some(before.msg_seq_num + 1));
+        assert_eq!(registry.snapshot(&ids[0]).unwrap().msg_seq_num, seq.unwrap());
+        assert_eq!(registry.update("ALICE->NOBODY", |s| s.msg_seq_num), None);
+
+        let mut replaced = FixSession::clone(&before);
+        replaced.state = "ACTIVE".to_string();
+        registry.insert(replaced);
+        let listed = registry.snapshots();
+        assert_eq!(listed.len(), ids.len());
+        assert_eq!(listed[0].state, "ACTIVE", "replaced in place");
+        assert!(registry.remove(&ids[0]));
+        assert!(registry.snapshot(&ids[0]).is_none());
+    }
+
+    #[test]
+    fn concurrent_updates_are_not_lost() {
+        let registry = Arc::new(SessionRegistry::new(seed_sessions(&Config::default())));
+        let id = registry.snapshots()[0].session_id.clone();
+        let start = registry.snapshot(&id).unwrap().expected_seq_num;
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let (registry, id) = (registry.clone(), id.clone());
+                std::thread::spawn(move || {
+                    for _ in 0..1_000 {
+                        registry.update(&id, |s| s.expected_seq_num += 1);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(registry.snapshot(&id).unwrap().expected_seq_num, start + 8_000);
+    }
+
+    #[test]
+    fn concurrent_sends_get_every_seq_num_once() {
+        let registry = SessionRegistry::new(seed_sessions(&Config::default()));
+        let id = registry.snapshots()[0].session_id.clone();
+        let handle = registry.get(&id).unwrap();
+        let first = handle.snapshot().msg_seq_num;
+        assert_eq!(handle.last_seq_num(), first - 1);
+
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let handle = handle.clone();
+                std::thread::spawn(move || {
+                    (0..1_000)
+                        .map(|_| handle.allocate_seq_num())
+                        .collect::<Vec<u64>>()
+                })
+            })
+            .collect();
+        let mut allocated: Vec<u64> = threads
+            .into_iter()
+            .flat_map(|thread| thread.join().unwrap())
+            .collect();
+        allocated.sort_unstable();
+        let expected: Vec<u64> = (first..first + 8_000).collect();
+        assert_eq!(allocated, expected, "no duplicates and no gaps");
+
+        let last = first + 7_999;
+        assert_eq!(handle.last_seq_num(), last);
+        handle.advance_seq_num(last + 10);
+        handle.advance_seq_num(first);
+        assert_eq!(handle.allocate_seq_num(), last + 11);
+
+        assert!(handle.release_seq_num(last + 11));
+        assert_eq!(handle.allocate_seq_num(), last + 11);
+        let later = handle.allocate_seq_num();
+        assert!(!handle.release_seq_num(last + 11));
+        assert_eq!(handle.last_seq_num(), later, "only the last number goes back");
+    }
+}
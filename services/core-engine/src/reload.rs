@@ -0,0 +1,344 @@
+//! Reloading the configuration while the engine runs.
+//!
+//! `POST /api/v1/admin/reload`, or a SIGHUP, reads the configuration file
+//! and the `FIX_RULE_PACKS` files again, and puts what it can in force
+//! without a restart:
+//!
+//! - Sessions added to the file are registered, with their dictionary, and
+//!   resume as at startup, and sessions no longer in it are dropped. Sessions in both keep their
+//!   connection, sequence numbers and state.
+//! - A session's rule packs, schedule and timestamp precision change in
+//!   place, and its heartbeat interval from its next Logon.
+//! - The rule packs in the files are loaded again, replacing those of the
+//!   same name; packs registered through the API stay.
+//! - The rate limit changes at once, keeping each client's bucket.
+//!
+//! Anything else that changed — the listeners, authentication, logging, or a
+//! session's FIX version, transport, dictionary or store — is reported as
+//! needing a restart, and stays as it was until then. A file that fails the
+//! checks made at startup changes nothing. Environment variables are read
+//! again too, but hold the values the process started with.
+
+use axum::{extract::State, http::StatusCode, response::Json};
+use serde::Serialize;
+use std::{collections::HashSet, sync::Arc};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    audit::{self, Actor, AuditAction},
+    config::Config,
+    dictionary,
+    problem::{ApiError, Problem},
+    rate_limit::{RateLimitConfig, RateLimiter},
+    registry::SessionRegistry,
+    resume_sessions,
+    rules::RulePacks,
+    seed_sessions,
+    timeline::EventKind,
+    AppState, FixSession,
+};
+
+/// The session settings a reload changes without reconnecting it.
+const IN_PLACE: [&str; 4] = ["rule_packs", "schedule", "heartbeat_secs", "timestamp_precision"];
+
+/// What a reload changed, and what waits for a restart.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ReloadReport {
+    pub sessions_added: Vec<String>,
+    pub sessions_removed: Vec<String>,
+    /// Sessions whose rule packs, schedule, heartbeat interval or timestamp
+    /// precision changed.
+    pub sessions_updated: Vec<String>,
+    /// Rule packs read from their files again.
+    pub rule_packs: Vec<String>,
+    /// The rate limit now in force, if it changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<String>,
+    /// Settings that changed but take effect only after a restart, e.g.
+    /// `listeners` or `sessions.ALICE->BROKER_A.transport`.
+    pub restart_required: Vec<String>,
+}
+
+impl ReloadReport {
+    /// The report in a line, for the timeline and the audit log.
+    fn summary(&self) -> String {
+        let list = |items: &[String]| items.join(",");
+        format!(
+            "added=[{}] removed=[{}] updated=[{}] rule_packs={} rate_limit={} \
+             restart_required=[{}]",
+            list(&self.sessions_added),
+            list(&self.sessions_removed),
+            list(&self.sessions_updated),
+            self.rule_packs.len(),
+            self.rate_limit.as_deref().unwrap_or("unchanged"),
+            list(&self.restart_required)
+        )
+    }
+}
+
+/// Reads the configuration again and applies it, or says why it is invalid
+/// and changes nothing.
+async fn apply(state: &AppState) -> Result<ReloadReport, String> {
+    let config = Config::from_env()?;
+    let rate_limit = RateLimitConfig::from_env(&config.rate_limit)
+        .map_err(|e| format!("rate limit: {e}"))?;
+    let packs = RulePacks::reread()?;
+    let configured = seed_sessions(&config);
+
+    // Held throughout, so reloads do not interleave.
+    let mut in_force = state.config.lock().await;
+    let mut report = ReloadReport::default();
+    let global = [
+        ("listeners", in_force.listeners != config.listeners),
+        ("auth", in_force.auth != config.auth),
+        ("logging", in_force.logging != config.logging),
+    ];
+    for (setting, changed) in global {
+        if changed {
+            report.restart_required.push(setting.to_string());
+        }
+    }
+
+    // Read before anything changes, so a bad file fails the reload as it
+    // would fail startup.
+    let mut dictionaries = Vec::new();
+    for s in configured.iter().filter(|s| state.sessions.get(&s.session_id).is_none()) {
+        if let Some(path) = &s.dictionary {
+            let id = &s.session_id;
+            let dict = dictionary::load(path);
+            let dict = dict.map_err(|e| format!("{id} dictionary {path}: {e}"))?;
+            dictionaries.push((id.clone(), dict));
+        }
+    }
+
+    let added = update_sessions(&state.sessions, configured, &mut report);
+    for session_id in &report.sessions_removed {
+        state.actors.remove(session_id);
+        state.dictionaries.remove_session(session_id);
+    }
+    for (session_id, dict) in dictionaries {
+        state.dictionaries.insert_session(&session_id, dict);
+    }
+    if !added.is_empty() {
+        for session in resume(state, added).await? {
+            state.sessions.insert(session);
+        }
+    }
+
+    {
+        let mut registry = state.rule_packs.lock().await;
+        for pack in packs {
+            report.rule_packs.push(pack.name.clone());
+            registry.add(pack);
+        }
+    }
+
+    let mut limiter = state.rate_limiter.lock().await;
+    if limiter.as_ref().map(RateLimiter::config) != rate_limit {
+        *limiter = match (limiter.take(), rate_limit) {
+            (Some(mut current), Some(config)) => {
+                current.reconfigure(config);
+                Some(current)
+            }
+            (None, Some(config)) => Some(RateLimiter::new(config)),
+            (_, None) => None,
+        };
+        report.rate_limit = Some(
+            rate_limit
+                .as_ref()
+                .map_or_else(|| "rate limit off".to_string(), RateLimitConfig::describe),
+        );
+    }
+    drop(limiter);
+
+    *in_force = config;
+    let mut timeline = state.timeline.lock().await;
+    for session_id in &report.sessions_added {
+        let detail = "added by reload";
+        timeline.record(EventKind::SessionScheduled, Some(session_id), detail);
+    }
+    timeline.record(EventKind::ConfigReloaded, None, report.summary());
+    Ok(report)
+}
+
+/// Brings the registered sessions in line with the configured ones, and
+/// returns those to register.
+fn update_sessions(
+    sessions: &SessionRegistry,
+    configured: Vec<FixSession>,
+    report: &mut ReloadReport,
+) -> Vec<FixSession> {
+    let ids: HashSet<&str> = configured.iter().map(|s| s.session_id.as_str()).collect();
+    for session in sessions.snapshots() {
+        if !ids.contains(session.session_id.as_str()) {
+            sessions.remove(&session.session_id);
+            report.sessions_removed.push(session.session_id.clone());
+        }
+    }
+    let mut added = Vec::new();
+    for new in configured {
+        let Some(handle) = sessions.get(&new.session_id) else {
+            report.sessions_added.push(new.session_id.clone());
+            added.push(new);
+            continue;
+        };
+        let session = handle.snapshot();
+        let transport = session.acceptor != new.acceptor
+            || session.host != new.host
+            || session.port != new.port;
+        let fixed = [
+            ("fix_version", session.fix_version != new.fix_version),
+            ("transport", transport),
+            ("dictionary", session.dictionary != new.dictionary),
+            ("store", session.store != new.store),
+        ];
+        for (setting, changed) in fixed {
+            if changed {
+                let id = &session.session_id;
+                report.restart_required.push(format!("sessions.{id}.{setting}"));
+            }
+        }
+        if session.rule_packs != new.rule_packs
+            || session.schedule != new.schedule
+            || session.heartbeat_secs != new.heartbeat_secs
+            || session.timestamp_precision != new.timestamp_precision
+        {
+            handle.update(|session| {
+                session.rule_packs = new.rule_packs.clone();
+                session.schedule = new.schedule.clone();
+                session.heartbeat_secs = new.heartbeat_secs;
+                session.timestamp_precision = new.timestamp_precision;
+                for setting in IN_PLACE {
+                    session.sources.insert(setting, new.sources[setting]);
+                }
+            });
+            report.sessions_updated.push(session.session_id.clone());
+        }
+    }
+    added
+}
+
+/// Resumes added sessions as at startup. The shared session state store is
+/// read on the blocking pool.
+async fn resume(state: &AppState, mut added: Vec<FixSession>) -> Result<Vec<FixSession>, String> {
+    let messages = Arc::clone(&state.messages).lock_owned().await;
+    let mut journal = Arc::clone(&state.journal).lock_owned().await;
+    let session_state = state.session_state.clone();
+    tokio::task::spawn_blocking(move || {
+        resume_sessions(&mut added, &messages, &mut journal, session_state.as_deref());
+        added
+    })
+    .await
+    .map_err(|e| format!("resuming the added sessions: {e}"))
+}
+
+/// Reloads the configuration file and rule pack files, applying what can
+/// change while the engine runs and reporting what needs a restart.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/reload",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Reloaded", body = ReloadReport),
+        (
+            status = 422,
+            description = "The configuration is invalid, and nothing changed",
+            body = Problem,
+            content_type = "application/problem+json"
+        ),
+    )
+)]
+pub async fn reload(
+    State(state): State<AppState>,
+    actor: Actor,
+) -> Result<Json<ReloadReport>, ApiError> {
+    match apply(&state).await {
+        Ok(report) => {
+            let summary = report.summary();
+            audit::record(&state, &actor, AuditAction::ConfigReloaded, None, summary).await;
+            Ok(Json(report))
+        }
+        Err(e) => {
+            warn!(error = %e, "Configuration reload rejected");
+            let error = ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_config");
+            Err(error.detail(e))
+        }
+    }
+}
+
+/// Reloads the configuration on each SIGHUP.
+#[cfg(unix)]
+pub async fn on_sighup(state: AppState) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            warn!(error = %e, "Failed to install the SIGHUP handler, reload through the API");
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        match apply(&state).await {
+            Ok(report) => {
+                info!(report = %report.summary(), "Configuration reloaded on SIGHUP");
+                let (actor, summary) = (Actor::system("SIGHUP"), report.summary());
+                audit::record(&state, &actor, AuditAction::ConfigReloaded, None, summary).await;
+            }
+            Err(e) => warn!(error = %e, "Configuration reload on SIGHUP rejected"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Schedule;
+
+    #[test]
+    fn reloads_change_sessions_in_place_and_report_the_rest() {
+        let mut running = seed_sessions(&Config::default());
+        running[0].msg_seq_num = 42;
+        running[0].state = "LOGGED_ON".to_string();
+        let mut configured = seed_sessions(&Config::default());
+        // BROKER_A gets a rule pack and a new port; MARKET_DATA is dropped
+        // and BROKER_B added.
+        configured[0].rule_packs = vec!["broker-a".to_string()];
+        configured[0].port = Some(9876);
+        configured[0].schedule = Some(Schedule {
+            start: "08:00".to_string(),
+            end: "17:00".to_string(),
+            days: Vec::new(),
+        });
+        configured[1].session_id = "ALICE->BROKER_B".to_string();
+
+        let running = SessionRegistry::new(running);
+        let mut report = ReloadReport::default();
+        let added = update_sessions(&running, configured, &mut report);
+        let running = running.snapshots();
+        assert_eq!(running.len(), 1);
+        let broker_a = &running[0];
+        assert_eq!((broker_a.msg_seq_num, broker_a.state.as_str()), (42, "LOGGED_ON"));
+        assert_eq!(broker_a.rule_packs, ["broker-a"]);
+        assert!(broker_a.schedule.is_some());
+        assert_eq!(broker_a.port, None, "the transport waits for a restart");
+        assert_eq!(added.len(), 1);
+        assert_eq!(
+            report,
+            ReloadReport {
+                sessions_added: vec!["ALICE->BROKER_B".to_string()],
+                sessions_removed: vec!["ALICE->MARKET_DATA".to_string()],
+                sessions_updated: vec!["ALICE->BROKER_A".to_string()],
+                restart_required: vec!["sessions.ALICE->BROKER_A.transport".to_string()],
+                ..ReloadReport::default()
+            }
+        );
+        assert_eq!(
+            report.summary(),
+            "added=[ALICE->BROKER_B] removed=[ALICE->MARKET_DATA] updated=[ALICE->BROKER_A] \
+             rule_packs=0 rate_limit=unchanged \
+             restart_required=[sessions.ALICE->BROKER_A.transport]"
+        );
+    }
+}
@@ -0,0 +1,522 @@
+//! Replays a session's stored messages for reconciliation.
+//!
+//! A replay selects messages by time or MsgSeqNum range and re-emits them,
+//! in order, to an HTTP callback, a Kafka REST Proxy topic, a configured
+//! sink, or back onto the FIX session itself. Messages re-emitted on the
+//! session are resent as PossDup copies (see [`poss_dup`]) and are not stored
+//! again, so the message store keeps one copy of each MsgSeqNum.
+//!
+//! A counterparty's ResendRequest is answered the same way, with admin
+//! messages and gaps in the store replaced by SequenceReset-GapFill (see
+//! [`fulfil_resend`]).
+
+use axum::{extract::State, http::StatusCode, response::Json};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    audit::{self, Actor, AuditAction},
+    clock::{epoch_ms, format_utc_timestamp},
+    message::{Field, FixMessage, SOH},
+    records::{self, Direction, StoredMessage},
+    session_events,
+    sinks::{self, ReplayMarker, Sink, SinkEvent, SinkKind},
+    traffic::Traffic,
+    AppState,
+};
+
+static REPLAYS: AtomicUsize = AtomicUsize::new(0);
+
+// ── PossDup resends ───────────────────────────────────────────────────────────
+
+/// A resend of `raw`: PossDupFlag(43)=Y, OrigSendingTime(122) set to the
+/// original SendingTime(52), SendingTime set to `now_ms`, and BodyLength and
+/// CheckSum recomputed. MsgSeqNum and the body are unchanged.
+pub fn poss_dup(raw: &[u8], now_ms: u64) -> Option<Vec<u8>> {
+    let msg = FixMessage::parse(raw).ok()?;
+    let begin_string = msg.fields.first().filter(|f| f.tag == 8)?.value_str();
+    let orig_sending_time = msg.fields.iter().find(|f| f.tag == 52)?.value.clone();
+    let has_orig = msg.fields.iter().any(|f| f.tag == 122);
+
+    let mut body = Vec::with_capacity(msg.fields.len() + 2);
+    for field in msg
+        .fields
+        .iter()
+        .filter(|f| !matches!(f.tag, 8 | 9 | 10 | 43))
+    {
+        match field.tag {
+            52 => {
+                body.push(Field {
+                    tag: 43,
+                    value: b"Y".to_vec(),
+                });
+                body.push(Field {
+                    tag: 52,
+                    value: format_utc_timestamp(now_ms).into_bytes(),
+                });
+                if !has_orig {
+                    body.push(Field {
+                        tag: 122,
+                        value: orig_sending_time.clone(),
+                    });
+                }
+            }
+            _ => body.push(field.clone()),
+        }
+    }
+    Some(FixMessage::framed(&begin_string, body).encode_with(msg.delimiter))
+}
+
+// ── Handler ───────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReplayTarget {
+    /// Each message is POSTed to `url` as a sink event.
+    Callback { url: String },
+    /// A Kafka REST Proxy topic URL.
+    KafkaRest { url: String },
+    /// A sink configured in `FIX_SINKS`.
+    Sink { name: String },
+    /// Outbound messages are resent on the session with PossDup.
+    Session,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReplayRequest {
+    session_id: String,
+    /// Time range `[from_ms, to_ms)`; use either this or a MsgSeqNum range.
+    #[serde(default)]
+    from_ms: Option<u64>,
+    #[serde(default)]
+    to_ms: Option<u64>,
+    /// MsgSeqNum range `[from_seq, to_seq]`; no `to_seq` means no upper bound.
+    #[serde(default)]
+    from_seq: Option<u64>,
+    #[serde(default)]
+    to_seq: Option<u64>,
+    /// Both directions when omitted; session replays are outbound only.
+    #[serde(default)]
+    direction: Option<Direction>,
+    target: ReplayTarget,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReplayResponse {
+    replay_id: String,
+    session_id: String,
+    total: usize,
+    replayed: usize,
+    failed: usize,
+}
+
+enum Range {
+    Time(u64, u64),
+    Seq(u64, Option<u64>),
+}
+
+impl Range {
+    fn from_request(req: &ReplayRequest) -> Option<Self> {
+        match (req.from_ms, req.to_ms, req.from_seq, req.to_seq) {
+            (Some(from), Some(to), None, None) if from < to => Some(Range::Time(from, to)),
+            (None, None, Some(from), to) if to.is_none_or(|to| from <= to) => {
+                Some(Range::Seq(from, to))
+            }
+            _ => None,
+        }
+    }
+
+    fn contains(&self, m: &StoredMessage) -> bool {
+        match *self {
+            Range::Time(from, to) => (from..to).contains(&m.recorded_at_ms),
+            Range::Seq(from, to) => m
+                .sequence_number
+                .is_some_and(|seq| seq >= from && to.is_none_or(|to| seq <= to)),
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/fix/replay",
+    tag = "fix",
+    request_body = ReplayRequest,
+    responses(
+        (status = 200, description = "OK", body = ReplayResponse),
+        (status = 400, description = "Invalid range or target"),
+    )
+)]
+pub async fn replay(
+    State(state): State<AppState>,
+    actor: Actor,
+    Json(req): Json<ReplayRequest>,
+) -> Result<Json<ReplayResponse>, StatusCode> {
+    let range = Range::from_request(&req).ok_or(StatusCode::BAD_REQUEST)?;
+    let direction = match (&req.target, req.direction) {
+        (ReplayTarget::Session, Some(Direction::Inbound)) => {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        (ReplayTarget::Session, _) => Some(Direction::Outbound),
+        (_, direction) => direction,
+    };
+    let sink = match &req.target {
+        ReplayTarget::Callback { url } => Some(Sink {
+            name: "callback".to_string(),
+            kind: SinkKind::Webhook,
+            url: url.clone(),
+        }),
+        ReplayTarget::KafkaRest { url } => Some(Sink {
+            name: "kafka_rest".to_string(),
+            kind: SinkKind::KafkaRest,
+            url: url.clone(),
+        }),
+        ReplayTarget::Sink { name } => Some(
+            state
+                .sinks
+                .get(name)
+                .cloned()
+                .ok_or(StatusCode::BAD_REQUEST)?,
+        ),
+        ReplayTarget::Session => None,
+    };
+
+    let messages: Vec<StoredMessage> = state
+        .messages
+        .lock()
+        .await
+        .for_session(&req.session_id)
+        .filter(|m| direction.is_none_or(|d| m.direction == d) && range.contains(m))
+        .cloned()
+        .collect();
+
+    let replay_id = format!("RP-{}", REPLAYS.fetch_add(1, Ordering::Relaxed) + 1);
+    let total = messages.len();
+    let target = match &sink {
+        Some(sink) => sink.url.as_str(),
+        None => "session",
+    };
+    audit::record(
+        &state,
+        &actor,
+        AuditAction::ReplayStarted,
+        Some(&req.session_id),
+        format!("{replay_id} of {total} messages to {target}"),
+    )
+    .await;
+    let mut replayed = 0;
+    for (i, message) in messages.into_iter().enumerate() {
+        let ok = match &sink {
+            Some(sink) => {
+                let mut event = SinkEvent::message(
+                    &message.session_id,
+                    message.recorded_at_ms,
+                    records::encode(&message),
+                );
+                event.replay = Some(ReplayMarker {
+                    redrive_id: replay_id.clone(),
+                    position: i + 1,
+                    total,
+                });
+                match sinks::deliver(&state.http, sink, &event).await {
+                    Ok(()) => true,
+                    Err(e) => {
+                        warn!(replay_id = %replay_id, error = %e, "Replay delivery failed");
+                        false
+                    }
+                }
+            }
+            None => resend(&state, &message).await,
+        };
+        replayed += usize::from(ok);
+    }
+
+    info!(
+        replay_id = %replay_id,
+        session_id = %req.session_id,
+        total,
+        replayed,
+        "Replay completed"
+    );
+    Ok(Json(ReplayResponse {
+        replay_id,
+        session_id: req.session_id,
+        total,
+        replayed,
+        failed: total - replayed,
+    }))
+}
+
+/// Re-emits a stored outbound message on its session as a PossDup copy.
+async fn resend(state: &AppState, message: &StoredMessage) -> bool {
+    let Some(raw) = BASE64
+        .decode(&message.raw_base64)
+        .ok()
+        .and_then(|raw| poss_dup(&raw, epoch_ms()))
+    else {
+        warn!(
+            session_id = %message.session_id,
+            sequence_number = ?message.sequence_number,
+            "Stored message cannot be resent"
+        );
+        return false;
+    };
+    emit(
+        state,
+        &message.session_id,
+        &message.msg_type,
+        message.sequence_number,
+        &raw,
+    )
+    .await;
+    true
+}
+
+/// Publishes a resent message to the sinks. Resends are not stored, so the
+/// store keeps the original of each MsgSeqNum.
+pub async fn emit(
+    state: &AppState,
+    session_id: &str,
+    msg_type: &str,
+    seq: Option<u64>,
+    raw: &[u8],
+) {
+    let resent = StoredMessage {
+        session_id: session_id.to_string(),
+        direction: Direction::Outbound,
+        msg_type: msg_type.to_string(),
+        sequence_number: seq,
+        raw_base64: BASE64.encode(raw),
+        recorded_at_ms: epoch_ms(),
+        execution: None,
+        attribution: None,
+    };
+    info!(
+        session_id = %resent.session_id,
+        msg_type = %resent.msg_type,
+        sequence_number = ?resent.sequence_number,
+        "FIX message resent (PossDup)"
+    );
+    sinks::publish(
+        state,
+        SinkEvent::message(
+            &resent.session_id,
+            resent.recorded_at_ms,
+            records::encode(&resent),
+        ),
+    );
+    session_events::on_message(state, session_id, Direction::Outbound, msg_type, raw).await;
+    state.traffic.publish(Traffic {
+        session_id: resent.session_id,
+        direction: Direction::Outbound,
+        msg_type: resent.msg_type,
+        sequence_number: seq,
+        raw: raw.to_vec(),
+        at_ms: resent.recorded_at_ms,
+        resend: true,
+    });
+}
+
+// ── ResendRequest fulfilment ──────────────────────────────────────────────────
+
+/// Session-level MsgTypes, which are never resent.
+fn is_admin(msg_type: &str) -> bool {
+    matches!(msg_type, "0" | "1" | "2" | "3" | "4" | "5" | "A")
+}
+
+/// Header of the messages answering a ResendRequest: our side of the session.
+pub struct ResendHeader<'a> {
+    pub begin_string: &'a str,
+    pub sender_comp_id: &'a str,
+    pub target_comp_id: &'a str,
+}
+
+/// One message answering a ResendRequest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resent {
+    pub msg_type: String,
+    pub seq: u64,
+    pub raw: Vec<u8>,
+}
+
+/// Answers a ResendRequest for `begin..=end` (`end` 0 meaning the last
+/// message sent) from `sent`, the session's stored outbound messages.
+/// Application messages are resent as [`poss_dup`] copies; each run of admin
+/// messages, or of MsgSeqNums missing from the store, becomes one
+/// SequenceReset-GapFill pointing past it.
+pub fn fulfil_resend(
+    sent: &[StoredMessage],
+    begin: u64,
+    end: u64,
+    header: &ResendHeader<'_>,
+    now_ms: u64,
+) -> Vec<Resent> {
+    let mut by_seq = BTreeMap::new();
+    for m in sent.iter().filter(|m| m.direction == Direction::Outbound) {
+        if let Some(seq) = m.sequence_number {
+            by_seq.entry(seq).or_insert(m);
+        }
+    }
+    let last = by_seq.keys().next_back().copied().unwrap_or(0);
+    let end = if end == 0 { last } else { end.min(last) };
+
+    let mut out = Vec::new();
+    let mut gap_from = None;
+    for seq in begin.max(1)..=end {
+        let app = by_seq.get(&seq).filter(|m| !is_admin(&m.msg_type));
+        let copy = app.and_then(|m| {
+            let raw = BASE64.decode(&m.raw_base64).ok()?;
+            Some((m, poss_dup(&raw, now_ms)?))
+        });
+        if app.is_some() && copy.is_none() {
+            warn!(
+                sequence_number = seq,
+                "Stored message cannot be resent, gap filling it"
+            );
+        }
+        match copy {
+            Some((m, raw)) => {
+                if let Some(from) = gap_from.take() {
+                    out.push(gap_fill(header, from, seq, now_ms));
+                }
+                out.push(Resent {
+                    msg_type: m.msg_type.clone(),
+                    seq,
+                    raw,
+                });
+            }
+            None => {
+                gap_from.get_or_insert(seq);
+            }
+        }
+    }
+    if let Some(from) = gap_from {
+        out.push(gap_fill(header, from, end + 1, now_ms));
+    }
+    out
+}
+
+/// SequenceReset-GapFill(35=4, 123=Y) sent as MsgSeqNum `seq`, moving the
+/// counterparty's expected sequence number to `new_seq`.
+fn gap_fill(header: &ResendHeader<'_>, seq: u64, new_seq: u64, now_ms: u64) -> Resent {
+    let sending_time = format_utc_timestamp(now_ms);
+    let body = [
+        (35, "4".to_string()),
+        (49, header.sender_comp_id.to_string()),
+        (56, header.target_comp_id.to_string()),
+        (34, seq.to_string()),
+        (43, "Y".to_string()),
+        (52, sending_time.clone()),
+        (122, sending_time),
+        (123, "Y".to_string()),
+        (36, new_seq.to_string()),
+    ]
+    .into_iter()
+    .map(|(tag, value)| Field {
+        tag,
+        value: value.into_bytes(),
+    })
+    .collect();
+    Resent {
+        msg_type: "4".to_string(),
+        seq,
+        raw: FixMessage::framed(header.begin_string, body).encode_with(SOH),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poss_dup_marks_resends() {
+        let raw = FixMessage::framed(
+            "FIX.4.4",
+            [
+                "35=D",
+                "49=ALICE",
+                "56=BROKER_A",
+                "34=7",
+                "52=20260223-00:00:00.000",
+                "55=7203",
+            ]
+            .iter()
+            .map(|f| {
+                let (tag, value) = f.split_once('=').unwrap();
+                Field {
+                    tag: tag.parse().unwrap(),
+                    value: value.as_bytes().to_vec(),
+                }
+            })
+            .collect(),
+        )
+        .encode_with(b'|');
+
+        let resent = poss_dup(&raw, 1_771_804_800_250).unwrap();
+        let text = String::from_utf8(resent.clone()).unwrap();
+        assert!(
+            text.contains("|34=7|43=Y|52=20260223-00:00:00.250|122=20260223-00:00:00.000|55=7203|")
+        );
+        assert!(crate::structure::check_framing(&resent).is_empty());
+
+        // Resending a resend keeps the first OrigSendingTime.
+        let again = String::from_utf8(poss_dup(&resent, 1_771_804_801_000).unwrap()).unwrap();
+        assert!(again.contains("|43=Y|52=20260223-00:00:01.000|122=20260223-00:00:00.000|"));
+        assert_eq!(again.matches("|43=").count(), 1);
+    }
+
+    #[test]
+    fn resend_requests_gap_fill_admin_messages_and_holes() {
+        let stored = |seq: u64, msg_type: &str| {
+            StoredMessage {
+            session_id: "ALICE->BROKER_A".to_string(),
+            direction: Direction::Outbound,
+            msg_type: msg_type.to_string(),
+            sequence_number: Some(seq),
+            raw_base64: BASE64.encode(format!(
+                "8=FIX.4.4|9=5|35={msg_type}|49=ALICE|56=BROKER_A|34={seq}|52=20260223-00:00:00.000|10=000|"
+            )),
+            recorded_at_ms: seq,
+            execution: None,
+            attribution: None,
+        }
+        };
+        // 1 Logon, 2 order, 3-4 heartbeats, 5 missing, 6 order, 7 heartbeat.
+        let sent = [
+            stored(1, "A"),
+            stored(2, "D"),
+            stored(3, "0"),
+            stored(4, "0"),
+            stored(6, "D"),
+            stored(7, "0"),
+        ];
+        let header = ResendHeader {
+            begin_string: "FIX.4.4",
+            sender_comp_id: "ALICE",
+            target_comp_id: "BROKER_A",
+        };
+
+        let resent = fulfil_resend(&sent, 1, 0, &header, 1_771_804_800_000);
+        let summary: Vec<(&str, u64)> = resent
+            .iter()
+            .map(|r| (r.msg_type.as_str(), r.seq))
+            .collect();
+        assert_eq!(summary, [("4", 1), ("D", 2), ("4", 3), ("D", 6), ("4", 7)]);
+        let text = |r: &Resent| String::from_utf8_lossy(&r.raw).replace('\x01', "|");
+        assert!(text(&resent[0])
+            .contains("|34=1|43=Y|52=20260223-00:00:00.000|122=20260223-00:00:00.000|123=Y|36=2|"));
+        assert!(text(&resent[1]).contains("|34=2|43=Y|"));
+        assert!(text(&resent[2]).contains("|34=3|") && text(&resent[2]).contains("|36=6|"));
+        assert!(text(&resent[4]).contains("|36=8|"));
+
+        let resent = fulfil_resend(&sent, 6, 6, &header, 1_771_804_800_000);
+        assert_eq!(resent.len(), 1);
+        assert_eq!(resent[0].msg_type, "D");
+        assert!(fulfil_resend(&sent, 9, 0, &header, 1_771_804_800_000).is_empty());
+    }
+}
@@ -0,0 +1,695 @@
+//! Validation rules beyond what a data dictionary can express.
+//!
+//! A conditional rule makes a field required when another field has one of a
+//! set of values, e.g. Price when OrdType is Limit. Rules are written as
+//! `Price if OrdType=2|4 in D|G`: alternatives for the required field are
+//! separated by `/` (any one satisfies the rule), and the optional `in` clause
+//! limits the rule to some MsgTypes. Fields may be given by name or tag.
+//!
+//! Rule packs are named sets of counterparty-specific rules, loaded from JSON
+//! files or the admin API and applied to the sessions that select them.
+//!
+//! Advisories never reject a message on their own: they warn about missing
+//! recommended fields and about deprecated ones.
+
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    audit::{self, Actor, AuditAction},
+    dictionary::Dictionary,
+    validation::Findings,
+    AppState, ErrorCategory, ValidationError,
+};
+
+/// Rules every dictionary-validated message is checked against.
+const BUILTIN: &[&str] = &[
+    "Price if OrdType=2|4 in D|G",
+    "StopPx if OrdType=3|4 in D|G",
+    "ExpireTime/ExpireDate if TimeInForce=6 in D|G",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleError(String);
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConditionalRule {
+    /// Any one of these fields satisfies the rule.
+    pub required: Vec<String>,
+    pub when_field: String,
+    pub when_values: Vec<String>,
+    /// MsgTypes the rule applies to; empty for all.
+    pub msg_types: Vec<String>,
+}
+
+impl ConditionalRule {
+    pub fn parse(spec: &str) -> Result<Self, RuleError> {
+        let malformed = || RuleError(format!("expected 'Field if Field=Value', got '{spec}'"));
+        let (required, condition) = spec.split_once(" if ").ok_or_else(malformed)?;
+        let (condition, msg_types) = match condition.split_once(" in ") {
+            Some((c, m)) => (c, m.split('|').map(|m| m.trim().to_string()).collect()),
+            None => (condition, Vec::new()),
+        };
+        let (when_field, when_values) = condition.split_once('=').ok_or_else(malformed)?;
+
+        let list = |s: &str, sep| -> Vec<String> {
+            s.split(sep)
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        let rule = Self {
+            required: list(required, '/'),
+            when_field: when_field.trim().to_string(),
+            when_values: list(when_values, '|'),
+            msg_types,
+        };
+        if rule.required.is_empty() || rule.when_field.is_empty() || rule.when_values.is_empty() {
+            return Err(malformed());
+        }
+        Ok(rule)
+    }
+
+    /// The error for `fields`, if the condition holds and no required field is
+    /// present. Fields the dictionary does not define never match.
+    fn check(&self, dict: &Dictionary, fields: &[(u32, String)]) -> Option<ValidationError> {
+        let tag = |name: &str| resolve(dict, name);
+        let value = |tag: u32| value_of(fields, tag);
+
+        if !self.msg_types.is_empty() {
+            let msg_type = value(35)?;
+            if !self.msg_types.contains(msg_type) {
+                return None;
+            }
+        }
+        let when_tag = tag(&self.when_field)?;
+        let actual = value(when_tag)?;
+        if !self.when_values.contains(actual) {
+            return None;
+        }
+        let required: Vec<u32> = self.required.iter().filter_map(|f| tag(f)).collect();
+        if required.is_empty() || required.iter().any(|t| value(*t).is_some()) {
+            return None;
+        }
+
+        let names: Vec<String> = required.iter().map(|t| dict.name_of(*t)).collect();
+        let field = match required.as_slice() {
+            [t] => format!("{} (tag {t})", names[0]),
+            _ => names.join(" or "),
+        };
+        Some(ValidationError {
+            path: None,
+            field: names[0].clone(),
+            tag: required[0],
+            message: format!(
+                "{field} is required when {}={actual}.",
+                dict.name_of(when_tag)
+            ),
+            category: ErrorCategory::Required,
+        })
+    }
+}
+
+fn resolve(dict: &Dictionary, field: &str) -> Option<u32> {
+    field.parse().ok().or_else(|| dict.tag_for(field))
+}
+
+fn value_of(fields: &[(u32, String)], tag: u32) -> Option<&String> {
+    fields.iter().find(|(t, _)| *t == tag).map(|(_, v)| v)
+}
+
+// ── Registry ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone)]
+pub struct ConditionalRules {
+    rules: Vec<ConditionalRule>,
+}
+
+impl ConditionalRules {
+    /// The built-in rules plus those in `FIX_CONDITIONAL_RULES`
+    /// (comma-separated rule specs).
+    pub fn from_env() -> Self {
+        let mut rules: Vec<ConditionalRule> = BUILTIN
+            .iter()
+            .map(|spec| ConditionalRule::parse(spec).expect("built-in rule is valid"))
+            .collect();
+        let specs = std::env::var("FIX_CONDITIONAL_RULES").unwrap_or_default();
+        for spec in specs.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match ConditionalRule::parse(spec) {
+                Ok(rule) => rules.push(rule),
+                Err(e) => warn!(spec, error = %e, "Ignoring malformed conditional rule"),
+            }
+        }
+        Self { rules }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn check(&self, dict: &Dictionary, fields: &[(u32, String)]) -> Vec<ValidationError> {
+        self.rules
+            .iter()
+            .filter_map(|rule| rule.check(dict, fields))
+            .collect()
+    }
+}
+
+// ── Rule packs ────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    #[default]
+    Error,
+    Warning,
+}
+
+/// One rule in a pack: within `msg_types` (all if empty) and when `when`
+/// (`Field=Value[|Value]`) holds, `field` must be present if `required`, and
+/// must be one of `values` if any are listed. Warning rules only fail
+/// validation in strict mode.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PackRule {
+    pub field: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub msg_types: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RulePack {
+    pub name: String,
+    pub rules: Vec<PackRule>,
+}
+
+impl RulePack {
+    pub fn from_json(json: &str) -> Result<Self, RuleError> {
+        let pack: Self = serde_json::from_str(json).map_err(|e| RuleError(e.to_string()))?;
+        pack.check()?;
+        Ok(pack)
+    }
+
+    fn check(&self) -> Result<(), RuleError> {
+        if self.name.trim().is_empty() {
+            return Err(RuleError("rule pack has no name".to_string()));
+        }
+        for rule in &self.rules {
+            if rule.field.trim().is_empty() || !rule.required && rule.values.is_empty() {
+                return Err(RuleError(format!(
+                    "rule for '{}' must set required or values",
+                    rule.field
+                )));
+            }
+            if rule.when.as_deref().is_some_and(|w| !w.contains('=')) {
+                return Err(RuleError(format!(
+                    "rule for '{}': when must be 'Field=Value'",
+                    rule.field
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self, dict: &Dictionary, fields: &[(u32, String)]) -> Findings {
+        let mut findings = Findings::default();
+        for rule in &self.rules {
+            if !rule.msg_types.is_empty()
+                && !value_of(fields, 35).is_some_and(|m| rule.msg_types.contains(m))
+            {
+                continue;
+            }
+            if let Some((when_field, when_values)) =
+                rule.when.as_deref().and_then(|w| w.split_once('='))
+            {
+                let holds = resolve(dict, when_field.trim())
+                    .and_then(|t| value_of(fields, t))
+                    .is_some_and(|v| when_values.split('|').any(|w| w.trim() == v));
+                if !holds {
+                    continue;
+                }
+            }
+            let Some(tag) = resolve(dict, &rule.field) else {
+                continue;
+            };
+            let name = dict.name_of(tag);
+            let error = |message, category| ValidationError {
+                path: None,
+                field: name.clone(),
+                tag,
+                message,
+                category,
+            };
+            let errors = match rule.severity {
+                Severity::Error => &mut findings.errors,
+                Severity::Warning => &mut findings.warnings,
+            };
+            match value_of(fields, tag) {
+                None if rule.required => errors.push(error(
+                    format!("{name} (tag {tag}) is required by rule pack {}.", self.name),
+                    ErrorCategory::Required,
+                )),
+                Some(v) if !rule.values.is_empty() && !rule.values.contains(v) => {
+                    errors.push(error(
+                        format!(
+                            "{name} must be {} under rule pack {}, got '{v}'.",
+                            rule.values.join(" or "),
+                            self.name
+                        ),
+                        ErrorCategory::InvalidValue,
+                    ))
+                }
+                _ => {}
+            }
+        }
+        findings
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RulePacks {
+    by_name: HashMap<String, RulePack>,
+}
+
+impl RulePacks {
+    /// Loads the JSON rule packs listed in `FIX_RULE_PACKS` (comma-separated paths).
+    pub fn from_env() -> Self {
+        let mut packs = Self::default();
+        for (path, pack) in read_files() {
+            match pack {
+                Ok(pack) => {
+                    let rules = pack.rules.len();
+                    info!(path = %path, name = %pack.name, rules, "Rule pack loaded");
+                    packs.add(pack);
+                }
+                Err(e) => warn!(path = %path, error = %e, "Failed to load rule pack"),
+            }
+        }
+        packs
+    }
+
+    /// The packs in the `FIX_RULE_PACKS` files as they are now, for a
+    /// reload; a file that cannot be loaded is an error.
+    pub fn reread() -> Result<Vec<RulePack>, String> {
+        read_files()
+            .into_iter()
+            .map(|(path, pack)| pack.map_err(|e| format!("rule pack {path}: {e}")))
+            .collect()
+    }
+
+    /// Adds a pack, replacing any with the same name.
+    pub fn add(&mut self, pack: RulePack) {
+        self.by_name.insert(pack.name.clone(), pack);
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_name.len()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RulePack> {
+        self.by_name.get(name)
+    }
+}
+
+/// Each file listed in `FIX_RULE_PACKS`, with its pack.
+fn read_files() -> Vec<(String, Result<RulePack, RuleError>)> {
+    let paths = std::env::var("FIX_RULE_PACKS").unwrap_or_default();
+    paths
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|path| {
+            let pack = std::fs::read_to_string(path)
+                .map_err(|e| RuleError(e.to_string()))
+                .and_then(|json| RulePack::from_json(&json));
+            (path.to_string(), pack)
+        })
+        .collect()
+}
+
+/// Reads `FIX_SESSION_RULE_PACKS` (comma-separated `session_id=pack[|pack]`
+/// entries).
+pub fn session_rule_packs_from_env() -> HashMap<String, Vec<String>> {
+    let mut selected = HashMap::new();
+    let Ok(spec) = std::env::var("FIX_SESSION_RULE_PACKS") else {
+        return selected;
+    };
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        match entry.rsplit_once('=') {
+            Some((id, packs)) if !packs.trim().is_empty() => {
+                let packs = packs.split('|').map(|p| p.trim().to_string()).collect();
+                selected.insert(id.trim().to_string(), packs);
+            }
+            _ => warn!(entry, "Ignoring malformed FIX_SESSION_RULE_PACKS entry"),
+        }
+    }
+    selected
+}
+
+// ── Advisories ────────────────────────────────────────────────────────────────
+
+/// Fields recommended on some messages; written `Field[/Alternative] in MsgType[|MsgType]`.
+const RECOMMENDED: &[&str] = &["TransactTime in D|F|G|8"];
+
+/// Fields deprecated from a FIX version on, by tag, as they may be missing
+/// from that version's dictionary.
+const DEPRECATED: &[(u32, &str, &str)] =
+    &[(20, "ExecTransType", "FIX.4.3"), (47, "Rule80A", "FIX.4.3")];
+
+#[derive(Debug, Clone)]
+struct Recommendation {
+    /// Any one of these fields satisfies the recommendation.
+    fields: Vec<String>,
+    msg_types: Vec<String>,
+}
+
+impl Recommendation {
+    fn parse(spec: &str) -> Result<Self, RuleError> {
+        let (fields, msg_types) = spec
+            .split_once(" in ")
+            .ok_or_else(|| RuleError(format!("expected 'Field in MsgType', got '{spec}'")))?;
+        let list = |s: &str, sep| -> Vec<String> {
+            s.split(sep)
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        let rec = Self {
+            fields: list(fields, '/'),
+            msg_types: list(msg_types, '|'),
+        };
+        if rec.fields.is_empty() || rec.msg_types.is_empty() {
+            return Err(RuleError(format!(
+                "expected 'Field in MsgType', got '{spec}'"
+            )));
+        }
+        Ok(rec)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Advisories {
+    recommended: Vec<Recommendation>,
+    /// Configured deprecations, by name or tag, for every version.
+    deprecated: Vec<String>,
+}
+
+impl Advisories {
+    /// The built-in advisories plus `FIX_RECOMMENDED_FIELDS` (comma-separated
+    /// `Field in MsgType[|MsgType]`) and `FIX_DEPRECATED_FIELDS`
+    /// (comma-separated names or tags).
+    pub fn from_env() -> Self {
+        let mut recommended: Vec<Recommendation> = RECOMMENDED
+            .iter()
+            .map(|spec| Recommendation::parse(spec).expect("built-in recommendation is valid"))
+            .collect();
+        let specs = std::env::var("FIX_RECOMMENDED_FIELDS").unwrap_or_default();
+        for spec in specs.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match Recommendation::parse(spec) {
+                Ok(rec) => recommended.push(rec),
+                Err(e) => warn!(spec, error = %e, "Ignoring malformed recommended field"),
+            }
+        }
+        let deprecated = std::env::var("FIX_DEPRECATED_FIELDS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(str::to_string)
+            .collect();
+        Self {
+            recommended,
+            deprecated,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.recommended.len() + DEPRECATED.len() + self.deprecated.len()
+    }
+
+    /// Warnings for `fields`. The message's BeginString, or else the
+    /// dictionary's, decides which built-in deprecations apply.
+    pub fn check(&self, dict: &Dictionary, fields: &[(u32, String)]) -> Vec<ValidationError> {
+        let mut warnings = Vec::new();
+        let warning = |tag: u32, name: String, message: String, category| ValidationError {
+            field: name,
+            tag,
+            message,
+            category,
+            path: None,
+        };
+
+        if let Some(msg_type) = value_of(fields, 35) {
+            for rec in self
+                .recommended
+                .iter()
+                .filter(|r| r.msg_types.contains(msg_type))
+            {
+                let tags: Vec<u32> = rec.fields.iter().filter_map(|f| resolve(dict, f)).collect();
+                if tags.is_empty() || tags.iter().any(|t| value_of(fields, *t).is_some()) {
+                    continue;
+                }
+                let names: Vec<String> = tags.iter().map(|t| dict.name_of(*t)).collect();
+                let field = match tags.as_slice() {
+                    [t] => format!("{} (tag {t})", names[0]),
+                    _ => names.join(" or "),
+                };
+                let on = dict
+                    .message(msg_type)
+                    .map_or(msg_type.as_str(), |m| &m.name);
+                warnings.push(warning(
+                    tags[0],
+                    names[0].clone(),
+                    format!("{field} is recommended on {on}."),
+                    ErrorCategory::Recommended,
+                ));
+            }
+        }
+
+        let version = value_of(fields, 8).map_or(dict.version.as_str(), String::as_str);
+        for &(tag, name, since) in DEPRECATED {
+            if version >= since && value_of(fields, tag).is_some() {
+                warnings.push(warning(
+                    tag,
+                    name.to_string(),
+                    format!("{name} (tag {tag}) is deprecated since {since}."),
+                    ErrorCategory::Deprecated,
+                ));
+            }
+        }
+        for tag in self.deprecated.iter().filter_map(|f| resolve(dict, f)) {
+            if value_of(fields, tag).is_some() {
+                let name = dict.name_of(tag);
+                warnings.push(warning(
+                    tag,
+                    name.clone(),
+                    format!("{name} (tag {tag}) is deprecated."),
+                    ErrorCategory::Deprecated,
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RulePacksResponse {
+    packs: Vec<RulePack>,
+    count: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/rule-packs",
+    tag = "admin",
+    responses(
+        (status = 200, description = "OK", body = RulePacksResponse),
+    )
+)]
+pub async fn list_packs(State(state): State<AppState>) -> Json<RulePacksResponse> {
+    let mut packs: Vec<RulePack> = state
+        .rule_packs
+        .lock()
+        .await
+        .by_name
+        .values()
+        .cloned()
+        .collect();
+    packs.sort_by(|a, b| a.name.cmp(&b.name));
+    let count = packs.len();
+    Json(RulePacksResponse { packs, count })
+}
+
+/// Registers a pack from the JSON request body, replacing any with the same name.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/rule-packs",
+    tag = "admin",
+    request_body(content = String, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Registered", body = RulePack),
+        (status = 400, description = "Invalid rule pack"),
+    )
+)]
+pub async fn register_pack(
+    State(state): State<AppState>,
+    actor: Actor,
+    body: String,
+) -> Result<Json<RulePack>, StatusCode> {
+    let pack = RulePack::from_json(&body).map_err(|e| {
+        info!(error = %e, "Rule pack rejected");
+        StatusCode::BAD_REQUEST
+    })?;
+    info!(name = %pack.name, rules = pack.rules.len(), "Rule pack registered");
+    state.rule_packs.lock().await.add(pack.clone());
+    audit::record(
+        &state,
+        &actor,
+        AuditAction::RulePackRegistered,
+        None,
+        format!("{} rules={}", pack.name, pack.rules.len()),
+    )
+    .await;
+    Ok(Json(pack))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::Dictionaries;
+
+    fn messages(rules: &ConditionalRules, fields: &[(u32, &str)]) -> Vec<String> {
+        let dict = Dictionaries::from_env().get("FIX.4.4").unwrap();
+        let fields: Vec<(u32, String)> = fields.iter().map(|(t, v)| (*t, v.to_string())).collect();
+        rules
+            .check(&dict, &fields)
+            .into_iter()
+            .map(|e| e.message)
+            .collect()
+    }
+
+    #[test]
+    fn builtin_rules() {
+        let rules = ConditionalRules::from_env();
+        assert_eq!(
+            messages(
+                &rules,
+                &[(35, "D"), (40, "4"), (59, "6"), (432, "20261231")]
+            ),
+            vec![
+                "Price (tag 44) is required when OrdType=4.",
+                "StopPx (tag 99) is required when OrdType=4.",
+            ]
+        );
+        assert_eq!(
+            messages(&rules, &[(35, "D"), (40, "1"), (59, "6")]),
+            vec!["ExpireTime or ExpireDate is required when TimeInForce=6."]
+        );
+        // Not an order entry message.
+        assert!(messages(&rules, &[(35, "8"), (40, "2")]).is_empty());
+    }
+
+    #[test]
+    fn parses_custom_rules() {
+        let rule = ConditionalRule::parse("MaxFloor if 18=G|1").unwrap();
+        assert_eq!(rule.required, vec!["MaxFloor"]);
+        assert_eq!(rule.when_field, "18");
+        assert_eq!(rule.when_values, vec!["G", "1"]);
+        assert!(rule.msg_types.is_empty());
+
+        let rules = ConditionalRules { rules: vec![rule] };
+        assert_eq!(
+            messages(&rules, &[(35, "G"), (18, "G")]),
+            vec!["MaxFloor (tag 111) is required when ExecInst=G."]
+        );
+        assert!(ConditionalRule::parse("Price when OrdType=2").is_err());
+        assert!(ConditionalRule::parse("Price if OrdType").is_err());
+    }
+
+    #[test]
+    fn applies_rule_packs() {
+        let pack = RulePack::from_json(
+            r#"{"name": "BROKER_X", "rules": [
+                {"field": "Account", "msg_types": ["D"], "required": true},
+                {"field": "21", "values": ["1"]},
+                {"field": "MaxFloor", "when": "OrdType=2", "required": true},
+                {"field": "TransactTime", "msg_types": ["D"], "required": true, "severity": "warning"}
+            ]}"#,
+        )
+        .unwrap();
+        let dict = Dictionaries::from_env().get("FIX.4.4").unwrap();
+        let check = |fields: &[(u32, &str)]| -> Vec<String> {
+            let fields: Vec<(u32, String)> =
+                fields.iter().map(|(t, v)| (*t, v.to_string())).collect();
+            let findings = pack.validate(&dict, &fields);
+            findings
+                .errors
+                .into_iter()
+                .chain(findings.warnings)
+                .map(|e| e.message)
+                .collect()
+        };
+        assert_eq!(
+            check(&[(35, "D"), (21, "2"), (40, "2")]),
+            vec![
+                "Account (tag 1) is required by rule pack BROKER_X.",
+                "HandlInst must be 1 under rule pack BROKER_X, got '2'.",
+                "MaxFloor (tag 111) is required by rule pack BROKER_X.",
+                "TransactTime (tag 60) is required by rule pack BROKER_X.",
+            ]
+        );
+        assert!(check(&[(35, "G"), (21, "1"), (40, "1")]).is_empty());
+
+        assert!(RulePack::from_json(r#"{"name": "X", "rules": [{"field": "1"}]}"#).is_err());
+    }
+
+    #[test]
+    fn advisories_warn_about_recommended_and_deprecated_fields() {
+        let advisories = Advisories::from_env();
+        let dict = Dictionaries::from_env().get("FIX.4.4").unwrap();
+        let check = |fields: &[(u32, &str)]| -> Vec<String> {
+            let fields: Vec<(u32, String)> =
+                fields.iter().map(|(t, v)| (*t, v.to_string())).collect();
+            advisories
+                .check(&dict, &fields)
+                .into_iter()
+                .map(|e| e.message)
+                .collect()
+        };
+        assert_eq!(
+            check(&[(8, "FIX.4.4"), (35, "D"), (47, "A")]),
+            vec![
+                "TransactTime (tag 60) is recommended on NewOrderSingle.",
+                "Rule80A (tag 47) is deprecated since FIX.4.3.",
+            ]
+        );
+        // Still current in FIX 4.2.
+        assert!(check(&[
+            (8, "FIX.4.2"),
+            (35, "D"),
+            (60, "20260223-00:00:00"),
+            (47, "A")
+        ])
+        .is_empty());
+    }
+}
@@ -0,0 +1,1817 @@
+//! Simple Binary Encoding (SBE) codec.
+//!
+//! Message schemas are loaded from SBE XML (`sbe:messageSchema`) at startup
+//! from `FIX_SBE_SCHEMAS` or at runtime through the API. A schema message
+//! whose `semanticType` is a MsgType converts to and from a tag=value message:
+//! each field's `id` is its FIX tag, a group's `id` is its NoXXX count tag,
+//! and `data` elements carry raw data fields.
+//!
+//! Supported encodings are the primitive types, fixed-length char arrays,
+//! enums, sets (as their integer value), and decimal composites with a
+//! constant or variable exponent.
+
+use axum::{extract::State, http::StatusCode, response::Json};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    audit::{self, Actor, AuditAction},
+    message::{length_tag_for, Field, FixMessage},
+    parse_raw,
+    structure::{HEADER_TAGS, TRAILER_TAGS},
+    AppState, ParseRequest, ParsedField,
+};
+
+// ── Schema model ──────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Primitive {
+    Char,
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+}
+
+impl Primitive {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" => Self::Char,
+            "int8" => Self::I8,
+            "int16" => Self::I16,
+            "int32" => Self::I32,
+            "int64" => Self::I64,
+            "uint8" => Self::U8,
+            "uint16" => Self::U16,
+            "uint32" => Self::U32,
+            "uint64" => Self::U64,
+            "float" => Self::F32,
+            "double" => Self::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::Char | Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::I64 | Self::U64 | Self::F64 => 8,
+        }
+    }
+
+    fn range(self) -> (i128, i128) {
+        match self {
+            Self::Char | Self::U8 => (0, u8::MAX.into()),
+            Self::I8 => (i8::MIN.into(), i8::MAX.into()),
+            Self::I16 => (i16::MIN.into(), i16::MAX.into()),
+            Self::I32 => (i32::MIN.into(), i32::MAX.into()),
+            Self::I64 => (i64::MIN.into(), i64::MAX.into()),
+            Self::U16 => (0, u16::MAX.into()),
+            Self::U32 => (0, u32::MAX.into()),
+            Self::U64 => (0, u64::MAX.into()),
+            Self::F32 | Self::F64 => (0, 0),
+        }
+    }
+
+    fn is_float(self) -> bool {
+        matches!(self, Self::F32 | Self::F64)
+    }
+
+    /// The spec's default null value for optional fields of this type.
+    fn default_null(self) -> i128 {
+        match self {
+            Self::Char => 0,
+            Self::I8 | Self::I16 | Self::I32 | Self::I64 => self.range().0,
+            _ => self.range().1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Encoding {
+    /// A primitive, or a fixed-length char array when `length > 1`.
+    Scalar {
+        prim: Primitive,
+        length: usize,
+        null: Option<i128>,
+    },
+    Enum {
+        prim: Primitive,
+        values: Vec<String>,
+    },
+    Decimal {
+        mantissa: Primitive,
+        mantissa_null: Option<i128>,
+        exponent: Exponent,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Exponent {
+    Constant(i32),
+    Field(Primitive),
+}
+
+impl Encoding {
+    fn size(&self) -> usize {
+        match self {
+            Encoding::Scalar { prim, length, .. } => prim.size() * length,
+            Encoding::Enum { prim, .. } => prim.size(),
+            Encoding::Decimal {
+                mantissa, exponent, ..
+            } => {
+                mantissa.size()
+                    + match exponent {
+                        Exponent::Constant(_) => 0,
+                        Exponent::Field(p) => p.size(),
+                    }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FieldDef {
+    name: String,
+    tag: u32,
+    offset: usize,
+    encoding: Encoding,
+    optional: bool,
+    /// Value of a `presence="constant"` field, which takes no space on the wire.
+    constant: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct DataDef {
+    tag: u32,
+    length: Primitive,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Block {
+    block_length: usize,
+    fields: Vec<FieldDef>,
+    groups: Vec<GroupDef>,
+    data: Vec<DataDef>,
+}
+
+impl Block {
+    fn has_tag(&self, tag: u32) -> bool {
+        self.fields
+            .iter()
+            .any(|f| f.tag == tag && f.constant.is_none())
+            || self.groups.iter().any(|g| g.tag == tag)
+            || self.data.iter().any(|d| d.tag == tag)
+    }
+
+    /// The tag that opens each instance when this block is a group.
+    fn first_tag(&self) -> Option<u32> {
+        self.fields
+            .iter()
+            .find(|f| f.constant.is_none())
+            .map(|f| f.tag)
+            .or_else(|| self.groups.first().map(|g| g.tag))
+            .or_else(|| self.data.first().map(|d| d.tag))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct GroupDef {
+    tag: u32,
+    block_length_prim: Primitive,
+    count_prim: Primitive,
+    block: Block,
+}
+
+#[derive(Debug, Clone)]
+struct MessageDef {
+    name: String,
+    template_id: u16,
+    msg_type: String,
+    block: Block,
+}
+
+/// Members of the message header composite, in wire order.
+#[derive(Debug, Clone)]
+struct HeaderDef {
+    members: Vec<(String, Primitive)>,
+}
+
+impl HeaderDef {
+    fn size(&self) -> usize {
+        self.members.iter().map(|(_, p)| p.size()).sum()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Schema {
+    id: u16,
+    version: u16,
+    package: String,
+    big_endian: bool,
+    header: HeaderDef,
+    messages: Vec<MessageDef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SbeError(String);
+
+impl fmt::Display for SbeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn err<T>(msg: impl Into<String>) -> Result<T, SbeError> {
+    Err(SbeError(msg.into()))
+}
+
+// ── Schema loading ────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone)]
+enum TypeDef {
+    Encoding(Encoding, bool, Option<String>),
+    Composite(Vec<(String, Primitive, Option<String>)>),
+    /// Set choices are kept as the encoding integer.
+    Set(Primitive),
+}
+
+fn parse_num<T: std::str::FromStr>(
+    node: roxmltree::Node,
+    attr: &str,
+) -> Result<Option<T>, SbeError> {
+    match node.attribute(attr) {
+        None => Ok(None),
+        Some(v) => v
+            .trim()
+            .parse()
+            .map(Some)
+            .or_else(|_| err(format!("invalid {attr} {v:?}"))),
+    }
+}
+
+fn primitive_of(
+    types: &HashMap<String, TypeDef>,
+    name: &str,
+) -> Result<(Primitive, Option<i128>), SbeError> {
+    if let Some(p) = Primitive::from_name(name) {
+        return Ok((p, None));
+    }
+    match types.get(name) {
+        Some(TypeDef::Encoding(
+            Encoding::Scalar {
+                prim,
+                length: 1,
+                null,
+            },
+            _,
+            _,
+        )) => Ok((*prim, *null)),
+        _ => err(format!("{name} is not a primitive type")),
+    }
+}
+
+fn load_types(root: roxmltree::Node) -> Result<HashMap<String, TypeDef>, SbeError> {
+    let mut types = HashMap::new();
+    let type_nodes = root
+        .children()
+        .filter(|n| n.has_tag_name("types"))
+        .flat_map(|n| n.children().filter(|c| c.is_element()));
+
+    // Enums and sets may reference plain types, so plain types go first.
+    let (plain, rest): (Vec<_>, Vec<_>) = type_nodes.partition(|n| n.has_tag_name("type"));
+    for node in plain.into_iter().chain(rest) {
+        let name = node.attribute("name").unwrap_or_default().to_string();
+        let def = match node.tag_name().name() {
+            "type" => {
+                let prim_name = node.attribute("primitiveType").unwrap_or_default();
+                let prim = Primitive::from_name(prim_name).ok_or_else(|| {
+                    SbeError(format!("type {name}: unknown primitive {prim_name:?}"))
+                })?;
+                let null = parse_num::<i128>(node, "nullValue")?;
+                let presence = node.attribute("presence").unwrap_or("required");
+                TypeDef::Encoding(
+                    Encoding::Scalar {
+                        prim,
+                        length: parse_num(node, "length")?.unwrap_or(1),
+                        null,
+                    },
+                    presence == "optional",
+                    (presence == "constant")
+                        .then(|| node.text().unwrap_or_default().trim().to_string()),
+                )
+            }
+            "enum" => {
+                let (prim, _) =
+                    primitive_of(&types, node.attribute("encodingType").unwrap_or_default())?;
+                let values = node
+                    .children()
+                    .filter(|c| c.has_tag_name("validValue"))
+                    .map(|c| c.text().unwrap_or_default().trim().to_string())
+                    .collect();
+                TypeDef::Encoding(Encoding::Enum { prim, values }, false, None)
+            }
+            "set" => {
+                let (prim, _) =
+                    primitive_of(&types, node.attribute("encodingType").unwrap_or_default())?;
+                TypeDef::Set(prim)
+            }
+            "composite" => {
+                let mut members = Vec::new();
+                for member in node.children().filter(|c| c.is_element()) {
+                    if !member.has_tag_name("type") {
+                        return err(format!(
+                            "composite {name}: only <type> members are supported"
+                        ));
+                    }
+                    let prim =
+                        Primitive::from_name(member.attribute("primitiveType").unwrap_or_default())
+                            .ok_or_else(|| {
+                                SbeError(format!("composite {name}: unknown primitive"))
+                            })?;
+                    let constant = (member.attribute("presence") == Some("constant"))
+                        .then(|| member.text().unwrap_or_default().trim().to_string());
+                    members.push((
+                        member.attribute("name").unwrap_or_default().to_string(),
+                        prim,
+                        constant,
+                    ));
+                }
+                TypeDef::Composite(members)
+            }
+            _ => continue,
+        };
+        types.insert(name, def);
+    }
+    Ok(types)
+}
+
+fn resolve_field(
+    types: &HashMap<String, TypeDef>,
+    node: roxmltree::Node,
+    offset: usize,
+) -> Result<FieldDef, SbeError> {
+    let name = node.attribute("name").unwrap_or_default().to_string();
+    let tag = parse_num(node, "id")?.ok_or_else(|| SbeError(format!("field {name} has no id")))?;
+    let type_name = node.attribute("type").unwrap_or_default();
+    let presence = node.attribute("presence").unwrap_or("required");
+
+    let (encoding, type_optional, type_constant) =
+        if let Some(prim) = Primitive::from_name(type_name) {
+            (
+                Encoding::Scalar {
+                    prim,
+                    length: 1,
+                    null: None,
+                },
+                false,
+                None,
+            )
+        } else {
+            match types.get(type_name) {
+                Some(TypeDef::Encoding(enc, optional, constant)) => {
+                    (enc.clone(), *optional, constant.clone())
+                }
+                Some(TypeDef::Set(prim)) => (
+                    Encoding::Scalar {
+                        prim: *prim,
+                        length: 1,
+                        null: None,
+                    },
+                    false,
+                    None,
+                ),
+                Some(TypeDef::Composite(members)) => {
+                    let member = |n: &str| members.iter().find(|(m, _, _)| m == n);
+                    let (Some((_, mantissa, _)), Some((_, exp_prim, exp_const))) =
+                        (member("mantissa"), member("exponent"))
+                    else {
+                        return err(format!(
+                            "field {name}: composite {type_name} is not a decimal"
+                        ));
+                    };
+                    let exponent = match exp_const {
+                        Some(v) => Exponent::Constant(
+                            v.parse()
+                                .or_else(|_| err(format!("{type_name}: invalid exponent")))?,
+                        ),
+                        None => Exponent::Field(*exp_prim),
+                    };
+                    (
+                        Encoding::Decimal {
+                            mantissa: *mantissa,
+                            mantissa_null: None,
+                            exponent,
+                        },
+                        false,
+                        None,
+                    )
+                }
+                None => return err(format!("field {name}: unknown type {type_name:?}")),
+            }
+        };
+
+    let constant = match (presence, node.attribute("valueRef")) {
+        // valueRef is `EnumName.ValueName`; the tag=value form is the enum's value.
+        ("constant", Some(value_ref)) => {
+            let (enum_name, value_name) = value_ref.split_once('.').unwrap_or_default();
+            let enum_node = node
+                .document()
+                .descendants()
+                .find(|n| n.has_tag_name("enum") && n.attribute("name") == Some(enum_name));
+            let value = enum_node.and_then(|e| {
+                e.children()
+                    .find(|c| {
+                        c.has_tag_name("validValue") && c.attribute("name") == Some(value_name)
+                    })
+                    .and_then(|c| c.text())
+            });
+            Some(value.unwrap_or_default().trim().to_string())
+        }
+        ("constant", None) => Some(node.text().unwrap_or_default().trim().to_string()),
+        _ => type_constant,
+    };
+
+    Ok(FieldDef {
+        name,
+        tag,
+        offset: parse_num(node, "offset")?.unwrap_or(offset),
+        encoding,
+        optional: presence == "optional" || type_optional,
+        constant,
+    })
+}
+
+fn load_block(types: &HashMap<String, TypeDef>, node: roxmltree::Node) -> Result<Block, SbeError> {
+    let mut block = Block::default();
+    let mut offset = 0;
+    for child in node.children().filter(|c| c.is_element()) {
+        match child.tag_name().name() {
+            "field" => {
+                let field = resolve_field(types, child, offset)?;
+                if field.constant.is_none() {
+                    offset = field.offset + field.encoding.size();
+                }
+                block.fields.push(field);
+            }
+            "group" => {
+                let tag = parse_num(child, "id")?
+                    .ok_or_else(|| SbeError("group has no id".to_string()))?;
+                let dimension = child
+                    .attribute("dimensionType")
+                    .unwrap_or("groupSizeEncoding");
+                let Some(TypeDef::Composite(members)) = types.get(dimension) else {
+                    return err(format!("unknown dimensionType {dimension:?}"));
+                };
+                let member = |n: &str| members.iter().find(|(m, _, _)| m == n).map(|(_, p, _)| *p);
+                let (Some(block_length_prim), Some(count_prim)) =
+                    (member("blockLength"), member("numInGroup"))
+                else {
+                    return err(format!("{dimension} needs blockLength and numInGroup"));
+                };
+                let mut group_block = load_block(types, child)?;
+                if let Some(len) = parse_num(child, "blockLength")? {
+                    group_block.block_length = len;
+                }
+                block.groups.push(GroupDef {
+                    tag,
+                    block_length_prim,
+                    count_prim,
+                    block: group_block,
+                });
+            }
+            "data" => {
+                let tag = parse_num(child, "id")?
+                    .ok_or_else(|| SbeError("data has no id".to_string()))?;
+                let type_name = child.attribute("type").unwrap_or_default();
+                let Some(TypeDef::Composite(members)) = types.get(type_name) else {
+                    return err(format!("unknown data type {type_name:?}"));
+                };
+                let length = members
+                    .iter()
+                    .find(|(m, _, _)| m == "length")
+                    .map(|(_, p, _)| *p)
+                    .ok_or_else(|| SbeError(format!("{type_name} has no length member")))?;
+                block.data.push(DataDef { tag, length });
+            }
+            _ => {}
+        }
+    }
+    block.block_length = offset;
+    Ok(block)
+}
+
+impl Schema {
+    pub fn from_xml(xml: &str) -> Result<Self, SbeError> {
+        let doc = roxmltree::Document::parse(xml).map_err(|e| SbeError(e.to_string()))?;
+        let root = doc.root_element();
+        if root.tag_name().name() != "messageSchema" {
+            return err("root element is not messageSchema");
+        }
+        let types = load_types(root)?;
+
+        let header_type = root.attribute("headerType").unwrap_or("messageHeader");
+        let Some(TypeDef::Composite(members)) = types.get(header_type) else {
+            return err(format!("header type {header_type:?} not defined"));
+        };
+        let header = HeaderDef {
+            members: members.iter().map(|(n, p, _)| (n.clone(), *p)).collect(),
+        };
+        for required in ["blockLength", "templateId", "schemaId", "version"] {
+            if !header.members.iter().any(|(n, _)| n == required) {
+                return err(format!("header type has no {required}"));
+            }
+        }
+
+        let mut messages = Vec::new();
+        for node in root.children().filter(|n| n.tag_name().name() == "message") {
+            let name = node.attribute("name").unwrap_or_default().to_string();
+            let mut block =
+                load_block(&types, node).map_err(|e| SbeError(format!("message {name}: {e}")))?;
+            if let Some(len) = parse_num(node, "blockLength")? {
+                block.block_length = len;
+            }
+            messages.push(MessageDef {
+                template_id: parse_num(node, "id")?
+                    .ok_or_else(|| SbeError(format!("message {name} has no id")))?,
+                msg_type: node
+                    .attribute("semanticType")
+                    .unwrap_or_default()
+                    .to_string(),
+                name,
+                block,
+            });
+        }
+
+        Ok(Self {
+            id: parse_num(root, "id")?.unwrap_or(0),
+            version: parse_num(root, "version")?.unwrap_or(0),
+            package: root.attribute("package").unwrap_or_default().to_string(),
+            big_endian: root.attribute("byteOrder") == Some("bigEndian"),
+            header,
+            messages,
+        })
+    }
+}
+
+// ── Wire primitives ───────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scalar {
+    Int(i128),
+    Float(f64),
+}
+
+struct Writer {
+    buf: Vec<u8>,
+    big_endian: bool,
+}
+
+impl Writer {
+    fn put_at(&mut self, pos: usize, prim: Primitive, value: Scalar) {
+        macro_rules! bytes {
+            ($t:ty, $v:expr) => {
+                if self.big_endian {
+                    ($v as $t).to_be_bytes().to_vec()
+                } else {
+                    ($v as $t).to_le_bytes().to_vec()
+                }
+            };
+        }
+        let int = match value {
+            Scalar::Int(i) => i,
+            Scalar::Float(f) => f as i128,
+        };
+        let float = match value {
+            Scalar::Int(i) => i as f64,
+            Scalar::Float(f) => f,
+        };
+        let bytes = match prim {
+            Primitive::Char | Primitive::U8 => vec![int as u8],
+            Primitive::I8 => vec![int as i8 as u8],
+            Primitive::I16 => bytes!(i16, int),
+            Primitive::I32 => bytes!(i32, int),
+            Primitive::I64 => bytes!(i64, int),
+            Primitive::U16 => bytes!(u16, int),
+            Primitive::U32 => bytes!(u32, int),
+            Primitive::U64 => bytes!(u64, int),
+            Primitive::F32 => bytes!(f32, float),
+            Primitive::F64 => bytes!(f64, float),
+        };
+        if self.buf.len() < pos + bytes.len() {
+            self.buf.resize(pos + bytes.len(), 0);
+        }
+        self.buf[pos..pos + bytes.len()].copy_from_slice(&bytes);
+    }
+
+    fn push(&mut self, prim: Primitive, value: Scalar) {
+        let pos = self.buf.len();
+        self.put_at(pos, prim, value);
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    big_endian: bool,
+}
+
+impl Reader<'_> {
+    fn get(&self, pos: usize, prim: Primitive) -> Result<Scalar, SbeError> {
+        let size = prim.size();
+        let Some(b) = self.buf.get(pos..pos + size) else {
+            return err(format!("frame truncated at byte {pos}"));
+        };
+        macro_rules! num {
+            ($t:ty) => {{
+                let arr = b.try_into().unwrap_or_default();
+                if self.big_endian {
+                    <$t>::from_be_bytes(arr)
+                } else {
+                    <$t>::from_le_bytes(arr)
+                }
+            }};
+        }
+        Ok(match prim {
+            Primitive::Char | Primitive::U8 => Scalar::Int(b[0].into()),
+            Primitive::I8 => Scalar::Int((b[0] as i8).into()),
+            Primitive::I16 => Scalar::Int(num!(i16).into()),
+            Primitive::I32 => Scalar::Int(num!(i32).into()),
+            Primitive::I64 => Scalar::Int(num!(i64).into()),
+            Primitive::U16 => Scalar::Int(num!(u16).into()),
+            Primitive::U32 => Scalar::Int(num!(u32).into()),
+            Primitive::U64 => Scalar::Int(num!(u64).into()),
+            Primitive::F32 => Scalar::Float(num!(f32).into()),
+            Primitive::F64 => Scalar::Float(num!(f64)),
+        })
+    }
+
+    fn int(&self, pos: usize, prim: Primitive) -> Result<usize, SbeError> {
+        match self.get(pos, prim)? {
+            Scalar::Int(i) => usize::try_from(i).or_else(|_| err("negative length")),
+            Scalar::Float(_) => err("length is not an integer"),
+        }
+    }
+}
+
+// ── Value conversion ──────────────────────────────────────────────────────────
+
+fn parse_int(field: &FieldDef, prim: Primitive, value: &str) -> Result<i128, SbeError> {
+    let v: i128 = value.trim().parse().or_else(|_| {
+        err(format!(
+            "{}({}): {value:?} is not an integer",
+            field.name, field.tag
+        ))
+    })?;
+    let (min, max) = prim.range();
+    if v < min || v > max {
+        return err(format!(
+            "{}({}): {value} out of range",
+            field.name, field.tag
+        ));
+    }
+    Ok(v)
+}
+
+/// Splits a decimal string into a mantissa at the given exponent, without
+/// going through floating point.
+fn decimal_mantissa(value: &str, exponent: i32) -> Option<i128> {
+    let value = value.trim();
+    let (neg, digits) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    };
+    let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+    if int.is_empty() && frac.is_empty()
+        || !(int.bytes().chain(frac.bytes())).all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let scale = usize::try_from(-exponent).ok()?;
+    let frac = frac.trim_end_matches('0');
+    if frac.len() > scale {
+        return None;
+    }
+    let text = format!("{int}{frac:0<scale$}");
+    let m: i128 = if text.is_empty() {
+        0
+    } else {
+        text.parse().ok()?
+    };
+    Some(if neg { -m } else { m })
+}
+
+/// Formats `mantissa * 10^exponent` as a FIX decimal without trailing zeros.
+pub fn format_decimal(mantissa: i128, exponent: i32) -> String {
+    if exponent >= 0 {
+        return (mantissa * 10i128.pow(exponent as u32)).to_string();
+    }
+    let scale = (-exponent) as usize;
+    let digits = format!("{:0>width$}", mantissa.unsigned_abs(), width = scale + 1);
+    let (int, frac) = digits.split_at(digits.len() - scale);
+    let frac = frac.trim_end_matches('0');
+    let sign = if mantissa < 0 { "-" } else { "" };
+    if frac.is_empty() {
+        format!("{sign}{int}")
+    } else {
+        format!("{sign}{int}.{frac}")
+    }
+}
+
+fn encode_field(
+    w: &mut Writer,
+    base: usize,
+    field: &FieldDef,
+    value: Option<&str>,
+) -> Result<(), SbeError> {
+    let pos = base + field.offset;
+    let missing = || {
+        SbeError(format!(
+            "missing required field {}({})",
+            field.name, field.tag
+        ))
+    };
+
+    match &field.encoding {
+        Encoding::Scalar { prim, length, null } if *length > 1 => {
+            let bytes = value.unwrap_or_default().as_bytes();
+            if bytes.len() > *length {
+                return err(format!(
+                    "{}({}): longer than {length} bytes",
+                    field.name, field.tag
+                ));
+            }
+            if value.is_none() && !field.optional {
+                return Err(missing());
+            }
+            for i in 0..*length {
+                let b = bytes.get(i).copied().map_or(null.unwrap_or(0), i128::from);
+                w.put_at(pos + i * prim.size(), *prim, Scalar::Int(b));
+            }
+        }
+        Encoding::Scalar { prim, null, .. } => {
+            let scalar = match value {
+                None if field.optional => Scalar::Int(null.unwrap_or_else(|| prim.default_null())),
+                None => return Err(missing()),
+                Some(v) if *prim == Primitive::Char => {
+                    Scalar::Int(v.bytes().next().unwrap_or(0).into())
+                }
+                Some(v) if prim.is_float() => Scalar::Float(v.trim().parse().or_else(|_| {
+                    err(format!(
+                        "{}({}): {v:?} is not a number",
+                        field.name, field.tag
+                    ))
+                })?),
+                Some(v) => Scalar::Int(parse_int(field, *prim, v)?),
+            };
+            if prim.is_float() && value.is_none() {
+                w.put_at(pos, *prim, Scalar::Float(f64::NAN));
+            } else {
+                w.put_at(pos, *prim, scalar);
+            }
+        }
+        Encoding::Enum { prim, values } => {
+            let v = match value {
+                Some(v) => v,
+                None if field.optional => {
+                    w.put_at(pos, *prim, Scalar::Int(prim.default_null()));
+                    return Ok(());
+                }
+                None => return Err(missing()),
+            };
+            if !values.iter().any(|valid| valid == v) {
+                return err(format!(
+                    "{}({}): invalid value {v:?}",
+                    field.name, field.tag
+                ));
+            }
+            let scalar = if *prim == Primitive::Char {
+                v.bytes().next().unwrap_or(0).into()
+            } else {
+                parse_int(field, *prim, v)?
+            };
+            w.put_at(pos, *prim, Scalar::Int(scalar));
+        }
+        Encoding::Decimal {
+            mantissa,
+            mantissa_null,
+            exponent,
+        } => {
+            let exp = match (exponent, value) {
+                (Exponent::Constant(e), _) => *e,
+                (Exponent::Field(_), Some(v)) => {
+                    -(v.split_once('.')
+                        .map_or(0, |(_, f)| f.trim_end_matches('0').len())
+                        as i32)
+                }
+                (Exponent::Field(_), None) => 0,
+            };
+            let m = match value {
+                Some(v) => decimal_mantissa(v, exp)
+                    .filter(|m| (mantissa.range().0..=mantissa.range().1).contains(m))
+                    .ok_or_else(|| {
+                        SbeError(format!(
+                            "{}({}): {v:?} does not fit 10^{exp}",
+                            field.name, field.tag
+                        ))
+                    })?,
+                None if field.optional => mantissa_null.unwrap_or_else(|| mantissa.default_null()),
+                None => return Err(missing()),
+            };
+            w.put_at(pos, *mantissa, Scalar::Int(m));
+            if let Exponent::Field(p) = exponent {
+                w.put_at(pos + mantissa.size(), *p, Scalar::Int(exp.into()));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn decode_field(r: &Reader, base: usize, field: &FieldDef) -> Result<Option<String>, SbeError> {
+    let pos = base + field.offset;
+    let is_null = |v: i128, null: Option<i128>, prim: Primitive| {
+        field.optional && v == null.unwrap_or_else(|| prim.default_null())
+    };
+    let int = |s: Scalar| match s {
+        Scalar::Int(i) => i,
+        Scalar::Float(f) => f as i128,
+    };
+
+    Ok(match &field.encoding {
+        Encoding::Scalar { prim, length, .. } if *length > 1 => {
+            let mut bytes = Vec::with_capacity(*length);
+            for i in 0..*length {
+                bytes.push(int(r.get(pos + i * prim.size(), *prim)?) as u8);
+            }
+            let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+            (end > 0).then(|| String::from_utf8_lossy(&bytes[..end]).into_owned())
+        }
+        Encoding::Scalar { prim, null, .. } => match r.get(pos, *prim)? {
+            Scalar::Float(f) if f.is_nan() => None,
+            Scalar::Float(f) => Some(f.to_string()),
+            Scalar::Int(v) if is_null(v, *null, *prim) => None,
+            Scalar::Int(v) if *prim == Primitive::Char => Some(char::from(v as u8).to_string()),
+            Scalar::Int(v) => Some(v.to_string()),
+        },
+        Encoding::Enum { prim, .. } => {
+            let v = int(r.get(pos, *prim)?);
+            if is_null(v, None, *prim) {
+                None
+            } else if *prim == Primitive::Char {
+                Some(char::from(v as u8).to_string())
+            } else {
+                Some(v.to_string())
+            }
+        }
+        Encoding::Decimal {
+            mantissa,
+            mantissa_null,
+            exponent,
+        } => {
+            let m = int(r.get(pos, *mantissa)?);
+            if is_null(m, *mantissa_null, *mantissa) {
+                None
+            } else {
+                let exp = match exponent {
+                    Exponent::Constant(e) => *e,
+                    Exponent::Field(p) => int(r.get(pos + mantissa.size(), *p)?) as i32,
+                };
+                Some(format_decimal(m, exp))
+            }
+        }
+    })
+}
+
+// ── Levels (message body or group instance) ───────────────────────────────────
+
+/// Fields of one level of a tag=value message, split along the schema's groups.
+#[derive(Debug, Default)]
+struct Level<'a> {
+    values: Vec<(u32, &'a str)>,
+    groups: HashMap<u32, Vec<Level<'a>>>,
+}
+
+impl<'a> Level<'a> {
+    fn value(&self, tag: u32) -> Option<&'a str> {
+        self.values.iter().find(|(t, _)| *t == tag).map(|(_, v)| *v)
+    }
+}
+
+fn gather<'a>(
+    fields: &'a [ParsedField],
+    pos: &mut usize,
+    block: &Block,
+    is_root: bool,
+    unmapped: &mut Vec<u32>,
+) -> Level<'a> {
+    let mut level = Level::default();
+    let delimiter = (!is_root).then(|| block.first_tag()).flatten();
+
+    while let Some(field) = fields.get(*pos) {
+        if !is_root {
+            let repeated = Some(field.tag) == delimiter && level.value(field.tag).is_some();
+            if !block.has_tag(field.tag) || repeated {
+                break;
+            }
+        }
+        *pos += 1;
+
+        if let Some(group) = block.groups.iter().find(|g| g.tag == field.tag) {
+            let count: usize = field.value.trim().parse().unwrap_or(0);
+            let mut instances = Vec::new();
+            for _ in 0..count {
+                if *pos >= fields.len() {
+                    break;
+                }
+                instances.push(gather(fields, pos, &group.block, false, unmapped));
+            }
+            level.groups.insert(field.tag, instances);
+        } else if block.has_tag(field.tag) {
+            level.values.push((field.tag, field.value.as_str()));
+        } else if !(HEADER_TAGS.contains(&field.tag)
+            || TRAILER_TAGS.contains(&field.tag)
+            || block
+                .data
+                .iter()
+                .any(|d| length_tag_for(d.tag) == Some(field.tag)))
+        {
+            unmapped.push(field.tag);
+        }
+    }
+    level
+}
+
+fn encode_level(w: &mut Writer, block: &Block, level: &Level) -> Result<(), SbeError> {
+    let base = w.buf.len();
+    w.buf.resize(base + block.block_length, 0);
+    for field in block.fields.iter().filter(|f| f.constant.is_none()) {
+        encode_field(w, base, field, level.value(field.tag))?;
+    }
+    for group in &block.groups {
+        let instances = level
+            .groups
+            .get(&group.tag)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        w.push(
+            group.block_length_prim,
+            Scalar::Int(group.block.block_length as i128),
+        );
+        w.push(group.count_prim, Scalar::Int(instances.len() as i128));
+        for instance in instances {
+            encode_level(w, &group.block, instance)?;
+        }
+    }
+    for data in &block.data {
+        let bytes = level.value(data.tag).unwrap_or_default().as_bytes();
+        w.push(data.length, Scalar::Int(bytes.len() as i128));
+        w.buf.extend_from_slice(bytes);
+    }
+    Ok(())
+}
+
+/// Decodes one level starting at `pos` and returns the position after it.
+fn decode_level(
+    r: &Reader,
+    mut pos: usize,
+    block: &Block,
+    block_length: usize,
+    out: &mut Vec<Field>,
+) -> Result<usize, SbeError> {
+    let field = |tag: u32, value: String| Field {
+        tag,
+        value: value.into_bytes(),
+    };
+    for def in &block.fields {
+        let value = match &def.constant {
+            Some(constant) => Some(constant.clone()),
+            // Fields beyond the sender's block length were added in a later schema version.
+            None if def.offset + def.encoding.size() > block_length => None,
+            None => decode_field(r, pos, def)?,
+        };
+        if let Some(value) = value {
+            out.push(field(def.tag, value));
+        }
+    }
+    pos += block_length;
+
+    for group in &block.groups {
+        let group_block_length = r.int(pos, group.block_length_prim)?;
+        pos += group.block_length_prim.size();
+        let count = r.int(pos, group.count_prim)?;
+        pos += group.count_prim.size();
+        if count > 0 {
+            out.push(field(group.tag, count.to_string()));
+        }
+        for _ in 0..count {
+            pos = decode_level(r, pos, &group.block, group_block_length, out)?;
+        }
+    }
+    for data in &block.data {
+        let len = r.int(pos, data.length)?;
+        pos += data.length.size();
+        let Some(bytes) = r.buf.get(pos..pos + len) else {
+            return err(format!("frame truncated in data field {}", data.tag));
+        };
+        pos += len;
+        if len > 0 {
+            if let Some(len_tag) = length_tag_for(data.tag) {
+                out.push(field(len_tag, len.to_string()));
+            }
+            out.push(Field {
+                tag: data.tag,
+                value: bytes.to_vec(),
+            });
+        }
+    }
+    Ok(pos)
+}
+
+impl Schema {
+    fn message_for(&self, msg_type: &str, template_id: Option<u16>) -> Option<&MessageDef> {
+        self.messages.iter().find(|m| match template_id {
+            Some(id) => m.template_id == id,
+            None => m.msg_type == msg_type,
+        })
+    }
+
+    /// Encodes tag=value fields as an SBE frame (message header and body),
+    /// returning it with the template used and any tags the schema has no field for.
+    pub fn encode(
+        &self,
+        fields: &[ParsedField],
+        template_id: Option<u16>,
+    ) -> Result<(Vec<u8>, u16, Vec<u32>), SbeError> {
+        let msg_type = fields
+            .iter()
+            .find(|f| f.tag == 35)
+            .map_or("", |f| f.value.as_str());
+        let Some(message) = self.message_for(msg_type, template_id) else {
+            return err(format!(
+                "schema {} has no message for MsgType {msg_type:?}",
+                self.id
+            ));
+        };
+
+        let mut unmapped = Vec::new();
+        // MsgType and the rest of the header are carried by the SBE message header.
+        let level = gather(fields, &mut 0, &message.block, true, &mut unmapped);
+
+        let mut w = Writer {
+            buf: Vec::new(),
+            big_endian: self.big_endian,
+        };
+        for (name, prim) in &self.header.members {
+            let value = match name.as_str() {
+                "blockLength" => message.block.block_length as i128,
+                "templateId" => message.template_id.into(),
+                "schemaId" => self.id.into(),
+                "version" => self.version.into(),
+                _ => 0,
+            };
+            w.push(*prim, Scalar::Int(value));
+        }
+        encode_level(&mut w, &message.block, &level)?;
+        Ok((w.buf, message.template_id, unmapped))
+    }
+
+    fn header_value(&self, frame: &[u8], member: &str) -> Result<usize, SbeError> {
+        let r = Reader {
+            buf: frame,
+            big_endian: self.big_endian,
+        };
+        let mut pos = 0;
+        for (name, prim) in &self.header.members {
+            if name == member {
+                return r.int(pos, *prim);
+            }
+            pos += prim.size();
+        }
+        err(format!("header has no {member}"))
+    }
+
+    /// Decodes an SBE frame into a tag=value message.
+    pub fn decode(
+        &self,
+        frame: &[u8],
+        begin_string: &str,
+    ) -> Result<(FixMessage, String), SbeError> {
+        let template_id = self.header_value(frame, "templateId")?;
+        let block_length = self.header_value(frame, "blockLength")?;
+        let Some(message) = self
+            .messages
+            .iter()
+            .find(|m| usize::from(m.template_id) == template_id)
+        else {
+            return err(format!("schema {} has no template {template_id}", self.id));
+        };
+
+        let r = Reader {
+            buf: frame,
+            big_endian: self.big_endian,
+        };
+        let mut body = vec![Field {
+            tag: 35,
+            value: message.msg_type.clone().into_bytes(),
+        }];
+        decode_level(
+            &r,
+            self.header.size(),
+            &message.block,
+            block_length,
+            &mut body,
+        )?;
+        Ok((FixMessage::framed(begin_string, body), message.name.clone()))
+    }
+}
+
+// ── Registry ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Default)]
+pub struct SbeSchemas {
+    schemas: Vec<Schema>,
+}
+
+impl SbeSchemas {
+    /// Loads the schema files listed in `FIX_SBE_SCHEMAS` (comma-separated paths).
+    pub fn from_env() -> Self {
+        let mut schemas = Self::default();
+        let Ok(paths) = std::env::var("FIX_SBE_SCHEMAS") else {
+            return schemas;
+        };
+        for path in paths.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match std::fs::read_to_string(path)
+                .map_err(|e| SbeError(e.to_string()))
+                .and_then(|xml| Schema::from_xml(&xml))
+            {
+                Ok(schema) => schemas.add(schema),
+                Err(e) => warn!(path, error = %e, "Failed to load SBE schema"),
+            }
+        }
+        schemas
+    }
+
+    pub fn add(&mut self, schema: Schema) {
+        info!(
+            schema_id = schema.id,
+            version = schema.version,
+            messages = schema.messages.len(),
+            "SBE schema loaded"
+        );
+        self.schemas.retain(|s| s.id != schema.id);
+        self.schemas.push(schema);
+    }
+
+    pub fn len(&self) -> usize {
+        self.schemas.len()
+    }
+
+    fn get(&self, id: Option<u16>) -> Option<&Schema> {
+        match id {
+            Some(id) => self.schemas.iter().find(|s| s.id == id),
+            None if self.schemas.len() == 1 => self.schemas.first(),
+            None => None,
+        }
+    }
+
+    /// Finds the schema a frame belongs to by the schemaId in its header.
+    fn for_frame(&self, frame: &[u8]) -> Option<&Schema> {
+        self.schemas
+            .iter()
+            .find(|s| s.header_value(frame, "schemaId") == Ok(usize::from(s.id)))
+    }
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MessageSummary {
+    name: String,
+    template_id: u16,
+    msg_type: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SchemaSummary {
+    schema_id: u16,
+    version: u16,
+    package: String,
+    #[schema(value_type = String)]
+    byte_order: &'static str,
+    messages: Vec<MessageSummary>,
+}
+
+impl From<&Schema> for SchemaSummary {
+    fn from(s: &Schema) -> Self {
+        Self {
+            schema_id: s.id,
+            version: s.version,
+            package: s.package.clone(),
+            byte_order: if s.big_endian {
+                "bigEndian"
+            } else {
+                "littleEndian"
+            },
+            messages: s
+                .messages
+                .iter()
+                .map(|m| MessageSummary {
+                    name: m.name.clone(),
+                    template_id: m.template_id,
+                    msg_type: m.msg_type.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SchemasResponse {
+    schemas: Vec<SchemaSummary>,
+    count: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/fix/sbe/schemas",
+    tag = "fix",
+    responses(
+        (status = 200, description = "OK", body = SchemasResponse),
+    )
+)]
+pub async fn list_schemas(State(state): State<AppState>) -> Json<SchemasResponse> {
+    let schemas: Vec<SchemaSummary> = state
+        .sbe
+        .lock()
+        .await
+        .schemas
+        .iter()
+        .map(SchemaSummary::from)
+        .collect();
+    let count = schemas.len();
+    Json(SchemasResponse { schemas, count })
+}
+
+/// Loads a schema from the XML request body, replacing any with the same id.
+#[utoipa::path(
+    post,
+    path = "/api/v1/fix/sbe/schemas",
+    tag = "fix",
+    request_body(content = String, content_type = "application/xml"),
+    responses(
+        (status = 200, description = "Loaded schema", body = SchemaSummary),
+        (status = 400, description = "Invalid schema"),
+    )
+)]
+pub async fn load_schema(
+    State(state): State<AppState>,
+    actor: Actor,
+    body: String,
+) -> Result<Json<SchemaSummary>, StatusCode> {
+    let schema = Schema::from_xml(&body).map_err(|e| {
+        info!(error = %e, "SBE schema rejected");
+        StatusCode::BAD_REQUEST
+    })?;
+    let summary = SchemaSummary::from(&schema);
+    state.sbe.lock().await.add(schema);
+    audit::record(
+        &state,
+        &actor,
+        AuditAction::SbeSchemaLoaded,
+        None,
+        format!(
+            "schema {} version {} ({})",
+            summary.schema_id, summary.version, summary.package
+        ),
+    )
+    .await;
+    Ok(Json(summary))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = SbeEncodeRequest)]
+pub struct EncodeRequest {
+    #[serde(flatten)]
+    message: ParseRequest,
+    /// Required when more than one schema is loaded.
+    schema_id: Option<u16>,
+    /// Picks the template directly instead of matching MsgType to `semanticType`.
+    template_id: Option<u16>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = SbeEncodeResponse)]
+pub struct EncodeResponse {
+    schema_id: u16,
+    template_id: u16,
+    frame_base64: String,
+    length: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    unmapped: Vec<u32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = SbeDecodeRequest)]
+pub struct DecodeRequest {
+    frame_base64: String,
+    #[serde(default = "default_begin_string")]
+    begin_string: String,
+}
+
+fn default_begin_string() -> String {
+    "FIX.4.4".to_string()
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = SbeDecodeResponse)]
+pub struct DecodeResponse {
+    schema_id: u16,
+    template: String,
+    msg_type: String,
+    /// SOH-delimited wire form, with BodyLength and CheckSum computed
+    fix_message: String,
+    /// `|`-delimited form for logs and display
+    display: String,
+    fields: Vec<ParsedField>,
+}
+
+fn unprocessable(e: SbeError) -> StatusCode {
+    info!(error = %e, "SBE conversion rejected");
+    StatusCode::UNPROCESSABLE_ENTITY
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/fix/sbe/encode",
+    tag = "fix",
+    request_body = EncodeRequest,
+    responses(
+        (status = 200, description = "OK", body = EncodeResponse),
+        (status = 400, description = "Unknown schema or message, or not a FIX message"),
+    )
+)]
+pub async fn encode_handler(
+    State(state): State<AppState>,
+    Json(req): Json<EncodeRequest>,
+) -> Result<Json<EncodeResponse>, StatusCode> {
+    let raw = req.message.bytes().ok_or(StatusCode::BAD_REQUEST)?;
+    let parsed = parse_raw(&raw);
+    let schemas = state.sbe.lock().await;
+    // Without schema_id the choice is only unambiguous when one schema is loaded.
+    let schema = schemas.get(req.schema_id).ok_or(StatusCode::BAD_REQUEST)?;
+    let (frame, template_id, unmapped) = schema
+        .encode(&parsed.fields, req.template_id)
+        .map_err(unprocessable)?;
+
+    info!(
+        msg_type = %parsed.msg_type,
+        schema_id = schema.id,
+        template_id,
+        length = frame.len(),
+        "FIX message encoded as SBE"
+    );
+    Ok(Json(EncodeResponse {
+        schema_id: schema.id,
+        template_id,
+        length: frame.len(),
+        frame_base64: BASE64.encode(&frame),
+        unmapped,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/fix/sbe/decode",
+    tag = "fix",
+    request_body = DecodeRequest,
+    responses(
+        (status = 200, description = "OK", body = DecodeResponse),
+        (status = 400, description = "Unknown schema or invalid base64"),
+        (status = 422, description = "Not decodable with the schema"),
+    )
+)]
+pub async fn decode_handler(
+    State(state): State<AppState>,
+    Json(req): Json<DecodeRequest>,
+) -> Result<Json<DecodeResponse>, StatusCode> {
+    let frame = BASE64
+        .decode(req.frame_base64.trim())
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let schemas = state.sbe.lock().await;
+    let schema = schemas
+        .for_frame(&frame)
+        .ok_or(StatusCode::UNPROCESSABLE_ENTITY)?;
+    let (msg, template) = schema
+        .decode(&frame, &req.begin_string)
+        .map_err(unprocessable)?;
+
+    let wire = msg.encode_with(msg.delimiter);
+    let parsed = parse_raw(&wire);
+    Ok(Json(DecodeResponse {
+        schema_id: schema.id,
+        template,
+        msg_type: parsed.msg_type,
+        fix_message: String::from_utf8_lossy(&wire).into_owned(),
+        display: String::from_utf8_lossy(&msg.encode_with(b'|')).into_owned(),
+        fields: parsed.fields,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<sbe:messageSchema xmlns:sbe="http://fixprotocol.io/2016/sbe" package="test" id="8" version="1" byteOrder="littleEndian">
+  <types>
+    <composite name="messageHeader">
+      <type name="blockLength" primitiveType="uint16"/>
+      <type name="templateId" primitiveType="uint16"/>
+      <type name="schemaId" primitiveType="uint16"/>
+      <type name="version" primitiveType="uint16"/>
+    </composite>
+    <composite name="groupSize">
+      <type name="blockLength" primitiveType="uint16"/>
+      <type name="numInGroup" primitiveType="uint8"/>
+    </composite>
+    <composite name="varString">
+      <type name="length" primitiveType="uint16"/>
+      <type name="varData" primitiveType="uint8" length="0"/>
+    </composite>
+    <composite name="PRICE9">
+      <type name="mantissa" primitiveType="int64"/>
+      <type name="exponent" primitiveType="int8" presence="constant">-9</type>
+    </composite>
+    <type name="ClOrdID" primitiveType="char" length="20"/>
+    <type name="Symbol" primitiveType="char" length="8"/>
+    <enum name="Side" encodingType="uint8">
+      <validValue name="Buy">1</validValue>
+      <validValue name="Sell">2</validValue>
+    </enum>
+    <enum name="OrdType" encodingType="char">
+      <validValue name="Market">1</validValue>
+      <validValue name="Limit">2</validValue>
+    </enum>
+  </types>
+  <sbe:message name="NewOrderSingle" id="514" semanticType="D">
+    <field name="ClOrdID" id="11" type="ClOrdID"/>
+    <field name="Price" id="44" type="PRICE9" presence="optional"/>
+    <field name="OrderQty" id="38" type="uint32"/>
+    <field name="Side" id="54" type="Side"/>
+    <field name="OrdType" id="40" type="OrdType"/>
+    <field name="Symbol" id="55" type="Symbol"/>
+    <group name="NoPartyIDs" id="453" dimensionType="groupSize">
+      <field name="PartyID" id="448" type="Symbol"/>
+      <field name="PartyRole" id="452" type="uint8"/>
+    </group>
+    <data name="Text" id="58" type="varString"/>
+  </sbe:message>
+</sbe:messageSchema>"#;
+
+    /// A big-endian schema with one message per shape: a flat block with
+    /// every kind of encoding, a repeating group, and an empty body.
+    const MULTI: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<sbe:messageSchema xmlns:sbe="http://fixprotocol.io/2016/sbe" package="multi" id="9" version="2" byteOrder="bigEndian">
+  <types>
+    <composite name="messageHeader">
+      <type name="blockLength" primitiveType="uint16"/>
+      <type name="templateId" primitiveType="uint16"/>
+      <type name="schemaId" primitiveType="uint16"/>
+      <type name="version" primitiveType="uint16"/>
+    </composite>
+    <composite name="groupSizeEncoding">
+      <type name="blockLength" primitiveType="uint16"/>
+      <type name="numInGroup" primitiveType="uint16"/>
+    </composite>
+    <composite name="Decimal">
+      <type name="mantissa" primitiveType="int64"/>
+      <type name="exponent" primitiveType="int8"/>
+    </composite>
+    <composite name="PRICE4">
+      <type name="mantissa" primitiveType="int32"/>
+      <type name="exponent" primitiveType="int8" presence="constant">-4</type>
+    </composite>
+    <type name="ID" primitiveType="char" length="12"/>
+    <type name="Symbol" primitiveType="char" length="8"/>
+    <type name="Qty" primitiveType="int32" presence="optional" nullValue="-1"/>
+    <type name="Timestamp" primitiveType="uint64"/>
+    <enum name="ExecType" encodingType="char">
+      <validValue name="New">0</validValue>
+      <validValue name="Canceled">4</validValue>
+      <validValue name="Trade">F</validValue>
+    </enum>
+    <enum name="Side" encodingType="uint8">
+      <validValue name="Buy">1</validValue>
+      <validValue name="Sell">2</validValue>
+    </enum>
+    <enum name="MDUpdateAction" encodingType="uint8">
+      <validValue name="New">0</validValue>
+      <validValue name="Change">1</validValue>
+      <validValue name="Delete">2</validValue>
+    </enum>
+    <enum name="MDEntryType" encodingType="char">
+      <validValue name="Bid">0</validValue>
+      <validValue name="Offer">1</validValue>
+    </enum>
+    <set name="ExecInst" encodingType="uint16">
+      <choice name="NotHeld">0</choice>
+      <choice name="Work">2</choice>
+    </set>
+  </types>
+  <sbe:message name="ExecutionReport" id="2" semanticType="8">
+    <field name="OrderID" id="37" type="ID"/>
+    <field name="ExecID" id="17" type="ID"/>
+    <field name="ExecType" id="150" type="ExecType"/>
+    <field name="Side" id="54" type="Side"/>
+    <field name="Symbol" id="55" type="Symbol"/>
+    <field name="LastPx" id="31" type="Decimal" presence="optional"/>
+    <field name="LastQty" id="32" type="Qty"/>
+    <field name="LeavesQty" id="151" type="double"/>
+    <field name="ExecInst" id="18" type="ExecInst"/>
+    <field name="SecurityIDSource" id="22" type="char" presence="constant">8</field>
+  </sbe:message>
+  <sbe:message name="MarketDataIncrementalRefresh" id="3" semanticType="X">
+    <field name="LastUpdateTime" id="779" type="Timestamp"/>
+    <group name="NoMDEntries" id="268">
+      <field name="MDUpdateAction" id="279" type="MDUpdateAction"/>
+      <field name="MDEntryType" id="269" type="MDEntryType"/>
+      <field name="Symbol" id="55" type="Symbol"/>
+      <field name="MDEntryPx" id="270" type="PRICE4" presence="optional"/>
+      <field name="MDEntrySize" id="271" type="int32"/>
+      <field name="NetChgPrevDay" id="451" type="Decimal"/>
+    </group>
+  </sbe:message>
+  <sbe:message name="Heartbeat" id="4" semanticType="0">
+    <field name="TestReqID" id="112" type="ID" presence="optional"/>
+  </sbe:message>
+</sbe:messageSchema>"#;
+
+    fn fields(raw: &str) -> Vec<ParsedField> {
+        parse_raw(raw.as_bytes()).fields
+    }
+
+    fn display(msg: &FixMessage) -> String {
+        String::from_utf8(msg.encode_with(b'|')).unwrap()
+    }
+
+    #[test]
+    fn round_trips_through_sbe() {
+        let schema = Schema::from_xml(SCHEMA).unwrap();
+        let input = "8=FIX.4.4|9=0|35=D|49=ALICE|11=ORD1|44=150.25|38=100|54=1|40=2|55=AAPL|453=2|448=TRDR|452=11|448=FIRM|452=1|58=hello|9999=x|10=000|";
+        let (frame, template_id, unmapped) = schema.encode(&fields(input), None).unwrap();
+        assert_eq!(template_id, 514);
+        assert_eq!(unmapped, vec![9999]);
+        // header 8 + block (20 + 8 + 4 + 1 + 1 + 8) + group 3 + 2 * 9 + data 2 + 5
+        assert_eq!(frame.len(), 8 + 42 + 3 + 18 + 7);
+
+        let (msg, template) = schema.decode(&frame, "FIX.4.4").unwrap();
+        assert_eq!(template, "NewOrderSingle");
+        let out = display(&msg);
+        assert!(
+            out.contains("|35=D|11=ORD1|44=150.25|38=100|54=1|40=2|55=AAPL|453=2|448=TRDR|452=11|448=FIRM|452=1|58=hello|"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn optional_fields_decode_as_absent() {
+        let schema = Schema::from_xml(SCHEMA).unwrap();
+        let (frame, _, _) = schema
+            .encode(&fields("35=D|11=A|38=1|54=2|40=1|55=X|"), None)
+            .unwrap();
+        let out = display(&schema.decode(&frame, "FIX.4.4").unwrap().0);
+        assert!(out.contains("|35=D|11=A|38=1|54=2|40=1|55=X|10="), "{out}");
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        let schema = Schema::from_xml(SCHEMA).unwrap();
+        let e = schema
+            .encode(&fields("35=D|11=A|38=1|54=3|40=1|55=X|"), None)
+            .unwrap_err();
+        assert_eq!(e.to_string(), "Side(54): invalid value \"3\"");
+        assert!(schema
+            .encode(&fields("35=D|11=A|54=1|40=1|55=X|"), None)
+            .is_err());
+        assert!(schema
+            .encode(
+                &fields("35=D|11=A|38=1|54=1|40=1|55=X|44=1.0000000001|"),
+                None
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn each_message_type_round_trips_big_endian() {
+        let schema = Schema::from_xml(MULTI).unwrap();
+        let cases = [
+            (
+                "35=8|49=BROKER_A|52=20260223-09:30:00.000|37=O-1|17=E-1|150=F|54=2|55=VOD.L|31=101.125|32=300|151=99.5|18=5|",
+                2,
+                "ExecutionReport",
+                57,
+                8 + 57,
+                "|35=8|37=O-1|17=E-1|150=F|54=2|55=VOD.L|31=101.125|32=300|151=99.5|18=5|22=8|10=",
+            ),
+            (
+                "35=8|37=O-2|17=E-2|150=0|54=1|55=VOD.L|151=500|18=0|",
+                2,
+                "ExecutionReport",
+                57,
+                8 + 57,
+                "|35=8|37=O-2|17=E-2|150=0|54=1|55=VOD.L|151=500|18=0|22=8|10=",
+            ),
+            (
+                "35=X|779=1771804800000|268=2|279=0|269=0|55=VOD.L|270=101.25|271=500|451=-0.5|279=2|269=1|55=VOD.L|271=0|451=0|",
+                3,
+                "MarketDataIncrementalRefresh",
+                8,
+                8 + 8 + 4 + 2 * 27,
+                "|35=X|779=1771804800000|268=2|279=0|269=0|55=VOD.L|270=101.25|271=500|451=-0.5|279=2|269=1|55=VOD.L|271=0|451=0|10=",
+            ),
+            (
+                "35=X|779=1|",
+                3,
+                "MarketDataIncrementalRefresh",
+                8,
+                8 + 8 + 4,
+                "|35=X|779=1|10=",
+            ),
+            (
+                "35=0|112=PING-1|",
+                4,
+                "Heartbeat",
+                12,
+                8 + 12,
+                "|35=0|112=PING-1|10=",
+            ),
+            ("35=0|", 4, "Heartbeat", 12, 8 + 12, "|35=0|10="),
+        ];
+        for (input, template_id, name, block_length, len, expected) in cases {
+            let (frame, template, unmapped) = schema.encode(&fields(input), None).unwrap();
+            assert_eq!((template, unmapped), (template_id, vec![]), "{input}");
+            assert_eq!(frame.len(), len, "{input}");
+            let header: Vec<u16> = frame[..8]
+                .chunks(2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                .collect();
+            assert_eq!(header, [block_length, template_id, 9, 2], "{input}");
+
+            let (msg, template) = schema.decode(&frame, "FIXT.1.1").unwrap();
+            assert_eq!(template, name);
+            let out = display(&msg);
+            assert!(out.starts_with("8=FIXT.1.1|"), "{out}");
+            assert!(out.contains(expected), "{input} decoded as {out}");
+        }
+    }
+
+    #[test]
+    fn messages_are_chosen_by_template_or_msg_type() {
+        let schema = Schema::from_xml(MULTI).unwrap();
+        let (_, template, _) = schema.encode(&fields("35=0|"), Some(4)).unwrap();
+        assert_eq!(template, 4);
+        let e = schema.encode(&fields("35=D|11=A|"), None).unwrap_err();
+        assert_eq!(e.to_string(), "schema 9 has no message for MsgType \"D\"");
+        let e = schema.encode(&fields("35=0|"), Some(99)).unwrap_err();
+        assert_eq!(e.to_string(), "schema 9 has no message for MsgType \"0\"");
+
+        let mut schemas = SbeSchemas::default();
+        schemas.add(Schema::from_xml(SCHEMA).unwrap());
+        schemas.add(Schema::from_xml(MULTI).unwrap());
+        schemas.add(Schema::from_xml(MULTI).unwrap());
+        assert_eq!(schemas.len(), 2);
+        assert!(schemas.get(None).is_none());
+        let (frame, _, _) = schema.encode(&fields("35=0|"), None).unwrap();
+        assert_eq!(schemas.for_frame(&frame).map(|s| s.id), Some(9));
+        assert!(schemas.for_frame(&frame[..3]).is_none());
+    }
+
+    #[test]
+    fn truncated_frames_are_rejected() {
+        let schema = Schema::from_xml(SCHEMA).unwrap();
+        let (frame, _, _) = schema
+            .encode(
+                &fields("35=D|11=ORD1|38=100|54=1|40=2|55=AAPL|453=1|448=TRDR|452=11|58=hello|"),
+                None,
+            )
+            .unwrap();
+        assert!(schema.decode(&frame, "FIX.4.4").is_ok());
+        for len in 0..frame.len() {
+            assert!(
+                schema.decode(&frame[..len], "FIX.4.4").is_err(),
+                "decoded the first {len} of {} bytes",
+                frame.len()
+            );
+        }
+        let e = |len: usize| {
+            schema
+                .decode(&frame[..len], "FIX.4.4")
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(e(1), "frame truncated at byte 2");
+        assert_eq!(e(8), "frame truncated at byte 8");
+        assert_eq!(e(frame.len() - 1), "frame truncated in data field 58");
+
+        let mut unknown = frame.clone();
+        unknown[2..4].copy_from_slice(&999u16.to_le_bytes());
+        let e = schema.decode(&unknown, "FIX.4.4").unwrap_err();
+        assert_eq!(e.to_string(), "schema 8 has no template 999");
+    }
+
+    #[test]
+    fn bad_schemas_are_rejected() {
+        let error = |from: &str, to: &str| {
+            assert!(SCHEMA.contains(from), "{from}");
+            Schema::from_xml(&SCHEMA.replace(from, to))
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(Schema::from_xml("<sbe:messageSchema").is_err());
+        assert!(Schema::from_xml(&SCHEMA.replace("</sbe:messageSchema>", "")).is_err());
+        let cases = [
+            (
+                "messageSchema",
+                "schema",
+                "root element is not messageSchema",
+            ),
+            (
+                r#"<composite name="messageHeader">"#,
+                r#"<composite name="header">"#,
+                r#"header type "messageHeader" not defined"#,
+            ),
+            (
+                r#"<type name="version" primitiveType="uint16"/>"#,
+                "",
+                "header type has no version",
+            ),
+            (
+                r#"primitiveType="char" length="20""#,
+                r#"primitiveType="string" length="20""#,
+                r#"type ClOrdID: unknown primitive "string""#,
+            ),
+            (
+                r#"<type name="varData" primitiveType="uint8" length="0"/>"#,
+                r#"<ref name="varData" type="ClOrdID"/>"#,
+                "composite varString: only <type> members are supported",
+            ),
+            (
+                r#"encodingType="uint8""#,
+                r#"encodingType="Symbol""#,
+                "Symbol is not a primitive type",
+            ),
+            (
+                r#"type="uint32""#,
+                r#"type="Quantity""#,
+                r#"message NewOrderSingle: field OrderQty: unknown type "Quantity""#,
+            ),
+            (
+                r#"name="OrderQty" id="38""#,
+                r#"name="OrderQty""#,
+                "message NewOrderSingle: field OrderQty has no id",
+            ),
+            (
+                r#"id="38""#,
+                r#"id="qty""#,
+                r#"message NewOrderSingle: invalid id "qty""#,
+            ),
+            (
+                r#"type="PRICE9""#,
+                r#"type="groupSize""#,
+                "message NewOrderSingle: field Price: composite groupSize is not a decimal",
+            ),
+            (
+                r#"dimensionType="groupSize""#,
+                r#"dimensionType="groupSizeEncoding""#,
+                r#"message NewOrderSingle: unknown dimensionType "groupSizeEncoding""#,
+            ),
+            (
+                r#"dimensionType="groupSize""#,
+                r#"dimensionType="varString""#,
+                "message NewOrderSingle: varString needs blockLength and numInGroup",
+            ),
+            (
+                r#"type="varString""#,
+                r#"type="Symbol""#,
+                r#"message NewOrderSingle: unknown data type "Symbol""#,
+            ),
+            (
+                r#"name="NewOrderSingle" id="514""#,
+                r#"name="NewOrderSingle""#,
+                "message NewOrderSingle has no id",
+            ),
+        ];
+        for (from, to, expected) in cases {
+            assert_eq!(error(from, to), expected, "{from} -> {to}");
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Secrets read from outside the configuration.
+//!
+//! A setting that holds a secret — a TLS private key, the federation API key
+//! or the alert signing secret — can name where to find it instead of holding
+//! it inline:
+//!
+//! - `env:NAME`: the environment variable `NAME`, e.g. one a secret store's
+//!   agent injects.
+//! - `file:PATH`: the file's contents, without a trailing newline, e.g. a
+//!   mounted Kubernetes or Docker secret.
+//! - `vault:PATH#FIELD`: `FIELD` of the KV v2 secret at `PATH` in Vault, with
+//!   `FIX_VAULT_ADDR`, `FIX_VAULT_TOKEN` (itself an `env:` or `file:`
+//!   reference, or the token) and `FIX_VAULT_MOUNT` (default `secret`).
+//!
+//! Any other value is the secret itself, as before. Other stores, such as a
+//! cloud KMS, plug in as a [`SecretProvider`] under their own scheme.
+//! Secrets are resolved once at startup, and held as [`Secret`], which is
+//! zeroed when dropped and never printed.
+
+use axum::async_trait;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    time::Duration,
+};
+use zeroize::Zeroizing;
+
+const VAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A secret in memory, zeroed when dropped.
+#[derive(Clone)]
+pub struct Secret(Zeroizing<String>);
+
+impl Secret {
+    pub fn new(secret: String) -> Self {
+        Self(Zeroizing::new(secret))
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A secret given inline in a request body, such as an upstream's API key.
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(..)")
+    }
+}
+
+/// A store secrets are read from, under a scheme such as `vault`.
+#[async_trait]
+pub trait SecretProvider: Send + Sync {
+    /// Reads the secret `reference`, the part of the setting after the
+    /// scheme, names.
+    async fn fetch(&self, reference: &str) -> Result<Secret, String>;
+}
+
+struct Env;
+
+#[async_trait]
+impl SecretProvider for Env {
+    async fn fetch(&self, name: &str) -> Result<Secret, String> {
+        std::env::var(name)
+            .map(Secret::new)
+            .map_err(|_| format!("environment variable {name} is not set"))
+    }
+}
+
+struct File;
+
+#[async_trait]
+impl SecretProvider for File {
+    async fn fetch(&self, path: &str) -> Result<Secret, String> {
+        let mut contents = Zeroizing::new(
+            tokio::fs::read_to_string(path)
+                .await
+                .map_err(|e| format!("{path}: {e}"))?,
+        );
+        let len = contents.trim_end_matches(['\r', '\n']).len();
+        contents.truncate(len);
+        Ok(Secret(contents))
+    }
+}
+
+/// Reads KV version 2 secrets from Vault's HTTP API.
+pub struct Vault {
+    addr: String,
+    mount: String,
+    token: Secret,
+    http: reqwest::Client,
+}
+
+#[async_trait]
+impl SecretProvider for Vault {
+    async fn fetch(&self, reference: &str) -> Result<Secret, String> {
+        let (path, field) = reference
+            .split_once('#')
+            .ok_or_else(|| format!("vault:{reference} must be vault:PATH#FIELD"))?;
+        let url = format!(
+            "{}/v1/{}/data/{}",
+            self.addr.trim_end_matches('/'),
+            self.mount,
+            path.trim_start_matches('/')
+        );
+        let failed = |e: &dyn fmt::Display| format!("vault {path}: {e}");
+        let resp = self
+            .http
+            .get(&url)
+            .header("X-Vault-Token", self.token.expose())
+            .timeout(VAULT_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| failed(&e))?;
+        if !resp.status().is_success() {
+            return Err(failed(&resp.status()));
+        }
+        let body: Value = resp.json().await.map_err(|e| failed(&e))?;
+        body.get("data")
+            .and_then(|d| d.get("data"))
+            .and_then(|d| d.get(field))
+            .and_then(Value::as_str)
+            .map(|s| Secret::new(s.to_string()))
+            .ok_or_else(|| failed(&format!("no string field {field}")))
+    }
+}
+
+/// The providers settings may name, by scheme.
+pub struct Secrets {
+    providers: BTreeMap<&'static str, Box<dyn SecretProvider>>,
+}
+
+impl Default for Secrets {
+    fn default() -> Self {
+        let mut secrets = Self {
+            providers: BTreeMap::new(),
+        };
+        secrets.register("env", Box::new(Env));
+        secrets.register("file", Box::new(File));
+        secrets
+    }
+}
+
+impl Secrets {
+    /// `env:` and `file:`, and `vault:` with `FIX_VAULT_ADDR` set.
+    pub async fn from_env(http: &reqwest::Client) -> Result<Self, String> {
+        let var = |name| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let mut secrets = Self::default();
+        if let Some(addr) = var("FIX_VAULT_ADDR") {
+            let token = secrets
+                .var("FIX_VAULT_TOKEN")
+                .await?
+                .ok_or("FIX_VAULT_ADDR is set without FIX_VAULT_TOKEN")?;
+            let mount = var("FIX_VAULT_MOUNT").unwrap_or_else(|| "secret".to_string());
+            let vault = Vault {
+                addr: addr.trim().to_string(),
+                mount: mount.trim().trim_matches('/').to_string(),
+                token,
+                http: http.clone(),
+            };
+            secrets.register("vault", Box::new(vault));
+        }
+        Ok(secrets)
+    }
+
+    pub fn register(&mut self, scheme: &'static str, provider: Box<dyn SecretProvider>) {
+        self.providers.insert(scheme, provider);
+    }
+
+    /// The schemes settings may use, for startup reporting.
+    pub fn describe(&self) -> String {
+        let schemes: Vec<_> = self.providers.keys().copied().collect();
+        format!("secret providers={}", schemes.join(","))
+    }
+
+    /// The secret `setting` names, or `setting` itself if it names none.
+    pub async fn resolve(&self, setting: &str) -> Result<Secret, String> {
+        match self.provider(setting) {
+            Some((provider, reference)) => provider.fetch(reference).await,
+            None => Ok(Secret::new(setting.to_string())),
+        }
+    }
+
+    /// The contents of the file `setting` names, or of the secret it refers
+    /// to; for settings, such as TLS keys, that have always been paths.
+    pub async fn resolve_file(&self, setting: &str) -> Result<Secret, String> {
+        match self.provider(setting) {
+            Some((provider, reference)) => provider.fetch(reference).await,
+            None => File.fetch(setting.trim()).await,
+        }
+    }
+
+    /// The secret in the environment variable `name`, resolved; `None` if
+    /// it is unset. Errors name the variable.
+    pub async fn var(&self, name: &str) -> Result<Option<Secret>, String> {
+        match std::env::var(name) {
+            Ok(setting) if !setting.trim().is_empty() => self
+                .resolve(setting.trim())
+                .await
+                .map(Some)
+                .map_err(|e| format!("{name}: {e}")),
+            _ => Ok(None),
+        }
+    }
+
+    fn provider<'a>(&self, setting: &'a str) -> Option<(&dyn SecretProvider, &'a str)> {
+        let (scheme, reference) = setting.trim().split_once(':')?;
+        let provider = self.providers.get(scheme)?;
+        Some((provider.as_ref(), reference))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed;
+
+    #[async_trait]
+    impl SecretProvider for Fixed {
+        async fn fetch(&self, reference: &str) -> Result<Secret, String> {
+            match reference {
+                "fix/broker-a#password" => Ok(Secret::new("hunter2".to_string())),
+                other => Err(format!("no secret {other}")),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn settings_name_where_their_secret_is() {
+        let dir = std::env::temp_dir().join(format!("fix-secrets-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("password");
+        std::fs::write(&path, "from-a-file\n").unwrap();
+        let mut secrets = Secrets::default();
+        secrets.register("kms", Box::new(Fixed));
+
+        let file = secrets.resolve(&format!("file:{}", path.display())).await.unwrap();
+        assert_eq!(file.expose(), "from-a-file");
+        let kms = secrets.resolve("kms:fix/broker-a#password").await.unwrap();
+        assert_eq!(kms.expose(), "hunter2");
+        assert!(secrets.resolve("kms:fix/broker-b#password").await.is_err());
+        assert!(secrets.resolve("env:FIX_TEST_SECRET_THAT_IS_NOT_SET").await.is_err());
+        // Values naming no provider are the secret, or for keys the path.
+        assert_eq!(secrets.resolve("plain:text").await.unwrap().expose(), "plain:text");
+        let key = secrets.resolve_file(&path.display().to_string()).await.unwrap();
+        assert_eq!(key.expose(), "from-a-file");
+
+        assert_eq!(format!("{kms:?}"), "Secret(..)");
+        assert_eq!(secrets.describe(), "secret providers=env,file,kms");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,681 @@
+//! Security definitions downloaded from venues.
+//!
+//! For the sessions named in `FIX_SECURITY_LIST`, the gateway sends a
+//! SecurityListRequest (35=x) for all securities each time the session
+//! becomes `ACTIVE`; `POST /api/v1/securities/request` asks for one at any
+//! time. The SecurityList (35=y) fragments answering it are collected under
+//! their SecurityReqID (320) and replace the session's cached definitions
+//! once LastFragment (893) arrives. A SecurityDefinition (35=d) updates one
+//! instrument.
+//!
+//! Orders sent on a session are checked against its definitions: the
+//! SecurityID is filled in, and a price off the tick size or a quantity that
+//! is not a whole number of lots is refused. Once a session's list is
+//! complete, a symbol missing from it is refused too.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+use tracing::{info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    auth::Caller, clock::epoch_ms, find_session, lookup_field, problem::ApiError,
+    queue_if_offline, send_message, AppState, FixSession, Groups, OutputEncoding, SendOptions,
+    SendRequest,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SecurityDefinition {
+    pub session_id: String,
+    pub symbol: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub security_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub security_id_source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub security_type: Option<String>,
+    /// MinPriceIncrement (969).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tick_size: Option<f64>,
+    /// RoundLot (561).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lot_size: Option<f64>,
+    pub updated_at_ms: u64,
+}
+
+impl SecurityDefinition {
+    fn new(session_id: &str, symbol: &str, at_ms: u64) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            symbol: symbol.to_string(),
+            security_id: None,
+            security_id_source: None,
+            security_type: None,
+            tick_size: None,
+            lot_size: None,
+            updated_at_ms: at_ms,
+        }
+    }
+
+    /// Sets the instrument field `tag`; other tags are ignored.
+    fn set(&mut self, tag: u32, value: &str) {
+        let positive = || value.parse::<f64>().ok().filter(|v| *v > 0.0);
+        match tag {
+            48 => self.security_id = Some(value.to_string()),
+            22 => self.security_id_source = Some(value.to_string()),
+            167 => self.security_type = Some(value.to_string()),
+            969 => self.tick_size = positive(),
+            561 => self.lot_size = positive(),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[schema(as = SecurityListState)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ListState {
+    /// Sent (or queued), waiting for fragments.
+    Pending,
+    /// Some fragments received, not the last.
+    Loading,
+    Complete,
+    /// SecurityRequestResult (560) was not 0.
+    Rejected,
+}
+
+/// A SecurityListRequest and the SecurityList answering it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SecurityListDownload {
+    pub security_req_id: String,
+    pub session_id: String,
+    pub state: ListState,
+    /// Instruments received so far.
+    pub received: usize,
+    /// TotNoRelatedSym (393), if the venue sent it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_result: Option<String>,
+    pub requested_at_ms: u64,
+    pub updated_at_ms: u64,
+}
+
+/// Why an order was refused by [`SecurityCache::check_order`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum SecurityError {
+    /// The session's list is complete and does not have the symbol.
+    UnknownSymbol(String),
+    OffTick {
+        price: String,
+        tick_size: f64,
+    },
+    OddLot {
+        qty: String,
+        lot_size: f64,
+    },
+}
+
+impl fmt::Display for SecurityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownSymbol(symbol) => write!(f, "{symbol} is not in the security list"),
+            Self::OffTick { price, tick_size } => {
+                write!(
+                    f,
+                    "price {price} is not a multiple of the tick size {tick_size}"
+                )
+            }
+            Self::OddLot { qty, lot_size } => {
+                write!(
+                    f,
+                    "quantity {qty} is not a multiple of the lot size {lot_size}"
+                )
+            }
+        }
+    }
+}
+
+/// Whether `value` is a whole multiple of `step`, allowing for the rounding
+/// of decimal prices in binary floating point.
+fn is_multiple(value: f64, step: f64) -> bool {
+    let steps = value / step;
+    (steps - steps.round()).abs() < 1e-6
+}
+
+#[derive(Default)]
+struct SessionSecurities {
+    by_symbol: HashMap<String, SecurityDefinition>,
+    /// Whether a full list has been received.
+    complete: bool,
+    /// Definitions collected for the download in progress.
+    loading: HashMap<String, SecurityDefinition>,
+}
+
+#[derive(Default)]
+pub struct SecurityCache {
+    /// Sessions whose list is requested when they are logged on.
+    on_logon: HashSet<String>,
+    sessions: HashMap<String, SessionSecurities>,
+    downloads: HashMap<String, SecurityListDownload>,
+}
+
+impl SecurityCache {
+    /// Reads `FIX_SECURITY_LIST`, a comma-separated list of session IDs.
+    pub fn from_env() -> Self {
+        let on_logon = std::env::var("FIX_SECURITY_LIST")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect();
+        Self {
+            on_logon,
+            ..Self::default()
+        }
+    }
+
+    pub fn download_on_logon(&self, session_id: &str) -> bool {
+        self.on_logon.contains(session_id)
+    }
+
+    pub fn get(&self, session_id: &str, symbol: &str) -> Option<&SecurityDefinition> {
+        self.sessions.get(session_id)?.by_symbol.get(symbol)
+    }
+
+    /// Definitions ordered by session and symbol, optionally for one of each.
+    pub fn list(&self, session_id: Option<&str>, symbol: Option<&str>) -> Vec<SecurityDefinition> {
+        let mut items: Vec<SecurityDefinition> = self
+            .sessions
+            .iter()
+            .filter(|(id, _)| session_id.is_none_or(|s| s == id.as_str()))
+            .flat_map(|(_, s)| s.by_symbol.values())
+            .filter(|d| symbol.is_none_or(|s| s == d.symbol))
+            .cloned()
+            .collect();
+        items.sort_by(|a, b| (&a.session_id, &a.symbol).cmp(&(&b.session_id, &b.symbol)));
+        items
+    }
+
+    /// Downloads, newest first.
+    pub fn downloads(&self) -> Vec<SecurityListDownload> {
+        let mut items: Vec<SecurityListDownload> = self.downloads.values().cloned().collect();
+        items.sort_by(|a, b| {
+            (b.requested_at_ms, &b.security_req_id).cmp(&(a.requested_at_ms, &a.security_req_id))
+        });
+        items
+    }
+
+    fn requested(&mut self, session_id: &str, security_req_id: &str, at_ms: u64) {
+        self.sessions
+            .entry(session_id.to_string())
+            .or_default()
+            .loading
+            .clear();
+        self.downloads.insert(
+            security_req_id.to_string(),
+            SecurityListDownload {
+                security_req_id: security_req_id.to_string(),
+                session_id: session_id.to_string(),
+                state: ListState::Pending,
+                received: 0,
+                total: None,
+                request_result: None,
+                requested_at_ms: at_ms,
+                updated_at_ms: at_ms,
+            },
+        );
+    }
+
+    /// Applies a SecurityList fragment. Each NoRelatedSym (146) instance
+    /// starts with its Symbol (55). A list answering a request of ours
+    /// replaces the session's definitions when its last fragment arrives; an
+    /// unsolicited one updates the instruments it names.
+    pub fn on_security_list(
+        &mut self,
+        session_id: &str,
+        fields: &[(u32, &str)],
+        at_ms: u64,
+    ) -> Option<SecurityListDownload> {
+        let value = |tag: u32| fields.iter().find(|(t, _)| *t == tag).map(|(_, v)| *v);
+        let mut definitions: Vec<SecurityDefinition> = Vec::new();
+        for &(tag, v) in fields.iter().skip_while(|(t, _)| *t != 146) {
+            match (tag, definitions.last_mut()) {
+                (55, _) => definitions.push(SecurityDefinition::new(session_id, v, at_ms)),
+                (tag, Some(definition)) => definition.set(tag, v),
+                _ => {}
+            }
+        }
+        let last_fragment = value(893) != Some("N");
+        let rejected = value(560).filter(|r| *r != "0");
+        let securities = self.sessions.entry(session_id.to_string()).or_default();
+
+        let download = value(320)
+            .and_then(|id| self.downloads.get_mut(id))
+            .filter(|d| d.session_id == session_id);
+        let Some(download) = download else {
+            for definition in definitions {
+                securities
+                    .by_symbol
+                    .insert(definition.symbol.clone(), definition);
+            }
+            return None;
+        };
+        download.updated_at_ms = at_ms;
+        download.total = value(393).and_then(|t| t.parse().ok()).or(download.total);
+        if let Some(result) = rejected {
+            download.state = ListState::Rejected;
+            download.request_result = Some(result.to_string());
+            securities.loading.clear();
+            return Some(download.clone());
+        }
+        download.received += definitions.len();
+        for definition in definitions {
+            securities
+                .loading
+                .insert(definition.symbol.clone(), definition);
+        }
+        if last_fragment {
+            download.state = ListState::Complete;
+            securities.by_symbol = std::mem::take(&mut securities.loading);
+            securities.complete = true;
+        } else {
+            download.state = ListState::Loading;
+        }
+        Some(download.clone())
+    }
+
+    /// Applies a SecurityDefinition for one instrument.
+    pub fn on_definition(
+        &mut self,
+        session_id: &str,
+        fields: &[(u32, &str)],
+        at_ms: u64,
+    ) -> Option<SecurityDefinition> {
+        let symbol = fields.iter().find(|(t, _)| *t == 55)?.1;
+        let mut definition = SecurityDefinition::new(session_id, symbol, at_ms);
+        for &(tag, v) in fields {
+            definition.set(tag, v);
+        }
+        self.sessions
+            .entry(session_id.to_string())
+            .or_default()
+            .by_symbol
+            .insert(symbol.to_string(), definition.clone());
+        Some(definition)
+    }
+
+    /// Checks an outbound order's Symbol, Price, StopPx and OrderQty against
+    /// the session's definition, and fills in SecurityID (48) and
+    /// SecurityIDSource (22) if the order has none. Orders are let through
+    /// unchecked until a definition or complete list is known.
+    pub fn check_order(
+        &self,
+        session_id: &str,
+        fields: &mut HashMap<String, String>,
+    ) -> Result<(), SecurityError> {
+        let Some(symbol) = lookup_field(fields, "Symbol", 55) else {
+            return Ok(());
+        };
+        let terms = OrderTerms {
+            symbol,
+            price: lookup_field(fields, "Price", 44).map(String::as_str),
+            stop_px: lookup_field(fields, "StopPx", 99).map(String::as_str),
+            order_qty: lookup_field(fields, "OrderQty", 38).and_then(|q| q.parse().ok()),
+        };
+        let Some(definition) = self.check_terms(session_id, &terms)? else {
+            return Ok(());
+        };
+        if lookup_field(fields, "SecurityID", 48).is_none() {
+            if let Some(security_id) = &definition.security_id {
+                fields.insert("48".to_string(), security_id.clone());
+                if let Some(source) = &definition.security_id_source {
+                    fields.insert("22".to_string(), source.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks an order's terms against the session's definition, returning
+    /// the definition if there is one. Allocates only to report an error.
+    pub fn check_terms(
+        &self,
+        session_id: &str,
+        terms: &OrderTerms<'_>,
+    ) -> Result<Option<&SecurityDefinition>, SecurityError> {
+        let Some(securities) = self.sessions.get(session_id) else {
+            return Ok(None);
+        };
+        let Some(definition) = securities.by_symbol.get(terms.symbol) else {
+            return match securities.complete {
+                true => Err(SecurityError::UnknownSymbol(terms.symbol.to_string())),
+                false => Ok(None),
+            };
+        };
+        if let Some(tick_size) = definition.tick_size {
+            for price in [terms.price, terms.stop_px].into_iter().flatten() {
+                if price.parse().is_ok_and(|p| !is_multiple(p, tick_size)) {
+                    return Err(SecurityError::OffTick {
+                        price: price.to_string(),
+                        tick_size,
+                    });
+                }
+            }
+        }
+        if let (Some(lot_size), Some(qty)) = (definition.lot_size, terms.order_qty) {
+            if !is_multiple(qty, lot_size) {
+                return Err(SecurityError::OddLot {
+                    qty: qty.to_string(),
+                    lot_size,
+                });
+            }
+        }
+        Ok(Some(definition))
+    }
+}
+
+/// The fields of an order checked against its security definition.
+#[derive(Debug, Clone, Copy)]
+pub struct OrderTerms<'a> {
+    pub symbol: &'a str,
+    pub price: Option<&'a str>,
+    pub stop_px: Option<&'a str>,
+    pub order_qty: Option<f64>,
+}
+
+/// Sends a SecurityListRequest (35=x) for all securities, or queues it if
+/// the session is not logged on.
+pub async fn request_list(
+    state: &AppState,
+    session: &FixSession,
+) -> Result<SecurityListDownload, StatusCode> {
+    let security_req_id = format!("SL-{}", uuid::Uuid::new_v4().simple());
+    state
+        .securities
+        .lock()
+        .await
+        .requested(&session.session_id, &security_req_id, epoch_ms());
+
+    let mut fields = HashMap::from([
+        ("320".to_string(), security_req_id.clone()),
+        // SecurityListRequestType 4: all securities.
+        ("559".to_string(), "4".to_string()),
+    ]);
+    for (name, value) in [
+        ("BeginString", &session.fix_version),
+        ("SenderCompID", &session.sender_comp_id),
+        ("TargetCompID", &session.target_comp_id),
+    ] {
+        fields.insert(name.to_string(), value.clone());
+    }
+    let send = SendRequest {
+        msg_type: "SecurityListRequest".to_string(),
+        fields,
+        groups: Groups::new(),
+        metadata: HashMap::new(),
+        reply_to: None,
+        ttl_ms: None,
+        template: None,
+        attribution: None,
+    };
+    let opts = SendOptions {
+        encoding: OutputEncoding::default(),
+        validate: false,
+        strict: None,
+    };
+    let queued = queue_if_offline(state, &opts, &send).await?;
+    if queued.is_none() {
+        send_message(state, &opts, send).await?;
+    }
+    info!(
+        session_id = %session.session_id,
+        security_req_id = %security_req_id,
+        queued = queued.is_some(),
+        "SecurityListRequest sent"
+    );
+    state
+        .securities
+        .lock()
+        .await
+        .downloads
+        .get(&security_req_id)
+        .cloned()
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Requests the security list of a session that has just logged on, if it
+/// is configured to download one.
+pub async fn on_logon(state: &AppState, session: &FixSession) -> Option<SecurityListDownload> {
+    let wanted = state
+        .securities
+        .lock()
+        .await
+        .download_on_logon(&session.session_id);
+    if !wanted {
+        return None;
+    }
+    match request_list(state, session).await {
+        Ok(download) => Some(download),
+        Err(status) => {
+            warn!(
+                session_id = %session.session_id,
+                status = %status,
+                "SecurityListRequest failed"
+            );
+            None
+        }
+    }
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = SecurityListRequest)]
+pub struct ListRequestBody {
+    session_id: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/securities/request",
+    tag = "securities",
+    request_body = ListRequestBody,
+    responses(
+        (status = 200, description = "The download started", body = SecurityListDownload),
+        (status = 404, description = "Unknown session"),
+        (status = 429, description = "The client is over its rate limit; see Retry-After"),
+    )
+)]
+pub async fn request(
+    State(state): State<AppState>,
+    caller: Caller,
+    Json(req): Json<ListRequestBody>,
+) -> Result<Json<SecurityListDownload>, ApiError> {
+    caller.check_session(&req.session_id)?;
+    let session = find_session(&state, &req.session_id).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(request_list(&state, &session).await?))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DownloadsResponse {
+    items: Vec<SecurityListDownload>,
+    count: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/securities/lists",
+    tag = "securities",
+    responses(
+        (status = 200, description = "OK", body = DownloadsResponse),
+    )
+)]
+pub async fn downloads(State(state): State<AppState>) -> Json<DownloadsResponse> {
+    let items = state.securities.lock().await.downloads();
+    let count = items.len();
+    Json(DownloadsResponse { items, count })
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SecuritiesQuery {
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
+    symbol: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SecuritiesResponse {
+    items: Vec<SecurityDefinition>,
+    count: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/securities",
+    tag = "securities",
+    params(SecuritiesQuery),
+    responses(
+        (status = 200, description = "OK", body = SecuritiesResponse),
+    )
+)]
+pub async fn list(
+    State(state): State<AppState>,
+    Query(query): Query<SecuritiesQuery>,
+) -> Json<SecuritiesResponse> {
+    let items = state
+        .securities
+        .lock()
+        .await
+        .list(query.session_id.as_deref(), query.symbol.as_deref());
+    let count = items.len();
+    Json(SecuritiesResponse { items, count })
+}
+
+/// One symbol's definition: the given session's, or the most recently
+/// updated across sessions.
+#[utoipa::path(
+    get,
+    path = "/api/v1/securities/{symbol}",
+    tag = "securities",
+    params(("symbol" = String, Path, description = "The instrument's symbol."), SecuritiesQuery),
+    responses(
+        (status = 200, description = "OK", body = SecurityDefinition),
+        (status = 404, description = "Unknown symbol"),
+    )
+)]
+pub async fn get(
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+    Query(query): Query<SecuritiesQuery>,
+) -> Result<Json<SecurityDefinition>, StatusCode> {
+    let cache = state.securities.lock().await;
+    let definition = match query.session_id {
+        Some(session_id) => cache.get(&session_id, &symbol).cloned(),
+        None => cache
+            .list(None, Some(&symbol))
+            .into_iter()
+            .max_by_key(|d| d.updated_at_ms),
+    };
+    definition.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SESSION: &str = "ALICE->BROKER_A";
+
+    fn fragment<'a>(req_id: &'a str, last: &'a str, symbols: &[&'a str]) -> Vec<(u32, &'a str)> {
+        let mut fields = vec![(320, req_id), (560, "0"), (893, last), (146, "2")];
+        for symbol in symbols {
+            fields.extend([(55, *symbol), (48, "JP3633400001"), (22, "4")]);
+            fields.extend([(969, "0.5"), (561, "100")]);
+        }
+        fields
+    }
+
+    #[test]
+    fn security_lists_replace_definitions_on_their_last_fragment() {
+        let mut cache = SecurityCache::default();
+        cache.on_definition(SESSION, &[(55, "OLD"), (969, "1")], 1);
+        cache.requested(SESSION, "SL1", 2);
+
+        let first = cache.on_security_list(SESSION, &fragment("SL1", "N", &["7203", "6758"]), 3);
+        assert_eq!(first.unwrap().state, ListState::Loading);
+        assert!(cache.get(SESSION, "7203").is_none());
+        assert!(cache.get(SESSION, "OLD").is_some());
+
+        let last = cache
+            .on_security_list(SESSION, &fragment("SL1", "Y", &["9984"]), 4)
+            .unwrap();
+        assert_eq!((last.state, last.received), (ListState::Complete, 3));
+        assert!(cache.get(SESSION, "OLD").is_none());
+        let definition = cache.get(SESSION, "6758").unwrap();
+        assert_eq!(definition.security_id.as_deref(), Some("JP3633400001"));
+        assert_eq!(
+            (definition.tick_size, definition.lot_size),
+            (Some(0.5), Some(100.0))
+        );
+        assert_eq!(cache.list(Some(SESSION), None).len(), 3);
+
+        cache.requested(SESSION, "SL2", 5);
+        let rejected = cache
+            .on_security_list(SESSION, &[(320, "SL2"), (560, "2")], 6)
+            .unwrap();
+        assert_eq!(rejected.state, ListState::Rejected);
+        assert_eq!(cache.list(Some(SESSION), None).len(), 3);
+    }
+
+    #[test]
+    fn orders_are_checked_and_enriched_against_definitions() {
+        let mut cache = SecurityCache::default();
+        let order = |price: &str, qty: &str| {
+            HashMap::from([
+                ("55".to_string(), "7203".to_string()),
+                ("44".to_string(), price.to_string()),
+                ("38".to_string(), qty.to_string()),
+            ])
+        };
+        // Nothing known for the session: let through untouched.
+        let mut fields = order("2500.3", "7");
+        assert_eq!(cache.check_order(SESSION, &mut fields), Ok(()));
+        assert!(!fields.contains_key("48"));
+
+        cache.requested(SESSION, "SL1", 1);
+        cache.on_security_list(SESSION, &fragment("SL1", "Y", &["7203"]), 2);
+        let mut fields = order("2500.5", "300");
+        assert_eq!(cache.check_order(SESSION, &mut fields), Ok(()));
+        assert_eq!(fields["48"], "JP3633400001");
+        assert_eq!(fields["22"], "4");
+
+        let mut fields = order("2500.3", "300");
+        assert!(matches!(
+            cache.check_order(SESSION, &mut fields),
+            Err(SecurityError::OffTick { .. })
+        ));
+        let mut fields = order("2500", "150");
+        assert!(matches!(
+            cache.check_order(SESSION, &mut fields),
+            Err(SecurityError::OddLot { .. })
+        ));
+        let mut fields = order("2500", "100");
+        fields.insert("55".to_string(), "1234".to_string());
+        assert_eq!(
+            cache.check_order(SESSION, &mut fields),
+            Err(SecurityError::UnknownSymbol("1234".to_string()))
+        );
+        assert!(is_multiple(0.3, 0.1) && is_multiple(101.25, 0.05));
+    }
+}
@@ -0,0 +1,367 @@
+//! Server-Sent Events feed of session lifecycle events.
+//!
+//! Dashboards that cannot hold a WebSocket follow sessions through
+//! `GET /api/v1/fix/sessions/events`. Transport state changes reported to the
+//! gateway become `CONNECTED`, `LOGON_COMPLETE` and `DISCONNECTED` events.
+//! Recorded TestRequests, ResendRequests and SequenceResets become
+//! `HEARTBEAT_MISSED`, `RESEND_INITIATED` and `SEQUENCE_RESET`.
+//!
+//! Each event has an increasing ID, and the most recent ones are kept so a
+//! client reconnecting with `Last-Event-ID` is sent the events it missed.
+
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, convert::Infallible};
+use tokio::sync::{broadcast, Mutex};
+use tracing::{info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{clock::epoch_ms, records::Direction, store::field_value, AppState};
+
+/// Events kept for clients resuming with `Last-Event-ID`.
+const RECENT_EVENTS: usize = 1000;
+
+/// Events buffered per client before it is lagged.
+const STREAM_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SessionEventKind {
+    /// The transport is up and a Logon has been sent (`LOGON_SENT`).
+    Connected,
+    /// The session is logged on (`ACTIVE`).
+    LogonComplete,
+    /// The gateway sent a TestRequest because the counterparty went quiet.
+    HeartbeatMissed,
+    /// A ResendRequest was sent or received.
+    ResendInitiated,
+    /// The session is `DISCONNECTED`.
+    Disconnected,
+    /// A SequenceReset, Reset or GapFill, was sent or received.
+    SequenceReset,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SessionEvent {
+    pub id: u64,
+    pub at_ms: u64,
+    pub session_id: String,
+    pub kind: SessionEventKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direction: Option<Direction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub test_req_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub begin_seq_no: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_seq_no: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_seq_no: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gap_fill: Option<bool>,
+}
+
+impl SessionEvent {
+    fn new(session_id: &str, kind: SessionEventKind) -> Self {
+        Self {
+            id: 0,
+            at_ms: epoch_ms(),
+            session_id: session_id.to_string(),
+            kind,
+            previous_state: None,
+            direction: None,
+            test_req_id: None,
+            begin_seq_no: None,
+            end_seq_no: None,
+            new_seq_no: None,
+            gap_fill: None,
+        }
+    }
+
+    /// The event for a session-level message, if it is one that has one.
+    fn for_message(
+        session_id: &str,
+        direction: Direction,
+        msg_type: &str,
+        raw: &[u8],
+    ) -> Option<Self> {
+        let text = |tag| field_value(raw, tag).map(|v| String::from_utf8_lossy(v).into_owned());
+        let number = |tag| text(tag).and_then(|v| v.parse().ok());
+        let mut event = match (msg_type, direction) {
+            ("1", Direction::Outbound) => {
+                let mut event = Self::new(session_id, SessionEventKind::HeartbeatMissed);
+                event.test_req_id = text(112);
+                event
+            }
+            ("2", _) => {
+                let mut event = Self::new(session_id, SessionEventKind::ResendInitiated);
+                event.begin_seq_no = number(7);
+                event.end_seq_no = number(16);
+                event
+            }
+            ("4", _) => {
+                let mut event = Self::new(session_id, SessionEventKind::SequenceReset);
+                event.new_seq_no = number(36);
+                event.gap_fill = Some(text(123).as_deref() == Some("Y"));
+                event
+            }
+            _ => return None,
+        };
+        event.direction = Some(direction);
+        Some(event)
+    }
+}
+
+#[derive(Default)]
+struct Recent {
+    last_id: u64,
+    events: VecDeque<SessionEvent>,
+}
+
+pub struct SessionEvents {
+    recent: Mutex<Recent>,
+    sender: broadcast::Sender<SessionEvent>,
+}
+
+impl Default for SessionEvents {
+    fn default() -> Self {
+        Self {
+            recent: Mutex::new(Recent::default()),
+            sender: broadcast::channel(STREAM_CAPACITY).0,
+        }
+    }
+}
+
+impl SessionEvents {
+    async fn publish(&self, mut event: SessionEvent) {
+        let mut recent = self.recent.lock().await;
+        recent.last_id += 1;
+        event.id = recent.last_id;
+        info!(
+            session_id = %event.session_id,
+            kind = ?event.kind,
+            id = event.id,
+            "Session event"
+        );
+        if recent.events.len() == RECENT_EVENTS {
+            recent.events.pop_front();
+        }
+        recent.events.push_back(event.clone());
+        // Sent under the lock, so a subscriber sees each event exactly once.
+        let _ = self.sender.send(event);
+    }
+
+    /// Subscribes to new events, returning with it the kept events after
+    /// `last_id`.
+    async fn subscribe(
+        &self,
+        last_id: Option<u64>,
+    ) -> (Vec<SessionEvent>, broadcast::Receiver<SessionEvent>) {
+        let recent = self.recent.lock().await;
+        let missed = match last_id {
+            Some(last_id) => recent
+                .events
+                .iter()
+                .filter(|e| e.id > last_id)
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+        (missed, self.sender.subscribe())
+    }
+}
+
+/// Publishes the event for a session state change, if it has one.
+pub async fn on_state_change(
+    state: &AppState,
+    session_id: &str,
+    previous: &str,
+    current: &str,
+) {
+    let kind = match current {
+        "LOGON_SENT" => SessionEventKind::Connected,
+        "ACTIVE" => SessionEventKind::LogonComplete,
+        "DISCONNECTED" => SessionEventKind::Disconnected,
+        _ => return,
+    };
+    if previous == current {
+        return;
+    }
+    let mut event = SessionEvent::new(session_id, kind);
+    event.previous_state = Some(previous.to_string());
+    state.session_events.publish(event).await;
+}
+
+/// Publishes the event for a sent or received message, if it has one.
+pub async fn on_message(
+    state: &AppState,
+    session_id: &str,
+    direction: Direction,
+    msg_type: &str,
+    raw: &[u8],
+) {
+    if let Some(event) = SessionEvent::for_message(session_id, direction, msg_type, raw) {
+        state.session_events.publish(event).await;
+    }
+}
+
+// ── Handler ───────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventsQuery {
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Comma-separated event kinds; all kinds if omitted.
+    #[serde(default)]
+    pub kind: Option<String>,
+}
+
+struct Filter {
+    session_id: Option<String>,
+    kinds: Vec<SessionEventKind>,
+}
+
+impl Filter {
+    fn new(query: EventsQuery) -> Self {
+        let kinds = query
+            .kind
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|k| !k.is_empty())
+            .filter_map(|k| serde_json::from_value(serde_json::Value::from(k)).ok())
+            .collect();
+        Self {
+            session_id: query.session_id,
+            kinds,
+        }
+    }
+
+    fn matches(&self, event: &SessionEvent) -> bool {
+        self.session_id
+            .as_ref()
+            .is_none_or(|s| *s == event.session_id)
+            && (self.kinds.is_empty() || self.kinds.contains(&event.kind))
+    }
+}
+
+fn sse_event(event: &SessionEvent) -> Event {
+    Event::default()
+        .id(event.id.to_string())
+        .data(serde_json::to_string(event).unwrap_or_default())
+}
+
+/// Streams session events as Server-Sent Events, each a JSON object in the
+/// `data` field with its ID as the SSE `id`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/fix/sessions/events",
+    tag = "sessions",
+    params(EventsQuery),
+    responses(
+        (
+            status = 200,
+            description = "Server-Sent Events, one session event each",
+            content_type = "text/event-stream",
+            body = SessionEvent
+        ),
+    )
+)]
+pub async fn stream(
+    State(state): State<AppState>,
+    Query(query): Query<EventsQuery>,
+    headers: HeaderMap,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let last_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok());
+    let filter = Filter::new(query);
+    let (missed, receiver) = state.session_events.subscribe(last_id).await;
+    let missed: VecDeque<SessionEvent> = missed.into_iter().filter(|e| filter.matches(e)).collect();
+
+    let events = stream::unfold(
+        (missed, receiver, filter),
+        |(mut missed, mut receiver, filter)| async move {
+            if let Some(event) = missed.pop_front() {
+                return Some((Ok(sse_event(&event)), (missed, receiver, filter)));
+            }
+            loop {
+                match receiver.recv().await {
+                    Ok(event) if filter.matches(&event) => {
+                        return Some((Ok(sse_event(&event)), (missed, receiver, filter)));
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!(missed, "Session event client lagging, events dropped");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        },
+    );
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_messages_become_events() {
+        let raw = b"8=FIX.4.4|35=2|34=9|7=5|16=0|10=000|";
+        let event =
+            SessionEvent::for_message("ALICE->BROKER_A", Direction::Inbound, "2", raw).unwrap();
+        assert_eq!(event.kind, SessionEventKind::ResendInitiated);
+        assert_eq!((event.begin_seq_no, event.end_seq_no), (Some(5), Some(0)));
+
+        let raw = b"8=FIX.4.4|35=4|34=5|43=Y|123=Y|36=9|10=000|";
+        let event =
+            SessionEvent::for_message("ALICE->BROKER_A", Direction::Outbound, "4", raw).unwrap();
+        assert_eq!(event.kind, SessionEventKind::SequenceReset);
+        assert_eq!((event.new_seq_no, event.gap_fill), (Some(9), Some(true)));
+
+        // A TestRequest from the counterparty is not a missed heartbeat of ours.
+        let raw = b"8=FIX.4.4|35=1|34=3|112=T1|10=000|";
+        assert!(
+            SessionEvent::for_message("ALICE->BROKER_A", Direction::Inbound, "1", raw).is_none()
+        );
+        let event =
+            SessionEvent::for_message("ALICE->BROKER_A", Direction::Outbound, "1", raw).unwrap();
+        assert_eq!(event.test_req_id.as_deref(), Some("T1"));
+        assert!(
+            SessionEvent::for_message("ALICE->BROKER_A", Direction::Inbound, "0", raw).is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn resuming_returns_the_events_after_the_last_id() {
+        let events = SessionEvents::default();
+        for kind in [
+            SessionEventKind::Connected,
+            SessionEventKind::LogonComplete,
+            SessionEventKind::Disconnected,
+        ] {
+            events.publish(SessionEvent::new("ALICE->BROKER_A", kind)).await;
+        }
+        let (missed, _) = events.subscribe(Some(1)).await;
+        let ids: Vec<u64> = missed.iter().map(|e| e.id).collect();
+        assert_eq!(ids, [2, 3]);
+        assert!(events.subscribe(None).await.0.is_empty());
+
+        let filter = Filter::new(EventsQuery {
+            session_id: None,
+            kind: Some("DISCONNECTED, LOGON_COMPLETE".to_string()),
+        });
+        assert_eq!(missed.iter().filter(|e| filter.matches(e)).count(), 2);
+        assert!(!filter.matches(&SessionEvent::new("X", SessionEventKind::Connected)));
+    }
+}
@@ -0,0 +1,363 @@
+//! Session listing and per-session detail.
+//!
+//! `GET /api/v1/fix/sessions` filters sessions by state, FIX version and
+//! counterparty, sorts them by a chosen key and returns one page at a time,
+//! so deployments with hundreds of sessions are not sent all of them on
+//! every poll. `GET /api/v1/fix/sessions/{session_id}` returns one session
+//! with traffic, queue and order statistics.
+//! `GET /api/v1/fix/sessions/{session_id}/config` returns the settings it
+//! runs with, each with where it came from: the session's entry in the
+//! configuration file, the file's `[defaults]`, an environment variable or
+//! the engine's default.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::BTreeMap, sync::Arc};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    config::Source, orders::StatusFilter, records::Direction, AppState,
+    FixSession,
+};
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SessionsQuery {
+    /// Comma-separated session states, e.g. `ACTIVE,LOGON_SENT`.
+    state: Option<String>,
+    fix_version: Option<String>,
+    /// The counterparty's CompID, i.e. the session's TargetCompID.
+    counterparty: Option<String>,
+    /// A sort key, `-` prefixed for descending; registration order if omitted.
+    sort: Option<String>,
+    limit: Option<usize>,
+    #[serde(default)]
+    offset: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    SessionId,
+    State,
+    FixVersion,
+    Counterparty,
+    MsgSeqNum,
+    ConnectedAt,
+}
+
+impl SortKey {
+    fn parse(key: &str) -> Option<Self> {
+        Some(match key {
+            "session_id" => Self::SessionId,
+            "state" => Self::State,
+            "fix_version" => Self::FixVersion,
+            "counterparty" | "target_comp_id" => Self::Counterparty,
+            "msg_seq_num" => Self::MsgSeqNum,
+            "connected_at" => Self::ConnectedAt,
+            _ => return None,
+        })
+    }
+
+    fn compare(self, a: &FixSession, b: &FixSession) -> Ordering {
+        match self {
+            Self::SessionId => a.session_id.cmp(&b.session_id),
+            Self::State => a.state.cmp(&b.state),
+            Self::FixVersion => a.fix_version.cmp(&b.fix_version),
+            Self::Counterparty => a.target_comp_id.cmp(&b.target_comp_id),
+            Self::MsgSeqNum => a.msg_seq_num.cmp(&b.msg_seq_num),
+            Self::ConnectedAt => a.connected_at.cmp(&b.connected_at),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SessionsResponse {
+    sessions: Vec<FixSession>,
+    /// Sessions on this page.
+    count: usize,
+    /// Sessions matching the filters.
+    total: usize,
+    offset: usize,
+}
+
+/// One page of the sessions matching `q`, in its sort order.
+fn select(
+    sessions: &[Arc<FixSession>],
+    q: &SessionsQuery,
+) -> Result<SessionsResponse, StatusCode> {
+    let limit = q.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let sort = match q.sort.as_deref() {
+        Some(sort) => {
+            let (descending, key) = match sort.strip_prefix('-') {
+                Some(key) => (true, key),
+                None => (false, sort),
+            };
+            Some((SortKey::parse(key).ok_or(StatusCode::BAD_REQUEST)?, descending))
+        }
+        None => None,
+    };
+    let states: Vec<String> = q
+        .state
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_ascii_uppercase)
+        .collect();
+
+    let mut matching: Vec<&FixSession> = sessions
+        .iter()
+        .map(Arc::as_ref)
+        .filter(|s| states.is_empty() || states.contains(&s.state))
+        .filter(|s| q.fix_version.as_ref().is_none_or(|v| *v == s.fix_version))
+        .filter(|s| q.counterparty.as_ref().is_none_or(|c| *c == s.target_comp_id))
+        .collect();
+    if let Some((key, descending)) = sort {
+        // Stable, so ties keep registration order.
+        matching.sort_by(|a, b| {
+            let order = key.compare(a, b);
+            if descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+    }
+    let total = matching.len();
+    let sessions: Vec<FixSession> = matching
+        .into_iter()
+        .skip(q.offset)
+        .take(limit)
+        .cloned()
+        .collect();
+    Ok(SessionsResponse {
+        count: sessions.len(),
+        sessions,
+        total,
+        offset: q.offset,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/fix/sessions",
+    tag = "sessions",
+    params(SessionsQuery),
+    responses(
+        (status = 200, description = "One page of matching sessions", body = SessionsResponse),
+        (status = 400, description = "Unknown sort key or invalid limit"),
+    )
+)]
+pub async fn list(
+    State(state): State<AppState>,
+    Query(q): Query<SessionsQuery>,
+) -> Result<Json<SessionsResponse>, StatusCode> {
+    select(&state.sessions.snapshots(), &q).map(Json)
+}
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct SessionStats {
+    messages_sent: usize,
+    messages_received: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_sent_at_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_received_at_ms: Option<u64>,
+    /// Messages waiting in the outbound queue.
+    queued: usize,
+    /// Orders sent on the session and not yet in a terminal state.
+    open_orders: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SessionDetail {
+    #[serde(flatten)]
+    session: FixSession,
+    stats: SessionStats,
+}
+
+/// One session with its statistics.
+#[utoipa::path(
+    get,
+    path = "/api/v1/fix/sessions/{session_id}",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "The session ID, e.g. `ALICE->BROKER_A`.")),
+    responses(
+        (status = 200, description = "OK", body = SessionDetail),
+        (status = 404, description = "Unknown session"),
+    )
+)]
+pub async fn get(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> Result<Json<SessionDetail>, StatusCode> {
+    let session = crate::find_session(&state, &session_id).ok_or(StatusCode::NOT_FOUND)?;
+    let mut stats = SessionStats::default();
+    {
+        let messages = state.messages.lock().await;
+        for m in messages.for_session(&session_id) {
+            let (count, last) = match m.direction {
+                Direction::Outbound => (&mut stats.messages_sent, &mut stats.last_sent_at_ms),
+                Direction::Inbound => {
+                    (&mut stats.messages_received, &mut stats.last_received_at_ms)
+                }
+            };
+            *count += 1;
+            *last = Some(last.unwrap_or_default().max(m.recorded_at_ms));
+        }
+    }
+    stats.queued = state.outbound_queue.lock().await.items(&session_id).len();
+    stats.open_orders = state
+        .orders
+        .lock()
+        .await
+        .blotter(StatusFilter::Open, Some(&session_id))
+        .len();
+    Ok(Json(SessionDetail { session, stats }))
+}
+
+/// A setting a session runs with.
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
+pub struct EffectiveSetting {
+    value: serde_json::Value,
+    source: Source,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SessionSettings {
+    session_id: String,
+    /// By name, e.g. `heartbeat_secs`.
+    settings: BTreeMap<String, EffectiveSetting>,
+}
+
+fn effective_settings(session: &FixSession) -> SessionSettings {
+    let values = serde_json::to_value(session).unwrap_or_default();
+    let settings = session
+        .sources
+        .iter()
+        .map(|(&name, &source)| {
+            let value = values.get(name).cloned().unwrap_or_default();
+            (name.to_string(), EffectiveSetting { value, source })
+        })
+        .collect();
+    SessionSettings {
+        session_id: session.session_id.clone(),
+        settings,
+    }
+}
+
+/// The settings a session runs with, resolved from its own, the
+/// configuration file's defaults, the environment and the engine's defaults.
+#[utoipa::path(
+    get,
+    path = "/api/v1/fix/sessions/{session_id}/config",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "The session ID, e.g. `ALICE->BROKER_A`.")),
+    responses(
+        (status = 200, description = "OK", body = SessionSettings),
+        (status = 404, description = "Unknown session"),
+    )
+)]
+pub async fn config(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> Result<Json<SessionSettings>, StatusCode> {
+    let session = crate::find_session(&state, &session_id).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(effective_settings(&session)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, seed_sessions};
+    use serde_json::json;
+
+    fn ids(response: &SessionsResponse) -> Vec<&str> {
+        response.sessions.iter().map(|s| s.session_id.as_str()).collect()
+    }
+
+    #[test]
+    fn sessions_are_filtered_sorted_and_paged() {
+        let template = seed_sessions(&Config::default()).remove(0);
+        let mut sessions = vec![template; 3];
+        for (i, s) in sessions.iter_mut().enumerate() {
+            s.session_id = format!("ALICE->BROKER_{i}");
+            s.state = "ACTIVE".to_string();
+            s.msg_seq_num = 10 - i as u64;
+        }
+        sessions[1].state = "DISCONNECTED".to_string();
+        let sessions: Vec<Arc<FixSession>> = sessions.into_iter().map(Arc::new).collect();
+
+        let active = select(
+            &sessions,
+            &SessionsQuery {
+                state: Some("active".to_string()),
+                ..SessionsQuery::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(active.total, 2);
+        assert!(active.sessions.iter().all(|s| s.state == "ACTIVE"));
+
+        let page = select(
+            &sessions,
+            &SessionsQuery {
+                sort: Some("msg_seq_num".to_string()),
+                limit: Some(2),
+                offset: 1,
+                ..SessionsQuery::default()
+            },
+        )
+        .unwrap();
+        assert_eq!((page.total, page.count), (3, 2));
+        assert_eq!(ids(&page), ["ALICE->BROKER_1", "ALICE->BROKER_0"]);
+
+        let unknown = SessionsQuery {
+            sort: Some("-volume".to_string()),
+            ..SessionsQuery::default()
+        };
+        assert_eq!(select(&sessions, &unknown).err(), Some(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn settings_say_where_they_came_from() {
+        let config: Config = toml::from_str(
+            r#"
+[defaults]
+heartbeat_secs = 45
+timestamp_precision = "nanos"
+
+[[sessions]]
+sender_comp_id = "ALICE"
+target_comp_id = "BROKER_A"
+timestamp_precision = "micros"
+rule_packs = ["BROKER_A"]
+"#,
+        )
+        .unwrap();
+        let session = seed_sessions(&config).remove(0);
+        let settings = effective_settings(&session);
+        assert_eq!(settings.session_id, "ALICE->BROKER_A");
+        let setting = |name: &str| {
+            let s = &settings.settings[name];
+            (s.value.clone(), s.source)
+        };
+        assert_eq!(setting("heartbeat_secs"), (json!(45), Source::Defaults));
+        assert_eq!(setting("timestamp_precision"), (json!("micros"), Source::Session));
+        assert_eq!(setting("rule_packs"), (json!(["BROKER_A"]), Source::Session));
+        assert_eq!(setting("fix_version"), (json!("FIX.4.4"), Source::Session));
+        assert_eq!(setting("host"), (json!(null), Source::BuiltIn));
+        assert_eq!(setting("acceptor"), (json!(false), Source::BuiltIn));
+    }
+}
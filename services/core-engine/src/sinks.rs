@@ -0,0 +1,687 @@
+//! Downstream sinks for messages and order events.
+//!
+//! Every stored message and new order is published to each configured sink as
+//! it happens. Order book updates go only to the sinks in `FIX_BOOK_SINKS`,
+//! conflated (see [`crate::conflation`]). A re-drive re-publishes a time range
+//! of stored history to chosen sinks, marking each event with a `replay` block
+//! (and the `X-Fix-Replay` header) and pacing itself so live publishing is not
+//! crowded out. Live and re-driven events that fail with a send error, 429 or
+//! 5xx are retried with exponential backoff.
+
+use axum::{extract::State, http::StatusCode, response::Json};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{sync::Arc, time::Duration};
+use tokio::{sync::Mutex, time::MissedTickBehavior};
+use tracing::{info, warn, Instrument};
+use utoipa::ToSchema;
+
+use crate::{
+    audit::{self, Actor, AuditAction},
+    clock::epoch_ms,
+    records, telemetry, AppState,
+};
+
+const DEFAULT_REDRIVE_RATE: u32 = 50;
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+const FIRST_RETRY: Duration = Duration::from_secs(1);
+const MAX_RETRY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkKind {
+    /// Each event is POSTed as-is.
+    Webhook,
+    /// Kafka via a Confluent-compatible REST Proxy topic URL.
+    KafkaRest,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Sink {
+    pub name: String,
+    pub kind: SinkKind,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Retry {
+    max_attempts: u32,
+    first_retry: Duration,
+}
+
+impl Retry {
+    fn delay(&self, attempt: u32) -> Duration {
+        self.first_retry
+            .saturating_mul(1 << (attempt - 1).min(16))
+            .min(MAX_RETRY)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Sinks {
+    sinks: Vec<Sink>,
+    /// Events per second per re-drive unless the request sets its own rate.
+    redrive_rate: u32,
+    retry: Retry,
+}
+
+impl Default for Sinks {
+    fn default() -> Self {
+        Self {
+            sinks: Vec::new(),
+            redrive_rate: DEFAULT_REDRIVE_RATE,
+            retry: Retry {
+                max_attempts: DEFAULT_MAX_ATTEMPTS,
+                first_retry: FIRST_RETRY,
+            },
+        }
+    }
+}
+
+impl Sinks {
+    /// Reads `FIX_SINKS` (comma-separated `name=kind:url` entries, where kind
+    /// is `webhook` or `kafka_rest`), `FIX_REDRIVE_RATE_PER_SEC` and
+    /// `FIX_SINK_MAX_ATTEMPTS`.
+    pub fn from_env() -> Self {
+        let var = |name| std::env::var(name).ok().and_then(|v| v.parse().ok());
+        let mut sinks = Self::default();
+        if let Some(rate) = var("FIX_REDRIVE_RATE_PER_SEC").filter(|&r| r > 0) {
+            sinks.redrive_rate = rate;
+        }
+        if let Some(attempts) = var("FIX_SINK_MAX_ATTEMPTS") {
+            sinks.retry.max_attempts = attempts.max(1);
+        }
+        let Ok(spec) = std::env::var("FIX_SINKS") else {
+            return sinks;
+        };
+
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let parsed = entry.split_once('=').and_then(|(name, target)| {
+                let (kind, url) = target.split_once(':')?;
+                let kind = match kind {
+                    "webhook" => SinkKind::Webhook,
+                    "kafka_rest" => SinkKind::KafkaRest,
+                    _ => return None,
+                };
+                Some((name, kind, url))
+            });
+            match parsed {
+                Some((name, kind, url)) if !name.is_empty() && url.starts_with("http") => {
+                    sinks.sinks.push(Sink {
+                        name: name.to_string(),
+                        kind,
+                        url: url.to_string(),
+                    })
+                }
+                _ => warn!(entry, "Ignoring malformed FIX_SINKS entry"),
+            }
+        }
+        sinks
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Sink> {
+        self.sinks.iter().find(|s| s.name == name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.sinks.len()
+    }
+}
+
+// ── Events ────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventType {
+    Message,
+    Order,
+    /// An order book update, sent only to the sinks in `FIX_BOOK_SINKS`.
+    Book,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayMarker {
+    pub redrive_id: String,
+    /// 1-based position of this event within the re-drive.
+    pub position: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SinkEvent {
+    #[serde(rename = "type")]
+    pub event_type: EventType,
+    /// Partition key: session ID for messages, ClOrdID for orders, symbol
+    /// for books.
+    pub key: String,
+    pub at_ms: u64,
+    /// The record in its versioned storage format.
+    pub record: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replay: Option<ReplayMarker>,
+}
+
+impl SinkEvent {
+    pub fn message(session_id: &str, at_ms: u64, record: Value) -> Self {
+        Self {
+            event_type: EventType::Message,
+            key: session_id.to_string(),
+            at_ms,
+            record,
+            replay: None,
+        }
+    }
+
+    pub fn order(cl_ord_id: &str, at_ms: u64, record: Value) -> Self {
+        Self {
+            event_type: EventType::Order,
+            key: cl_ord_id.to_string(),
+            at_ms,
+            record,
+            replay: None,
+        }
+    }
+
+    pub fn book(symbol: &str, at_ms: u64, record: Value) -> Self {
+        Self {
+            event_type: EventType::Book,
+            key: symbol.to_string(),
+            at_ms,
+            record,
+            replay: None,
+        }
+    }
+}
+
+pub async fn deliver(
+    client: &reqwest::Client,
+    sink: &Sink,
+    event: &SinkEvent,
+) -> Result<(), String> {
+    let req = match sink.kind {
+        SinkKind::Webhook => client.post(&sink.url).json(event),
+        SinkKind::KafkaRest => client
+            .post(&sink.url)
+            .header("Content-Type", "application/vnd.kafka.json.v2+json")
+            .body(
+                serde_json::json!({ "records": [{ "key": event.key, "value": event }] })
+                    .to_string(),
+            ),
+    };
+    let req = match &event.replay {
+        Some(marker) => req.header("X-Fix-Replay", &marker.redrive_id),
+        None => req,
+    };
+
+    let resp = req.send().await.map_err(|e| e.to_string())?;
+    if resp.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("sink returned {}", resp.status()))
+    }
+}
+
+/// [`deliver`], retrying send errors, 429 and 5xx answers with exponential
+/// backoff until `retry.max_attempts` attempts have been made.
+async fn deliver_with_retry(
+    client: &reqwest::Client,
+    retry: Retry,
+    sink: &Sink,
+    event: &SinkEvent,
+) -> Result<(), String> {
+    let mut attempt = 1;
+    loop {
+        let error = match deliver(client, sink, event).await {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        if !retryable(&error) || attempt >= retry.max_attempts {
+            return Err(error);
+        }
+        let delay = retry.delay(attempt);
+        info!(
+            sink = %sink.name,
+            key = %event.key,
+            attempt,
+            error = %error,
+            retry_in_ms = delay.as_millis() as u64,
+            "Sink delivery failed, retrying"
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Whether a [`deliver`] error is worth retrying: anything but a 4xx other
+/// than 429.
+fn retryable(error: &str) -> bool {
+    match error.strip_prefix("sink returned ") {
+        Some(status) => status.starts_with('5') || status.starts_with("429"),
+        None => true,
+    }
+}
+
+/// Publishes a live event to every sink in the background.
+pub fn publish(state: &AppState, event: SinkEvent) {
+    if state.sinks.sinks.is_empty() {
+        return;
+    }
+    let client = state.http.clone();
+    let sinks = state.sinks.clone();
+    tokio::spawn(
+        async move {
+            for sink in &sinks.sinks {
+                if let Err(e) = deliver_with_retry(&client, sinks.retry, sink, &event).await {
+                    warn!(sink = %sink.name, error = %e, "Sink publish failed");
+                }
+            }
+        }
+        .instrument(telemetry::background_span()),
+    );
+}
+
+// ── Re-drive ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RedriveState {
+    Running,
+    Completed,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RedriveJob {
+    pub redrive_id: String,
+    pub from_ms: u64,
+    pub to_ms: u64,
+    pub sinks: Vec<String>,
+    pub rate_per_sec: u32,
+    pub total: usize,
+    pub published: usize,
+    pub failed: usize,
+    pub state: RedriveState,
+    pub started_at_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at_ms: Option<u64>,
+}
+
+type Jobs = Arc<Mutex<Vec<RedriveJob>>>;
+
+async fn update_job(jobs: &Jobs, redrive_id: &str, f: impl FnOnce(&mut RedriveJob)) {
+    let mut jobs = jobs.lock().await;
+    if let Some(job) = jobs.iter_mut().find(|j| j.redrive_id == redrive_id) {
+        f(job);
+    }
+}
+
+/// Delivers `events` to `sinks` at most `rate` per second. Time spent
+/// retrying an event delays the next one rather than being made up with a
+/// burst afterwards.
+async fn run_redrive(
+    client: reqwest::Client,
+    jobs: Jobs,
+    retry: Retry,
+    redrive_id: String,
+    sinks: Vec<Sink>,
+    events: Vec<SinkEvent>,
+    rate: u32,
+) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(1) / rate);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    for event in events {
+        ticker.tick().await;
+        let mut ok = true;
+        for sink in &sinks {
+            if let Err(e) = deliver_with_retry(&client, retry, sink, &event).await {
+                warn!(
+                    redrive_id = %redrive_id,
+                    sink = %sink.name,
+                    error = %e,
+                    "Re-drive delivery failed"
+                );
+                ok = false;
+            }
+        }
+        update_job(&jobs, &redrive_id, |job| {
+            if ok {
+                job.published += 1;
+            } else {
+                job.failed += 1;
+            }
+        })
+        .await;
+    }
+
+    update_job(&jobs, &redrive_id, |job| {
+        job.state = RedriveState::Completed;
+        job.finished_at_ms = Some(epoch_ms());
+        info!(
+            redrive_id = %job.redrive_id,
+            published = job.published,
+            failed = job.failed,
+            "Re-drive completed"
+        );
+    })
+    .await;
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RedriveRequest {
+    from_ms: u64,
+    to_ms: u64,
+    /// Sink names; all configured sinks when omitted.
+    #[serde(default)]
+    sinks: Option<Vec<String>>,
+    /// Event types to include; both when omitted.
+    #[serde(default)]
+    types: Option<Vec<EventType>>,
+    #[serde(default)]
+    rate_per_sec: Option<u32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RedrivesResponse {
+    items: Vec<RedriveJob>,
+    count: usize,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/redrives",
+    tag = "admin",
+    request_body = RedriveRequest,
+    responses(
+        (status = 202, description = "Redrive started", body = RedriveJob),
+        (status = 400, description = "Unknown sink or invalid range"),
+    )
+)]
+pub async fn start_redrive(
+    State(state): State<AppState>,
+    actor: Actor,
+    Json(req): Json<RedriveRequest>,
+) -> Result<(StatusCode, Json<RedriveJob>), StatusCode> {
+    if req.from_ms >= req.to_ms || req.rate_per_sec == Some(0) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let sinks: Vec<Sink> = match &req.sinks {
+        Some(names) => names
+            .iter()
+            .map(|n| state.sinks.sinks.iter().find(|s| &s.name == n).cloned())
+            .collect::<Option<_>>()
+            .ok_or(StatusCode::BAD_REQUEST)?,
+        None => state.sinks.sinks.clone(),
+    };
+    if sinks.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let wants = |t: EventType| req.types.as_ref().is_none_or(|types| types.contains(&t));
+
+    let mut events = Vec::new();
+    if wants(EventType::Message) {
+        let messages = state.messages.lock().await.between(req.from_ms, req.to_ms);
+        events.extend(
+            messages
+                .iter()
+                .map(|m| SinkEvent::message(&m.session_id, m.recorded_at_ms, records::encode(m))),
+        );
+    }
+    if wants(EventType::Order) {
+        let orders = state
+            .orders
+            .lock()
+            .await
+            .sent_between(req.from_ms, req.to_ms);
+        events.extend(
+            orders
+                .iter()
+                .map(|o| SinkEvent::order(&o.cl_ord_id, o.sent_at_ms, records::encode(o))),
+        );
+    }
+    events.sort_by_key(|e| e.at_ms);
+
+    let now = epoch_ms();
+    let total = events.len();
+    let job = {
+        let mut jobs = state.redrives.lock().await;
+        let job = RedriveJob {
+            redrive_id: format!("RD-{}", jobs.len() + 1),
+            from_ms: req.from_ms,
+            to_ms: req.to_ms,
+            sinks: sinks.iter().map(|s| s.name.clone()).collect(),
+            rate_per_sec: req.rate_per_sec.unwrap_or(state.sinks.redrive_rate),
+            total,
+            published: 0,
+            failed: 0,
+            state: RedriveState::Running,
+            started_at_ms: now,
+            finished_at_ms: None,
+        };
+        jobs.push(job.clone());
+        job
+    };
+
+    for (i, event) in events.iter_mut().enumerate() {
+        event.replay = Some(ReplayMarker {
+            redrive_id: job.redrive_id.clone(),
+            position: i + 1,
+            total,
+        });
+    }
+
+    info!(
+        redrive_id = %job.redrive_id,
+        from_ms = job.from_ms,
+        to_ms = job.to_ms,
+        total,
+        rate_per_sec = job.rate_per_sec,
+        "Re-drive started"
+    );
+    audit::record(
+        &state,
+        &actor,
+        AuditAction::RedriveStarted,
+        None,
+        format!(
+            "{} [{}, {}) to {} events={total}",
+            job.redrive_id,
+            job.from_ms,
+            job.to_ms,
+            job.sinks.join(",")
+        ),
+    )
+    .await;
+    tokio::spawn(run_redrive(
+        state.http.clone(),
+        state.redrives.clone(),
+        state.sinks.retry,
+        job.redrive_id.clone(),
+        sinks,
+        events,
+        job.rate_per_sec,
+    ));
+
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/redrives",
+    tag = "admin",
+    responses(
+        (status = 200, description = "OK", body = RedrivesResponse),
+    )
+)]
+pub async fn list_redrives(State(state): State<AppState>) -> Json<RedrivesResponse> {
+    let items = state.redrives.lock().await.clone();
+    let count = items.len();
+    Json(RedrivesResponse { items, count })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::HeaderMap, routing::post, Router};
+    use std::{sync::Mutex as StdMutex, time::Instant};
+
+    /// A request a test sink received: when, its `X-Fix-Replay` header and
+    /// the status it was answered with.
+    type Received = Arc<StdMutex<Vec<(Instant, Option<String>, u16)>>>;
+
+    /// Starts a sink that answers with `statuses` in turn, then 200.
+    async fn sink(name: &str, statuses: Vec<u16>) -> (Sink, Received) {
+        let received = Received::default();
+        let app = Router::new().route(
+            "/",
+            post({
+                let received = received.clone();
+                move |headers: HeaderMap| async move {
+                    let mut received = received.lock().unwrap();
+                    let status = statuses.get(received.len()).copied().unwrap_or(200);
+                    let replay = headers
+                        .get("X-Fix-Replay")
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_string);
+                    received.push((Instant::now(), replay, status));
+                    StatusCode::from_u16(status).unwrap()
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let sink = Sink {
+            name: name.to_string(),
+            kind: SinkKind::Webhook,
+            url,
+        };
+        (sink, received)
+    }
+
+    fn retry(max_attempts: u32) -> Retry {
+        Retry {
+            max_attempts,
+            first_retry: Duration::from_millis(20),
+        }
+    }
+
+    fn event(key: &str) -> SinkEvent {
+        SinkEvent::order(key, 0, serde_json::json!({ "cl_ord_id": key }))
+    }
+
+    #[test]
+    fn retries_back_off_exponentially_up_to_a_cap() {
+        let retry = Sinks::default().retry;
+        assert_eq!(retry.delay(1), Duration::from_secs(1));
+        assert_eq!(retry.delay(4), Duration::from_secs(8));
+        assert_eq!(retry.delay(40), MAX_RETRY);
+        assert!(retryable("error sending request"));
+        assert!(retryable("sink returned 503 Service Unavailable"));
+        assert!(retryable("sink returned 429 Too Many Requests"));
+        assert!(!retryable("sink returned 400 Bad Request"));
+    }
+
+    #[tokio::test]
+    async fn a_sink_that_recovers_gets_the_event_after_backing_off() {
+        let (sink, received) = sink("risk", vec![503, 503]).await;
+        let client = reqwest::Client::new();
+        deliver_with_retry(&client, retry(5), &sink, &event("O1"))
+            .await
+            .unwrap();
+
+        let received = received.lock().unwrap();
+        let statuses: Vec<u16> = received.iter().map(|r| r.2).collect();
+        assert_eq!(statuses, [503, 503, 200]);
+        let gaps: Vec<Duration> = received.windows(2).map(|w| w[1].0 - w[0].0).collect();
+        assert!(gaps[0] >= Duration::from_millis(20), "{gaps:?}");
+        assert!(gaps[1] >= Duration::from_millis(40), "{gaps:?}");
+    }
+
+    #[tokio::test]
+    async fn a_sink_that_stays_down_or_refuses_is_given_up_on() {
+        let client = reqwest::Client::new();
+
+        let (down, received) = sink("down", vec![503; 10]).await;
+        let error = deliver_with_retry(&client, retry(3), &down, &event("O1"))
+            .await
+            .unwrap_err();
+        assert!(error.contains("503"), "{error}");
+        assert_eq!(received.lock().unwrap().len(), 3);
+
+        let (refusing, received) = sink("refusing", vec![400]).await;
+        let error = deliver_with_retry(&client, retry(3), &refusing, &event("O1"))
+            .await
+            .unwrap_err();
+        assert!(error.contains("400"), "{error}");
+        assert_eq!(received.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn a_redrive_rides_out_an_outage_without_bursting_past_its_rate() {
+        // Four failures back off for 20 + 40 + 80 + 160 ms, six 50 ms ticks
+        // at 20 events per second. The next event goes out at once, but the
+        // ones after it are paced again rather than sent in a burst.
+        let (flaky, flaky_received) = sink("flaky", vec![503; 4]).await;
+        let (refusing, _) = sink("refusing", vec![400]).await;
+        let jobs = Jobs::default();
+        jobs.lock().await.push(RedriveJob {
+            redrive_id: "RD-1".to_string(),
+            from_ms: 0,
+            to_ms: 1,
+            sinks: vec!["flaky".to_string(), "refusing".to_string()],
+            rate_per_sec: 20,
+            total: 4,
+            published: 0,
+            failed: 0,
+            state: RedriveState::Running,
+            started_at_ms: 0,
+            finished_at_ms: None,
+        });
+        let events = (1..=4)
+            .map(|i| SinkEvent {
+                replay: Some(ReplayMarker {
+                    redrive_id: "RD-1".to_string(),
+                    position: i,
+                    total: 4,
+                }),
+                ..event(&format!("O{i}"))
+            })
+            .collect();
+
+        run_redrive(
+            reqwest::Client::new(),
+            jobs.clone(),
+            retry(5),
+            "RD-1".to_string(),
+            vec![flaky, refusing],
+            events,
+            20,
+        )
+        .await;
+
+        let delivered: Vec<Instant> = {
+            let received = flaky_received.lock().unwrap();
+            assert!(received.iter().all(|r| r.1.as_deref() == Some("RD-1")));
+            received
+                .iter()
+                .filter(|r| r.2 == 200)
+                .map(|r| r.0)
+                .collect()
+        };
+        assert_eq!(delivered.len(), 4);
+        for pair in delivered[1..].windows(2) {
+            assert!(
+                pair[1] - pair[0] >= Duration::from_millis(45),
+                "events after the outage were sent {:?} apart",
+                pair[1] - pair[0]
+            );
+        }
+
+        let jobs = jobs.lock().await;
+        let job = &jobs[0];
+        assert_eq!(job.state, RedriveState::Completed);
+        assert_eq!((job.published, job.failed), (3, 1));
+        assert!(job.finished_at_ms.is_some());
+    }
+}
@@ -0,0 +1,218 @@
+//! Sequence numbers and session state shared through Redis.
+//!
+//! With `FIX_STATE_REDIS_URL` set, each session's outbound MsgSeqNums are
+//! allocated by a Lua script that increments the counter, raised to this
+//! instance's floor first, in one atomic step, and its next MsgSeqNum and
+//! state are written to a hash after each send. Two gateway instances pointed
+//! at the same Redis (an active/standby pair) therefore agree on numbering,
+//! and the standby can take over a session after failover without a sequence
+//! reset. A number whose message this instance did not send is given back by
+//! a second script, unless another send has allocated past it.
+//!
+//! The connection is synchronous, with 2s timeouts, so request handlers reach
+//! the store through [`SessionStateStore::blocking`], which runs the call on
+//! tokio's blocking pool instead of a runtime worker.
+//!
+//! Keys, under `FIX_STATE_REDIS_PREFIX` (default `fix`):
+//! - `<prefix>:seq_num:<session_id>`: the last MsgSeqNum allocated on the
+//!   session.
+//! - `<prefix>:session:<session_id>`: hash of `msg_seq_num`, `state` and
+//!   `updated_at_ms`.
+
+use redis::{Commands, Connection, RedisResult, Script};
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+const DEFAULT_PREFIX: &str = "fix";
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Raises the counter in `KEYS[1]` to the floor in `ARGV[1]` if it is
+/// below, then increments it. Redis runs a script without interleaving other
+/// commands, so no two callers get the same number.
+const NEXT_SEQ_NUM: &str = r"
+local floor = tonumber(ARGV[1])
+local current = tonumber(redis.call('GET', KEYS[1]) or '0')
+if current < floor then
+    redis.call('SET', KEYS[1], floor)
+end
+return redis.call('INCR', KEYS[1])
+";
+
+/// Gives back the number in `ARGV[1]` if it is still the last one allocated
+/// in `KEYS[1]`: 1 if it was given back, 0 if a later one was allocated.
+const RELEASE_SEQ_NUM: &str = r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    redis.call('DECR', KEYS[1])
+    return 1
+end
+return 0
+";
+
+/// A session's numbering and state as last saved by any instance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionSnapshot {
+    pub msg_seq_num: u64,
+    pub state: Option<String>,
+}
+
+impl SessionSnapshot {
+    fn from_hash(hash: &HashMap<String, String>) -> Option<Self> {
+        Some(Self {
+            msg_seq_num: hash.get("msg_seq_num")?.parse().ok()?,
+            state: hash.get("state").cloned(),
+        })
+    }
+}
+
+pub struct SessionStateStore {
+    client: redis::Client,
+    prefix: String,
+    next_seq_num: Script,
+    release_seq_num: Script,
+    /// Reused between calls; dropped after an error so the next call
+    /// reconnects.
+    conn: Mutex<Option<Connection>>,
+}
+
+impl SessionStateStore {
+    /// `None` unless `FIX_STATE_REDIS_URL` is set.
+    pub fn from_env() -> Option<RedisResult<Self>> {
+        let url = std::env::var("FIX_STATE_REDIS_URL").ok()?;
+        let url = url.trim();
+        if url.is_empty() {
+            return None;
+        }
+        let prefix = std::env::var("FIX_STATE_REDIS_PREFIX")
+            .ok()
+            .filter(|p| !p.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_PREFIX.to_string());
+        Some(redis::Client::open(url).map(|client| Self {
+            client,
+            prefix,
+            next_seq_num: Script::new(NEXT_SEQ_NUM),
+            release_seq_num: Script::new(RELEASE_SEQ_NUM),
+            conn: Mutex::new(None),
+        }))
+    }
+
+    /// Redacted connection details, for startup reporting.
+    pub fn describe(&self) -> String {
+        let info = self.client.get_connection_info();
+        format!(
+            "session state store (redis: {} prefix={})",
+            info.addr, self.prefix
+        )
+    }
+
+    /// Runs `f` against the store on the blocking pool, so a slow or
+    /// unreachable Redis stalls only the calling request.
+    pub async fn blocking<T, F>(self: &Arc<Self>, f: F) -> RedisResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Self) -> RedisResult<T> + Send + 'static,
+    {
+        let store = Arc::clone(self);
+        tokio::task::spawn_blocking(move || f(&store))
+            .await
+            .unwrap_or_else(|e| Err(io::Error::other(e).into()))
+    }
+
+    fn seq_key(&self, session_id: &str) -> String {
+        format!("{}:seq_num:{session_id}", self.prefix)
+    }
+
+    fn session_key(&self, session_id: &str) -> String {
+        format!("{}:session:{session_id}", self.prefix)
+    }
+
+    fn with_conn<T>(&self, f: impl FnOnce(&mut Connection) -> RedisResult<T>) -> RedisResult<T> {
+        let mut slot = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        let conn = match slot.as_mut() {
+            Some(conn) => conn,
+            None => {
+                let conn = self.client.get_connection_with_timeout(TIMEOUT)?;
+                conn.set_read_timeout(Some(TIMEOUT))?;
+                conn.set_write_timeout(Some(TIMEOUT))?;
+                slot.insert(conn)
+            }
+        };
+        let result = f(conn);
+        if result.is_err() {
+            *slot = None;
+        }
+        result
+    }
+
+    /// The last MsgSeqNum any instance allocated on the session, 0 if none.
+    pub fn last_seq_num(&self, session_id: &str) -> RedisResult<u64> {
+        let key = self.seq_key(session_id);
+        self.with_conn(|conn| conn.get::<_, Option<u64>>(&key))
+            .map(Option::unwrap_or_default)
+    }
+
+    /// Allocates the session's next MsgSeqNum, never below `floor + 1` so
+    /// numbering also continues past messages this instance recorded locally.
+    pub fn next_seq_num(&self, session_id: &str, floor: u64) -> RedisResult<u64> {
+        let key = self.seq_key(session_id);
+        self.with_conn(|conn| self.next_seq_num.key(&key).arg(floor).invoke(conn))
+    }
+
+    /// Gives back `seq_num`, allocated by [`Self::next_seq_num`] but never
+    /// sent, unless any instance has allocated past it; whether it did.
+    pub fn release_seq_num(&self, session_id: &str, seq_num: u64) -> RedisResult<bool> {
+        let key = self.seq_key(session_id);
+        self.with_conn(|conn| self.release_seq_num.key(&key).arg(seq_num).invoke(conn))
+    }
+
+    pub fn load(&self, session_id: &str) -> RedisResult<Option<SessionSnapshot>> {
+        let key = self.session_key(session_id);
+        let hash: HashMap<String, String> = self.with_conn(|conn| conn.hgetall(&key))?;
+        Ok(SessionSnapshot::from_hash(&hash))
+    }
+
+    pub fn save(
+        &self,
+        session_id: &str,
+        snapshot: &SessionSnapshot,
+        now_ms: u64,
+    ) -> RedisResult<()> {
+        let key = self.session_key(session_id);
+        let mut items = vec![
+            ("msg_seq_num", snapshot.msg_seq_num.to_string()),
+            ("updated_at_ms", now_ms.to_string()),
+        ];
+        if let Some(state) = &snapshot.state {
+            items.push(("state", state.clone()));
+        }
+        self.with_conn(|conn| conn.hset_multiple(&key, &items))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_saved_snapshots() {
+        let hash: HashMap<String, String> = [
+            ("msg_seq_num", "42"),
+            ("state", "ACTIVE"),
+            ("updated_at_ms", "1740268800000"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        assert_eq!(
+            SessionSnapshot::from_hash(&hash),
+            Some(SessionSnapshot {
+                msg_seq_num: 42,
+                state: Some("ACTIVE".to_string()),
+            })
+        );
+        assert_eq!(SessionSnapshot::from_hash(&HashMap::new()), None);
+    }
+}
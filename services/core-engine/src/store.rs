@@ -0,0 +1,989 @@
+//! Messages sent and received by the engine, in the order they were recorded.
+//!
+//! With `FIX_STORE_DIR` set, every message is also appended to a per-session
+//! log on disk (one versioned JSON record per line, synced by the group
+//! commit before the message is acknowledged), and the logs are read back at
+//! startup so history and sequence numbers survive restarts, like QuickFIX's
+//! FileStore.
+//!
+//! A [`Retention`] policy bounds the store by age, size or trading days.
+//! Compaction, run in the background and on demand, moves expired messages
+//! out of the live logs into `archive/<session>.<YYYYMMDD>.log` files and
+//! rewrites the logs with what is left.
+//!
+//! `GET /api/v1/fix/messages` searches the store with cursor pagination.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    alerts,
+    attribution::Attribution,
+    audit::{self, Actor, AuditAction},
+    clock::{epoch_ms, format_utc_timestamp},
+    commit::{Durable, GroupCommit},
+    message::{detect_delimiter, lenient_tag, scan},
+    orders::ReportContext,
+    records::{self, Direction, StoredMessage},
+    session_events,
+    sinks::{self, SinkEvent},
+    traffic::Traffic,
+    AppState,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tracing::{info, warn};
+
+#[derive(Default)]
+pub struct MessageStore {
+    messages: Vec<StoredMessage>,
+    files: Option<FileStore>,
+    retention: Retention,
+    commit: GroupCommit,
+}
+
+impl MessageStore {
+    /// A file-backed store in `FIX_STORE_DIR`, or an in-memory one if unset,
+    /// with the retention policy from the environment.
+    pub fn from_env() -> io::Result<Self> {
+        let mut store = match std::env::var("FIX_STORE_DIR") {
+            Ok(dir) if !dir.trim().is_empty() => Self::open(dir.trim())?,
+            _ => Self::default(),
+        };
+        store.retention = Retention::from_env();
+        Ok(store)
+    }
+
+    /// Opens the session logs in `dir`, creating it if needed, and loads
+    /// every message they hold.
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let files = FileStore::open(dir.into())?;
+        let mut messages = files.load()?;
+        messages.sort_by_key(|m| m.recorded_at_ms);
+        Ok(Self {
+            messages,
+            files: Some(files),
+            retention: Retention::default(),
+            commit: GroupCommit::default(),
+        })
+    }
+
+    /// Syncs appends through the group commit task rather than one by one.
+    pub fn set_group_commit(&mut self, commit: GroupCommit) {
+        self.commit = commit;
+    }
+
+    /// Where messages are persisted, for startup reporting.
+    pub fn describe(&self) -> String {
+        let store = match &self.files {
+            Some(files) => format!("message store (file: {})", files.dir.display()),
+            None => "message store (in-memory)".to_string(),
+        };
+        format!("{store} retention={}", self.retention.describe())
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Checks the store directory still takes synced writes; always `Ok` in
+    /// memory.
+    pub fn check_writable(&self) -> io::Result<()> {
+        self.files.as_ref().map_or(Ok(()), FileStore::probe)
+    }
+
+    /// Records a message; with a file store it is on disk once the returned
+    /// [`Durable`] resolves, which the caller awaits without the store lock.
+    pub fn append(&mut self, message: StoredMessage) -> Durable {
+        let durable = match &mut self.files {
+            Some(files) => match files.append(&message) {
+                Ok(file) => self.commit.sync(&file),
+                Err(e) => Durable::Synced(Err(e)),
+            },
+            None => Durable::done(),
+        };
+        self.messages.push(message);
+        durable
+    }
+
+    /// The highest sequence number stored for each session in `direction`.
+    pub fn last_seq_nums(&self, direction: Direction) -> HashMap<String, u64> {
+        let mut last: HashMap<String, u64> = HashMap::new();
+        for m in self.messages.iter().filter(|m| m.direction == direction) {
+            if let Some(seq) = m.sequence_number {
+                let entry = last.entry(m.session_id.clone()).or_default();
+                *entry = (*entry).max(seq);
+            }
+        }
+        last
+    }
+
+    /// A session's messages in the order they were recorded.
+    pub fn for_session<'a>(
+        &'a self,
+        session_id: &'a str,
+    ) -> impl Iterator<Item = &'a StoredMessage> + 'a {
+        self.messages
+            .iter()
+            .filter(move |m| m.session_id == session_id)
+    }
+
+    /// Applies the retention policy at `now_ms`: expired messages are
+    /// archived (with a file store) and removed, and the session logs they
+    /// came from are rewritten. On error the store is left unchanged.
+    pub fn compact(&mut self, now_ms: u64) -> io::Result<Compaction> {
+        let mut by_session: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, m) in self.messages.iter().enumerate() {
+            by_session.entry(&m.session_id).or_default().push(i);
+        }
+        let mut keep = vec![true; self.messages.len()];
+        for indices in by_session.values() {
+            let session: Vec<&StoredMessage> = indices.iter().map(|&i| &self.messages[i]).collect();
+            for (&i, k) in indices.iter().zip(self.retention.keep(&session, now_ms)) {
+                keep[i] = k;
+            }
+        }
+
+        let (kept, expired): (Vec<_>, Vec<_>) = self
+            .messages
+            .iter()
+            .cloned()
+            .zip(&keep)
+            .partition(|(_, &k)| k);
+        let kept: Vec<StoredMessage> = kept.into_iter().map(|(m, _)| m).collect();
+        let expired: Vec<StoredMessage> = expired.into_iter().map(|(m, _)| m).collect();
+        let sessions: HashSet<&str> = expired.iter().map(|m| m.session_id.as_str()).collect();
+
+        if let Some(files) = &mut self.files {
+            files.archive(&expired)?;
+            for session_id in &sessions {
+                let remaining = kept.iter().filter(|m| m.session_id == *session_id);
+                files.rewrite(session_id, remaining)?;
+            }
+        }
+        let report = Compaction {
+            archived: expired.len(),
+            retained: kept.len(),
+            sessions: sessions.len(),
+        };
+        self.messages = kept;
+        Ok(report)
+    }
+
+    /// Messages recorded in `[from_ms, to_ms)`.
+    pub fn between(&self, from_ms: u64, to_ms: u64) -> Vec<StoredMessage> {
+        self.messages
+            .iter()
+            .filter(|m| (from_ms..to_ms).contains(&m.recorded_at_ms))
+            .cloned()
+            .collect()
+    }
+}
+
+// ── File store ────────────────────────────────────────────────────────────────
+
+/// Append-only per-session logs, `<session>.log` in `dir`.
+struct FileStore {
+    dir: PathBuf,
+    open: HashMap<String, Arc<File>>,
+}
+
+impl FileStore {
+    fn open(dir: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            open: HashMap::new(),
+        })
+    }
+
+    fn file_name(session_id: &str) -> String {
+        format!("{}.log", file_stem(session_id))
+    }
+
+    fn load(&self) -> io::Result<Vec<StoredMessage>> {
+        let mut messages = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "log") {
+                messages.extend(load_log(&path)?);
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Appends a message to its session's log, unsynced; the log written.
+    fn append(&mut self, message: &StoredMessage) -> io::Result<Arc<File>> {
+        let file = match self.open.get(&message.session_id) {
+            Some(file) => file.clone(),
+            None => {
+                let path = self.dir.join(Self::file_name(&message.session_id));
+                let file = OpenOptions::new().create(true).append(true).open(path)?;
+                let file = Arc::new(file);
+                self.open.insert(message.session_id.clone(), file.clone());
+                file
+            }
+        };
+        let mut line = records::encode(message).to_string();
+        line.push('\n');
+        (&*file).write_all(line.as_bytes())?;
+        Ok(file)
+    }
+
+    /// Writes, syncs and removes a probe file.
+    fn probe(&self) -> io::Result<()> {
+        let path = self.dir.join(".probe");
+        let mut file = File::create(&path)?;
+        file.write_all(b"ok")?;
+        file.sync_data()?;
+        fs::remove_file(path)
+    }
+
+    /// Appends messages to the per-session, per-day archive files.
+    fn archive(&self, messages: &[StoredMessage]) -> io::Result<()> {
+        let dir = self.dir.join("archive");
+        fs::create_dir_all(&dir)?;
+        let mut files: HashMap<PathBuf, File> = HashMap::new();
+        for m in messages {
+            let day = &format_utc_timestamp(m.recorded_at_ms)[..8];
+            let path = dir.join(format!("{}.{day}.log", file_stem(&m.session_id)));
+            let file = match files.entry(path) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => {
+                    let file = OpenOptions::new().create(true).append(true).open(e.key())?;
+                    e.insert(file)
+                }
+            };
+            let mut line = records::encode(m).to_string();
+            line.push('\n');
+            file.write_all(line.as_bytes())?;
+        }
+        for file in files.values() {
+            file.sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// Replaces a session's log with `messages`, via a temporary file renamed
+    /// over it so a crash leaves either the old log or the new one.
+    fn rewrite<'a>(
+        &mut self,
+        session_id: &str,
+        messages: impl Iterator<Item = &'a StoredMessage>,
+    ) -> io::Result<()> {
+        let path = self.dir.join(Self::file_name(session_id));
+        let tmp = path.with_extension("log.tmp");
+        let mut file = File::create(&tmp)?;
+        for m in messages {
+            let mut line = records::encode(m).to_string();
+            line.push('\n');
+            file.write_all(line.as_bytes())?;
+        }
+        file.sync_data()?;
+        // The open handle would keep appending to the replaced file.
+        self.open.remove(session_id);
+        fs::rename(tmp, path)
+    }
+}
+
+/// File name stem for a session's files; characters unsafe in file names,
+/// such as the `>` in session IDs, become `_`.
+pub fn file_stem(session_id: &str) -> String {
+    session_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Reads one session log. A line that does not decode, such as one cut short
+/// by a crash mid-write, is skipped.
+fn load_log(path: &Path) -> io::Result<Vec<StoredMessage>> {
+    let mut messages = Vec::new();
+    for (n, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line)
+            .map_err(|e| records::RecordError::Malformed(e.to_string()))
+            .and_then(records::decode::<StoredMessage>)
+        {
+            Ok(message) => messages.push(message),
+            Err(e) => warn!(
+                path = %path.display(),
+                line = n + 1,
+                error = %e,
+                "Skipping unreadable message record"
+            ),
+        }
+    }
+    info!(path = %path.display(), messages = messages.len(), "Session log loaded");
+    Ok(messages)
+}
+
+/// Stores a message and publishes it to the configured sinks. Fails with
+/// 503 if it could not be stored.
+pub async fn record(
+    state: &AppState,
+    session_id: &str,
+    direction: Direction,
+    msg_type: &str,
+    sequence_number: Option<u64>,
+    raw: &[u8],
+    execution: Option<ReportContext>,
+) -> Result<(), StatusCode> {
+    let message = StoredMessage {
+        session_id: session_id.to_string(),
+        direction,
+        msg_type: msg_type.to_string(),
+        sequence_number,
+        raw_base64: BASE64.encode(raw),
+        recorded_at_ms: epoch_ms(),
+        execution,
+        attribution: None,
+    };
+    publish(state, message, raw).await
+}
+
+/// Stores an outbound message a client sent, attributed to whoever sent it,
+/// and publishes it to the configured sinks. Fails with 503 if it could not
+/// be stored, and the message must not then be sent.
+pub async fn record_sent(
+    state: &AppState,
+    session_id: &str,
+    msg_type: &str,
+    sequence_number: Option<u64>,
+    raw: &[u8],
+    attribution: Option<Attribution>,
+) -> Result<(), StatusCode> {
+    let message = StoredMessage {
+        session_id: session_id.to_string(),
+        direction: Direction::Outbound,
+        msg_type: msg_type.to_string(),
+        sequence_number,
+        raw_base64: BASE64.encode(raw),
+        recorded_at_ms: epoch_ms(),
+        execution: None,
+        attribution,
+    };
+    publish(state, message, raw).await
+}
+
+/// Stores `message`, whose wire bytes are `raw`, and once it is on disk
+/// publishes it to the sinks, the traffic feed and what tracks sessions. A
+/// message that could not be stored is published nowhere.
+async fn publish(state: &AppState, message: StoredMessage, raw: &[u8]) -> Result<(), StatusCode> {
+    let session_id = message.session_id.clone();
+    let msg_type = message.msg_type.clone();
+    let (direction, sequence_number) = (message.direction, message.sequence_number);
+    let at_ms = message.recorded_at_ms;
+    let record = records::encode(&message);
+    let durable = state.messages.lock().await.append(message);
+    durable.wait().await.map_err(|e| {
+        warn!(session_id = %session_id, error = %e, "Failed to persist message");
+        StatusCode::SERVICE_UNAVAILABLE
+    })?;
+
+    sinks::publish(state, SinkEvent::message(&session_id, at_ms, record));
+    session_events::on_message(state, &session_id, direction, &msg_type, raw).await;
+    alerts::on_message(state, &session_id, direction, &msg_type, raw).await;
+    state
+        .throughput
+        .lock()
+        .await
+        .record(&session_id, direction, &msg_type, raw, at_ms);
+    state.traffic.publish(Traffic {
+        session_id,
+        direction,
+        msg_type,
+        sequence_number,
+        raw: raw.to_vec(),
+        at_ms,
+        resend: false,
+    });
+    Ok(())
+}
+
+// ── Retention ─────────────────────────────────────────────────────────────────
+
+const DAY_MS: u64 = 86_400_000;
+const DEFAULT_COMPACT_INTERVAL_SECS: u64 = 300;
+
+/// Limits on how much history the store keeps. A message past any limit
+/// expires, except that the newest message in each direction of a session is
+/// always kept, so sequence numbers still resume from the store.
+#[derive(Debug, Clone, Default)]
+pub struct Retention {
+    max_age_ms: Option<u64>,
+    /// Per session, in wire bytes.
+    max_bytes: Option<u64>,
+    /// Trading days kept, counting the current one.
+    trading_days: Option<u64>,
+    /// UTC time of day, in ms, at which one trading day ends and the next
+    /// begins.
+    day_end_ms: u64,
+    interval: Duration,
+}
+
+impl Retention {
+    /// Reads `FIX_STORE_MAX_AGE_DAYS`, `FIX_STORE_MAX_BYTES`,
+    /// `FIX_STORE_TRADING_DAYS`, `FIX_TRADING_DAY_END` (UTC `HH:MM`) and
+    /// `FIX_STORE_COMPACT_INTERVAL_SECS`.
+    pub fn from_env() -> Self {
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .filter(|&v| v > 0)
+        };
+        let day_end_ms = std::env::var("FIX_TRADING_DAY_END")
+            .ok()
+            .and_then(|v| match parse_time_of_day(&v) {
+                Some(ms) => Some(ms),
+                None => {
+                    warn!(value = %v, "Ignoring malformed FIX_TRADING_DAY_END");
+                    None
+                }
+            })
+            .unwrap_or(0);
+        Self {
+            max_age_ms: var("FIX_STORE_MAX_AGE_DAYS").map(|d| d * DAY_MS),
+            max_bytes: var("FIX_STORE_MAX_BYTES"),
+            trading_days: var("FIX_STORE_TRADING_DAYS"),
+            day_end_ms,
+            interval: Duration::from_secs(
+                var("FIX_STORE_COMPACT_INTERVAL_SECS").unwrap_or(DEFAULT_COMPACT_INTERVAL_SECS),
+            ),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_age_ms.is_some() || self.max_bytes.is_some() || self.trading_days.is_some()
+    }
+
+    fn describe(&self) -> String {
+        let mut limits = Vec::new();
+        if let Some(ms) = self.max_age_ms {
+            limits.push(format!("max_age_days={}", ms / DAY_MS));
+        }
+        if let Some(bytes) = self.max_bytes {
+            limits.push(format!("max_bytes={bytes}"));
+        }
+        if let Some(days) = self.trading_days {
+            limits.push(format!("trading_days={days}"));
+        }
+        if limits.is_empty() {
+            "unlimited".to_string()
+        } else {
+            limits.join(",")
+        }
+    }
+
+    /// Trading days since the epoch; a day starts at the previous day's end.
+    fn trading_day(&self, ms: u64) -> u64 {
+        (ms + DAY_MS - self.day_end_ms) / DAY_MS
+    }
+
+    /// Which of a session's messages, oldest first, to keep at `now_ms`.
+    fn keep(&self, messages: &[&StoredMessage], now_ms: u64) -> Vec<bool> {
+        let today = self.trading_day(now_ms);
+        let mut keep: Vec<bool> = messages
+            .iter()
+            .map(|m| {
+                let young = self
+                    .max_age_ms
+                    .is_none_or(|age| m.recorded_at_ms + age >= now_ms);
+                let recent = self
+                    .trading_days
+                    .is_none_or(|days| today - self.trading_day(m.recorded_at_ms) < days);
+                young && recent
+            })
+            .collect();
+
+        if let Some(max_bytes) = self.max_bytes {
+            let mut total = 0;
+            for (m, k) in messages.iter().zip(keep.iter_mut()).rev() {
+                total += m.raw_base64.len() as u64 / 4 * 3;
+                if total > max_bytes {
+                    *k = false;
+                }
+            }
+        }
+
+        for direction in [Direction::Inbound, Direction::Outbound] {
+            if let Some(i) = messages.iter().rposition(|m| m.direction == direction) {
+                keep[i] = true;
+            }
+        }
+        keep
+    }
+}
+
+/// `HH:MM` as milliseconds since midnight.
+pub fn parse_time_of_day(s: &str) -> Option<u64> {
+    let (h, m) = s.trim().split_once(':')?;
+    let (h, m): (u64, u64) = (h.parse().ok()?, m.parse().ok()?);
+    (h < 24 && m < 60).then_some((h * 60 + m) * 60_000)
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Compaction {
+    archived: usize,
+    retained: usize,
+    /// Sessions that had messages archived.
+    sessions: usize,
+}
+
+async fn compact_now(state: &AppState) -> Result<Compaction, StatusCode> {
+    let report = state
+        .messages
+        .lock()
+        .await
+        .compact(epoch_ms())
+        .map_err(|e| {
+            warn!(error = %e, "Message store compaction failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if report.archived > 0 {
+        info!(
+            archived = report.archived,
+            retained = report.retained,
+            sessions = report.sessions,
+            "Message store compacted"
+        );
+    }
+    Ok(report)
+}
+
+/// Periodically compacts the store while a retention policy is configured.
+pub async fn run_compactor(state: AppState) {
+    let retention = state.messages.lock().await.retention.clone();
+    if !retention.is_enabled() {
+        return;
+    }
+    let mut ticker = tokio::time::interval(retention.interval);
+    loop {
+        ticker.tick().await;
+        let _ = compact_now(&state).await;
+    }
+}
+
+/// Compacts the store now, archiving whatever the retention policy expires.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/store/compact",
+    tag = "admin",
+    responses(
+        (status = 200, description = "OK", body = Compaction),
+    )
+)]
+pub async fn compact(
+    State(state): State<AppState>,
+    actor: Actor,
+) -> Result<Json<Compaction>, StatusCode> {
+    let compaction = compact_now(&state).await?;
+    audit::record(
+        &state,
+        &actor,
+        AuditAction::StoreCompacted,
+        None,
+        format!(
+            "archived={} retained={}",
+            compaction.archived, compaction.retained
+        ),
+    )
+    .await;
+    Ok(Json(compaction))
+}
+
+// ── Search ────────────────────────────────────────────────────────────────────
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MessageQuery {
+    pub session_id: Option<String>,
+    pub direction: Option<Direction>,
+    /// MsgType value (`D`) or name (`NewOrderSingle`).
+    pub msg_type: Option<String>,
+    pub cl_ord_id: Option<String>,
+    pub symbol: Option<String>,
+    /// MsgSeqNum range, inclusive.
+    pub from_seq: Option<u64>,
+    pub to_seq: Option<u64>,
+    /// Recording time range `[from_ms, to_ms)`.
+    pub from_ms: Option<u64>,
+    pub to_ms: Option<u64>,
+    /// Messages sent by this API key or OIDC user.
+    pub principal: Option<String>,
+    /// `next_cursor` from the previous page.
+    pub(crate) cursor: Option<String>,
+    pub(crate) limit: Option<usize>,
+}
+
+/// A page position: the recording time of the last message returned and
+/// how many matches with that time were returned, written `<ms>.<n>`. It
+/// stays valid while messages are appended or older ones compacted away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cursor {
+    at_ms: u64,
+    seen: usize,
+}
+
+impl Cursor {
+    fn parse(s: &str) -> Option<Self> {
+        let (at_ms, seen) = s.split_once('.')?;
+        Some(Self {
+            at_ms: at_ms.parse().ok()?,
+            seen: seen.parse().ok()?,
+        })
+    }
+}
+
+/// First value of `tag` in a raw message.
+pub fn field_value(raw: &[u8], tag: u32) -> Option<&[u8]> {
+    scan(raw, detect_delimiter(raw))
+        .into_iter()
+        .find(|s| lenient_tag(s.tag) == Some(tag))
+        .and_then(|s| s.value)
+}
+
+impl MessageQuery {
+    fn matches(&self, m: &StoredMessage, msg_type: Option<&str>) -> bool {
+        let header = self
+            .session_id
+            .as_ref()
+            .is_none_or(|id| &m.session_id == id)
+            && self.direction.is_none_or(|d| m.direction == d)
+            && msg_type.is_none_or(|t| m.msg_type == t)
+            && self.from_ms.is_none_or(|from| m.recorded_at_ms >= from)
+            && self.to_ms.is_none_or(|to| m.recorded_at_ms < to)
+            && self.principal.as_ref().is_none_or(|p| {
+                m.attribution.as_ref().and_then(|a| a.principal.as_ref()) == Some(p)
+            });
+        let seq = match (self.from_seq, self.to_seq) {
+            (None, None) => true,
+            (from, to) => m.sequence_number.is_some_and(|seq| {
+                from.is_none_or(|from| seq >= from) && to.is_none_or(|to| seq <= to)
+            }),
+        };
+        if !(header && seq) {
+            return false;
+        }
+        if self.cl_ord_id.is_none() && self.symbol.is_none() {
+            return true;
+        }
+        let Ok(raw) = BASE64.decode(&m.raw_base64) else {
+            return false;
+        };
+        let has = |tag, want: &Option<String>| {
+            want.as_ref()
+                .is_none_or(|want| field_value(&raw, tag) == Some(want.as_bytes()))
+        };
+        has(11, &self.cl_ord_id) && has(55, &self.symbol)
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MessageView {
+    session_id: String,
+    direction: Direction,
+    msg_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sequence_number: Option<u64>,
+    recorded_at_ms: u64,
+    fix_message: String,
+    /// Who sent an outbound message through the API.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attribution: Option<Attribution>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MessagesResponse {
+    items: Vec<MessageView>,
+    count: usize,
+    /// Pass as `cursor` for the next page; absent on the last page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+}
+
+impl MessageStore {
+    /// Every message matching `q`'s filters, oldest first; its cursor and
+    /// limit are ignored.
+    pub fn matching<'a>(
+        &'a self,
+        q: &'a MessageQuery,
+    ) -> impl Iterator<Item = &'a StoredMessage> + 'a {
+        let msg_type = q.msg_type.as_deref().map(crate::msg_type_to_num);
+        self.messages
+            .iter()
+            .filter(move |m| q.matches(m, msg_type.as_deref()))
+    }
+
+    /// One page of matching messages, oldest first.
+    fn search(&self, q: &MessageQuery, cursor: Option<Cursor>, limit: usize) -> MessagesResponse {
+        let msg_type = q.msg_type.as_deref().map(crate::msg_type_to_num);
+        let start = cursor.map_or(0, |c| {
+            self.messages
+                .partition_point(|m| m.recorded_at_ms < c.at_ms)
+        });
+        let mut skip = cursor.map_or(0, |c| c.seen);
+        let mut items: Vec<&StoredMessage> = Vec::new();
+        let mut more = false;
+        for m in self.messages[start..].iter() {
+            if !q.matches(m, msg_type.as_deref()) {
+                continue;
+            }
+            if skip > 0 && cursor.is_some_and(|c| c.at_ms == m.recorded_at_ms) {
+                skip -= 1;
+                continue;
+            }
+            if items.len() == limit {
+                more = true;
+                break;
+            }
+            items.push(m);
+        }
+
+        let next_cursor = more.then(|| {
+            let last = items.last().map_or(0, |m| m.recorded_at_ms);
+            let mut seen = items
+                .iter()
+                .rev()
+                .take_while(|m| m.recorded_at_ms == last)
+                .count();
+            if let Some(c) = cursor.filter(|c| c.at_ms == last) {
+                seen += c.seen;
+            }
+            format!("{last}.{seen}")
+        });
+        let items: Vec<MessageView> = items
+            .into_iter()
+            .map(|m| MessageView {
+                session_id: m.session_id.clone(),
+                direction: m.direction,
+                msg_type: m.msg_type.clone(),
+                sequence_number: m.sequence_number,
+                recorded_at_ms: m.recorded_at_ms,
+                fix_message: BASE64
+                    .decode(&m.raw_base64)
+                    .map(|raw| String::from_utf8_lossy(&raw).into_owned())
+                    .unwrap_or_default(),
+                attribution: m.attribution.clone(),
+            })
+            .collect();
+        MessagesResponse {
+            count: items.len(),
+            items,
+            next_cursor,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/fix/messages",
+    tag = "fix",
+    params(MessageQuery),
+    responses(
+        (status = 200, description = "OK", body = MessagesResponse),
+        (status = 400, description = "Invalid query"),
+    )
+)]
+pub async fn search(
+    State(state): State<AppState>,
+    Query(q): Query<MessageQuery>,
+) -> Result<Json<MessagesResponse>, StatusCode> {
+    let cursor = match &q.cursor {
+        Some(c) => Some(Cursor::parse(c).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+    let limit = q.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let store = state.messages.lock().await;
+    Ok(Json(store.search(&q, cursor, limit)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(session_id: &str, direction: Direction, seq: u64) -> StoredMessage {
+        StoredMessage {
+            session_id: session_id.to_string(),
+            direction,
+            msg_type: "0".to_string(),
+            sequence_number: Some(seq),
+            raw_base64: BASE64.encode(format!("8=FIX.4.4\x0134={seq}\x01")),
+            recorded_at_ms: seq,
+            execution: None,
+            attribution: None,
+        }
+    }
+
+    #[test]
+    fn file_store_survives_reopen() {
+        let dir = std::env::temp_dir().join(format!("fix-store-{}", uuid::Uuid::new_v4()));
+        {
+            let mut store = MessageStore::open(&dir).unwrap();
+            store.append(message("ALICE->BROKER_A", Direction::Outbound, 1));
+            store.append(message("ALICE->BROKER_A", Direction::Inbound, 1));
+            store.append(message("ALICE->BROKER_A", Direction::Outbound, 2));
+            store.append(message("ALICE->MARKET_DATA", Direction::Outbound, 7));
+        }
+        assert!(dir.join("ALICE-_BROKER_A.log").exists());
+
+        // A torn final write is skipped.
+        let mut log = OpenOptions::new()
+            .append(true)
+            .open(dir.join("ALICE-_BROKER_A.log"))
+            .unwrap();
+        log.write_all(b"{\"schema_version\":1,\"sess").unwrap();
+
+        let store = MessageStore::open(&dir).unwrap();
+        assert_eq!(store.len(), 4);
+        let last = store.last_seq_nums(Direction::Outbound);
+        assert_eq!(last["ALICE->BROKER_A"], 2);
+        assert_eq!(last["ALICE->MARKET_DATA"], 7);
+        assert_eq!(store.between(0, 3).len(), 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn compaction_archives_expired_messages() {
+        let dir = std::env::temp_dir().join(format!("fix-store-{}", uuid::Uuid::new_v4()));
+        let day = |d: u64, hour: u64| d * DAY_MS + hour * 3_600_000;
+        let at = |session: &str, direction, seq, ms| StoredMessage {
+            recorded_at_ms: ms,
+            ..message(session, direction, seq)
+        };
+        let mut store = MessageStore::open(&dir).unwrap();
+        store.retention = Retention {
+            trading_days: Some(2),
+            day_end_ms: 22 * 3_600_000,
+            ..Retention::default()
+        };
+        // Trading days end at 22:00 UTC, so day 10 23:00 belongs to day 11.
+        store.append(at("ALICE->BROKER_A", Direction::Outbound, 1, day(9, 12)));
+        store.append(at("ALICE->BROKER_A", Direction::Outbound, 2, day(9, 23)));
+        store.append(at("ALICE->BROKER_A", Direction::Outbound, 3, day(10, 23)));
+        store.append(at("ALICE->BROKER_B", Direction::Outbound, 1, day(8, 12)));
+        store.append(at("ALICE->BROKER_B", Direction::Inbound, 1, day(8, 13)));
+
+        let report = store.compact(day(11, 12)).unwrap();
+        assert_eq!(
+            (report.archived, report.retained, report.sessions),
+            (1, 4, 1)
+        );
+        // BROKER_B's messages are old but the newest per direction are kept.
+        assert_eq!(store.for_session("ALICE->BROKER_B").count(), 2);
+        assert!(dir.join("archive/ALICE-_BROKER_A.19700110.log").exists());
+
+        // The rewritten log is what a restart reads, and appends still land.
+        store.append(at("ALICE->BROKER_A", Direction::Outbound, 4, day(11, 12)));
+        let reopened = MessageStore::open(&dir).unwrap();
+        let seqs: Vec<_> = reopened
+            .for_session("ALICE->BROKER_A")
+            .filter_map(|m| m.sequence_number)
+            .collect();
+        assert_eq!(seqs, [2, 3, 4]);
+
+        store.retention = Retention {
+            max_bytes: Some(40),
+            ..Retention::default()
+        };
+        store.compact(day(11, 12)).unwrap();
+        assert_eq!(store.for_session("ALICE->BROKER_A").count(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn search_filters_and_pages_through_messages() {
+        let order = |seq: u64, cl_ord_id: &str, symbol: &str, ms| StoredMessage {
+            msg_type: "D".to_string(),
+            raw_base64: BASE64.encode(format!(
+                "8=FIX.4.4\x0135=D\x0134={seq}\x0111={cl_ord_id}\x0155={symbol}\x01"
+            )),
+            recorded_at_ms: ms,
+            ..message("ALICE->BROKER_A", Direction::Outbound, seq)
+        };
+        let mut store = MessageStore::default();
+        store.append(order(1, "O1", "AAPL", 100));
+        store.append(message("ALICE->BROKER_A", Direction::Inbound, 1));
+        store.append(order(2, "O2", "MSFT", 200));
+        store.append(order(3, "O3", "AAPL", 200));
+        store.append(order(4, "O4", "AAPL", 200));
+        store.append(order(5, "O1", "AAPL", 300));
+
+        let query = |q: &str| -> MessageQuery { serde_json::from_str(q).unwrap() };
+        let seqs = |page: &MessagesResponse| -> Vec<u64> {
+            page.items
+                .iter()
+                .filter_map(|m| m.sequence_number)
+                .collect()
+        };
+        let q = query(r#"{"cl_ord_id": "O1"}"#);
+        assert_eq!(seqs(&store.search(&q, None, 10)), [1, 5]);
+        let q = query(r#"{"msg_type": "NewOrderSingle", "symbol": "AAPL", "to_ms": 300}"#);
+        assert_eq!(seqs(&store.search(&q, None, 10)), [1, 3, 4]);
+        let q = query(r#"{"direction": "outbound", "from_seq": 2, "to_seq": 4}"#);
+        assert_eq!(seqs(&store.search(&q, None, 10)), [2, 3, 4]);
+
+        // Pages split inside a run of equal timestamps without repeats.
+        let q = query(r#"{"msg_type": "D"}"#);
+        let mut cursor = None;
+        let mut pages = Vec::new();
+        loop {
+            let page = store.search(&q, cursor, 2);
+            pages.push(seqs(&page));
+            match page.next_cursor {
+                Some(next) => cursor = Cursor::parse(&next),
+                None => break,
+            }
+        }
+        assert_eq!(pages, [vec![1, 2], vec![3, 4], vec![5]]);
+
+        // Orders are found by who sent them.
+        let attribution = Attribution {
+            principal: Some("oms-prod".to_string()),
+            ..Attribution::default()
+        };
+        store.append(StoredMessage {
+            attribution: Some(attribution.clone()),
+            ..order(6, "O6", "MSFT", 400)
+        });
+        let page = store.search(&query(r#"{"principal": "oms-prod"}"#), None, 10);
+        assert_eq!(seqs(&page), [6]);
+        assert_eq!(page.items[0].attribution, Some(attribution));
+        assert_eq!(
+            Cursor::parse("200.1"),
+            Some(Cursor {
+                at_ms: 200,
+                seen: 1
+            })
+        );
+        assert_eq!(Cursor::parse("200"), None);
+    }
+}
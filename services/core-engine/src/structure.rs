@@ -0,0 +1,381 @@
+//! Structural layout rules for tag=value messages: header, body, trailer
+//! placement, repeating-group delimiters and wire framing.
+
+use crate::{message, tag_to_name, ErrorCategory, ParsedField, ValidationError};
+
+/// Standard header tags. BeginString, BodyLength and MsgType must lead in that
+/// order; the rest may appear in any order but before the first body field.
+pub const HEADER_TAGS: &[u32] = &[
+    8, 9, 35, 34, 43, 49, 50, 52, 56, 57, 90, 91, 97, 115, 116, 122, 128, 129, 142, 143, 144, 145,
+    212, 213, 347, 369, 627, 1128, 1129, 1156,
+];
+
+/// Standard trailer tags. CheckSum must be the final field.
+pub const TRAILER_TAGS: &[u32] = &[93, 89, 10];
+
+/// A repeating group: the NoXXX count tag, the tag that must open every
+/// instance, and every tag that may appear inside an instance.
+pub struct GroupDef {
+    pub count_tag: u32,
+    pub delimiter: u32,
+    pub members: &'static [u32],
+}
+
+const GROUPS: &[GroupDef] = &[
+    GroupDef {
+        count_tag: 73, // NoOrders
+        delimiter: 11,
+        members: &[11, 67, 1, 21, 55, 54, 38, 40, 44, 59, 126, 432],
+    },
+    GroupDef {
+        count_tag: 78, // NoAllocs
+        delimiter: 79,
+        members: &[79, 80, 661, 736],
+    },
+    GroupDef {
+        count_tag: 146, // NoRelatedSym
+        delimiter: 55,
+        members: &[55, 65, 48, 22, 167, 200, 207, 460, 461],
+    },
+    GroupDef {
+        count_tag: 267, // NoMDEntryTypes
+        delimiter: 269,
+        members: &[269],
+    },
+    GroupDef {
+        count_tag: 268, // NoMDEntries (snapshot)
+        delimiter: 269,
+        members: &[269, 270, 271, 272, 273, 290, 299, 346],
+    },
+    GroupDef {
+        count_tag: 453, // NoPartyIDs
+        delimiter: 448,
+        members: &[448, 447, 452],
+    },
+];
+
+/// MarketDataIncrementalRefresh instances open with MDUpdateAction instead.
+const INCREMENTAL_MD_ENTRIES: GroupDef = GroupDef {
+    count_tag: 268,
+    delimiter: 279,
+    members: &[279, 269, 55, 270, 271, 272, 273, 278, 280, 290, 299, 346],
+};
+
+/// Returns the group definition for `count_tag` within a message of type `msg_type` (wire code).
+pub fn group_for(msg_type: &str, count_tag: u32) -> Option<&'static GroupDef> {
+    if msg_type == "X" && count_tag == INCREMENTAL_MD_ENTRIES.count_tag {
+        return Some(&INCREMENTAL_MD_ENTRIES);
+    }
+    GROUPS.iter().find(|g| g.count_tag == count_tag)
+}
+
+/// Checks field placement in wire order and returns every ordering violation.
+pub fn check_ordering(fields: &[ParsedField]) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    for (pos, (tag, label)) in [(8, "first"), (9, "second"), (35, "third")]
+        .into_iter()
+        .enumerate()
+    {
+        if fields.get(pos).map(|f| f.tag) != Some(tag) {
+            errors.push(ordering_error(
+                tag,
+                format!("{}({tag}) must be the {label} field.", tag_to_name(tag)),
+            ));
+        }
+    }
+
+    let msg_type = fields
+        .iter()
+        .find(|f| f.tag == 35)
+        .map(|f| f.value.as_str())
+        .unwrap_or_default();
+
+    let mut body_started = false;
+    let mut trailer_started = false;
+    let mut i = 0;
+    while i < fields.len() {
+        let tag = fields[i].tag;
+        if TRAILER_TAGS.contains(&tag) {
+            trailer_started = true;
+        } else if trailer_started {
+            errors.push(ordering_error(
+                tag,
+                format!("{}({tag}) appears after the trailer.", tag_to_name(tag)),
+            ));
+        } else if HEADER_TAGS.contains(&tag) {
+            if body_started {
+                errors.push(ordering_error(
+                    tag,
+                    format!(
+                        "Header field {}({tag}) appears after body fields.",
+                        tag_to_name(tag)
+                    ),
+                ));
+            }
+        } else {
+            body_started = true;
+        }
+
+        if let Some(group) = group_for(msg_type, tag) {
+            i = check_group(fields, i, group, &mut errors);
+            continue;
+        }
+        i += 1;
+    }
+
+    match fields.last() {
+        Some(last) if last.tag == 10 => {}
+        _ => errors.push(ordering_error(
+            10,
+            "CheckSum(10) must be the last field.".to_string(),
+        )),
+    }
+
+    errors
+}
+
+/// Returns the position and tag of every repeated occurrence of a tag outside
+/// repeating groups. Tags inside a group instance may legitimately repeat.
+pub fn duplicate_tags(fields: &[ParsedField]) -> Vec<(usize, u32)> {
+    let msg_type = fields
+        .iter()
+        .find(|f| f.tag == 35)
+        .map(|f| f.value.as_str())
+        .unwrap_or_default();
+
+    let mut seen: Vec<u32> = Vec::new();
+    let mut duplicates = Vec::new();
+    let mut i = 0;
+    while i < fields.len() {
+        let tag = fields[i].tag;
+        if seen.contains(&tag) {
+            duplicates.push((i, tag));
+        } else {
+            seen.push(tag);
+        }
+        i += 1;
+        if let Some(group) = group_for(msg_type, tag) {
+            while i < fields.len() && group.members.contains(&fields[i].tag) {
+                i += 1;
+            }
+        }
+    }
+    duplicates
+}
+
+/// Walks the instances of the group whose count field sits at `start`,
+/// returning the index of the first field after the group.
+fn check_group(
+    fields: &[ParsedField],
+    start: usize,
+    group: &GroupDef,
+    errors: &mut Vec<ValidationError>,
+) -> usize {
+    let count = fields[start].value.parse::<u32>().unwrap_or(0);
+    let mut i = start + 1;
+    if count == 0 {
+        return i;
+    }
+
+    let missing_delimiter = || {
+        ordering_error(
+            group.count_tag,
+            format!(
+                "{}({}) instance must begin with {}({}).",
+                tag_to_name(group.count_tag),
+                group.count_tag,
+                tag_to_name(group.delimiter),
+                group.delimiter
+            ),
+        )
+    };
+
+    if fields.get(i).map(|f| f.tag) != Some(group.delimiter) {
+        errors.push(missing_delimiter());
+    }
+
+    // A member repeating within one instance means a new instance began
+    // without its delimiter.
+    let mut seen: Vec<u32> = Vec::new();
+    while i < fields.len() && group.members.contains(&fields[i].tag) {
+        let tag = fields[i].tag;
+        if tag == group.delimiter {
+            seen.clear();
+        } else if seen.contains(&tag) {
+            errors.push(missing_delimiter());
+            seen.clear();
+        }
+        seen.push(tag);
+        i += 1;
+    }
+    i
+}
+
+/// Checks a raw message's wire framing: every field is a terminated
+/// `tag=value` pair, BodyLength matches the bytes between it and CheckSum, and
+/// CheckSum matches the byte sum. A `|` delimiter counts as SOH, so
+/// human-readable captures of valid messages pass.
+pub fn check_framing(raw: &[u8]) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let delimiter = message::detect_delimiter(raw);
+    let segments = message::scan(raw, delimiter);
+
+    for segment in &segments {
+        let tag = message::lenient_tag(segment.tag);
+        let at = segment.offset;
+        match (tag, segment.value) {
+            (_, None) => errors.push(framing_error(
+                tag.unwrap_or(0),
+                format!("Field at byte {at} has no '='."),
+            )),
+            (None, _) => errors.push(framing_error(
+                0,
+                format!("Field at byte {at} has an invalid tag."),
+            )),
+            (Some(tag), Some(b"")) => errors.push(framing_error(
+                tag,
+                format!(
+                    "{}({tag}) at byte {at} has an empty value.",
+                    tag_to_name(tag)
+                ),
+            )),
+            _ => {}
+        }
+    }
+    if segments.last().is_some_and(|s| !s.terminated) {
+        errors.push(framing_error(
+            10,
+            "Message does not end with a delimiter.".to_string(),
+        ));
+    }
+
+    let find = |tag: u32| {
+        segments
+            .iter()
+            .find(|s| message::lenient_tag(s.tag) == Some(tag))
+    };
+    let Some(checksum) = segments
+        .iter()
+        .rev()
+        .find(|s| message::lenient_tag(s.tag) == Some(10))
+    else {
+        // Reported as a missing trailer field.
+        return errors;
+    };
+
+    if let Some(body_length) = find(9).filter(|s| s.offset < checksum.offset) {
+        let value = body_length.value.unwrap_or_default();
+        let body_start = body_length.offset + body_length.tag.len() + value.len() + 2;
+        let actual = checksum.offset.saturating_sub(body_start);
+        match std::str::from_utf8(value)
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+        {
+            Some(declared) if declared == actual => {}
+            Some(declared) => errors.push(framing_error(
+                9,
+                format!("BodyLength(9) is {declared} but the body is {actual} bytes."),
+            )),
+            None => errors.push(framing_error(
+                9,
+                format!("BodyLength(9) is not a number; the body is {actual} bytes."),
+            )),
+        }
+    }
+
+    let computed = message::checksum(&raw[..checksum.offset], delimiter);
+    let declared = checksum.value.unwrap_or_default();
+    let valid_format = declared.len() == 3 && declared.iter().all(u8::is_ascii_digit);
+    if !valid_format || declared != format!("{computed:03}").as_bytes() {
+        errors.push(framing_error(
+            10,
+            format!(
+                "CheckSum(10) is '{}' but the computed checksum is {computed:03}.",
+                String::from_utf8_lossy(declared)
+            ),
+        ));
+    }
+
+    errors
+}
+
+fn framing_error(tag: u32, message: String) -> ValidationError {
+    ValidationError {
+        path: None,
+        field: tag_to_name(tag),
+        tag,
+        message,
+        category: ErrorCategory::Framing,
+    }
+}
+
+fn ordering_error(tag: u32, message: String) -> ValidationError {
+    ValidationError {
+        path: None,
+        field: tag_to_name(tag),
+        tag,
+        message,
+        category: ErrorCategory::Ordering,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Field, FixMessage, SOH};
+
+    fn order() -> Vec<u8> {
+        let field = |tag, value: &str| Field {
+            tag,
+            value: value.as_bytes().to_vec(),
+        };
+        FixMessage::framed(
+            "FIX.4.4",
+            vec![field(35, "D"), field(49, "ALICE"), field(55, "AAPL")],
+        )
+        .encode_with(b'|')
+    }
+
+    fn messages(raw: &[u8]) -> Vec<String> {
+        check_framing(raw).into_iter().map(|e| e.message).collect()
+    }
+
+    #[test]
+    fn accepts_well_framed_messages() {
+        assert!(messages(&order()).is_empty());
+        let soh: Vec<u8> = order()
+            .iter()
+            .map(|&b| if b == b'|' { SOH } else { b })
+            .collect();
+        assert!(messages(&soh).is_empty());
+    }
+
+    #[test]
+    fn reports_length_and_checksum_mismatches() {
+        let raw = String::from_utf8(order()).unwrap();
+        let (head, checksum) = raw.rsplit_once("10=").unwrap();
+        let tampered = raw.replace("AAPL", "MSFT");
+        let errors = messages(tampered.as_bytes());
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("CheckSum(10) is '"));
+
+        let longer = format!("{}10={checksum}", head.replace("AAPL", "AAPL.O"));
+        let errors = messages(longer.as_bytes());
+        assert_eq!(errors[0], "BodyLength(9) is 22 but the body is 24 bytes.");
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn reports_broken_structure() {
+        let errors = messages(b"8=FIX.4.4|9=5|35=0|junk|58=|10=000");
+        assert_eq!(
+            errors[..3],
+            [
+                "Field at byte 19 has no '='.",
+                "Text(58) at byte 24 has an empty value.",
+                "Message does not end with a delimiter.",
+            ]
+        );
+    }
+}
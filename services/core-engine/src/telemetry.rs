@@ -0,0 +1,297 @@
+//! OpenTelemetry traces.
+//!
+//! With `OTEL_EXPORTER_OTLP_ENDPOINT` (or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`)
+//! set, spans are exported over OTLP/gRPC as service `OTEL_SERVICE_NAME`
+//! (default `fix-engine`). Every HTTP request gets a server span that
+//! continues the trace in its W3C `traceparent` header, and `/send` adds
+//! child spans for building, persisting and transmitting the message. Orders
+//! forwarded to an upstream gateway carry the trace on, and an
+//! ExecutionReport joins the trace of the order it answers, so an order can
+//! be followed from the OMS to its fills across services.
+//!
+//! The request span comes from tower-http's `TraceLayer`. It carries the
+//! request ID, and its events log each request with its status and latency.
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{HeaderMap, HeaderName, HeaderValue},
+    response::Response,
+};
+use opentelemetry::{
+    global,
+    propagation::{Extractor, Injector},
+    trace::{SpanContext, TraceContextExt, TraceError, TracerProvider as _},
+    Context, KeyValue,
+};
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator, runtime, trace::TracerProvider, Resource,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+use tracing::{debug, field::Empty, info, info_span, warn, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+use crate::{
+    config::{LogFormat, LoggingConfig},
+    problem::{self, CorrelationId},
+};
+
+const DEFAULT_SERVICE_NAME: &str = "fix-engine";
+/// Orders whose trace an ExecutionReport can still join.
+const MAX_ORDER_TRACES: usize = 100_000;
+
+/// Installs the log subscriber, logging as `logging` says and exporting
+/// spans if an OTLP endpoint is configured. The returned provider flushes
+/// them at shutdown.
+pub fn init(logging: &LoggingConfig) -> Option<TracerProvider> {
+    let configured = ["OTEL_EXPORTER_OTLP_ENDPOINT", "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT"]
+        .iter()
+        .any(|name| std::env::var(name).is_ok_and(|v| !v.trim().is_empty()));
+    let provider = configured.then(provider);
+    let tracer = match &provider {
+        Some(Ok(provider)) => Some(provider.tracer(DEFAULT_SERVICE_NAME)),
+        _ => None,
+    };
+
+    // The configuration file checked the level, so it parses.
+    let filter = match &logging.level {
+        Some(level) => EnvFilter::new(level),
+        None => EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| EnvFilter::new("fix_engine=info,tower_http=debug")),
+    };
+    let json = logging.format == LogFormat::Json;
+    tracing_subscriber::registry()
+        .with(tracer.map(|t| tracing_opentelemetry::layer().with_tracer(t)))
+        .with(filter)
+        .with(json.then(|| tracing_subscriber::fmt::layer().json()))
+        .with((!json).then(tracing_subscriber::fmt::layer))
+        .init();
+
+    match provider? {
+        Ok(provider) => {
+            info!("OpenTelemetry trace export enabled");
+            Some(provider)
+        }
+        Err(e) => {
+            warn!(error = %e, "OpenTelemetry trace export disabled");
+            None
+        }
+    }
+}
+
+fn provider() -> Result<TracerProvider, TraceError> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .build()?;
+    let service = std::env::var("OTEL_SERVICE_NAME")
+        .unwrap_or_else(|_| DEFAULT_SERVICE_NAME.to_string());
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new("service.name", service)]))
+        .build())
+}
+
+/// Flushes the spans not yet exported.
+pub fn shutdown(provider: TracerProvider) {
+    if let Err(e) = provider.shutdown() {
+        warn!(error = %e, "OpenTelemetry shutdown failed");
+    }
+}
+
+// ── Propagation ──────────────────────────────────────────────────────────────
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+/// The current trace as headers for an outgoing request.
+pub fn headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let context = Span::current().context();
+    global::get_text_map_propagator(|p| {
+        p.inject_context(&context, &mut HeaderInjector(&mut headers))
+    });
+    headers
+}
+
+// ── Requests ─────────────────────────────────────────────────────────────────
+
+/// `TraceLayer`'s span for a request: a server span continuing the caller's
+/// trace, with the request ID.
+pub fn request_span(request: &Request) -> Span {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| request.uri().path(), MatchedPath::as_str)
+        .to_string();
+    let request_id = request
+        .extensions()
+        .get::<CorrelationId>()
+        .map_or("", |id| id.0.as_str());
+    let span = info_span!(
+        "HTTP request",
+        request_id = %request_id,
+        otel.name = %format!("{} {route}", request.method()),
+        otel.kind = "server",
+        otel.status_code = Empty,
+        http.request.method = %request.method(),
+        http.route = %route,
+        http.response.status_code = Empty,
+    );
+    let parent =
+        global::get_text_map_propagator(|p| p.extract(&HeaderExtractor(request.headers())));
+    span.set_parent(parent);
+    span
+}
+
+pub fn on_request(request: &Request, _span: &Span) {
+    debug!(uri = %request.uri(), "Request received");
+}
+
+pub fn on_response(response: &Response, latency: Duration, span: &Span) {
+    let status = response.status();
+    span.record("http.response.status_code", status.as_u16());
+    let latency_ms = latency.as_secs_f64() * 1000.0;
+    if status.is_server_error() {
+        span.record("otel.status_code", "ERROR");
+        warn!(status = status.as_u16(), latency_ms, "Request failed");
+    } else {
+        info!(status = status.as_u16(), latency_ms, "Request completed");
+    }
+}
+
+/// A span for work a request leaves running in the background: it carries
+/// the request ID and follows from the request's span without holding it
+/// open.
+pub fn background_span() -> Span {
+    let request_id = problem::current_correlation_id().unwrap_or_default();
+    let span = info_span!(parent: None, "background", request_id = %request_id);
+    span.follows_from(Span::current());
+    span
+}
+
+// ── Order traces ─────────────────────────────────────────────────────────────
+
+/// The trace each recent order was sent in, by ClOrdID.
+#[derive(Debug, Default)]
+pub struct OrderTraces {
+    by_cl_ord_id: HashMap<String, SpanContext>,
+    /// ClOrdIDs, oldest first, for eviction.
+    order: VecDeque<String>,
+}
+
+impl OrderTraces {
+    /// Remembers the current span as the one `cl_ord_id` was sent in.
+    pub fn record(&mut self, cl_ord_id: &str) {
+        let context = Span::current().context();
+        let span_context = context.span().span_context().clone();
+        if span_context.is_valid() {
+            self.insert(cl_ord_id, span_context);
+        }
+    }
+
+    fn insert(&mut self, cl_ord_id: &str, span_context: SpanContext) {
+        if self
+            .by_cl_ord_id
+            .insert(cl_ord_id.to_string(), span_context)
+            .is_none()
+        {
+            self.order.push_back(cl_ord_id.to_string());
+        }
+        while self.order.len() > MAX_ORDER_TRACES {
+            if let Some(oldest) = self.order.pop_front() {
+                self.by_cl_ord_id.remove(&oldest);
+            }
+        }
+    }
+
+    /// Makes `span` part of the trace `cl_ord_id` was sent in, if known.
+    /// Must be called before the span is first entered.
+    pub fn join(&self, cl_ord_id: &str, span: &Span) {
+        if let Some(span_context) = self.by_cl_ord_id.get(cl_ord_id) {
+            span.set_parent(Context::new().with_remote_span_context(span_context.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{SpanId, TraceFlags, TraceId, TraceState};
+
+    fn span_context(n: u128) -> SpanContext {
+        SpanContext::new(
+            TraceId::from_bytes(n.to_be_bytes()),
+            SpanId::from_bytes((n as u64).to_be_bytes()),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        )
+    }
+
+    #[test]
+    fn oldest_order_traces_are_evicted() {
+        let mut traces = OrderTraces::default();
+        for n in 1..=MAX_ORDER_TRACES as u128 + 1 {
+            traces.insert(&format!("ORD-{n}"), span_context(n));
+        }
+        assert_eq!(traces.by_cl_ord_id.len(), MAX_ORDER_TRACES);
+        assert!(!traces.by_cl_ord_id.contains_key("ORD-1"));
+        assert!(traces.by_cl_ord_id.contains_key("ORD-2"));
+
+        // Re-sending a ClOrdID keeps one entry for it.
+        traces.insert("ORD-2", span_context(7));
+        assert_eq!(traces.order.len(), MAX_ORDER_TRACES);
+    }
+
+    #[test]
+    fn trace_context_headers_are_read() {
+        let propagator = TraceContextPropagator::new();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+        );
+        let context = opentelemetry::propagation::TextMapPropagator::extract(
+            &propagator,
+            &HeaderExtractor(&headers),
+        );
+        assert_eq!(
+            context.span().span_context().trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+
+        let mut injected = HeaderMap::new();
+        opentelemetry::propagation::TextMapPropagator::inject_context(
+            &propagator,
+            &context,
+            &mut HeaderInjector(&mut injected),
+        );
+        assert_eq!(injected["traceparent"], headers["traceparent"]);
+    }
+}
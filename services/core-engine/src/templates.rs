@@ -0,0 +1,328 @@
+//! Named message templates.
+//!
+//! A template is a MsgType with default fields, groups and metadata, stored
+//! under a name such as `ARCA_DAY_LIMIT`. A `/send` request naming a template
+//! starts from it: the request's own fields and metadata override the
+//! template's one by one, and a group given in the request replaces the
+//! template's group of the same name. Templates are loaded from the JSON files
+//! in `FIX_MESSAGE_TEMPLATES` at startup and managed through
+//! `/api/v1/fix/templates`.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    audit::{self, Actor, AuditAction},
+    msg_type_to_num,
+    problem::ApiError,
+    AppState, Groups, SendRequest,
+};
+
+const MAX_NAME_LEN: usize = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MessageTemplate {
+    /// Letters, digits, `_`, `-` and `.`, e.g. `ARCA_DAY_LIMIT`.
+    pub name: String,
+    pub msg_type: String,
+    #[serde(default)]
+    pub fields: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schema(value_type = BTreeMap<String, Vec<HashMap<String, String>>>)]
+    pub groups: Groups,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl MessageTemplate {
+    fn check(&self) -> Result<(), String> {
+        let valid_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.');
+        let valid_name = (1..=MAX_NAME_LEN).contains(&self.name.len())
+            && self.name.chars().all(valid_char);
+        if !valid_name {
+            return Err(format!(
+                "name must be 1 to {MAX_NAME_LEN} letters, digits, '_', '-' or '.'"
+            ));
+        }
+        if self.msg_type.trim().is_empty() {
+            return Err("msg_type is required".to_string());
+        }
+        Ok(())
+    }
+
+    /// Fills in `req` from the template, keeping what the request gives.
+    fn apply(&self, req: &mut SendRequest) {
+        req.msg_type = self.msg_type.clone();
+        for (name, value) in &self.fields {
+            req.fields.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+        for (name, instances) in &self.groups {
+            req.groups.entry(name.clone()).or_insert_with(|| instances.clone());
+        }
+        for (name, value) in &self.metadata {
+            req.metadata.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Templates {
+    by_name: HashMap<String, MessageTemplate>,
+}
+
+impl Templates {
+    /// Loads the templates in the JSON files listed in `FIX_MESSAGE_TEMPLATES`
+    /// (comma-separated paths), each holding an array of templates.
+    pub fn from_env() -> Self {
+        let mut templates = Self::default();
+        let paths = std::env::var("FIX_MESSAGE_TEMPLATES").unwrap_or_default();
+        for path in paths.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let loaded = std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|json| {
+                    serde_json::from_str::<Vec<MessageTemplate>>(&json).map_err(|e| e.to_string())
+                });
+            match loaded {
+                Ok(list) => {
+                    for template in list {
+                        match template.check() {
+                            Ok(()) => templates.add(template),
+                            Err(e) => {
+                                warn!(path, name = %template.name, error = %e, "Invalid template")
+                            }
+                        }
+                    }
+                    info!(path, templates = templates.len(), "Message templates loaded");
+                }
+                Err(e) => warn!(path, error = %e, "Failed to load message templates"),
+            }
+        }
+        templates
+    }
+
+    /// Adds a template, replacing any with the same name.
+    pub fn add(&mut self, template: MessageTemplate) {
+        self.by_name.insert(template.name.clone(), template);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<MessageTemplate> {
+        self.by_name.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MessageTemplate> {
+        self.by_name.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_name.len()
+    }
+
+    fn sorted(&self) -> Vec<MessageTemplate> {
+        let mut templates: Vec<MessageTemplate> = self.by_name.values().cloned().collect();
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        templates
+    }
+}
+
+/// Expands the template a send request names, if any. A request that also
+/// gives a MsgType must give the template's, by name or code.
+pub async fn resolve(state: &AppState, req: &mut SendRequest) -> Result<(), ApiError> {
+    let Some(name) = req.template.as_deref() else {
+        return Ok(());
+    };
+    let templates = state.templates.lock().await;
+    let template = templates.get(name).ok_or_else(|| {
+        ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "unknown_template")
+            .detail(format!("no message template named {name}"))
+    })?;
+    resolve_with(template, req)
+}
+
+fn resolve_with(template: &MessageTemplate, req: &mut SendRequest) -> Result<(), ApiError> {
+    let given = req.msg_type.trim();
+    if !given.is_empty() && msg_type_to_num(given) != msg_type_to_num(&template.msg_type) {
+        return Err(ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "template_msg_type_mismatch")
+            .detail(format!(
+                "template {} is for MsgType {}, not {}",
+                template.name, template.msg_type, req.msg_type
+            )));
+    }
+    template.apply(req);
+    Ok(())
+}
+
+// ── Handlers ─────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TemplatesResponse {
+    templates: Vec<MessageTemplate>,
+    count: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/fix/templates",
+    tag = "fix",
+    responses(
+        (status = 200, description = "OK", body = TemplatesResponse),
+    )
+)]
+pub async fn list(State(state): State<AppState>) -> Json<TemplatesResponse> {
+    let templates = state.templates.lock().await.sorted();
+    Json(TemplatesResponse {
+        count: templates.len(),
+        templates,
+    })
+}
+
+/// Stores a template, replacing any with the same name.
+#[utoipa::path(
+    post,
+    path = "/api/v1/fix/templates",
+    tag = "fix",
+    request_body = MessageTemplate,
+    responses(
+        (status = 200, description = "Stored", body = MessageTemplate),
+        (status = 400, description = "Invalid template"),
+    )
+)]
+pub async fn register(
+    State(state): State<AppState>,
+    actor: Actor,
+    Json(template): Json<MessageTemplate>,
+) -> Result<Json<MessageTemplate>, ApiError> {
+    template.check().map_err(|e| {
+        info!(name = %template.name, error = %e, "Message template rejected");
+        ApiError::new(StatusCode::BAD_REQUEST, "invalid_template").detail(e)
+    })?;
+    info!(
+        name = %template.name,
+        msg_type = %template.msg_type,
+        fields = template.fields.len(),
+        "Message template stored"
+    );
+    state.templates.lock().await.add(template.clone());
+    audit::record(
+        &state,
+        &actor,
+        AuditAction::TemplateStored,
+        None,
+        format!("{} ({})", template.name, template.msg_type),
+    )
+    .await;
+    Ok(Json(template))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/fix/templates/{name}",
+    tag = "fix",
+    params(("name" = String, Path, description = "The template's name.")),
+    responses(
+        (status = 200, description = "OK", body = MessageTemplate),
+        (status = 404, description = "Unknown template"),
+    )
+)]
+pub async fn get(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<MessageTemplate>, StatusCode> {
+    state
+        .templates
+        .lock()
+        .await
+        .get(&name)
+        .cloned()
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/fix/templates/{name}",
+    tag = "fix",
+    params(("name" = String, Path, description = "The template's name.")),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 404, description = "Unknown template"),
+    )
+)]
+pub async fn delete(
+    State(state): State<AppState>,
+    actor: Actor,
+    Path(name): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    state
+        .templates
+        .lock()
+        .await
+        .remove(&name)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    info!(name = %name, "Message template deleted");
+    audit::record(&state, &actor, AuditAction::TemplateDeleted, None, name).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day_limit() -> MessageTemplate {
+        serde_json::from_value(serde_json::json!({
+            "name": "ARCA_DAY_LIMIT",
+            "msg_type": "NewOrderSingle",
+            "fields": { "TargetCompID": "ARCA", "OrdType": "2", "TimeInForce": "0", "Side": "1" },
+            "metadata": { "desk": "equities" }
+        }))
+        .unwrap()
+    }
+
+    fn request(json: serde_json::Value) -> SendRequest {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn requests_override_template_defaults() {
+        let mut req = request(serde_json::json!({
+            "template": "ARCA_DAY_LIMIT",
+            "fields": { "Symbol": "AAPL", "Side": "2", "Price": "187.5" },
+            "metadata": { "strategy_id": "S1" }
+        }));
+        resolve_with(&day_limit(), &mut req).unwrap();
+        assert_eq!(req.msg_type, "NewOrderSingle");
+        assert_eq!(req.fields["TargetCompID"], "ARCA");
+        assert_eq!(req.fields["Side"], "2");
+        assert_eq!(req.fields["Symbol"], "AAPL");
+        assert_eq!(req.metadata.len(), 2);
+    }
+
+    #[test]
+    fn a_different_msg_type_is_rejected() {
+        let mut req = request(serde_json::json!({ "template": "ARCA_DAY_LIMIT", "msg_type": "F" }));
+        let err = resolve_with(&day_limit(), &mut req).unwrap_err();
+        assert_eq!(err.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let mut req = request(serde_json::json!({ "template": "ARCA_DAY_LIMIT", "msg_type": "D" }));
+        assert!(resolve_with(&day_limit(), &mut req).is_ok());
+    }
+
+    #[test]
+    fn names_are_checked() {
+        assert!(day_limit().check().is_ok());
+        let mut template = day_limit();
+        template.name = "ARCA DAY".to_string();
+        assert!(template.check().is_err());
+        template.name = "ARCA_DAY".to_string();
+        template.msg_type = " ".to_string();
+        assert!(template.check().is_err());
+    }
+}
@@ -0,0 +1,320 @@
+//! Per-session message rates, from counters kept as messages are recorded.
+//!
+//! Every message sent or received is counted, with its size, in one-second
+//! buckets covering the last 15 minutes and in totals since startup. Rejects
+//! (Reject, BusinessMessageReject, or an ExecutionReport rejecting an order)
+//! and ResendRequests are counted too. `GET
+//! /api/v1/fix/sessions/{session_id}/stats` sums the buckets into 1, 5 and 15
+//! minute windows, so it costs the same however much the store holds. It
+//! shows the session's outbound channel too (see [`crate::outbound`]).
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+use crate::{
+    clock::epoch_ms, outbound::ChannelStats, records::Direction, store::field_value, AppState,
+};
+
+/// One bucket per second of the longest window.
+const BUCKETS: usize = 900;
+const WINDOWS_SECS: [u64; 3] = [60, 300, 900];
+
+/// Whether a message is a Reject, a BusinessMessageReject, or an
+/// ExecutionReport rejecting an order.
+pub fn is_reject(msg_type: &str, raw: &[u8]) -> bool {
+    match msg_type {
+        "3" | "j" => true,
+        "8" => field_value(raw, 150) == Some(b"8"),
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Counts {
+    messages: u64,
+    bytes: u64,
+    rejects: u64,
+    resend_requests: u64,
+}
+
+impl Counts {
+    fn add(&mut self, other: &Counts) {
+        self.messages += other.messages;
+        self.bytes += other.bytes;
+        self.rejects += other.rejects;
+        self.resend_requests += other.resend_requests;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+    /// Seconds since the epoch this bucket counts.
+    second: u64,
+    inbound: Counts,
+    outbound: Counts,
+}
+
+impl Bucket {
+    fn counts(&mut self, direction: Direction) -> &mut Counts {
+        match direction {
+            Direction::Inbound => &mut self.inbound,
+            Direction::Outbound => &mut self.outbound,
+        }
+    }
+}
+
+struct SessionCounters {
+    /// Indexed by second modulo [`BUCKETS`]; a bucket for an older second
+    /// is stale and reset before reuse.
+    buckets: Vec<Bucket>,
+    total: Bucket,
+    last_inbound_ms: Option<u64>,
+    last_outbound_ms: Option<u64>,
+}
+
+impl SessionCounters {
+    fn new() -> Self {
+        Self {
+            buckets: vec![Bucket::default(); BUCKETS],
+            total: Bucket::default(),
+            last_inbound_ms: None,
+            last_outbound_ms: None,
+        }
+    }
+}
+
+pub struct Throughput {
+    started_ms: u64,
+    by_session: HashMap<String, SessionCounters>,
+}
+
+impl Throughput {
+    pub fn new(started_ms: u64) -> Self {
+        Self {
+            started_ms,
+            by_session: HashMap::new(),
+        }
+    }
+
+    pub fn record(
+        &mut self,
+        session_id: &str,
+        direction: Direction,
+        msg_type: &str,
+        raw: &[u8],
+        now_ms: u64,
+    ) {
+        let counters = match self.by_session.get_mut(session_id) {
+            Some(counters) => counters,
+            None => self
+                .by_session
+                .entry(session_id.to_string())
+                .or_insert_with(SessionCounters::new),
+        };
+        let message = Counts {
+            messages: 1,
+            bytes: raw.len() as u64,
+            rejects: u64::from(is_reject(msg_type, raw)),
+            resend_requests: u64::from(msg_type == "2"),
+        };
+        let second = now_ms / 1000;
+        let bucket = &mut counters.buckets[(second % BUCKETS as u64) as usize];
+        if bucket.second != second {
+            *bucket = Bucket {
+                second,
+                ..Bucket::default()
+            };
+        }
+        bucket.counts(direction).add(&message);
+        counters.total.counts(direction).add(&message);
+
+        let last = match direction {
+            Direction::Inbound => &mut counters.last_inbound_ms,
+            Direction::Outbound => &mut counters.last_outbound_ms,
+        };
+        *last = Some(last.map_or(now_ms, |at| at.max(now_ms)));
+    }
+
+    fn summary(&self, session_id: &str, now_ms: u64) -> ThroughputResponse {
+        let counters = self.by_session.get(session_id);
+        let uptime_secs = now_ms.saturating_sub(self.started_ms) / 1000;
+        let window = |secs: u64| {
+            let now = now_ms / 1000;
+            let mut sum = Bucket::default();
+            for bucket in counters.into_iter().flat_map(|c| &c.buckets) {
+                if bucket.second <= now && bucket.second + secs > now {
+                    sum.inbound.add(&bucket.inbound);
+                    sum.outbound.add(&bucket.outbound);
+                }
+            }
+            ThroughputWindow::of(&sum, secs, secs.min(uptime_secs))
+        };
+        let total = counters.map(|c| c.total).unwrap_or_default();
+        let last_inbound_ms = counters.and_then(|c| c.last_inbound_ms);
+        let last_outbound_ms = counters.and_then(|c| c.last_outbound_ms);
+        ThroughputResponse {
+            session_id: session_id.to_string(),
+            since_ms: self.started_ms,
+            last_inbound_ms,
+            last_outbound_ms,
+            last_activity_ms: last_inbound_ms.max(last_outbound_ms),
+            windows: WINDOWS_SECS.into_iter().map(window).collect(),
+            total: ThroughputWindow::of(&total, uptime_secs, uptime_secs),
+            outbound: None,
+        }
+    }
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DirectionThroughput {
+    messages: u64,
+    bytes: u64,
+    rejects: u64,
+    resend_requests: u64,
+    messages_per_sec: f64,
+    bytes_per_sec: f64,
+}
+
+impl DirectionThroughput {
+    fn of(counts: &Counts, elapsed_secs: u64) -> Self {
+        let per_sec = |n: u64| n as f64 / elapsed_secs.max(1) as f64;
+        Self {
+            messages: counts.messages,
+            bytes: counts.bytes,
+            rejects: counts.rejects,
+            resend_requests: counts.resend_requests,
+            messages_per_sec: per_sec(counts.messages),
+            bytes_per_sec: per_sec(counts.bytes),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ThroughputWindow {
+    window_secs: u64,
+    /// Received from the counterparty.
+    inbound: DirectionThroughput,
+    /// Sent to the counterparty.
+    outbound: DirectionThroughput,
+}
+
+impl ThroughputWindow {
+    /// Rates are over `elapsed_secs`, shorter than the window until the
+    /// gateway has been up that long.
+    fn of(sum: &Bucket, window_secs: u64, elapsed_secs: u64) -> Self {
+        Self {
+            window_secs,
+            inbound: DirectionThroughput::of(&sum.inbound, elapsed_secs),
+            outbound: DirectionThroughput::of(&sum.outbound, elapsed_secs),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ThroughputResponse {
+    session_id: String,
+    /// When counting started, at gateway startup.
+    since_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_inbound_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_outbound_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_activity_ms: Option<u64>,
+    /// The last 1, 5 and 15 minutes.
+    windows: Vec<ThroughputWindow>,
+    /// Since `since_ms`.
+    total: ThroughputWindow,
+    /// The session's outbound channel, once anything has been sent on it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outbound: Option<ChannelStats>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/fix/sessions/{session_id}/stats",
+    tag = "sessions",
+    params(("session_id" = String, Path, description = "The session ID, e.g. `ALICE->BROKER_A`.")),
+    responses(
+        (status = 200, description = "OK", body = ThroughputResponse),
+        (status = 404, description = "Unknown session"),
+    )
+)]
+pub async fn get(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> Result<Json<ThroughputResponse>, StatusCode> {
+    crate::find_session(&state, &session_id).ok_or(StatusCode::NOT_FOUND)?;
+    let mut summary = state
+        .throughput
+        .lock()
+        .await
+        .summary(&session_id, epoch_ms());
+    summary.outbound = state.outbound.stats(&session_id);
+    Ok(Json(summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SESSION: &str = "ALICE->BROKER_A";
+    const START_MS: u64 = 1_771_804_800_000;
+
+    #[test]
+    fn windows_count_recent_messages_only() {
+        let mut throughput = Throughput::new(START_MS);
+        let order = b"8=FIX.4.4\x0135=D\x0111=ORD-1\x01";
+        let rejected = b"8=FIX.4.4\x0135=8\x01150=8\x0139=8\x01";
+        // 600 orders over the first 10 minutes, one a second.
+        for s in 0..600 {
+            throughput.record(SESSION, Direction::Outbound, "D", order, START_MS + s * 1000);
+        }
+        let now = START_MS + 600_000;
+        throughput.record(SESSION, Direction::Inbound, "8", rejected, now - 30_000);
+        throughput.record(SESSION, Direction::Inbound, "2", b"35=2\x01", now - 400_000);
+
+        let summary = throughput.summary(SESSION, now);
+        let [minute, five, fifteen] = &summary.windows[..] else {
+            panic!("expected 3 windows");
+        };
+        assert_eq!(minute.outbound.messages, 59);
+        assert_eq!(minute.outbound.bytes, 59 * order.len() as u64);
+        assert!((minute.outbound.messages_per_sec - 59.0 / 60.0).abs() < 1e-9);
+        assert_eq!(minute.inbound.rejects, 1);
+        assert_eq!(five.inbound.resend_requests, 0);
+        assert_eq!(fifteen.inbound.resend_requests, 1);
+        // Up 10 minutes, so the 15 minute rate is over 10.
+        assert_eq!(fifteen.outbound.messages, 600);
+        assert!((fifteen.outbound.messages_per_sec - 1.0).abs() < 1e-9);
+
+        assert_eq!(summary.total.outbound.messages, 600);
+        assert_eq!(summary.total.inbound.messages, 2);
+        assert_eq!(summary.last_inbound_ms, Some(now - 30_000));
+        assert_eq!(summary.last_activity_ms, Some(START_MS + 599_000));
+    }
+
+    #[test]
+    fn stale_buckets_are_reset_before_reuse() {
+        let mut throughput = Throughput::new(START_MS);
+        throughput.record(SESSION, Direction::Inbound, "0", b"35=0\x01", START_MS);
+        let later = START_MS + BUCKETS as u64 * 1000;
+        throughput.record(SESSION, Direction::Inbound, "0", b"35=0\x01", later);
+
+        let summary = throughput.summary(SESSION, later);
+        assert_eq!(summary.windows[2].inbound.messages, 1);
+        assert_eq!(summary.total.inbound.messages, 2);
+
+        let silent = throughput.summary("ALICE->BROKER_B", later);
+        assert_eq!(silent.total.inbound.messages, 0);
+        assert_eq!(silent.last_activity_ms, None);
+    }
+}
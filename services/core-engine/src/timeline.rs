@@ -0,0 +1,215 @@
+//! Lifecycle event timeline.
+//!
+//! Startup and shutdown steps (config loaded, stores opened, sessions
+//! scheduled and connected, drain) are recorded with their wall-clock time so
+//! post-incident reviews can reconstruct what the gateway did and when. Every
+//! event is also logged, so the sequence survives a restart in the log stream.
+
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{clock::epoch_ms, AppState};
+
+/// Oldest events are dropped beyond this many.
+const MAX_EVENTS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EventKind {
+    ProcessStarted,
+    ConfigLoaded,
+    ConfigReloaded,
+    StoreOpened,
+    SessionScheduled,
+    SessionConnected,
+    SessionDisconnected,
+    ListenerBound,
+    DrainInitiated,
+    DrainCompleted,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LifecycleEvent {
+    pub at_ms: u64,
+    pub kind: EventKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub detail: String,
+}
+
+#[derive(Default)]
+pub struct Timeline {
+    events: VecDeque<LifecycleEvent>,
+}
+
+impl Timeline {
+    pub fn record(&mut self, kind: EventKind, session_id: Option<&str>, detail: impl Into<String>) {
+        let event = LifecycleEvent {
+            at_ms: epoch_ms(),
+            kind,
+            session_id: session_id.map(str::to_string),
+            detail: detail.into(),
+        };
+        info!(
+            kind = ?event.kind,
+            session_id = event.session_id.as_deref().unwrap_or(""),
+            detail = %event.detail,
+            "Lifecycle event"
+        );
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    fn query(&self, q: &TimelineQuery) -> Vec<LifecycleEvent> {
+        self.events
+            .iter()
+            .filter(|e| q.since_ms.is_none_or(|since| e.at_ms >= since))
+            .filter(|e| q.kind.is_none_or(|kind| e.kind == kind))
+            .filter(|e| {
+                q.session_id
+                    .as_deref()
+                    .is_none_or(|id| e.session_id.as_deref() == Some(id))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TimelineQuery {
+    since_ms: Option<u64>,
+    kind: Option<EventKind>,
+    session_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TimelineResponse {
+    events: Vec<LifecycleEvent>,
+    count: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/timeline",
+    tag = "admin",
+    params(TimelineQuery),
+    responses(
+        (status = 200, description = "OK", body = TimelineResponse),
+    )
+)]
+pub async fn list(
+    State(state): State<AppState>,
+    Query(q): Query<TimelineQuery>,
+) -> Json<TimelineResponse> {
+    let events = state.timeline.lock().await.query(&q);
+    let count = events.len();
+    Json(TimelineResponse { events, count })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(q: serde_json::Value) -> TimelineQuery {
+        serde_json::from_value(q).unwrap()
+    }
+
+    /// A startup and a reconnect of one session, 1 s apart from 1000 ms.
+    fn timeline() -> Timeline {
+        let mut timeline = Timeline::default();
+        timeline.record(EventKind::ProcessStarted, None, "fix-engine 0.1.0");
+        timeline.record(EventKind::ConfigLoaded, None, "sessions=2");
+        timeline.record(EventKind::SessionConnected, Some("ALICE->BROKER_A"), "");
+        timeline.record(EventKind::SessionConnected, Some("ALICE->BROKER_B"), "");
+        timeline.record(EventKind::SessionDisconnected, Some("ALICE->BROKER_A"), "");
+        timeline.record(EventKind::SessionConnected, Some("ALICE->BROKER_A"), "");
+        for (i, event) in timeline.events.iter_mut().enumerate() {
+            event.at_ms = 1000 * (i as u64 + 1);
+        }
+        timeline
+    }
+
+    fn kinds(events: &[LifecycleEvent]) -> Vec<EventKind> {
+        events.iter().map(|e| e.kind).collect()
+    }
+
+    #[test]
+    fn events_are_listed_in_the_order_they_happened() {
+        let events = timeline().query(&query(serde_json::json!({})));
+        assert_eq!(
+            kinds(&events),
+            [
+                EventKind::ProcessStarted,
+                EventKind::ConfigLoaded,
+                EventKind::SessionConnected,
+                EventKind::SessionConnected,
+                EventKind::SessionDisconnected,
+                EventKind::SessionConnected,
+            ]
+        );
+        assert!(events.windows(2).all(|w| w[0].at_ms <= w[1].at_ms));
+        assert_eq!(events[1].detail, "sessions=2");
+        assert_eq!(events[0].session_id, None);
+    }
+
+    #[test]
+    fn the_oldest_events_are_dropped_past_the_limit() {
+        let mut timeline = Timeline::default();
+        for i in 0..MAX_EVENTS + 2 {
+            timeline.record(EventKind::ConfigReloaded, None, i.to_string());
+        }
+        let events = timeline.query(&query(serde_json::json!({})));
+        assert_eq!(events.len(), MAX_EVENTS);
+        assert_eq!(events[0].detail, "2");
+        assert_eq!(events[MAX_EVENTS - 1].detail, (MAX_EVENTS + 1).to_string());
+    }
+
+    #[test]
+    fn events_are_filtered_by_time_kind_and_session() {
+        let timeline = timeline();
+
+        let since = timeline.query(&query(serde_json::json!({ "since_ms": 5000 })));
+        assert_eq!(
+            kinds(&since),
+            [EventKind::SessionDisconnected, EventKind::SessionConnected]
+        );
+
+        let connects = timeline.query(&query(serde_json::json!({ "kind": "SESSION_CONNECTED" })));
+        let at: Vec<u64> = connects.iter().map(|e| e.at_ms).collect();
+        assert_eq!(at, [3000, 4000, 6000]);
+
+        let session = timeline.query(&query(
+            serde_json::json!({ "session_id": "ALICE->BROKER_A" }),
+        ));
+        assert_eq!(
+            kinds(&session),
+            [
+                EventKind::SessionConnected,
+                EventKind::SessionDisconnected,
+                EventKind::SessionConnected,
+            ]
+        );
+
+        let all = timeline.query(&query(serde_json::json!({
+            "since_ms": 4000,
+            "kind": "SESSION_CONNECTED",
+            "session_id": "ALICE->BROKER_A",
+        })));
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].at_ms, 6000);
+
+        let none = timeline.query(&query(serde_json::json!({ "session_id": "ALICE->NOBODY" })));
+        assert!(none.is_empty());
+    }
+}
@@ -0,0 +1,230 @@
+//! TLS for the REST API.
+//!
+//! With `FIX_TLS_CERT` and `FIX_TLS_KEY` set, the listener on `FIX_ADDR`
+//! serves HTTPS only, with rustls: HTTP/2 and HTTP/1.1, TLS 1.2 and 1.3.
+//! `FIX_TLS_CERT` is a PEM certificate chain, leaf first, and `FIX_TLS_KEY`
+//! its PEM private key, as a path or a secret reference such as
+//! `vault:fix/gateway#tls_key` (see [`crate::secrets`]). Setting one without
+//! the other is a startup error, rather than falling back to plain HTTP.
+//!
+//! `FIX_TLS_CLIENT_CA` adds client certificate verification against the CA
+//! certificates in that PEM file. With `FIX_TLS_CLIENT_AUTH=required`, the
+//! default, a client without a valid certificate fails the handshake; with
+//! `optional`, a certificate is verified if the client presents one.
+
+use axum::Router;
+use axum_server::{tls_rustls::RustlsConfig, Handle};
+use rustls::{
+    pki_types::{CertificateDer, PrivateKeyDer},
+    server::WebPkiClientVerifier,
+    RootCertStore, ServerConfig,
+};
+use std::{
+    fs::File,
+    future::Future,
+    io::{self, BufReader},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::secrets::{Secret, Secrets};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientAuth {
+    Required,
+    Optional,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    cert: PathBuf,
+    /// A path, or a secret reference.
+    key: String,
+    client_ca: Option<PathBuf>,
+    client_auth: ClientAuth,
+}
+
+impl TlsConfig {
+    /// Reads `FIX_TLS_CERT`, `FIX_TLS_KEY`, `FIX_TLS_CLIENT_CA` and
+    /// `FIX_TLS_CLIENT_AUTH`; `None` serves plain HTTP.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let var = |name| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        Self::parse(
+            var("FIX_TLS_CERT"),
+            var("FIX_TLS_KEY"),
+            var("FIX_TLS_CLIENT_CA"),
+            var("FIX_TLS_CLIENT_AUTH"),
+        )
+    }
+
+    fn parse(
+        cert: Option<String>,
+        key: Option<String>,
+        client_ca: Option<String>,
+        client_auth: Option<String>,
+    ) -> Result<Option<Self>, String> {
+        let client_auth = match client_auth.as_deref().map(str::trim) {
+            None | Some("required") => ClientAuth::Required,
+            Some("optional") => ClientAuth::Optional,
+            Some(other) => {
+                return Err(format!(
+                    "FIX_TLS_CLIENT_AUTH must be `required` or `optional`, not `{other}`"
+                ))
+            }
+        };
+        let (cert, key) = match (cert, key) {
+            (Some(cert), Some(key)) => (cert, key),
+            (None, None) if client_ca.is_none() => return Ok(None),
+            (None, None) => return Err("FIX_TLS_CLIENT_CA requires FIX_TLS_CERT".to_string()),
+            (Some(_), None) => return Err("FIX_TLS_CERT is set without FIX_TLS_KEY".to_string()),
+            (None, Some(_)) => return Err("FIX_TLS_KEY is set without FIX_TLS_CERT".to_string()),
+        };
+        let path = |p: String| PathBuf::from(p.trim());
+        Ok(Some(Self {
+            cert: path(cert),
+            key: key.trim().to_string(),
+            client_ca: client_ca.map(path),
+            client_auth,
+        }))
+    }
+
+    /// What is served, for startup reporting.
+    pub fn describe(&self) -> String {
+        let clients = match (&self.client_ca, self.client_auth) {
+            (None, _) => "no client certificates".to_string(),
+            (Some(ca), ClientAuth::Required) => {
+                format!("client certificates required ({})", ca.display())
+            }
+            (Some(ca), ClientAuth::Optional) => {
+                format!("client certificates optional ({})", ca.display())
+            }
+        };
+        format!("tls (cert: {}) {clients}", self.cert.display())
+    }
+
+    /// Loads the certificates and key; a file or secret that cannot be read
+    /// or holds no usable PEM is an error naming it.
+    pub async fn server_config(&self, secrets: &Secrets) -> Result<Arc<ServerConfig>, String> {
+        let chain = load_certs(&self.cert)?;
+        let key = parse_key(&secrets.resolve_file(&self.key).await?, &self.key)?;
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| e.to_string())?;
+        let builder = match &self.client_ca {
+            None => builder.with_no_client_auth(),
+            Some(ca) => {
+                let mut roots = RootCertStore::empty();
+                for cert in load_certs(ca)? {
+                    roots.add(cert).map_err(|e| format!("{}: {e}", ca.display()))?;
+                }
+                let verifier =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+                let verifier = match self.client_auth {
+                    ClientAuth::Required => verifier,
+                    ClientAuth::Optional => verifier.allow_unauthenticated(),
+                };
+                builder.with_client_cert_verifier(verifier.build().map_err(|e| e.to_string())?)
+            }
+        };
+        let mut config = builder
+            .with_single_cert(chain, key)
+            .map_err(|e| format!("{}: {e}", self.key))?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(Arc::new(config))
+    }
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, String> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = rustls_pemfile::certs(&mut open(path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    if certs.is_empty() {
+        return Err(format!("{}: no PEM certificates", path.display()));
+    }
+    Ok(certs)
+}
+
+/// The private key in `pem`, read from the file or secret `name`.
+fn parse_key(pem: &Secret, name: &str) -> Result<PrivateKeyDer<'static>, String> {
+    rustls_pemfile::private_key(&mut pem.expose().as_bytes())
+        .map_err(|e| format!("{name}: {e}"))?
+        .ok_or_else(|| format!("{name}: no PEM private key"))
+}
+
+/// Serves `app` over TLS on `listener` until `shutdown` resolves, then waits
+/// for in-flight requests to finish.
+pub async fn serve(
+    listener: tokio::net::TcpListener,
+    config: Arc<ServerConfig>,
+    app: Router,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> io::Result<()> {
+    let handle = Handle::new();
+    let draining = handle.clone();
+    tokio::spawn(async move {
+        shutdown.await;
+        draining.graceful_shutdown(None);
+    });
+    axum_server::from_tcp_rustls(listener.into_std()?, RustlsConfig::from_config(config))
+        .handle(handle)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(vars: [Option<&str>; 4]) -> Result<Option<TlsConfig>, String> {
+        let [cert, key, client_ca, client_auth] = vars.map(|v| v.map(str::to_string));
+        TlsConfig::parse(cert, key, client_ca, client_auth)
+    }
+
+    #[test]
+    fn half_configured_tls_is_an_error() {
+        assert_eq!(parse([None; 4]), Ok(None));
+        assert!(parse([Some("cert.pem"), None, None, None]).is_err());
+        assert!(parse([None, Some("key.pem"), None, None]).is_err());
+        assert!(parse([None, None, Some("ca.pem"), None]).is_err());
+        assert!(parse([Some("cert.pem"), Some("key.pem"), None, Some("sometimes")]).is_err());
+
+        let tls = parse([Some("cert.pem"), Some("key.pem"), Some("ca.pem"), Some("optional")])
+            .unwrap()
+            .unwrap();
+        assert_eq!(tls.client_ca, Some(PathBuf::from("ca.pem")));
+        assert_eq!(tls.client_auth, ClientAuth::Optional);
+    }
+
+    #[tokio::test]
+    async fn unreadable_files_are_named() {
+        let dir = std::env::temp_dir().join(format!("fix-tls-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let empty = dir.join("cert.pem");
+        std::fs::write(&empty, "not a certificate\n").unwrap();
+        let missing = dir.join("key.pem");
+        let tls = TlsConfig {
+            cert: empty.clone(),
+            key: missing.display().to_string(),
+            client_ca: None,
+            client_auth: ClientAuth::Required,
+        };
+
+        let secrets = Secrets::default();
+        let err = tls.server_config(&secrets).await.unwrap_err();
+        assert!(err.starts_with(&empty.display().to_string()), "{err}");
+        assert!(err.contains("no PEM certificates"), "{err}");
+
+        let err = parse_key(&Secret::new("not a key\n".to_string()), "vault:fix/gateway#tls_key")
+            .unwrap_err();
+        assert_eq!(err, "vault:fix/gateway#tls_key: no PEM private key");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,223 @@
+//! Live feed of FIX traffic.
+//!
+//! Every message the store records, inbound or outbound, and every PossDup
+//! resend is broadcast to the WebSocket clients of `GET /api/v1/fix/stream`.
+//! Each client chooses the sessions, direction and MsgTypes it wants, and is
+//! sent each matching message both parsed and in its raw wire form. Nothing
+//! is broadcast while no client is connected.
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+use utoipa::IntoParams;
+
+use crate::{msg_type_to_num, parse_raw, records::Direction, AppState, ParseResponse};
+
+/// Messages buffered per stream client before it is lagged.
+const STREAM_CAPACITY: usize = 4096;
+
+/// A message as it crossed the wire.
+#[derive(Debug, Clone)]
+pub struct Traffic {
+    pub session_id: String,
+    pub direction: Direction,
+    /// MsgType code, as stored.
+    pub msg_type: String,
+    pub sequence_number: Option<u64>,
+    pub raw: Vec<u8>,
+    pub at_ms: u64,
+    /// A PossDup resend of a message sent earlier.
+    pub resend: bool,
+}
+
+pub struct TrafficFeed {
+    sender: broadcast::Sender<Arc<Traffic>>,
+}
+
+impl Default for TrafficFeed {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(STREAM_CAPACITY).0,
+        }
+    }
+}
+
+impl TrafficFeed {
+    pub fn publish(&self, traffic: Traffic) {
+        if self.sender.receiver_count() > 0 {
+            // A client may disconnect in between, which is not an error.
+            let _ = self.sender.send(Arc::new(traffic));
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<Traffic>> {
+        self.sender.subscribe()
+    }
+}
+
+// ── Filters ───────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StreamQuery {
+    /// Only this session's messages; all sessions if omitted.
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub direction: Option<Direction>,
+    /// Comma-separated MsgTypes, as codes (`D`) or names (`NewOrderSingle`).
+    #[serde(default)]
+    pub msg_type: Option<String>,
+}
+
+/// A client's filter, with MsgTypes resolved to their codes.
+#[derive(Debug)]
+pub struct Filter {
+    session_id: Option<String>,
+    direction: Option<Direction>,
+    msg_types: Vec<String>,
+}
+
+impl Filter {
+    pub fn new(query: StreamQuery) -> Self {
+        let msg_types = query
+            .msg_type
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(msg_type_to_num)
+            .collect();
+        Self {
+            session_id: query.session_id,
+            direction: query.direction,
+            msg_types,
+        }
+    }
+
+    pub fn matches(&self, traffic: &Traffic) -> bool {
+        self.session_id
+            .as_ref()
+            .is_none_or(|s| *s == traffic.session_id)
+            && self.direction.is_none_or(|d| d == traffic.direction)
+            && (self.msg_types.is_empty() || self.msg_types.contains(&traffic.msg_type))
+    }
+}
+
+// ── Handler ───────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize)]
+struct TrafficMessage<'a> {
+    session_id: &'a str,
+    direction: Direction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sequence_number: Option<u64>,
+    at_ms: u64,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    resend: bool,
+    /// The message as sent or received, delimiters included.
+    raw: String,
+    message: ParseResponse,
+}
+
+/// Upgrades to a WebSocket that sends each matching message as it is sent
+/// or received.
+#[utoipa::path(
+    get,
+    path = "/api/v1/fix/stream",
+    tag = "fix",
+    params(StreamQuery),
+    responses(
+        (status = 101, description = "Switched to a WebSocket of sent and received messages"),
+    )
+)]
+pub async fn stream(
+    State(state): State<AppState>,
+    Query(query): Query<StreamQuery>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    ws.on_upgrade(move |socket| stream_traffic(state, Filter::new(query), socket))
+}
+
+async fn stream_traffic(state: AppState, filter: Filter, mut socket: WebSocket) {
+    let mut traffic = state.traffic.subscribe();
+    info!(filter = ?filter, "FIX traffic stream client connected");
+    loop {
+        tokio::select! {
+            next = traffic.recv() => match next {
+                Ok(t) if filter.matches(&t) => {
+                    if send_traffic(&mut socket, &t).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!(missed, "FIX traffic stream client lagging, messages dropped");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    info!(filter = ?filter, "FIX traffic stream client disconnected");
+}
+
+async fn send_traffic(socket: &mut WebSocket, traffic: &Traffic) -> Result<(), axum::Error> {
+    let message = TrafficMessage {
+        session_id: &traffic.session_id,
+        direction: traffic.direction,
+        sequence_number: traffic.sequence_number,
+        at_ms: traffic.at_ms,
+        resend: traffic.resend,
+        raw: String::from_utf8_lossy(&traffic.raw).into_owned(),
+        message: parse_raw(&traffic.raw),
+    };
+    let text = serde_json::to_string(&message).unwrap_or_default();
+    socket.send(Message::Text(text)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn traffic(session_id: &str, direction: Direction, msg_type: &str) -> Traffic {
+        Traffic {
+            session_id: session_id.to_string(),
+            direction,
+            msg_type: msg_type.to_string(),
+            sequence_number: Some(1),
+            raw: Vec::new(),
+            at_ms: 0,
+            resend: false,
+        }
+    }
+
+    #[test]
+    fn filters_combine_session_direction_and_msg_types() {
+        let filter = Filter::new(StreamQuery {
+            session_id: Some("ALICE->BROKER_A".to_string()),
+            direction: Some(Direction::Inbound),
+            msg_type: Some("ExecutionReport, 9".to_string()),
+        });
+        assert_eq!(filter.msg_types, ["8", "9"]);
+        assert!(filter.matches(&traffic("ALICE->BROKER_A", Direction::Inbound, "8")));
+        assert!(filter.matches(&traffic("ALICE->BROKER_A", Direction::Inbound, "9")));
+        assert!(!filter.matches(&traffic("ALICE->BROKER_A", Direction::Inbound, "D")));
+        assert!(!filter.matches(&traffic("ALICE->BROKER_A", Direction::Outbound, "8")));
+        assert!(!filter.matches(&traffic("ALICE->BROKER_B", Direction::Inbound, "8")));
+
+        let everything = Filter::new(StreamQuery::default());
+        assert!(everything.matches(&traffic("ALICE->BROKER_B", Direction::Outbound, "0")));
+    }
+}
@@ -0,0 +1,592 @@
+//! Message validation against a data dictionary.
+
+use std::collections::HashMap;
+use tracing::warn;
+
+use crate::{
+    clock,
+    dictionary::{Dictionary, GroupDef, Member},
+    ErrorCategory, ValidationError,
+};
+
+/// Validation results by severity: errors always fail validation, warnings
+/// only in strict mode.
+#[derive(Debug, Default)]
+pub struct Findings {
+    pub errors: Vec<ValidationError>,
+    pub warnings: Vec<ValidationError>,
+}
+
+impl Findings {
+    pub fn extend(&mut self, other: Findings) {
+        self.errors.extend(other.errors);
+        self.warnings.extend(other.warnings);
+    }
+}
+
+/// Tags accepted without a dictionary definition when unknown tags are
+/// rejected: user-defined ranges, by default 5000-9999 and 20000 upward.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomTagRanges(Vec<(u32, u32)>);
+
+impl CustomTagRanges {
+    const DEFAULT: &'static str = "5000-9999,20000-";
+
+    /// Parses comma-separated ranges; `20000-` is open-ended and a single tag
+    /// is its own range.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut ranges = Vec::new();
+        for range in spec.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+            let bound = |s: &str| {
+                s.trim()
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid tag range '{range}'"))
+            };
+            let (lo, hi) = match range.split_once('-') {
+                Some((lo, "")) => (bound(lo)?, u32::MAX),
+                Some((lo, hi)) => (bound(lo)?, bound(hi)?),
+                None => (bound(range)?, bound(range)?),
+            };
+            if lo > hi {
+                return Err(format!("invalid tag range '{range}'"));
+            }
+            ranges.push((lo, hi));
+        }
+        Ok(Self(ranges))
+    }
+
+    /// Reads `FIX_ALLOWED_CUSTOM_TAGS`, falling back to the default ranges.
+    pub fn from_env() -> Self {
+        let spec =
+            std::env::var("FIX_ALLOWED_CUSTOM_TAGS").unwrap_or_else(|_| Self::DEFAULT.to_string());
+        Self::parse(&spec).unwrap_or_else(|e| {
+            warn!(error = %e, "Ignoring FIX_ALLOWED_CUSTOM_TAGS");
+            Self::parse(Self::DEFAULT).expect("default ranges are valid")
+        })
+    }
+
+    pub fn contains(&self, tag: u32) -> bool {
+        self.0.iter().any(|&(lo, hi)| (lo..=hi).contains(&tag))
+    }
+}
+
+/// Tags that are neither defined in `dict` nor in an allowed custom range.
+pub fn unknown_tags(
+    dict: &Dictionary,
+    tags: impl IntoIterator<Item = u32>,
+    allowed: &CustomTagRanges,
+) -> Vec<ValidationError> {
+    let mut seen = Vec::new();
+    tags.into_iter()
+        .filter(|&tag| dict.field(tag).is_none() && !allowed.contains(tag))
+        .filter(|&tag| {
+            let first = !seen.contains(&tag);
+            seen.push(tag);
+            first
+        })
+        .map(|tag| ValidationError {
+            path: None,
+            field: tag.to_string(),
+            tag,
+            message: format!(
+                "Tag {tag} is not defined in the {} dictionary.",
+                dict.version
+            ),
+            category: ErrorCategory::UnknownTag,
+        })
+        .collect()
+}
+
+/// Field-map keys that are neither a tag number nor a name in `dict`; these
+/// are otherwise dropped by [`fields_from_map`].
+pub fn unknown_field_names(
+    dict: &Dictionary,
+    map: &HashMap<String, String>,
+) -> Vec<ValidationError> {
+    let mut names: Vec<&String> = map
+        .keys()
+        .filter(|key| key.parse::<u32>().is_err() && dict.tag_for(key).is_none())
+        .collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| ValidationError {
+            path: None,
+            field: name.clone(),
+            tag: 0,
+            message: format!(
+                "Field {name} is not defined in the {} dictionary.",
+                dict.version
+            ),
+            category: ErrorCategory::UnknownTag,
+        })
+        .collect()
+}
+
+/// Converts a `/validate` field map, keyed by field name or tag number, to
+/// tag/value pairs. Names the dictionary does not know are dropped.
+pub fn fields_from_map(dict: &Dictionary, map: &HashMap<String, String>) -> Vec<(u32, String)> {
+    let mut fields: Vec<(u32, String)> = map
+        .iter()
+        .filter_map(|(key, value)| {
+            let tag = key.parse().ok().or_else(|| dict.tag_for(key))?;
+            Some((tag, value.clone()))
+        })
+        .collect();
+    fields.sort_by_key(|(tag, _)| *tag);
+    fields
+}
+
+fn missing_required(
+    members: &[Member],
+    present: &impl Fn(u32) -> bool,
+    mut report: impl FnMut(u32),
+) {
+    for member in members {
+        let (tag, required) = match member {
+            Member::Field { tag, required } => (*tag, *required),
+            Member::Group(g) => (g.count_tag, g.required),
+        };
+        if required && !present(tag) {
+            report(tag);
+        }
+    }
+}
+
+/// Every tag that can appear inside an instance of `group`, nested groups included.
+fn group_tags(group: &GroupDef, tags: &mut Vec<u32>) {
+    for member in &group.members {
+        match member {
+            Member::Field { tag, .. } => tags.push(*tag),
+            Member::Group(g) => {
+                tags.push(g.count_tag);
+                group_tags(g, tags);
+            }
+        }
+    }
+}
+
+/// A run of tag/value pairs within a message.
+type Span<'a> = &'a [(u32, String)];
+
+/// The fields following `group`'s count tag that belong to the group, up to
+/// the first tag that cannot, split into instances at each delimiter. Member
+/// fields before the first delimiter are returned separately.
+fn instances<'a>(group: &GroupDef, fields: &'a [(u32, String)]) -> (Span<'a>, Vec<Span<'a>>) {
+    let mut tags = Vec::new();
+    group_tags(group, &mut tags);
+    let end = fields
+        .iter()
+        .position(|(t, _)| !tags.contains(t))
+        .unwrap_or(fields.len());
+    let fields = &fields[..end];
+
+    let starts: Vec<usize> = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, (t, _))| *t == group.delimiter)
+        .map(|(i, _)| i)
+        .collect();
+    let leading = &fields[..starts.first().copied().unwrap_or(fields.len())];
+    let instances = starts
+        .iter()
+        .enumerate()
+        .map(|(n, &start)| &fields[start..starts.get(n + 1).copied().unwrap_or(fields.len())])
+        .collect();
+    (leading, instances)
+}
+
+/// Checks every group in `members`: the count matches the instances that
+/// follow, each instance opens with the delimiter and has its required
+/// fields. `prefix` is the path of the enclosing instance, e.g. `NoLegs[1].`.
+fn validate_groups(
+    dict: &Dictionary,
+    members: &[Member],
+    fields: &[(u32, String)],
+    prefix: &str,
+    errors: &mut Vec<ValidationError>,
+) {
+    for group in members.iter().filter_map(|m| match m {
+        Member::Group(g) => Some(g),
+        _ => None,
+    }) {
+        let Some(pos) = fields.iter().position(|(t, _)| *t == group.count_tag) else {
+            continue;
+        };
+        let name = dict.name_of(group.count_tag);
+        let error = |tag: u32, path: String, message: String, category| ValidationError {
+            field: dict.name_of(tag),
+            tag,
+            message,
+            category,
+            path: Some(path),
+        };
+        let (leading, instances) = instances(group, &fields[pos + 1..]);
+
+        if let Some((tag, _)) = leading.first() {
+            errors.push(error(
+                group.count_tag,
+                format!("{prefix}{name}[1].{}", dict.name_of(*tag)),
+                format!(
+                    "{prefix}{name}[1] must begin with {} (tag {}).",
+                    dict.name_of(group.delimiter),
+                    group.delimiter
+                ),
+                ErrorCategory::Ordering,
+            ));
+        }
+        // A non-numeric count is reported by the NUMINGROUP type check.
+        if let Ok(declared) = fields[pos].1.parse::<usize>() {
+            if declared != instances.len() {
+                errors.push(error(
+                    group.count_tag,
+                    format!("{prefix}{name}"),
+                    format!(
+                        "{prefix}{name} is {declared} but {} instance(s) follow.",
+                        instances.len()
+                    ),
+                    ErrorCategory::GroupCount,
+                ));
+            }
+        }
+
+        for (n, instance) in instances.into_iter().enumerate() {
+            let entry = format!("{prefix}{name}[{}]", n + 1);
+            let present = |tag: u32| instance.iter().any(|(t, _)| *t == tag);
+            missing_required(&group.members, &present, |tag| {
+                errors.push(error(
+                    tag,
+                    format!("{entry}.{}", dict.name_of(tag)),
+                    format!("{entry} requires {} (tag {tag}).", dict.name_of(tag)),
+                    ErrorCategory::Required,
+                ))
+            });
+            validate_groups(dict, &group.members, instance, &format!("{entry}."), errors);
+        }
+    }
+}
+
+fn is_integer(v: &str) -> bool {
+    let digits = v.strip_prefix('-').unwrap_or(v);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn is_decimal(v: &str) -> bool {
+    let digits = v.strip_prefix('-').unwrap_or(v);
+    let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+    !(int.is_empty() && frac.is_empty())
+        && int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit())
+}
+
+fn is_month_year(v: &str) -> bool {
+    if v.len() == 8 && clock::parse_date_end_of_day(v).is_some() {
+        return true;
+    }
+    let (Some(month), Some(week)) = (v.get(..6), v.get(6..)) else {
+        return false;
+    };
+    clock::parse_date_end_of_day(&format!("{month}01")).is_some()
+        && matches!(week, "" | "w1" | "w2" | "w3" | "w4" | "w5")
+}
+
+/// The expected format of a dictionary type, if `value` does not match it.
+/// Types without a checkable format (STRING, DATA, ...) always match.
+fn type_mismatch(field_type: &str, value: &str) -> Option<&'static str> {
+    let (ok, expected) = match field_type {
+        "INT" => (is_integer(value), "integer"),
+        "LENGTH" | "SEQNUM" | "NUMINGROUP" | "TAGNUM" => (
+            is_integer(value) && !value.starts_with('-'),
+            "non-negative integer",
+        ),
+        "DAYOFMONTH" => (
+            value.parse::<u8>().is_ok_and(|d| (1..=31).contains(&d)),
+            "1-31",
+        ),
+        "FLOAT" | "PRICE" | "QTY" | "AMT" | "PRICEOFFSET" | "PERCENTAGE" => {
+            (is_decimal(value), "decimal")
+        }
+        "CHAR" => (value.chars().count() == 1, "single character"),
+        "BOOLEAN" => (value == "Y" || value == "N", "Y or N"),
+        "UTCTIMESTAMP" => (
+            clock::parse_utc_timestamp(value).is_some(),
+            "YYYYMMDD-HH:MM:SS[.sss]",
+        ),
+        "UTCTIMEONLY" | "TZTIMEONLY" => (
+            clock::parse_utc_timestamp(&format!("19700101-{value}")).is_some(),
+            "HH:MM:SS[.sss]",
+        ),
+        "UTCDATEONLY" | "UTCDATE" | "LOCALMKTDATE" => {
+            (clock::parse_date_end_of_day(value).is_some(), "YYYYMMDD")
+        }
+        "MONTHYEAR" => (is_month_year(value), "YYYYMM, YYYYMMDD or YYYYMMwN"),
+        _ => (true, ""),
+    };
+    (!ok).then_some(expected)
+}
+
+/// Validates a message's fields, in wire order, against `dict`. `framed` is
+/// false for field maps, which have no trailer to check.
+pub fn validate(dict: &Dictionary, fields: &[(u32, String)], framed: bool) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let present = |tag: u32| fields.iter().any(|(t, _)| *t == tag);
+    let error = |tag: u32, message: String, category| ValidationError {
+        path: None,
+        field: dict.name_of(tag),
+        tag,
+        message,
+        category,
+    };
+
+    missing_required(&dict.header, &present, |tag| {
+        errors.push(error(
+            tag,
+            "Required header field missing.".to_string(),
+            ErrorCategory::Required,
+        ))
+    });
+
+    let msg_type = fields
+        .iter()
+        .find(|(t, _)| *t == 35)
+        .map(|(_, v)| v.as_str());
+    match msg_type.map(|m| (m, dict.message(m))) {
+        Some((_, Some(message))) => {
+            missing_required(&message.members, &present, |tag| {
+                errors.push(error(
+                    tag,
+                    format!(
+                        "{} requires {} (tag {tag}).",
+                        message.name,
+                        dict.name_of(tag)
+                    ),
+                    ErrorCategory::Required,
+                ))
+            });
+            validate_groups(dict, &message.members, fields, "", &mut errors);
+        }
+        Some((code, None)) => errors.push(error(
+            35,
+            format!(
+                "MsgType '{code}' is not defined in the {} dictionary.",
+                dict.version
+            ),
+            ErrorCategory::InvalidMsgType,
+        )),
+        // Already reported as a missing header field.
+        None => {}
+    }
+
+    for (tag, value) in fields {
+        let Some(def) = dict.field(*tag) else {
+            continue;
+        };
+        if let Some(expected) = type_mismatch(&def.field_type, value) {
+            errors.push(error(
+                *tag,
+                format!(
+                    "{} must be {} ({expected}), got '{value}'.",
+                    def.name, def.field_type
+                ),
+                ErrorCategory::InvalidType,
+            ));
+            continue;
+        }
+        if def.values.is_empty() {
+            continue;
+        }
+        let mut values = if def.is_multi_value() {
+            value.split(' ').collect()
+        } else {
+            vec![value.as_str()]
+        };
+        values.retain(|v| !def.values.iter().any(|allowed| allowed == v));
+        for v in values {
+            errors.push(error(
+                *tag,
+                format!("Invalid value '{v}' for {}.", def.name),
+                ErrorCategory::InvalidValue,
+            ));
+        }
+    }
+
+    if framed {
+        missing_required(&dict.trailer, &present, |tag| {
+            errors.push(error(
+                tag,
+                "Required trailer field missing.".to_string(),
+                ErrorCategory::Required,
+            ))
+        });
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::Dictionaries;
+
+    const XML: &str = r#"<fix type="FIX" major="4" minor="4" servicepack="0">
+        <header><field name="MsgType" required="Y"/></header>
+        <trailer><field name="CheckSum" required="Y"/></trailer>
+        <messages>
+          <message name="Multileg" msgtype="AB">
+            <group name="NoLegs" required="Y">
+              <field name="LegSymbol" required="Y"/>
+              <field name="LegSide" required="Y"/>
+            </group>
+          </message>
+        </messages>
+        <fields>
+          <field number="35" name="MsgType"/><field number="10" name="CheckSum"/>
+          <field number="555" name="NoLegs"/><field number="600" name="LegSymbol"/>
+          <field number="624" name="LegSide">
+            <value enum="1" description="BUY"/><value enum="2" description="SELL"/>
+          </field>
+          <field number="18" name="ExecInst" type="MULTIPLEVALUESTRING">
+            <value enum="1"/><value enum="G"/>
+          </field>
+        </fields></fix>"#;
+
+    fn fields(spec: &[(u32, &str)]) -> Vec<(u32, String)> {
+        spec.iter().map(|(t, v)| (*t, v.to_string())).collect()
+    }
+
+    #[test]
+    fn checks_each_group_instance() {
+        let dict = Dictionary::from_xml(XML).unwrap();
+        let msg = fields(&[
+            (35, "AB"),
+            (555, "2"),
+            (600, "A"),
+            (624, "1"),
+            (600, "B"),
+            (10, "000"),
+        ]);
+        let errors = validate(&dict, &msg, true);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "NoLegs[2] requires LegSide (tag 624).");
+        assert_eq!(errors[0].path.as_deref(), Some("NoLegs[2].LegSide"));
+
+        // Field maps have no trailer.
+        let errors = validate(&dict, &fields(&[(35, "AB")]), false);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].tag, 555);
+    }
+
+    #[test]
+    fn checks_group_counts_and_delimiters() {
+        let dict = Dictionary::from_xml(XML).unwrap();
+        let msg = fields(&[
+            (35, "AB"),
+            (555, "3"),
+            (624, "2"),
+            (600, "A"),
+            (624, "1"),
+            (600, "B"),
+            (624, "1"),
+        ]);
+        let errors: Vec<(String, Option<String>)> = validate(&dict, &msg, false)
+            .into_iter()
+            .map(|e| (e.message, e.path))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    "NoLegs[1] must begin with LegSymbol (tag 600).".to_string(),
+                    Some("NoLegs[1].LegSide".to_string())
+                ),
+                (
+                    "NoLegs is 3 but 2 instance(s) follow.".to_string(),
+                    Some("NoLegs".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_values_outside_enum() {
+        let dict = Dictionary::from_xml(XML).unwrap();
+        let msg = fields(&[(35, "AB"), (555, "1"), (600, "A"), (624, "X"), (18, "G 9")]);
+        let errors: Vec<String> = validate(&dict, &msg, false)
+            .into_iter()
+            .map(|e| e.message)
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "Invalid value 'X' for LegSide.",
+                "Invalid value '9' for ExecInst."
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_data_types() {
+        let dict = Dictionaries::from_env().get("FIX.4.4").unwrap();
+        let msg = fields(&[
+            (35, "D"),
+            (34, "-1"),
+            (38, "1e3"),
+            (44, "101.25"),
+            (43, "yes"),
+            (52, "20260223-25:00:00"),
+            (60, "20260223-09:30:00.123456"),
+            (200, "202603w2"),
+        ]);
+        let errors: Vec<String> = validate(&dict, &msg, false)
+            .into_iter()
+            .filter(|e| matches!(e.category, ErrorCategory::InvalidType))
+            .map(|e| e.message)
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "MsgSeqNum must be SEQNUM (non-negative integer), got '-1'.",
+                "OrderQty must be QTY (decimal), got '1e3'.",
+                "PossDupFlag must be BOOLEAN (Y or N), got 'yes'.",
+                "SendingTime must be UTCTIMESTAMP (YYYYMMDD-HH:MM:SS[.sss]), got '20260223-25:00:00'.",
+            ]
+        );
+    }
+
+    #[test]
+    fn flags_tags_outside_dictionary_and_custom_ranges() {
+        let dict = Dictionary::from_xml(XML).unwrap();
+        let allowed = CustomTagRanges::parse(CustomTagRanges::DEFAULT).unwrap();
+        assert!(allowed.contains(5000) && allowed.contains(9999) && allowed.contains(123_456));
+        assert!(!allowed.contains(4999) && !allowed.contains(10_000));
+
+        let errors = unknown_tags(&dict, [35, 44, 7001, 12000, 44, 20001], &allowed);
+        let tags: Vec<u32> = errors.iter().map(|e| e.tag).collect();
+        assert_eq!(tags, vec![44, 12000]);
+        assert_eq!(
+            errors[0].message,
+            "Tag 44 is not defined in the FIX.4.4 dictionary."
+        );
+
+        let map = HashMap::from([
+            ("MsgType".to_string(), "AB".to_string()),
+            ("LegSyde".to_string(), "1".to_string()),
+        ]);
+        let errors = unknown_field_names(&dict, &map);
+        assert_eq!(
+            errors[0].message,
+            "Field LegSyde is not defined in the FIX.4.4 dictionary."
+        );
+        assert_eq!(errors.len(), 1);
+
+        let narrow = CustomTagRanges::parse("7001, 9000-9100").unwrap();
+        assert!(narrow.contains(7001) && !narrow.contains(7002));
+        assert!(CustomTagRanges::parse("9-1").is_err());
+    }
+
+    #[test]
+    fn reports_unknown_msg_type() {
+        let dict = Dictionary::from_xml(XML).unwrap();
+        let errors = validate(&dict, &fields(&[(35, "ZZ")]), false);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0].category, ErrorCategory::InvalidMsgType));
+    }
+}
@@ -0,0 +1,63 @@
+//! Per-venue profiles, keyed by TargetCompID.
+
+use std::collections::HashMap;
+use tracing::warn;
+
+#[derive(Debug, Clone, Default)]
+pub struct VenueProfile {
+    /// Client metadata keys copied into custom FIX tags on outbound orders.
+    pub metadata_tags: Vec<(String, u32)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VenueProfiles {
+    by_target: HashMap<String, VenueProfile>,
+}
+
+impl VenueProfiles {
+    /// Reads `FIX_METADATA_TAGS`, a comma-separated list of
+    /// `TARGET:metadata_key=tag` entries, e.g.
+    /// `BROKER_A:strategy_id=7001,BROKER_A:user_tag=7002`.
+    pub fn from_env() -> Self {
+        let mut profiles = Self::default();
+        let Ok(spec) = std::env::var("FIX_METADATA_TAGS") else {
+            return profiles;
+        };
+
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let parsed = entry.split_once(':').and_then(|(target, mapping)| {
+                let (key, tag) = mapping.split_once('=')?;
+                Some((target, key, tag.parse::<u32>().ok()?))
+            });
+            match parsed {
+                Some((target, key, tag)) if tag > 0 => profiles
+                    .by_target
+                    .entry(target.to_string())
+                    .or_default()
+                    .metadata_tags
+                    .push((key.to_string(), tag)),
+                _ => warn!(entry, "Ignoring malformed FIX_METADATA_TAGS entry"),
+            }
+        }
+        profiles
+    }
+
+    pub fn get(&self, target_comp_id: &str) -> Option<&VenueProfile> {
+        self.by_target.get(target_comp_id)
+    }
+}
+
+impl VenueProfile {
+    /// Copies mapped metadata values into `fields` under their custom tags.
+    pub fn apply_metadata(
+        &self,
+        metadata: &HashMap<String, String>,
+        fields: &mut HashMap<String, String>,
+    ) {
+        for (key, tag) in &self.metadata_tags {
+            if let Some(value) = metadata.get(key) {
+                fields.insert(tag.to_string(), value.clone());
+            }
+        }
+    }
+}